@@ -0,0 +1,314 @@
+use clap::Args;
+use log::info;
+use mazze_types::{H256, U256};
+use mazzecore::pow::ProofOfWorkProblem;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::VMManager;
+use crate::miner::{Miner, ThreadLayout};
+use crate::telemetry::TelemetrySnapshot;
+
+/// Fixed seed hash so that results of different runs are comparable.
+const BENCH_SEED_HASH: [u8; 32] = [0x42; 32];
+/// Give up on a case if some thread has not started hashing by then.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(600);
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Thread counts to benchmark, e.g. `1,4,8`. Defaults to the number of
+    /// available cores.
+    #[clap(long, use_value_delimiter = true)]
+    threads: Vec<usize>,
+
+    /// How long to measure each case
+    #[clap(long, default_value = "10")]
+    duration_secs: u64,
+
+    /// Hashing time ignored at the start of each case
+    #[clap(long, default_value = "2")]
+    warmup_secs: u64,
+
+    /// RandomX modes to benchmark: `light` (cache only) and/or `full`
+    /// (dataset)
+    #[clap(
+        long,
+        value_enum,
+        use_value_delimiter = true,
+        default_value = "light,full"
+    )]
+    dataset: Vec<DatasetMode>,
+
+    /// NUMA binding of the mining threads to benchmark: `on` and/or `off`
+    #[clap(long, value_enum, use_value_delimiter = true, default_value = "on")]
+    numa: Vec<NumaBinding>,
+
+    /// Print the results as JSON instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DatasetMode {
+    Light,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum NumaBinding {
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeResult {
+    pub node_id: usize,
+    pub threads: usize,
+    pub hashrate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub threads: usize,
+    pub full_dataset: bool,
+    pub numa_binding: bool,
+    /// Time to build the RandomX cache or dataset and start all threads
+    pub init_secs: f64,
+    pub hashes: u64,
+    pub duration_secs: f64,
+    pub hashrate: f64,
+    pub hashrate_per_thread: f64,
+    pub numa_nodes: Vec<NodeResult>,
+}
+
+impl BenchResult {
+    fn new(
+        threads: usize, full_dataset: bool, numa_binding: bool, init: Duration,
+        start: &TelemetrySnapshot, end: &TelemetrySnapshot, duration: Duration,
+    ) -> Self {
+        let secs = duration.as_secs_f64().max(f64::EPSILON);
+        let hashes = end.hashes.saturating_sub(start.hashes);
+        let hashrate = hashes as f64 / secs;
+
+        let mut numa_nodes: Vec<NodeResult> = Vec::new();
+        for (before, after) in start.threads.iter().zip(&end.threads) {
+            let hashrate =
+                after.hashes.saturating_sub(before.hashes) as f64 / secs;
+            match numa_nodes.iter_mut().find(|n| n.node_id == after.node_id) {
+                Some(node) => {
+                    node.threads += 1;
+                    node.hashrate += hashrate;
+                }
+                None => numa_nodes.push(NodeResult {
+                    node_id: after.node_id,
+                    threads: 1,
+                    hashrate,
+                }),
+            }
+        }
+        numa_nodes.sort_by_key(|n| n.node_id);
+
+        BenchResult {
+            threads,
+            full_dataset,
+            numa_binding,
+            init_secs: init.as_secs_f64(),
+            hashes,
+            duration_secs: secs,
+            hashrate,
+            hashrate_per_thread: hashrate / threads.max(1) as f64,
+            numa_nodes,
+        }
+    }
+}
+
+/// The problem hashed by the benchmark. Its boundary is zero so that no
+/// solution is ever found and the threads never stop to wait for a new job.
+fn bench_problem() -> ProofOfWorkProblem {
+    ProofOfWorkProblem::new_from_boundary_with_seed_hash(
+        1,
+        H256::repeat_byte(0x11),
+        U256::zero(),
+        H256::from(BENCH_SEED_HASH),
+    )
+}
+
+pub fn run(args: BenchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let threads = if args.threads.is_empty() {
+        vec![thread::available_parallelism().map_or(1, |n| n.get())]
+    } else {
+        args.threads.clone()
+    };
+    if threads.contains(&0) {
+        return Err("Thread counts must be positive".into());
+    }
+
+    let mut results = Vec::new();
+    for &dataset in &args.dataset {
+        for &numa in &args.numa {
+            for &num_threads in &threads {
+                results.push(run_case(
+                    num_threads,
+                    dataset == DatasetMode::Full,
+                    numa == NumaBinding::On,
+                    Duration::from_secs(args.warmup_secs),
+                    Duration::from_secs(args.duration_secs),
+                )?);
+            }
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        print!("{}", format_table(&results));
+    }
+    Ok(())
+}
+
+fn run_case(
+    num_threads: usize, full_dataset: bool, numa_binding: bool,
+    warmup: Duration, duration: Duration,
+) -> Result<BenchResult, Box<dyn std::error::Error>> {
+    info!(
+        "Benchmarking {} threads, full dataset: {}, NUMA binding: {}",
+        num_threads, full_dataset, numa_binding
+    );
+    let init_start = Instant::now();
+    let vm_manager = Arc::new(
+        VMManager::with_options(&BENCH_SEED_HASH, full_dataset, numa_binding)
+            .map_err(|e| format!("Failed to create VMs: {}", e))?,
+    );
+    // The receiver is kept so that the miner does not warn about a closed
+    // solution channel.
+    let layout = ThreadLayout {
+        num_threads,
+        ..Default::default()
+    };
+    let (mut miner, _solutions) =
+        Miner::with_vm_manager(&layout, 0, "bench".into(), vm_manager)
+            .map_err(|e| format!("Failed to start mining threads: {}", e))?;
+    miner.mine(&bench_problem());
+
+    let result = measure(&miner, init_start, warmup, duration).map(
+        |(init, start, end, elapsed)| {
+            BenchResult::new(
+                num_threads,
+                full_dataset,
+                numa_binding,
+                init,
+                &start,
+                &end,
+                elapsed,
+            )
+        },
+    );
+    // Release the VMs before the next case allocates its own.
+    miner.stop();
+    result
+}
+
+fn measure(
+    miner: &Miner, init_start: Instant, warmup: Duration, duration: Duration,
+) -> Result<
+    (Duration, TelemetrySnapshot, TelemetrySnapshot, Duration),
+    Box<dyn std::error::Error>,
+> {
+    while miner
+        .telemetry
+        .snapshot()
+        .threads
+        .iter()
+        .any(|t| t.hashes == 0)
+    {
+        if init_start.elapsed() > STARTUP_TIMEOUT {
+            return Err("Mining threads did not start hashing".into());
+        }
+        thread::sleep(STARTUP_POLL_INTERVAL);
+    }
+    let init = init_start.elapsed();
+
+    thread::sleep(warmup);
+    let start = miner.telemetry.snapshot();
+    let measure_start = Instant::now();
+    thread::sleep(duration);
+    let end = miner.telemetry.snapshot();
+    Ok((init, start, end, measure_start.elapsed()))
+}
+
+fn format_table(results: &[BenchResult]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:>7}  {:>7}  {:>4}  {:>8}  {:>12}  {:>12}  {}",
+        "threads",
+        "dataset",
+        "numa",
+        "init(s)",
+        "H/s",
+        "H/s/thread",
+        "H/s per node"
+    );
+    for result in results {
+        let nodes = result
+            .numa_nodes
+            .iter()
+            .map(|n| format!("{}:{:.1}", n.node_id, n.hashrate))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            out,
+            "{:>7}  {:>7}  {:>4}  {:>8.1}  {:>12.1}  {:>12.1}  {}",
+            result.threads,
+            if result.full_dataset { "full" } else { "light" },
+            if result.numa_binding { "on" } else { "off" },
+            result.init_secs,
+            result.hashrate,
+            result.hashrate_per_thread,
+            nodes
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::Telemetry;
+
+    #[test]
+    fn test_result_from_snapshots() {
+        let telemetry = Telemetry::new(&[(0, 0), (1, 1), (2, 0)]);
+        telemetry.add_hashes(0, 10);
+        let start = telemetry.snapshot();
+        telemetry.add_hashes(0, 20);
+        telemetry.add_hashes(1, 40);
+        telemetry.add_hashes(2, 60);
+        let end = telemetry.snapshot();
+
+        let result = BenchResult::new(
+            3,
+            true,
+            false,
+            Duration::from_secs(5),
+            &start,
+            &end,
+            Duration::from_secs(2),
+        );
+        assert_eq!(result.hashes, 120);
+        assert_eq!(result.hashrate, 60.0);
+        assert_eq!(result.hashrate_per_thread, 20.0);
+        assert_eq!(result.numa_nodes.len(), 2);
+        assert_eq!(result.numa_nodes[0].threads, 2);
+        assert_eq!(result.numa_nodes[0].hashrate, 40.0);
+        assert_eq!(result.numa_nodes[1].hashrate, 20.0);
+
+        let table = format_table(&[result]);
+        assert_eq!(table.lines().count(), 2);
+        assert!(table.lines().nth(1).unwrap().contains("full"));
+    }
+}
@@ -1,15 +1,16 @@
 use log::{debug, info, trace};
-use mazze_types::{H256, U256};
-use mazzecore::pow::ProofOfWorkProblem;
+use mazze_types::{BigEndianHash, H256, U256};
+use mazzecore::pow::{pow_hash_to_quality, ProofOfWorkProblem};
 use std::sync::atomic;
 use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProblemState {
     block_height: u64,
     block_hash: [u8; 32],
     boundary: [u8; 32],
     seed_hash: [u8; 32],
+    pow_lower_bound: bool,
 }
 
 impl ProblemState {
@@ -24,6 +25,7 @@ impl ProblemState {
             block_hash: block_hash.as_bytes().try_into().unwrap(),
             boundary: boundary_bytes,
             seed_hash: seed_hash.as_bytes().try_into().unwrap(),
+            pow_lower_bound: false,
         }
     }
 }
@@ -38,13 +40,14 @@ impl From<&ProofOfWorkProblem> for ProblemState {
             block_hash: problem.block_hash.as_bytes().try_into().unwrap(),
             boundary: boundary_bytes,
             seed_hash: problem.seed_hash.as_bytes().try_into().unwrap(),
+            pow_lower_bound: problem.pow_lower_bound,
         }
     }
 }
 
 impl From<&AtomicProblemState> for ProblemState {
     fn from(state: &AtomicProblemState) -> Self {
-        state.get_problem_details().into()
+        state.with_state(|state| state.clone())
     }
 }
 
@@ -67,6 +70,7 @@ impl Default for AtomicProblemState {
             block_hash: H256::zero().as_bytes().try_into().unwrap(),
             boundary: [0u8; 32],
             seed_hash: [0u8; 32],
+            pow_lower_bound: false,
         };
         Self {
             state: AtomicPtr::new(Box::into_raw(Box::new(initial_state))),
@@ -87,6 +91,7 @@ impl AtomicProblemState {
             block_hash: block_hash.as_bytes().try_into().unwrap(),
             boundary: boundary_bytes,
             seed_hash: seed_hash.as_bytes().try_into().unwrap(),
+            pow_lower_bound: false,
         };
         Self {
             state: AtomicPtr::new(Box::into_raw(Box::new(initial_state))),
@@ -174,53 +179,65 @@ impl AtomicProblemState {
         })
     }
 
+    /// Whether `hash`, computed with `nonce`, meets the boundary. Once the
+    /// two-sided PoW target applies, the hash is taken relative to the lower
+    /// bound given by the nonce.
+    #[cfg(target_arch = "x86_64")]
+    pub fn check_hash_simd(&self, hash: &H256, nonce: &U256) -> bool {
+        self.with_state(|state| {
+            if state.pow_lower_bound {
+                let quality: H256 = BigEndianHash::from_uint(
+                    &pow_hash_to_quality(hash, nonce, true),
+                );
+                Self::hash_le_boundary_simd(&quality, &state.boundary)
+            } else {
+                Self::hash_le_boundary_simd(hash, &state.boundary)
+            }
+        })
+    }
+
     #[cfg(target_arch = "x86_64")]
-    pub fn check_hash_simd(&self, hash: &H256) -> bool {
+    fn hash_le_boundary_simd(hash: &H256, boundary: &[u8; 32]) -> bool {
         use log::trace;
         unsafe {
             use std::arch::x86_64::{
-                __m256i, _mm256_cmpeq_epi8, _mm256_cmpgt_epi8,
-                _mm256_loadu_si256, _mm256_movemask_epi8, _mm256_testz_si256,
+                __m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256,
+                _mm256_movemask_epi8,
             };
 
-            self.with_state(|state| {
-                trace!("Comparing hash:     {}", hex::encode(hash.as_bytes()));
-                trace!("Against boundary:   {}", hex::encode(&state.boundary));
+            trace!("Comparing hash:     {}", hex::encode(hash.as_bytes()));
+            trace!("Against boundary:   {}", hex::encode(boundary));
 
-                let boundary_vec = _mm256_loadu_si256(
-                    state.boundary.as_ptr() as *const __m256i
-                );
-                let hash_vec = _mm256_loadu_si256(
-                    hash.as_bytes().as_ptr() as *const __m256i
-                );
+            let boundary_vec =
+                _mm256_loadu_si256(boundary.as_ptr() as *const __m256i);
+            let hash_vec =
+                _mm256_loadu_si256(hash.as_bytes().as_ptr() as *const __m256i);
 
-                // First check equality
-                let eq = _mm256_cmpeq_epi8(hash_vec, boundary_vec);
-                let eq_mask = _mm256_movemask_epi8(eq);
+            // First check equality
+            let eq = _mm256_cmpeq_epi8(hash_vec, boundary_vec);
+            let eq_mask = _mm256_movemask_epi8(eq);
 
-                trace!("Equality mask: {:032b}", eq_mask);
+            trace!("Equality mask: {:032b}", eq_mask);
 
-                if eq_mask == -1 {
-                    trace!("All bytes equal, returning true");
-                    return true; // All bytes equal
-                }
+            if eq_mask == -1 {
+                trace!("All bytes equal, returning true");
+                return true; // All bytes equal
+            }
 
-                // Find first differing byte
-                let first_diff = eq_mask.trailing_ones() as usize;
-                trace!("First differing byte at position: {}", first_diff);
-                trace!(
-                    "Hash byte: {:02x}, Boundary byte: {:02x}",
-                    hash.as_bytes()[first_diff],
-                    state.boundary[first_diff]
-                );
+            // Find first differing byte
+            let first_diff = eq_mask.trailing_ones() as usize;
+            trace!("First differing byte at position: {}", first_diff);
+            trace!(
+                "Hash byte: {:02x}, Boundary byte: {:02x}",
+                hash.as_bytes()[first_diff],
+                boundary[first_diff]
+            );
 
-                // Compare the first differing byte
-                let result =
-                    hash.as_bytes()[first_diff] <= state.boundary[first_diff];
-                trace!("Final comparison result: {}", result);
+            // Compare the first differing byte
+            let result = hash.as_bytes()[first_diff] <= boundary[first_diff];
+            trace!("Final comparison result: {}", result);
 
-                result
-            })
+            result
         }
     }
 }
@@ -325,7 +342,8 @@ mod tests {
 
             // Debug prints for verification
             trace!("Testing valid hash comparison:");
-            let simd_result = atomic_state.check_hash_simd(&valid_hash);
+            let simd_result =
+                atomic_state.check_hash_simd(&valid_hash, &U256::zero());
             let scalar_result = U256::from(valid_hash.as_bytes()) <= boundary;
             trace!(
                 "SIMD result: {}, Scalar result: {}",
@@ -338,7 +356,8 @@ mod tests {
             );
 
             trace!("\nTesting invalid hash comparison:");
-            let simd_result = atomic_state.check_hash_simd(&invalid_hash);
+            let simd_result =
+                atomic_state.check_hash_simd(&invalid_hash, &U256::zero());
             let scalar_result = U256::from(invalid_hash.as_bytes()) <= boundary;
             trace!(
                 "SIMD result: {}, Scalar result: {}",
@@ -352,6 +371,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pow_lower_bound_hash_check() {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let boundary = U256::from_str(
+                "00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            )
+            .unwrap();
+            let nonce = U256::MAX;
+            let lower_bound = mazzecore::pow::nonce_to_lower_bound(&nonce);
+            let small_hash: H256 = BigEndianHash::from_uint(&boundary);
+            let windowed_hash: H256 =
+                BigEndianHash::from_uint(&(lower_bound + 1));
+            let problem = ProofOfWorkProblem::new_from_boundary(
+                1,
+                H256::zero(),
+                boundary,
+                H256::zero(),
+            );
+
+            // Before the transition only the hash itself is compared.
+            let atomic_state = AtomicProblemState::default();
+            atomic_state.update(ProblemState::from(&problem));
+            assert!(atomic_state.check_hash_simd(&small_hash, &nonce));
+            assert!(!atomic_state.check_hash_simd(&windowed_hash, &nonce));
+
+            // After the transition the hash must be in the window of the
+            // nonce, and low nonces keep a zero lower bound.
+            atomic_state.update(ProblemState::from(
+                &problem.with_pow_lower_bound(true),
+            ));
+            assert!(!atomic_state.check_hash_simd(&small_hash, &nonce));
+            assert!(atomic_state.check_hash_simd(&windowed_hash, &nonce));
+            assert!(atomic_state.check_hash_simd(&small_hash, &U256::one()));
+            assert!(
+                ProblemState::from(&atomic_state).pow_lower_bound,
+                "The flag should be kept when copying the state"
+            );
+        }
+    }
+
     #[test]
     fn test_boundary_conversions() {
         let boundary_hex =
@@ -428,7 +488,8 @@ mod tests {
             let hash = H256::from_str(hash_hex).unwrap();
 
             // Test SIMD implementation
-            let simd_result = atomic_state.check_hash_simd(&hash);
+            let simd_result =
+                atomic_state.check_hash_simd(&hash, &U256::zero());
 
             // Test scalar comparison for verification
             let scalar_result = U256::from(hash.as_bytes()) <= boundary;
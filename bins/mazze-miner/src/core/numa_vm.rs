@@ -3,7 +3,8 @@ use mazze_types::{H256, U256};
 use mazzecore::pow::ProofOfWorkProblem;
 use rust_randomx::{Context as RandomXContext, Hasher};
 use std::cell::RefCell;
-use std::{str::FromStr, sync::Arc, sync::RwLock};
+use std::thread;
+use std::{str::FromStr, sync::Arc, sync::Mutex, sync::RwLock};
 
 use super::{NumaError, NumaTopology, ThreadAssignment};
 use crate::core::{AtomicProblemState, ProblemState};
@@ -15,11 +16,15 @@ pub struct ThreadLocalVM {
 
 impl ThreadLocalVM {
     pub fn new(
-        node_id: usize, ctx: Arc<RandomXContext>, topology: &NumaTopology,
+        node_id: usize, ctx: Arc<RandomXContext>,
+        topology: Option<&NumaTopology>,
     ) -> Result<Self, NumaError> {
         info!("Creating new thread-local VM for NUMA node {}", node_id);
 
-        topology.bind_thread_to_node(node_id)?;
+        // Without a topology the thread is left where the OS schedules it
+        if let Some(topology) = topology {
+            topology.bind_thread_to_node(node_id)?;
+        }
 
         let hasher = Hasher::new(ctx);
 
@@ -46,8 +51,8 @@ impl ThreadLocalVM {
         self.problem_state.get_block_height()
     }
 
-    pub fn check_hash(&self, hash: &H256) -> bool {
-        self.problem_state.check_hash_simd(hash)
+    pub fn check_hash(&self, hash: &H256, nonce: &U256) -> bool {
+        self.problem_state.check_hash_simd(hash, nonce)
     }
 
     pub fn update(
@@ -71,10 +76,16 @@ pub struct VMManager {
     pub topology: NumaTopology,
     reference_state: AtomicProblemState,
     context: RwLock<Arc<RandomXContext>>,
+    /// Context of the next seed hash, built while mining on the current one.
+    next_context: Mutex<Option<(H256, Arc<RandomXContext>)>>,
+    /// Seed hash whose context is being built in the background.
+    preparing: Mutex<Option<H256>>,
+    full_memory: bool,
+    numa_binding: bool,
 }
 
 impl VMManager {
-    fn randomx_use_full_memory() -> bool {
+    pub fn randomx_use_full_memory() -> bool {
         // Default to full memory (dataset) for best performance unless overridden.
         match std::env::var("RANDOMX_FULL_MEM") {
             Ok(v) => match v.as_str() {
@@ -85,28 +96,46 @@ impl VMManager {
         }
     }
 
-    pub fn new() -> Result<Self, NumaError> {
+    pub fn new(full_memory: bool) -> Result<Self, NumaError> {
         // TODO: init with new seed hash
-        info!("Initializing RandomX context");
         // This is the genesis hash, we should receive a new hash on subscribe or delay VM creation
         let temp_seed_hash = [
             64, 150, 60, 66, 190, 75, 98, 194, 155, 219, 240, 243, 85, 138, 89,
             208, 98, 34, 241, 9, 35, 101, 195, 39, 166, 14, 116, 82, 106, 188,
             165, 14,
         ];
-        let context = RwLock::new(Arc::new(RandomXContext::new(
-            &temp_seed_hash,
-            Self::randomx_use_full_memory(),
-        )));
+        Self::with_options(&temp_seed_hash, full_memory, true)
+    }
+
+    /// Creates the VMs for `seed_hash`, with the full RandomX dataset or
+    /// only the light cache, and with or without binding the mining threads
+    /// to their NUMA node.
+    pub fn with_options(
+        seed_hash: &[u8], full_memory: bool, numa_binding: bool,
+    ) -> Result<Self, NumaError> {
+        info!(
+            "Initializing RandomX context (full memory: {}, NUMA binding: {})",
+            full_memory, numa_binding
+        );
+        let context =
+            RwLock::new(Arc::new(RandomXContext::new(seed_hash, full_memory)));
         info!("RandomX context initialized");
 
         Ok(Self {
             topology: NumaTopology::detect()?,
             reference_state: AtomicProblemState::default(),
             context,
+            next_context: Mutex::new(None),
+            preparing: Mutex::new(None),
+            full_memory,
+            numa_binding,
         })
     }
 
+    pub fn numa_binding(&self) -> bool {
+        self.numa_binding
+    }
+
     pub fn is_block_hash_matching(&self, block_hash: &H256) -> bool {
         self.reference_state.matches(block_hash)
     }
@@ -132,7 +161,7 @@ impl VMManager {
                 *vm_ref = Some(ThreadLocalVM::new(
                     assignment.node_id,
                     self.get_context(), // Using get_context() to access through RwLock
-                    &self.topology,
+                    Some(&self.topology).filter(|_| self.numa_binding),
                 )?);
             }
             Ok(f(vm_ref.as_mut().unwrap()))
@@ -140,16 +169,25 @@ impl VMManager {
     }
 
     pub fn update_if_needed(
-        &self, problem: &ProofOfWorkProblem,
+        self: &Arc<Self>, problem: &ProofOfWorkProblem,
     ) -> Result<(), NumaError> {
         let problem_seed_hash = problem.seed_hash.as_bytes();
-        if problem_seed_hash != self.reference_state.get_seed_hash() {
+        // Compare with the context rather than the reference state, which
+        // has no seed hash before the first job, so that the context built
+        // by `with_options` is reused
+        if self.get_context().key() != problem_seed_hash {
+            // Switch to the prepared context if there is one, otherwise build
+            // it now, which stalls mining until it is done
+            let context = match self.take_prepared_context(&problem.seed_hash) {
+                Some(context) => context,
+                None => Arc::new(RandomXContext::new(
+                    problem_seed_hash,
+                    self.full_memory,
+                )),
+            };
             // Update context using the RwLock for interior mutability
             let mut context_write = self.context.write().unwrap();
-            *context_write = Arc::new(RandomXContext::new(
-                problem_seed_hash,
-                Self::randomx_use_full_memory(),
-            ));
+            *context_write = context;
             debug!("RandomX context updated with new seed hash");
         }
 
@@ -160,6 +198,134 @@ impl VMManager {
         self.reference_state.update(ProblemState::from(problem));
         info!("Reference state updated successfully");
 
+        if let Some(next_seed_hash) = problem.next_seed_hash {
+            self.prepare_next(next_seed_hash);
+        }
+
         Ok(())
     }
+
+    /// Builds the context of `seed_hash` in a background thread, so that the
+    /// switch to the next RandomX epoch does not stall mining. With the full
+    /// dataset, this needs memory for a second dataset until the switch.
+    pub fn prepare_next(self: &Arc<Self>, seed_hash: H256) {
+        if seed_hash.as_bytes() == self.reference_state.get_seed_hash()
+            || self.is_prepared(&seed_hash)
+        {
+            return;
+        }
+        {
+            // One context at a time, another seed hash is retried with the
+            // next job
+            let mut preparing = self.preparing.lock().unwrap();
+            if preparing.is_some() {
+                return;
+            }
+            *preparing = Some(seed_hash);
+        }
+
+        let manager = Arc::clone(self);
+        let spawned = thread::Builder::new()
+            .name("randomx-prepare".into())
+            .spawn(move || {
+                info!("Preparing RandomX context for seed hash {}", seed_hash);
+                let context = Arc::new(RandomXContext::new(
+                    seed_hash.as_bytes(),
+                    manager.full_memory,
+                ));
+                *manager.next_context.lock().unwrap() =
+                    Some((seed_hash, context));
+                *manager.preparing.lock().unwrap() = None;
+                info!("RandomX context for seed hash {} is ready", seed_hash);
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to spawn RandomX prepare thread: {}", e);
+            *self.preparing.lock().unwrap() = None;
+        }
+    }
+
+    fn is_prepared(&self, seed_hash: &H256) -> bool {
+        self.next_context
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(false, |(hash, _)| hash == seed_hash)
+    }
+
+    fn take_prepared_context(
+        &self, seed_hash: &H256,
+    ) -> Option<Arc<RandomXContext>> {
+        let mut next_context = self.next_context.lock().unwrap();
+        match next_context.take() {
+            Some((hash, context)) if hash == *seed_hash => {
+                info!("Switching to prepared RandomX context");
+                Some(context)
+            }
+            other => {
+                *next_context = other;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn problem(
+        seed_hash: H256, next_seed_hash: Option<H256>,
+    ) -> ProofOfWorkProblem {
+        ProofOfWorkProblem::new_from_boundary_with_seed_hash(
+            1,
+            H256::repeat_byte(0x11),
+            U256::MAX,
+            seed_hash,
+        )
+        .with_next_seed_hash(next_seed_hash)
+    }
+
+    fn wait_for_prepared(manager: &VMManager, seed_hash: &H256) {
+        let start = Instant::now();
+        while !manager.is_prepared(seed_hash) {
+            assert!(
+                start.elapsed() < Duration::from_secs(60),
+                "context not prepared"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_switch_to_prepared_context() {
+        let (seed_0, seed_1) = (H256::repeat_byte(7), H256::repeat_byte(1));
+        let manager = Arc::new(
+            VMManager::with_options(seed_0.as_bytes(), false, false).unwrap(),
+        );
+
+        // The context of the initial seed hash is not built again.
+        let initial = manager.get_context();
+        manager.update_if_needed(&problem(seed_0, None)).unwrap();
+        assert!(Arc::ptr_eq(&manager.get_context(), &initial));
+
+        // Jobs of the current epoch announce the next seed hash.
+        assert!(!manager.is_prepared(&seed_1));
+        manager
+            .update_if_needed(&problem(seed_0, Some(seed_1)))
+            .unwrap();
+        wait_for_prepared(&manager, &seed_1);
+        let prepared = manager.next_context.lock().unwrap().clone().unwrap().1;
+        assert!(!Arc::ptr_eq(&manager.get_context(), &prepared));
+
+        // The first job of the next epoch switches to the prepared context.
+        manager.update_if_needed(&problem(seed_1, None)).unwrap();
+        assert!(Arc::ptr_eq(&manager.get_context(), &prepared));
+        assert!(!manager.is_prepared(&seed_1));
+        assert_eq!(manager.reference_state.get_seed_hash(), seed_1.0);
+
+        // The current seed hash is not prepared again.
+        manager.prepare_next(seed_1);
+        assert!(manager.preparing.lock().unwrap().is_none());
+    }
 }
@@ -0,0 +1,256 @@
+use log::{debug, info, warn};
+use mazze_types::H256;
+use std::time::Instant;
+use tokio::{
+    net::TcpStream,
+    time::{sleep, sleep_until, timeout, Duration},
+};
+use tokio_util::codec::{Framed, LinesCodec};
+
+use crate::stratum_client::authenticate;
+
+/// Timings of the endpoint failover.
+#[derive(Debug, Clone)]
+pub struct FailoverConfig {
+    /// Give up connecting to an endpoint after this long.
+    pub connect_timeout: Duration,
+    /// Backoff after the first failure of an endpoint, doubled on each
+    /// consecutive failure.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How often the primary endpoint is probed while mining on a fallback.
+    pub primary_check_interval: Duration,
+    /// A session lasting at least this long resets the failure count of its
+    /// endpoint.
+    pub stable_session: Duration,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        FailoverConfig {
+            connect_timeout: Duration::from_secs(10),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            primary_check_interval: Duration::from_secs(60),
+            stable_session: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct EndpointState {
+    address: String,
+    consecutive_failures: u32,
+    retry_at: Option<Instant>,
+}
+
+/// Ordered list of stratum endpoints, the first one being the primary.
+///
+/// The miner always uses the first endpoint that is not backing off after a
+/// failure, and switches back to the primary as soon as it passes a health
+/// check.
+pub struct EndpointPool {
+    endpoints: Vec<EndpointState>,
+    config: FailoverConfig,
+}
+
+impl EndpointPool {
+    pub fn new(addresses: Vec<String>, config: FailoverConfig) -> Self {
+        assert!(!addresses.is_empty(), "at least one endpoint is required");
+        EndpointPool {
+            endpoints: addresses
+                .into_iter()
+                .map(|address| EndpointState {
+                    address,
+                    consecutive_failures: 0,
+                    retry_at: None,
+                })
+                .collect(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &FailoverConfig {
+        &self.config
+    }
+
+    pub fn address(&self, index: usize) -> &str {
+        &self.endpoints[index].address
+    }
+
+    pub fn is_primary(&self, index: usize) -> bool {
+        index == 0
+    }
+
+    /// Returns the first endpoint available at `now`, or the earliest time
+    /// an endpoint becomes available again.
+    fn select(&self, now: Instant) -> Result<usize, Instant> {
+        let mut earliest: Option<(usize, Instant)> = None;
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            match endpoint.retry_at {
+                Some(retry_at) if retry_at > now => {
+                    if earliest.map_or(true, |(_, at)| retry_at < at) {
+                        earliest = Some((index, retry_at));
+                    }
+                }
+                _ => return Ok(index),
+            }
+        }
+        Err(earliest.expect("endpoints is not empty; qed").1)
+    }
+
+    /// Waits until an endpoint is available and returns its index.
+    pub async fn next_endpoint(&self) -> usize {
+        loop {
+            match self.select(Instant::now()) {
+                Ok(index) => return index,
+                Err(retry_at) => {
+                    debug!(
+                        "All endpoints are backing off, waiting {:?}",
+                        retry_at.saturating_duration_since(Instant::now())
+                    );
+                    sleep_until(retry_at.into()).await;
+                }
+            }
+        }
+    }
+
+    /// Records that a session with the endpoint ended or could not be
+    /// established, after `uptime` of mining on it.
+    pub fn on_failure(&mut self, index: usize, uptime: Duration) {
+        self.on_failure_at(index, uptime, Instant::now());
+    }
+
+    fn on_failure_at(&mut self, index: usize, uptime: Duration, now: Instant) {
+        let config = &self.config;
+        let endpoint = &mut self.endpoints[index];
+        if uptime >= config.stable_session {
+            endpoint.consecutive_failures = 0;
+        }
+        let backoff = config
+            .initial_backoff
+            .checked_mul(1 << endpoint.consecutive_failures.min(16))
+            .unwrap_or(config.max_backoff)
+            .min(config.max_backoff);
+        endpoint.consecutive_failures += 1;
+        endpoint.retry_at = Some(now + backoff);
+        warn!(
+            "Endpoint {} failed {} time(s) in a row, retrying it in {:?}",
+            endpoint.address, endpoint.consecutive_failures, backoff
+        );
+    }
+
+    /// Records a successfully established session.
+    pub fn on_connected(&mut self, index: usize) {
+        self.endpoints[index].retry_at = None;
+        info!("Mining on endpoint {}", self.endpoints[index].address);
+    }
+
+    /// Resolves once the primary endpoint passes a health check. Never
+    /// resolves if `current` already is the primary.
+    pub async fn wait_for_primary(
+        &self, current: usize, worker_name: &str, stratum_secret: Option<&H256>,
+    ) {
+        if self.is_primary(current) {
+            return futures::future::pending().await;
+        }
+        loop {
+            sleep(self.config.primary_check_interval).await;
+            if self
+                .health_check(self.address(0), worker_name, stratum_secret)
+                .await
+            {
+                info!("Primary endpoint {} is healthy again", self.address(0));
+                return;
+            }
+        }
+    }
+
+    /// An endpoint is healthy if it accepts a connection and authorizes the
+    /// worker.
+    async fn health_check(
+        &self, address: &str, worker_name: &str, stratum_secret: Option<&H256>,
+    ) -> bool {
+        let connect = timeout(self.config.connect_timeout, async {
+            let stream = TcpStream::connect(address).await?;
+            let mut framed = Framed::new(stream, LinesCodec::new());
+            authenticate(&mut framed, worker_name, stratum_secret).await?;
+            Ok::<_, Box<dyn std::error::Error>>(())
+        });
+        match connect.await {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                debug!("Health check of {} failed: {:?}", address, e);
+                false
+            }
+            Err(_) => {
+                debug!("Health check of {} timed out", address);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> EndpointPool {
+        EndpointPool::new(
+            vec!["primary:1".into(), "backup:2".into(), "backup:3".into()],
+            FailoverConfig::default(),
+        )
+    }
+
+    #[test]
+    fn test_prefers_primary() {
+        let pool = pool();
+        assert_eq!(pool.select(Instant::now()), Ok(0));
+    }
+
+    #[test]
+    fn test_fails_over_in_order() {
+        let mut pool = pool();
+        let now = Instant::now();
+        pool.on_failure_at(0, Duration::ZERO, now);
+        assert_eq!(pool.select(now), Ok(1));
+        pool.on_failure_at(1, Duration::ZERO, now);
+        assert_eq!(pool.select(now), Ok(2));
+
+        // The primary is retried first once its backoff is over.
+        assert_eq!(pool.select(now + Duration::from_secs(1)), Ok(0));
+    }
+
+    #[test]
+    fn test_waits_for_earliest_endpoint() {
+        let mut pool = pool();
+        let now = Instant::now();
+        pool.on_failure_at(0, Duration::ZERO, now);
+        pool.on_failure_at(0, Duration::ZERO, now);
+        pool.on_failure_at(1, Duration::ZERO, now);
+        pool.on_failure_at(2, Duration::ZERO, now);
+        assert_eq!(pool.select(now), Err(now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_backoff_grows_and_resets() {
+        let mut pool = pool();
+        let now = Instant::now();
+        for _ in 0..10 {
+            pool.on_failure_at(0, Duration::ZERO, now);
+        }
+        assert_eq!(
+            pool.endpoints[0].retry_at,
+            Some(now + Duration::from_secs(60))
+        );
+
+        // A long enough session resets the backoff.
+        pool.on_connected(0);
+        pool.on_failure_at(0, Duration::from_secs(600), now);
+        assert_eq!(pool.endpoints[0].consecutive_failures, 1);
+        assert_eq!(
+            pool.endpoints[0].retry_at,
+            Some(now + Duration::from_secs(1))
+        );
+    }
+}
@@ -1,92 +1,82 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+use clap::Parser;
+use failover::{EndpointPool, FailoverConfig};
 use log::{error, info};
 use mazzecore::pow::ProofOfWorkSolution;
-use miner_config::MinerConfig;
-use std::process;
+use miner_config::{CliArgs, Command, MinerConfig};
+use std::time::Instant;
 use tokio;
 use tokio::signal::ctrl_c;
-use tokio::time::{sleep, Duration};
+use tokio::time::{timeout, Duration};
 
+mod bench;
 mod core;
+mod failover;
 mod miner;
 mod miner_config;
 mod stratum_client;
+mod telemetry;
 
 use miner::Miner;
 use stratum_client::StratumClient;
 
-async fn connect_with_retry(
-    config: &MinerConfig, miner: Miner,
-    solution_receiver: tokio::sync::broadcast::Receiver<(
-        ProofOfWorkSolution,
-        u64,
-    )>,
-) -> Result<StratumClient, Box<dyn std::error::Error>> {
-    let initial_delay = Duration::from_secs(1);
-    let max_delay = Duration::from_secs(60);
-    let mut delay = initial_delay;
-
-    loop {
-        match StratumClient::connect(
-            &config.stratum_address,
-            &config.stratum_secret,
-            miner.clone(),
-            solution_receiver.resubscribe(),
-        )
-        .await
-        {
-            Ok(client) => {
-                info!("Connected to server successfully");
-                return Ok(client);
-            }
-            Err(e) => {
-                error!(
-                    "Failed to connect: {:?}. Retrying in {:?}...",
-                    e, delay
-                );
-                sleep(delay).await;
-                delay = std::cmp::min(delay * 2, max_delay);
-            }
-        }
-    }
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::builder()
-        .format_timestamp_millis()
-        .filter_module(
-            "mazze_miner::core::atomic_state",
-            log::LevelFilter::Debug,
-        )
-        .init();
-
-    info!("Initializing Mazze Miner client...");
+    let mut cli_args = CliArgs::parse();
+    if let Some(Command::Bench(args)) = cli_args.command.take() {
+        init_logger(None);
+        info!("Running RandomX benchmark...");
+        return bench::run(args);
+    }
 
-    let config = match MinerConfig::new() {
+    let config = MinerConfig::new(cli_args);
+    init_logger(config.as_ref().ok().and_then(|config| config.log_level));
+    let config = match config {
         Ok(config) => config,
         Err(e) => {
-            error!("Failed to load configuration: {:?}", e);
+            error!("Failed to load configuration: {}", e);
             return Err(e);
         }
     };
 
+    if config.print_config {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+
+    info!("Initializing Mazze Miner client...");
     info!(
-        "Starting Mazze Miner client with worker id {} and num_threads {}",
-        config.worker_id, config.num_threads
+        "Starting Mazze Miner client with worker {} (id {}) and threads {:?}",
+        config.worker_name, config.worker_id, config.threads
     );
 
     // Create NUMA-aware miner instead of legacy miner
-    let (miner, solution_receiver) =
-        match Miner::new_numa(config.num_threads, config.worker_id) {
-            Ok(result) => result,
-            Err(e) => {
-                error!("Failed to initialize NUMA-aware miner: {:?}", e);
-                return Err("Failed to initialize NUMA-aware miner".into());
+    let (miner, solution_receiver) = match Miner::new_numa(
+        &config.threads,
+        config.full_memory,
+        config.worker_id,
+        config.worker_name.clone(),
+    ) {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to initialize NUMA-aware miner: {:?}", e);
+            return Err("Failed to initialize NUMA-aware miner".into());
+        }
+    };
+
+    if let Some(port) = config.telemetry_port {
+        let addr = ([127, 0, 0, 1], port).into();
+        let telemetry = miner.telemetry.clone();
+        let worker_name = config.worker_name.clone();
+        tokio::spawn(async move {
+            if let Err(e) = telemetry::serve(addr, telemetry, worker_name).await
+            {
+                error!("Telemetry endpoint failed: {:?}", e);
             }
-        };
+        });
+    }
 
     // Set up Ctrl+C handler
     let (tx, mut rx) = tokio::sync::mpsc::channel(1);
@@ -97,32 +87,65 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let _ = tx.send(()).await;
     });
 
-    loop {
-        match connect_with_retry(
-            &config,
-            miner.clone(),
-            solution_receiver.resubscribe(),
-        )
-        .await
-        {
-            Ok(mut client) => {
-                info!("Starting mining operation");
-                tokio::select! {
-                    result = client.run() => {
-                        match result {
-                            Ok(_) => info!("Mining operation completed successfully"),
-                            Err(e) => error!("Error during client execution: {:?}", e),
-                        }
-                    }
-                    _ = rx.recv() => {
-                        info!("Received shutdown signal. Stopping mining operation.");
-                        break;
-                    }
+    let mut endpoints = EndpointPool::new(
+        config.stratum_addresses.clone(),
+        FailoverConfig::default(),
+    );
+
+    // The miner, and with it the RandomX datasets of every NUMA node, is
+    // kept across sessions so that switching endpoints does not rebuild them.
+    'mining: loop {
+        let index = tokio::select! {
+            index = endpoints.next_endpoint() => index,
+            _ = rx.recv() => break 'mining,
+        };
+        let address = endpoints.address(index).to_string();
+        info!("Connecting to {}", address);
+
+        let connect = timeout(
+            endpoints.config().connect_timeout,
+            StratumClient::connect(
+                &address,
+                config.stratum_secret,
+                miner.clone(),
+                solution_receiver.resubscribe(),
+            ),
+        );
+        let mut client = match connect.await {
+            Ok(Ok(client)) => client,
+            Ok(Err(e)) => {
+                error!("Failed to connect to {}: {:?}", address, e);
+                endpoints.on_failure(index, Duration::ZERO);
+                continue;
+            }
+            Err(_) => {
+                error!("Timed out connecting to {}", address);
+                endpoints.on_failure(index, Duration::ZERO);
+                continue;
+            }
+        };
+        endpoints.on_connected(index);
+        let started = Instant::now();
+
+        info!("Starting mining operation");
+        tokio::select! {
+            result = client.run() => {
+                match result {
+                    Ok(_) => info!("Session with {} ended", address),
+                    Err(e) => error!("Error during client execution on {}: {:?}", address, e),
                 }
+                endpoints.on_failure(index, started.elapsed());
             }
-            Err(e) => {
-                error!("Failed to connect to the node: {:?}", e);
-                process::exit(1);
+            _ = endpoints.wait_for_primary(
+                index,
+                &config.worker_name,
+                config.stratum_secret.as_ref(),
+            ) => {
+                info!("Switching back to the primary endpoint");
+            }
+            _ = rx.recv() => {
+                info!("Received shutdown signal. Stopping mining operation.");
+                break 'mining;
             }
         }
     }
@@ -130,3 +153,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Shutting down Mazze Miner client...");
     Ok(())
 }
+
+/// `RUST_LOG` takes precedence over the configured log level.
+fn init_logger(log_level: Option<log::LevelFilter>) {
+    let mut builder = env_logger::builder();
+    builder.format_timestamp_millis().filter_module(
+        "mazze_miner::core::atomic_state",
+        log::LevelFilter::Debug,
+    );
+    if let (Some(level), Err(_)) = (log_level, std::env::var("RUST_LOG")) {
+        builder.filter_level(level);
+    }
+    builder.init();
+}
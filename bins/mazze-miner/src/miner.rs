@@ -7,6 +7,7 @@ use mazzecore::pow::{
 };
 use serde_json::Value;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::{mpsc, Barrier, Mutex};
 use std::thread;
@@ -18,6 +19,7 @@ use crate::core::NumaError;
 use crate::core::ThreadAssignment;
 use crate::core::*;
 use crate::core::{VMManager, THREAD_VM};
+use crate::telemetry::Telemetry;
 
 const CHECK_INTERVAL: u64 = 64 * 128 as u64;
 
@@ -31,6 +33,70 @@ atomic ptr swap ───────────────────►   u
                                       compare states    ◄─┘
 */
 
+/// How the mining threads are laid out over the cores of the machine.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadLayout {
+    /// Number of mining threads, spread round-robin over the NUMA nodes
+    pub num_threads: usize,
+    /// Run this many threads on every NUMA node instead of `num_threads`
+    pub threads_per_numa_node: Option<usize>,
+    /// Cores to pin the threads to, one thread per entry. Takes precedence
+    /// over the thread counts.
+    pub core_affinity: Vec<usize>,
+}
+
+impl ThreadLayout {
+    /// Assigns the threads given the `(node_id, cores)` of each NUMA node.
+    pub fn assignments(
+        &self, nodes: &[(usize, Vec<usize>)],
+    ) -> Vec<ThreadAssignment> {
+        let num_nodes = nodes.len().max(1);
+        if !self.core_affinity.is_empty() {
+            return self
+                .core_affinity
+                .iter()
+                .enumerate()
+                .map(|(thread_id, &core_id)| ThreadAssignment {
+                    thread_id,
+                    node_id: nodes
+                        .iter()
+                        .find(|(_, cores)| cores.contains(&core_id))
+                        .map_or(thread_id % num_nodes, |(node_id, _)| *node_id),
+                    core_id,
+                })
+                .collect();
+        }
+
+        if let Some(per_node) = self.threads_per_numa_node {
+            let mut assignments = Vec::with_capacity(per_node * nodes.len());
+            for (node_id, cores) in nodes {
+                for index in 0..per_node {
+                    let thread_id = assignments.len();
+                    let core_id = if cores.is_empty() {
+                        thread_id
+                    } else {
+                        cores[index % cores.len()]
+                    };
+                    assignments.push(ThreadAssignment {
+                        thread_id,
+                        node_id: *node_id,
+                        core_id,
+                    });
+                }
+            }
+            return assignments;
+        }
+
+        (0..self.num_threads)
+            .map(|thread_id| ThreadAssignment {
+                thread_id,
+                node_id: thread_id % num_nodes,
+                core_id: thread_id,
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone)]
 pub struct Miner {
     pub worker_id: usize,
@@ -38,25 +104,65 @@ pub struct Miner {
     num_threads: usize,
     solution_sender: mpsc::Sender<(ProofOfWorkSolution, u64)>,
     vm_manager: Arc<VMManager>,
+    pub telemetry: Arc<Telemetry>,
+    shutdown: Arc<AtomicBool>,
+    threads: Arc<Mutex<Vec<thread::JoinHandle<()>>>>,
 }
 
 impl Miner {
     pub fn new_numa(
-        num_threads: usize, worker_id: usize,
+        layout: &ThreadLayout, full_memory: bool, worker_id: usize,
+        worker_name: String,
+    ) -> Result<
+        (Self, broadcast::Receiver<(ProofOfWorkSolution, u64)>),
+        NumaError,
+    > {
+        let vm_manager = Arc::new(VMManager::new(full_memory)?);
+        Self::with_vm_manager(layout, worker_id, worker_name, vm_manager)
+    }
+
+    /// Starts the mining threads of `layout` using the VMs of `vm_manager`.
+    pub fn with_vm_manager(
+        layout: &ThreadLayout, worker_id: usize, worker_name: String,
+        vm_manager: Arc<VMManager>,
     ) -> Result<
         (Self, broadcast::Receiver<(ProofOfWorkSolution, u64)>),
         NumaError,
     > {
         let (stratum_tx, stratum_rx) = broadcast::channel(32);
         let (solution_tx, solution_rx) = mpsc::channel();
-        let vm_manager = Arc::new(VMManager::new()?);
+
+        let topology = &vm_manager.topology;
+        let nodes = topology
+            .get_nodes()
+            .into_iter()
+            .map(|node_id| {
+                let cores =
+                    topology.get_cores_for_node(node_id).unwrap_or_default();
+                (node_id, cores)
+            })
+            .collect::<Vec<_>>();
+        let assignments = layout.assignments(&nodes);
+        if assignments.is_empty() {
+            return Err(NumaError::ThreadAssignmentFailed);
+        }
+
+        let threads = assignments
+            .iter()
+            .map(|assignment| (assignment.thread_id, assignment.node_id))
+            .collect::<Vec<_>>();
+        let telemetry = Arc::new(Telemetry::new(&threads));
+        telemetry.spawn_sampler();
 
         let miner = Miner {
             worker_id,
-            worker_name: format!("worker-{}", worker_id),
-            num_threads,
+            worker_name,
+            num_threads: assignments.len(),
             solution_sender: solution_tx,
             vm_manager: Arc::clone(&vm_manager),
+            telemetry,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            threads: Arc::new(Mutex::new(Vec::new())),
         };
 
         // Spawn solution handler
@@ -66,11 +172,25 @@ impl Miner {
         let client_seed = Self::generate_client_seed();
 
         // Spawn mining threads
-        miner.spawn_numa_mining_threads(client_seed)?;
+        miner.spawn_numa_mining_threads(assignments, client_seed)?;
 
         Ok((miner, stratum_rx))
     }
 
+    /// Stops the mining threads and the telemetry sampler and waits for them
+    /// to exit, which releases their VMs.
+    pub fn stop(&self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        let handles =
+            std::mem::take(&mut *self.threads.lock().expect("not poisoned"));
+        for handle in handles {
+            if handle.join().is_err() {
+                warn!("[{}] Mining thread panicked", self.worker_name);
+            }
+        }
+        self.telemetry.stop_sampler();
+    }
+
     pub fn mine(&mut self, problem: &ProofOfWorkProblem) {
         debug!(
             "[{}] mine() called with new height={}, hash={:.8}, seed_hash={}",
@@ -92,7 +212,9 @@ impl Miner {
     pub fn parse_job(
         &mut self, params: &[Value],
     ) -> Result<ProofOfWorkProblem, String> {
-        if params.len() != 5 {
+        // The seed hash of the next RandomX epoch is only sent once it is
+        // known, and the two-sided PoW target flag once it applies
+        if params.len() < 5 || params.len() > 7 {
             return Err("Invalid job data: not enough parameters".into());
         }
 
@@ -134,12 +256,33 @@ impl Miner {
             seed_hash,
         );
 
+        let next_seed_hash = match params.get(5) {
+            Some(Value::Null) | None => None,
+            Some(value) => {
+                let next_seed_hash_str = value
+                    .as_str()
+                    .ok_or("Invalid next_seed_hash: not a string")?;
+                Some(H256::from_slice(
+                    &hex::decode(next_seed_hash_str.trim_start_matches("0x"))
+                        .map_err(|e| format!("Invalid next_seed_hash: {}", e))?,
+                ))
+            }
+        };
+        let pow_lower_bound = match params.get(6) {
+            Some(value) => value
+                .as_bool()
+                .ok_or("Invalid pow_lower_bound: not a bool")?,
+            None => false,
+        };
+
         let problem = ProofOfWorkProblem::new_from_boundary_with_seed_hash(
             block_height,
             pow_hash,
             boundary,
             seed_hash,
-        );
+        )
+        .with_next_seed_hash(next_seed_hash)
+        .with_pow_lower_bound(pow_lower_bound);
 
         self.mine(&problem);
 
@@ -147,7 +290,7 @@ impl Miner {
     }
 
     fn spawn_numa_mining_threads(
-        &self, client_seed: H256,
+        &self, assignments: Vec<ThreadAssignment>, client_seed: H256,
     ) -> Result<(), NumaError> {
         info!(
             "[{}] Spawning {} NUMA-aware mining threads",
@@ -156,23 +299,23 @@ impl Miner {
 
         let barrier = Arc::new(Barrier::new(self.num_threads));
 
-        for thread_id in 0..self.num_threads {
+        for assignment in assignments {
             let barrier = Arc::clone(&barrier);
-            let assignment = ThreadAssignment {
-                thread_id,
-                node_id: thread_id % self.vm_manager.topology.get_nodes().len(),
-                core_id: thread_id,
-            };
 
             info!(
                 "[{}] Assigning thread {} to NUMA node {} core {}",
                 self.worker_name,
-                thread_id,
+                assignment.thread_id,
                 assignment.node_id,
                 assignment.core_id
             );
 
-            self.spawn_mining_thread_numa(assignment, barrier, client_seed)?;
+            let handle = self.spawn_mining_thread_numa(
+                assignment,
+                barrier,
+                client_seed,
+            )?;
+            self.threads.lock().expect("not poisoned").push(handle);
         }
 
         Ok(())
@@ -186,6 +329,8 @@ impl Miner {
         let solution_sender = self.solution_sender.clone();
         let num_threads = self.num_threads;
         let vm_manager = self.vm_manager.clone();
+        let telemetry = self.telemetry.clone();
+        let shutdown = self.shutdown.clone();
 
         let handle = thread::spawn(move || {
             #[cfg(target_os = "linux")]
@@ -203,6 +348,8 @@ impl Miner {
                 num_threads,
                 barrier,
                 client_seed.clone(),
+                telemetry,
+                shutdown,
             );
         });
 
@@ -213,7 +360,8 @@ impl Miner {
         assignment: &ThreadAssignment, worker_name: String,
         solution_sender: mpsc::Sender<(ProofOfWorkSolution, u64)>,
         vm_manager: Arc<VMManager>, num_threads: usize, barrier: Arc<Barrier>,
-        client_seed: H256,
+        client_seed: H256, telemetry: Arc<Telemetry>,
+        shutdown: Arc<AtomicBool>,
     ) {
         info!(
             "[{}] Starting mining thread {} on NUMA node {} core {}",
@@ -225,7 +373,12 @@ impl Miner {
 
         // Set thread affinity
         #[cfg(target_os = "linux")]
-        if let Some(core_ids) = core_affinity::get_core_ids() {
+        if !vm_manager.numa_binding() {
+            debug!(
+                "[{}] NUMA binding disabled, not pinning thread",
+                worker_name
+            );
+        } else if let Some(core_ids) = core_affinity::get_core_ids() {
             if let Some(core_id) = core_ids.get(assignment.core_id) {
                 core_affinity::set_for_current(*core_id);
                 debug!(
@@ -240,6 +393,7 @@ impl Miner {
         // Mining loop
         loop {
             let result = vm_manager.with_vm(assignment, |vm| {
+                telemetry.on_hashing_started();
                 let start_nonce = Self::get_nonce_range_start(
                     assignment.thread_id,
                     num_threads,
@@ -261,6 +415,10 @@ impl Miner {
 
                 // Mining loop
                 loop {
+                    if shutdown.load(Ordering::Relaxed) {
+                        return true;
+                    }
+
                     if current_nonce.low_u64() % CHECK_INTERVAL == 0 {
                         let elapsed = start_time.elapsed();
                         if elapsed.as_secs() > 0 {
@@ -319,8 +477,9 @@ impl Miner {
                     let hash_bytes = vm.hasher.hash(&input);
                     let hash = H256::from_slice(&hash_bytes.as_ref());
                     hashes_computed += 1;
+                    telemetry.add_hashes(assignment.thread_id, 1);
 
-                    if vm.check_hash(&hash) {
+                    if vm.check_hash(&hash, &current_nonce) {
                         info!(
                             "[{}] Found solution! nonce={}, block hash={}, hash={}",
                             worker_name,
@@ -328,6 +487,7 @@ impl Miner {
                             hex::encode(vm.get_current_block_hash().as_bytes()),
                             hex::encode(hash)
                         );
+                        telemetry.on_solution_found();
                         let solution = ProofOfWorkSolution { nonce: current_nonce };
                         if let Err(e) = solution_sender.send((solution, vm.get_current_height())) {
                             warn!("[{}] Failed to send solution: {}", worker_name, e);
@@ -335,6 +495,9 @@ impl Miner {
                         // Wait for new block
                         loop {
                             thread::sleep(Duration::from_millis(50));
+                            if shutdown.load(Ordering::Relaxed) {
+                                return true;
+                            }
                             if !vm_manager.is_block_hash_matching(&vm.get_current_block_hash()) {
                                 debug!("[{}] New block detected after solution, resuming mining", worker_name);
                                 vm.update(
@@ -353,10 +516,23 @@ impl Miner {
                 }
             });
 
-            if let Err(e) = result {
-                error!("[{}] VM error: {}", worker_name, e);
-                thread::sleep(Duration::from_secs(1));
-                continue;
+            match result {
+                Ok(true) => {
+                    debug!(
+                        "[{}] Mining thread {} stopped",
+                        worker_name, assignment.thread_id
+                    );
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!("[{}] VM error: {}", worker_name, e);
+                    if shutdown.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    thread::sleep(Duration::from_secs(1));
+                    continue;
+                }
             }
 
             thread::yield_now();
@@ -472,7 +648,8 @@ mod tests {
         println!("Boundary: {}", hex::encode(&boundary));
         println!(
             "Is hash <= boundary? {}",
-            atomic_state.check_hash_simd(&H256::from_slice(&hash))
+            atomic_state
+                .check_hash_simd(&H256::from_slice(&hash), &U256::zero())
         );
 
         // Convert to U256 for direct comparison
@@ -485,7 +662,8 @@ mod tests {
             "0x91... should be greater than 0x12..."
         );
         assert!(
-            !atomic_state.check_hash_simd(&H256::from_slice(&hash)),
+            !atomic_state
+                .check_hash_simd(&H256::from_slice(&hash), &U256::zero()),
             "SIMD comparison should return false for hash > boundary"
         );
     }
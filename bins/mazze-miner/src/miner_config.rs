@@ -1,64 +1,381 @@
-use clap::Parser;
-use std::fs;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+use mazze_types::H256;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf, str::FromStr};
 use toml;
 
+use crate::bench::BenchArgs;
+use crate::core::VMManager;
+use crate::miner::ThreadLayout;
+
+const DEFAULT_NUM_THREADS: usize = 4;
+const DEFAULT_WORKER_ID: usize = 1;
+
+/// Command line arguments. Except for `--config`, they override the
+/// `[miner]` section of the config file.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
-struct CliArgs {
+pub struct CliArgs {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// Config file, required unless running a subcommand
     #[clap(long, value_parser)]
-    config: PathBuf,
+    config: Option<PathBuf>,
+
+    /// Stratum endpoint, may be repeated. The first one is the primary, the
+    /// others are used in order when it fails.
+    #[clap(long, multiple_occurrences = true)]
+    stratum_address: Vec<String>,
+
+    /// Number of mining threads [default: 4]
+    #[clap(long)]
+    num_threads: Option<usize>,
+
+    /// Run this many threads on every NUMA node instead of `--num-threads`
+    #[clap(long)]
+    threads_per_numa_node: Option<usize>,
+
+    /// Cores to pin the mining threads to, one thread per core, e.g. `0,2,4`
+    #[clap(long, use_value_delimiter = true)]
+    core_affinity: Vec<usize>,
+
+    /// Use the full RandomX dataset (`true`) or only the light cache
+    /// (`false`). Defaults to `RANDOMX_FULL_MEM`, or `true` if unset.
+    #[clap(long)]
+    full_memory: Option<bool>,
+
+    /// Worker id [default: 1]
+    #[clap(long)]
+    worker_id: Option<usize>,
 
+    /// Name used to authorize with the node, defaults to `worker-<id>`
     #[clap(long)]
-    stratum_address: Option<String>,
+    worker_name: Option<String>,
 
-    #[clap(long, default_value = "4")]
-    num_threads: usize,
+    /// Log level, `RUST_LOG` takes precedence if set
+    #[clap(long)]
+    log_level: Option<String>,
+
+    /// Serve hashrate and share statistics on 127.0.0.1:<port>
+    #[clap(long)]
+    telemetry_port: Option<u16>,
 
-    #[clap(long, default_value = "1")]
-    worker_id: usize,
+    /// Print the effective `[miner]` configuration and exit
+    #[clap(long)]
+    print_config: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Measure the RandomX hashrate of this machine without a node
+    Bench(BenchArgs),
+}
+
+/// `[miner]` section of the config file.
+///
+/// The miner is usually deployed with the config file of the node it mines
+/// for, which ignores this section.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MinerSection {
+    /// Ordered stratum endpoints, the first one being the primary. Defaults
+    /// to `stratum_listen_address:stratum_port` of the node.
+    pub stratum_endpoints: Vec<String>,
+    pub num_threads: Option<usize>,
+    pub threads_per_numa_node: Option<usize>,
+    pub core_affinity: Vec<usize>,
+    pub full_memory: Option<bool>,
+    pub worker_id: Option<usize>,
+    pub worker_name: Option<String>,
+    pub log_level: Option<String>,
+    pub telemetry_port: Option<u16>,
+}
+
+/// The parts of the config file read by the miner.
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    stratum_secret: Option<String>,
+    stratum_listen_address: Option<String>,
+    stratum_port: Option<u16>,
+    #[serde(default)]
+    miner: MinerSection,
 }
 
 #[derive(Debug)]
 pub struct MinerConfig {
-    pub stratum_address: String,
-    pub stratum_secret: String,
-    pub num_threads: usize,
+    /// Ordered stratum endpoints, the first one being the primary.
+    pub stratum_addresses: Vec<String>,
+    pub stratum_secret: Option<H256>,
+    pub threads: ThreadLayout,
+    pub full_memory: bool,
     pub worker_id: usize,
+    pub worker_name: String,
+    pub log_level: Option<LevelFilter>,
+    pub telemetry_port: Option<u16>,
+    pub print_config: bool,
 }
 
 impl MinerConfig {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        // Parse command line arguments
-        let cli_args = CliArgs::parse();
-
-        // Read config file
-        let config_content = fs::read_to_string(&cli_args.config)?;
-        let config_toml: toml::Value = toml::from_str(&config_content)?;
-
-        // Read stratum_secret from the config file
-        let stratum_secret = config_toml["stratum_secret"]
-            .as_str()
-            .ok_or("stratum_secret not found in config file")?
-            .to_string();
-
-        // Read stratum_address from CLI args or config file
-        let stratum_address = cli_args.stratum_address.unwrap_or_else(|| {
-            let listen_address = config_toml["stratum_listen_address"]
-                .as_str()
-                .expect("stratum_address not found in config file and not provided as CLI argument")
-                .to_string();
-            let port = config_toml["stratum_port"]
-                .as_integer()
-                .expect("stratum_port not found in config file and not provided as CLI argument");
-            format!("{}:{}", listen_address, port)
-        });
+    pub fn new(cli_args: CliArgs) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_path =
+            cli_args.config.clone().ok_or("--config is required")?;
+        let config_content = fs::read_to_string(&config_path).map_err(|e| {
+            format!("Failed to read {}: {}", config_path.display(), e)
+        })?;
+        let config = Self::from_toml(cli_args, &config_content)
+            .map_err(|e| format!("{}: {}", config_path.display(), e))?;
+        Ok(config)
+    }
+
+    fn from_toml(cli_args: CliArgs, content: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(content)
+            .map_err(|e| format!("invalid config: {}", e))?;
+        let section = file.miner;
+
+        // The node only requires authorization if stratum_secret is set
+        let stratum_secret = match &file.stratum_secret {
+            Some(hex_str) => Some(
+                H256::from_str(hex_str.trim_start_matches("0x"))
+                    .map_err(|e| format!("invalid stratum_secret: {}", e))?,
+            ),
+            None => None,
+        };
+
+        let stratum_addresses = if !cli_args.stratum_address.is_empty() {
+            cli_args.stratum_address
+        } else if !section.stratum_endpoints.is_empty() {
+            section.stratum_endpoints
+        } else {
+            match (file.stratum_listen_address, file.stratum_port) {
+                (Some(address), Some(port)) => {
+                    vec![format!("{}:{}", address, port)]
+                }
+                _ => {
+                    return Err("no stratum endpoint: set \
+                        `[miner] stratum_endpoints`, `stratum_listen_address` \
+                        and `stratum_port`, or pass --stratum-address"
+                        .into())
+                }
+            }
+        };
+        for address in &stratum_addresses {
+            validate_endpoint(address)?;
+        }
+
+        let threads = ThreadLayout {
+            num_threads: cli_args
+                .num_threads
+                .or(section.num_threads)
+                .unwrap_or(DEFAULT_NUM_THREADS),
+            threads_per_numa_node: cli_args
+                .threads_per_numa_node
+                .or(section.threads_per_numa_node),
+            core_affinity: if cli_args.core_affinity.is_empty() {
+                section.core_affinity
+            } else {
+                cli_args.core_affinity
+            },
+        };
+        if threads.num_threads == 0 {
+            return Err("num_threads must be positive".into());
+        }
+        if threads.threads_per_numa_node == Some(0) {
+            return Err("threads_per_numa_node must be positive".into());
+        }
+
+        let worker_id = cli_args
+            .worker_id
+            .or(section.worker_id)
+            .unwrap_or(DEFAULT_WORKER_ID);
+        let worker_name = cli_args
+            .worker_name
+            .or(section.worker_name)
+            .unwrap_or_else(|| format!("worker-{}", worker_id));
+        if worker_name.trim().is_empty() {
+            return Err("worker_name must not be empty".into());
+        }
+
+        let log_level = match cli_args.log_level.or(section.log_level) {
+            Some(level) => Some(
+                LevelFilter::from_str(&level)
+                    .map_err(|_| format!("invalid log_level `{}`", level))?,
+            ),
+            None => None,
+        };
 
         Ok(MinerConfig {
-            stratum_address,
+            stratum_addresses,
             stratum_secret,
-            num_threads: cli_args.num_threads,
-            worker_id: cli_args.worker_id,
+            threads,
+            full_memory: cli_args
+                .full_memory
+                .or(section.full_memory)
+                .unwrap_or_else(VMManager::randomx_use_full_memory),
+            worker_id,
+            worker_name,
+            log_level,
+            telemetry_port: cli_args.telemetry_port.or(section.telemetry_port),
+            print_config: cli_args.print_config,
         })
     }
+
+    /// The effective configuration as a `[miner]` section, in a form that
+    /// can be pasted into the config file.
+    pub fn to_toml(&self) -> String {
+        #[derive(Serialize)]
+        struct Output<'a> {
+            miner: &'a MinerSection,
+        }
+
+        let section = MinerSection {
+            stratum_endpoints: self.stratum_addresses.clone(),
+            num_threads: Some(self.threads.num_threads),
+            threads_per_numa_node: self.threads.threads_per_numa_node,
+            core_affinity: self.threads.core_affinity.clone(),
+            full_memory: Some(self.full_memory),
+            worker_id: Some(self.worker_id),
+            worker_name: Some(self.worker_name.clone()),
+            log_level: self
+                .log_level
+                .map(|level| level.to_string().to_lowercase()),
+            telemetry_port: self.telemetry_port,
+        };
+        toml::to_string(&Output { miner: &section })
+            .expect("miner section is serializable")
+    }
+}
+
+fn validate_endpoint(address: &str) -> Result<(), String> {
+    match address.rsplit_once(':') {
+        Some((host, port))
+            if !host.is_empty() && port.parse::<u16>().is_ok() =>
+        {
+            Ok(())
+        }
+        _ => Err(format!(
+            "invalid stratum endpoint `{}`, expected <host>:<port>",
+            address
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE_CONFIG: &str = r#"
+        mining_author = "0x1000000000000000000000000000000000000000"
+        stratum_listen_address = "127.0.0.1"
+        stratum_port = 32525
+        stratum_secret = "0x9c22ff5f21f0b81b113e63f7db6da94fedef11b2119b4088b89664fb9a3cb658"
+    "#;
+
+    fn cli(args: &[&str]) -> CliArgs {
+        CliArgs::try_parse_from(
+            ["mazze-miner", "--config", "hydra.toml"]
+                .iter()
+                .chain(args.iter()),
+        )
+        .expect("valid arguments")
+    }
+
+    #[test]
+    fn test_defaults_from_node_config() {
+        let config = MinerConfig::from_toml(cli(&[]), NODE_CONFIG).unwrap();
+        assert_eq!(config.stratum_addresses, vec!["127.0.0.1:32525"]);
+        assert!(config.stratum_secret.is_some());
+        assert_eq!(config.threads.num_threads, DEFAULT_NUM_THREADS);
+        assert_eq!(config.worker_id, DEFAULT_WORKER_ID);
+        assert_eq!(config.worker_name, "worker-1");
+        assert_eq!(config.log_level, None);
+    }
+
+    #[test]
+    fn test_miner_section_and_cli_overrides() {
+        let content = format!(
+            "{}\n{}",
+            NODE_CONFIG,
+            r#"
+            [miner]
+            stratum_endpoints = ["10.0.0.1:32525", "10.0.0.2:32525"]
+            threads_per_numa_node = 8
+            core_affinity = [0, 2, 4]
+            full_memory = false
+            worker_id = 7
+            log_level = "debug"
+            telemetry_port = 9100
+            "#
+        );
+        let config = MinerConfig::from_toml(cli(&[]), &content).unwrap();
+        assert_eq!(config.stratum_addresses.len(), 2);
+        assert_eq!(config.threads.threads_per_numa_node, Some(8));
+        assert_eq!(config.threads.core_affinity, vec![0, 2, 4]);
+        assert!(!config.full_memory);
+        assert_eq!(config.worker_name, "worker-7");
+        assert_eq!(config.log_level, Some(LevelFilter::Debug));
+        assert_eq!(config.telemetry_port, Some(9100));
+
+        let config = MinerConfig::from_toml(
+            cli(&[
+                "--stratum-address",
+                "10.0.0.3:1",
+                "--core-affinity",
+                "1,3",
+                "--worker-name",
+                "rig",
+                "--log-level",
+                "warn",
+            ]),
+            &content,
+        )
+        .unwrap();
+        assert_eq!(config.stratum_addresses, vec!["10.0.0.3:1"]);
+        assert_eq!(config.threads.core_affinity, vec![1, 3]);
+        assert_eq!(config.worker_id, 7);
+        assert_eq!(config.worker_name, "rig");
+        assert_eq!(config.log_level, Some(LevelFilter::Warn));
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        let invalid = [
+            "[miner]\nnum_thread = 4",
+            "[miner]\nnum_threads = \"four\"",
+            "[miner]\nstratum_endpoints = [\"localhost\"]",
+            "[miner]\nnum_threads = 0",
+            "[miner]\nlog_level = \"loud\"",
+        ];
+        for content in &invalid {
+            let content = format!("{}\n{}", NODE_CONFIG, content);
+            assert!(
+                MinerConfig::from_toml(cli(&[]), &content).is_err(),
+                "{} should be rejected",
+                content
+            );
+        }
+
+        let err = MinerConfig::from_toml(cli(&[]), "stratum_port = 32525")
+            .unwrap_err();
+        assert!(err.contains("no stratum endpoint"), "{}", err);
+    }
+
+    #[test]
+    fn test_print_config_round_trip() {
+        let config = MinerConfig::from_toml(
+            cli(&["--threads-per-numa-node", "2", "--full-memory", "true"]),
+            NODE_CONFIG,
+        )
+        .unwrap();
+        let printed = config.to_toml();
+        assert!(printed.starts_with("[miner]"), "{}", printed);
+
+        let reparsed = MinerConfig::from_toml(
+            cli(&[]),
+            &format!("{}\n{}", NODE_CONFIG, printed),
+        )
+        .unwrap();
+        assert_eq!(reparsed.to_toml(), printed);
+    }
 }
@@ -1,26 +1,151 @@
 use futures::{SinkExt, StreamExt};
 use log::{debug, error, info, trace, warn};
-use mazze_types::U256;
+use mazze_stratum::authorization_response;
+use mazze_types::{H256, U256};
 use mazzecore::pow::{ProofOfWorkProblem, ProofOfWorkSolution};
 use serde_json::{json, Value};
-use tokio::net::TcpStream;
-use tokio::time::{timeout, Duration};
+use std::str::FromStr;
+use tokio::{
+    net::TcpStream,
+    time::{interval_at, timeout, timeout_at, Duration, Instant},
+};
 use tokio_util::codec::{Framed, LinesCodec};
 
 use crate::miner::Miner;
 
+const SUBSCRIBE_REQUEST_ID: u64 = 1;
+const AUTHORIZE_REQUEST_ID: u64 = 2;
+const HASHRATE_REQUEST_ID: u64 = 3;
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the local hashrate is reported with `mining.hashrate`.
+const HASHRATE_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Subscribes `worker_name` and, if the node requires it, answers its
+/// authentication challenge with the shared `stratum_secret`.
+///
+/// Returns the notifications received while waiting for the responses.
+pub async fn authenticate(
+    framed: &mut Framed<TcpStream, LinesCodec>, worker_name: &str,
+    stratum_secret: Option<&H256>,
+) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+    let mut notifications = Vec::new();
+
+    info!("Sending subscription request for {}", worker_name);
+    let result = request(
+        framed,
+        SUBSCRIBE_REQUEST_ID,
+        "mining.subscribe",
+        json!([worker_name]),
+        &mut notifications,
+    )
+    .await?;
+
+    match result {
+        Value::Bool(true) => info!("Subscribed successfully"),
+        Value::String(challenge) => {
+            let secret = stratum_secret.ok_or(
+                "Node requires authorization but no stratum_secret is configured",
+            )?;
+            let challenge = H256::from_str(challenge.trim_start_matches("0x"))
+                .map_err(|e| format!("Invalid challenge: {}", e))?;
+            let response =
+                authorization_response(secret, &challenge, worker_name);
+
+            debug!("Sending authorization request for {}", worker_name);
+            let result = request(
+                framed,
+                AUTHORIZE_REQUEST_ID,
+                "mining.authorize",
+                json!([worker_name, format!("0x{:x}", response)]),
+                &mut notifications,
+            )
+            .await?;
+            if result.as_bool() != Some(true) {
+                warn!("Authorization failed");
+                return Err("Authorization failed".into());
+            }
+            info!("Authorized successfully as {}", worker_name);
+        }
+        _ => {
+            warn!("Subscription failed");
+            return Err(format!("Subscription failed: {}", result).into());
+        }
+    }
+
+    Ok(notifications)
+}
+
+/// Sends a request and waits for the response with the same id.
+/// Notifications received in the meantime are collected in `notifications`.
+async fn request(
+    framed: &mut Framed<TcpStream, LinesCodec>, id: u64, method: &str,
+    params: Value, notifications: &mut Vec<Value>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let request = json!({
+        "id": id,
+        "method": method,
+        "params": params,
+    });
+    let request_json = serde_json::to_string(&request)?;
+    trace!("{} request JSON: {}", method, request_json);
+    framed.send(request_json).await?;
+
+    loop {
+        let message = match timeout(RESPONSE_TIMEOUT, framed.next()).await {
+            Ok(Some(line_result)) => line_result?,
+            Ok(None) => {
+                error!("No response received for {}", method);
+                return Err(
+                    format!("No response received for {}", method).into()
+                );
+            }
+            Err(_) => return Err("Timeout waiting for message".into()),
+        };
+        let value: Value = serde_json::from_str(&message)?;
+
+        if value.get("method").is_some() {
+            notifications.push(value);
+            continue;
+        }
+        if value.get("id").and_then(Value::as_u64) != Some(id) {
+            debug!("Ignoring unexpected message: {}", message);
+            continue;
+        }
+        if let Some(error) = value.get("error").filter(|e| !e.is_null()) {
+            let code = error.get("code").and_then(Value::as_i64).unwrap_or(0);
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            warn!("{} rejected with code {}: {}", method, code, message);
+            return Err(format!(
+                "{} rejected with code {}: {}",
+                method, code, message
+            )
+            .into());
+        }
+        return match value.get("result") {
+            Some(result) => Ok(result.clone()),
+            None => {
+                error!("Invalid {} response", method);
+                Err(format!("Invalid {} response", method).into())
+            }
+        };
+    }
+}
+
 pub struct StratumClient {
     framed: Framed<TcpStream, LinesCodec>,
     miner: Miner,
     current_job: Option<ProofOfWorkProblem>,
-    stratum_secret: String,
+    stratum_secret: Option<H256>,
     solution_receiver:
         tokio::sync::broadcast::Receiver<(ProofOfWorkSolution, u64)>,
 }
 
 impl StratumClient {
     pub async fn connect(
-        addr: &str, stratum_secret: &str, miner: Miner,
+        addr: &str, stratum_secret: Option<H256>, miner: Miner,
         solution_receiver: tokio::sync::broadcast::Receiver<(
             ProofOfWorkSolution,
             u64,
@@ -34,52 +159,28 @@ impl StratumClient {
             framed,
             miner,
             current_job: None,
-            stratum_secret: stratum_secret.to_string(),
+            stratum_secret,
             solution_receiver,
         })
     }
 
     async fn subscribe(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        info!("Sending subscription request");
-        let request = json!({
-            "id": self.miner.worker_id,
-            "method": "mining.subscribe",
-            // TODO: investigate why self.stratum_secret is not working
-            // (same config file used in miner and node too)
-            // "params": ["999", self.stratum_secret]
-            "params": ["999", "test"]
-        });
-        let request_json = serde_json::to_string(&request)?;
-        trace!("Subscription request JSON: {}", request_json);
-        self.framed.send(request_json).await?;
-        info!("Subscription request sent");
-
-        match self.receive_message().await? {
-            Some(message) => {
-                let value: Value = serde_json::from_str(&message)?;
-                if let Some(result) = value.get("result") {
-                    if result.as_bool() == Some(true) {
-                        info!("Subscribed successfully");
-                        Ok(())
-                    } else {
-                        warn!("Subscription failed");
-                        Err("Subscription failed: {}".into())
-                    }
-                } else {
-                    error!("Invalid subscription response");
-                    Err("Invalid subscription response".into())
-                }
-            }
-            None => {
-                error!("No response received for subscription");
-                Err("No response received for subscription".into())
-            }
+        let notifications = authenticate(
+            &mut self.framed,
+            &self.miner.worker_name,
+            self.stratum_secret.as_ref(),
+        )
+        .await?;
+        for notification in notifications {
+            self.handle_message(notification).await?;
         }
+        Ok(())
     }
 
     async fn handle_job_notification(
         &mut self, params: &[Value],
     ) -> Result<(), Box<dyn std::error::Error>> {
+        self.miner.telemetry.on_job();
         match self.miner.parse_job(params) {
             Ok(problem) => {
                 self.current_job = Some(problem.clone());
@@ -96,29 +197,23 @@ impl StratumClient {
     pub async fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         self.subscribe().await?;
         let mut solution_receiver = self.solution_receiver.resubscribe();
+        // Reset by every message from the node only, so that sending
+        // shares or hashrate reports does not hide a silent node.
+        let mut deadline = Instant::now() + RESPONSE_TIMEOUT;
+        let mut hashrate_report = interval_at(
+            Instant::now() + HASHRATE_REPORT_INTERVAL,
+            HASHRATE_REPORT_INTERVAL,
+        );
 
         loop {
             tokio::select! {
-                message_result = self.receive_message() => {
+                message_result = self.receive_message(deadline) => {
                     match message_result? {
                         Some(message) => {
+                            deadline = Instant::now() + RESPONSE_TIMEOUT;
                             debug!("Received message: {}", message);
                             let value: Value = serde_json::from_str(&message)?;
-
-                            if let Some(method) = value.get("method").and_then(Value::as_str) {
-                                match method {
-                                    "mining.notify" => {
-                                        if let Some(params) = value.get("params").and_then(Value::as_array) {
-                                            self.handle_job_notification(params).await?;
-                                        }
-                                    }
-                                    _ => debug!("Received unknown method: {}", method),
-                                }
-                            } else if let Some(result) = value.get("result") {
-                                debug!("Received result: {:?}", result);
-                            } else {
-                                debug!("Received unknown message: {}", message);
-                            }
+                            self.handle_message(value).await?;
                         }
                         None => {
                             info!("Server closed the connection");
@@ -134,22 +229,76 @@ impl StratumClient {
                         }
                     }
                 }
+                _ = hashrate_report.tick() => {
+                    self.report_hashrate().await?;
+                }
             }
         }
 
         Ok(())
     }
 
+    async fn handle_message(
+        &mut self, value: Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(method) = value.get("method").and_then(Value::as_str) {
+            match method {
+                "mining.notify" => {
+                    if let Some(params) =
+                        value.get("params").and_then(Value::as_array)
+                    {
+                        self.handle_job_notification(params).await?;
+                    }
+                }
+                _ => debug!("Received unknown method: {}", method),
+            }
+        } else if let Some(result) = value.get("result") {
+            debug!("Received result: {:?}", result);
+            // Only share submissions use string ids.
+            if value.get("id").map_or(false, Value::is_string) {
+                let accepted = value.get("error").map_or(true, Value::is_null)
+                    && result
+                        .as_array()
+                        .and_then(|result| result.first())
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+                if !accepted {
+                    warn!("Share rejected: {}", value);
+                }
+                self.miner.telemetry.on_share_result(accepted);
+            }
+        } else {
+            debug!("Received unknown message: {}", value);
+        }
+        Ok(())
+    }
+
     async fn receive_message(
-        &mut self,
+        &mut self, deadline: Instant,
     ) -> Result<Option<String>, Box<dyn std::error::Error>> {
-        match timeout(Duration::from_secs(30), self.framed.next()).await {
+        match timeout_at(deadline, self.framed.next()).await {
             Ok(Some(line_result)) => Ok(Some(line_result?)),
             Ok(None) => Ok(None),
             Err(_) => Err("Timeout waiting for message".into()),
         }
     }
 
+    /// Reports the local hashrate to the node, which aggregates the reports
+    /// of all workers for `mazze_hashrate` and `eth_hashrate`.
+    async fn report_hashrate(
+        &mut self,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let hashrate = U256::from(self.miner.telemetry.hashrate() as u64);
+        let request = json!({
+            "id": HASHRATE_REQUEST_ID,
+            "method": "mining.hashrate",
+            "params": [self.miner.worker_name, format!("0x{:x}", hashrate)],
+        });
+        trace!("Hashrate report: {}", request);
+        self.framed.send(serde_json::to_string(&request)?).await?;
+        Ok(())
+    }
+
     async fn submit_share(
         &mut self, solution: &ProofOfWorkSolution,
     ) -> Result<(), Box<dyn std::error::Error>> {
@@ -176,3 +325,72 @@ impl StratumClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazze_stratum::{Error as StratumError, JobDispatcher, Stratum};
+    use std::sync::Arc;
+
+    struct VoidDispatcher;
+
+    impl JobDispatcher for VoidDispatcher {
+        fn submit(&self, _payload: Vec<String>) -> Result<(), StratumError> {
+            Ok(())
+        }
+    }
+
+    async fn connect(addr: &str) -> Framed<TcpStream, LinesCodec> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .expect("stratum server should accept connections");
+        Framed::new(stream, LinesCodec::new())
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_with_secret() {
+        let addr = "127.0.0.1:19990";
+        let secret = H256::from_low_u64_be(0x5ec12e7);
+        let _server = Stratum::start(
+            &addr.parse().unwrap(),
+            Arc::new(VoidDispatcher),
+            Some(secret),
+        )
+        .expect("stratum server should start");
+
+        let mut framed = connect(addr).await;
+        assert!(authenticate(&mut framed, "worker-1", Some(&secret))
+            .await
+            .is_ok());
+
+        let mut framed = connect(addr).await;
+        let wrong_secret = H256::from_low_u64_be(1);
+        let err = authenticate(&mut framed, "worker-2", Some(&wrong_secret))
+            .await
+            .expect_err("wrong secret should be rejected");
+        assert!(err.to_string().contains(&format!(
+            "code {}",
+            mazze_stratum::error_codes::UNAUTHORIZED_WORKER
+        )));
+
+        let mut framed = connect(addr).await;
+        assert!(authenticate(&mut framed, "worker-3", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_without_secret() {
+        let addr = "127.0.0.1:19991";
+        let _server = Stratum::start(
+            &addr.parse().unwrap(),
+            Arc::new(VoidDispatcher),
+            None,
+        )
+        .expect("stratum server should start");
+
+        let mut framed = connect(addr).await;
+        let secret = H256::from_low_u64_be(0x5ec12e7);
+        assert!(authenticate(&mut framed, "worker-1", Some(&secret))
+            .await
+            .is_ok());
+    }
+}
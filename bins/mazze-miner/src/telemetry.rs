@@ -0,0 +1,572 @@
+use log::{debug, info, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Hashrates are computed over this interval.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// Requests with more header lines than this are not answered.
+const MAX_HEADER_LINES: usize = 64;
+
+/// Aligned so that the counters of different mining threads never share a
+/// cache line (128 bytes, as adjacent lines are prefetched in pairs).
+#[repr(align(128))]
+struct ThreadCounter {
+    thread_id: usize,
+    node_id: usize,
+    hashes: AtomicU64,
+}
+
+struct Sample {
+    at: Instant,
+    hashes: Vec<u64>,
+    /// Per-thread hashrate over the last sample interval, in H/s
+    hashrates: Vec<f64>,
+}
+
+/// Counters updated by the mining threads and the stratum client.
+///
+/// Everything is lock free on the hot path, hashrates are derived by
+/// `sample` which runs every `SAMPLE_INTERVAL` on a background thread.
+pub struct Telemetry {
+    started: Instant,
+    threads: Vec<ThreadCounter>,
+    found_shares: AtomicU64,
+    accepted_shares: AtomicU64,
+    rejected_shares: AtomicU64,
+    jobs: AtomicU64,
+    /// When the last job was received, until a mining thread starts hashing
+    /// it
+    job_received_at: Mutex<Option<Instant>>,
+    started_jobs: AtomicU64,
+    last_job_latency_us: AtomicU64,
+    total_job_latency_us: AtomicU64,
+    sample: Mutex<Sample>,
+    sampler: Mutex<Option<thread::JoinHandle<()>>>,
+    stopped: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThreadSnapshot {
+    pub thread_id: usize,
+    pub node_id: usize,
+    pub hashes: u64,
+    pub hashrate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeSnapshot {
+    pub node_id: usize,
+    pub threads: usize,
+    pub hashes: u64,
+    pub hashrate: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShareSnapshot {
+    /// Solutions found by the mining threads
+    pub found: u64,
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobSnapshot {
+    pub received: u64,
+    /// Time from receiving the last job until a mining thread started
+    /// hashing it
+    pub last_latency_ms: f64,
+    pub average_latency_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetrySnapshot {
+    pub uptime_secs: u64,
+    /// Total hashrate in H/s
+    pub hashrate: f64,
+    pub hashes: u64,
+    pub threads: Vec<ThreadSnapshot>,
+    pub numa_nodes: Vec<NodeSnapshot>,
+    pub shares: ShareSnapshot,
+    pub jobs: JobSnapshot,
+}
+
+impl Telemetry {
+    /// `threads` are the `(thread_id, node_id)` of the mining threads.
+    pub fn new(threads: &[(usize, usize)]) -> Self {
+        let now = Instant::now();
+        Telemetry {
+            started: now,
+            threads: threads
+                .iter()
+                .map(|&(thread_id, node_id)| ThreadCounter {
+                    thread_id,
+                    node_id,
+                    hashes: AtomicU64::new(0),
+                })
+                .collect(),
+            found_shares: AtomicU64::new(0),
+            accepted_shares: AtomicU64::new(0),
+            rejected_shares: AtomicU64::new(0),
+            jobs: AtomicU64::new(0),
+            job_received_at: Mutex::new(None),
+            started_jobs: AtomicU64::new(0),
+            last_job_latency_us: AtomicU64::new(0),
+            total_job_latency_us: AtomicU64::new(0),
+            sample: Mutex::new(Sample {
+                at: now,
+                hashes: vec![0; threads.len()],
+                hashrates: vec![0.0; threads.len()],
+            }),
+            sampler: Mutex::new(None),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Spawns the thread computing the hashrates, which exits once
+    /// `stop_sampler` is called or the telemetry is dropped.
+    pub fn spawn_sampler(self: &Arc<Self>) {
+        let telemetry = Arc::downgrade(self);
+        let handle = thread::Builder::new()
+            .name("telemetry".into())
+            .spawn(move || {
+                let mut next_sample = Instant::now() + SAMPLE_INTERVAL;
+                loop {
+                    // Woken up early by `stop_sampler`
+                    thread::park_timeout(
+                        next_sample.saturating_duration_since(Instant::now()),
+                    );
+                    let telemetry = match telemetry.upgrade() {
+                        Some(telemetry) => telemetry,
+                        None => break,
+                    };
+                    if telemetry.stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let now = Instant::now();
+                    if now >= next_sample {
+                        telemetry.sample(now);
+                        next_sample += SAMPLE_INTERVAL;
+                    }
+                }
+            })
+            .expect("failed to spawn telemetry thread");
+        *self.sampler.lock() = Some(handle);
+    }
+
+    /// Stops the thread computing the hashrates and waits for it to exit.
+    pub fn stop_sampler(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.sampler.lock().take() {
+            handle.thread().unpark();
+            if handle.join().is_err() {
+                warn!("Telemetry thread panicked");
+            }
+        }
+    }
+
+    /// Records hashes computed by the thread at index `thread_index`.
+    pub fn add_hashes(&self, thread_index: usize, hashes: u64) {
+        if let Some(counter) = self.threads.get(thread_index) {
+            counter.hashes.fetch_add(hashes, Ordering::Relaxed);
+        }
+    }
+
+    pub fn on_solution_found(&self) {
+        self.found_shares.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn on_share_result(&self, accepted: bool) {
+        if accepted {
+            self.accepted_shares.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.rejected_shares.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a job received from the pool. Its latency is measured until
+    /// a mining thread starts hashing it.
+    pub fn on_job(&self) {
+        self.on_job_at(Instant::now());
+    }
+
+    fn on_job_at(&self, now: Instant) {
+        self.jobs.fetch_add(1, Ordering::Relaxed);
+        *self.job_received_at.lock() = Some(now);
+    }
+
+    /// Called by a mining thread when it starts hashing the current job.
+    pub fn on_hashing_started(&self) {
+        self.on_hashing_started_at(Instant::now());
+    }
+
+    fn on_hashing_started_at(&self, now: Instant) {
+        // Only the first thread to pick the job up measures its latency.
+        let received_at = match self.job_received_at.lock().take() {
+            Some(received_at) => received_at,
+            None => return,
+        };
+        let latency_us =
+            now.saturating_duration_since(received_at).as_micros() as u64;
+        self.started_jobs.fetch_add(1, Ordering::Relaxed);
+        self.last_job_latency_us
+            .store(latency_us, Ordering::Relaxed);
+        self.total_job_latency_us
+            .fetch_add(latency_us, Ordering::Relaxed);
+    }
+
+    /// Updates the hashrates with the hashes computed since the last sample.
+    pub fn sample(&self, now: Instant) {
+        let mut sample = self.sample.lock();
+        let elapsed = now.saturating_duration_since(sample.at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
+        }
+        for (index, counter) in self.threads.iter().enumerate() {
+            let hashes = counter.hashes.load(Ordering::Relaxed);
+            sample.hashrates[index] =
+                hashes.saturating_sub(sample.hashes[index]) as f64 / elapsed;
+            sample.hashes[index] = hashes;
+        }
+        sample.at = now;
+    }
+
+    /// Total hashrate over the last sample interval, in H/s.
+    pub fn hashrate(&self) -> f64 {
+        self.sample.lock().hashrates.iter().sum()
+    }
+
+    pub fn snapshot(&self) -> TelemetrySnapshot {
+        let hashrates = self.sample.lock().hashrates.clone();
+        let threads: Vec<_> = self
+            .threads
+            .iter()
+            .zip(hashrates)
+            .map(|(counter, hashrate)| ThreadSnapshot {
+                thread_id: counter.thread_id,
+                node_id: counter.node_id,
+                hashes: counter.hashes.load(Ordering::Relaxed),
+                hashrate,
+            })
+            .collect();
+
+        let mut nodes = BTreeMap::new();
+        for thread in &threads {
+            let node = nodes.entry(thread.node_id).or_insert(NodeSnapshot {
+                node_id: thread.node_id,
+                threads: 0,
+                hashes: 0,
+                hashrate: 0.0,
+            });
+            node.threads += 1;
+            node.hashes += thread.hashes;
+            node.hashrate += thread.hashrate;
+        }
+
+        let started_jobs = self.started_jobs.load(Ordering::Relaxed);
+        let last_latency_us = self.last_job_latency_us.load(Ordering::Relaxed);
+        let total_latency_us =
+            self.total_job_latency_us.load(Ordering::Relaxed);
+        TelemetrySnapshot {
+            uptime_secs: self.started.elapsed().as_secs(),
+            hashrate: threads.iter().map(|t| t.hashrate).sum(),
+            hashes: threads.iter().map(|t| t.hashes).sum(),
+            threads,
+            numa_nodes: nodes.into_values().collect(),
+            shares: ShareSnapshot {
+                found: self.found_shares.load(Ordering::Relaxed),
+                accepted: self.accepted_shares.load(Ordering::Relaxed),
+                rejected: self.rejected_shares.load(Ordering::Relaxed),
+            },
+            jobs: JobSnapshot {
+                received: self.jobs.load(Ordering::Relaxed),
+                last_latency_ms: last_latency_us as f64 / 1000.0,
+                average_latency_ms: if started_jobs == 0 {
+                    0.0
+                } else {
+                    total_latency_us as f64 / started_jobs as f64 / 1000.0
+                },
+            },
+        }
+    }
+}
+
+impl TelemetrySnapshot {
+    /// Renders the snapshot in the Prometheus text exposition format.
+    pub fn to_prometheus(&self, worker_name: &str) -> String {
+        let mut out = String::new();
+        let worker = format!("worker=\"{}\"", worker_name.replace('"', "'"));
+
+        let mut metric = |name: &str, kind: &str, help: &str| {
+            let _ = writeln!(out, "# HELP mazze_miner_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE mazze_miner_{} {}", name, kind);
+        };
+        metric("hashrate", "gauge", "Hashrate in H/s.");
+        metric(
+            "thread_hashrate",
+            "gauge",
+            "Hashrate of a mining thread in H/s.",
+        );
+        metric(
+            "thread_hashes_total",
+            "counter",
+            "Hashes computed by a thread.",
+        );
+        metric(
+            "numa_node_hashrate",
+            "gauge",
+            "Hashrate of a NUMA node in H/s.",
+        );
+        metric(
+            "numa_node_hashes_total",
+            "counter",
+            "Hashes computed on a NUMA node.",
+        );
+        metric("shares_found_total", "counter", "Solutions found.");
+        metric("shares_accepted_total", "counter", "Shares accepted.");
+        metric("shares_rejected_total", "counter", "Shares rejected.");
+        metric("jobs_total", "counter", "Jobs received.");
+        metric(
+            "job_latency_seconds",
+            "gauge",
+            "Time from receiving the last job to hashing it.",
+        );
+        metric("uptime_seconds", "counter", "Time since the miner started.");
+
+        let _ = writeln!(
+            out,
+            "mazze_miner_hashrate{{{}}} {}",
+            worker, self.hashrate
+        );
+        for thread in &self.threads {
+            let labels = format!(
+                "{},thread=\"{}\",numa_node=\"{}\"",
+                worker, thread.thread_id, thread.node_id
+            );
+            let _ = writeln!(
+                out,
+                "mazze_miner_thread_hashrate{{{}}} {}",
+                labels, thread.hashrate
+            );
+            let _ = writeln!(
+                out,
+                "mazze_miner_thread_hashes_total{{{}}} {}",
+                labels, thread.hashes
+            );
+        }
+        for node in &self.numa_nodes {
+            let labels = format!("{},numa_node=\"{}\"", worker, node.node_id);
+            let _ = writeln!(
+                out,
+                "mazze_miner_numa_node_hashrate{{{}}} {}",
+                labels, node.hashrate
+            );
+            let _ = writeln!(
+                out,
+                "mazze_miner_numa_node_hashes_total{{{}}} {}",
+                labels, node.hashes
+            );
+        }
+        let _ = writeln!(
+            out,
+            "mazze_miner_shares_found_total{{{}}} {}",
+            worker, self.shares.found
+        );
+        let _ = writeln!(
+            out,
+            "mazze_miner_shares_accepted_total{{{}}} {}",
+            worker, self.shares.accepted
+        );
+        let _ = writeln!(
+            out,
+            "mazze_miner_shares_rejected_total{{{}}} {}",
+            worker, self.shares.rejected
+        );
+        let _ = writeln!(
+            out,
+            "mazze_miner_jobs_total{{{}}} {}",
+            worker, self.jobs.received
+        );
+        let _ = writeln!(
+            out,
+            "mazze_miner_job_latency_seconds{{{}}} {}",
+            worker,
+            self.jobs.last_latency_ms / 1000.0
+        );
+        let _ = writeln!(
+            out,
+            "mazze_miner_uptime_seconds{{{}}} {}",
+            worker, self.uptime_secs
+        );
+        out
+    }
+}
+
+/// Serves the telemetry over HTTP: `/` and `/stats` return JSON, `/metrics`
+/// returns the Prometheus text format.
+pub async fn serve(
+    addr: SocketAddr, telemetry: Arc<Telemetry>, worker_name: String,
+) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Telemetry endpoint listening on http://{}", addr);
+    let worker_name = Arc::new(worker_name);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let telemetry = Arc::clone(&telemetry);
+        let worker_name = Arc::clone(&worker_name);
+        tokio::spawn(async move {
+            if let Err(e) =
+                handle_connection(stream, &telemetry, &worker_name).await
+            {
+                debug!("Telemetry request from {} failed: {:?}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream, telemetry: &Telemetry, worker_name: &str,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&mut stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    // Headers are not used, but have to be read before answering.
+    for _ in 0..MAX_HEADER_LINES {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (status, content_type, body) = response(path, telemetry, worker_name);
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn response(
+    path: &str, telemetry: &Telemetry, worker_name: &str,
+) -> (&'static str, &'static str, String) {
+    match path {
+        "/" | "/stats" => (
+            "200 OK",
+            "application/json",
+            serde_json::to_string(&telemetry.snapshot())
+                .expect("snapshot is serializable"),
+        ),
+        "/metrics" => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            telemetry.snapshot().to_prometheus(worker_name),
+        ),
+        _ => ("404 Not Found", "text/plain", "Not Found\n".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telemetry() -> Telemetry {
+        // Two threads on node 0, one on node 1.
+        Telemetry::new(&[(0, 0), (1, 1), (2, 0)])
+    }
+
+    #[test]
+    fn test_hashrate_per_thread_and_node() {
+        let telemetry = telemetry();
+        let start = telemetry.sample.lock().at;
+        telemetry.add_hashes(0, 100);
+        telemetry.add_hashes(1, 200);
+        telemetry.add_hashes(2, 300);
+        telemetry.sample(start + Duration::from_secs(10));
+
+        let snapshot = telemetry.snapshot();
+        assert_eq!(snapshot.hashes, 600);
+        assert_eq!(snapshot.hashrate, 60.0);
+        assert_eq!(snapshot.threads[1].hashrate, 20.0);
+        assert_eq!(snapshot.numa_nodes.len(), 2);
+        assert_eq!(snapshot.numa_nodes[0].threads, 2);
+        assert_eq!(snapshot.numa_nodes[0].hashes, 400);
+        assert_eq!(snapshot.numa_nodes[0].hashrate, 40.0);
+        assert_eq!(snapshot.numa_nodes[1].hashrate, 20.0);
+
+        // Only the hashes of the last interval count.
+        telemetry.add_hashes(0, 50);
+        telemetry.sample(start + Duration::from_secs(20));
+        assert_eq!(telemetry.hashrate(), 5.0);
+    }
+
+    #[test]
+    fn test_stop_sampler() {
+        let telemetry = Arc::new(telemetry());
+        telemetry.spawn_sampler();
+        let start = Instant::now();
+        telemetry.stop_sampler();
+        assert!(start.elapsed() < SAMPLE_INTERVAL);
+        assert!(telemetry.sampler.lock().is_none());
+    }
+
+    #[test]
+    fn test_shares_and_jobs() {
+        let telemetry = telemetry();
+        telemetry.on_solution_found();
+        telemetry.on_solution_found();
+        telemetry.on_share_result(true);
+        telemetry.on_share_result(false);
+        let start = Instant::now();
+        telemetry.on_job_at(start);
+        telemetry.on_hashing_started_at(start + Duration::from_millis(10));
+        // Only the first thread to start hashing the job counts.
+        telemetry.on_hashing_started_at(start + Duration::from_millis(20));
+        telemetry.on_job_at(start + Duration::from_millis(100));
+        telemetry.on_hashing_started_at(start + Duration::from_millis(130));
+        // A job no thread has started hashing yet.
+        telemetry.on_job_at(start + Duration::from_millis(200));
+
+        let snapshot = telemetry.snapshot();
+        assert_eq!(snapshot.shares.found, 2);
+        assert_eq!(snapshot.shares.accepted, 1);
+        assert_eq!(snapshot.shares.rejected, 1);
+        assert_eq!(snapshot.jobs.received, 3);
+        assert_eq!(snapshot.jobs.last_latency_ms, 30.0);
+        assert_eq!(snapshot.jobs.average_latency_ms, 20.0);
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let telemetry = telemetry();
+        telemetry.add_hashes(2, 7);
+
+        let (status, _, body) = response("/stats", &telemetry, "worker-1");
+        assert_eq!(status, "200 OK");
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["hashes"], 7);
+        assert_eq!(json["threads"][2]["node_id"], 0);
+
+        let (status, _, body) = response("/metrics", &telemetry, "worker-1");
+        assert_eq!(status, "200 OK");
+        assert!(body.contains("# TYPE mazze_miner_hashrate gauge"));
+        assert!(body.contains(
+            "mazze_miner_thread_hashes_total{worker=\"worker-1\",thread=\"2\",numa_node=\"0\"} 7"
+        ));
+
+        let (status, _, _) = response("/other", &telemetry, "worker-1");
+        assert_eq!(status, "404 Not Found");
+    }
+}
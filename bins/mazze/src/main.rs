@@ -11,7 +11,7 @@ use crate::command::rpc::RpcCommand;
 use clap::{crate_version, load_yaml, App, ArgMatches};
 use client::{
     archive::ArchiveClient,
-    common::{client_methods, ClientTrait},
+    common::{check_database, client_methods, ClientTrait},
     configuration::Configuration,
     full::FullClient,
     light::LightClient,
@@ -131,6 +131,21 @@ fn main() -> Result<(), String> {
         }
     };
 
+    if let ("db", Some(db_matches)) = matches.subcommand() {
+        if let ("check", Some(check_matches)) = db_matches.subcommand() {
+            let report =
+                check_database(&conf, check_matches.is_present("repair"))?;
+            println!("{}", report);
+            if !report.is_consistent() {
+                return Err(format!(
+                    "found {} inconsistencies in the database",
+                    report.inconsistencies.len()
+                ));
+            }
+        }
+        return Ok(());
+    }
+
     THROTTLING_SERVICE.write().initialize(
         conf.raw_conf.egress_queue_capacity,
         conf.raw_conf.egress_min_throttle,
@@ -200,6 +215,27 @@ fn handle_sub_command(matches: &ArgMatches) -> Result<Option<String>, String> {
         return Ok(Some(execute_output));
     }
 
+    // `snapshot import` starts the node with the bundle to import, which is
+    // set in `Configuration::parse`.
+    if let ("snapshot", Some(snapshot_matches)) = matches.subcommand() {
+        if snapshot_matches.subcommand_name() == Some("import") {
+            return Ok(None);
+        }
+    }
+
+    // `blocks import` starts the node with the block stream to import, which
+    // is set in `Configuration::parse`.
+    if let ("blocks", Some(blocks_matches)) = matches.subcommand() {
+        if blocks_matches.subcommand_name() == Some("import") {
+            return Ok(None);
+        }
+    }
+
+    // `db` sub-commands open the database configured in `Configuration::parse`.
+    if matches.subcommand_name() == Some("db") {
+        return Ok(None);
+    }
+
     // general RPC commands
     let mut subcmd_matches = matches;
     while let Some(m) = subcmd_matches.subcommand().1 {
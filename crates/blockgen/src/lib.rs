@@ -12,6 +12,7 @@ use log::{debug, info, trace, warn};
 use mazze_parameters::{
     consensus::GENESIS_GAS_LIMIT, consensus_internal::ELASTICITY_MULTIPLIER,
 };
+use mazze_stratum::PoolConfig;
 use mazze_types::{Address, SpaceMap, H256, U256};
 use mazzecore::{
     block_parameters::*, consensus::consensus_inner::StateBlameInfo, pow::*,
@@ -109,11 +110,12 @@ impl Worker {
                         let boundary = problem.as_ref().unwrap().boundary;
                         let block_hash = problem.as_ref().unwrap().block_hash;
                         let seed_hash = problem.as_ref().unwrap().seed_hash;
+                        let pow_lower_bound = problem.as_ref().unwrap().pow_lower_bound;
                         let mut nonce: u64 = rand::random();
                         for _i in 0..MINING_ITERATION {
                             let nonce_u256 = U256::from(nonce);
                             let hash = bg_pow.compute(&nonce_u256, &block_hash, &seed_hash);
-                            if ProofOfWorkProblem::validate_hash_against_boundary(&hash, &nonce_u256, &boundary) {
+                            if ProofOfWorkProblem::validate_hash_against_boundary(&hash, &nonce_u256, &boundary, pow_lower_bound) {
                                 // problem solved
                                 match solution_sender
                                     .send(ProofOfWorkSolution { nonce: nonce_u256 })
@@ -691,6 +693,9 @@ impl BlockGenerator {
                 .get_data_manager()
                 .db_manager
                 .get_current_seed_hash(block.block_header.height()),
+        )
+        .with_pow_lower_bound(
+            self.pow_lower_bound_enabled(block.block_header.height()),
         );
         let mut nonce: u64 = rand::random();
         loop {
@@ -726,10 +731,25 @@ impl BlockGenerator {
         hash
     }
 
+    /// Whether the two-sided PoW target applies to a block at `height`.
+    fn pow_lower_bound_enabled(&self, height: u64) -> bool {
+        self.graph
+            .verification_config
+            .pow_lower_bound_enabled(height)
+    }
+
     pub fn pow_config(&self) -> ProofOfWorkConfig {
         self.pow_config.clone()
     }
 
+    /// Hashrate mining for this node, as reported by its stratum workers.
+    pub fn hashrate(&self) -> U256 {
+        self.stratum
+            .read()
+            .as_ref()
+            .map_or_else(U256::zero, Stratum::hashrate)
+    }
+
     /// Start num_worker new workers
     pub fn start_new_worker(
         num_worker: u32, bg: Arc<BlockGenerator>, seed_hash: H256,
@@ -759,6 +779,17 @@ impl BlockGenerator {
             listen_addr: bg.pow_config.stratum_listen_addr.clone(),
             port: bg.pow_config.stratum_port,
             secret: bg.pow_config.stratum_secret,
+            pool: bg.pow_config.stratum_pool.as_ref().map(|pool| PoolConfig {
+                initial_difficulty: pool.initial_share_difficulty,
+                min_difficulty: pool.min_share_difficulty,
+                max_difficulty: pool.max_share_difficulty,
+                target_share_interval: Duration::from_millis(
+                    pool.share_target_interval_ms,
+                ),
+                retarget_interval: Duration::from_millis(
+                    pool.vardiff_retarget_interval_ms,
+                ),
+            }),
         };
         let stratum = Stratum::start(
             &cfg,
@@ -837,6 +868,9 @@ impl BlockGenerator {
                         .get_data_manager()
                         .db_manager
                         .get_current_seed_hash(new_block.block_header.height()),
+                )
+                .with_pow_lower_bound(
+                    bg.pow_lower_bound_enabled(new_block.block_header.height()),
                 );
 
                 current_mining_block = Some(new_block);
@@ -899,6 +933,7 @@ impl BlockGenerator {
                 &hash,
                 &nonce,
                 &problem.boundary,
+                problem.pow_lower_bound,
             ) {
                 info!(
                     "Solution found after checking {} hashes",
@@ -967,6 +1002,8 @@ impl BlockGenerator {
                     .unwrap()
                     .block_header
                     .height();
+                let db_manager =
+                    &bg.consensus_graph().get_data_manager().db_manager;
                 let problem = ProofOfWorkProblem::new(
                     height,
                     current_mining_block
@@ -975,11 +1012,10 @@ impl BlockGenerator {
                         .block_header
                         .problem_hash(),
                     *current_difficulty,
-                    bg.consensus_graph()
-                        .get_data_manager()
-                        .db_manager
-                        .get_current_seed_hash(height),
-                );
+                    db_manager.get_current_seed_hash(height),
+                )
+                .with_next_seed_hash(db_manager.get_next_seed_hash(height))
+                .with_pow_lower_bound(bg.pow_lower_bound_enabled(height));
                 last_assemble = SystemTime::now();
                 trace!("send problem: {:?}", problem);
                 BlockGenerator::send_problem(bg.clone(), problem);
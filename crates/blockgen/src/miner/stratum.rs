@@ -27,13 +27,11 @@
 use crate::miner::work_notify::NotifyWork;
 use log::{info, trace, warn};
 use mazze_stratum::{
-    Error as StratumServiceError, JobDispatcher, PushWorkHandler,
-    Stratum as StratumService,
+    Error as StratumServiceError, JobDispatcher, PoolConfig, PushWorkHandler,
+    ShareStats, ShareStatus, Stratum as StratumService, WorkerStats,
 };
 use mazze_types::{H256, U256};
-use mazzecore::pow::{
-    validate, PowComputer, ProofOfWorkProblem, ProofOfWorkSolution,
-};
+use mazzecore::pow::{PowComputer, ProofOfWorkProblem, ProofOfWorkSolution};
 use parking_lot::Mutex;
 use std::{
     collections::HashSet,
@@ -51,6 +49,8 @@ pub struct Options {
     pub port: u16,
     /// Secret for peers
     pub secret: Option<H256>,
+    /// Pool mode with per-worker share difficulty, if enabled
+    pub pool: Option<PoolConfig>,
 }
 
 fn clean_0x(s: &str) -> &str {
@@ -122,6 +122,37 @@ pub struct StratumJobDispatcher {
 
 impl JobDispatcher for StratumJobDispatcher {
     fn submit(&self, payload: Vec<String>) -> Result<(), StratumServiceError> {
+        self.check_solution(payload, None).map(|_| ())
+    }
+
+    fn submit_share(
+        &self, payload: Vec<String>, share_boundary: U256,
+    ) -> Result<ShareStatus, StratumServiceError> {
+        self.check_solution(payload, Some(share_boundary))
+    }
+}
+
+impl StratumJobDispatcher {
+    /// New stratum job dispatcher given the miner and client
+    fn new(
+        solution_sender: mpsc::Sender<ProofOfWorkSolution>,
+        pow: Arc<PowComputer>, pow_window_size: usize,
+    ) -> StratumJobDispatcher {
+        StratumJobDispatcher {
+            recent_problems: Mutex::new(vec![]),
+            solution_sender: Mutex::new(solution_sender),
+            pow,
+            window_size: pow_window_size,
+        }
+    }
+
+    /// Checks a submitted nonce against the recent problems. Without
+    /// `share_boundary` only block solutions are accepted, otherwise nonces
+    /// meeting the share boundary are accepted as pool shares. Solutions
+    /// meeting the block boundary are always sent to the block generator.
+    fn check_solution(
+        &self, payload: Vec<String>, share_boundary: Option<U256>,
+    ) -> Result<ShareStatus, StratumServiceError> {
         let payload = SubmitPayload::from_args(payload)
             .map_err(|e| StratumServiceError::Dispatch(e.to_string()))?;
 
@@ -138,7 +169,7 @@ impl JobDispatcher for StratumJobDispatcher {
         };
         {
             let mut probs = self.recent_problems.lock();
-            let mut found = false;
+            let mut status = None;
             for (pow_prob, solved_nonce) in probs.iter_mut() {
                 if pow_prob.block_hash == payload.pow_hash {
                     if solved_nonce.contains(&sol.nonce) {
@@ -148,13 +179,39 @@ impl JobDispatcher for StratumJobDispatcher {
                                 sol.nonce, payload.worker_id
                             ).into(),
                         ));
-                    } else if validate(self.pow.clone(), pow_prob, &sol) {
+                    }
+                    let hash = self.pow.compute(
+                        &sol.nonce,
+                        &pow_prob.block_hash,
+                        &pow_prob.seed_hash,
+                    );
+                    if ProofOfWorkProblem::validate_hash_against_boundary(
+                        &hash,
+                        &sol.nonce,
+                        &pow_prob.boundary,
+                        pow_prob.pow_lower_bound,
+                    ) {
                         solved_nonce.insert(sol.nonce);
                         info!(
                             "Stratum worker {} mined a block!",
                             payload.worker_id
                         );
-                        found = true;
+                        status = Some(ShareStatus::Block);
+                    } else if share_boundary.map_or(false, |boundary| {
+                        ProofOfWorkProblem::validate_hash_against_boundary(
+                            &hash,
+                            &sol.nonce,
+                            &boundary,
+                            pow_prob.pow_lower_bound,
+                        )
+                    }) {
+                        solved_nonce.insert(sol.nonce);
+                        trace!(
+                            target: "stratum",
+                            "Stratum worker {} submitted a valid share",
+                            payload.worker_id
+                        );
+                        status = Some(ShareStatus::Accepted);
                     } else {
                         return Err(StratumServiceError::InvalidSolution(
                             format!(
@@ -166,39 +223,29 @@ impl JobDispatcher for StratumJobDispatcher {
                     }
                 }
             }
-            if !found {
-                return Err(StratumServiceError::InvalidSolution(
-                    format!(
-                        "Solution for a stale job! worker_id = {}",
-                        payload.worker_id
-                    )
-                    .into(),
-                ));
-            }
+            let status = match status {
+                Some(status) => status,
+                None => {
+                    return Err(StratumServiceError::StaleShare(
+                        format!(
+                            "Solution for a stale job! worker_id = {}",
+                            payload.worker_id
+                        )
+                        .into(),
+                    ));
+                }
+            };
 
-            match self.solution_sender.lock().send(sol) {
-                Ok(_) => {}
-                Err(e) => {
-                    warn!("{}", e);
+            if status == ShareStatus::Block {
+                match self.solution_sender.lock().send(sol) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("{}", e);
+                    }
                 }
             }
-        }
-
-        Ok(())
-    }
-}
 
-impl StratumJobDispatcher {
-    /// New stratum job dispatcher given the miner and client
-    fn new(
-        solution_sender: mpsc::Sender<ProofOfWorkSolution>,
-        pow: Arc<PowComputer>, pow_window_size: usize,
-    ) -> StratumJobDispatcher {
-        StratumJobDispatcher {
-            recent_problems: Mutex::new(vec![]),
-            solution_sender: Mutex::new(solution_sender),
-            pow,
-            window_size: pow_window_size,
+            Ok(status)
         }
     }
 
@@ -210,25 +257,36 @@ impl StratumJobDispatcher {
         probs.push((current_problem.clone(), HashSet::new()));
     }
 
-    /// Serializes payload for stratum service
+    /// Serializes payload for stratum service. The seed hash of the next
+    /// RandomX epoch is appended once it is known. Once the two-sided PoW
+    /// target applies, a `true` flag is appended after it, with a `null` next
+    /// seed hash if that is not known yet.
     fn payload(
         &self, block_height: u64, pow_hash: H256, boundary: U256,
-        seed_hash: H256,
+        seed_hash: H256, next_seed_hash: Option<H256>, pow_lower_bound: bool,
     ) -> String {
         let pow_hash_str = format!("0x{:064x}", pow_hash);
         let boundary_str = format!("0x{:064x}", boundary);
         let seed_hash_str = format!("0x{:064x}", seed_hash);
+        let next_seed_hash_str = match (next_seed_hash, pow_lower_bound) {
+            (Some(hash), _) => format!(r#", "0x{:064x}""#, hash),
+            (None, true) => ", null".into(),
+            (None, false) => String::new(),
+        };
+        let pow_lower_bound_str = if pow_lower_bound { ", true" } else { "" };
 
         let stratum_payload = format!(
-            r#"["{}", "{}", "{}", "{}", "{}"]"#,
+            r#"["{}", "{}", "{}", "{}", "{}"{}{}]"#,
             pow_hash_str,
             block_height,
             pow_hash_str,
             boundary_str,
             seed_hash_str,
+            next_seed_hash_str,
+            pow_lower_bound_str,
         );
         trace!(
-            "STRATUM PAYLOAD (pow_hash,block_height, pow_hash, boundary, seed_hash): {}",
+            "STRATUM PAYLOAD (pow_hash,block_height, pow_hash, boundary, seed_hash[, next_seed_hash[, pow_lower_bound]]): {}",
             stratum_payload
         );
 
@@ -271,7 +329,7 @@ impl NotifyWork for Stratum {
         self.service.push_work_all(
             self.dispatcher.payload(
                 prob.block_height, prob.block_hash, prob.boundary,
-                prob.seed_hash,
+                prob.seed_hash, prob.next_seed_hash, prob.pow_lower_bound,
             )
         ).unwrap_or_else(
             |e| warn!(target: "stratum", "Error while pushing work: {:?}", e)
@@ -294,13 +352,14 @@ impl Stratum {
             pow_window_size,
         ));
 
-        let stratum_svc = StratumService::start(
+        let stratum_svc = StratumService::start_with_pool(
             &SocketAddr::new(
                 options.listen_addr.parse::<IpAddr>()?,
                 options.port,
             ),
             dispatcher.clone(),
             options.secret.clone(),
+            options.pool.clone(),
         )?;
 
         Ok(Stratum {
@@ -308,4 +367,19 @@ impl Stratum {
             service: stratum_svc,
         })
     }
+
+    /// Share statistics of the connected stratum workers
+    pub fn worker_stats(&self) -> Vec<WorkerStats> {
+        self.service.worker_stats()
+    }
+
+    /// Share statistics accumulated over all stratum workers
+    pub fn total_share_stats(&self) -> ShareStats {
+        self.service.total_share_stats()
+    }
+
+    /// Sum of the hashrates reported by the stratum workers
+    pub fn hashrate(&self) -> U256 {
+        self.service.hashrate()
+    }
 }
@@ -22,9 +22,13 @@ use malloc_size_of::{new_malloc_size_ops, MallocSizeOf, MallocSizeOfOps};
 use mazze_executor::machine::{new_machine_with_builtin, Machine, VmFactory};
 use mazze_parameters::genesis::DEV_GENESIS_KEY_PAIR_2;
 use mazze_storage::StorageManager;
-use mazze_types::{address_util::AddressUtil, Address, Space, U256};
+use mazze_types::{address_util::AddressUtil, Address, Space, H256, U256};
 use mazzecore::{
-    block_data_manager::BlockDataManager,
+    block_data_manager::{
+        db_check::{DbCheckReport, DbChecker},
+        db_manager::DBManager,
+        BlockDataManager,
+    },
     genesis_block::{self as genesis, genesis_block},
     pow::PowComputer,
     statistics::Statistics,
@@ -292,16 +296,17 @@ pub fn initialize_common_modules(
         Arc::new(network)
     };
 
+    let notifications = Notifications::init();
     let verification_config = conf.verification_config(machine.clone());
     let txpool = Arc::new(TransactionPool::new(
         conf.txpool_config(),
         verification_config.clone(),
         data_man.clone(),
         machine.clone(),
+        notifications.new_pending_transactions.clone(),
     ));
 
     let statistics = Arc::new(Statistics::new());
-    let notifications = Notifications::init();
 
     let consensus = Arc::new(ConsensusGraph::new(
         consensus_conf,
@@ -436,10 +441,11 @@ pub fn initialize_not_light_node_modules(
         network.clone(),
         sync_graph.clone(),
         conf.protocol_config(),
-        conf.state_sync_config(),
+        conf.state_sync_config()?,
         SyncPhaseType::CatchUpRecoverBlockHeaderFromDB,
         light_provider,
         consensus.clone(),
+        exit.clone(),
     ));
     sync.register().unwrap();
 
@@ -720,6 +726,46 @@ pub fn initialize_txgens(
     (maybe_multi_genesis_txgen, maybe_direct_txgen_with_contract)
 }
 
+/// Check the blocks db and the state db of a stopped node, and rebuild the
+/// derivable indices in place if `repair` is set.
+///
+/// The genesis is neither executed nor inserted, and the instance id is not
+/// updated as it is by `BlockDataManager::new`, so that the blocks db is only
+/// written when `repair` is set. The state db however is opened as on
+/// startup, which removes the unfinished snapshots and the delta MPTs of the
+/// missing snapshots.
+pub fn check_database(
+    conf: &Configuration, repair: bool,
+) -> Result<DbCheckReport, String> {
+    let ledger_db = db::open_database(&conf.db_settings())
+        .map_err(|e| format!("Failed to open database {:?}", e))?;
+    let storage_manager = Arc::new(
+        StorageManager::new(conf.storage_config(&conf.node_type()))
+            .map_err(|e| format!("Failed to initialize storage {:?}", e))?,
+    );
+
+    // The genesis hash is only used as the RandomX seed hash to compute the
+    // PoW hashes of the headers, which the checker reads without computing
+    // them.
+    let genesis_hash = H256::zero();
+    let config = conf.data_mananger_config();
+    let db_manager = DBManager::new_from_backend(
+        config.block_db_backend,
+        ledger_db,
+        Arc::new(PowComputer::new(genesis_hash)),
+        genesis_hash,
+    );
+
+    Ok(DbChecker::new(
+        &db_manager,
+        &storage_manager,
+        &config,
+        conf.chain_id_params(),
+        repair,
+    )
+    .check())
+}
+
 pub mod delegate_convert {
     use std::convert::Into as StdInto;
 
@@ -38,6 +38,7 @@ use mazzecore::{
     consensus_internal_parameters::*,
     consensus_parameters::*,
     light_protocol::LightNodeConfiguration,
+    pow::StratumPoolConfig,
     sync::{ProtocolConfiguration, StateSyncConfiguration, SyncGraphConfig},
     sync_parameters::*,
     transaction_pool::TxPoolConfig,
@@ -148,6 +149,7 @@ build_config! {
         (next_hardfork_transition_number, (Option<u64>), None)
         (next_hardfork_transition_height, (Option<u64>), None)
         (cancun_opcodes_transition_number, (Option<u64>), None)
+        (pow_lower_bound_transition_height, (Option<u64>), None)
         (referee_bound, (usize), REFEREE_DEFAULT_BOUND)
         (timer_chain_beta, (u64), TIMER_CHAIN_DEFAULT_BETA)
         (timer_chain_block_difficulty_ratio, (u64), TIMER_CHAIN_BLOCK_DEFAULT_DIFFICULTY_RATIO)
@@ -163,6 +165,13 @@ build_config! {
         (stratum_port, (u16), 32525)
         (stratum_secret, (Option<String>), None)
         (pow_problem_window_size, (usize), 1)
+        // Stratum pool mode with per-worker share difficulty and vardiff.
+        (stratum_pool_mode, (bool), false)
+        (stratum_initial_share_difficulty, (u64), 1_000)
+        (stratum_min_share_difficulty, (u64), 1)
+        (stratum_max_share_difficulty, (u64), u64::MAX)
+        (stratum_share_target_interval_ms, (u64), 10_000)
+        (stratum_vardiff_retarget_interval_ms, (u64), 60_000)
 
         // Network section.
         (jsonrpc_local_tcp_port, (Option<u16>), None)
@@ -189,6 +198,9 @@ build_config! {
         (udp_port, (Option<u16>), Some(55555))
 
         // Network parameters section.
+        // Insert the blocks in this block stream before syncing block headers
+        // from peers. The blocks are verified as if received from peers.
+        (blocks_import_path, (Option<String>), None)
         (blocks_request_timeout_ms, (u64), 15_000)
         (check_request_period_ms, (u64), 500)
         (chunk_size_byte, (u64), DEFAULT_CHUNK_SIZE)
@@ -219,6 +231,11 @@ build_config! {
         (received_tx_index_maintain_timeout_ms, (u64), 300_000)
         (request_block_with_public, (bool), false)
         (send_tx_period_ms, (u64), 1300)
+        // Restore the checkpoint state from this snapshot bundle instead of
+        // syncing it from peers. The bundle is verified against
+        // `snapshot_bundle_trusted_block`.
+        (snapshot_bundle_path, (Option<String>), None)
+        (snapshot_bundle_trusted_block, (Option<String>), None)
         (snapshot_candidate_request_timeout_ms, (u64), 10_000)
         (snapshot_chunk_request_timeout_ms, (u64), 30_000)
         (snapshot_manifest_request_timeout_ms, (u64), 30_000)
@@ -313,12 +330,14 @@ build_config! {
         (max_trans_count_received_in_catch_up, (u64), 60_000)
         (persist_tx_index, (bool), false)
         (persist_block_number_index, (bool), true)
+        (persist_address_index, (bool), false)
         (print_memory_usage_period_s, (Option<u64>), None)
         (target_block_gas_limit, (u64), DEFAULT_TARGET_BLOCK_GAS_LIMIT)
         (executive_trace, (bool), false)
         (check_status_genesis, (bool), true)
         (packing_gas_limit_block_count, (u64), 10)
         (poll_lifetime_in_seconds, (Option<u32>), None)
+        (pow_verification_threads, (usize), 4)
 
         // DAG-Embedded Tree Structure (DETS) Section.
         (is_consortium, (bool), false)
@@ -424,9 +443,54 @@ impl Configuration {
             config.raw_conf.node_type = Some(NodeType::Light);
         }
 
+        if let ("snapshot", Some(snapshot_matches)) = matches.subcommand() {
+            if let ("import", Some(import_matches)) =
+                snapshot_matches.subcommand()
+            {
+                config.raw_conf.snapshot_bundle_path =
+                    import_matches.value_of("path").map(Into::into);
+                config.raw_conf.snapshot_bundle_trusted_block =
+                    import_matches.value_of("trusted-block").map(Into::into);
+            }
+        }
+
+        if let ("blocks", Some(blocks_matches)) = matches.subcommand() {
+            if let ("import", Some(import_matches)) =
+                blocks_matches.subcommand()
+            {
+                config.raw_conf.blocks_import_path =
+                    import_matches.value_of("path").map(Into::into);
+            }
+        }
+
+        config.check_stratum_share_difficulty()?;
         Ok(config)
     }
 
+    fn check_stratum_share_difficulty(&self) -> Result<(), String> {
+        let initial = self.raw_conf.stratum_initial_share_difficulty;
+        let min = self.raw_conf.stratum_min_share_difficulty;
+        let max = self.raw_conf.stratum_max_share_difficulty;
+        if min == 0 {
+            return Err(
+                "stratum_min_share_difficulty should be positive".into()
+            );
+        }
+        if min > max {
+            return Err(format!(
+                "stratum_min_share_difficulty {} is larger than stratum_max_share_difficulty {}",
+                min, max
+            ));
+        }
+        if initial < min || initial > max {
+            return Err(format!(
+                "stratum_initial_share_difficulty {} is not in [{}, {}]",
+                initial, min, max
+            ));
+        }
+        Ok(())
+    }
+
     fn network_id(&self) -> u64 {
         match self.raw_conf.network_id {
             Some(x) => x,
@@ -733,7 +797,7 @@ impl Configuration {
             },
             |s| s.as_str(),
         );
-        ProofOfWorkConfig::new(
+        let mut pow_config = ProofOfWorkConfig::new(
             self.is_test_or_dev_mode(),
             mining_type,
             self.raw_conf.initial_difficulty,
@@ -741,7 +805,27 @@ impl Configuration {
             self.raw_conf.stratum_port,
             stratum_secret,
             self.raw_conf.pow_problem_window_size,
-        )
+        );
+        if self.raw_conf.stratum_pool_mode {
+            pow_config.stratum_pool = Some(StratumPoolConfig {
+                initial_share_difficulty: self
+                    .raw_conf
+                    .stratum_initial_share_difficulty,
+                min_share_difficulty: self
+                    .raw_conf
+                    .stratum_min_share_difficulty,
+                max_share_difficulty: self
+                    .raw_conf
+                    .stratum_max_share_difficulty,
+                share_target_interval_ms: self
+                    .raw_conf
+                    .stratum_share_target_interval_ms,
+                vardiff_retarget_interval_ms: self
+                    .raw_conf
+                    .stratum_vardiff_retarget_interval_ms,
+            });
+        }
+        pow_config
     }
 
     pub fn verification_config(
@@ -939,11 +1023,40 @@ impl Configuration {
                 .raw_conf
                 .min_phase_change_normal_peer_count,
             check_status_genesis: self.raw_conf.check_status_genesis,
+            blocks_to_import: self
+                .raw_conf
+                .blocks_import_path
+                .as_ref()
+                .map(PathBuf::from),
         }
     }
 
-    pub fn state_sync_config(&self) -> StateSyncConfiguration {
-        StateSyncConfiguration {
+    pub fn state_sync_config(&self) -> Result<StateSyncConfiguration, String> {
+        let snapshot_bundle_to_import = match &self
+            .raw_conf
+            .snapshot_bundle_path
+        {
+            None => None,
+            Some(path) => {
+                let trusted_block = self
+                    .raw_conf
+                    .snapshot_bundle_trusted_block
+                    .as_ref()
+                    .ok_or(
+                        "snapshot_bundle_trusted_block is required to import a snapshot bundle",
+                    )?;
+                let trusted_block =
+                    parse_hex_string::<H256>(trusted_block).map_err(|e| {
+                        format!(
+                            "snapshot_bundle_trusted_block is not a valid block hash: {:?}",
+                            e
+                        )
+                    })?;
+                Some((PathBuf::from(path), trusted_block))
+            }
+        };
+
+        Ok(StateSyncConfiguration {
             max_downloading_chunks: self.raw_conf.max_downloading_chunks,
             candidate_request_timeout: Duration::from_millis(
                 self.raw_conf.snapshot_candidate_request_timeout_ms,
@@ -957,7 +1070,8 @@ impl Configuration {
             max_downloading_manifest_attempts: self
                 .raw_conf
                 .max_downloading_manifest_attempts,
-        }
+            snapshot_bundle_to_import,
+        })
     }
 
     pub fn data_mananger_config(&self) -> DataManagerConfiguration {
@@ -966,6 +1080,7 @@ impl Configuration {
             persist_block_number_index: self
                 .raw_conf
                 .persist_block_number_index,
+            persist_address_index: self.raw_conf.persist_address_index,
             tx_cache_index_maintain_timeout: Duration::from_millis(
                 self.raw_conf.tx_cache_index_maintain_timeout_ms,
             ),
@@ -1028,7 +1143,9 @@ impl Configuration {
                 conf.additional_maintained_trace_epoch_count = Some(0);
             }
         }
-        if conf.additional_maintained_transaction_index_epoch_count != Some(0) {
+        if conf.additional_maintained_transaction_index_epoch_count != Some(0)
+            || conf.persist_address_index
+        {
             conf.persist_tx_index = true;
         }
         conf
@@ -1041,6 +1158,7 @@ impl Configuration {
                 .future_block_buffer_capacity,
             enable_state_expose: self.raw_conf.enable_state_expose,
             is_consortium: self.raw_conf.is_consortium,
+            pow_verification_threads: self.raw_conf.pow_verification_threads,
         }
     }
 
@@ -1313,6 +1431,11 @@ impl Configuration {
             .cancun_opcodes_transition_number
             .or(self.raw_conf.next_hardfork_transition_number)
             .unwrap_or(default_transition_time);
+        params.transition_heights.pow_lower_bound = self
+            .raw_conf
+            .pow_lower_bound_transition_height
+            .or(self.raw_conf.next_hardfork_transition_height)
+            .unwrap_or(default_transition_time);
     }
 }
 
@@ -1379,7 +1502,7 @@ pub fn parse_config_address_string(
 mod tests {
     use mazze_addr::Network;
 
-    use crate::configuration::parse_config_address_string;
+    use crate::configuration::{parse_config_address_string, Configuration};
 
     #[test]
     fn test_config_address_string() {
@@ -1416,4 +1539,54 @@ mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn test_snapshot_bundle_trusted_block() {
+        let mut conf = Configuration::default();
+        assert!(conf
+            .state_sync_config()
+            .unwrap()
+            .snapshot_bundle_to_import
+            .is_none());
+
+        conf.raw_conf.snapshot_bundle_path = Some("./bundle".into());
+        assert!(conf.state_sync_config().is_err());
+
+        conf.raw_conf.snapshot_bundle_trusted_block = Some("0x1234".into());
+        assert!(conf.state_sync_config().is_err());
+
+        conf.raw_conf.snapshot_bundle_trusted_block =
+            Some(format!("0x{}", "11".repeat(32)));
+        let (path, trusted_block) = conf
+            .state_sync_config()
+            .unwrap()
+            .snapshot_bundle_to_import
+            .unwrap();
+        assert_eq!(path.to_str(), Some("./bundle"));
+        assert_eq!(trusted_block.as_bytes(), &[0x11; 32]);
+    }
+
+    #[test]
+    fn test_stratum_share_difficulty() {
+        let mut conf = Configuration::default();
+        assert!(conf.check_stratum_share_difficulty().is_ok());
+
+        conf.raw_conf.stratum_initial_share_difficulty = 0;
+        assert!(conf.check_stratum_share_difficulty().is_err());
+
+        conf.raw_conf.stratum_initial_share_difficulty = 1_000;
+        conf.raw_conf.stratum_min_share_difficulty = 0;
+        assert!(conf.check_stratum_share_difficulty().is_err());
+
+        conf.raw_conf.stratum_min_share_difficulty = 2_000;
+        conf.raw_conf.stratum_max_share_difficulty = 1_000;
+        assert!(conf.check_stratum_share_difficulty().is_err());
+
+        conf.raw_conf.stratum_min_share_difficulty = 10;
+        conf.raw_conf.stratum_max_share_difficulty = 100;
+        assert!(conf.check_stratum_share_difficulty().is_err());
+
+        conf.raw_conf.stratum_max_share_difficulty = 1_000;
+        assert!(conf.check_stratum_share_difficulty().is_ok());
+    }
 }
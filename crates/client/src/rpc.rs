@@ -280,6 +280,7 @@ fn setup_rpc_apis(
                     rpc.consensus.clone(),
                     rpc.sync.clone(),
                     rpc.tx_pool.clone(),
+                    rpc.block_gen.clone(),
                 )
                 .to_delegate();
                 let evm_trace_handler = EthTraceHandler {
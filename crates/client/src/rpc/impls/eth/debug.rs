@@ -1,20 +1,25 @@
 use std::convert::TryInto;
 
 use crate::rpc::{
-    error_codes::invalid_params_msg,
+    error_codes::{call_execution_error, invalid_params_msg},
+    impls::eth::eth_handler::{call_epoch_number, sign_virtual_call},
     traits::eth_space::debug::Debug,
-    types::eth::{BlockNumber, CallRequest},
+    types::{
+        eth::{BlockNumber, CallRequest},
+        into_call_overrides, GethDebugTracingCallOptions,
+    },
 };
 use alloy_rpc_types_trace::geth::{
     GethDebugBuiltInTracerType,
     GethDebugTracerType::{BuiltInTracer, JsTracer},
-    GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, NoopFrame,
-    TraceResult,
+    GethDebugTracingOptions, GethTrace, NoopFrame, TraceResult,
 };
 use geth_tracer::to_alloy_h256;
 use jsonrpc_core::Result as JsonRpcResult;
+use mazze_executor::executive::ExecutionOutcome;
 use mazze_types::{Space, H256};
 use mazzecore::{ConsensusGraph, SharedConsensusGraph};
+use primitives::EpochNumber;
 
 pub struct GethDebugHandler {
     consensus: SharedConsensusGraph,
@@ -31,6 +36,112 @@ impl GethDebugHandler {
             .downcast_ref::<ConsensusGraph>()
             .expect("downcast should succeed")
     }
+
+    /// Trace the eth space transactions of an epoch. The tracer options must
+    /// have passed `precheck_tracer`.
+    fn trace_epoch(
+        &self, epoch_num: u64, opts: GethDebugTracingOptions,
+    ) -> JsonRpcResult<Vec<TraceResult>> {
+        let epoch_traces = self
+            .consensus_graph()
+            .collect_epoch_geth_trace(epoch_num, None, opts)
+            .map_err(|e| {
+                invalid_params_msg(&format!("invalid tx hash: {e}"))
+            })?;
+
+        let result = epoch_traces
+            .into_iter()
+            .filter(|val| val.space == Space::Ethereum)
+            .map(|val| TraceResult::Success {
+                result: val.trace,
+                tx_hash: Some(to_alloy_h256(val.tx_hash)),
+            })
+            .collect();
+        Ok(result)
+    }
+
+    /// Trace the eth space transactions of an epoch with a trace which needs
+    /// no execution, i.e. the one returned by `precheck_tracer`.
+    fn trace_epoch_without_execution(
+        &self, epoch_num: u64, trace: GethTrace,
+    ) -> JsonRpcResult<Vec<TraceResult>> {
+        let phantom_block = self
+            .consensus_graph()
+            .get_phantom_block_by_number(
+                EpochNumber::Number(epoch_num),
+                None,
+                false, /* include_traces */
+            )
+            .map_err(|e| invalid_params_msg(&e))?
+            .ok_or(invalid_params_msg("invalid block"))?;
+
+        let result = phantom_block
+            .transactions
+            .iter()
+            .map(|tx| TraceResult::Success {
+                result: trace.clone(),
+                tx_hash: Some(to_alloy_h256(tx.hash())),
+            })
+            .collect();
+        Ok(result)
+    }
+}
+
+/// Check that the requested tracer is supported and its config is valid.
+/// Every debug_trace* method must call it before executing anything, as the
+/// tracer expects a valid config. Returns the trace right away if nothing
+/// needs to be executed, i.e. for the NoopTracer.
+fn precheck_tracer(
+    opts: &GethDebugTracingOptions,
+) -> JsonRpcResult<Option<GethTrace>> {
+    if let Some(tracer_type) = &opts.tracer {
+        match tracer_type {
+            BuiltInTracer(builtin_tracer) => match builtin_tracer {
+                GethDebugBuiltInTracerType::FourByteTracer => (),
+                GethDebugBuiltInTracerType::CallTracer => {
+                    // pre check config
+                    let _ = opts
+                        .tracer_config
+                        .clone()
+                        .into_call_config()
+                        .map_err(|e| invalid_params_msg(&e.to_string()))?;
+                }
+                GethDebugBuiltInTracerType::PreStateTracer => {
+                    // pre check config
+                    let _ = opts
+                        .tracer_config
+                        .clone()
+                        .into_pre_state_config()
+                        .map_err(|e| invalid_params_msg(&e.to_string()))?;
+                }
+                GethDebugBuiltInTracerType::NoopTracer => {
+                    return Ok(Some(
+                        GethTrace::NoopTracer(NoopFrame::default()),
+                    ))
+                }
+                GethDebugBuiltInTracerType::MuxTracer => {
+                    // pre check config of every tracer in the mux
+                    let config = opts.tracer_config.clone().into_mux_config();
+                    let config = config
+                        .map_err(|e| invalid_params_msg(&e.to_string()))?;
+                    for (tracer, tracer_config) in config.0 {
+                        if tracer == GethDebugBuiltInTracerType::MuxTracer {
+                            return Err(invalid_params_msg(
+                                "nested muxTracer is not supported",
+                            ));
+                        }
+                        let _ = precheck_tracer(&GethDebugTracingOptions {
+                            tracer: Some(BuiltInTracer(tracer)),
+                            tracer_config: tracer_config.unwrap_or_default(),
+                            ..Default::default()
+                        })?;
+                    }
+                }
+            },
+            JsTracer(_) => return Err(invalid_params_msg("not supported")),
+        }
+    }
+    Ok(None)
 }
 
 impl Debug for GethDebugHandler {
@@ -43,40 +154,8 @@ impl Debug for GethDebugHandler {
     ) -> JsonRpcResult<GethTrace> {
         let opts = opts.unwrap_or_default();
 
-        // early return if tracer is not supported or NoopTracer is requested
-        if let Some(tracer_type) = &opts.tracer {
-            match tracer_type {
-                BuiltInTracer(builtin_tracer) => match builtin_tracer {
-                    GethDebugBuiltInTracerType::FourByteTracer => (),
-                    GethDebugBuiltInTracerType::CallTracer => {
-                        // pre check config
-                        let _ = opts
-                            .tracer_config
-                            .clone()
-                            .into_call_config()
-                            .map_err(|e| {
-                            invalid_params_msg(&e.to_string())
-                        })?;
-                        ()
-                    }
-                    GethDebugBuiltInTracerType::PreStateTracer => {
-                        // pre check config
-                        let _ = opts
-                            .tracer_config
-                            .clone()
-                            .into_pre_state_config()
-                            .map_err(|e| invalid_params_msg(&e.to_string()))?;
-                        ()
-                    }
-                    GethDebugBuiltInTracerType::NoopTracer => {
-                        return Ok(GethTrace::NoopTracer(NoopFrame::default()))
-                    }
-                    GethDebugBuiltInTracerType::MuxTracer => {
-                        return Err(invalid_params_msg("not supported"))
-                    }
-                },
-                JsTracer(_) => return Err(invalid_params_msg("not supported")),
-            }
+        if let Some(trace) = precheck_tracer(&opts)? {
+            return Ok(trace);
         }
 
         let tx_index = self
@@ -111,32 +190,24 @@ impl Debug for GethDebugHandler {
         &self, block_hash: H256, opts: Option<GethDebugTracingOptions>,
     ) -> JsonRpcResult<Vec<TraceResult>> {
         let opts = opts.unwrap_or_default();
+        let trace = precheck_tracer(&opts)?;
+
         let epoch_num = self
             .consensus_graph()
             .get_block_epoch_number_with_main_check(&block_hash, false)?;
 
-        let epoch_traces = self
-            .consensus_graph()
-            .collect_epoch_geth_trace(epoch_num, None, opts)
-            .map_err(|e| {
-                invalid_params_msg(&format!("invalid tx hash: {e}"))
-            })?;
-
-        let result = epoch_traces
-            .into_iter()
-            .filter(|val| val.space == Space::Ethereum)
-            .map(|val| TraceResult::Success {
-                result: val.trace,
-                tx_hash: Some(to_alloy_h256(val.tx_hash)),
-            })
-            .collect();
-        Ok(result)
+        match trace {
+            Some(trace) => self.trace_epoch_without_execution(epoch_num, trace),
+            None => self.trace_epoch(epoch_num, opts),
+        }
     }
 
     fn debug_trace_block_by_number(
         &self, block: BlockNumber, opts: Option<GethDebugTracingOptions>,
     ) -> JsonRpcResult<Vec<TraceResult>> {
         let opts = opts.unwrap_or_default();
+        let trace = precheck_tracer(&opts)?;
+
         let num = match block {
             BlockNumber::Num(block_number) => block_number,
             BlockNumber::Latest | BlockNumber::Safe => {
@@ -156,31 +227,214 @@ impl Debug for GethDebugHandler {
                 )?,
             _ => return Err(invalid_params_msg("not supported")),
         };
-        let epoch_traces = self
-            .consensus_graph()
-            .collect_epoch_geth_trace(num, None, opts)
-            .map_err(|e| {
-                invalid_params_msg(&format!("invalid tx hash: {e}"))
-            })?;
 
-        let result = epoch_traces
-            .into_iter()
-            .filter(|val| val.space == Space::Ethereum)
-            .map(|val| TraceResult::Success {
-                result: val.trace,
-                tx_hash: Some(to_alloy_h256(val.tx_hash)),
-            })
-            .collect();
-        Ok(result)
+        match trace {
+            Some(trace) => self.trace_epoch_without_execution(num, trace),
+            None => self.trace_epoch(num, opts),
+        }
     }
 
     fn debug_trace_call(
         &self, request: CallRequest, block_number: Option<BlockNumber>,
         opts: Option<GethDebugTracingCallOptions>,
     ) -> JsonRpcResult<GethTrace> {
-        let _ = request;
-        let _ = block_number;
-        let _ = opts;
-        todo!("not implemented yet");
+        let GethDebugTracingCallOptions {
+            tracing_options: opts,
+            state_overrides,
+            block_overrides,
+        } = opts.unwrap_or_default();
+        let overrides = into_call_overrides(state_overrides, block_overrides)?;
+
+        if let Some(trace) = precheck_tracer(&opts)? {
+            return Ok(trace);
+        }
+
+        let consensus_graph = self.consensus_graph();
+        let epoch = call_epoch_number(consensus_graph, block_number)?;
+
+        let chain_id = self.consensus.best_chain_id();
+        let (signed_tx, estimate_request) =
+            sign_virtual_call(chain_id.in_evm_space(), request)?;
+
+        let (outcome, trace) = consensus_graph.call_virtual_geth_trace(
+            &signed_tx,
+            epoch,
+            estimate_request,
+            &overrides,
+            opts,
+        )?;
+
+        // Reverted calls still produce a trace; only calls which are not
+        // executed at all have none.
+        match (trace, outcome) {
+            (Some(trace), _) => Ok(trace),
+            (None, ExecutionOutcome::NotExecutedDrop(e)) => {
+                Err(call_execution_error(
+                    "Transaction can not be executed".into(),
+                    format!("{:?}", e),
+                ))
+            }
+            (None, ExecutionOutcome::NotExecutedToReconsiderPacking(e)) => {
+                Err(call_execution_error(
+                    "Transaction can not be executed".into(),
+                    format!("{:?}", e),
+                ))
+            }
+            (None, _) => Err(invalid_params_msg("trace generation failed")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazzecore::{
+        block_data_manager::BlockDbBackend,
+        sync::utils::initialize_synchronization_graph,
+    };
+    use serde_json::{json, Value};
+    use tempdir::TempDir;
+
+    const SENDER: &str = "0x0000000000000000000000000000000000000001";
+    const CONTRACT: &str = "0x0000000000000000000000000000000000000003";
+    // SLOAD(2); SSTORE(1, 0x2a)
+    const CODE: &str = "0x60025450602a60015500";
+    const SLOT_1: &str =
+        "0x0000000000000000000000000000000000000000000000000000000000000001";
+    const SLOT_2: &str =
+        "0x0000000000000000000000000000000000000000000000000000000000000002";
+
+    fn call_options(json: &str) -> GethDebugTracingOptions {
+        serde_json::from_str::<GethDebugTracingCallOptions>(json)
+            .unwrap()
+            .tracing_options
+    }
+
+    /// Call `CONTRACT`, which has `CODE` and the value 7 at slot 2, with
+    /// `debug_traceCall` against the genesis state, and return the trace as
+    /// JSON.
+    fn debug_trace_call(tracer: Value) -> Value {
+        let db_dir = TempDir::new("debug_trace_call").unwrap();
+        let (_, consensus, _, _) = initialize_synchronization_graph(
+            db_dir.path().to_str().unwrap(),
+            1,
+            1,
+            1,
+            1,
+            50000,
+            BlockDbBackend::Rocksdb,
+            H256::zero(),
+        );
+        let handler = GethDebugHandler::new(consensus);
+
+        let request = serde_json::from_value(json!({
+            "from": SENDER,
+            "to": CONTRACT,
+            "gas": "0x186a0",
+        }))
+        .unwrap();
+        let mut opts = json!({
+            "stateOverrides": {
+                CONTRACT: {
+                    "code": CODE,
+                    "stateDiff": { SLOT_2: format!("0x{:064x}", 7) },
+                },
+            },
+        });
+        opts.as_object_mut()
+            .unwrap()
+            .extend(tracer.as_object().unwrap().clone());
+
+        let trace = handler
+            .debug_trace_call(
+                request,
+                Some(BlockNumber::Num(0)),
+                Some(serde_json::from_value(opts).unwrap()),
+            )
+            .unwrap();
+        serde_json::to_value(trace).unwrap()
+    }
+
+    #[test]
+    fn test_precheck_tracer() {
+        // default struct logger
+        assert_eq!(precheck_tracer(&call_options("{}")).unwrap(), None);
+
+        for tracer in ["callTracer", "prestateTracer", "4byteTracer"] {
+            let opts = call_options(&format!(r#"{{"tracer":"{}"}}"#, tracer));
+            assert_eq!(precheck_tracer(&opts).unwrap(), None);
+        }
+
+        let opts = call_options(
+            r#"{"tracer":"prestateTracer","tracerConfig":{"diffMode":true}}"#,
+        );
+        assert_eq!(precheck_tracer(&opts).unwrap(), None);
+
+        let opts = call_options(r#"{"tracer":"noopTracer"}"#);
+        assert_eq!(
+            precheck_tracer(&opts).unwrap(),
+            Some(GethTrace::NoopTracer(NoopFrame::default()))
+        );
+
+        let opts = call_options(
+            r#"{"tracer":"callTracer","tracerConfig":{"onlyTopCall":"yes"}}"#,
+        );
+        assert!(precheck_tracer(&opts).is_err());
+
+        let opts = call_options(r#"{"tracer":"{result: function() {}}"}"#);
+        assert!(precheck_tracer(&opts).is_err());
+
+        let opts = call_options(
+            r#"{"tracer":"muxTracer","tracerConfig":{"callTracer":{"withLog":true},"prestateTracer":{"diffMode":true},"4byteTracer":null}}"#,
+        );
+        assert_eq!(precheck_tracer(&opts).unwrap(), None);
+
+        let opts = call_options(
+            r#"{"tracer":"muxTracer","tracerConfig":{"callTracer":{"onlyTopCall":"yes"}}}"#,
+        );
+        assert!(precheck_tracer(&opts).is_err());
+
+        let opts = call_options(
+            r#"{"tracer":"muxTracer","tracerConfig":{"muxTracer":null}}"#,
+        );
+        assert!(precheck_tracer(&opts).is_err());
+    }
+
+    #[test]
+    fn test_debug_trace_call_with_call_tracer() {
+        let frame = debug_trace_call(json!({ "tracer": "callTracer" }));
+        assert_eq!(frame["type"], "CALL");
+        assert_eq!(frame["from"], SENDER);
+        assert_eq!(frame["to"], CONTRACT);
+        assert_eq!(frame["gas"], "0x186a0");
+        assert!(frame.get("error").is_none());
+        // The intrinsic gas, and the gas of the storage read and write.
+        let gas_used = frame["gasUsed"].as_str().unwrap();
+        assert!(u64::from_str_radix(&gas_used[2..], 16).unwrap() > 21_000);
+    }
+
+    #[test]
+    fn test_debug_trace_call_with_prestate_tracer() {
+        let prestate = debug_trace_call(json!({ "tracer": "prestateTracer" }));
+        let contract = &prestate[CONTRACT];
+        assert_eq!(contract["code"], CODE);
+        assert_eq!(contract["storage"][SLOT_2], format!("0x{:064x}", 7));
+
+        let diff = debug_trace_call(json!({
+            "tracer": "prestateTracer",
+            "tracerConfig": { "diffMode": true },
+        }));
+        // Only the written slot is changed.
+        let storage = &diff["post"][CONTRACT]["storage"];
+        assert_eq!(storage[SLOT_1], format!("0x{:064x}", 0x2a));
+        assert!(storage.get(SLOT_2).is_none());
+    }
+
+    #[test]
+    fn test_debug_trace_call_with_noop_tracer() {
+        assert_eq!(
+            debug_trace_call(json!({ "tracer": "noopTracer" })),
+            json!({})
+        );
     }
 }
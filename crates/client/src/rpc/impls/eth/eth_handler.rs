@@ -10,18 +10,25 @@ use crate::rpc::{
     impls::RpcImplConfiguration,
     traits::eth_space::eth::Eth,
     types::{
+        account_proof_keys,
         eth::{
-            AccountPendingTransactions, Block as RpcBlock, BlockNumber,
-            CallRequest, EthRpcLogFilter, Log, Receipt, SyncInfo, SyncStatus,
-            Transaction,
+            AccountPendingTransactions, AddressTransaction, Block as RpcBlock,
+            BlockNumber, CallRequest, EthRpcLogFilter, Log, Receipt, SyncInfo,
+            SyncStatus, Transaction,
         },
-        Bytes, FeeHistory, Index, MAX_GAS_CALL_REQUEST, U64 as HexU64,
+        into_call_overrides, query_address_transactions, AccessListResult,
+        AccountProof, AddressTransactions, BlockOverrides, Bytes, FeeHistory,
+        Index, SimulatePayload, SimulatedBlock, StateOverride,
+        MAX_GAS_CALL_REQUEST, U64 as HexU64,
     },
 };
+use blockgen::BlockGenerator;
 use clap::crate_version;
 use jsonrpc_core::{Error as RpcError, Result as RpcResult};
-use mazze_execute_helper::estimation::{
-    decode_error, EstimateExt, EstimateRequest,
+use mazze_execute_helper::{
+    estimation::{decode_error, EstimateExt, EstimateRequest},
+    overrides::CallOverrides,
+    simulation::SimulateBlock,
 };
 use mazze_executor::executive::{
     revert_reason_decode, ExecutionError, ExecutionOutcome, TxDropError,
@@ -51,25 +58,28 @@ use primitives::{
     StorageValue, TransactionStatus, TransactionWithSignature,
 };
 use rustc_hex::ToHex;
-use std::{cmp::min, convert::TryInto};
+use std::{cmp::min, convert::TryInto, sync::Arc};
 
 pub struct EthHandler {
     config: RpcImplConfiguration,
     consensus: SharedConsensusGraph,
     sync: SharedSynchronizationService,
     tx_pool: SharedTransactionPool,
+    block_gen: Arc<BlockGenerator>,
 }
 
 impl EthHandler {
     pub fn new(
         config: RpcImplConfiguration, consensus: SharedConsensusGraph,
         sync: SharedSynchronizationService, tx_pool: SharedTransactionPool,
+        block_gen: Arc<BlockGenerator>,
     ) -> Self {
         EthHandler {
             config,
             consensus,
             sync,
             tx_pool,
+            block_gen,
         }
     }
 
@@ -156,6 +166,52 @@ pub fn sign_call(
     Ok(transaction.fake_sign_rpc(from.with_evm_space()))
 }
 
+/// Prepare a call request for a virtual execution: sign it and record which
+/// of its fields were given.
+pub fn sign_virtual_call(
+    chain_id: u32, mut request: CallRequest,
+) -> RpcResult<(SignedTransaction, EstimateRequest)> {
+    // if gas_price is zero, it is considered as not set
+    request.unset_zero_gas_price();
+
+    let estimate_request = EstimateRequest {
+        has_sender: request.from.is_some(),
+        has_gas_limit: request.gas.is_some(),
+        has_gas_price: request.gas_price.is_some(),
+        has_nonce: request.nonce.is_some(),
+        has_storage_limit: false,
+    };
+    Ok((sign_call(chain_id, request)?, estimate_request))
+}
+
+/// Resolve the epoch a virtual call is executed against. Blocks given by hash
+/// must be main blocks, as non-main blocks are not exposed in eth RPC.
+pub fn call_epoch_number(
+    consensus_graph: &ConsensusGraph, block_number_or_hash: Option<BlockNumber>,
+) -> MazzeRpcResult<EpochNumber> {
+    Ok(match block_number_or_hash.unwrap_or_default() {
+        BlockNumber::Hash { hash, .. } => {
+            match consensus_graph.get_block_epoch_number(&hash) {
+                Some(e) => {
+                    // do not expose non-main blocks in eth RPC
+                    let main = consensus_graph
+                        .get_block_hashes_by_epoch(EpochNumber::Number(e))?
+                        .last()
+                        .cloned();
+
+                    if Some(hash) != main {
+                        bail!("Block {:?} not found", hash);
+                    }
+
+                    EpochNumber::Number(e)
+                }
+                None => bail!("Block {:?} not found", hash),
+            }
+        }
+        epoch => epoch.try_into()?,
+    })
+}
+
 fn block_tx_by_index(
     phantom_block: Option<PhantomBlock>, idx: usize,
 ) -> Option<Transaction> {
@@ -187,49 +243,23 @@ fn block_tx_by_index(
 
 impl EthHandler {
     fn exec_transaction(
-        &self, mut request: CallRequest,
-        block_number_or_hash: Option<BlockNumber>,
+        &self, request: CallRequest, block_number_or_hash: Option<BlockNumber>,
+        overrides: CallOverrides,
     ) -> MazzeRpcResult<(ExecutionOutcome, EstimateExt)> {
         let consensus_graph = self.consensus_graph();
-
-        let epoch = match block_number_or_hash.unwrap_or_default() {
-            BlockNumber::Hash { hash, .. } => {
-                match consensus_graph.get_block_epoch_number(&hash) {
-                    Some(e) => {
-                        // do not expose non-main blocks in eth RPC
-                        let main = consensus_graph
-                            .get_block_hashes_by_epoch(EpochNumber::Number(e))?
-                            .last()
-                            .cloned();
-
-                        if Some(hash) != main {
-                            bail!("Block {:?} not found", hash);
-                        }
-
-                        EpochNumber::Number(e)
-                    }
-                    None => bail!("Block {:?} not found", hash),
-                }
-            }
-            epoch => epoch.try_into()?,
-        };
-
-        // if gas_price is zero, it is considered as not set
-        request.unset_zero_gas_price();
-
-        let estimate_request = EstimateRequest {
-            has_sender: request.from.is_some(),
-            has_gas_limit: request.gas.is_some(),
-            has_gas_price: request.gas_price.is_some(),
-            has_nonce: request.nonce.is_some(),
-            has_storage_limit: false,
-        };
+        let epoch = call_epoch_number(consensus_graph, block_number_or_hash)?;
 
         let chain_id = self.consensus.best_chain_id();
-        let signed_tx = sign_call(chain_id.in_evm_space(), request)?;
+        let (signed_tx, estimate_request) =
+            sign_virtual_call(chain_id.in_evm_space(), request)?;
 
         trace!("call tx {:?}, request {:?}", signed_tx, estimate_request);
-        consensus_graph.call_virtual(&signed_tx, epoch, estimate_request)
+        consensus_graph.call_virtual(
+            &signed_tx,
+            epoch,
+            estimate_request,
+            &overrides,
+        )
     }
 
     fn send_transaction_with_signature(
@@ -435,8 +465,7 @@ impl Eth for EthHandler {
 
     fn hashrate(&self) -> jsonrpc_core::Result<U256> {
         info!("RPC Request: eth_hashrate");
-        // We do not mine
-        Ok(U256::zero())
+        Ok(self.block_gen.hashrate())
     }
 
     fn author(&self) -> jsonrpc_core::Result<H160> {
@@ -531,6 +560,32 @@ impl Eth for EthHandler {
         Ok(acc.map_or(U256::zero(), |acc| acc.balance).into())
     }
 
+    fn proof(
+        &self, address: H160, storage_keys: Vec<H256>,
+        block_num: Option<BlockNumber>,
+    ) -> jsonrpc_core::Result<AccountProof> {
+        let epoch_num = block_num.unwrap_or_default().try_into()?;
+
+        info!(
+            "RPC Request: eth_getProof address={:?} storage_keys={:?} epoch_num={:?}",
+            address, storage_keys, epoch_num
+        );
+
+        let address_with_space = address.with_evm_space();
+        let keys = account_proof_keys(&address_with_space, &storage_keys);
+        let (epoch_number, entries) = self
+            .consensus_graph()
+            .get_state_entries_with_proof(epoch_num, &keys, "block_num")?;
+
+        AccountProof::from_entries(
+            address,
+            &address_with_space,
+            &storage_keys,
+            epoch_number,
+            entries,
+        )
+    }
+
     fn storage_at(
         &self, address: H160, position: U256, block_num: Option<BlockNumber>,
     ) -> jsonrpc_core::Result<H256> {
@@ -790,15 +845,18 @@ impl Eth for EthHandler {
 
     fn call(
         &self, request: CallRequest, block_number_or_hash: Option<BlockNumber>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<BlockOverrides>,
     ) -> jsonrpc_core::Result<Bytes> {
         info!(
-            "RPC Request: eth_call request={:?}, block_num={:?}",
-            request, block_number_or_hash
+            "RPC Request: eth_call request={:?}, block_num={:?}, state_overrides={:?}, block_overrides={:?}",
+            request, block_number_or_hash, state_overrides, block_overrides
         );
+        let overrides = into_call_overrides(state_overrides, block_overrides)?;
         // TODO: EVM core: Check the EVM error message. To make the
         // assert_error_eq test case in solidity project compatible.
         let (execution_outcome, _estimation) =
-            self.exec_transaction(request, block_number_or_hash)?;
+            self.exec_transaction(request, block_number_or_hash, overrides)?;
         match execution_outcome {
             ExecutionOutcome::NotExecutedDrop(TxDropError::OldNonce(
                 expected,
@@ -847,14 +905,17 @@ impl Eth for EthHandler {
 
     fn estimate_gas(
         &self, request: CallRequest, block_number_or_hash: Option<BlockNumber>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<BlockOverrides>,
     ) -> jsonrpc_core::Result<U256> {
         info!(
-            "RPC Request: eth_estimateGas request={:?}, block_num={:?}",
-            request, block_number_or_hash
+            "RPC Request: eth_estimateGas request={:?}, block_num={:?}, state_overrides={:?}, block_overrides={:?}",
+            request, block_number_or_hash, state_overrides, block_overrides
         );
+        let overrides = into_call_overrides(state_overrides, block_overrides)?;
         // TODO: EVM core: same as call
         let (execution_outcome, estimation) =
-            self.exec_transaction(request, block_number_or_hash)?;
+            self.exec_transaction(request, block_number_or_hash, overrides)?;
         match execution_outcome {
             ExecutionOutcome::NotExecutedDrop(TxDropError::OldNonce(
                 expected,
@@ -910,6 +971,86 @@ impl Eth for EthHandler {
         Ok(estimation.estimated_gas_limit)
     }
 
+    fn simulate_v1(
+        &self, payload: SimulatePayload<CallRequest>,
+        block_number_or_hash: Option<BlockNumber>,
+    ) -> jsonrpc_core::Result<Vec<SimulatedBlock>> {
+        info!(
+            "RPC Request: eth_simulateV1 payload={:?}, block_num={:?}",
+            payload, block_number_or_hash
+        );
+        payload.check_size()?;
+
+        let consensus_graph = self.consensus_graph();
+        let epoch = call_epoch_number(consensus_graph, block_number_or_hash)?;
+        let chain_id = self.consensus.best_chain_id().in_evm_space();
+
+        let mut blocks = Vec::with_capacity(payload.block_state_calls.len());
+        for block in payload.block_state_calls {
+            let overrides = into_call_overrides(
+                block.state_overrides,
+                block.block_overrides,
+            )?;
+            let calls = block
+                .calls
+                .into_iter()
+                .map(|request| sign_virtual_call(chain_id, request))
+                .collect::<RpcResult<_>>()?;
+            blocks.push(SimulateBlock { overrides, calls });
+        }
+
+        let simulated = consensus_graph.simulate_virtual(
+            blocks,
+            Space::Ethereum,
+            epoch,
+            payload.validation,
+        )?;
+        simulated
+            .into_iter()
+            .map(|block| {
+                SimulatedBlock::from_primitive(block, Ok, |outcome| {
+                    call_execution_error(
+                        "Transaction can not be executed".into(),
+                        format!("{:?}", outcome),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn create_access_list(
+        &self, mut request: CallRequest,
+        block_number_or_hash: Option<BlockNumber>,
+    ) -> jsonrpc_core::Result<AccessListResult> {
+        info!(
+            "RPC Request: eth_createAccessList request={:?}, block_num={:?}",
+            request, block_number_or_hash
+        );
+        if request.transaction_type == Some(U64::from(LEGACY_TX_TYPE)) {
+            bail!(invalid_params(
+                "transactionType",
+                "legacy transactions do not support access lists"
+            ));
+        }
+        // Make sure the call is signed as a transaction with access list.
+        request.access_list.get_or_insert_with(Vec::new);
+
+        let consensus_graph = self.consensus_graph();
+        let epoch = call_epoch_number(consensus_graph, block_number_or_hash)?;
+        let chain_id = self.consensus.best_chain_id();
+        let (signed_tx, estimate_request) =
+            sign_virtual_call(chain_id.in_evm_space(), request)?;
+
+        let (access_list, outcome) = consensus_graph
+            .create_access_list_virtual(&signed_tx, epoch, estimate_request)?;
+        AccessListResult::from_outcome(access_list, &outcome, |outcome| {
+            call_execution_error(
+                "Transaction can not be executed".into(),
+                format!("{:?}", outcome),
+            )
+        })
+    }
+
     fn fee_history(
         &self, block_count: HexU64, newest_block: BlockNumber,
         reward_percentiles: Vec<f64>,
@@ -1289,4 +1430,20 @@ impl Eth for EthHandler {
             pending_count: pending_count.into(),
         })
     }
+
+    fn transactions_by_address(
+        &self, address: H160, cursor: Option<U64>, limit: Option<U64>,
+    ) -> jsonrpc_core::Result<AddressTransactions<AddressTransaction>> {
+        info!(
+            "RPC Request: eth_getTransactionsByAddress(addr={:?}, cursor={:?}, limit={:?})",
+            address, cursor, limit
+        );
+
+        query_address_transactions(
+            self.consensus_graph(),
+            &Address::from(address).with_evm_space(),
+            cursor,
+            limit,
+        )
+    }
 }
@@ -9,6 +9,7 @@ use crate::rpc::{
     traits::eth_space::eth_pubsub::EthPubSub as PubSub,
     types::eth::{
         eth_pubsub as pubsub, Header as RpcHeader, Log as RpcLog, Log,
+        SyncInfo, Transaction as RpcTransaction,
     },
 };
 use futures::{
@@ -25,14 +26,15 @@ use mazze_parameters::{
     consensus::DEFERRED_STATE_EPOCH_COUNT,
     consensus_internal::REWARD_EPOCH_COUNT,
 };
-use mazze_types::{Space, H256};
+use mazze_types::{Space, H256, U256};
 use mazzecore::{
-    channel::Channel, consensus::PhantomBlock, BlockDataManager,
-    ConsensusGraph, Notifications, SharedConsensusGraph,
+    channel::Channel, consensus::PhantomBlock, sync::SyncPhaseType,
+    BlockDataManager, ConsensusGraph, Notifications, SharedConsensusGraph,
 };
 use parking_lot::RwLock;
 use primitives::{
-    filter::LogFilter, log_entry::LocalizedLogEntry, BlockReceipts, EpochNumber,
+    filter::LogFilter, log_entry::LocalizedLogEntry, BlockReceipts,
+    EpochNumber, SignedTransaction,
 };
 use runtime::Executor;
 use std::{
@@ -50,7 +52,11 @@ pub struct PubSubClient {
     handler: Arc<ChainNotificationHandler>,
     heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
     logs_subscribers: Arc<RwLock<Subscribers<(Client, LogFilter)>>>,
+    pending_transactions_subscribers: Arc<RwLock<Subscribers<(Client, bool)>>>,
+    syncing_subscribers: Arc<RwLock<Subscribers<Client>>>,
     epochs_ordered: Arc<Channel<(u64, Vec<H256>)>>,
+    new_pending_transactions: Arc<Channel<Arc<SignedTransaction>>>,
+    sync_phase_changes: Arc<Channel<(SyncPhaseType, usize)>>,
     consensus: SharedConsensusGraph,
     heads_loop_started: Arc<RwLock<bool>>,
 }
@@ -75,7 +81,15 @@ impl PubSubClient {
             handler,
             heads_subscribers,
             logs_subscribers,
+            pending_transactions_subscribers: Arc::new(RwLock::new(
+                Subscribers::default(),
+            )),
+            syncing_subscribers: Arc::new(RwLock::new(Subscribers::default())),
             epochs_ordered: notifications.epochs_ordered.clone(),
+            new_pending_transactions: notifications
+                .new_pending_transactions
+                .clone(),
+            sync_phase_changes: notifications.sync_phase_changes.clone(),
             consensus: consensus.clone(),
             heads_loop_started: Arc::new(RwLock::new(false)),
         }
@@ -225,6 +239,110 @@ impl PubSubClient {
         let fut = fut.unit_error().boxed().compat();
         self.handler.executor.spawn(fut);
     }
+
+    // Start an async loop that continuously receives the transactions newly
+    // inserted into the transaction pool and publishes the EVM space ones to
+    // subscriber `id`, either in full or as hashes. The loop terminates when
+    // subscriber `id` unsubscribes.
+    fn start_pending_transactions_loop(&self, id: SubscriberId) {
+        trace!("start_pending_transactions_loop({:?})", id);
+
+        // clone everything we use in our async loop
+        let subscribers = self.pending_transactions_subscribers.clone();
+        let new_pending_transactions = self.new_pending_transactions.clone();
+
+        // subscribe to the `new_pending_transactions` channel
+        let mut receiver = new_pending_transactions.subscribe();
+
+        // loop asynchronously
+        let fut = async move {
+            while let Some(tx) = receiver.recv().await {
+                trace!("pending_transactions_loop({:?}): {:?}", id, tx.hash());
+
+                // retrieve subscriber
+                let (sub, full) = match subscribers.read().get(&id) {
+                    Some(sub) => sub.clone(),
+                    None => {
+                        // unsubscribed, terminate loop
+                        new_pending_transactions.unsubscribe(receiver.id);
+                        return;
+                    }
+                };
+
+                if tx.space() != Space::Ethereum {
+                    continue;
+                }
+
+                let result = if full {
+                    pubsub::Result::Transaction(RpcTransaction::from_signed(
+                        &tx,
+                        (None, None, None),
+                        (None, None),
+                    ))
+                } else {
+                    pubsub::Result::TransactionHash(tx.hash())
+                };
+
+                ChainNotificationHandler::notify_async(&sub, result).await;
+            }
+        };
+
+        // run futures@0.3 future on tokio@0.1 executor
+        let fut = fut.unit_error().boxed().compat();
+        self.handler.executor.spawn(fut);
+    }
+
+    // Start an async loop that continuously receives sync phase changes and
+    // publishes the sync status to subscriber `id`. The loop terminates when
+    // subscriber `id` unsubscribes.
+    fn start_syncing_loop(&self, id: SubscriberId) {
+        trace!("start_syncing_loop({:?})", id);
+
+        // clone everything we use in our async loop
+        let subscribers = self.syncing_subscribers.clone();
+        let sync_phase_changes = self.sync_phase_changes.clone();
+        let consensus = self.consensus.clone();
+
+        // subscribe to the `sync_phase_changes` channel
+        let mut receiver = sync_phase_changes.subscribe();
+
+        // loop asynchronously
+        let fut = async move {
+            while let Some((phase, sync_block_count)) = receiver.recv().await {
+                trace!("syncing_loop({:?}): {:?}", id, phase);
+
+                // retrieve subscriber
+                let sub = match subscribers.read().get(&id) {
+                    Some(sub) => sub.clone(),
+                    None => {
+                        // unsubscribed, terminate loop
+                        sync_phase_changes.unsubscribe(receiver.id);
+                        return;
+                    }
+                };
+
+                // same statistics as `eth_syncing`
+                let syncing = phase != SyncPhaseType::Normal;
+                let status = syncing.then(|| SyncInfo {
+                    starting_block: U256::from(consensus.block_count()),
+                    current_block: U256::from(consensus.block_count()),
+                    highest_block: U256::from(sync_block_count),
+                    warp_chunks_amount: None,
+                    warp_chunks_processed: None,
+                });
+
+                ChainNotificationHandler::notify_async(
+                    &sub,
+                    pubsub::Result::Syncing { syncing, status },
+                )
+                .await;
+            }
+        };
+
+        // run futures@0.3 future on tokio@0.1 executor
+        let fut = fut.unit_error().boxed().compat();
+        self.handler.executor.spawn(fut);
+    }
 }
 
 /// PubSub notification handler.
@@ -583,7 +701,45 @@ impl PubSub for PubSubClient {
                 "logs",
                 "Expected filter parameter.",
             ),
-            _ => error_codes::unimplemented(None),
+            // --------- newPendingTransactions ---------
+            (pubsub::Kind::NewPendingTransactions, None) => {
+                info!("eth pubsub newPendingTransactions");
+                let id = self
+                    .pending_transactions_subscribers
+                    .write()
+                    .push(subscriber, false);
+                self.start_pending_transactions_loop(id);
+                return;
+            }
+            (
+                pubsub::Kind::NewPendingTransactions,
+                Some(pubsub::Params::FullTransactions(full)),
+            ) => {
+                info!("eth pubsub newPendingTransactions full={}", full);
+                let id = self
+                    .pending_transactions_subscribers
+                    .write()
+                    .push(subscriber, full);
+                self.start_pending_transactions_loop(id);
+                return;
+            }
+            (pubsub::Kind::NewPendingTransactions, _) => {
+                error_codes::invalid_params(
+                    "newPendingTransactions",
+                    "Expected boolean parameter.",
+                )
+            }
+            // --------- syncing ---------
+            (pubsub::Kind::Syncing, None) => {
+                info!("eth pubsub syncing");
+                let id = self.syncing_subscribers.write().push(subscriber);
+                self.start_syncing_loop(id);
+                return;
+            }
+            (pubsub::Kind::Syncing, _) => error_codes::invalid_params(
+                "syncing",
+                "Expected no parameters.",
+            ),
         };
 
         let _ = subscriber.reject(error);
@@ -594,7 +750,13 @@ impl PubSub for PubSubClient {
     ) -> RpcResult<bool> {
         let res0 = self.heads_subscribers.write().remove(&id).is_some();
         let res1 = self.logs_subscribers.write().remove(&id).is_some();
-
-        Ok(res0 || res1)
+        let res2 = self
+            .pending_transactions_subscribers
+            .write()
+            .remove(&id)
+            .is_some();
+        let res3 = self.syncing_subscribers.write().remove(&id).is_some();
+
+        Ok(res0 || res1 || res2 || res3)
     }
 }
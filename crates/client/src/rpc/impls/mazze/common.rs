@@ -1336,10 +1336,13 @@ impl RpcImpl {
 
         let seed_hash =
             self.data_man.db_manager.get_current_seed_hash(epoch_num);
+        let next_seed_hash =
+            self.data_man.db_manager.get_next_seed_hash(epoch_num);
 
         let obj = serde_json::json!({
             "epochNumber": epoch_num,
             "randomXSeedHash": H256::from(seed_hash),
+            "nextRandomXSeedHash": next_seed_hash.map(H256::from),
             "startBlockHeight": start_block_height,
             "endBlockHeight": end_block_height,
             "nextTransitionBlockHeight": next_transition_block_height,
@@ -35,14 +35,17 @@ use crate::{
         impls::common::{self, RpcImpl as CommonImpl},
         traits::{debug::LocalRpc, mazze::Mazze, test::TestRpc},
         types::{
-            errors::check_rpc_address_network, Account as RpcAccount,
-            AccountPendingInfo, AccountPendingTransactions, BlameInfo,
-            Block as RpcBlock, BlockHashOrEpochNumber, Bytes, CallRequest,
+            errors::check_rpc_address_network, mazze::MazzeAccessList,
+            AccessListResult, Account as RpcAccount, AccountPendingInfo,
+            AccountPendingTransactions, AccountProof, AddressTransaction,
+            AddressTransactions, BlameInfo, Block as RpcBlock,
+            BlockHashOrEpochNumber, BlockOverrides, Bytes, CallRequest,
             CheckBalanceAgainstTransactionResponse, ConsensusGraphStates,
             EpochNumber, EstimateGasAndCollateralResponse, FeeHistory,
             Log as RpcLog, MazzeFeeHistory, MazzeRpcLogFilter,
             Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, RpcAddress,
-            SendTxRequest, SponsorInfo, StatOnGasLoad, Status as RpcStatus,
+            SendTxRequest, SimulatePayload, SimulatedBlock, SponsorInfo,
+            StatOnGasLoad, StateOverride, Status as RpcStatus,
             StorageCollateralInfo, SyncGraphStates, TokenSupplyInfo,
             Transaction as RpcTransaction, WrapTransaction, U64 as HexU64,
         },
@@ -1098,12 +1101,17 @@ impl Mazze for MazzeHandler {
     not_supported! {
         fn account_pending_transactions(&self, address: RpcAddress, maybe_start_nonce: Option<U256>, maybe_limit: Option<U64>) -> BoxFuture<AccountPendingTransactions>;
         fn block_by_block_number(&self, block_number: U64, include_txs: bool) -> BoxFuture<Option<RpcBlock>>;
-        fn call(&self, request: CallRequest, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>) -> JsonRpcResult<Bytes>;
-        fn estimate_gas_and_collateral(&self, request: CallRequest, epoch_num: Option<EpochNumber>) -> JsonRpcResult<EstimateGasAndCollateralResponse>;
+        fn call(&self, request: CallRequest, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>, state_overrides: Option<StateOverride<RpcAddress>>, block_overrides: Option<BlockOverrides<RpcAddress>>) -> JsonRpcResult<Bytes>;
+        fn simulate_v1(&self, payload: SimulatePayload<CallRequest, RpcAddress>, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>) -> JsonRpcResult<Vec<SimulatedBlock<RpcAddress>>>;
+        fn create_access_list(&self, request: CallRequest, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>) -> JsonRpcResult<AccessListResult<MazzeAccessList>>;
+        fn estimate_gas_and_collateral(&self, request: CallRequest, epoch_num: Option<EpochNumber>, state_overrides: Option<StateOverride<RpcAddress>>, block_overrides: Option<BlockOverrides<RpcAddress>>) -> JsonRpcResult<EstimateGasAndCollateralResponse>;
         fn get_block_reward_info(&self, num: EpochNumber) -> JsonRpcResult<Vec<RpcRewardInfo>>;
         fn get_supply_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<TokenSupplyInfo>;
         fn get_collateral_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<StorageCollateralInfo>;
         fn get_fee_burnt(&self, epoch: Option<EpochNumber>) -> JsonRpcResult<U256>;
+        fn hashrate(&self) -> JsonRpcResult<U256>;
+        fn proof(&self, address: RpcAddress, storage_keys: Vec<H256>, epoch_num: Option<EpochNumber>) -> JsonRpcResult<AccountProof<RpcAddress>>;
+        fn transactions_by_address(&self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>) -> JsonRpcResult<AddressTransactions<AddressTransaction>>;
         fn max_priority_fee_per_gas(&self) -> BoxFuture<U256>;
     }
 }
@@ -1202,5 +1210,7 @@ impl LocalRpc for DebugRpcImpl {
         fn sync_graph_state(&self) -> JsonRpcResult<SyncGraphStates>;
         fn transactions_by_epoch(&self, epoch_number: U64) -> JsonRpcResult<Vec<WrapTransaction>>;
         fn transactions_by_block(&self, block_hash: H256) -> JsonRpcResult<Vec<WrapTransaction>>;
+        fn export_snapshot(&self, snapshot_epoch_id: H256, path: String) -> JsonRpcResult<()>;
+        fn export_blocks(&self, from_epoch: U64, to_epoch: U64, path: String) -> JsonRpcResult<U64>;
     }
 }
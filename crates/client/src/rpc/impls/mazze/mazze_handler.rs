@@ -5,10 +5,16 @@
 use crate::rpc::{
     error_codes::{internal_error_msg, invalid_params_msg},
     types::{
+        account_proof_keys,
         call_request::rpc_call_request_network,
-        errors::check_rpc_address_network, MazzeFeeHistory, RpcAddress,
-        SponsorInfo, StatOnGasLoad, StorageCollateralInfo, TokenSupplyInfo,
-        WrapTransaction, U64 as HexU64,
+        errors::check_rpc_address_network,
+        into_call_overrides,
+        mazze::{from_primitive_access_list, MazzeAccessList},
+        query_address_transactions, AccessListResult, AccountProof,
+        AddressTransaction, AddressTransactions, BlockOverrides,
+        MazzeFeeHistory, RpcAddress, SimulatePayload, SimulatedBlock,
+        SponsorInfo, StatOnGasLoad, StateOverride, StorageCollateralInfo,
+        TokenSupplyInfo, WrapTransaction, U64 as HexU64,
     },
 };
 use blockgen::BlockGenerator;
@@ -46,7 +52,8 @@ use random_crash::*;
 use rlp::Rlp;
 use rustc_hex::ToHex;
 use std::{
-    collections::BTreeMap, net::SocketAddr, sync::Arc, thread, time::Duration,
+    collections::BTreeMap, net::SocketAddr, path::Path, sync::Arc, thread,
+    time::Duration,
 };
 use txgen::{DirectTransactionGenerator, TransactionGenerator};
 // To convert from RpcResult to BoxFuture by delegate! macro automatically.
@@ -77,7 +84,10 @@ use crate::{
     },
 };
 use mazze_addr::Network;
-use mazze_execute_helper::estimation::EstimateRequest;
+use mazze_execute_helper::{
+    estimation::EstimateRequest, overrides::CallOverrides,
+    simulation::SimulateBlock,
+};
 use mazze_executor::state::State;
 use mazze_parameters::{
     collateral::MAZZIES_PER_STORAGE_COLLATERAL_UNIT,
@@ -90,7 +100,7 @@ use mazzecore::{
 };
 use primitives::transaction::{
     eth_transaction::EthereumTransaction,
-    native_transaction::TypedNativeTransaction,
+    native_transaction::TypedNativeTransaction, LEGACY_TX_TYPE,
 };
 use serde::Serialize;
 
@@ -107,7 +117,7 @@ pub struct RpcImpl {
     pub config: RpcImplConfiguration,
     pub consensus: SharedConsensusGraph,
     pub sync: SharedSynchronizationService,
-    block_gen: Arc<BlockGenerator>,
+    pub block_gen: Arc<BlockGenerator>,
     pub tx_pool: SharedTransactionPool,
     maybe_txgen: Option<Arc<TransactionGenerator>>,
     maybe_direct_txgen: Option<Arc<Mutex<DirectTransactionGenerator>>>,
@@ -526,6 +536,50 @@ impl RpcImpl {
         Ok(Some(root))
     }
 
+    fn proof(
+        &self, address: RpcAddress, storage_keys: Vec<H256>,
+        epoch_num: Option<EpochNumber>,
+    ) -> RpcResult<AccountProof<RpcAddress>> {
+        self.check_address_network(address.network)?;
+        let epoch_num = epoch_num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: mazze_getProof address={:?} storage_keys={:?} epoch={:?}",
+            address, storage_keys, epoch_num
+        );
+
+        let address_with_space = address.hex_address.with_native_space();
+        let keys = account_proof_keys(&address_with_space, &storage_keys);
+        let (epoch_number, entries) = self
+            .consensus_graph()
+            .get_state_entries_with_proof(epoch_num, &keys, "epoch_num")?;
+
+        Ok(AccountProof::from_entries(
+            address,
+            &address_with_space,
+            &storage_keys,
+            epoch_number,
+            entries,
+        )?)
+    }
+
+    fn transactions_by_address(
+        &self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>,
+    ) -> RpcResult<AddressTransactions<AddressTransaction>> {
+        self.check_address_network(address.network)?;
+        info!(
+            "RPC Request: mazze_getTransactionsByAddress address={:?} cursor={:?} limit={:?}",
+            address, cursor, limit
+        );
+
+        Ok(query_address_transactions(
+            self.consensus_graph(),
+            &address.hex_address.with_native_space(),
+            cursor,
+            limit,
+        )?)
+    }
+
     fn send_usable_genesis_accounts(
         &self, account_start_index: usize,
     ) -> RpcResult<Bytes> {
@@ -1134,12 +1188,16 @@ impl RpcImpl {
     fn call(
         &self, request: CallRequest,
         block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>,
+        state_overrides: Option<StateOverride<RpcAddress>>,
+        block_overrides: Option<BlockOverrides<RpcAddress>>,
     ) -> RpcResult<Bytes> {
         let epoch = Some(
             self.get_epoch_number_with_main_check(block_hash_or_epoch_number)?,
         );
+        let overrides =
+            self.call_overrides(state_overrides, block_overrides)?;
         let (execution_outcome, _estimation) =
-            self.exec_transaction(request, epoch)?;
+            self.exec_transaction(request, epoch, overrides)?;
         match execution_outcome {
             ExecutionOutcome::NotExecutedDrop(TxDropError::OldNonce(
                 expected,
@@ -1185,12 +1243,16 @@ impl RpcImpl {
 
     fn estimate_gas_and_collateral(
         &self, request: CallRequest, epoch: Option<EpochNumber>,
+        state_overrides: Option<StateOverride<RpcAddress>>,
+        block_overrides: Option<BlockOverrides<RpcAddress>>,
     ) -> RpcResult<EstimateGasAndCollateralResponse> {
         info!(
-            "RPC Request: mazze_estimateGasAndCollateral request={:?}, epoch={:?}",request,epoch
+            "RPC Request: mazze_estimateGasAndCollateral request={:?}, epoch={:?}, state_overrides={:?}, block_overrides={:?}",request,epoch,state_overrides,block_overrides
         );
+        let overrides =
+            self.call_overrides(state_overrides, block_overrides)?;
         let (execution_outcome, estimation) =
-            self.exec_transaction(request, epoch)?;
+            self.exec_transaction(request, epoch, overrides)?;
         match execution_outcome {
             ExecutionOutcome::NotExecutedDrop(TxDropError::OldNonce(
                 expected,
@@ -1304,44 +1366,144 @@ impl RpcImpl {
         ))
     }
 
-    fn exec_transaction(
-        &self, request: CallRequest, epoch: Option<EpochNumber>,
-    ) -> RpcResult<(ExecutionOutcome, EstimateExt)> {
-        let rpc_request_network = invalid_params_check(
-            "request",
-            rpc_call_request_network(
-                request.from.as_ref(),
-                request.to.as_ref(),
-            ),
-        )?;
-        invalid_params_check(
-            "request",
-            check_rpc_address_network(
-                rpc_request_network,
-                self.sync.network.get_network_type(),
-            ),
-        )?;
+    fn call_overrides(
+        &self, state_overrides: Option<StateOverride<RpcAddress>>,
+        block_overrides: Option<BlockOverrides<RpcAddress>>,
+    ) -> RpcResult<CallOverrides> {
+        let network = self.sync.network.get_network_type();
+        let addresses = state_overrides
+            .iter()
+            .flat_map(|state| state.keys())
+            .chain(block_overrides.iter().filter_map(|b| b.coinbase.as_ref()));
+        for address in addresses {
+            invalid_params_check(
+                "overrides",
+                check_rpc_address_network(Some(address.network), network),
+            )?;
+        }
+        Ok(into_call_overrides(state_overrides, block_overrides)?)
+    }
 
-        let consensus_graph = self.consensus_graph();
-        let epoch = epoch.unwrap_or(EpochNumber::LatestState);
+    fn simulate_v1(
+        &self, payload: SimulatePayload<CallRequest, RpcAddress>,
+        block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>,
+    ) -> RpcResult<Vec<SimulatedBlock<RpcAddress>>> {
+        info!(
+            "RPC Request: mazze_simulateV1 payload={:?}, epoch={:?}",
+            payload, block_hash_or_epoch_number
+        );
+        payload.check_size()?;
+        let epoch =
+            self.get_epoch_number_with_main_check(block_hash_or_epoch_number)?;
+
+        let mut blocks = Vec::with_capacity(payload.block_state_calls.len());
+        for block in payload.block_state_calls {
+            let overrides = self
+                .call_overrides(block.state_overrides, block.block_overrides)?;
+            let calls = block
+                .calls
+                .into_iter()
+                .map(|request| self.sign_virtual_call(request, &epoch))
+                .collect::<RpcResult<_>>()?;
+            blocks.push(SimulateBlock { overrides, calls });
+        }
 
-        let estimate_request = EstimateRequest {
-            has_sender: request.from.is_some(),
-            has_gas_limit: request.gas.is_some(),
-            has_gas_price: request.gas_price.is_some()
-                || request.max_priority_fee_per_gas.is_some(),
-            has_nonce: request.nonce.is_some(),
-            has_storage_limit: request.storage_limit.is_some(),
+        let simulated = self.consensus_graph().simulate_virtual(
+            blocks,
+            Space::Native,
+            epoch.into(),
+            payload.validation,
+        )?;
+        let network = *self.sync.network.get_network_type();
+        let format_address = |address| {
+            RpcAddress::try_from_h160(address, network)
+                .map_err(|e| internal_error_msg(&e))
         };
+        Ok(simulated
+            .into_iter()
+            .map(|block| {
+                SimulatedBlock::from_primitive(
+                    block,
+                    format_address,
+                    |outcome| {
+                        call_execution_error(
+                            "Transaction can not be executed".into(),
+                            format!("{:?}", outcome),
+                        )
+                    },
+                )
+            })
+            .collect::<JsonRpcResult<_>>()?)
+    }
 
-        let epoch_height = consensus_graph
-            .get_height_from_epoch_number(epoch.clone().into())?;
-        let chain_id = consensus_graph.best_chain_id();
-        let signed_tx =
-            sign_call(epoch_height, chain_id.in_native_space(), request)?;
-        trace!("call tx {:?}", signed_tx);
+    fn create_access_list(
+        &self, mut request: CallRequest,
+        block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>,
+    ) -> RpcResult<AccessListResult<MazzeAccessList>> {
+        info!(
+            "RPC Request: mazze_createAccessList request={:?}, epoch={:?}",
+            request, block_hash_or_epoch_number
+        );
+        if request.transaction_type == Some(U64::from(LEGACY_TX_TYPE)) {
+            bail!(invalid_params(
+                "transactionType",
+                "legacy transactions do not support access lists"
+            ));
+        }
+        // Make sure the call is signed as a transaction with access list.
+        request.access_list.get_or_insert_with(Vec::new);
+
+        let epoch =
+            self.get_epoch_number_with_main_check(block_hash_or_epoch_number)?;
+        let (signed_tx, estimate_request) =
+            self.sign_virtual_call(request, &epoch)?;
+        let (access_list, outcome) =
+            self.consensus_graph().create_access_list_virtual(
+                &signed_tx,
+                epoch.into(),
+                estimate_request,
+            )?;
+
+        let network = *self.sync.network.get_network_type();
+        Ok(AccessListResult::from_outcome(
+            from_primitive_access_list(access_list, network),
+            &outcome,
+            |outcome| {
+                call_execution_error(
+                    "Transaction can not be executed".into(),
+                    format!("{:?}", outcome),
+                )
+            },
+        )?)
+    }
+
+    /// Check the network of a call request and sign it for a virtual
+    /// execution in `epoch`, recording which of its fields were given.
+    fn sign_virtual_call(
+        &self, request: CallRequest, epoch: &EpochNumber,
+    ) -> RpcResult<(SignedTransaction, EstimateRequest)> {
+        sign_virtual_call(
+            self.consensus_graph(),
+            self.sync.network.get_network_type(),
+            request,
+            epoch,
+        )
+    }
 
-        consensus_graph.call_virtual(&signed_tx, epoch.into(), estimate_request)
+    fn exec_transaction(
+        &self, request: CallRequest, epoch: Option<EpochNumber>,
+        overrides: CallOverrides,
+    ) -> RpcResult<(ExecutionOutcome, EstimateExt)> {
+        let epoch = epoch.unwrap_or(EpochNumber::LatestState);
+        let (signed_tx, estimate_request) =
+            self.sign_virtual_call(request, &epoch)?;
+
+        self.consensus_graph().call_virtual(
+            &signed_tx,
+            epoch.into(),
+            estimate_request,
+            &overrides,
+        )
     }
 
     fn current_sync_phase(&self) -> RpcResult<String> {
@@ -1457,6 +1619,11 @@ impl RpcImpl {
         })
     }
 
+    pub fn hashrate(&self) -> RpcResult<U256> {
+        info!("RPC Request: mazze_hashrate");
+        Ok(self.block_gen.hashrate())
+    }
+
     pub fn get_fee_burnt(&self, epoch: Option<EpochNumber>) -> RpcResult<U256> {
         let epoch = epoch.unwrap_or(EpochNumber::LatestState).into();
         let state_db = self
@@ -1907,6 +2074,35 @@ impl RpcImpl {
         self.get_transactions(&block, main, epoch_number)
     }
 
+    fn export_snapshot(
+        &self, snapshot_epoch_id: H256, path: String,
+    ) -> JsonRpcResult<()> {
+        info!(
+            "debug_exportSnapshot snapshot_epoch_id={:?} path={}",
+            snapshot_epoch_id, path
+        );
+        self.sync
+            .export_snapshot_bundle(&snapshot_epoch_id, Path::new(&path))
+            .map_err(internal_error)
+    }
+
+    fn export_blocks(
+        &self, from_epoch: U64, to_epoch: U64, path: String,
+    ) -> JsonRpcResult<U64> {
+        info!(
+            "debug_exportBlocks from_epoch={} to_epoch={} path={}",
+            from_epoch, to_epoch, path
+        );
+        self.sync
+            .export_blocks(
+                from_epoch.as_u64(),
+                to_epoch.as_u64(),
+                Path::new(&path),
+            )
+            .map(U64::from)
+            .map_err(internal_error)
+    }
+
     fn get_transactions(
         &self, blocks: &Vec<Arc<Block>>, main: &Arc<Block>, epoch_number: u64,
     ) -> JsonRpcResult<Vec<WrapTransaction>> {
@@ -2086,6 +2282,39 @@ impl RpcImpl {
 }
 
 #[allow(dead_code)]
+/// Check the network of a call request and sign it for a virtual execution
+/// in `epoch`, recording which of its fields were given.
+pub fn sign_virtual_call(
+    consensus_graph: &ConsensusGraph, network: &Network, request: CallRequest,
+    epoch: &EpochNumber,
+) -> RpcResult<(SignedTransaction, EstimateRequest)> {
+    let rpc_request_network = invalid_params_check(
+        "request",
+        rpc_call_request_network(request.from.as_ref(), request.to.as_ref()),
+    )?;
+    invalid_params_check(
+        "request",
+        check_rpc_address_network(rpc_request_network, network),
+    )?;
+
+    let estimate_request = EstimateRequest {
+        has_sender: request.from.is_some(),
+        has_gas_limit: request.gas.is_some(),
+        has_gas_price: request.gas_price.is_some()
+            || request.max_priority_fee_per_gas.is_some(),
+        has_nonce: request.nonce.is_some(),
+        has_storage_limit: request.storage_limit.is_some(),
+    };
+
+    let epoch_height =
+        consensus_graph.get_height_from_epoch_number(epoch.clone().into())?;
+    let chain_id = consensus_graph.best_chain_id();
+    let signed_tx =
+        sign_call(epoch_height, chain_id.in_native_space(), request)?;
+    trace!("call tx {:?}", signed_tx);
+    Ok((signed_tx, estimate_request))
+}
+
 pub struct MazzeHandler {
     common: Arc<CommonImpl>,
     rpc_impl: Arc<RpcImpl>,
@@ -2145,10 +2374,16 @@ impl Mazze for MazzeHandler {
             fn balance(&self, address: RpcAddress, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>) -> BoxFuture<U256>;
             fn collateral_for_storage(&self, address: RpcAddress, num: Option<EpochNumber>)
                 -> BoxFuture<U256>;
-            fn call(&self, request: CallRequest, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>)
+            fn call(&self, request: CallRequest, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>,
+                state_overrides: Option<StateOverride<RpcAddress>>, block_overrides: Option<BlockOverrides<RpcAddress>>)
                 -> JsonRpcResult<Bytes>;
+            fn simulate_v1(&self, payload: SimulatePayload<CallRequest, RpcAddress>, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>)
+                -> JsonRpcResult<Vec<SimulatedBlock<RpcAddress>>>;
+            fn create_access_list(&self, request: CallRequest, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>)
+                -> JsonRpcResult<AccessListResult<MazzeAccessList>>;
             fn estimate_gas_and_collateral(
-                &self, request: CallRequest, epoch_number: Option<EpochNumber>)
+                &self, request: CallRequest, epoch_number: Option<EpochNumber>,
+                state_overrides: Option<StateOverride<RpcAddress>>, block_overrides: Option<BlockOverrides<RpcAddress>>)
                 -> JsonRpcResult<EstimateGasAndCollateralResponse>;
             fn check_balance_against_transaction(
                 &self, account_addr: RpcAddress, contract_addr: RpcAddress, gas_limit: U256, gas_price: U256, storage_limit: U256, epoch: Option<EpochNumber>,
@@ -2161,9 +2396,14 @@ impl Mazze for MazzeHandler {
             fn transaction_by_hash(&self, hash: H256) -> BoxFuture<Option<RpcTransaction>>;
             fn transaction_receipt(&self, tx_hash: H256) -> BoxFuture<Option<RpcReceipt>>;
             fn storage_root(&self, address: RpcAddress, epoch_num: Option<EpochNumber>) -> BoxFuture<Option<StorageRoot>>;
+            fn proof(&self, address: RpcAddress, storage_keys: Vec<H256>, epoch_num: Option<EpochNumber>)
+                -> JsonRpcResult<AccountProof<RpcAddress>>;
+            fn transactions_by_address(&self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>)
+                -> JsonRpcResult<AddressTransactions<AddressTransaction>>;
             fn get_supply_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<TokenSupplyInfo>;
             fn get_collateral_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<StorageCollateralInfo>;
             fn get_fee_burnt(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<U256>;
+            fn hashrate(&self) -> JsonRpcResult<U256>;
         }
     }
 }
@@ -2274,6 +2514,8 @@ impl LocalRpc for LocalRpcImpl {
             fn sign_transaction(&self, tx: SendTxRequest, password: Option<String>) -> JsonRpcResult<String>;
             fn transactions_by_epoch(&self, epoch_number: U64) -> JsonRpcResult<Vec<WrapTransaction>>;
             fn transactions_by_block(&self, block_hash: H256) -> JsonRpcResult<Vec<WrapTransaction>>;
+            fn export_snapshot(&self, snapshot_epoch_id: H256, path: String) -> JsonRpcResult<()>;
+            fn export_blocks(&self, from_epoch: U64, to_epoch: U64, path: String) -> JsonRpcResult<U64>;
         }
     }
 }
@@ -9,7 +9,7 @@ use crate::rpc::{
     traits::pubsub::PubSub,
     types::{
         pubsub::{self, SubscriptionEpoch},
-        Header as RpcHeader, Log as RpcLog,
+        Header as RpcHeader, Log as RpcLog, Transaction as RpcTransaction,
     },
 };
 use futures::{
@@ -29,11 +29,13 @@ use mazze_parameters::{
 };
 use mazze_types::{Space, H256};
 use mazzecore::{
-    channel::Channel, BlockDataManager, Notifications, SharedConsensusGraph,
+    channel::Channel, sync::SyncPhaseType, BlockDataManager, Notifications,
+    SharedConsensusGraph,
 };
 use parking_lot::RwLock;
 use primitives::{
     filter::LogFilter, log_entry::LocalizedLogEntry, BlockReceipts,
+    SignedTransaction,
 };
 use runtime::Executor;
 use std::{
@@ -51,7 +53,11 @@ pub struct PubSubClient {
     heads_subscribers: Arc<RwLock<Subscribers<Client>>>,
     epochs_subscribers: Arc<RwLock<Subscribers<Client>>>,
     logs_subscribers: Arc<RwLock<Subscribers<(Client, LogFilter)>>>,
+    pending_transactions_subscribers: Arc<RwLock<Subscribers<(Client, bool)>>>,
+    syncing_subscribers: Arc<RwLock<Subscribers<Client>>>,
     epochs_ordered: Arc<Channel<(u64, Vec<H256>)>>,
+    new_pending_transactions: Arc<Channel<Arc<SignedTransaction>>>,
+    sync_phase_changes: Arc<Channel<(SyncPhaseType, usize)>>,
 }
 
 impl PubSubClient {
@@ -91,7 +97,15 @@ impl PubSubClient {
             heads_subscribers,
             epochs_subscribers,
             logs_subscribers,
+            pending_transactions_subscribers: Arc::new(RwLock::new(
+                Subscribers::default(),
+            )),
+            syncing_subscribers: Arc::new(RwLock::new(Subscribers::default())),
             epochs_ordered: notifications.epochs_ordered.clone(),
+            new_pending_transactions: notifications
+                .new_pending_transactions
+                .clone(),
+            sync_phase_changes: notifications.sync_phase_changes.clone(),
         }
     }
 
@@ -220,6 +234,112 @@ impl PubSubClient {
         let fut = fut.unit_error().boxed().compat();
         self.handler.executor.spawn(fut);
     }
+
+    // Start an async loop that continuously receives the transactions newly
+    // inserted into the transaction pool and publishes the native space ones
+    // to subscriber `id`, either in full or as hashes. The loop terminates
+    // when subscriber `id` unsubscribes.
+    fn start_pending_transactions_loop(&self, id: SubscriberId) {
+        trace!("start_pending_transactions_loop({:?})", id);
+
+        // clone everything we use in our async loop
+        let subscribers = self.pending_transactions_subscribers.clone();
+        let new_pending_transactions = self.new_pending_transactions.clone();
+        let network = self.handler.network;
+
+        // subscribe to the `new_pending_transactions` channel
+        let mut receiver = new_pending_transactions.subscribe();
+
+        // loop asynchronously
+        let fut = async move {
+            while let Some(tx) = receiver.recv().await {
+                trace!("pending_transactions_loop({:?}): {:?}", id, tx.hash());
+
+                // retrieve subscriber
+                let (sub, full) = match subscribers.read().get(&id) {
+                    Some(sub) => sub.clone(),
+                    None => {
+                        // unsubscribed, terminate loop
+                        new_pending_transactions.unsubscribe(receiver.id);
+                        return;
+                    }
+                };
+
+                if tx.space() != Space::Native {
+                    continue;
+                }
+
+                let result = if full {
+                    match RpcTransaction::from_signed(&tx, None, network) {
+                        Ok(tx) => pubsub::Result::Transaction(tx),
+                        Err(e) => {
+                            error!(
+                                "Unexpected error while constructing RpcTransaction: {:?}",
+                                e
+                            );
+                            continue;
+                        }
+                    }
+                } else {
+                    pubsub::Result::TransactionHash(tx.hash())
+                };
+
+                ChainNotificationHandler::notify_async(&sub, result).await;
+            }
+        };
+
+        // run futures@0.3 future on tokio@0.1 executor
+        let fut = fut.unit_error().boxed().compat();
+        self.handler.executor.spawn(fut);
+    }
+
+    // Start an async loop that continuously receives sync phase changes and
+    // publishes them, along with the block counts of consensus and of the
+    // sync graph, to subscriber `id`. The loop terminates when subscriber
+    // `id` unsubscribes.
+    fn start_syncing_loop(&self, id: SubscriberId) {
+        trace!("start_syncing_loop({:?})", id);
+
+        // clone everything we use in our async loop
+        let subscribers = self.syncing_subscribers.clone();
+        let sync_phase_changes = self.sync_phase_changes.clone();
+        let consensus = self.handler.consensus.clone();
+
+        // subscribe to the `sync_phase_changes` channel
+        let mut receiver = sync_phase_changes.subscribe();
+
+        // loop asynchronously
+        let fut = async move {
+            while let Some((phase, sync_block_count)) = receiver.recv().await {
+                trace!("syncing_loop({:?}): {:?}", id, phase);
+
+                // retrieve subscriber
+                let sub = match subscribers.read().get(&id) {
+                    Some(sub) => sub.clone(),
+                    None => {
+                        // unsubscribed, terminate loop
+                        sync_phase_changes.unsubscribe(receiver.id);
+                        return;
+                    }
+                };
+
+                ChainNotificationHandler::notify_async(
+                    &sub,
+                    pubsub::Result::Syncing {
+                        syncing: phase != SyncPhaseType::Normal,
+                        phase: phase.into(),
+                        current_block: consensus.block_count().into(),
+                        highest_block: (sync_block_count as u64).into(),
+                    },
+                )
+                .await;
+            }
+        };
+
+        // run futures@0.3 future on tokio@0.1 executor
+        let fut = fut.unit_error().boxed().compat();
+        self.handler.executor.spawn(fut);
+    }
 }
 
 /// PubSub notification handler.
@@ -542,7 +662,42 @@ impl PubSub for PubSubClient {
                 "logs",
                 "Expected filter parameter.",
             ),
-            _ => error_codes::unimplemented(None),
+            // --------- newPendingTransactions ---------
+            (pubsub::Kind::NewPendingTransactions, None) => {
+                let id = self
+                    .pending_transactions_subscribers
+                    .write()
+                    .push(subscriber, false);
+                self.start_pending_transactions_loop(id);
+                return;
+            }
+            (
+                pubsub::Kind::NewPendingTransactions,
+                Some(pubsub::Params::FullTransactions(full)),
+            ) => {
+                let id = self
+                    .pending_transactions_subscribers
+                    .write()
+                    .push(subscriber, full);
+                self.start_pending_transactions_loop(id);
+                return;
+            }
+            (pubsub::Kind::NewPendingTransactions, _) => {
+                error_codes::invalid_params(
+                    "newPendingTransactions",
+                    "Expected boolean parameter.",
+                )
+            }
+            // --------- syncing ---------
+            (pubsub::Kind::Syncing, None) => {
+                let id = self.syncing_subscribers.write().push(subscriber);
+                self.start_syncing_loop(id);
+                return;
+            }
+            (pubsub::Kind::Syncing, _) => error_codes::invalid_params(
+                "syncing",
+                "Expected no parameters.",
+            ),
         };
 
         let _ = subscriber.reject(error);
@@ -554,7 +709,171 @@ impl PubSub for PubSubClient {
         let res0 = self.heads_subscribers.write().remove(&id).is_some();
         let res1 = self.epochs_subscribers.write().remove(&id).is_some();
         let res2 = self.logs_subscribers.write().remove(&id).is_some();
+        let res3 = self
+            .pending_transactions_subscribers
+            .write()
+            .remove(&id)
+            .is_some();
+        let res4 = self.syncing_subscribers.write().remove(&id).is_some();
+
+        Ok(res0 || res1 || res2 || res3 || res4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::futures::Stream;
+    use mazze_types::{Address, AddressSpaceUtil, U256};
+    use mazzecore::{
+        block_data_manager::BlockDbBackend,
+        sync::utils::initialize_synchronization_graph,
+    };
+    use primitives::transaction::{
+        Action, Eip155Transaction, NativeTransaction,
+    };
+    use runtime::Runtime;
+    use serde_json::{json, Value};
+    use tempdir::TempDir;
+
+    struct TestPubSub {
+        pubsub: PubSubClient,
+        notifications: Arc<Notifications>,
+        consensus: SharedConsensusGraph,
+        // Dropped after the pubsub client.
+        _runtime: Runtime,
+        _db_dir: TempDir,
+    }
+
+    fn test_pubsub() -> TestPubSub {
+        let db_dir = TempDir::new("pubsub").unwrap();
+        let (_, consensus, _, _) = initialize_synchronization_graph(
+            db_dir.path().to_str().unwrap(),
+            1,
+            1,
+            1,
+            1,
+            50000,
+            BlockDbBackend::Rocksdb,
+            H256::zero(),
+        );
+        let runtime = Runtime::with_default_thread_count();
+        let notifications = Notifications::init();
+        let pubsub = PubSubClient::new(
+            runtime.executor(),
+            consensus.clone(),
+            notifications.clone(),
+            Network::Main,
+        );
+        TestPubSub {
+            pubsub,
+            notifications,
+            consensus,
+            _runtime: runtime,
+            _db_dir: db_dir,
+        }
+    }
+
+    /// Subscribe to `kind` through `mazze_subscribe`, and return the results
+    /// of the notifications to the subscriber, or the error the subscription
+    /// is rejected with.
+    fn subscribe(
+        pubsub: &PubSubClient, kind: pubsub::Kind,
+        params: Option<pubsub::Params>,
+    ) -> jsonrpc_core::Result<impl Iterator<Item = Value>> {
+        let (subscriber, id, notifications) =
+            Subscriber::new_test("mazze_subscription");
+        pubsub.subscribe(Metadata::default(), subscriber, kind, params);
+        id.wait().unwrap()?;
+        Ok(notifications.wait().map(|notification| {
+            let notification: Value =
+                serde_json::from_str(&notification.unwrap()).unwrap();
+            notification["params"]["result"].clone()
+        }))
+    }
 
-        Ok(res0 || res1 || res2)
+    #[test]
+    fn test_subscribe_new_pending_transactions() {
+        let test = test_pubsub();
+        let mut hashes =
+            subscribe(&test.pubsub, pubsub::Kind::NewPendingTransactions, None)
+                .unwrap();
+
+        let sender = Address::from_low_u64_be(1);
+        let eth_tx = Eip155Transaction {
+            nonce: U256::zero(),
+            gas_price: U256::one(),
+            gas: 21_000.into(),
+            action: Action::Call(Address::from_low_u64_be(2)),
+            value: U256::zero(),
+            chain_id: Some(1),
+            data: vec![],
+        }
+        .fake_sign_rpc(sender.with_evm_space());
+        let native_tx = NativeTransaction {
+            gas: 21_000.into(),
+            action: Action::Call(Address::from_low_u64_be(2)),
+            chain_id: 1,
+            ..Default::default()
+        }
+        .fake_sign(sender.with_native_space());
+
+        // The transactions are published in the order the pool inserts them,
+        // and the eth space ones are skipped.
+        let pending_transactions = &test.notifications.new_pending_transactions;
+        pending_transactions.send(Arc::new(eth_tx));
+        pending_transactions.send(Arc::new(native_tx.clone()));
+        assert_eq!(hashes.next().unwrap(), json!(native_tx.hash()));
+    }
+
+    #[test]
+    fn test_subscribe_syncing() {
+        let test = test_pubsub();
+        let mut statuses =
+            subscribe(&test.pubsub, pubsub::Kind::Syncing, None).unwrap();
+
+        test.notifications
+            .sync_phase_changes
+            .send((SyncPhaseType::CatchUpSyncBlockHeader, 10));
+        test.notifications
+            .sync_phase_changes
+            .send((SyncPhaseType::Normal, 12));
+
+        let block_count = format!("{:#x}", test.consensus.block_count());
+        assert_eq!(
+            statuses.next().unwrap(),
+            json!({
+                "syncing": true,
+                "phase": "syncBlockHeader",
+                "currentBlock": block_count,
+                "highestBlock": "0xa",
+            })
+        );
+        assert_eq!(
+            statuses.next().unwrap(),
+            json!({
+                "syncing": false,
+                "phase": "normal",
+                "currentBlock": block_count,
+                "highestBlock": "0xc",
+            })
+        );
+    }
+
+    #[test]
+    fn test_subscribe_rejects_invalid_params() {
+        let test = test_pubsub();
+        assert!(subscribe(
+            &test.pubsub,
+            pubsub::Kind::Syncing,
+            Some(pubsub::Params::FullTransactions(true)),
+        )
+        .is_err());
+        assert!(subscribe(
+            &test.pubsub,
+            pubsub::Kind::NewPendingTransactions,
+            Some(pubsub::Params::Epochs(SubscriptionEpoch::LatestState)),
+        )
+        .is_err());
     }
 }
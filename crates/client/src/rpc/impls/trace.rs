@@ -6,30 +6,53 @@ use super::super::types::LocalizedBlockTrace;
 use crate::{
     common::delegate_convert::into_jsonrpc_result,
     rpc::{
+        error_codes::{call_execution_error, invalid_params},
+        impls::{
+            eth::eth_handler::{
+                call_epoch_number, sign_virtual_call as sign_eth_virtual_call,
+            },
+            mazze::mazze_handler::sign_virtual_call,
+        },
         traits::{eth_space::trace::Trace as EthTrace, trace::Trace},
         types::{
             eth::{
-                BlockNumber, LocalizedTrace as EthLocalizedTrace,
-                Res as EthRes, TraceFilter as EthTraceFilter,
+                BlockNumber, CallRequest as EthCallRequest,
+                LocalizedTrace as EthLocalizedTrace, Res as EthRes,
+                Reward as EthReward, RewardType as EthRewardType,
+                Trace as EthTraceEntry, TraceFilter as EthTraceFilter,
             },
-            Action as RpcAction, EpochNumber as RpcEpochNumber, EpochTrace,
-            LocalizedTrace as RpcLocalizedTrace, LocalizedTrace,
-            TraceFilter as RpcTraceFilter,
+            trace_options, Action as RpcAction, Bytes, CallRequest,
+            EpochNumber as RpcEpochNumber, EpochTrace,
+            LocalizedTrace as RpcLocalizedTrace, LocalizedTrace, Reward,
+            RpcAddress, StateDiff, TraceFilter as RpcTraceFilter, TraceResults,
+            TraceType,
         },
         RpcResult,
     },
 };
 use jsonrpc_core::{Error as JsonRpcError, Result as JsonRpcResult};
 use mazze_addr::Network;
-use mazze_execute_helper::exec_tracer::TraceFilter as PrimitiveTraceFilter;
+use mazze_execute_helper::{
+    estimation::EstimateRequest,
+    exec_tracer::{
+        Action as VmAction, ExecTrace, Reward as PrimitiveReward,
+        TraceFilter as PrimitiveTraceFilter, TransactionExecTraces,
+    },
+    parity_trace::ParityTrace,
+};
+use mazze_executor::executive::ExecutionOutcome;
+use mazze_parameters::consensus_internal::REWARD_EPOCH_COUNT;
 use mazze_types::{Space, H256};
 use mazzecore::{
-    block_data_manager::DataVersionTuple, BlockDataManager, ConsensusGraph,
+    block_data_manager::DataVersionTuple, consensus::PhantomBlock,
+    rpc_errors::invalid_params_check, BlockDataManager, ConsensusGraph,
     ConsensusGraphTrait, SharedConsensusGraph,
 };
-use primitives::EpochNumber;
+use primitives::{EpochNumber, SignedTransaction, TransactionWithSignature};
 use std::{convert::TryInto, sync::Arc};
 
+type MazzeTraceResults = TraceResults<LocalizedTrace, RpcAddress>;
+
 macro_rules! unwrap_or_return {
     ($e:ident) => {
         let $e = match $e {
@@ -85,7 +108,7 @@ impl TraceHandler {
                     .data_man
                     .block_height_by_hash(&main_hash)
                     .ok_or("main block missing")?;
-                match LocalizedBlockTrace::from(
+                let mut block_trace = match LocalizedBlockTrace::from(
                     traces,
                     block_hash,
                     main_hash,
@@ -93,12 +116,17 @@ impl TraceHandler {
                     &block.transactions,
                     self.network,
                 ) {
-                    Ok(t) => Ok(Some(t)),
+                    Ok(t) => t,
                     Err(e) => bail!(format!(
                         "Traces not found for block {:?}: {:?}",
                         block_hash, e
                     )),
-                }
+                };
+                block_trace.reward = self
+                    .block_reward(&block_hash, epoch_number)
+                    .map(|reward| Reward::try_from(reward, self.network))
+                    .transpose()?;
+                Ok(Some(block_trace))
             }
         }
     }
@@ -227,7 +255,7 @@ impl TraceHandler {
             epoch,
             epoch_hash,
         )?;
-        let traces = consensus
+        let mut traces: Vec<_> = consensus
             .filter_block_traces(&trace_filter, block_traces)?
             .into_iter()
             .map(|trace| {
@@ -235,8 +263,225 @@ impl TraceHandler {
                     .expect("Local address conversion should succeed")
             })
             .collect();
+
+        // Block rewards are given in the native space.
+        if space == Space::Native {
+            for (block_hash, reward) in self.epoch_rewards(epoch)? {
+                traces.push(RpcLocalizedTrace {
+                    action: RpcAction::try_from(
+                        VmAction::Reward(reward),
+                        self.network,
+                    )?,
+                    valid: true,
+                    epoch_hash: Some(epoch_hash),
+                    epoch_number: Some(epoch.into()),
+                    block_hash: Some(block_hash),
+                    transaction_position: None,
+                    transaction_hash: None,
+                });
+            }
+        }
         Ok(traces)
     }
+
+    /// The rewards of the blocks in the epoch `epoch_number` which have been
+    /// computed, with the block hashes.
+    fn epoch_rewards(
+        &self, epoch_number: u64,
+    ) -> RpcResult<Vec<(H256, PrimitiveReward)>> {
+        let block_hashes = self
+            .consensus
+            .get_block_hashes_by_epoch(EpochNumber::Number(epoch_number))?;
+        Ok(block_hashes
+            .into_iter()
+            .filter_map(|block_hash| {
+                self.block_reward(&block_hash, epoch_number)
+                    .map(|reward| (block_hash, reward))
+            })
+            .collect())
+    }
+
+    /// The reward of a block in the epoch `epoch_number`. It is computed when
+    /// executing the epoch `REWARD_EPOCH_COUNT` later, so it is `None` before
+    /// that.
+    fn block_reward(
+        &self, block_hash: &H256, epoch_number: u64,
+    ) -> Option<PrimitiveReward> {
+        let epoch_later = self
+            .consensus
+            .get_hash_from_epoch_number(EpochNumber::Number(
+                epoch_number.checked_add(REWARD_EPOCH_COUNT)?,
+            ))
+            .ok()?;
+        let reward_result =
+            self.data_man.block_reward_result_by_hash_with_epoch(
+                block_hash,
+                &epoch_later,
+                false, /* update_main_assumption */
+                true,  /* update_cache */
+            )?;
+        let header = self.data_man.block_header_by_hash(block_hash)?;
+        Some(PrimitiveReward {
+            author: *header.author(),
+            value: reward_result.total_reward,
+        })
+    }
+}
+
+impl TraceHandler {
+    fn call_many_impl(
+        &self, requests: Vec<(CallRequest, Vec<TraceType>)>,
+        epoch: Option<RpcEpochNumber>,
+    ) -> RpcResult<Vec<MazzeTraceResults>> {
+        let epoch = epoch.unwrap_or(RpcEpochNumber::LatestState);
+        let mut calls = Vec::with_capacity(requests.len());
+        for (request, trace_types) in requests {
+            let call = sign_virtual_call(
+                self.consensus_graph(),
+                &self.network,
+                request,
+                &epoch,
+            )?;
+            calls.push((call, trace_types));
+        }
+        self.trace_calls(calls, epoch.into_primitive())
+    }
+
+    fn raw_transaction_impl(
+        &self, raw: Bytes, trace_types: Vec<TraceType>,
+        epoch: Option<RpcEpochNumber>,
+    ) -> RpcResult<MazzeTraceResults> {
+        let call = decode_raw_transaction(raw, Space::Native)?;
+        let epoch = epoch.unwrap_or(RpcEpochNumber::LatestState);
+        let mut results = self
+            .trace_calls(vec![(call, trace_types)], epoch.into_primitive())?;
+        Ok(results.pop().expect("one call is traced"))
+    }
+
+    fn trace_calls(
+        &self,
+        calls: Vec<((SignedTransaction, EstimateRequest), Vec<TraceType>)>,
+        epoch: EpochNumber,
+    ) -> RpcResult<Vec<MazzeTraceResults>> {
+        let all_types: Vec<TraceType> =
+            calls.iter().flat_map(|(_, types)| types.clone()).collect();
+        let (txs, trace_types): (Vec<_>, Vec<_>) = calls.into_iter().unzip();
+        let results = self.consensus_graph().call_virtual_parity_trace(
+            txs,
+            Space::Native,
+            epoch,
+            trace_options(&all_types),
+        )?;
+
+        results
+            .into_iter()
+            .zip(trace_types)
+            .map(|((outcome, trace), trace_types)| {
+                check_executed(&outcome)?;
+                self.trace_results(trace, &trace_types, None)
+            })
+            .collect()
+    }
+
+    fn replay_transaction_impl(
+        &self, tx_hash: H256, trace_types: Vec<TraceType>,
+    ) -> RpcResult<Option<MazzeTraceResults>> {
+        let tx_index = match self
+            .data_man
+            .transaction_index_by_hash(&tx_hash, false /* update_cache */)
+        {
+            Some(tx_index) if !tx_index.is_phantom => tx_index,
+            _ => return Ok(None),
+        };
+        let epoch_num =
+            match self.consensus.get_block_epoch_number(&tx_index.block_hash) {
+                Some(epoch_num) => epoch_num,
+                None => return Ok(None),
+            };
+
+        let traces = self.consensus_graph().collect_epoch_parity_trace(
+            epoch_num,
+            Some(tx_hash),
+            trace_options(&trace_types),
+        )?;
+        traces
+            .into_iter()
+            .find(|t| t.tx_hash == tx_hash && t.space == Space::Native)
+            .map(|t| self.trace_results(t.trace, &trace_types, None))
+            .transpose()
+    }
+
+    fn replay_block_transactions_impl(
+        &self, block_hash: H256, trace_types: Vec<TraceType>,
+    ) -> RpcResult<Option<Vec<MazzeTraceResults>>> {
+        let epoch_num = match self.consensus.get_block_epoch_number(&block_hash)
+        {
+            Some(epoch_num) => epoch_num,
+            None => return Ok(None),
+        };
+
+        let traces = self.consensus_graph().collect_epoch_parity_trace(
+            epoch_num,
+            None,
+            trace_options(&trace_types),
+        )?;
+        traces
+            .into_iter()
+            .filter(|t| t.block_hash == block_hash && t.space == Space::Native)
+            .map(|t| self.trace_results(t.trace, &trace_types, Some(t.tx_hash)))
+            .collect::<RpcResult<_>>()
+            .map(Some)
+    }
+
+    /// Convert the native space parts of a parity-style trace, keeping the
+    /// requested outputs only.
+    fn trace_results(
+        &self, trace: ParityTrace, trace_types: &[TraceType],
+        transaction_hash: Option<H256>,
+    ) -> RpcResult<MazzeTraceResults> {
+        let traces = if trace_types.contains(&TraceType::Trace) {
+            TransactionExecTraces::from(trace.traces)
+                .filter_space(Space::Native)
+                .0
+                .into_iter()
+                .map(|trace| {
+                    Ok(RpcLocalizedTrace {
+                        action: RpcAction::try_from(
+                            trace.action,
+                            self.network,
+                        )?,
+                        valid: trace.valid,
+                        epoch_hash: None,
+                        epoch_number: None,
+                        block_hash: None,
+                        transaction_position: None,
+                        transaction_hash: None,
+                    })
+                })
+                .collect::<Result<_, String>>()?
+        } else {
+            vec![]
+        };
+        let state_diff = match trace.state_diff {
+            Some(diffs) if trace_types.contains(&TraceType::StateDiff) => {
+                Some(StateDiff::new(diffs, Space::Native, |address| {
+                    RpcAddress::try_from_h160(address, self.network)
+                })?)
+            }
+            _ => None,
+        };
+
+        Ok(TraceResults {
+            output: trace.output.into(),
+            trace: traces,
+            vm_trace: trace
+                .vm_trace
+                .filter(|_| trace_types.contains(&TraceType::VmTrace))
+                .map(Into::into),
+            state_diff,
+            transaction_hash,
+        })
+    }
 }
 
 impl Trace for TraceHandler {
@@ -262,6 +507,44 @@ impl Trace for TraceHandler {
     fn epoch_traces(&self, epoch: RpcEpochNumber) -> JsonRpcResult<EpochTrace> {
         into_jsonrpc_result(self.epoch_trace_impl(epoch.into_primitive()))
     }
+
+    fn call(
+        &self, request: CallRequest, trace_types: Vec<TraceType>,
+        epoch: Option<RpcEpochNumber>,
+    ) -> JsonRpcResult<MazzeTraceResults> {
+        let mut results = into_jsonrpc_result(
+            self.call_many_impl(vec![(request, trace_types)], epoch),
+        )?;
+        Ok(results.pop().expect("one call is traced"))
+    }
+
+    fn call_many(
+        &self, requests: Vec<(CallRequest, Vec<TraceType>)>,
+        epoch: Option<RpcEpochNumber>,
+    ) -> JsonRpcResult<Vec<MazzeTraceResults>> {
+        into_jsonrpc_result(self.call_many_impl(requests, epoch))
+    }
+
+    fn raw_transaction(
+        &self, raw: Bytes, trace_types: Vec<TraceType>,
+        epoch: Option<RpcEpochNumber>,
+    ) -> JsonRpcResult<MazzeTraceResults> {
+        into_jsonrpc_result(self.raw_transaction_impl(raw, trace_types, epoch))
+    }
+
+    fn replay_transaction(
+        &self, tx_hash: H256, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<MazzeTraceResults>> {
+        into_jsonrpc_result(self.replay_transaction_impl(tx_hash, trace_types))
+    }
+
+    fn replay_block_transactions(
+        &self, block_hash: H256, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<Vec<MazzeTraceResults>>> {
+        into_jsonrpc_result(
+            self.replay_block_transactions_impl(block_hash, trace_types),
+        )
+    }
 }
 
 pub struct EthTraceHandler {
@@ -293,50 +576,14 @@ impl EthTrace for EthTraceHandler {
 
         unwrap_or_return!(phantom_block);
 
-        let mut eth_traces = Vec::new();
-        let block_number = phantom_block.main_header.height();
-        let block_hash = phantom_block.main_header.hash();
-
-        for (idx, tx_traces) in phantom_block.traces.into_iter().enumerate() {
-            let tx_hash = phantom_block.transactions[idx].hash();
-
-            for (action, result, subtraces) in
-                PrimitiveTraceFilter::space_filter(Space::Ethereum)
-                    .filter_trace_pairs(tx_traces)
-                    .map_err(|_| JsonRpcError::internal_error())?
-            {
-                let mut eth_trace = EthLocalizedTrace {
-                    action: RpcAction::try_from(
-                        action.action,
-                        self.trace_handler.network,
-                    )
-                    .map_err(|_| JsonRpcError::internal_error())?
-                    .try_into()
-                    .map_err(|_| JsonRpcError::internal_error())?,
-                    result: EthRes::None,
-                    trace_address: vec![],
-                    subtraces,
-                    transaction_position: Some(idx),
-                    transaction_hash: Some(tx_hash),
-                    block_number,
-                    block_hash,
-                    // action and its result should have the same `valid`.
-                    valid: action.valid,
-                };
-
-                eth_trace.set_result(
-                    RpcAction::try_from(
-                        result.action,
-                        self.trace_handler.network,
-                    )
-                    .map_err(|_| JsonRpcError::internal_error())?,
-                )?;
-
-                eth_traces.push(eth_trace);
-            }
-        }
-
-        Ok(Some(eth_traces))
+        let rewards = self
+            .trace_handler
+            .epoch_rewards(phantom_block.main_header.height())?;
+        Ok(Some(eth_block_traces(
+            phantom_block,
+            rewards,
+            self.trace_handler.network,
+        )?))
     }
 
     fn filter_traces(
@@ -432,6 +679,252 @@ impl EthTrace for EthTraceHandler {
 
         Ok(Some(eth_traces))
     }
+
+    fn call(
+        &self, request: EthCallRequest, trace_types: Vec<TraceType>,
+        block_number: Option<BlockNumber>,
+    ) -> JsonRpcResult<TraceResults<EthTraceEntry>> {
+        let mut results =
+            self.call_many(vec![(request, trace_types)], block_number)?;
+        Ok(results.pop().expect("one call is traced"))
+    }
+
+    fn call_many(
+        &self, requests: Vec<(EthCallRequest, Vec<TraceType>)>,
+        block_number: Option<BlockNumber>,
+    ) -> JsonRpcResult<Vec<TraceResults<EthTraceEntry>>> {
+        let chain_id = self.trace_handler.consensus.best_chain_id();
+        let calls = requests
+            .into_iter()
+            .map(|(request, trace_types)| {
+                Ok((
+                    sign_eth_virtual_call(chain_id.in_evm_space(), request)?,
+                    trace_types,
+                ))
+            })
+            .collect::<JsonRpcResult<_>>()?;
+        self.trace_calls(calls, block_number)
+    }
+
+    fn raw_transaction(
+        &self, raw: Bytes, trace_types: Vec<TraceType>,
+        block_number: Option<BlockNumber>,
+    ) -> JsonRpcResult<TraceResults<EthTraceEntry>> {
+        let call = decode_raw_transaction(raw, Space::Ethereum)?;
+        let mut results =
+            self.trace_calls(vec![(call, trace_types)], block_number)?;
+        Ok(results.pop().expect("one call is traced"))
+    }
+
+    fn replay_transaction(
+        &self, tx_hash: H256, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<TraceResults<EthTraceEntry>>> {
+        let tx_index = self
+            .trace_handler
+            .data_man
+            .transaction_index_by_hash(&tx_hash, false /* update_cache */);
+
+        unwrap_or_return!(tx_index);
+
+        // Phantom transactions are not executed on their own.
+        if tx_index.is_phantom {
+            return Ok(None);
+        }
+
+        let epoch_num = self
+            .trace_handler
+            .consensus
+            .get_block_epoch_number(&tx_index.block_hash);
+
+        unwrap_or_return!(epoch_num);
+
+        let traces = self
+            .trace_handler
+            .consensus_graph()
+            .collect_epoch_parity_trace(
+                epoch_num,
+                Some(tx_hash),
+                trace_options(&trace_types),
+            )?;
+        traces
+            .into_iter()
+            .find(|t| t.tx_hash == tx_hash && t.space == Space::Ethereum)
+            .map(|t| self.trace_results(t.trace, &trace_types, None))
+            .transpose()
+    }
+
+    fn replay_block_transactions(
+        &self, block_number: BlockNumber, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<Vec<TraceResults<EthTraceEntry>>>> {
+        let consensus_graph = self.trace_handler.consensus_graph();
+        let epoch = call_epoch_number(consensus_graph, Some(block_number))?;
+        let epoch_num = consensus_graph
+            .get_height_from_epoch_number(epoch)
+            .map_err(|msg| invalid_params("block_number", msg))?;
+
+        let traces = consensus_graph.collect_epoch_parity_trace(
+            epoch_num,
+            None,
+            trace_options(&trace_types),
+        )?;
+        traces
+            .into_iter()
+            .filter(|t| t.space == Space::Ethereum)
+            .map(|t| self.trace_results(t.trace, &trace_types, Some(t.tx_hash)))
+            .collect::<JsonRpcResult<_>>()
+            .map(Some)
+    }
+}
+
+impl EthTraceHandler {
+    fn trace_calls(
+        &self,
+        calls: Vec<((SignedTransaction, EstimateRequest), Vec<TraceType>)>,
+        block_number: Option<BlockNumber>,
+    ) -> JsonRpcResult<Vec<TraceResults<EthTraceEntry>>> {
+        let consensus_graph = self.trace_handler.consensus_graph();
+        let epoch = call_epoch_number(consensus_graph, block_number)?;
+
+        let all_types: Vec<TraceType> =
+            calls.iter().flat_map(|(_, types)| types.clone()).collect();
+        let (txs, trace_types): (Vec<_>, Vec<_>) = calls.into_iter().unzip();
+        let results = consensus_graph.call_virtual_parity_trace(
+            txs,
+            Space::Ethereum,
+            epoch,
+            trace_options(&all_types),
+        )?;
+
+        results
+            .into_iter()
+            .zip(trace_types)
+            .map(|((outcome, trace), trace_types)| {
+                check_executed(&outcome)?;
+                self.trace_results(trace, &trace_types, None)
+            })
+            .collect()
+    }
+
+    /// Convert the eth space parts of a parity-style trace, keeping the
+    /// requested outputs only.
+    fn trace_results(
+        &self, trace: ParityTrace, trace_types: &[TraceType],
+        transaction_hash: Option<H256>,
+    ) -> JsonRpcResult<TraceResults<EthTraceEntry>> {
+        let traces = if trace_types.contains(&TraceType::Trace) {
+            self.to_eth_trace_entries(trace.traces)?
+        } else {
+            vec![]
+        };
+        let state_diff = match trace.state_diff {
+            Some(diffs) if trace_types.contains(&TraceType::StateDiff) => Some(
+                StateDiff::new(diffs, Space::Ethereum, Ok::<_, JsonRpcError>)?,
+            ),
+            _ => None,
+        };
+
+        Ok(TraceResults {
+            output: trace.output.into(),
+            trace: traces,
+            vm_trace: trace
+                .vm_trace
+                .filter(|_| trace_types.contains(&TraceType::VmTrace))
+                .map(Into::into),
+            state_diff,
+            transaction_hash,
+        })
+    }
+
+    fn to_eth_trace_entries(
+        &self, traces: Vec<ExecTrace>,
+    ) -> JsonRpcResult<Vec<EthTraceEntry>> {
+        let trace_pairs = PrimitiveTraceFilter::space_filter(Space::Ethereum)
+            .filter_trace_pairs(traces.into())
+            .map_err(|_| JsonRpcError::internal_error())?;
+        let trace_addresses =
+            trace_addresses(trace_pairs.iter().map(|(_, _, n)| *n));
+
+        trace_pairs
+            .into_iter()
+            .zip(trace_addresses)
+            .map(|((action, result, subtraces), trace_address)| {
+                EthTraceEntry::new(
+                    RpcAction::try_from(
+                        action.action,
+                        self.trace_handler.network,
+                    )
+                    .map_err(|_| JsonRpcError::internal_error())?
+                    .try_into()
+                    .map_err(|_| JsonRpcError::internal_error())?,
+                    RpcAction::try_from(
+                        result.action,
+                        self.trace_handler.network,
+                    )
+                    .map_err(|_| JsonRpcError::internal_error())?,
+                    trace_address,
+                    subtraces,
+                )
+            })
+            .collect()
+    }
+}
+
+/// The traces of the transactions in the Ethereum space of `phantom_block`,
+/// followed by the rewards of the blocks in its epoch.
+fn eth_block_traces(
+    phantom_block: PhantomBlock, rewards: Vec<(H256, PrimitiveReward)>,
+    network: Network,
+) -> JsonRpcResult<Vec<EthLocalizedTrace>> {
+    let mut eth_traces = Vec::new();
+    let block_number = phantom_block.main_header.height();
+    let block_hash = phantom_block.main_header.hash();
+
+    for (idx, tx_traces) in phantom_block.traces.into_iter().enumerate() {
+        let tx_hash = phantom_block.transactions[idx].hash();
+
+        for (action, result, subtraces) in
+            PrimitiveTraceFilter::space_filter(Space::Ethereum)
+                .filter_trace_pairs(tx_traces)
+                .map_err(|_| JsonRpcError::internal_error())?
+        {
+            let mut eth_trace = EthLocalizedTrace {
+                action: RpcAction::try_from(action.action, network)
+                    .map_err(|_| JsonRpcError::internal_error())?
+                    .try_into()
+                    .map_err(|_| JsonRpcError::internal_error())?,
+                result: EthRes::None,
+                trace_address: vec![],
+                subtraces,
+                transaction_position: Some(idx),
+                transaction_hash: Some(tx_hash),
+                block_number,
+                block_hash,
+                // action and its result should have the same `valid`.
+                valid: action.valid,
+            };
+
+            eth_trace.set_result(
+                RpcAction::try_from(result.action, network)
+                    .map_err(|_| JsonRpcError::internal_error())?,
+            )?;
+
+            eth_traces.push(eth_trace);
+        }
+    }
+
+    for (_, reward) in rewards {
+        eth_traces.push(EthLocalizedTrace::reward(
+            EthReward {
+                author: reward.author,
+                value: reward.value,
+                reward_type: EthRewardType::Block,
+            },
+            block_number,
+            block_hash,
+        ));
+    }
+
+    Ok(eth_traces)
 }
 
 fn to_eth_traces(
@@ -465,7 +958,17 @@ fn to_eth_traces(
                 eth_traces[index].subtraces =
                     sublen_stack.pop().expect("stack_index matches");
             }
-            RpcAction::InternalTransferAction(_) => {}
+            RpcAction::Suicide(_) => {
+                if let Some(parent_subtraces) = sublen_stack.last_mut() {
+                    *parent_subtraces += 1;
+                }
+
+                eth_traces.push(trace.try_into().map_err(|e| {
+                    error!("eth trace conversion error: {:?}", e);
+                    JsonRpcError::internal_error()
+                })?);
+            }
+            RpcAction::InternalTransferAction(_) | RpcAction::Reward(_) => {}
         }
     }
 
@@ -476,3 +979,126 @@ fn to_eth_traces(
 
     Ok(eth_traces)
 }
+
+/// Decode a signed transaction of `space` to execute it as a virtual call
+/// with all its fields given.
+fn decode_raw_transaction(
+    raw: Bytes, space: Space,
+) -> RpcResult<(SignedTransaction, EstimateRequest)> {
+    let tx: TransactionWithSignature = invalid_params_check(
+        "raw",
+        TransactionWithSignature::from_raw(&raw.into_vec()),
+    )?;
+    if tx.space() != space {
+        bail!(invalid_params("raw", "Incorrect transaction space"));
+    }
+    let public = invalid_params_check("raw", tx.recover_public())?;
+
+    let estimate_request = EstimateRequest {
+        has_sender: true,
+        has_gas_limit: true,
+        has_gas_price: true,
+        has_nonce: true,
+        has_storage_limit: space == Space::Native,
+    };
+    Ok((SignedTransaction::new(public, tx), estimate_request))
+}
+
+/// Fail if the call is not executed at all, as it has no traces.
+fn check_executed(outcome: &ExecutionOutcome) -> JsonRpcResult<()> {
+    match outcome {
+        ExecutionOutcome::NotExecutedDrop(e) => Err(call_execution_error(
+            "Transaction can not be executed".into(),
+            format!("{:?}", e),
+        )),
+        ExecutionOutcome::NotExecutedToReconsiderPacking(e) => {
+            Err(call_execution_error(
+                "Transaction can not be executed".into(),
+                format!("{:?}", e),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The positions of the traces in the call tree, given the number of
+/// subtraces of each trace in pre-order.
+fn trace_addresses(subtraces: impl Iterator<Item = usize>) -> Vec<Vec<usize>> {
+    // The open traces with their addresses, the numbers of their subtraces
+    // left and visited.
+    let mut stack: Vec<(Vec<usize>, usize, usize)> = Vec::new();
+    let mut addresses = Vec::new();
+    for n in subtraces {
+        while matches!(stack.last(), Some((_, 0, _))) {
+            stack.pop();
+        }
+        let address = match stack.last_mut() {
+            Some((parent, left, visited)) => {
+                let mut address = parent.clone();
+                address.push(*visited);
+                *left -= 1;
+                *visited += 1;
+                address
+            }
+            None => vec![],
+        };
+        if n > 0 {
+            stack.push((address.clone(), n, 0));
+        }
+        addresses.push(address);
+    }
+    addresses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{eth_block_traces, trace_addresses, PrimitiveReward};
+    use mazze_addr::Network;
+    use mazze_types::{Address, H256, U256};
+    use mazzecore::consensus::PhantomBlock;
+    use primitives::BlockHeaderBuilder;
+
+    #[test]
+    fn test_trace_addresses() {
+        // 0 -> [1 -> [2], 3 -> [4, 5]]
+        let subtraces = vec![2, 1, 0, 2, 0, 0];
+        assert_eq!(
+            trace_addresses(subtraces.into_iter()),
+            vec![vec![], vec![0], vec![0, 0], vec![1], vec![1, 0], vec![1, 1],]
+        );
+    }
+
+    #[test]
+    fn test_eth_block_traces_with_rewards() {
+        let main_header = BlockHeaderBuilder::new().with_height(3).build();
+        let block_hash = main_header.hash();
+        let phantom_block = PhantomBlock {
+            main_header,
+            transactions: vec![],
+            receipts: vec![],
+            errors: vec![],
+            bloom: Default::default(),
+            traces: vec![],
+        };
+        let author = Address::from_low_u64_be(1);
+        let rewards = vec![(
+            H256::from_low_u64_be(2),
+            PrimitiveReward {
+                author,
+                value: U256::from(100),
+            },
+        )];
+
+        let traces =
+            eth_block_traces(phantom_block, rewards, Network::Main).unwrap();
+        assert_eq!(traces.len(), 1);
+        let json = serde_json::to_value(&traces[0]).unwrap();
+        assert_eq!(json["type"], "reward");
+        assert_eq!(json["action"]["author"], format!("{:?}", author));
+        assert_eq!(json["action"]["value"], "0x64");
+        assert_eq!(json["action"]["rewardType"], "block");
+        assert_eq!(json["blockNumber"], 3);
+        assert_eq!(json["blockHash"], format!("{:?}", block_hash));
+        assert_eq!(json["transactionHash"], serde_json::Value::Null);
+    }
+}
@@ -1,7 +1,9 @@
-use crate::rpc::types::eth::{BlockNumber, CallRequest};
+use crate::rpc::types::{
+    eth::{BlockNumber, CallRequest},
+    GethDebugTracingCallOptions,
+};
 use alloy_rpc_types_trace::geth::{
-    GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace,
-    TraceResult,
+    GethDebugTracingOptions, GethTrace, TraceResult,
 };
 use jsonrpc_core::Result as JsonRpcResult;
 use jsonrpc_derive::rpc;
@@ -33,6 +35,8 @@ pub trait Debug {
         &self, block: BlockNumber, opts: Option<GethDebugTracingOptions>,
     ) -> JsonRpcResult<Vec<TraceResult>>;
 
+    /// The `debug_traceCall` method executes a call against the state of the
+    /// given block, like `eth_call`, and returns its trace.
     #[rpc(name = "debug_traceCall")]
     fn debug_trace_call(
         &self, request: CallRequest, block_number: Option<BlockNumber>,
@@ -26,10 +26,12 @@ use mazze_types::{H128, H160, H256, U256, U64};
 
 use crate::rpc::types::{
     eth::{
-        AccountPendingTransactions, Block, BlockNumber, CallRequest,
-        EthRpcLogFilter, FilterChanges, Log, Receipt, SyncStatus, Transaction,
+        AccountPendingTransactions, AddressTransaction, Block, BlockNumber,
+        CallRequest, EthRpcLogFilter, FilterChanges, Log, Receipt, SyncStatus,
+        Transaction,
     },
-    Bytes, FeeHistory, Index,
+    AccessListResult, AccountProof, AddressTransactions, BlockOverrides, Bytes,
+    FeeHistory, Index, SimulatePayload, SimulatedBlock, StateOverride,
 };
 
 /// Eth rpc interface.
@@ -95,10 +97,13 @@ pub trait Eth {
         &self, address: H160, block: Option<BlockNumber>,
     ) -> Result<U256>;
 
-    // /// Returns the account- and storage-values of the specified account
-    // including the Merkle-proof #[rpc(name = "eth_getProof")]
-    // fn proof(&self, _: H160, _: Vec<H256>, _: Option<BlockNumber>) ->
-    // BoxFuture<EthAccount>;
+    /// Returns the account- and storage-values of the specified account
+    /// including the Merkle-proof. See `AccountProof` for the proof format.
+    #[rpc(name = "eth_getProof")]
+    fn proof(
+        &self, address: H160, storage_keys: Vec<H256>,
+        block: Option<BlockNumber>,
+    ) -> Result<AccountProof>;
 
     /// Returns content of the storage at given address.
     #[rpc(name = "eth_getStorageAt")]
@@ -163,18 +168,38 @@ pub trait Eth {
     #[rpc(name = "eth_submitTransaction")]
     fn submit_transaction(&self, transaction: Bytes) -> Result<H256>;
 
-    /// Call contract, returning the output data.
+    /// Call contract, returning the output data. The optional overrides are
+    /// applied to the state and block of the call only.
     #[rpc(name = "eth_call")]
     fn call(
         &self, transaction: CallRequest, block: Option<BlockNumber>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<BlockOverrides>,
     ) -> Result<Bytes>;
 
     /// Estimate gas needed for execution of given contract.
     #[rpc(name = "eth_estimateGas")]
     fn estimate_gas(
         &self, transaction: CallRequest, block: Option<BlockNumber>,
+        state_overrides: Option<StateOverride>,
+        block_overrides: Option<BlockOverrides>,
     ) -> Result<U256>;
 
+    /// Simulate blocks of calls on top of the given block. The state changes
+    /// of every call are visible to the following ones.
+    #[rpc(name = "eth_simulateV1")]
+    fn simulate_v1(
+        &self, payload: SimulatePayload<CallRequest>,
+        block: Option<BlockNumber>,
+    ) -> Result<Vec<SimulatedBlock>>;
+
+    /// Generate the EIP-2930 access list of a call, executing it until the
+    /// list no longer changes, and return the list with the gas used.
+    #[rpc(name = "eth_createAccessList")]
+    fn create_access_list(
+        &self, transaction: CallRequest, block: Option<BlockNumber>,
+    ) -> Result<AccessListResult>;
+
     /// Get transaction by its hash.
     #[rpc(name = "eth_getTransactionByHash")]
     fn transaction_by_hash(
@@ -257,6 +282,13 @@ pub trait Eth {
         &self, address: H160, maybe_start_nonce: Option<U256>,
         maybe_limit: Option<U64>,
     ) -> Result<AccountPendingTransactions>;
+
+    /// Get the transactions touching an address, from the newest to the
+    /// oldest. It requires the address index to be enabled.
+    #[rpc(name = "eth_getTransactionsByAddress")]
+    fn transactions_by_address(
+        &self, address: H160, cursor: Option<U64>, limit: Option<U64>,
+    ) -> Result<AddressTransactions<AddressTransaction>>;
 }
 
 /// Eth filters rpc api (polling).
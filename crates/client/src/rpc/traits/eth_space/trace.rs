@@ -2,7 +2,13 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use crate::rpc::types::eth::{BlockNumber, LocalizedTrace, TraceFilter};
+use crate::rpc::types::{
+    eth::{
+        BlockNumber, CallRequest, LocalizedTrace, Trace as EthTrace,
+        TraceFilter,
+    },
+    Bytes, TraceResults, TraceType,
+};
 use jsonrpc_core::Result as JsonRpcResult;
 use jsonrpc_derive::rpc;
 use mazze_types::H256;
@@ -27,4 +33,41 @@ pub trait Trace {
     fn transaction_traces(
         &self, tx_hash: H256,
     ) -> JsonRpcResult<Option<Vec<LocalizedTrace>>>;
+
+    /// Executes the call on top of the given block and returns the
+    /// requested traces of it.
+    #[rpc(name = "trace_call")]
+    fn call(
+        &self, request: CallRequest, trace_types: Vec<TraceType>,
+        block_number: Option<BlockNumber>,
+    ) -> JsonRpcResult<TraceResults<EthTrace>>;
+
+    /// Executes the calls one after another on top of the given block and
+    /// returns the requested traces of each call.
+    #[rpc(name = "trace_callMany")]
+    fn call_many(
+        &self, requests: Vec<(CallRequest, Vec<TraceType>)>,
+        block_number: Option<BlockNumber>,
+    ) -> JsonRpcResult<Vec<TraceResults<EthTrace>>>;
+
+    /// Executes the signed transaction on top of the given block without
+    /// sending it and returns the requested traces of it.
+    #[rpc(name = "trace_rawTransaction")]
+    fn raw_transaction(
+        &self, raw: Bytes, trace_types: Vec<TraceType>,
+        block_number: Option<BlockNumber>,
+    ) -> JsonRpcResult<TraceResults<EthTrace>>;
+
+    /// Replays the transaction and returns the requested traces of it.
+    #[rpc(name = "trace_replayTransaction")]
+    fn replay_transaction(
+        &self, tx_hash: H256, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<TraceResults<EthTrace>>>;
+
+    /// Replays all the transactions of the given block and returns the
+    /// requested traces of each transaction.
+    #[rpc(name = "trace_replayBlockTransactions")]
+    fn replay_block_transactions(
+        &self, block_number: BlockNumber, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<Vec<TraceResults<EthTrace>>>>;
 }
@@ -132,4 +132,18 @@ pub trait LocalRpc {
     fn transactions_by_block(
         &self, block_hash: H256,
     ) -> JsonRpcResult<Vec<WrapTransaction>>;
+
+    /// Write the bundle of the local snapshot at `snapshot_epoch_id` to
+    /// `path` on the node, for bootstrapping new nodes offline.
+    #[rpc(name = "debug_exportSnapshot")]
+    fn export_snapshot(
+        &self, snapshot_epoch_id: H256, path: String,
+    ) -> JsonRpcResult<()>;
+
+    /// Write the blocks of the epochs in `[from_epoch, to_epoch]` to `path` on
+    /// the node in consensus order, and return the number of blocks written.
+    #[rpc(name = "debug_exportBlocks")]
+    fn export_blocks(
+        &self, from_epoch: U64, to_epoch: U64, path: String,
+    ) -> JsonRpcResult<U64>;
 }
@@ -3,13 +3,16 @@
 // See http://www.gnu.org/licenses/
 
 use crate::rpc::types::{
-    Account as RpcAccount, AccountPendingInfo, AccountPendingTransactions,
-    Block, BlockHashOrEpochNumber, Bytes, CallRequest,
-    CheckBalanceAgainstTransactionResponse, EpochNumber,
-    EstimateGasAndCollateralResponse, Log as RpcLog, MazzeFeeHistory,
-    MazzeFilterChanges, MazzeRpcLogFilter, Receipt as RpcReceipt,
-    RewardInfo as RpcRewardInfo, RpcAddress, SponsorInfo, Status as RpcStatus,
-    StorageCollateralInfo, TokenSupplyInfo, Transaction, U64 as HexU64,
+    mazze::MazzeAccessList, AccessListResult, Account as RpcAccount,
+    AccountPendingInfo, AccountPendingTransactions, AccountProof,
+    AddressTransaction, AddressTransactions, Block, BlockHashOrEpochNumber,
+    BlockOverrides, Bytes, CallRequest, CheckBalanceAgainstTransactionResponse,
+    EpochNumber, EstimateGasAndCollateralResponse, Log as RpcLog,
+    MazzeFeeHistory, MazzeFilterChanges, MazzeRpcLogFilter,
+    Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, RpcAddress,
+    SimulatePayload, SimulatedBlock, SponsorInfo, StateOverride,
+    Status as RpcStatus, StorageCollateralInfo, TokenSupplyInfo, Transaction,
+    U64 as HexU64,
 };
 use jsonrpc_core::{BoxFuture, Result as JsonRpcResult, Value as JsonValue};
 use jsonrpc_derive::rpc;
@@ -23,9 +26,10 @@ pub trait Mazze {
     // necessary).        #[rpc(name = "mazze_protocolVersion")]
     //        fn protocol_version(&self) -> JsonRpcResult<String>;
     //
-    /// Returns the number of hashes per second that the node is mining with.
-    //        #[rpc(name = "mazze_hashrate")]
-    //        fn hashrate(&self) -> JsonRpcResult<U256>;
+    /// Returns the number of hashes per second that the node is mining with,
+    /// as reported by its stratum workers.
+    #[rpc(name = "mazze_hashrate")]
+    fn hashrate(&self) -> JsonRpcResult<U256>;
 
     //        /// Returns block author.
     //        #[rpc(name = "mazze_coinbase")]
@@ -93,6 +97,14 @@ pub trait Mazze {
         &self, address: RpcAddress, epoch_num: Option<EpochNumber>,
     ) -> BoxFuture<Option<StorageRoot>>;
 
+    /// Returns the account and the given storage slots of `address` with
+    /// their Merkle proofs. See `AccountProof` for the proof format.
+    #[rpc(name = "mazze_getProof")]
+    fn proof(
+        &self, address: RpcAddress, storage_keys: Vec<H256>,
+        epoch_num: Option<EpochNumber>,
+    ) -> JsonRpcResult<AccountProof<RpcAddress>>;
+
     /// Returns block with given hash.
     #[rpc(name = "mazze_getBlockByHash")]
     fn block_by_hash(
@@ -146,13 +158,32 @@ pub trait Mazze {
     //        #[rpc(name = "mazze_submitTransaction")]
     //        fn submit_transaction(&self, Bytes) -> JsonRpcResult<H256>;
 
-    /// Call contract, returning the output data.
+    /// Call contract, returning the output data. The optional overrides are
+    /// applied to the state and block of the call only.
     #[rpc(name = "mazze_call")]
     fn call(
         &self, tx: CallRequest,
         block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>,
+        state_overrides: Option<StateOverride<RpcAddress>>,
+        block_overrides: Option<BlockOverrides<RpcAddress>>,
     ) -> JsonRpcResult<Bytes>;
 
+    /// Simulate blocks of calls on top of the given epoch. The state changes
+    /// of every call are visible to the following ones.
+    #[rpc(name = "mazze_simulateV1")]
+    fn simulate_v1(
+        &self, payload: SimulatePayload<CallRequest, RpcAddress>,
+        block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>,
+    ) -> JsonRpcResult<Vec<SimulatedBlock<RpcAddress>>>;
+
+    /// Generate the access list of a call, executing it until the list no
+    /// longer changes, and return the list with the gas used.
+    #[rpc(name = "mazze_createAccessList")]
+    fn create_access_list(
+        &self, tx: CallRequest,
+        block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>,
+    ) -> JsonRpcResult<AccessListResult<MazzeAccessList>>;
+
     /// Returns logs matching the filter provided.
     #[rpc(name = "mazze_getLogs")]
     fn get_logs(&self, filter: MazzeRpcLogFilter) -> BoxFuture<Vec<RpcLog>>;
@@ -176,10 +207,19 @@ pub trait Mazze {
         maybe_limit: Option<U64>,
     ) -> BoxFuture<AccountPendingTransactions>;
 
+    /// Get the transactions touching an address, from the newest to the
+    /// oldest. It requires the address index to be enabled.
+    #[rpc(name = "mazze_getTransactionsByAddress")]
+    fn transactions_by_address(
+        &self, address: RpcAddress, cursor: Option<U64>, limit: Option<U64>,
+    ) -> JsonRpcResult<AddressTransactions<AddressTransaction>>;
+
     /// Return estimated gas and collateral usage.
     #[rpc(name = "mazze_estimateGasAndCollateral")]
     fn estimate_gas_and_collateral(
         &self, request: CallRequest, epoch_number: Option<EpochNumber>,
+        state_overrides: Option<StateOverride<RpcAddress>>,
+        block_overrides: Option<BlockOverrides<RpcAddress>>,
     ) -> JsonRpcResult<EstimateGasAndCollateralResponse>;
 
     #[rpc(name = "mazze_feeHistory")]
@@ -3,7 +3,8 @@
 // See http://www.gnu.org/licenses/
 
 use crate::rpc::types::{
-    EpochNumber, EpochTrace, LocalizedBlockTrace, LocalizedTrace, TraceFilter,
+    Bytes, CallRequest, EpochNumber, EpochTrace, LocalizedBlockTrace,
+    LocalizedTrace, RpcAddress, TraceFilter, TraceResults, TraceType,
 };
 use jsonrpc_core::Result as JsonRpcResult;
 use jsonrpc_derive::rpc;
@@ -33,4 +34,41 @@ pub trait Trace {
     /// Return all traces of both spaces in an epoch.
     #[rpc(name = "trace_epoch")]
     fn epoch_traces(&self, epoch: EpochNumber) -> JsonRpcResult<EpochTrace>;
+
+    /// Executes the call on top of the given epoch and returns the
+    /// requested traces of it.
+    #[rpc(name = "trace_call")]
+    fn call(
+        &self, request: CallRequest, trace_types: Vec<TraceType>,
+        epoch: Option<EpochNumber>,
+    ) -> JsonRpcResult<TraceResults<LocalizedTrace, RpcAddress>>;
+
+    /// Executes the calls one after another on top of the given epoch and
+    /// returns the requested traces of each call.
+    #[rpc(name = "trace_callMany")]
+    fn call_many(
+        &self, requests: Vec<(CallRequest, Vec<TraceType>)>,
+        epoch: Option<EpochNumber>,
+    ) -> JsonRpcResult<Vec<TraceResults<LocalizedTrace, RpcAddress>>>;
+
+    /// Executes the signed transaction on top of the given epoch without
+    /// sending it and returns the requested traces of it.
+    #[rpc(name = "trace_rawTransaction")]
+    fn raw_transaction(
+        &self, raw: Bytes, trace_types: Vec<TraceType>,
+        epoch: Option<EpochNumber>,
+    ) -> JsonRpcResult<TraceResults<LocalizedTrace, RpcAddress>>;
+
+    /// Replays the transaction and returns the requested traces of it.
+    #[rpc(name = "trace_replayTransaction")]
+    fn replay_transaction(
+        &self, tx_hash: H256, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<TraceResults<LocalizedTrace, RpcAddress>>>;
+
+    /// Replays all the transactions of the given block and returns the
+    /// requested traces of each transaction.
+    #[rpc(name = "trace_replayBlockTransactions")]
+    fn replay_block_transactions(
+        &self, block_hash: H256, trace_types: Vec<TraceType>,
+    ) -> JsonRpcResult<Option<Vec<TraceResults<LocalizedTrace, RpcAddress>>>>;
 }
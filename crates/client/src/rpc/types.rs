@@ -2,10 +2,14 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
+mod access_list_result;
 mod account;
+mod account_proof;
+mod address_transactions;
 mod blame_info;
 mod block;
 mod bytes;
+mod call_overrides;
 mod consensus_graph_states;
 mod epoch_number;
 pub mod errors;
@@ -19,6 +23,7 @@ mod provenance;
 pub mod pubsub;
 mod receipt;
 mod reward_info;
+mod simulate;
 mod sponsor_info;
 mod stat_on_gas_load;
 mod status;
@@ -27,15 +32,28 @@ mod sync_graph_states;
 mod token_supply_info;
 mod trace;
 mod trace_filter;
+mod trace_results;
 mod transaction;
 mod tx_pool;
 mod variadic_u64;
 
 pub use self::{
+    access_list_result::AccessListResult,
     account::Account,
+    account_proof::{
+        account_proof_keys, AccountProof, StateEntryProof, StorageProof,
+    },
+    address_transactions::{
+        query_address_transactions, AddressTransaction, AddressTransactions,
+        DEFAULT_ADDRESS_TRANSACTIONS_LIMIT, MAX_ADDRESS_TRANSACTIONS_LIMIT,
+    },
     blame_info::BlameInfo,
     block::{Block, BlockTransactions, Header},
     bytes::Bytes,
+    call_overrides::{
+        into_call_overrides, AccountOverride, BlockOverrides,
+        GethDebugTracingCallOptions, StateOverride,
+    },
     consensus_graph_states::ConsensusGraphStates,
     epoch_number::{BlockHashOrEpochNumber, EpochNumber},
     fee_history::FeeHistory,
@@ -56,6 +74,10 @@ pub use self::{
     provenance::Origin,
     receipt::Receipt,
     reward_info::RewardInfo,
+    simulate::{
+        SimulateCall, SimulatePayload, SimulatedBlock, SimulatedCall,
+        SimulatedLog, MAX_SIMULATE_BLOCKS, MAX_SIMULATE_GAS,
+    },
     sponsor_info::SponsorInfo,
     stat_on_gas_load::StatOnGasLoad,
     status::Status,
@@ -64,9 +86,12 @@ pub use self::{
     token_supply_info::TokenSupplyInfo,
     trace::{
         Action, EpochTrace, LocalizedBlockTrace, LocalizedTrace,
-        LocalizedTransactionTrace,
+        LocalizedTransactionTrace, Reward,
     },
     trace_filter::TraceFilter,
+    trace_results::{
+        trace_options, StateDiff, TraceResults, TraceType, VmTrace,
+    },
     transaction::{PackedOrExecuted, Transaction, WrapTransaction},
     tx_pool::{
         AccountPendingInfo, AccountPendingTransactions,
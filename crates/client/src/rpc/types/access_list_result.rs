@@ -0,0 +1,87 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use jsonrpc_core::Error as RpcError;
+use mazze_executor::executive::{
+    revert_reason_decode, ExecutionError, ExecutionOutcome,
+};
+use mazze_types::U256;
+use mazze_vm_types::Error as VmError;
+use primitives::AccessList;
+use serde::Serialize;
+
+/// The access list generated for a call, as returned by
+/// `eth_createAccessList` and `mazze_createAccessList`, with the gas used by
+/// the call when executed with this list. `error` is set if the call fails.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListResult<List = AccessList> {
+    pub access_list: List,
+    pub gas_used: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl<List> AccessListResult<List> {
+    /// Build the result from the outcome of the last execution with
+    /// `access_list`. Calls which are not executed at all fail with
+    /// `not_executed`.
+    pub fn from_outcome<N>(
+        access_list: List, outcome: &ExecutionOutcome, not_executed: N,
+    ) -> Result<Self, RpcError>
+    where
+        N: Fn(&ExecutionOutcome) -> RpcError,
+    {
+        let (executed, error) = match outcome {
+            ExecutionOutcome::Finished(executed) => (executed, None),
+            ExecutionOutcome::ExecutionErrorBumpNonce(
+                ExecutionError::VmError(VmError::Reverted),
+                executed,
+            ) => (
+                executed,
+                Some(format!(
+                    "execution reverted: {}",
+                    revert_reason_decode(&executed.output)
+                )),
+            ),
+            ExecutionOutcome::ExecutionErrorBumpNonce(e, executed) => {
+                (executed, Some(format!("{:?}", e)))
+            }
+            ExecutionOutcome::NotExecutedDrop(_)
+            | ExecutionOutcome::NotExecutedToReconsiderPacking(_) => {
+                return Err(not_executed(outcome));
+            }
+        };
+
+        Ok(AccessListResult {
+            access_list,
+            gas_used: executed.gas_used,
+            error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazze_types::{Address, H256};
+    use primitives::AccessListItem;
+    use serde_json;
+
+    #[test]
+    fn test_serialize() {
+        let result: AccessListResult = AccessListResult {
+            access_list: vec![AccessListItem {
+                address: Address::from_low_u64_be(1),
+                storage_keys: vec![H256::from_low_u64_be(2)],
+            }],
+            gas_used: 30_000.into(),
+            error: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            r#"{"accessList":[{"address":"0x0000000000000000000000000000000000000001","storageKeys":["0x0000000000000000000000000000000000000000000000000000000000000002"]}],"gasUsed":"0x7530"}"#
+        );
+    }
+}
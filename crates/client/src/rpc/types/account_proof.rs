@@ -0,0 +1,218 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::Bytes;
+use jsonrpc_core::Error as RpcError;
+use mazze_storage::StateProofWithRoots;
+use mazze_types::{AddressWithSpace, H160, H256, U256, U64};
+use primitives::{
+    Account, StateRoot, StorageKey, StorageKeyWithSpace, StorageValue,
+};
+use rlp::Rlp;
+use serde::Serialize;
+
+/// Proof of an account and some of its storage slots in the state after
+/// executing an epoch, as returned by `eth_getProof` and `mazze_getProof`.
+///
+/// Mazze keeps its state in three tries: the snapshot, the intermediate delta
+/// and the delta trie, so each entry is proven by a RLP-encoded `StateProof`
+/// holding one `TrieProof` per trie. All entries are proven against
+/// `stateRoot`, whose hash is committed in the header of the main block
+/// `DEFERRED_STATE_EPOCH_COUNT` epochs later. The key padding of the
+/// intermediate trie is derived from `prevSnapshotStateRoot`, the state root
+/// of the epoch one snapshot period before (absent in the first period),
+/// whose hash is committed in the headers the same way.
+///
+/// An entry is checked offline with `StateProofWithRoots::verify` of the
+/// storage crate, passing the raw `key` and `value` of the entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof<Addr = H160> {
+    pub address: Addr,
+    pub balance: U256,
+    pub nonce: U256,
+    pub code_hash: H256,
+    pub epoch_number: U64,
+    pub state_root: StateRoot,
+    pub prev_snapshot_state_root: Option<StateRoot>,
+    pub account_proof: StateEntryProof,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+/// Proof of a single storage slot of an account.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: U256,
+    pub proof: StateEntryProof,
+}
+
+/// A raw state entry with its proof. `value` is `None` if the key does not
+/// exist.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateEntryProof {
+    /// The storage key, as in `StorageKeyWithSpace::to_key_bytes`.
+    pub key: Bytes,
+    /// The RLP-encoded account or storage value.
+    pub value: Option<Bytes>,
+    /// The RLP-encoded `StateProof`.
+    pub proof: Bytes,
+}
+
+/// The storage keys to prove for `address` and its `slots`, in the order
+/// expected by `AccountProof::from_entries`.
+pub fn account_proof_keys<'a>(
+    address: &'a AddressWithSpace, slots: &'a [H256],
+) -> Vec<StorageKeyWithSpace<'a>> {
+    let mut keys = Vec::with_capacity(slots.len() + 1);
+    keys.push(
+        StorageKey::new_account_key(&address.address).with_space(address.space),
+    );
+    keys.extend(slots.iter().map(|slot| {
+        StorageKey::new_storage_key(&address.address, slot.as_bytes())
+            .with_space(address.space)
+    }));
+    keys
+}
+
+impl<Addr> AccountProof<Addr> {
+    /// Build the proof of `address` from the state entries under the keys
+    /// returned by `account_proof_keys`.
+    pub fn from_entries(
+        address: Addr, address_with_space: &AddressWithSpace, slots: &[H256],
+        epoch_number: u64,
+        entries: Vec<(Option<Box<[u8]>>, StateProofWithRoots)>,
+    ) -> Result<Self, RpcError> {
+        let keys = account_proof_keys(address_with_space, slots);
+        if entries.len() != keys.len() {
+            return Err(RpcError::internal_error());
+        }
+        let state_root = entries[0].1.state_root.clone();
+        let prev_snapshot_state_root =
+            entries[0].1.prev_snapshot_state_root.clone();
+
+        let mut entries =
+            keys.iter().zip(entries).map(|(key, (value, proof))| {
+                let value = value.map(Vec::from);
+                let entry_proof = StateEntryProof {
+                    key: key.to_key_bytes().into(),
+                    value: value.clone().map(Into::into),
+                    proof: rlp::encode(&proof.state_proof).into(),
+                };
+                (value, entry_proof)
+            });
+
+        let (account, account_proof) = entries.next().unwrap();
+        let account = match account {
+            Some(raw) => Account::new_from_rlp(
+                address_with_space.address,
+                &Rlp::new(&raw),
+            )
+            .map_err(|e| {
+                RpcError::invalid_params(format!(
+                    "Failed to decode account: {:?}",
+                    e
+                ))
+            })?,
+            None => Account::new_empty(address_with_space),
+        };
+
+        let storage_proof = slots
+            .iter()
+            .zip(entries)
+            .map(|(slot, (value, proof))| {
+                let value = match value {
+                    Some(raw) => {
+                        rlp::decode::<StorageValue>(&raw)
+                            .map_err(|e| {
+                                RpcError::invalid_params(format!(
+                                    "Failed to decode storage value: {:?}",
+                                    e
+                                ))
+                            })?
+                            .value
+                    }
+                    None => U256::zero(),
+                };
+                Ok(StorageProof {
+                    key: *slot,
+                    value,
+                    proof,
+                })
+            })
+            .collect::<Result<_, RpcError>>()?;
+
+        Ok(AccountProof {
+            address,
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash,
+            epoch_number: epoch_number.into(),
+            state_root,
+            prev_snapshot_state_root,
+            account_proof,
+            storage_proof,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keccak_hash::KECCAK_EMPTY;
+    use mazze_types::AddressSpaceUtil;
+    use serde_json;
+
+    #[test]
+    fn test_from_entries() {
+        let address = H160::from_low_u64_be(1).with_evm_space();
+        let slots = vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)];
+        let account =
+            Account::new_empty_with_balance(&address, &10.into(), &2.into());
+        let storage = StorageValue {
+            value: 5.into(),
+            owner: None,
+        };
+        let entries = vec![
+            (
+                Some(rlp::encode(&account).into_boxed_slice()),
+                StateProofWithRoots::default(),
+            ),
+            (
+                Some(rlp::encode(&storage).into_boxed_slice()),
+                StateProofWithRoots::default(),
+            ),
+            (None, StateProofWithRoots::default()),
+        ];
+
+        let proof = AccountProof::from_entries(
+            address.address,
+            &address,
+            &slots,
+            3,
+            entries,
+        )
+        .unwrap();
+        assert_eq!(proof.balance, 10.into());
+        assert_eq!(proof.nonce, 2.into());
+        assert_eq!(proof.code_hash, KECCAK_EMPTY);
+        assert_eq!(proof.epoch_number, 3.into());
+        assert_eq!(proof.storage_proof[0].value, 5.into());
+        assert_eq!(proof.storage_proof[1].value, U256::zero());
+        assert_eq!(proof.storage_proof[1].proof.value, None);
+        assert_eq!(
+            proof.storage_proof[1].proof.key.clone().into_vec(),
+            StorageKey::new_storage_key(&address.address, slots[1].as_bytes())
+                .with_evm_space()
+                .to_key_bytes()
+        );
+
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json["prevSnapshotStateRoot"], serde_json::Value::Null);
+        assert!(json["stateRoot"]["intermediateDeltaRoot"].is_string());
+        assert!(json["accountProof"]["proof"].is_string());
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::error_codes::{build_rpc_server_error, codes, invalid_params};
+use jsonrpc_core::Error as RpcError;
+use mazze_types::{AddressWithSpace, H256, U64};
+use mazzecore::{
+    block_data_manager::AddressTransaction as PrimitiveAddressTransaction,
+    ConsensusGraph, ConsensusGraphTrait,
+};
+use primitives::EpochNumber;
+
+/// The default number of transactions in a page of
+/// `mazze_getTransactionsByAddress` and `eth_getTransactionsByAddress`.
+pub const DEFAULT_ADDRESS_TRANSACTIONS_LIMIT: u64 = 100;
+/// The maximum number of transactions in a page of
+/// `mazze_getTransactionsByAddress` and `eth_getTransactionsByAddress`.
+pub const MAX_ADDRESS_TRANSACTIONS_LIMIT: u64 = 1000;
+
+/// A page of the transactions touching an address, from the newest to the
+/// oldest.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransactions<T> {
+    pub transactions: Vec<T>,
+    /// The cursor to query the next page, or `None` if there are no more
+    /// transactions.
+    pub next_cursor: Option<U64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransaction {
+    pub transaction_hash: H256,
+    pub block_hash: H256,
+    pub epoch_number: U64,
+}
+
+impl From<PrimitiveAddressTransaction> for AddressTransaction {
+    fn from(tx: PrimitiveAddressTransaction) -> Self {
+        AddressTransaction {
+            transaction_hash: tx.transaction_hash,
+            block_hash: tx.block_hash,
+            epoch_number: tx.epoch_number.into(),
+        }
+    }
+}
+
+/// Query a page of the address index of `address`. The transactions of the
+/// epochs reverted by a main chain switch are skipped.
+pub fn query_address_transactions<T>(
+    consensus: &ConsensusGraph, address: &AddressWithSpace,
+    cursor: Option<U64>, limit: Option<U64>,
+) -> Result<AddressTransactions<T>, RpcError>
+where
+    T: From<PrimitiveAddressTransaction>,
+{
+    let data_man = consensus.get_data_manager();
+    if !data_man.address_index_enabled() {
+        return Err(build_rpc_server_error(
+            codes::UNSUPPORTED,
+            "The address index is disabled. Set `persist_address_index` to \
+             enable it."
+                .into(),
+        ));
+    }
+    let limit =
+        limit.map_or(DEFAULT_ADDRESS_TRANSACTIONS_LIMIT, |l| l.as_u64());
+    if limit > MAX_ADDRESS_TRANSACTIONS_LIMIT {
+        return Err(invalid_params(
+            "limit",
+            format!(
+                "limit should not exceed {}",
+                MAX_ADDRESS_TRANSACTIONS_LIMIT
+            ),
+        ));
+    }
+
+    let (transactions, next_cursor) = data_man.transactions_by_address(
+        address,
+        cursor.map(|c| c.as_u64()),
+        limit as usize,
+        |tx| {
+            consensus
+                .get_hash_from_epoch_number(EpochNumber::Number(
+                    tx.epoch_number,
+                ))
+                .map_or(false, |hash| hash == tx.epoch_hash)
+        },
+    );
+    Ok(AddressTransactions {
+        transactions: transactions.into_iter().map(Into::into).collect(),
+        next_cursor: next_cursor.map(Into::into),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_serialize_address_transactions() {
+        let page = AddressTransactions {
+            transactions: vec![AddressTransaction::from(
+                PrimitiveAddressTransaction {
+                    transaction_hash: H256::from_low_u64_be(1),
+                    block_hash: H256::from_low_u64_be(2),
+                    epoch_hash: H256::from_low_u64_be(3),
+                    epoch_number: 16,
+                },
+            )],
+            next_cursor: Some(4.into()),
+        };
+        assert_eq!(
+            serde_json::to_string(&page).unwrap(),
+            r#"{"transactions":[{"transactionHash":"0x0000000000000000000000000000000000000000000000000000000000000001","blockHash":"0x0000000000000000000000000000000000000000000000000000000000000002","epochNumber":"0x10"}],"nextCursor":"0x4"}"#
+        );
+    }
+}
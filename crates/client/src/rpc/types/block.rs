@@ -314,10 +314,17 @@ impl Block {
                 .into(),
             timestamp: b.block_header.timestamp().into(),
             difficulty: b.block_header.difficulty().clone().into(),
-            pow_quality: b
-                .block_header
-                .pow_hash
-                .map(|h| pow::pow_hash_to_quality(&h, &b.block_header.nonce())),
+            pow_quality: b.block_header.pow_hash.map(|h| {
+                pow::pow_hash_to_quality(
+                    &h,
+                    &b.block_header.nonce(),
+                    consensus
+                        .get_tx_pool()
+                        .machine()
+                        .params()
+                        .pow_lower_bound_enabled(b.block_header.height()),
+                )
+            }),
             adaptive: b.block_header.adaptive(),
             referee_hashes: b
                 .block_header
@@ -473,7 +480,15 @@ impl Header {
             referee_hashes,
             nonce: h.nonce().into(),
             pow_quality: h.pow_hash.map(|pow_hash| {
-                pow::pow_hash_to_quality(&pow_hash, &h.nonce())
+                pow::pow_hash_to_quality(
+                    &pow_hash,
+                    &h.nonce(),
+                    consensus
+                        .get_tx_pool()
+                        .machine()
+                        .params()
+                        .pow_lower_bound_enabled(h.height()),
+                )
             }), /* TODO(thegaram):
                  * include custom */
         })
@@ -0,0 +1,192 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::rpc::types::Bytes;
+use alloy_rpc_types_trace::geth::GethDebugTracingOptions;
+use jsonrpc_core::Error as RpcError;
+use mazze_execute_helper::overrides::{
+    AccountOverride as PrimitiveAccountOverride,
+    BlockOverrides as PrimitiveBlockOverrides, CallOverrides,
+    StateOverride as PrimitiveStateOverride,
+};
+use mazze_types::{H160, H256, U256, U64};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, hash::Hash};
+
+/// Overrides of a single account for a virtual call. `state` replaces the
+/// whole storage of the account and `stateDiff` only the given slots; at most
+/// one of them can be set.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    pub nonce: Option<U64>,
+    pub code: Option<Bytes>,
+    pub state: Option<HashMap<H256, H256>>,
+    pub state_diff: Option<HashMap<H256, H256>>,
+}
+
+/// Account overrides by address: hex addresses in the eth space, base32
+/// addresses in the native space.
+pub type StateOverride<Addr = H160> = HashMap<Addr, AccountOverride>;
+
+/// Overrides of the block a virtual call is executed in.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BlockOverrides<Addr = H160> {
+    pub number: Option<U64>,
+    #[serde(alias = "timestamp")]
+    pub time: Option<U64>,
+    pub coinbase: Option<Addr>,
+    pub base_fee: Option<U256>,
+}
+
+/// The options of `debug_traceCall`: the tracing options of the other
+/// `debug_trace*` methods, and the overrides of `eth_call`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GethDebugTracingCallOptions {
+    #[serde(flatten)]
+    pub tracing_options: GethDebugTracingOptions,
+    pub state_overrides: Option<StateOverride>,
+    pub block_overrides: Option<BlockOverrides>,
+}
+
+impl AccountOverride {
+    fn into_primitive(self) -> Result<PrimitiveAccountOverride, RpcError> {
+        if self.state.is_some() && self.state_diff.is_some() {
+            return Err(RpcError::invalid_params(
+                "Account override can not specify both state and stateDiff",
+            ));
+        }
+        Ok(PrimitiveAccountOverride {
+            balance: self.balance,
+            nonce: self.nonce.map(|n| n.as_u64().into()),
+            code: self.code.map(Bytes::into_vec),
+            state: self.state,
+            state_diff: self.state_diff,
+        })
+    }
+}
+
+impl<Addr: Into<H160>> BlockOverrides<Addr> {
+    fn into_primitive(self) -> PrimitiveBlockOverrides {
+        PrimitiveBlockOverrides {
+            number: self.number.map(|n| n.as_u64()),
+            timestamp: self.time.map(|t| t.as_u64()),
+            author: self.coinbase.map(Into::into),
+            base_gas_price: self.base_fee,
+        }
+    }
+}
+
+/// Convert the overrides of a call request into the ones applied by the
+/// executor.
+pub fn into_call_overrides<Addr: Into<H160> + Eq + Hash>(
+    state: Option<StateOverride<Addr>>, block: Option<BlockOverrides<Addr>>,
+) -> Result<CallOverrides, RpcError> {
+    let state = match state {
+        None => None,
+        Some(state) => Some(
+            state
+                .into_iter()
+                .map(|(address, account)| {
+                    Ok((address.into(), account.into_primitive()?))
+                })
+                .collect::<Result<PrimitiveStateOverride, RpcError>>()?,
+        ),
+    };
+    Ok(CallOverrides {
+        state,
+        block: block.map(BlockOverrides::into_primitive),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_deserialize_overrides() {
+        let state: StateOverride = serde_json::from_str(
+            r#"{
+                "0x0000000000000000000000000000000000000001": {
+                    "balance": "0x10",
+                    "nonce": "0x2",
+                    "code": "0x6000",
+                    "stateDiff": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let block: BlockOverrides = serde_json::from_str(
+            r#"{"number":"0x5","timestamp":"0x64","coinbase":"0x0000000000000000000000000000000000000002","baseFee":"0x1"}"#,
+        )
+        .unwrap();
+
+        let overrides = into_call_overrides(Some(state), Some(block)).unwrap();
+        let account =
+            &overrides.state.as_ref().unwrap()[&H160::from_low_u64_be(1)];
+        assert_eq!(account.balance, Some(16.into()));
+        assert_eq!(account.nonce, Some(2.into()));
+        assert_eq!(account.code, Some(vec![0x60, 0x00]));
+        assert_eq!(
+            account.state_diff.as_ref().unwrap()[&H256::from_low_u64_be(1)],
+            H256::from_low_u64_be(2)
+        );
+        assert_eq!(account.state, None);
+
+        let block = overrides.block.unwrap();
+        assert_eq!(block.number, Some(5));
+        assert_eq!(block.timestamp, Some(100));
+        assert_eq!(block.author, Some(H160::from_low_u64_be(2)));
+        assert_eq!(block.base_gas_price, Some(1.into()));
+    }
+
+    #[test]
+    fn test_invalid_overrides() {
+        let state: StateOverride = serde_json::from_str(
+            r#"{"0x0000000000000000000000000000000000000001":{"state":{},"stateDiff":{}}}"#,
+        )
+        .unwrap();
+        assert!(into_call_overrides(Some(state), None).is_err());
+
+        assert!(serde_json::from_str::<StateOverride>(
+            r#"{"0x0000000000000000000000000000000000000001":{"storage":{}}}"#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_deserialize_trace_call_options() {
+        let opts: GethDebugTracingCallOptions = serde_json::from_str(
+            r#"{
+                "tracer": "callTracer",
+                "stateOverrides": {
+                    "0x0000000000000000000000000000000000000001": {"balance": "0x10"}
+                },
+                "blockOverrides": {"number": "0x5"}
+            }"#,
+        )
+        .unwrap();
+        assert!(opts.tracing_options.tracer.is_some());
+
+        let overrides =
+            into_call_overrides(opts.state_overrides, opts.block_overrides)
+                .unwrap();
+        assert_eq!(
+            overrides.state.unwrap()[&H160::from_low_u64_be(1)].balance,
+            Some(16.into())
+        );
+        assert_eq!(overrides.block.unwrap().number, Some(5));
+
+        let opts: GethDebugTracingCallOptions =
+            serde_json::from_str("{}").unwrap();
+        assert!(opts.state_overrides.is_none());
+        assert!(opts.block_overrides.is_none());
+    }
+}
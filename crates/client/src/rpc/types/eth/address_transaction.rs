@@ -0,0 +1,26 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use mazze_types::{H256, U256};
+use mazzecore::block_data_manager::AddressTransaction as PrimitiveAddressTransaction;
+
+/// A transaction touching an address. An eth-space block is an epoch, so it
+/// is identified by the main block of the epoch.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressTransaction {
+    pub transaction_hash: H256,
+    pub block_hash: H256,
+    pub block_number: U256,
+}
+
+impl From<PrimitiveAddressTransaction> for AddressTransaction {
+    fn from(tx: PrimitiveAddressTransaction) -> Self {
+        AddressTransaction {
+            transaction_hash: tx.transaction_hash,
+            block_hash: tx.epoch_hash,
+            block_number: tx.epoch_number.into(),
+        }
+    }
+}
@@ -4,14 +4,14 @@
 
 //! Pub-Sub types.
 
-use super::{EthRpcLogFilter, Header, Log};
+use super::{EthRpcLogFilter, Header, Log, SyncInfo, Transaction};
 use mazze_types::H256;
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
 use serde_json::{from_value, Value};
 
 /// Subscription result.
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(untagged, rename_all = "camelCase")]
 // NOTE: rename_all does not apply to enum member fields
 // see: https://github.com/serde-rs/serde/issues/1061
@@ -24,6 +24,16 @@ pub enum Result {
 
     /// Transaction hash
     TransactionHash(H256),
+
+    /// Transaction
+    Transaction(Transaction),
+
+    /// Sync status, `status` is only set while syncing
+    Syncing {
+        syncing: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        status: Option<SyncInfo>,
+    },
 }
 
 /// Subscription kind.
@@ -48,6 +58,8 @@ pub enum Params {
     None,
     /// Log parameters.
     Logs(EthRpcLogFilter),
+    /// Whether to publish full transactions instead of their hashes.
+    FullTransactions(bool),
 }
 
 impl Default for Params {
@@ -69,9 +81,51 @@ impl<'a> Deserialize<'a> for Params {
             return Ok(Params::None);
         }
 
+        if let Value::Bool(full) = v {
+            return Ok(Params::FullTransactions(full));
+        }
+
         // try to interpret as a log filter
         from_value(v.clone()).map(Params::Logs).map_err(|e| {
             D::Error::custom(format!("Invalid Pub-Sub parameters: {}", e))
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_deserialize_full_transactions() {
+        assert_eq!(
+            serde_json::from_str::<Params>("true").unwrap(),
+            Params::FullTransactions(true)
+        );
+        assert_eq!(
+            serde_json::from_str::<Params>("null").unwrap(),
+            Params::None
+        );
+    }
+
+    #[test]
+    fn test_serialize_syncing() {
+        let syncing = Result::Syncing {
+            syncing: true,
+            status: Some(SyncInfo::default()),
+        };
+        assert_eq!(
+            serde_json::to_string(&syncing).unwrap(),
+            r#"{"syncing":true,"status":{"startingBlock":"0x0","currentBlock":"0x0","highestBlock":"0x0","warpChunksAmount":null,"warpChunksProcessed":null}}"#
+        );
+        let synced = Result::Syncing {
+            syncing: false,
+            status: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&synced).unwrap(),
+            r#"{"syncing":false}"#
+        );
+    }
+}
@@ -2,6 +2,7 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
+mod address_transaction;
 mod block;
 mod block_number;
 mod call_request;
@@ -16,6 +17,7 @@ mod transaction;
 mod tx_pool;
 
 pub use self::{
+    address_transaction::AddressTransaction,
     block::{Block, Header},
     block_number::BlockNumber,
     call_request::CallRequest,
@@ -23,7 +25,7 @@ pub use self::{
     log::Log,
     receipt::Receipt,
     sync::{SyncInfo, SyncStatus},
-    trace::{LocalizedTrace, Res},
+    trace::{LocalizedTrace, Res, Reward, RewardType, Trace},
     trace_filter::TraceFilter,
     transaction::Transaction,
     tx_pool::AccountPendingTransactions,
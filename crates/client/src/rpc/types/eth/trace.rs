@@ -97,6 +97,38 @@ pub struct Call {
     call_type: CallType,
 }
 
+/// Suicide
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suicide {
+    /// Address.
+    pub address: H160,
+    /// Refund address.
+    pub refund_address: H160,
+    /// Balance.
+    pub balance: U256,
+}
+
+/// Reward type.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RewardType {
+    /// Block
+    Block,
+}
+
+/// Reward action
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reward {
+    /// Author's address.
+    pub author: H160,
+    /// Reward amount.
+    pub value: U256,
+    /// Reward type.
+    pub reward_type: RewardType,
+}
+
 /// Action
 #[derive(Debug)]
 pub enum Action {
@@ -104,8 +136,10 @@ pub enum Action {
     Call(Call),
     /// Create
     Create(Create),
-    /* TODO: Support Suicide
-     * TODO: Support Reward */
+    /// Suicide
+    Suicide(Suicide),
+    /// Reward
+    Reward(Reward),
 }
 
 impl TryFrom<RpcMazzeAction> for Action {
@@ -128,6 +162,16 @@ impl TryFrom<RpcMazzeAction> for Action {
                 init: create.init,
                 create_type: create.create_type.into(),
             })),
+            RpcMazzeAction::Suicide(suicide) => Ok(Action::Suicide(Suicide {
+                address: suicide.address.hex_address,
+                refund_address: suicide.refund_address.hex_address,
+                balance: suicide.balance,
+            })),
+            RpcMazzeAction::Reward(reward) => Ok(Action::Reward(Reward {
+                author: reward.author.hex_address,
+                value: reward.value,
+                reward_type: RewardType::Block,
+            })),
             action => {
                 bail!("unsupported action in eth space: {:?}", action);
             }
@@ -210,6 +254,14 @@ impl Serialize for LocalizedTrace {
                 struc.serialize_field("type", "create")?;
                 struc.serialize_field("action", create)?;
             }
+            Action::Suicide(ref suicide) => {
+                struc.serialize_field("type", "suicide")?;
+                struc.serialize_field("action", suicide)?;
+            }
+            Action::Reward(ref reward) => {
+                struc.serialize_field("type", "reward")?;
+                struc.serialize_field("action", reward)?;
+            }
         }
 
         match self.result {
@@ -269,6 +321,21 @@ impl TryFrom<RpcMazzeLocalizedTrace> for LocalizedTrace {
 }
 
 impl LocalizedTrace {
+    /// The trace of the reward of a block in the epoch `block_number`.
+    pub fn reward(reward: Reward, block_number: u64, block_hash: H256) -> Self {
+        LocalizedTrace {
+            action: Action::Reward(reward),
+            result: Res::None,
+            trace_address: vec![],
+            subtraces: 0,
+            transaction_position: None,
+            transaction_hash: None,
+            block_number,
+            block_hash,
+            valid: true,
+        }
+    }
+
     pub fn set_result(
         &mut self, result: RpcMazzeAction,
     ) -> Result<(), JsonRpcError> {
@@ -276,56 +343,65 @@ impl LocalizedTrace {
             // One action matches exactly one result.
             bail!(JsonRpcError::internal_error());
         }
-        match result {
+        self.result = Res::from_action(&self.action, result)?;
+        Ok(())
+    }
+}
+
+impl Res {
+    /// Convert the result action of `action`.
+    fn from_action(
+        action: &Action, result_action: RpcMazzeAction,
+    ) -> Result<Self, JsonRpcError> {
+        let result = match result_action {
             RpcMazzeAction::CallResult(call_result) => {
-                if !matches!(self.action, Action::Call(_)) {
-                    bail!(JsonRpcError::internal_error());
-                }
+                let gas = match action {
+                    Action::Call(call) => call.gas,
+                    _ => bail!(JsonRpcError::internal_error()),
+                };
                 match call_result.outcome {
-                    Outcome::Success => {
-                        // FIXME(lpl): Convert gas_left to gas_used.
-                        self.result = Res::Call(CallResult {
-                            gas_used: call_result.gas_left,
-                            output: call_result.return_data,
-                        })
-                    }
-                    Outcome::Reverted => {
-                        self.result = Res::FailedCall(TraceError::Reverted);
-                    }
-                    Outcome::Fail => {
-                        self.result = Res::FailedCall(TraceError::Error(
-                            call_result.return_data,
-                        ));
-                    }
+                    Outcome::Success => Res::Call(CallResult {
+                        gas_used: gas.saturating_sub(call_result.gas_left),
+                        output: call_result.return_data,
+                    }),
+                    Outcome::Reverted => Res::FailedCall(TraceError::Reverted),
+                    Outcome::Fail => Res::FailedCall(TraceError::Error(
+                        call_result.return_data,
+                    )),
                 }
             }
             RpcMazzeAction::CreateResult(create_result) => {
-                if !matches!(self.action, Action::Create(_)) {
-                    bail!(JsonRpcError::internal_error());
-                }
+                let gas = match action {
+                    Action::Create(create) => create.gas,
+                    _ => bail!(JsonRpcError::internal_error()),
+                };
                 match create_result.outcome {
-                    Outcome::Success => {
-                        // FIXME(lpl): Convert gas_left to gas_used.
-                        // FIXME(lpl): Check if `return_data` is `code`.
-                        self.result = Res::Create(CreateResult {
-                            gas_used: create_result.gas_left,
-                            code: create_result.return_data,
-                            address: create_result.addr.hex_address,
-                        })
-                    }
+                    // The return data of a successful create is the code
+                    // deployed at the new address.
+                    Outcome::Success => Res::Create(CreateResult {
+                        gas_used: gas.saturating_sub(create_result.gas_left),
+                        code: create_result.return_data,
+                        address: create_result.addr.hex_address,
+                    }),
                     Outcome::Reverted => {
-                        self.result = Res::FailedCreate(TraceError::Reverted);
-                    }
-                    Outcome::Fail => {
-                        self.result = Res::FailedCreate(TraceError::Error(
-                            create_result.return_data,
-                        ));
+                        Res::FailedCreate(TraceError::Reverted)
                     }
+                    Outcome::Fail => Res::FailedCreate(TraceError::Error(
+                        create_result.return_data,
+                    )),
+                }
+            }
+            // Suicide actions are paired with themselves, and they have no
+            // result.
+            RpcMazzeAction::Suicide(_) => {
+                if !matches!(action, Action::Suicide(_)) {
+                    bail!(JsonRpcError::internal_error());
                 }
+                Res::None
             }
             _ => bail!(JsonRpcError::internal_error()),
-        }
-        Ok(())
+        };
+        Ok(result)
     }
 }
 
@@ -342,6 +418,21 @@ pub struct Trace {
     result: Res,
 }
 
+impl Trace {
+    pub fn new(
+        action: Action, result_action: RpcMazzeAction,
+        trace_address: Vec<usize>, subtraces: usize,
+    ) -> Result<Self, JsonRpcError> {
+        let result = Res::from_action(&action, result_action)?;
+        Ok(Trace {
+            trace_address,
+            subtraces,
+            action,
+            result,
+        })
+    }
+}
+
 impl Serialize for Trace {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -357,6 +448,14 @@ impl Serialize for Trace {
                 struc.serialize_field("type", "create")?;
                 struc.serialize_field("action", create)?;
             }
+            Action::Suicide(ref suicide) => {
+                struc.serialize_field("type", "suicide")?;
+                struc.serialize_field("action", suicide)?;
+            }
+            Action::Reward(ref reward) => {
+                struc.serialize_field("type", "reward")?;
+                struc.serialize_field("action", reward)?;
+            }
         }
 
         match self.result {
@@ -403,3 +502,36 @@ impl fmt::Display for TraceError {
         message.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::types::trace::CallResult as RpcMazzeCallResult;
+
+    #[test]
+    fn test_call_gas_used() {
+        let action = Action::Call(Call {
+            from: H160::from_low_u64_be(1),
+            to: H160::from_low_u64_be(2),
+            value: U256::zero(),
+            gas: U256::from(50_000),
+            input: Bytes::new(vec![]),
+            call_type: CallType::Call,
+        });
+        let result = RpcMazzeAction::CallResult(RpcMazzeCallResult {
+            outcome: Outcome::Success,
+            gas_left: U256::from(30_000),
+            return_data: Bytes::new(vec![]),
+        });
+
+        let trace = Trace::new(action, result, vec![], 0).unwrap();
+        match trace.result {
+            Res::Call(ref call_result) => {
+                assert_eq!(call_result.gas_used, U256::from(20_000))
+            }
+            ref res => panic!("unexpected result {:?}", res),
+        }
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["result"]["gasUsed"], "0x4e20");
+    }
+}
@@ -20,14 +20,15 @@
 
 //! Pub-Sub types.
 
-use super::{Header, Log, MazzeRpcLogFilter};
-use mazze_types::{H256, U256};
+use super::{Header, Log, MazzeRpcLogFilter, Transaction};
+use mazze_types::{H256, U256, U64};
+use mazzecore::sync::SyncPhaseType;
 use serde::{de::Error, Deserialize, Deserializer, Serialize};
 use serde_json::{from_value, Value};
 
 /// Subscription result.
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(untagged, rename_all = "camelCase")]
 // NOTE: rename_all does not apply to enum member fields
 // see: https://github.com/serde-rs/serde/issues/1061
@@ -51,6 +52,48 @@ pub enum Result {
     /// Chain reorg
     #[serde(rename_all = "camelCase")]
     ChainReorg { revert_to: U256 },
+
+    /// Transaction
+    Transaction(Transaction),
+
+    /// Sync phase, with the number of blocks in consensus and in the sync
+    /// graph as the progress of syncing
+    #[serde(rename_all = "camelCase")]
+    Syncing {
+        syncing: bool,
+        phase: SyncPhase,
+        current_block: U64,
+        highest_block: U64,
+    },
+}
+
+/// Sync phase published to `syncing` subscribers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncPhase {
+    RecoverBlockHeaderFromDb,
+    SyncBlockHeader,
+    Checkpoint,
+    FillBlockBody,
+    SyncBlock,
+    Normal,
+}
+
+impl From<SyncPhaseType> for SyncPhase {
+    fn from(phase: SyncPhaseType) -> Self {
+        match phase {
+            SyncPhaseType::CatchUpRecoverBlockHeaderFromDB => {
+                SyncPhase::RecoverBlockHeaderFromDb
+            }
+            SyncPhaseType::CatchUpSyncBlockHeader => SyncPhase::SyncBlockHeader,
+            SyncPhaseType::CatchUpCheckpoint => SyncPhase::Checkpoint,
+            SyncPhaseType::CatchUpFillBlockBodyPhase => {
+                SyncPhase::FillBlockBody
+            }
+            SyncPhaseType::CatchUpSyncBlock => SyncPhase::SyncBlock,
+            SyncPhaseType::Normal => SyncPhase::Normal,
+        }
+    }
 }
 
 /// Subscription kind.
@@ -90,6 +133,8 @@ pub enum Params {
     Logs(MazzeRpcLogFilter),
     /// Epoch parameters.
     Epochs(SubscriptionEpoch),
+    /// Whether to publish full transactions instead of their hashes.
+    FullTransactions(bool),
 }
 
 impl Default for Params {
@@ -111,6 +156,10 @@ impl<'a> Deserialize<'a> for Params {
             return Ok(Params::None);
         }
 
+        if let Value::Bool(full) = v {
+            return Ok(Params::FullTransactions(full));
+        }
+
         // try to interpret as a log filter
         if let Ok(v) = from_value(v.clone()).map(Params::Logs) {
             return Ok(v);
@@ -0,0 +1,397 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{
+    eth::CallRequest as EthCallRequest, BlockOverrides, Bytes, CallRequest,
+    StateOverride, MAX_GAS_CALL_REQUEST,
+};
+use jsonrpc_core::Error as RpcError;
+use mazze_execute_helper::simulation::SimulatedBlock as PrimitiveSimulatedBlock;
+use mazze_executor::executive::{
+    revert_reason_decode, ExecutionError, ExecutionOutcome,
+};
+use mazze_types::{Address, H160, H256, U256, U64};
+use mazze_vm_types::Error as VmError;
+use rustc_hex::ToHex;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+
+/// The maximum number of blocks in a simulation request.
+pub const MAX_SIMULATE_BLOCKS: usize = 256;
+/// The maximum total gas of the calls in a simulation request.
+pub const MAX_SIMULATE_GAS: u64 = 10 * MAX_GAS_CALL_REQUEST;
+
+/// Error code of a reverted call, as in `eth_call`.
+const REVERTED_ERROR_CODE: i64 = 3;
+/// Error code of a call failed in the VM for another reason.
+const VM_ERROR_CODE: i64 = -32015;
+
+/// A simulation request: blocks of calls executed one after another, the
+/// state changes of every call being visible to the following ones.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    deny_unknown_fields,
+    bound(deserialize = "Call: Deserialize<'de>, \
+                         Addr: Deserialize<'de> + Eq + Hash")
+)]
+pub struct SimulatePayload<Call, Addr = H160> {
+    pub block_state_calls: Vec<SimulateBlock<Call, Addr>>,
+    /// Check the calls like packed transactions: nonces and balances are
+    /// enforced and gas is charged.
+    #[serde(default)]
+    pub validation: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    deny_unknown_fields,
+    bound(deserialize = "Call: Deserialize<'de>, \
+                         Addr: Deserialize<'de> + Eq + Hash")
+)]
+pub struct SimulateBlock<Call, Addr = H160> {
+    pub block_overrides: Option<BlockOverrides<Addr>>,
+    pub state_overrides: Option<StateOverride<Addr>>,
+    #[serde(default)]
+    pub calls: Vec<Call>,
+}
+
+/// A call in a simulation request.
+pub trait SimulateCall {
+    /// The gas limit of the call, if it's set.
+    fn gas(&self) -> Option<U256>;
+}
+
+impl SimulateCall for EthCallRequest {
+    fn gas(&self) -> Option<U256> {
+        self.gas
+    }
+}
+
+impl SimulateCall for CallRequest {
+    fn gas(&self) -> Option<U256> {
+        self.gas
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedBlock<Addr = H160> {
+    pub number: U64,
+    pub timestamp: U64,
+    pub gas_used: U256,
+    pub base_fee_per_gas: U256,
+    pub calls: Vec<SimulatedCall<Addr>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedCall<Addr = H160> {
+    pub return_data: Bytes,
+    pub logs: Vec<SimulatedLog<Addr>>,
+    pub gas_used: U256,
+    pub status: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<SimulatedCallError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedLog<Addr = H160> {
+    pub address: Addr,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedCallError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+impl<Addr> SimulatedBlock<Addr> {
+    /// Convert a simulated block, formatting the log addresses with
+    /// `format_address`. Calls which are not executed at all fail the whole
+    /// simulation with `not_executed`.
+    pub fn from_primitive<F, N>(
+        block: PrimitiveSimulatedBlock, format_address: F, not_executed: N,
+    ) -> Result<Self, RpcError>
+    where
+        F: Fn(Address) -> Result<Addr, RpcError>,
+        N: Fn(&ExecutionOutcome) -> RpcError,
+    {
+        let mut calls = Vec::with_capacity(block.outcomes.len());
+        for outcome in block.outcomes {
+            let (executed, error) = match &outcome {
+                ExecutionOutcome::Finished(executed) => (executed, None),
+                ExecutionOutcome::ExecutionErrorBumpNonce(
+                    ExecutionError::VmError(VmError::Reverted),
+                    executed,
+                ) => {
+                    let reason = revert_reason_decode(&executed.output);
+                    let error = SimulatedCallError {
+                        code: REVERTED_ERROR_CODE,
+                        message: format!("execution reverted: {}", reason),
+                        data: Some(format!(
+                            "0x{}",
+                            executed.output.to_hex::<String>()
+                        )),
+                    };
+                    (executed, Some(error))
+                }
+                ExecutionOutcome::ExecutionErrorBumpNonce(e, executed) => {
+                    let error = SimulatedCallError {
+                        code: VM_ERROR_CODE,
+                        message: format!("{:?}", e),
+                        data: None,
+                    };
+                    (executed, Some(error))
+                }
+                ExecutionOutcome::NotExecutedDrop(_)
+                | ExecutionOutcome::NotExecutedToReconsiderPacking(_) => {
+                    return Err(not_executed(&outcome));
+                }
+            };
+
+            let logs = executed
+                .logs
+                .iter()
+                .map(|log| {
+                    Ok(SimulatedLog {
+                        address: format_address(log.address)?,
+                        topics: log.topics.clone(),
+                        data: log.data.clone().into(),
+                    })
+                })
+                .collect::<Result<_, RpcError>>()?;
+            calls.push(SimulatedCall {
+                return_data: executed.output.clone().into(),
+                logs,
+                gas_used: executed.gas_used,
+                status: U64::from(error.is_none() as u64),
+                error,
+            });
+        }
+
+        Ok(SimulatedBlock {
+            number: block.number.into(),
+            timestamp: block.timestamp.into(),
+            gas_used: block.gas_used,
+            base_fee_per_gas: block.base_gas_price,
+            calls,
+        })
+    }
+}
+
+impl<Call: SimulateCall, Addr> SimulatePayload<Call, Addr> {
+    /// Check the number of blocks, the gas of the calls, and that the block
+    /// number and timestamp overrides are strictly increasing. A call
+    /// without gas is given `MAX_GAS_CALL_REQUEST`.
+    pub fn check_size(&self) -> Result<(), RpcError> {
+        if self.block_state_calls.is_empty() {
+            return Err(RpcError::invalid_params("empty input"));
+        }
+        if self.block_state_calls.len() > MAX_SIMULATE_BLOCKS {
+            return Err(RpcError::invalid_params(format!(
+                "too many blocks, at most {} are allowed",
+                MAX_SIMULATE_BLOCKS
+            )));
+        }
+
+        let max_call_gas = U256::from(MAX_GAS_CALL_REQUEST);
+        let mut total_gas = U256::zero();
+        for call in self.block_state_calls.iter().flat_map(|b| &b.calls) {
+            let gas = call.gas().unwrap_or(max_call_gas);
+            if gas > max_call_gas {
+                return Err(RpcError::invalid_params(format!(
+                    "call gas {} exceeds the cap {}",
+                    gas, max_call_gas
+                )));
+            }
+            total_gas += gas;
+        }
+        if total_gas > MAX_SIMULATE_GAS.into() {
+            return Err(RpcError::invalid_params(format!(
+                "total gas {} of the calls exceeds the cap {}",
+                total_gas, MAX_SIMULATE_GAS
+            )));
+        }
+
+        // A block without overrides advances the number and the timestamp of
+        // the previous block by one.
+        let mut number: Option<u64> = None;
+        let mut timestamp: Option<u64> = None;
+        for block in &self.block_state_calls {
+            let overrides = block.block_overrides.as_ref();
+            number = check_increasing(
+                "number",
+                number,
+                overrides.and_then(|o| o.number).map(|n| n.as_u64()),
+            )?;
+            timestamp = check_increasing(
+                "timestamp",
+                timestamp,
+                overrides.and_then(|o| o.time).map(|t| t.as_u64()),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The `name` of a block given the one of the previous block and its
+/// override, which must be larger.
+fn check_increasing(
+    name: &str, previous: Option<u64>, overridden: Option<u64>,
+) -> Result<Option<u64>, RpcError> {
+    match (previous, overridden) {
+        (Some(previous), Some(overridden)) if overridden <= previous => {
+            Err(RpcError::invalid_params(format!(
+                "block {} override {} is not larger than the previous {}",
+                name, overridden, previous
+            )))
+        }
+        (_, Some(overridden)) => Ok(Some(overridden)),
+        (previous, None) => Ok(previous.map(|p| p.saturating_add(1))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::types::eth::CallRequest;
+    use serde_json;
+
+    #[test]
+    fn test_deserialize_payload() {
+        let payload: SimulatePayload<CallRequest> = serde_json::from_str(
+            r#"{
+                "blockStateCalls": [
+                    {
+                        "stateOverrides": {
+                            "0x0000000000000000000000000000000000000001": {
+                                "balance": "0x10"
+                            }
+                        },
+                        "calls": [
+                            {
+                                "from": "0x0000000000000000000000000000000000000001",
+                                "to": "0x0000000000000000000000000000000000000002",
+                                "value": "0x1"
+                            }
+                        ]
+                    },
+                    {
+                        "blockOverrides": { "time": "0x64" }
+                    }
+                ],
+                "validation": true
+            }"#,
+        )
+        .unwrap();
+        assert!(payload.validation);
+        assert!(payload.check_size().is_ok());
+        let blocks = &payload.block_state_calls;
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].calls.len(), 1);
+        assert_eq!(blocks[0].calls[0].value, Some(1.into()));
+        assert!(blocks[1].calls.is_empty());
+        assert_eq!(
+            blocks[1].block_overrides.as_ref().unwrap().time,
+            Some(100.into())
+        );
+
+        let empty: SimulatePayload<CallRequest> =
+            serde_json::from_str(r#"{"blockStateCalls":[]}"#).unwrap();
+        assert!(!empty.validation);
+        assert!(empty.check_size().is_err());
+    }
+
+    fn payload(json: &str) -> SimulatePayload<CallRequest> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_check_call_gas() {
+        let capped =
+            payload(r#"{"blockStateCalls":[{"calls":[{"gas":"0xe4e1c0"}]}]}"#);
+        assert!(capped.check_size().is_ok());
+
+        let too_much =
+            payload(r#"{"blockStateCalls":[{"calls":[{"gas":"0xe4e1c1"}]}]}"#);
+        assert!(too_much.check_size().is_err());
+    }
+
+    #[test]
+    fn test_check_total_gas() {
+        // Calls without gas are given `MAX_GAS_CALL_REQUEST` each.
+        let calls = vec!["{}"; 10].join(",");
+        let capped = payload(&format!(
+            r#"{{"blockStateCalls":[{{"calls":[{}]}}]}}"#,
+            calls
+        ));
+        assert!(capped.check_size().is_ok());
+
+        let calls = vec!["{}"; 11].join(",");
+        let too_much = payload(&format!(
+            r#"{{"blockStateCalls":[{{"calls":[{}]}}]}}"#,
+            calls
+        ));
+        assert!(too_much.check_size().is_err());
+    }
+
+    #[test]
+    fn test_check_increasing_overrides() {
+        let increasing = payload(
+            r#"{"blockStateCalls":[
+                {"blockOverrides":{"number":"0x1","time":"0x10"}},
+                {},
+                {"blockOverrides":{"number":"0x3","time":"0x12"}}
+            ]}"#,
+        );
+        assert!(increasing.check_size().is_ok());
+
+        // The block without overrides is number 2.
+        let number_not_increasing = payload(
+            r#"{"blockStateCalls":[
+                {"blockOverrides":{"number":"0x1"}},
+                {},
+                {"blockOverrides":{"number":"0x2"}}
+            ]}"#,
+        );
+        assert!(number_not_increasing.check_size().is_err());
+
+        let time_not_increasing = payload(
+            r#"{"blockStateCalls":[
+                {"blockOverrides":{"time":"0x10"}},
+                {"blockOverrides":{"time":"0x10"}}
+            ]}"#,
+        );
+        assert!(time_not_increasing.check_size().is_err());
+    }
+
+    #[test]
+    fn test_serialize_result() {
+        let block: SimulatedBlock = SimulatedBlock {
+            number: 1.into(),
+            timestamp: 2.into(),
+            gas_used: 21000.into(),
+            base_fee_per_gas: U256::one(),
+            calls: vec![SimulatedCall {
+                return_data: Bytes::new(vec![]),
+                logs: vec![],
+                gas_used: 21000.into(),
+                status: 1.into(),
+                error: None,
+            }],
+        };
+        assert_eq!(
+            serde_json::to_string(&block).unwrap(),
+            r#"{"number":"0x1","timestamp":"0x2","gasUsed":"0x5208","baseFeePerGas":"0x1","calls":[{"returnData":"0x","logs":[],"gasUsed":"0x5208","status":"0x1"}]}"#
+        );
+    }
+}
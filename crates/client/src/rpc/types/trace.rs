@@ -10,7 +10,8 @@ use mazze_execute_helper::exec_tracer::{
     Call as VmCall, CallResult as VmCallResult, Create as VmCreate,
     CreateResult as VmCreateResult, ExecTrace,
     InternalTransferAction as VmInternalTransferAction,
-    LocalizedTrace as PrimitiveLocalizedTrace, Outcome, TransactionExecTraces,
+    LocalizedTrace as PrimitiveLocalizedTrace, Outcome, Reward as VmReward,
+    Suicide as VmSuicide, TransactionExecTraces,
 };
 use mazze_executor::internal_contract::evm_map;
 use mazze_parameters::internal_contract_addresses::CROSS_SPACE_CONTRACT_ADDRESS;
@@ -31,6 +32,8 @@ pub enum Action {
     CallResult(CallResult),
     CreateResult(CreateResult),
     InternalTransferAction(InternalTransferAction),
+    Suicide(Suicide),
+    Reward(Reward),
 }
 
 impl Action {
@@ -51,6 +54,12 @@ impl Action {
                     InternalTransferAction::try_from(x, network)?,
                 )
             }
+            VmAction::Suicide(x) => {
+                Action::Suicide(Suicide::try_from(x, network)?)
+            }
+            VmAction::Reward(x) => {
+                Action::Reward(Reward::try_from(x, network)?)
+            }
         })
     }
 }
@@ -65,6 +74,8 @@ impl Into<VmActionType> for ActionType {
             Self::InternalTransferAction => {
                 VmActionType::InternalTransferAction
             }
+            Self::Suicide => VmActionType::Suicide,
+            Self::Reward => VmActionType::Reward,
         }
     }
 }
@@ -193,10 +204,54 @@ impl InternalTransferAction {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suicide {
+    pub space: Space,
+    pub address: RpcAddress,
+    pub refund_address: RpcAddress,
+    pub balance: U256,
+}
+
+impl Suicide {
+    fn try_from(suicide: VmSuicide, network: Network) -> Result<Self, String> {
+        Ok(Self {
+            space: suicide.space,
+            address: RpcAddress::try_from_h160(suicide.address, network)?,
+            refund_address: RpcAddress::try_from_h160(
+                suicide.refund_address,
+                network,
+            )?,
+            balance: suicide.balance,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reward {
+    pub author: RpcAddress,
+    pub value: U256,
+}
+
+impl Reward {
+    pub fn try_from(
+        reward: VmReward, network: Network,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            author: RpcAddress::try_from_h160(reward.author, network)?,
+            value: reward.value,
+        })
+    }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LocalizedBlockTrace {
     pub transaction_traces: Vec<LocalizedTransactionTrace>,
+    /// The reward of the block, if it has been computed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward: Option<Reward>,
     /// Epoch hash.
     pub epoch_hash: H256,
     /// Epoch number.
@@ -259,6 +314,14 @@ impl Serialize for LocalizedTrace {
                 struc.serialize_field("type", "internal_transfer_action")?;
                 struc.serialize_field("action", internal_action)?;
             }
+            Action::Suicide(ref suicide) => {
+                struc.serialize_field("type", "suicide")?;
+                struc.serialize_field("action", suicide)?;
+            }
+            Action::Reward(ref reward) => {
+                struc.serialize_field("type", "reward")?;
+                struc.serialize_field("action", reward)?;
+            }
         }
 
         struc.serialize_field("valid", &self.valid)?;
@@ -365,6 +428,7 @@ impl LocalizedBlockTrace {
 
         Ok(LocalizedBlockTrace {
             transaction_traces,
+            reward: None,
             epoch_hash,
             epoch_number: epoch_number.into(),
             block_hash,
@@ -0,0 +1,296 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The results of the parity-style ad-hoc tracing RPCs: `trace_call`,
+//! `trace_callMany`, `trace_rawTransaction`, `trace_replayTransaction` and
+//! `trace_replayBlockTransactions`.
+
+use crate::rpc::types::Bytes;
+use mazze_execute_helper::{
+    observer::vm_tracer::{
+        MemoryDiff as PrimitiveMemoryDiff, StorageDiff as PrimitiveStorageDiff,
+        VmExecutedOperation as PrimitiveVmExecutedOperation,
+        VmOperation as PrimitiveVmOperation, VmTrace as PrimitiveVmTrace,
+    },
+    parity_trace::ParityTraceOptions,
+};
+use mazze_executor::state::{
+    AccountDiff as PrimitiveAccountDiff, AccountValues,
+};
+use mazze_types::{BigEndianHash, Space, H160, H256, U256};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// The outputs to return from a parity-style tracing RPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TraceType {
+    /// The call traces.
+    Trace,
+    /// The opcode-level traces.
+    VmTrace,
+    /// The changes of the accounts.
+    StateDiff,
+}
+
+/// The tracers to run for the requested outputs.
+pub fn trace_options(trace_types: &[TraceType]) -> ParityTraceOptions {
+    ParityTraceOptions {
+        vm_trace: trace_types.contains(&TraceType::VmTrace),
+        state_diff: trace_types.contains(&TraceType::StateDiff),
+    }
+}
+
+/// The traces of a transaction. Each output is empty if it is not
+/// requested.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceResults<T, Addr = H160> {
+    pub output: Bytes,
+    pub trace: Vec<T>,
+    pub vm_trace: Option<VmTrace>,
+    pub state_diff: Option<StateDiff<Addr>>,
+    /// Only set for the replayed transactions of a block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<H256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VmTrace {
+    pub code: Bytes,
+    pub ops: Vec<VmOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VmOperation {
+    pub pc: usize,
+    pub cost: u64,
+    pub ex: Option<VmExecutedOperation>,
+    pub sub: Option<VmTrace>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VmExecutedOperation {
+    pub used: u64,
+    pub push: Vec<U256>,
+    pub mem: Option<MemoryDiff>,
+    pub store: Option<StorageDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemoryDiff {
+    pub off: usize,
+    pub data: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageDiff {
+    pub key: U256,
+    pub val: U256,
+}
+
+impl From<PrimitiveVmTrace> for VmTrace {
+    fn from(trace: PrimitiveVmTrace) -> Self {
+        VmTrace {
+            code: trace.code.into(),
+            ops: trace.ops.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<PrimitiveVmOperation> for VmOperation {
+    fn from(op: PrimitiveVmOperation) -> Self {
+        VmOperation {
+            pc: op.pc,
+            cost: op.cost,
+            ex: op.ex.map(Into::into),
+            sub: op.sub.map(Into::into),
+        }
+    }
+}
+
+impl From<PrimitiveVmExecutedOperation> for VmExecutedOperation {
+    fn from(ex: PrimitiveVmExecutedOperation) -> Self {
+        VmExecutedOperation {
+            used: ex.used,
+            push: ex.push,
+            mem: ex.mem.map(|PrimitiveMemoryDiff { off, data }| MemoryDiff {
+                off,
+                data: data.into(),
+            }),
+            store: ex.store.map(|PrimitiveStorageDiff { key, val }| {
+                StorageDiff { key, val }
+            }),
+        }
+    }
+}
+
+/// The change of a value.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Diff<T> {
+    #[serde(rename = "=")]
+    Same,
+    #[serde(rename = "+")]
+    Born(T),
+    #[serde(rename = "-")]
+    Died(T),
+    #[serde(rename = "*")]
+    Changed(ChangedType<T>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangedType<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T: PartialEq> Diff<T> {
+    fn new(pre: Option<T>, post: Option<T>) -> Self {
+        match (pre, post) {
+            (None, Some(to)) => Diff::Born(to),
+            (Some(from), None) => Diff::Died(from),
+            (Some(from), Some(to)) if from != to => {
+                Diff::Changed(ChangedType { from, to })
+            }
+            _ => Diff::Same,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccountDiff {
+    pub balance: Diff<U256>,
+    pub nonce: Diff<U256>,
+    pub code: Diff<Bytes>,
+    pub storage: BTreeMap<H256, Diff<H256>>,
+}
+
+impl AccountDiff {
+    fn new(pre: Option<AccountValues>, post: Option<AccountValues>) -> Self {
+        let mut storage: BTreeMap<H256, (Option<H256>, Option<H256>)> =
+            BTreeMap::new();
+        for (values, is_post) in pre
+            .iter()
+            .map(|v| (v, false))
+            .chain(post.iter().map(|v| (v, true)))
+        {
+            // Only the 32-byte keys of the contract storage are reported.
+            for (key, value) in &values.storage {
+                if key.len() != H256::len_bytes() {
+                    continue;
+                }
+                let entry = storage.entry(H256::from_slice(key)).or_default();
+                let value = Some(H256::from_uint(value));
+                if is_post {
+                    entry.1 = value;
+                } else {
+                    entry.0 = value;
+                }
+            }
+        }
+
+        AccountDiff {
+            balance: Diff::new(
+                pre.as_ref().map(|v| v.balance),
+                post.as_ref().map(|v| v.balance),
+            ),
+            nonce: Diff::new(
+                pre.as_ref().map(|v| v.nonce),
+                post.as_ref().map(|v| v.nonce),
+            ),
+            code: Diff::new(
+                pre.as_ref().map(|v| v.code.clone().into()),
+                post.as_ref().map(|v| v.code.clone().into()),
+            ),
+            storage: storage
+                .into_iter()
+                .map(|(key, (pre, post))| (key, Diff::new(pre, post)))
+                .collect(),
+        }
+    }
+}
+
+/// The changes of the accounts, serialized as a map keyed by the address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateDiff<Addr = H160>(pub Vec<(Addr, AccountDiff)>);
+
+impl<Addr> StateDiff<Addr> {
+    /// Collect the changes of the accounts in `space`, with their addresses
+    /// converted by `address`.
+    pub fn new<F, E>(
+        diffs: Vec<PrimitiveAccountDiff>, space: Space, address: F,
+    ) -> Result<Self, E>
+    where
+        F: Fn(H160) -> Result<Addr, E>,
+    {
+        diffs
+            .into_iter()
+            .filter(|diff| diff.address.space == space)
+            .map(|diff| {
+                Ok((
+                    address(diff.address.address)?,
+                    AccountDiff::new(diff.pre, diff.post),
+                ))
+            })
+            .collect::<Result<_, E>>()
+            .map(StateDiff)
+    }
+}
+
+impl<Addr: Serialize> Serialize for StateDiff<Addr> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazze_types::AddressSpaceUtil;
+    use serde_json;
+
+    #[test]
+    fn test_serialize_state_diff() {
+        let address = H160::from_low_u64_be(1);
+        let contract = H160::from_low_u64_be(2);
+        let key = H256::from_low_u64_be(3).0.to_vec();
+        let values = |balance: u64, storage: Option<u64>| AccountValues {
+            balance: balance.into(),
+            nonce: 0.into(),
+            code: vec![],
+            storage: storage
+                .map(|value| (key.clone(), value.into()))
+                .into_iter()
+                .collect(),
+        };
+        let diffs = vec![
+            PrimitiveAccountDiff {
+                address: address.with_evm_space(),
+                pre: Some(values(100, None)),
+                post: Some(values(90, None)),
+            },
+            PrimitiveAccountDiff {
+                address: contract.with_evm_space(),
+                pre: None,
+                post: Some(values(10, Some(1))),
+            },
+            // Filtered by the space.
+            PrimitiveAccountDiff {
+                address: address.with_native_space(),
+                pre: None,
+                post: Some(values(1, None)),
+            },
+        ];
+
+        let state_diff =
+            StateDiff::new(diffs, Space::Ethereum, Ok::<_, ()>).unwrap();
+        assert_eq!(
+            serde_json::to_string(&state_diff).unwrap(),
+            r#"{"0x0000000000000000000000000000000000000001":{"balance":{"*":{"from":"0x64","to":"0x5a"}},"nonce":"=","code":"=","storage":{}},"0x0000000000000000000000000000000000000002":{"balance":{"+":"0xa"},"nonce":{"+":"0x0"},"code":{"+":"0x"},"storage":{"0x0000000000000000000000000000000000000000000000000000000000000003":{"+":"0x0000000000000000000000000000000000000000000000000000000000000001"}}}}"#
+        );
+    }
+}
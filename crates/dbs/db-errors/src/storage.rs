@@ -15,6 +15,7 @@ error_chain! {
         Account(AccountError);
         Io(io::Error);
         IntegerConversionError(std::num::TryFromIntError);
+        MdbxError(libmdbx::Error);
         ParseIntError(num::ParseIntError);
         RlpDecodeError(rlp::DecoderError);
         SqliteError(sqlite::Error);
@@ -42,14 +42,25 @@ impl DeltaMptIterator {
 
         Ok(())
     }
+
+    /// Recompute the merkle root of the trie from its key-values, which are
+    /// streamed into the merger in key order.
+    pub fn compute_merkle_root(&self) -> Result<MerkleHash> {
+        MptMerger::new(None, &mut SimpleMpt::new_discard_write())
+            .merge_delta_mpt(self)
+    }
 }
 
 use crate::{
     impls::{
         delta_mpt::{CowNodeRef, DeltaMpt, NodeRefDeltaMpt},
         errors::Result,
-        merkle_patricia_trie::{CompressedPathRaw, KVInserter, MptKeyValue},
+        merkle_patricia_trie::{
+            simple_mpt::SimpleMpt, CompressedPathRaw, KVInserter, MptKeyValue,
+            MptMerger,
+        },
     },
     utils::guarded_value::GuardedValue,
 };
+use primitives::MerkleHash;
 use std::sync::Arc;
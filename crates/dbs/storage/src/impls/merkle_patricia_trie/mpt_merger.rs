@@ -71,6 +71,30 @@ impl<'a> MptMerger<'a> {
         self.rw_cursor.finish()
     }
 
+    /// Insert the key-values of a delta MPT as it's iterated. Tombstones are
+    /// values of the delta MPT, so they are inserted rather than deleted.
+    pub fn merge_delta_mpt(
+        &mut self, delta_mpt: &DeltaMptIterator,
+    ) -> Result<MerkleHash> {
+        self.rw_cursor.load_root(false)?;
+
+        struct Inserter<'x, 'a: 'x> {
+            merger: &'x mut MptMerger<'a>,
+        }
+
+        impl<'x, 'a: 'x> KVInserter<MptKeyValue> for Inserter<'x, 'a> {
+            fn push(&mut self, v: MptKeyValue) -> Result<()> {
+                let (key, value) = v;
+                self.merger.rw_cursor.insert(&key, value)?;
+                Ok(())
+            }
+        }
+
+        delta_mpt.iterate(&mut Inserter { merger: self })?;
+
+        self.rw_cursor.finish()
+    }
+
     /// The iterators operate on key, value store.
     pub fn merge_insertion_deletion_separated<'k>(
         &mut self,
@@ -183,6 +207,7 @@ impl GetRwMpt for MergeMptsInRequest<'_> {
 
 use crate::{
     impls::{
+        delta_mpt::DeltaMptIterator,
         errors::*,
         merkle_patricia_trie::{mpt_cursor::*, KVInserter, MptKeyValue},
     },
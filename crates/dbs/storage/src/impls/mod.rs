@@ -36,6 +36,9 @@ pub mod defaults {
     /// Limit the number of open snapshots to set an upper limit on open files
     /// in Storage subsystem.
     pub const DEFAULT_MAX_OPEN_SNAPSHOTS: u16 = 10;
+    /// The upper limit of the memory map of each MDBX state db, when
+    /// `mdbx_map_size_mb` isn't set.
+    pub const DEFAULT_MDBX_MAP_SIZE_MB: u64 = 65536;
     pub const MAX_CACHED_TRIE_NODES_R_LFU_COUNTER: u32 =
         DeltaMptsNodeMemoryManager::MAX_CACHED_TRIE_NODES_R_LFU_COUNTER;
 
@@ -26,6 +26,68 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
         snapshot_db_manager: &SnapshotDbManager, epoch_id: &EpochId,
         epoch_height: u64,
     ) -> Result<Self> {
+        let chunk_index_by_upper_key = Self::check_chunk_boundaries(
+            number_chunks,
+            &chunk_boundaries,
+            &chunk_boundary_proofs,
+            &merkle_root,
+        )?;
+        let temp_snapshot_db = snapshot_db_manager
+            .new_temp_snapshot_for_full_sync(
+                epoch_id,
+                &merkle_root,
+                epoch_height,
+            )?;
+
+        Ok(Self::new_with_temp_snapshot_db(
+            number_chunks,
+            chunk_boundaries,
+            chunk_boundary_proofs,
+            merkle_root,
+            chunk_index_by_upper_key,
+            temp_snapshot_db,
+        ))
+    }
+
+    /// Reopen the temp snapshot of a restoration interrupted by a restart.
+    /// The chunks restored before the restart must be added back with
+    /// `resume_chunk`. Return `None` if the temp snapshot doesn't exist.
+    pub fn open(
+        number_chunks: usize, chunk_boundaries: Vec<Vec<u8>>,
+        chunk_boundary_proofs: Vec<TrieProof>, merkle_root: MerkleHash,
+        snapshot_db_manager: &SnapshotDbManager, epoch_id: &EpochId,
+        epoch_height: u64,
+    ) -> Result<Option<Self>> {
+        let chunk_index_by_upper_key = Self::check_chunk_boundaries(
+            number_chunks,
+            &chunk_boundaries,
+            &chunk_boundary_proofs,
+            &merkle_root,
+        )?;
+        let temp_snapshot_db = match snapshot_db_manager
+            .open_temp_snapshot_for_full_sync(
+                epoch_id,
+                &merkle_root,
+                epoch_height,
+            )? {
+            Some(temp_snapshot_db) => temp_snapshot_db,
+            None => return Ok(None),
+        };
+
+        Ok(Some(Self::new_with_temp_snapshot_db(
+            number_chunks,
+            chunk_boundaries,
+            chunk_boundary_proofs,
+            merkle_root,
+            chunk_index_by_upper_key,
+            temp_snapshot_db,
+        )))
+    }
+
+    fn check_chunk_boundaries(
+        number_chunks: usize, chunk_boundaries: &Vec<Vec<u8>>,
+        chunk_boundary_proofs: &Vec<TrieProof>, merkle_root: &MerkleHash,
+    ) -> Result<HashMap<Vec<u8>, usize>> {
         if number_chunks != chunk_boundaries.len() + 1 {
             bail!(ErrorKind::InvalidSnapshotSyncProof)
         }
@@ -53,8 +115,16 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
             chunk_index_by_upper_key
                 .insert(chunk_boundary.clone(), chunk_index);
         }
+        Ok(chunk_index_by_upper_key)
+    }
 
-        Ok(Self {
+    fn new_with_temp_snapshot_db(
+        number_chunks: usize, chunk_boundaries: Vec<Vec<u8>>,
+        chunk_boundary_proofs: Vec<TrieProof>, merkle_root: MerkleHash,
+        chunk_index_by_upper_key: HashMap<Vec<u8>, usize>,
+        temp_snapshot_db: SnapshotDbManager::SnapshotDbWrite,
+    ) -> Self {
+        Self {
             number_chunks,
             merkle_root,
             chunk_boundaries,
@@ -64,24 +134,31 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
             pending_boundary_nodes: Default::default(),
             boundary_subtree_total_size: Default::default(),
             chunk_index_by_upper_key,
-            temp_snapshot_db: snapshot_db_manager
-                .new_temp_snapshot_for_full_sync(
-                    epoch_id,
-                    &merkle_root,
-                    epoch_height,
-                )?,
-        })
+            temp_snapshot_db,
+        }
     }
 
     pub fn is_completed(&self) -> bool {
         self.number_incomplete_chunk == 0
     }
 
-    // FIXME: multi-threading, where &mut can be dropped.
     pub fn restore_chunk<Key: Borrow<[u8]> + Debug>(
         &mut self, chunk_upper_key: &Option<Vec<u8>>, keys: &Vec<Key>,
         values: Vec<Vec<u8>>,
     ) -> Result<bool> {
+        Ok(self
+            .restore_chunk_with_boundary(chunk_upper_key, keys, values)?
+            .is_some())
+    }
+
+    /// Same as `restore_chunk`, but return the boundary nodes of the chunk
+    /// when it's restored, so that the restoration can be resumed after a
+    /// restart.
+    // FIXME: multi-threading, where &mut can be dropped.
+    pub fn restore_chunk_with_boundary<Key: Borrow<[u8]> + Debug>(
+        &mut self, chunk_upper_key: &Option<Vec<u8>>, keys: &Vec<Key>,
+        values: Vec<Vec<u8>>,
+    ) -> Result<Option<RestoredChunkBoundary>> {
         let chunk_index = match chunk_upper_key {
             None => self.number_chunks - 1,
             Some(upper_key) => {
@@ -89,7 +166,7 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
                     Some(index) => *index,
                     None => {
                         warn!("chunk key {:?} does not match boundaries in manifest", upper_key);
-                        return Ok(false);
+                        return Ok(None);
                     }
                 }
             }
@@ -100,7 +177,7 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
             for key in &keys[1..] {
                 if key.borrow().le(previous.borrow()) {
                     warn!("chunk key not in order");
-                    return Ok(false);
+                    return Ok(None);
                 }
                 previous = key;
             }
@@ -124,7 +201,7 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
                         "first chunk key {:?} less than left range {:?}",
                         first_key, key_range_left
                     );
-                    return Ok(false);
+                    return Ok(None);
                 }
             }
         };
@@ -143,7 +220,7 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
                         "last chunk key {:?} larger than left range {:?}",
                         last_key, key_range_right_excl,
                     );
-                    return Ok(false);
+                    return Ok(None);
                 }
             }
 
@@ -161,69 +238,119 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
         );
 
         let chunk_rebuilder = chunk_verifier.restore(keys, &values)?;
-        if chunk_rebuilder.is_valid {
-            self.chunk_verified[chunk_index] = true;
-            self.number_incomplete_chunk -= 1;
-
-            self.temp_snapshot_db.start_transaction()?;
-            // Commit key-values.
-            for (key, value) in keys.into_iter().zip(values.into_iter()) {
-                self.temp_snapshot_db.put_kv(key.borrow(), &*value)?;
-            }
+        if !chunk_rebuilder.is_valid {
+            return Ok(None);
+        }
 
-            // Commit inner nodes.
-            let mut snapshot_mpt =
-                self.temp_snapshot_db.open_snapshot_mpt_owned()?;
-            for (path, node) in chunk_rebuilder.inner_nodes_to_write {
-                snapshot_mpt.write_node(&path, &node)?;
-            }
-            drop(snapshot_mpt);
-            self.temp_snapshot_db.commit_transaction()?;
-
-            // Combine changes around boundary nodes.
-            for (path, node) in chunk_rebuilder.boundary_nodes {
-                let mut children_table = VanillaChildrenTable::default();
-                unsafe {
-                    for (child_index, merkle_ref) in
-                        node.get_children_table_ref().iter()
-                    {
-                        *children_table.get_child_mut_unchecked(child_index) =
-                            SubtreeMerkleWithSize {
-                                merkle: *merkle_ref,
-                                subtree_size: 0,
-                                delta_subtree_size: 0,
-                            }
-                    }
-                    *children_table.get_children_count_mut() =
-                        node.get_children_count();
+        self.temp_snapshot_db.start_transaction()?;
+        // Commit key-values.
+        for (key, value) in keys.into_iter().zip(values.into_iter()) {
+            self.temp_snapshot_db.put_kv(key.borrow(), &*value)?;
+        }
+
+        // Commit inner nodes.
+        let mut snapshot_mpt =
+            self.temp_snapshot_db.open_snapshot_mpt_owned()?;
+        for (path, node) in chunk_rebuilder.inner_nodes_to_write {
+            snapshot_mpt.write_node(&path, &node)?;
+        }
+        drop(snapshot_mpt);
+        self.temp_snapshot_db.commit_transaction()?;
+
+        // Changes around boundary nodes are combined after all chunks are
+        // restored.
+        let mut boundary_nodes = vec![];
+        for (path, node) in chunk_rebuilder.boundary_nodes {
+            let mut children_table = VanillaChildrenTable::default();
+            unsafe {
+                for (child_index, merkle_ref) in
+                    node.get_children_table_ref().iter()
+                {
+                    *children_table.get_child_mut_unchecked(child_index) =
+                        SubtreeMerkleWithSize {
+                            merkle: *merkle_ref,
+                            subtree_size: 0,
+                            delta_subtree_size: 0,
+                        }
                 }
-                self.pending_boundary_nodes.insert(
-                    path,
-                    SnapshotMptNode(VanillaTrieNode::new(
-                        node.get_merkle().clone(),
-                        children_table,
-                        node.value_as_slice()
-                            .into_option()
-                            .map(|ref_v| ref_v.into()),
-                        node.compressed_path_ref().into(),
-                    )),
-                );
-            }
-            for (subtree_index, subtree_size) in
-                chunk_rebuilder.boundary_subtree_total_size
-            {
-                *self
-                    .boundary_subtree_total_size
-                    .entry(subtree_index)
-                    .or_default() += subtree_size;
+                *children_table.get_children_count_mut() =
+                    node.get_children_count();
             }
+            boundary_nodes.push((
+                path,
+                SnapshotMptNode(VanillaTrieNode::new(
+                    node.get_merkle().clone(),
+                    children_table,
+                    node.value_as_slice()
+                        .into_option()
+                        .map(|ref_v| ref_v.into()),
+                    node.compressed_path_ref().into(),
+                )),
+            ));
         }
+        let restored_chunk_boundary = RestoredChunkBoundary {
+            chunk_index,
+            boundary_nodes,
+            boundary_subtree_total_size: chunk_rebuilder
+                .boundary_subtree_total_size
+                .into_iter()
+                .map(|(subtree_index, subtree_size)| {
+                    (
+                        subtree_index.parent_node,
+                        subtree_index.child_index,
+                        subtree_size,
+                    )
+                })
+                .collect(),
+        };
+        self.add_chunk_boundary(&restored_chunk_boundary);
 
         if self.is_completed() {
             self.finalize()?
         }
 
-        Ok(chunk_rebuilder.is_valid)
+        Ok(Some(restored_chunk_boundary))
+    }
+
+    /// Add back a chunk which was restored into the temp snapshot before a
+    /// restart.
+    pub fn resume_chunk(
+        &mut self, restored_chunk_boundary: &RestoredChunkBoundary,
+    ) -> Result<()> {
+        let chunk_index = restored_chunk_boundary.chunk_index;
+        if chunk_index >= self.number_chunks || self.chunk_verified[chunk_index]
+        {
+            bail!(ErrorKind::InvalidSnapshotSyncProof)
+        }
+        self.add_chunk_boundary(restored_chunk_boundary);
+
+        if self.is_completed() {
+            self.finalize()?
+        }
+        Ok(())
+    }
+
+    fn add_chunk_boundary(
+        &mut self, restored_chunk_boundary: &RestoredChunkBoundary,
+    ) {
+        self.chunk_verified[restored_chunk_boundary.chunk_index] = true;
+        self.number_incomplete_chunk -= 1;
+
+        for (path, node) in &restored_chunk_boundary.boundary_nodes {
+            self.pending_boundary_nodes
+                .insert(path.clone(), node.clone());
+        }
+        for (parent_node, child_index, subtree_size) in
+            &restored_chunk_boundary.boundary_subtree_total_size
+        {
+            *self
+                .boundary_subtree_total_size
+                .entry(BoundarySubtreeIndex {
+                    parent_node: *parent_node,
+                    child_index: *child_index,
+                })
+                .or_default() += subtree_size;
+        }
     }
 
     // FIXME: multi-threading
@@ -261,6 +388,61 @@ impl<SnapshotDbManager: SnapshotDbManagerTrait>
     }
 }
 
+/// The boundary nodes of a restored chunk, which are only written after all
+/// chunks are restored. The caller keeps them to resume the restoration with
+/// `FullSyncVerifier::resume_chunk` after a restart.
+#[derive(Clone, Debug, Default)]
+pub struct RestoredChunkBoundary {
+    pub chunk_index: usize,
+    boundary_nodes: Vec<(CompressedPathRaw, SnapshotMptNode)>,
+    /// The total size of boundary subtrees, indexed by the merkle of the
+    /// parent node and the child index.
+    boundary_subtree_total_size: Vec<(MerkleHash, u8, u64)>,
+}
+
+impl Encodable for RestoredChunkBoundary {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3).append(&self.chunk_index);
+        s.begin_list(self.boundary_nodes.len());
+        for (path, node) in &self.boundary_nodes {
+            s.begin_list(2).append(path).append(&node.0);
+        }
+        s.begin_list(self.boundary_subtree_total_size.len());
+        for (parent_node, child_index, subtree_size) in
+            &self.boundary_subtree_total_size
+        {
+            s.begin_list(3)
+                .append(parent_node)
+                .append(child_index)
+                .append(subtree_size);
+        }
+    }
+}
+
+impl Decodable for RestoredChunkBoundary {
+    fn decode(rlp: &Rlp) -> std::result::Result<Self, DecoderError> {
+        Ok(Self {
+            chunk_index: rlp.val_at(0)?,
+            boundary_nodes: rlp
+                .at(1)?
+                .iter()
+                .map(|item| Ok((item.val_at(0)?, item.val_at(1)?)))
+                .collect::<std::result::Result<_, DecoderError>>()?,
+            boundary_subtree_total_size: rlp
+                .at(2)?
+                .iter()
+                .map(|item| {
+                    Ok((item.val_at(0)?, item.val_at(1)?, item.val_at(2)?))
+                })
+                .collect::<std::result::Result<_, DecoderError>>()?,
+        })
+    }
+}
+
+// The boundary nodes are persisted in the blocks db with the state sync
+// progress.
+mazze_internal_common::impl_db_encoding_as_rlp!(RestoredChunkBoundary);
+
 use crate::{
     impls::{
         errors::*,
@@ -279,4 +461,5 @@ use crate::{
     TrieProof,
 };
 use primitives::{EpochId, MerkleHash};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::{borrow::Borrow, collections::HashMap, fmt::Debug};
@@ -6,4 +6,4 @@ pub mod full_sync_verifier;
 pub(in super::super::super) mod mpt_slice_verifier;
 mod slice_restore_read_write_path_node;
 
-pub use self::full_sync_verifier::FullSyncVerifier;
+pub use self::full_sync_verifier::{FullSyncVerifier, RestoredChunkBoundary};
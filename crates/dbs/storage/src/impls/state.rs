@@ -794,6 +794,33 @@ impl State {
         inserter.iterate(dumper)
     }
 
+    /// Recompute the state root from the key-values of the snapshot, the
+    /// intermediate MPT and the delta MPT, instead of reading the merkle
+    /// roots stored in them.
+    pub fn recompute_state_root(&self) -> Result<StateRoot> {
+        let intermediate_delta_root = match &self.maybe_intermediate_trie {
+            None => MERKLE_NULL_NODE,
+            Some(intermediate_trie) => DeltaMptIterator {
+                mpt: intermediate_trie.clone(),
+                maybe_root_node: self.intermediate_trie_root.clone(),
+            }
+            .compute_merkle_root()?,
+        };
+        let delta_root = DeltaMptIterator {
+            mpt: self.delta_trie.clone(),
+            maybe_root_node: self.delta_trie_root.clone(),
+        }
+        .compute_merkle_root()?;
+
+        Ok(StateRoot {
+            snapshot_root: StorageManager::compute_snapshot_merkle_root(
+                &self.snapshot_db,
+            )?,
+            intermediate_delta_root,
+            delta_root,
+        })
+    }
+
     fn revert(&mut self) {
         self.dirty = false;
 
@@ -965,6 +992,7 @@ use crate::{
         node_merkle_proof::NodeMerkleProof,
         state_manager::*,
         state_proof::StateProof,
+        storage_manager::storage_manager::StorageManager,
     },
     state::*,
     storage_db::*,
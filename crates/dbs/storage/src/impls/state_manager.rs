@@ -2,8 +2,8 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-pub type DeltaDbManager = DeltaDbManagerRocksdb;
-pub type SnapshotDbManager = SnapshotDbManagerSqlite;
+pub type DeltaDbManager = DeltaDbManagerByBackend;
+pub type SnapshotDbManager = SnapshotDbManagerByBackend;
 pub type SnapshotDb = <SnapshotDbManager as SnapshotDbManagerTrait>::SnapshotDb;
 
 pub struct StateTrees {
@@ -60,6 +60,7 @@ impl StateManager {
                 conf.single_mpt_cache_start_size,
                 conf.single_mpt_cache_size,
                 conf.single_mpt_slab_idle_size,
+                &conf.state_db_backend,
             ))
         } else {
             None
@@ -639,6 +640,21 @@ impl StateManager {
         }
     }
 
+    /// Recompute the state root at `state_index` from the key-values of its
+    /// tries. Return `None` if the state isn't available.
+    pub fn recompute_state_root(
+        self: &Arc<Self>, state_index: StateIndex,
+    ) -> Result<Option<StateRoot>> {
+        match self.get_state_no_commit_inner(
+            state_index,
+            /* try_open = */ false,
+            /* open_mpt_snapshot = */ false,
+        )? {
+            None => Ok(None),
+            Some(state) => Ok(Some(state.recompute_state_root()?)),
+        }
+    }
+
     fn get_state_for_genesis_write_inner(self: &Arc<Self>) -> State {
         State::new(
             self.clone(),
@@ -830,8 +846,8 @@ use crate::{
         errors::*,
         replicated_state::ReplicatedState,
         storage_db::{
-            delta_db_manager_rocksdb::DeltaDbManagerRocksdb,
-            snapshot_db_manager_sqlite::SnapshotDbManagerSqlite,
+            delta_db_manager_by_backend::DeltaDbManagerByBackend,
+            snapshot_db_manager_by_backend::SnapshotDbManagerByBackend,
         },
         storage_manager::{
             single_mpt_storage_manager::SingleMptStorageManager,
@@ -847,7 +863,7 @@ use crate::{
 use malloc_size_of_derive::MallocSizeOf as MallocSizeOfDerive;
 use mazze_types::Space;
 use primitives::{
-    DeltaMptKeyPadding, EpochId, MerkleHash, StorageKeyWithSpace,
+    DeltaMptKeyPadding, EpochId, MerkleHash, StateRoot, StorageKeyWithSpace,
     GENESIS_DELTA_MPT_KEY_PADDING, MERKLE_NULL_NODE, NULL_EPOCH,
 };
 use std::sync::{
@@ -138,9 +138,62 @@ impl StateProof {
     }
 }
 
+/// A `StateProof` together with the state roots it is checked against, so
+/// that a state entry can be verified offline given only the state root
+/// hashes committed in block headers.
+///
+/// `prev_snapshot_state_root` is the state root of the epoch
+/// `snapshot_epoch_count` epochs before, from which the key padding of the
+/// intermediate trie is derived. It is `None` for the epochs of the first
+/// snapshot period.
+#[derive(Clone, Debug, Default, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct StateProofWithRoots {
+    pub state_proof: StateProof,
+    pub state_root: StateRoot,
+    pub prev_snapshot_state_root: Option<StateRoot>,
+}
+
+impl StateProofWithRoots {
+    /// Check that `key` maps to `value` (or is absent if `value` is `None`)
+    /// in the state whose root hash is `state_root_hash`.
+    /// `prev_snapshot_state_root_hash` must be the state root hash of the
+    /// epoch `snapshot_epoch_count` epochs before, if any.
+    pub fn verify(
+        &self, key: &[u8], value: Option<&[u8]>, state_root_hash: &MerkleHash,
+        prev_snapshot_state_root_hash: Option<&MerkleHash>,
+    ) -> bool {
+        if self.state_root.compute_state_root_hash() != *state_root_hash {
+            return false;
+        }
+
+        let maybe_intermediate_padding = match (
+            &self.prev_snapshot_state_root,
+            prev_snapshot_state_root_hash,
+        ) {
+            (None, None) => None,
+            (Some(root), Some(hash))
+                if root.compute_state_root_hash() == *hash =>
+            {
+                Some(StorageKeyWithSpace::delta_mpt_padding(
+                    &root.snapshot_root,
+                    &root.intermediate_delta_root,
+                ))
+            }
+            _ => return false,
+        };
+
+        self.state_proof.is_valid_kv(
+            &key.to_vec(),
+            value,
+            self.state_root.clone(),
+            maybe_intermediate_padding,
+        )
+    }
+}
+
 use crate::impls::merkle_patricia_trie::TrieProof;
 use primitives::{
-    CheckInput, DeltaMptKeyPadding, MptValue, StateRoot, StorageKeyWithSpace,
-    MERKLE_NULL_NODE,
+    CheckInput, DeltaMptKeyPadding, MerkleHash, MptValue, StateRoot,
+    StorageKeyWithSpace, MERKLE_NULL_NODE,
 };
 use rlp_derive::{RlpDecodable, RlpEncodable};
@@ -0,0 +1,256 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The Delta MPT db manager of the backend selected by `state_db_type`.
+
+pub enum DeltaDbManagerByBackend {
+    Rocksdb(DeltaDbManagerRocksdb),
+    Mdbx(DeltaDbManagerMdbx),
+}
+
+impl DeltaDbManagerByBackend {
+    pub fn new(
+        delta_db_path: PathBuf, state_db_backend: &StateDbBackend,
+    ) -> Result<DeltaDbManagerByBackend> {
+        let (manager, other_backend_prefix) = match state_db_backend {
+            StateDbBackend::Rocksdb => (
+                DeltaDbManagerByBackend::Rocksdb(DeltaDbManagerRocksdb::new(
+                    delta_db_path.clone(),
+                )?),
+                DeltaDbManagerMdbx::DELTA_DB_MDBX_DIR_PREFIX,
+            ),
+            StateDbBackend::Mdbx(mdbx_config) => (
+                DeltaDbManagerByBackend::Mdbx(DeltaDbManagerMdbx::new(
+                    delta_db_path.clone(),
+                    mdbx_config.clone(),
+                )?),
+                DeltaDbManagerRocksdb::DELTA_DB_ROCKSDB_DIR_PREFIX,
+            ),
+        };
+
+        // The delta dbs which don't belong to the selected backend are
+        // deleted as unexpected files when the persisted state is scanned, so
+        // refuse to switch the backend of an existing data dir.
+        for entry in fs::read_dir(&delta_db_path)? {
+            let entry = entry?;
+            if entry
+                .file_name()
+                .to_str()
+                .map_or(false, |name| name.starts_with(other_backend_prefix))
+            {
+                bail!(
+                    "Delta db {} was created by another state_db_type.",
+                    entry.path().display()
+                );
+            }
+        }
+
+        Ok(manager)
+    }
+}
+
+impl DeltaDbManagerTrait for DeltaDbManagerByBackend {
+    type DeltaDb = DeltaDbByBackend;
+
+    fn get_delta_db_dir(&self) -> &Path {
+        match self {
+            Self::Rocksdb(manager) => manager.get_delta_db_dir(),
+            Self::Mdbx(manager) => manager.get_delta_db_dir(),
+        }
+    }
+
+    fn get_delta_db_name(&self, snapshot_epoch_id: &EpochId) -> String {
+        match self {
+            Self::Rocksdb(manager) => {
+                manager.get_delta_db_name(snapshot_epoch_id)
+            }
+            Self::Mdbx(manager) => manager.get_delta_db_name(snapshot_epoch_id),
+        }
+    }
+
+    fn get_delta_db_path(&self, delta_db_name: &str) -> PathBuf {
+        match self {
+            Self::Rocksdb(manager) => manager.get_delta_db_path(delta_db_name),
+            Self::Mdbx(manager) => manager.get_delta_db_path(delta_db_name),
+        }
+    }
+
+    fn new_empty_delta_db(&self, delta_db_name: &str) -> Result<Self::DeltaDb> {
+        match self {
+            Self::Rocksdb(manager) => manager
+                .new_empty_delta_db(delta_db_name)
+                .map(DeltaDbByBackend::Rocksdb),
+            Self::Mdbx(manager) => manager
+                .new_empty_delta_db(delta_db_name)
+                .map(DeltaDbByBackend::Mdbx),
+        }
+    }
+
+    fn get_delta_db(
+        &self, delta_db_name: &str,
+    ) -> Result<Option<Self::DeltaDb>> {
+        match self {
+            Self::Rocksdb(manager) => Ok(manager
+                .get_delta_db(delta_db_name)?
+                .map(DeltaDbByBackend::Rocksdb)),
+            Self::Mdbx(manager) => Ok(manager
+                .get_delta_db(delta_db_name)?
+                .map(DeltaDbByBackend::Mdbx)),
+        }
+    }
+
+    fn destroy_delta_db(&self, delta_db_name: &str) -> Result<()> {
+        match self {
+            Self::Rocksdb(manager) => manager.destroy_delta_db(delta_db_name),
+            Self::Mdbx(manager) => manager.destroy_delta_db(delta_db_name),
+        }
+    }
+}
+
+pub enum DeltaDbByBackend {
+    Rocksdb(KvdbRocksdb),
+    Mdbx(KvdbMdbx),
+}
+
+impl MallocSizeOf for DeltaDbByBackend {
+    fn size_of(&self, _ops: &mut MallocSizeOfOps) -> usize {
+        0
+    }
+}
+
+impl KeyValueDbTypes for DeltaDbByBackend {
+    type ValueType = Box<[u8]>;
+}
+
+impl KeyValueDbTraitRead for DeltaDbByBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        match self {
+            Self::Rocksdb(db) => db.get(key),
+            Self::Mdbx(db) => db.get(key),
+        }
+    }
+}
+
+mark_kvdb_multi_reader!(DeltaDbByBackend);
+
+impl KeyValueDbTraitTransactional for DeltaDbByBackend {
+    type TransactionType = DeltaDbTransactionByBackend;
+
+    fn start_transaction(
+        &self, immediate_write: bool,
+    ) -> Result<Self::TransactionType> {
+        match self {
+            Self::Rocksdb(db) => db
+                .start_transaction(immediate_write)
+                .map(DeltaDbTransactionByBackend::Rocksdb),
+            Self::Mdbx(db) => db
+                .start_transaction(immediate_write)
+                .map(DeltaDbTransactionByBackend::Mdbx),
+        }
+    }
+}
+
+impl DeltaDbTrait for DeltaDbByBackend {}
+
+pub enum DeltaDbTransactionByBackend {
+    Rocksdb(KvdbRocksDbTransaction),
+    Mdbx(KvdbMdbxTransaction),
+}
+
+impl KeyValueDbTypes for DeltaDbTransactionByBackend {
+    type ValueType = Box<[u8]>;
+}
+
+impl KeyValueDbTraitSingleWriter for DeltaDbTransactionByBackend {
+    fn delete(&mut self, key: &[u8]) -> Result<Option<Option<Box<[u8]>>>> {
+        match self {
+            Self::Rocksdb(transaction) => transaction.delete(key),
+            Self::Mdbx(transaction) => transaction.delete(key),
+        }
+    }
+
+    fn put(
+        &mut self, key: &[u8], value: &[u8],
+    ) -> Result<Option<Option<Box<[u8]>>>> {
+        match self {
+            Self::Rocksdb(transaction) => transaction.put(key, value),
+            Self::Mdbx(transaction) => transaction.put(key, value),
+        }
+    }
+}
+
+impl KeyValueDbTraitOwnedRead for DeltaDbTransactionByBackend {
+    fn get_mut(&mut self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        match self {
+            Self::Rocksdb(transaction) => transaction.get_mut(key),
+            Self::Mdbx(transaction) => transaction.get_mut(key),
+        }
+    }
+}
+
+impl KeyValueDbTransactionTrait for DeltaDbTransactionByBackend {
+    fn commit(&mut self, db: &dyn Any) -> Result<()> {
+        // Each backend commits with the db that it wraps.
+        match (self, db.downcast_ref::<DeltaDbByBackend>()) {
+            (
+                Self::Rocksdb(transaction),
+                Some(DeltaDbByBackend::Rocksdb(db)),
+            ) => transaction.commit(db),
+            (Self::Mdbx(transaction), Some(DeltaDbByBackend::Mdbx(db))) => {
+                transaction.commit(db)
+            }
+            _ => {
+                unreachable!();
+            }
+        }
+    }
+
+    fn revert(&mut self) -> Result<()> {
+        match self {
+            Self::Rocksdb(transaction) => transaction.revert(),
+            Self::Mdbx(transaction) => transaction.revert(),
+        }
+    }
+
+    fn restart(
+        &mut self, immediate_write: bool, no_revert: bool,
+    ) -> Result<()> {
+        match self {
+            Self::Rocksdb(transaction) => {
+                transaction.restart(immediate_write, no_revert)
+            }
+            Self::Mdbx(transaction) => {
+                transaction.restart(immediate_write, no_revert)
+            }
+        }
+    }
+}
+
+impl Drop for DeltaDbTransactionByBackend {
+    fn drop(&mut self) {
+        // No-op
+    }
+}
+
+use super::{
+    super::{
+        super::storage_db::{
+            delta_db_manager::{DeltaDbManagerTrait, DeltaDbTrait},
+            key_value_db::*,
+        },
+        errors::*,
+    },
+    delta_db_manager_mdbx::DeltaDbManagerMdbx,
+    delta_db_manager_rocksdb::DeltaDbManagerRocksdb,
+    kvdb_mdbx::{KvdbMdbx, KvdbMdbxTransaction},
+    kvdb_rocksdb::{KvdbRocksDbTransaction, KvdbRocksdb},
+};
+use crate::StateDbBackend;
+use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
+use primitives::EpochId;
+use std::{
+    any::Any,
+    fs,
+    path::{Path, PathBuf},
+};
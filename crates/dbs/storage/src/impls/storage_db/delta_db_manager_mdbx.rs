@@ -0,0 +1,85 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+pub struct DeltaDbManagerMdbx {
+    delta_db_path: PathBuf,
+    mdbx_config: MdbxConfig,
+    creation_mutex: Mutex<()>,
+}
+
+impl DeltaDbManagerMdbx {
+    pub const DELTA_DB_MDBX_DIR_PREFIX: &'static str = "mdbx_";
+
+    pub fn new(
+        delta_db_path: PathBuf, mdbx_config: MdbxConfig,
+    ) -> Result<DeltaDbManagerMdbx> {
+        if !delta_db_path.exists() {
+            fs::create_dir_all(delta_db_path.clone())?;
+        }
+
+        Ok(Self {
+            delta_db_path,
+            mdbx_config,
+            creation_mutex: Default::default(),
+        })
+    }
+}
+
+impl DeltaDbManagerTrait for DeltaDbManagerMdbx {
+    type DeltaDb = KvdbMdbx;
+
+    fn get_delta_db_dir(&self) -> &Path {
+        self.delta_db_path.as_path()
+    }
+
+    fn get_delta_db_name(&self, snapshot_epoch_id: &EpochId) -> String {
+        Self::DELTA_DB_MDBX_DIR_PREFIX.to_string()
+            + &snapshot_epoch_id.as_ref().to_hex::<String>()
+    }
+
+    fn get_delta_db_path(&self, delta_db_name: &str) -> PathBuf {
+        self.delta_db_path.join(delta_db_name)
+    }
+
+    fn new_empty_delta_db(&self, delta_db_name: &str) -> Result<Self::DeltaDb> {
+        let _lock = self.creation_mutex.lock();
+
+        let path = self.get_delta_db_path(delta_db_name);
+        if path.exists() {
+            Err(ErrorKind::DeltaMPTAlreadyExists.into())
+        } else {
+            KvdbMdbx::open(&path, &self.mdbx_config)
+        }
+    }
+
+    fn get_delta_db(
+        &self, delta_db_name: &str,
+    ) -> Result<Option<Self::DeltaDb>> {
+        let path = self.get_delta_db_path(delta_db_name);
+        if path.exists() {
+            Ok(Some(KvdbMdbx::open(&path, &self.mdbx_config)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn destroy_delta_db(&self, delta_db_name: &str) -> Result<()> {
+        Ok(fs::remove_dir_all(self.get_delta_db_path(delta_db_name))?)
+    }
+}
+
+use super::{
+    super::{
+        super::storage_db::delta_db_manager::DeltaDbManagerTrait, errors::*,
+    },
+    kvdb_mdbx::KvdbMdbx,
+};
+use crate::MdbxConfig;
+use parking_lot::Mutex;
+use primitives::EpochId;
+use rustc_hex::ToHex;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
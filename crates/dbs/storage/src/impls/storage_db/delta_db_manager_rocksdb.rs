@@ -8,7 +8,7 @@ pub struct DeltaDbManagerRocksdb {
 }
 
 impl DeltaDbManagerRocksdb {
-    const DELTA_DB_ROCKSDB_DIR_PREFIX: &'static str = "rocksdb_";
+    pub const DELTA_DB_ROCKSDB_DIR_PREFIX: &'static str = "rocksdb_";
     const ROCKSDB_CONFIG: DatabaseConfig = DatabaseConfig {
         max_open_files: 512,
         memory_budget: None,
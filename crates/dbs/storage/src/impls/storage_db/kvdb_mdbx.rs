@@ -2,18 +2,507 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use crate::impls::errors::Result;
-use error_chain::bail;
-use std::path::Path;
-
-#[allow(dead_code)]
+/// A key-value db on top of the unnamed table of an MDBX environment.
 pub struct KvdbMdbx {
-    _unreachable: (),
+    db: Arc<Database<NoWriteMap>>,
+}
+
+impl Clone for KvdbMdbx {
+    fn clone(&self) -> Self {
+        Self {
+            db: Arc::clone(&self.db),
+        }
+    }
 }
 
 impl KvdbMdbx {
-    #[allow(dead_code)]
-    pub fn open(_path: &Path) -> Result<Self> {
-        bail!("MDBX backend not implemented yet")
+    pub fn open(path: &Path, config: &MdbxConfig) -> Result<Self> {
+        Ok(Self {
+            db: Arc::new(Self::open_database(
+                path, config, /* max_tables = */ 0,
+            )?),
+        })
+    }
+
+    /// Open the MDBX environment at `path`, with room for `max_tables` named
+    /// tables besides the unnamed one.
+    pub fn open_database(
+        path: &Path, config: &MdbxConfig, max_tables: usize,
+    ) -> Result<Database<NoWriteMap>> {
+        if !path.exists() {
+            fs::create_dir_all(path)?;
+        }
+
+        let map_size_mb = config
+            .map_size_mb
+            .unwrap_or(defaults::DEFAULT_MDBX_MAP_SIZE_MB);
+        let sync_mode = match config.sync_mode {
+            MdbxSyncMode::Safe => SyncMode::Durable,
+            MdbxSyncMode::Relaxed => SyncMode::NoMetaSync,
+        };
+
+        let mut builder = Database::<NoWriteMap>::new();
+        builder
+            .set_geometry(Geometry {
+                size: Some(..(map_size_mb << 20) as usize),
+                ..Default::default()
+            })
+            .set_flags(DatabaseFlags {
+                mode: Mode::ReadWrite { sync_mode },
+                ..Default::default()
+            })
+            .set_max_tables(max_tables);
+        if let Some(max_readers) = config.max_readers {
+            builder.set_max_readers(max_readers);
+        }
+
+        Ok(builder.open(path)?)
+    }
+
+    fn get_impl(
+        db: &Database<NoWriteMap>, key: &[u8],
+    ) -> Result<Option<Box<[u8]>>> {
+        let txn = db.begin_ro_txn()?;
+        let table = txn.open_table(None)?;
+        Ok(txn
+            .get::<Vec<u8>>(&table, key)?
+            .map(|db_value| db_value.into_boxed_slice()))
+    }
+
+    /// Apply the operations atomically in one write transaction. A `None`
+    /// value deletes the key.
+    fn write_impl<'a>(
+        db: &Database<NoWriteMap>,
+        ops: impl Iterator<Item = (&'a [u8], Option<&'a [u8]>)>,
+    ) -> Result<()> {
+        let txn = db.begin_rw_txn()?;
+        let table = txn.open_table(None)?;
+        for (key, maybe_value) in ops {
+            match maybe_value {
+                Some(value) => {
+                    txn.put(&table, key, value, WriteFlags::empty())?;
+                }
+                None => {
+                    txn.del(&table, key, None)?;
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+impl MallocSizeOf for KvdbMdbx {
+    fn size_of(&self, _ops: &mut MallocSizeOfOps) -> usize {
+        0
+    }
+}
+
+pub struct KvdbMdbxTransaction {
+    db: Arc<Database<NoWriteMap>>,
+    /// The pending writes, where a `None` value is a deletion.
+    pending: Vec<(Box<[u8]>, Option<Box<[u8]>>)>,
+}
+
+impl KeyValueDbTraitRead for KvdbMdbx {
+    fn get(&self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        Self::get_impl(&self.db, key)
+    }
+}
+
+mark_kvdb_multi_reader!(KvdbMdbx);
+
+impl KeyValueDbTypes for KvdbMdbx {
+    type ValueType = Box<[u8]>;
+}
+
+impl KeyValueDbTrait for KvdbMdbx {
+    fn delete(&self, key: &[u8]) -> Result<Option<Option<Box<[u8]>>>> {
+        random_crash_if_enabled("mdbx delete");
+        Self::write_impl(&self.db, std::iter::once((key, None)))?;
+        Ok(None)
+    }
+
+    fn put(
+        &self, key: &[u8], value: &[u8],
+    ) -> Result<Option<Option<Box<[u8]>>>> {
+        random_crash_if_enabled("mdbx put");
+        Self::write_impl(&self.db, std::iter::once((key, Some(value))))?;
+        Ok(None)
+    }
+}
+
+impl KeyValueDbTypes for KvdbMdbxTransaction {
+    type ValueType = Box<[u8]>;
+}
+
+impl KeyValueDbTraitSingleWriter for KvdbMdbxTransaction {
+    fn delete(&mut self, key: &[u8]) -> Result<Option<Option<Box<[u8]>>>> {
+        self.pending.push((key.into(), None));
+        Ok(None)
+    }
+
+    fn put(
+        &mut self, key: &[u8], value: &[u8],
+    ) -> Result<Option<Option<Box<[u8]>>>> {
+        self.pending.push((key.into(), Some(value.into())));
+        Ok(None)
+    }
+}
+
+impl KeyValueDbTraitOwnedRead for KvdbMdbxTransaction {
+    fn get_mut(&mut self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        // The latest pending write of the key shadows the committed value.
+        match self.pending.iter().rev().find(|(k, _)| &**k == key) {
+            Some((_, maybe_value)) => Ok(maybe_value.clone()),
+            None => KvdbMdbx::get_impl(&self.db, key),
+        }
+    }
+}
+
+impl KeyValueDbTransactionTrait for KvdbMdbxTransaction {
+    fn commit(&mut self, db: &dyn Any) -> Result<()> {
+        random_crash_if_enabled("mdbx commit");
+        match db.downcast_ref::<KvdbMdbx>() {
+            Some(as_kvdb_mdbx) => {
+                KvdbMdbx::write_impl(
+                    &as_kvdb_mdbx.db,
+                    self.pending
+                        .iter()
+                        .map(|(key, value)| (&**key, value.as_deref())),
+                )?;
+                self.pending.clear();
+                Ok(())
+            }
+            None => {
+                unreachable!();
+            }
+        }
+    }
+
+    fn revert(&mut self) -> Result<()> {
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn restart(
+        &mut self, _immediate_write: bool, no_revert: bool,
+    ) -> Result<()> {
+        if !no_revert {
+            self.revert()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for KvdbMdbxTransaction {
+    fn drop(&mut self) {
+        // No-op
+    }
+}
+
+impl KeyValueDbTraitTransactional for KvdbMdbx {
+    type TransactionType = KvdbMdbxTransaction;
+
+    fn start_transaction(
+        &self, _immediate_write: bool,
+    ) -> Result<Self::TransactionType> {
+        Ok(KvdbMdbxTransaction {
+            db: self.db.clone(),
+            pending: vec![],
+        })
+    }
+}
+
+impl DeltaDbTrait for KvdbMdbx {}
+
+pub struct KvdbMdbxIteratorTag();
+
+/// The value types of the tables iterated by `KvdbMdbxRangeIter`.
+pub trait KvdbMdbxValue: 'static + Sized {
+    fn from_db_value(db_value: Vec<u8>) -> Self;
+}
+
+impl KvdbMdbxValue for Box<[u8]> {
+    fn from_db_value(db_value: Vec<u8>) -> Self {
+        db_value.into_boxed_slice()
+    }
+}
+
+impl KvdbMdbxValue for () {
+    fn from_db_value(_db_value: Vec<u8>) -> Self {}
+}
+
+/// A named table of an MDBX environment, which is iterable by key range. A
+/// table without environment is empty, e.g. the table of the NULL snapshot.
+pub struct KvdbMdbxTable<ValueType> {
+    db: Option<Arc<Database<NoWriteMap>>>,
+    table_name: &'static str,
+    _marker_value_type: PhantomData<ValueType>,
+}
+
+impl<ValueType> KvdbMdbxTable<ValueType> {
+    pub fn new(
+        db: Option<Arc<Database<NoWriteMap>>>, table_name: &'static str,
+    ) -> Self {
+        Self {
+            db,
+            table_name,
+            _marker_value_type: PhantomData,
+        }
+    }
+}
+
+/// The range iterator reads the table in batches, each batch within its own
+/// read transaction, so that it doesn't hold a transaction or borrow the
+/// environment between the calls to `next`.
+pub struct KvdbMdbxRangeIter<ValueType> {
+    db: Option<Arc<Database<NoWriteMap>>>,
+    table_name: &'static str,
+    lower_bound: Vec<u8>,
+    lower_bound_excl: bool,
+    upper_bound_excl: Option<Vec<u8>>,
+    batch: VecDeque<(Vec<u8>, ValueType)>,
+    exhausted: bool,
+}
+
+impl<ValueType: KvdbMdbxValue> KvdbMdbxRangeIter<ValueType> {
+    const BATCH_SIZE: usize = 1024;
+
+    fn new(
+        table: &KvdbMdbxTable<ValueType>, lower_bound: &[u8],
+        lower_bound_excl: bool, upper_bound_excl: Option<&[u8]>,
+    ) -> Self {
+        Self {
+            db: table.db.clone(),
+            table_name: table.table_name,
+            lower_bound: lower_bound.to_vec(),
+            lower_bound_excl,
+            upper_bound_excl: upper_bound_excl.map(|key| key.to_vec()),
+            batch: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    fn load_batch(&mut self) -> Result<()> {
+        let db = match &self.db {
+            None => {
+                self.exhausted = true;
+                return Ok(());
+            }
+            Some(db) => db,
+        };
+        let txn = db.begin_ro_txn()?;
+        let table = txn.open_table(Some(self.table_name))?;
+        let mut cursor = txn.cursor(&table)?;
+        let mut item =
+            cursor.set_range::<Vec<u8>, Vec<u8>>(&self.lower_bound)?;
+        if self.lower_bound_excl
+            && item
+                .as_ref()
+                .map_or(false, |(key, _)| *key == self.lower_bound)
+        {
+            item = cursor.next::<Vec<u8>, Vec<u8>>()?;
+        }
+        while let Some((key, value)) = item {
+            if self
+                .upper_bound_excl
+                .as_ref()
+                .map_or(false, |upper_bound| key >= *upper_bound)
+            {
+                break;
+            }
+            // The next batch continues after the last key of this batch.
+            self.lower_bound = key.clone();
+            self.lower_bound_excl = true;
+            self.batch.push_back((key, ValueType::from_db_value(value)));
+            if self.batch.len() == Self::BATCH_SIZE {
+                return Ok(());
+            }
+            item = cursor.next::<Vec<u8>, Vec<u8>>()?;
+        }
+        self.exhausted = true;
+        Ok(())
+    }
+}
+
+impl<ValueType: KvdbMdbxValue> FallibleIterator
+    for KvdbMdbxRangeIter<ValueType>
+{
+    type Error = Error;
+    type Item = (Vec<u8>, ValueType);
+
+    fn next(&mut self) -> Result<Option<Self::Item>> {
+        if self.batch.is_empty() && !self.exhausted {
+            self.load_batch()?;
+        }
+        Ok(self.batch.pop_front())
+    }
+}
+
+impl<'a, ValueType: KvdbMdbxValue>
+    WrappedLifetimeFamily<
+        'a,
+        dyn FallibleIterator<Item = (Vec<u8>, ValueType), Error = Error>,
+    > for KvdbIterIterator<(Vec<u8>, ValueType), [u8], KvdbMdbxIteratorTag>
+{
+    type Out = KvdbMdbxRangeIter<ValueType>;
+}
+
+impl<ValueType: KvdbMdbxValue>
+    WrappedTrait<
+        dyn FallibleIterator<Item = (Vec<u8>, ValueType), Error = Error>,
+    > for KvdbIterIterator<(Vec<u8>, ValueType), [u8], KvdbMdbxIteratorTag>
+{
+}
+
+impl<ValueType: KvdbMdbxValue>
+    KeyValueDbIterableTrait<(Vec<u8>, ValueType), [u8], KvdbMdbxIteratorTag>
+    for KvdbMdbxTable<ValueType>
+{
+    fn iter_range(
+        &mut self, lower_bound_incl: &[u8], upper_bound_excl: Option<&[u8]>,
+    ) -> Result<
+        Wrap<
+            KvdbIterIterator<(Vec<u8>, ValueType), [u8], KvdbMdbxIteratorTag>,
+            dyn FallibleIterator<Item = (Vec<u8>, ValueType), Error = Error>,
+        >,
+    > {
+        Ok(Wrap(KvdbMdbxRangeIter::new(
+            self,
+            lower_bound_incl,
+            /* lower_bound_excl = */ false,
+            upper_bound_excl,
+        )))
+    }
+
+    fn iter_range_excl(
+        &mut self, lower_bound_excl: &[u8], upper_bound_excl: &[u8],
+    ) -> Result<
+        Wrap<
+            KvdbIterIterator<(Vec<u8>, ValueType), [u8], KvdbMdbxIteratorTag>,
+            dyn FallibleIterator<Item = (Vec<u8>, ValueType), Error = Error>,
+        >,
+    > {
+        Ok(Wrap(KvdbMdbxRangeIter::new(
+            self,
+            lower_bound_excl,
+            /* lower_bound_excl = */ true,
+            Some(upper_bound_excl),
+        )))
     }
 }
+
+enable_impl_transmute_for_element_satisfy! {
+    generic ValueType: KvdbMdbxValue;
+    trait 'static + KeyValueDbIterableTrait<
+        (Vec<u8>, ValueType),
+        [u8],
+        KvdbMdbxIteratorTag,
+    >;
+    for KvdbMdbxTable<ValueType>;
+}
+
+impl<'a, ValueType: KvdbMdbxValue>
+    WrappedLifetimeFamily<
+        'a,
+        dyn KeyValueDbIterableTrait<
+            (Vec<u8>, ValueType),
+            [u8],
+            KvdbMdbxIteratorTag,
+        >,
+    > for KvdbMdbxTable<ValueType>
+{
+    type Out = Self;
+}
+
+impl<ValueType: KvdbMdbxValue>
+    WrappedTrait<
+        dyn KeyValueDbIterableTrait<
+            (Vec<u8>, ValueType),
+            [u8],
+            KvdbMdbxIteratorTag,
+        >,
+    > for KvdbMdbxTable<ValueType>
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::random;
+    use std::path::PathBuf;
+
+    fn open_for_test() -> (PathBuf, KvdbMdbx) {
+        let path = std::env::temp_dir()
+            .join(format!("mazze_kvdb_mdbx_test_{}", random::<u64>()));
+        let kvdb = KvdbMdbx::open(&path, &MdbxConfig::default()).unwrap();
+        (path, kvdb)
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let (path, kvdb) = open_for_test();
+
+        assert_eq!(kvdb.get(b"key").unwrap(), None);
+        KeyValueDbTrait::put(&kvdb, b"key", b"value").unwrap();
+        assert_eq!(kvdb.get(b"key").unwrap(), Some(b"value".to_vec().into()));
+        KeyValueDbTrait::delete(&kvdb, b"key").unwrap();
+        assert_eq!(kvdb.get(b"key").unwrap(), None);
+
+        drop(kvdb);
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_transaction() {
+        let (path, kvdb) = open_for_test();
+        KeyValueDbTrait::put(&kvdb, b"deleted", b"value").unwrap();
+
+        let mut transaction = kvdb.start_transaction(false).unwrap();
+        transaction.put(b"key", b"value").unwrap();
+        transaction.delete(b"deleted").unwrap();
+        assert_eq!(
+            transaction.get_mut(b"key").unwrap(),
+            Some(b"value".to_vec().into())
+        );
+        assert_eq!(transaction.get_mut(b"deleted").unwrap(), None);
+        // Nothing is written before the commit.
+        assert_eq!(kvdb.get(b"key").unwrap(), None);
+        transaction.commit(kvdb.as_any()).unwrap();
+        assert_eq!(kvdb.get(b"key").unwrap(), Some(b"value".to_vec().into()));
+        assert_eq!(kvdb.get(b"deleted").unwrap(), None);
+
+        transaction.put(b"reverted", b"value").unwrap();
+        transaction.revert().unwrap();
+        transaction.commit(kvdb.as_any()).unwrap();
+        assert_eq!(kvdb.get(b"reverted").unwrap(), None);
+
+        drop(transaction);
+        drop(kvdb);
+        fs::remove_dir_all(path).unwrap();
+    }
+}
+
+use super::super::{
+    super::storage_db::{delta_db_manager::DeltaDbTrait, key_value_db::*},
+    defaults,
+    errors::*,
+};
+use crate::{
+    utils::{
+        tuple::ElementSatisfy,
+        wrap::{Wrap, WrappedLifetimeFamily, WrappedTrait},
+    },
+    MdbxConfig, MdbxSyncMode,
+};
+use fallible_iterator::FallibleIterator;
+use libmdbx::{
+    Database, DatabaseFlags, Geometry, Mode, NoWriteMap, SyncMode, WriteFlags,
+};
+use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
+use random_crash::random_crash_if_enabled;
+use std::{
+    any::Any, collections::VecDeque, fs, marker::PhantomData, path::Path,
+    sync::Arc,
+};
@@ -3,14 +3,20 @@
 // See http://www.gnu.org/licenses/
 
 // TODO: check berkeley db as well.
+pub mod delta_db_manager_by_backend;
+pub mod delta_db_manager_mdbx;
 pub mod delta_db_manager_rocksdb;
 pub mod delta_db_manager_sqlite;
 pub mod kvdb_rocksdb;
 pub mod kvdb_mdbx;
 pub mod kvdb_sqlite;
 pub mod kvdb_sqlite_sharded;
+pub mod snapshot_db_manager_by_backend;
+pub mod snapshot_db_manager_mdbx;
 pub mod snapshot_db_manager_sqlite;
+pub mod snapshot_db_mdbx;
 pub mod snapshot_db_sqlite;
+pub mod snapshot_kv_db_mdbx;
 pub mod snapshot_kv_db_sqlite;
 pub mod snapshot_mpt;
 pub mod snapshot_mpt_db_sqlite;
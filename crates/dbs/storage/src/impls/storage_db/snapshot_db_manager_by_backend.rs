@@ -0,0 +1,928 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The snapshot db manager of the backend selected by `state_db_type`.
+
+pub enum SnapshotDbManagerByBackend {
+    Sqlite(SnapshotDbManagerSqlite),
+    Mdbx(SnapshotDbManagerMdbx),
+}
+
+impl SnapshotDbManagerByBackend {
+    pub fn new(
+        snapshot_path: PathBuf, max_open_snapshots: u16,
+        use_isolated_db_for_mpt_table: bool,
+        use_isolated_db_for_mpt_table_height: Option<u64>,
+        era_epoch_count: u64, state_db_backend: &StateDbBackend,
+    ) -> Result<SnapshotDbManagerByBackend> {
+        let other_backend_prefix = match state_db_backend {
+            StateDbBackend::Rocksdb => {
+                SnapshotDbManagerMdbx::SNAPSHOT_DB_MDBX_DIR_PREFIX
+            }
+            StateDbBackend::Mdbx(_) => {
+                if use_isolated_db_for_mpt_table {
+                    bail!("The MDBX snapshots keep the MPT in the snapshot, use_isolated_db_for_mpt_table is not supported.");
+                }
+                SnapshotDbManagerSqlite::SNAPSHOT_DB_SQLITE_DIR_PREFIX
+            }
+        };
+
+        // The snapshots which don't belong to the selected backend are deleted
+        // as unexpected files when the persisted state is scanned, so refuse
+        // to switch the backend of an existing data dir. The check runs before
+        // the manager is created, because the sqlite manager creates its
+        // latest MPT snapshot.
+        let mpt_snapshot_path = snapshot_path
+            .parent()
+            .unwrap()
+            .join(SnapshotDbManagerSqlite::MPT_SNAPSHOT_DIR);
+        for snapshot_dir in [&snapshot_path, &mpt_snapshot_path] {
+            if !snapshot_dir.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(snapshot_dir)? {
+                let entry = entry?;
+                if entry.file_name().to_str().map_or(false, |name| {
+                    name.starts_with(other_backend_prefix)
+                }) {
+                    bail!(
+                        "Snapshot {} was created by another state_db_type.",
+                        entry.path().display()
+                    );
+                }
+            }
+        }
+
+        Ok(match state_db_backend {
+            StateDbBackend::Rocksdb => SnapshotDbManagerByBackend::Sqlite(
+                SnapshotDbManagerSqlite::new(
+                    snapshot_path,
+                    max_open_snapshots,
+                    use_isolated_db_for_mpt_table,
+                    use_isolated_db_for_mpt_table_height,
+                    era_epoch_count,
+                )?,
+            ),
+            StateDbBackend::Mdbx(mdbx_config) => {
+                SnapshotDbManagerByBackend::Mdbx(SnapshotDbManagerMdbx::new(
+                    snapshot_path,
+                    max_open_snapshots,
+                    mdbx_config.clone(),
+                )?)
+            }
+        })
+    }
+
+    pub fn update_latest_snapshot_id(&self, snapshot_id: EpochId, height: u64) {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.update_latest_snapshot_id(snapshot_id, height)
+            }
+            Self::Mdbx(manager) => {
+                manager.update_latest_snapshot_id(snapshot_id, height)
+            }
+        }
+    }
+
+    pub fn clean_snapshot_epoch_id_before_recovered(&self) {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.clean_snapshot_epoch_id_before_recovered()
+            }
+            Self::Mdbx(manager) => {
+                manager.clean_snapshot_epoch_id_before_recovered()
+            }
+        }
+    }
+
+    pub fn set_reconstruct_snapshot_id(
+        &self, reconstruct_main: Option<EpochId>,
+    ) {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.set_reconstruct_snapshot_id(reconstruct_main)
+            }
+            Self::Mdbx(manager) => {
+                manager.set_reconstruct_snapshot_id(reconstruct_main)
+            }
+        }
+    }
+
+    pub fn recreate_latest_mpt_snapshot(&self) -> Result<()> {
+        match self {
+            Self::Sqlite(manager) => manager.recreate_latest_mpt_snapshot(),
+            Self::Mdbx(manager) => manager.recreate_latest_mpt_snapshot(),
+        }
+    }
+}
+
+impl SnapshotDbManagerTrait for SnapshotDbManagerByBackend {
+    type SnapshotDb = SnapshotDbByBackend;
+    type SnapshotDbWrite = SnapshotDbWriteableByBackend;
+
+    fn get_snapshot_dir(&self) -> &Path {
+        match self {
+            Self::Sqlite(manager) => manager.get_snapshot_dir(),
+            Self::Mdbx(manager) => manager.get_snapshot_dir(),
+        }
+    }
+
+    fn get_snapshot_db_name(&self, snapshot_epoch_id: &EpochId) -> String {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.get_snapshot_db_name(snapshot_epoch_id)
+            }
+            Self::Mdbx(manager) => {
+                manager.get_snapshot_db_name(snapshot_epoch_id)
+            }
+        }
+    }
+
+    fn get_snapshot_db_path(&self, snapshot_epoch_id: &EpochId) -> PathBuf {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.get_snapshot_db_path(snapshot_epoch_id)
+            }
+            Self::Mdbx(manager) => {
+                manager.get_snapshot_db_path(snapshot_epoch_id)
+            }
+        }
+    }
+
+    fn get_mpt_snapshot_dir(&self) -> &Path {
+        match self {
+            Self::Sqlite(manager) => manager.get_mpt_snapshot_dir(),
+            Self::Mdbx(manager) => manager.get_mpt_snapshot_dir(),
+        }
+    }
+
+    fn get_latest_mpt_snapshot_db_name(&self) -> String {
+        match self {
+            Self::Sqlite(manager) => manager.get_latest_mpt_snapshot_db_name(),
+            Self::Mdbx(manager) => manager.get_latest_mpt_snapshot_db_name(),
+        }
+    }
+
+    fn recovery_latest_mpt_snapshot_from_checkpoint(
+        &self, snapshot_epoch_id: &EpochId,
+        before_era_main_hash: Option<EpochId>,
+    ) -> Result<()> {
+        match self {
+            Self::Sqlite(manager) => manager
+                .recovery_latest_mpt_snapshot_from_checkpoint(
+                    snapshot_epoch_id,
+                    before_era_main_hash,
+                ),
+            Self::Mdbx(manager) => manager
+                .recovery_latest_mpt_snapshot_from_checkpoint(
+                    snapshot_epoch_id,
+                    before_era_main_hash,
+                ),
+        }
+    }
+
+    fn create_mpt_snapshot_from_latest(
+        &self, new_snapshot_epoch_id: &EpochId,
+    ) -> Result<()> {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.create_mpt_snapshot_from_latest(new_snapshot_epoch_id)
+            }
+            Self::Mdbx(manager) => {
+                manager.create_mpt_snapshot_from_latest(new_snapshot_epoch_id)
+            }
+        }
+    }
+
+    fn get_epoch_id_from_snapshot_db_name(
+        &self, snapshot_db_name: &str,
+    ) -> Result<EpochId> {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.get_epoch_id_from_snapshot_db_name(snapshot_db_name)
+            }
+            Self::Mdbx(manager) => {
+                manager.get_epoch_id_from_snapshot_db_name(snapshot_db_name)
+            }
+        }
+    }
+
+    fn try_get_new_snapshot_epoch_from_temp_path(
+        &self, dir_name: &str,
+    ) -> Option<EpochId> {
+        match self {
+            Self::Sqlite(manager) => {
+                SnapshotDbManagerTrait::try_get_new_snapshot_epoch_from_temp_path(
+                    manager, dir_name,
+                )
+            }
+            Self::Mdbx(manager) => {
+                manager.try_get_new_snapshot_epoch_from_temp_path(dir_name)
+            }
+        }
+    }
+
+    fn try_get_new_snapshot_epoch_from_mpt_temp_path(
+        &self, dir_name: &str,
+    ) -> Option<EpochId> {
+        match self {
+            Self::Sqlite(manager) => {
+                SnapshotDbManagerTrait::try_get_new_snapshot_epoch_from_mpt_temp_path(
+                    manager, dir_name,
+                )
+            }
+            Self::Mdbx(manager) => {
+                manager.try_get_new_snapshot_epoch_from_mpt_temp_path(dir_name)
+            }
+        }
+    }
+
+    fn is_full_sync_temp_snapshot_db_name(&self, dir_name: &str) -> bool {
+        match self {
+            Self::Sqlite(manager) => {
+                SnapshotDbManagerTrait::is_full_sync_temp_snapshot_db_name(
+                    manager, dir_name,
+                )
+            }
+            Self::Mdbx(manager) => {
+                manager.is_full_sync_temp_snapshot_db_name(dir_name)
+            }
+        }
+    }
+
+    fn new_snapshot_by_merging<'m>(
+        &self, old_snapshot_epoch_id: &EpochId, snapshot_epoch_id: EpochId,
+        delta_mpt: DeltaMptIterator, in_progress_snapshot_info: SnapshotInfo,
+        snapshot_info_map_rwlock: &'m RwLock<PersistedSnapshotInfoMap>,
+        new_epoch_height: u64, recover_mpt_with_kv_snapshot_exist: bool,
+    ) -> Result<(RwLockWriteGuard<'m, PersistedSnapshotInfoMap>, SnapshotInfo)>
+    {
+        match self {
+            Self::Sqlite(manager) => manager.new_snapshot_by_merging(
+                old_snapshot_epoch_id,
+                snapshot_epoch_id,
+                delta_mpt,
+                in_progress_snapshot_info,
+                snapshot_info_map_rwlock,
+                new_epoch_height,
+                recover_mpt_with_kv_snapshot_exist,
+            ),
+            Self::Mdbx(manager) => manager.new_snapshot_by_merging(
+                old_snapshot_epoch_id,
+                snapshot_epoch_id,
+                delta_mpt,
+                in_progress_snapshot_info,
+                snapshot_info_map_rwlock,
+                new_epoch_height,
+                recover_mpt_with_kv_snapshot_exist,
+            ),
+        }
+    }
+
+    fn get_snapshot_by_epoch_id(
+        &self, snapshot_epoch_id: &EpochId, try_open: bool,
+        open_mpt_snapshot: bool,
+    ) -> Result<Option<Self::SnapshotDb>> {
+        match self {
+            Self::Sqlite(manager) => Ok(manager
+                .get_snapshot_by_epoch_id(
+                    snapshot_epoch_id,
+                    try_open,
+                    open_mpt_snapshot,
+                )?
+                .map(SnapshotDbByBackend::Sqlite)),
+            Self::Mdbx(manager) => Ok(manager
+                .get_snapshot_by_epoch_id(
+                    snapshot_epoch_id,
+                    try_open,
+                    open_mpt_snapshot,
+                )?
+                .map(SnapshotDbByBackend::Mdbx)),
+        }
+    }
+
+    fn destroy_snapshot(&self, snapshot_epoch_id: &EpochId) -> Result<()> {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.destroy_snapshot(snapshot_epoch_id)
+            }
+            Self::Mdbx(manager) => manager.destroy_snapshot(snapshot_epoch_id),
+        }
+    }
+
+    fn new_temp_snapshot_for_full_sync(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        new_epoch_height: u64,
+    ) -> Result<Self::SnapshotDbWrite> {
+        match self {
+            Self::Sqlite(manager) => manager
+                .new_temp_snapshot_for_full_sync(
+                    snapshot_epoch_id,
+                    merkle_root,
+                    new_epoch_height,
+                )
+                .map(SnapshotDbWriteableByBackend::Sqlite),
+            Self::Mdbx(manager) => manager
+                .new_temp_snapshot_for_full_sync(
+                    snapshot_epoch_id,
+                    merkle_root,
+                    new_epoch_height,
+                )
+                .map(SnapshotDbWriteableByBackend::Mdbx),
+        }
+    }
+
+    fn open_temp_snapshot_for_full_sync(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        epoch_height: u64,
+    ) -> Result<Option<Self::SnapshotDbWrite>> {
+        match self {
+            Self::Sqlite(manager) => Ok(manager
+                .open_temp_snapshot_for_full_sync(
+                    snapshot_epoch_id,
+                    merkle_root,
+                    epoch_height,
+                )?
+                .map(SnapshotDbWriteableByBackend::Sqlite)),
+            Self::Mdbx(manager) => Ok(manager
+                .open_temp_snapshot_for_full_sync(
+                    snapshot_epoch_id,
+                    merkle_root,
+                    epoch_height,
+                )?
+                .map(SnapshotDbWriteableByBackend::Mdbx)),
+        }
+    }
+
+    fn destroy_temp_snapshots_for_full_sync(&self) -> Result<()> {
+        match self {
+            Self::Sqlite(manager) => {
+                manager.destroy_temp_snapshots_for_full_sync()
+            }
+            Self::Mdbx(manager) => {
+                manager.destroy_temp_snapshots_for_full_sync()
+            }
+        }
+    }
+
+    fn finalize_full_sync_snapshot<'m>(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        snapshot_info_map_rwlock: &'m RwLock<PersistedSnapshotInfoMap>,
+    ) -> Result<RwLockWriteGuard<'m, PersistedSnapshotInfoMap>> {
+        match self {
+            Self::Sqlite(manager) => manager.finalize_full_sync_snapshot(
+                snapshot_epoch_id,
+                merkle_root,
+                snapshot_info_map_rwlock,
+            ),
+            Self::Mdbx(manager) => manager.finalize_full_sync_snapshot(
+                snapshot_epoch_id,
+                merkle_root,
+                snapshot_info_map_rwlock,
+            ),
+        }
+    }
+}
+
+pub enum SnapshotDbByBackend {
+    Sqlite(SnapshotDbSqlite),
+    Mdbx(SnapshotDbMdbx),
+}
+
+impl KeyValueDbTypes for SnapshotDbByBackend {
+    type ValueType = Box<[u8]>;
+}
+
+impl KeyValueDbTraitRead for SnapshotDbByBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        match self {
+            Self::Sqlite(db) => db.get(key),
+            Self::Mdbx(db) => db.get(key),
+        }
+    }
+}
+
+impl KeyValueDbTraitOwnedRead for SnapshotDbByBackend {
+    fn get_mut(&mut self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        match self {
+            Self::Sqlite(db) => db.get_mut(key),
+            Self::Mdbx(db) => db.get_mut(key),
+        }
+    }
+}
+
+impl KeyValueDbTraitSingleWriter for SnapshotDbByBackend {
+    fn delete(&mut self, key: &[u8]) -> Result<Option<Option<Box<[u8]>>>> {
+        match self {
+            Self::Sqlite(db) => KeyValueDbTraitSingleWriter::delete(db, key),
+            Self::Mdbx(db) => KeyValueDbTraitSingleWriter::delete(db, key),
+        }
+    }
+
+    fn put(
+        &mut self, key: &[u8], value: &[u8],
+    ) -> Result<Option<Option<Box<[u8]>>>> {
+        match self {
+            Self::Sqlite(db) => {
+                KeyValueDbTraitSingleWriter::put(db, key, value)
+            }
+            Self::Mdbx(db) => KeyValueDbTraitSingleWriter::put(db, key, value),
+        }
+    }
+}
+
+impl<'db> OpenSnapshotMptTrait<'db> for SnapshotDbByBackend {
+    type SnapshotDbAsOwnedType = SnapshotMptByBackend<
+        <SnapshotDbSqlite as OpenSnapshotMptTrait<'db>>::SnapshotDbAsOwnedType,
+    >;
+    type SnapshotDbBorrowMutType = SnapshotMptByBackend<
+        <SnapshotDbSqlite as OpenSnapshotMptTrait<'db>>::SnapshotDbBorrowMutType,
+    >;
+    type SnapshotDbBorrowSharedType = SnapshotMptByBackend<
+        <SnapshotDbSqlite as OpenSnapshotMptTrait<'db>>::SnapshotDbBorrowSharedType,
+    >;
+
+    fn open_snapshot_mpt_owned(
+        &'db mut self,
+    ) -> Result<Self::SnapshotDbBorrowMutType> {
+        match self {
+            Self::Sqlite(db) => db
+                .open_snapshot_mpt_owned()
+                .map(SnapshotMptByBackend::Sqlite),
+            Self::Mdbx(db) => {
+                db.open_snapshot_mpt_owned().map(SnapshotMptByBackend::Mdbx)
+            }
+        }
+    }
+
+    fn open_snapshot_mpt_as_owned(
+        &'db self,
+    ) -> Result<Self::SnapshotDbAsOwnedType> {
+        match self {
+            Self::Sqlite(db) => db
+                .open_snapshot_mpt_as_owned()
+                .map(SnapshotMptByBackend::Sqlite),
+            Self::Mdbx(db) => db
+                .open_snapshot_mpt_as_owned()
+                .map(SnapshotMptByBackend::Mdbx),
+        }
+    }
+
+    fn open_snapshot_mpt_shared(
+        &'db self,
+    ) -> Result<Self::SnapshotDbBorrowSharedType> {
+        match self {
+            Self::Sqlite(db) => db
+                .open_snapshot_mpt_shared()
+                .map(SnapshotMptByBackend::Sqlite),
+            Self::Mdbx(db) => db
+                .open_snapshot_mpt_shared()
+                .map(SnapshotMptByBackend::Mdbx),
+        }
+    }
+}
+
+impl SnapshotDbByBackend {
+    pub fn dumped_delta_kv_set_keys_iterator(
+        &self,
+    ) -> Result<KvdbIterableByBackend<<Self as KeyValueDbTypes>::ValueType>>
+    {
+        match self {
+            Self::Sqlite(db) => Ok(KvdbIterableByBackend::Sqlite(
+                db.dumped_delta_kv_set_keys_iterator()?,
+            )),
+            Self::Mdbx(db) => Ok(KvdbIterableByBackend::Mdbx(
+                db.dumped_delta_kv_set_keys_iterator()?,
+            )),
+        }
+    }
+
+    pub fn dumped_delta_kv_delete_keys_iterator(
+        &self,
+    ) -> Result<KvdbIterableByBackend<()>> {
+        match self {
+            Self::Sqlite(db) => Ok(KvdbIterableByBackend::Sqlite(
+                db.dumped_delta_kv_delete_keys_iterator()?,
+            )),
+            Self::Mdbx(db) => Ok(KvdbIterableByBackend::Mdbx(
+                db.dumped_delta_kv_delete_keys_iterator()?,
+            )),
+        }
+    }
+}
+
+/// The snapshots are opened, created and merged by the snapshot db manager
+/// of each backend.
+impl SnapshotDbTrait for SnapshotDbByBackend {
+    type SnapshotKvdbIterTraitTag = KvdbIterableByBackendTag;
+    type SnapshotKvdbIterType =
+        KvdbIterableByBackend<<Self as KeyValueDbTypes>::ValueType>;
+
+    /// The NULL snapshot is empty, so it's the same for every backend.
+    fn get_null_snapshot() -> Self {
+        Self::Sqlite(SnapshotDbSqlite::get_null_snapshot())
+    }
+
+    fn open(
+        _snapshot_path: &Path, _readonly: bool,
+        _already_open_snapshots: &AlreadyOpenSnapshots<Self>,
+        _open_semaphore: &Arc<Semaphore>,
+    ) -> Result<SnapshotDbByBackend> {
+        unreachable!()
+    }
+
+    fn create(
+        _snapshot_path: &Path,
+        _already_open_snapshots: &AlreadyOpenSnapshots<Self>,
+        _open_semaphore: &Arc<Semaphore>, _mpt_table_in_current_db: bool,
+    ) -> Result<SnapshotDbByBackend> {
+        unreachable!()
+    }
+
+    fn direct_merge(
+        &mut self, _old_snapshot_db: Option<&Arc<SnapshotDbByBackend>>,
+        _mpt_snapshot: &mut Option<SnapshotMptDbSqlite>,
+        _recover_mpt_with_kv_snapshot_exist: bool,
+        _in_reconstruct_snapshot_state: bool,
+    ) -> Result<MerkleHash> {
+        unreachable!()
+    }
+
+    fn copy_and_merge(
+        &mut self, _old_snapshot_db: &Arc<SnapshotDbByBackend>,
+        _mpt_snapshot_db: &mut Option<SnapshotMptDbSqlite>,
+        _in_reconstruct_snapshot_state: bool,
+    ) -> Result<MerkleHash> {
+        unreachable!()
+    }
+
+    fn start_transaction(&mut self) -> Result<()> {
+        unreachable!()
+    }
+
+    fn commit_transaction(&mut self) -> Result<()> {
+        unreachable!()
+    }
+
+    fn is_mpt_table_in_current_db(&self) -> bool {
+        match self {
+            Self::Sqlite(db) => db.is_mpt_table_in_current_db(),
+            Self::Mdbx(db) => db.is_mpt_table_in_current_db(),
+        }
+    }
+
+    fn snapshot_kv_iterator(
+        &self,
+    ) -> Result<
+        Wrap<
+            Self::SnapshotKvdbIterType,
+            dyn KeyValueDbIterableTrait<
+                MptKeyValue,
+                [u8],
+                KvdbIterableByBackendTag,
+            >,
+        >,
+    > {
+        Ok(Wrap(match self {
+            Self::Sqlite(db) => {
+                KvdbIterableByBackend::Sqlite(db.snapshot_kv_iterator()?.take())
+            }
+            Self::Mdbx(db) => {
+                KvdbIterableByBackend::Mdbx(db.snapshot_kv_iterator()?.take())
+            }
+        }))
+    }
+}
+
+pub enum SnapshotDbWriteableByBackend {
+    Sqlite(SnapshotDbWriteable),
+    Mdbx(SnapshotDbWriteableMdbx),
+}
+
+impl KeyValueDbTypes for SnapshotDbWriteableByBackend {
+    type ValueType = Box<[u8]>;
+}
+
+impl SnapshotDbWriteableTrait for SnapshotDbWriteableByBackend {
+    type SnapshotDbBorrowMutType = SnapshotMptByBackend<
+        <SnapshotDbWriteable as SnapshotDbWriteableTrait>::SnapshotDbBorrowMutType,
+    >;
+
+    fn start_transaction(&mut self) -> Result<()> {
+        match self {
+            Self::Sqlite(db) => db.start_transaction(),
+            Self::Mdbx(db) => db.start_transaction(),
+        }
+    }
+
+    fn commit_transaction(&mut self) -> Result<()> {
+        match self {
+            Self::Sqlite(db) => db.commit_transaction(),
+            Self::Mdbx(db) => db.commit_transaction(),
+        }
+    }
+
+    fn put_kv(
+        &mut self, key: &[u8], value: &<Self::ValueType as DbValueType>::Type,
+    ) -> Result<Option<Option<Self::ValueType>>> {
+        match self {
+            Self::Sqlite(db) => db.put_kv(key, value),
+            Self::Mdbx(db) => db.put_kv(key, value),
+        }
+    }
+
+    fn open_snapshot_mpt_owned(
+        &mut self,
+    ) -> Result<Self::SnapshotDbBorrowMutType> {
+        match self {
+            Self::Sqlite(db) => db
+                .open_snapshot_mpt_owned()
+                .map(SnapshotMptByBackend::Sqlite),
+            Self::Mdbx(db) => {
+                db.open_snapshot_mpt_owned().map(SnapshotMptByBackend::Mdbx)
+            }
+        }
+    }
+}
+
+/// The snapshot MPT of either backend. The sqlite snapshot MPT type differs
+/// by how it's opened.
+pub enum SnapshotMptByBackend<SqliteMpt> {
+    Sqlite(SqliteMpt),
+    Mdbx(SnapshotMptMdbx),
+}
+
+impl<SqliteMpt: SnapshotMptTraitRead> SnapshotMptTraitRead
+    for SnapshotMptByBackend<SqliteMpt>
+{
+    fn get_merkle_root(&self) -> MerkleHash {
+        match self {
+            Self::Sqlite(mpt) => mpt.get_merkle_root(),
+            Self::Mdbx(mpt) => mpt.get_merkle_root(),
+        }
+    }
+
+    fn load_node(
+        &mut self, path: &dyn CompressedPathTrait,
+    ) -> Result<Option<SnapshotMptNode>> {
+        match self {
+            Self::Sqlite(mpt) => mpt.load_node(path),
+            Self::Mdbx(mpt) => mpt.load_node(path),
+        }
+    }
+}
+
+impl<SqliteMpt: SnapshotMptTraitReadAndIterate> SnapshotMptTraitReadAndIterate
+    for SnapshotMptByBackend<SqliteMpt>
+{
+    fn iterate_subtree_trie_nodes_without_root(
+        &mut self, path: &dyn CompressedPathTrait,
+    ) -> Result<Box<dyn SnapshotMptIteraterTrait + '_>> {
+        match self {
+            Self::Sqlite(mpt) => {
+                mpt.iterate_subtree_trie_nodes_without_root(path)
+            }
+            Self::Mdbx(mpt) => {
+                mpt.iterate_subtree_trie_nodes_without_root(path)
+            }
+        }
+    }
+}
+
+impl<SqliteMpt: SnapshotMptTraitRw> SnapshotMptTraitRw
+    for SnapshotMptByBackend<SqliteMpt>
+{
+    fn delete_node(&mut self, path: &dyn CompressedPathTrait) -> Result<()> {
+        match self {
+            Self::Sqlite(mpt) => mpt.delete_node(path),
+            Self::Mdbx(mpt) => mpt.delete_node(path),
+        }
+    }
+
+    fn write_node(
+        &mut self, path: &dyn CompressedPathTrait, trie_node: &SnapshotMptNode,
+    ) -> Result<()> {
+        match self {
+            Self::Sqlite(mpt) => mpt.write_node(path, trie_node),
+            Self::Mdbx(mpt) => mpt.write_node(path, trie_node),
+        }
+    }
+}
+
+/// A table of a snapshot of either backend, which is iterable by key range.
+pub enum KvdbIterableByBackend<ValueType> {
+    Sqlite(KvdbSqliteSharded<ValueType>),
+    Mdbx(KvdbMdbxTable<ValueType>),
+}
+
+pub struct KvdbIterableByBackendTag();
+
+/// The iterator of either backend is boxed, because the sqlite iterator
+/// borrows the table it iterates.
+impl<'a, ValueType: 'static>
+    WrappedLifetimeFamily<
+        'a,
+        dyn FallibleIterator<Item = (Vec<u8>, ValueType), Error = Error>,
+    >
+    for KvdbIterIterator<(Vec<u8>, ValueType), [u8], KvdbIterableByBackendTag>
+{
+    type Out = Box<
+        dyn 'a + FallibleIterator<Item = (Vec<u8>, ValueType), Error = Error>,
+    >;
+}
+
+impl<ValueType: 'static>
+    WrappedTrait<
+        dyn FallibleIterator<Item = (Vec<u8>, ValueType), Error = Error>,
+    >
+    for KvdbIterIterator<(Vec<u8>, ValueType), [u8], KvdbIterableByBackendTag>
+{
+}
+
+macro_rules! make_KeyValueDbIterableTrait_of_KvdbIterableByBackend {
+    ($ValueType:ty) => {
+        impl
+            KeyValueDbIterableTrait<
+                (Vec<u8>, $ValueType),
+                [u8],
+                KvdbIterableByBackendTag,
+            > for KvdbIterableByBackend<$ValueType>
+        {
+            fn iter_range(
+                &mut self, lower_bound_incl: &[u8],
+                upper_bound_excl: Option<&[u8]>,
+            ) -> Result<
+                Wrap<
+                    KvdbIterIterator<
+                        (Vec<u8>, $ValueType),
+                        [u8],
+                        KvdbIterableByBackendTag,
+                    >,
+                    dyn FallibleIterator<
+                        Item = (Vec<u8>, $ValueType),
+                        Error = Error,
+                    >,
+                >,
+            > {
+                let iter: Box<
+                    dyn '_
+                        + FallibleIterator<
+                            Item = (Vec<u8>, $ValueType),
+                            Error = Error,
+                        >,
+                > = match self {
+                    Self::Sqlite(kvdb) => Box::new(
+                        kvdb.iter_range(lower_bound_incl, upper_bound_excl)?
+                            .take(),
+                    ),
+                    Self::Mdbx(table) => Box::new(
+                        table
+                            .iter_range(lower_bound_incl, upper_bound_excl)?
+                            .take(),
+                    ),
+                };
+                Ok(Wrap(iter))
+            }
+
+            fn iter_range_excl(
+                &mut self, lower_bound_excl: &[u8], upper_bound_excl: &[u8],
+            ) -> Result<
+                Wrap<
+                    KvdbIterIterator<
+                        (Vec<u8>, $ValueType),
+                        [u8],
+                        KvdbIterableByBackendTag,
+                    >,
+                    dyn FallibleIterator<
+                        Item = (Vec<u8>, $ValueType),
+                        Error = Error,
+                    >,
+                >,
+            > {
+                let iter: Box<
+                    dyn '_
+                        + FallibleIterator<
+                            Item = (Vec<u8>, $ValueType),
+                            Error = Error,
+                        >,
+                > = match self {
+                    Self::Sqlite(kvdb) => Box::new(
+                        kvdb.iter_range_excl(
+                            lower_bound_excl,
+                            upper_bound_excl,
+                        )?
+                        .take(),
+                    ),
+                    Self::Mdbx(table) => Box::new(
+                        table
+                            .iter_range_excl(
+                                lower_bound_excl,
+                                upper_bound_excl,
+                            )?
+                            .take(),
+                    ),
+                };
+                Ok(Wrap(iter))
+            }
+        }
+
+        impl
+            ElementSatisfy<
+                dyn KeyValueDbIterableTrait<
+                    (Vec<u8>, $ValueType),
+                    [u8],
+                    KvdbIterableByBackendTag,
+                >,
+            > for KvdbIterableByBackend<$ValueType>
+        {
+            fn to_constrain_object(
+                &self,
+            ) -> &(dyn KeyValueDbIterableTrait<
+                (Vec<u8>, $ValueType),
+                [u8],
+                KvdbIterableByBackendTag,
+            > + 'static) {
+                self
+            }
+
+            fn to_constrain_object_mut(
+                &mut self,
+            ) -> &mut (dyn KeyValueDbIterableTrait<
+                (Vec<u8>, $ValueType),
+                [u8],
+                KvdbIterableByBackendTag,
+            > + 'static) {
+                self
+            }
+        }
+
+        impl
+            WrappedLifetimeFamily<
+                '_,
+                dyn KeyValueDbIterableTrait<
+                    (Vec<u8>, $ValueType),
+                    [u8],
+                    KvdbIterableByBackendTag,
+                >,
+            > for KvdbIterableByBackend<$ValueType>
+        {
+            type Out = Self;
+        }
+
+        impl
+            WrappedTrait<
+                dyn KeyValueDbIterableTrait<
+                    (Vec<u8>, $ValueType),
+                    [u8],
+                    KvdbIterableByBackendTag,
+                >,
+            > for KvdbIterableByBackend<$ValueType>
+        {
+        }
+    };
+}
+
+make_KeyValueDbIterableTrait_of_KvdbIterableByBackend!(Box<[u8]>);
+make_KeyValueDbIterableTrait_of_KvdbIterableByBackend!(());
+
+use super::{
+    super::{
+        super::storage_db::{
+            key_value_db::*, snapshot_db::*, snapshot_db_manager::*,
+            snapshot_mpt::*,
+        },
+        delta_mpt::DeltaMptIterator,
+        errors::*,
+        merkle_patricia_trie::CompressedPathTrait,
+        storage_manager::PersistedSnapshotInfoMap,
+    },
+    kvdb_mdbx::KvdbMdbxTable,
+    kvdb_sqlite_sharded::KvdbSqliteSharded,
+    snapshot_db_manager_mdbx::{
+        SnapshotDbManagerMdbx, SnapshotDbWriteableMdbx,
+    },
+    snapshot_db_manager_sqlite::{
+        AlreadyOpenSnapshots, SnapshotDbManagerSqlite, SnapshotDbWriteable,
+    },
+    snapshot_db_mdbx::SnapshotDbMdbx,
+    snapshot_db_sqlite::SnapshotDbSqlite,
+    snapshot_kv_db_mdbx::SnapshotMptMdbx,
+    snapshot_mpt_db_sqlite::SnapshotMptDbSqlite,
+};
+use crate::{
+    utils::{
+        tuple::ElementSatisfy,
+        wrap::{Wrap, WrappedLifetimeFamily, WrappedTrait},
+    },
+    MptKeyValue, StateDbBackend,
+};
+use fallible_iterator::FallibleIterator;
+use parking_lot::{RwLock, RwLockWriteGuard};
+use primitives::{EpochId, MerkleHash};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::Semaphore;
@@ -0,0 +1,596 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+/// The snapshot db manager on MDBX. The MPT of a snapshot is always kept in
+/// the snapshot itself, so there is no latest MPT snapshot to maintain and
+/// the MPT snapshot dir stays empty.
+pub struct SnapshotDbManagerMdbx {
+    snapshot_path: PathBuf,
+    mdbx_config: MdbxConfig,
+    already_open_snapshots: AlreadyOpenSnapshots<SnapshotKvDbMdbx>,
+    /// Set a limit on the number of open snapshots. When the limit is reached,
+    /// consensus initiated open should wait, other non-critical opens such as
+    /// rpc initiated opens should simply abort when the limit is reached.
+    open_snapshot_semaphore: Arc<Semaphore>,
+    open_create_delete_lock: Mutex<()>,
+    mpt_snapshot_path: PathBuf,
+    reconstruct_snapshot_id_for_reboot: RwLock<Option<EpochId>>,
+}
+
+pub struct SnapshotDbWriteableMdbx {
+    pub kv_snapshot_db: SnapshotKvDbMdbx,
+}
+
+impl KeyValueDbTypes for SnapshotDbWriteableMdbx {
+    type ValueType = Box<[u8]>;
+}
+
+impl SnapshotDbWriteableTrait for SnapshotDbWriteableMdbx {
+    type SnapshotDbBorrowMutType = SnapshotMptMdbx;
+
+    fn start_transaction(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn commit_transaction(&mut self) -> Result<()> {
+        self.kv_snapshot_db.flush()
+    }
+
+    fn put_kv(
+        &mut self, key: &[u8], value: &<Self::ValueType as DbValueType>::Type,
+    ) -> Result<Option<Option<Self::ValueType>>> {
+        self.kv_snapshot_db.put(key, value)
+    }
+
+    fn open_snapshot_mpt_owned(
+        &mut self,
+    ) -> Result<Self::SnapshotDbBorrowMutType> {
+        self.kv_snapshot_db.open_snapshot_mpt()
+    }
+}
+
+impl SnapshotDbManagerMdbx {
+    const MPT_SNAPSHOT_DIR: &'static str = "mpt_snapshot";
+    pub const SNAPSHOT_DB_MDBX_DIR_PREFIX: &'static str = "mdbx_";
+
+    pub fn new(
+        snapshot_path: PathBuf, max_open_snapshots: u16,
+        mdbx_config: MdbxConfig,
+    ) -> Result<Self> {
+        if !snapshot_path.exists() {
+            fs::create_dir_all(snapshot_path.clone())?;
+        }
+
+        let mpt_snapshot_path = snapshot_path
+            .parent()
+            .unwrap()
+            .join(SnapshotDbManagerMdbx::MPT_SNAPSHOT_DIR);
+        if !mpt_snapshot_path.exists() {
+            fs::create_dir_all(mpt_snapshot_path.clone())?;
+        }
+
+        Ok(Self {
+            snapshot_path,
+            mdbx_config,
+            already_open_snapshots: Default::default(),
+            open_snapshot_semaphore: Arc::new(Semaphore::new(
+                max_open_snapshots as usize,
+            )),
+            open_create_delete_lock: Default::default(),
+            mpt_snapshot_path,
+            reconstruct_snapshot_id_for_reboot: RwLock::new(None),
+        })
+    }
+
+    pub fn update_latest_snapshot_id(
+        &self, _snapshot_id: EpochId, _height: u64,
+    ) {
+    }
+
+    pub fn clean_snapshot_epoch_id_before_recovered(&self) {}
+
+    pub fn set_reconstruct_snapshot_id(
+        &self, reconstruct_main: Option<EpochId>,
+    ) {
+        debug!("set_reconstruct_snapshot_id to {:?}", reconstruct_main);
+        *self.reconstruct_snapshot_id_for_reboot.write() = reconstruct_main;
+    }
+
+    pub fn recreate_latest_mpt_snapshot(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn open_snapshot_readonly(
+        &self, snapshot_path: PathBuf, try_open: bool,
+    ) -> Result<Option<Arc<SnapshotKvDbMdbx>>> {
+        // To serialize simultaneous opens.
+        let _open_lock = self.open_create_delete_lock.lock();
+
+        if let Some(already_open) =
+            self.already_open_snapshots.read().get(&snapshot_path)
+        {
+            match already_open {
+                None => {
+                    // Already open for exclusive write
+                    return Ok(None);
+                }
+                Some(open_shared_weak) => {
+                    match Weak::upgrade(open_shared_weak) {
+                        None => {}
+                        Some(already_open) => {
+                            return Ok(Some(already_open));
+                        }
+                    }
+                }
+            }
+        }
+        let file_exists = snapshot_path.exists();
+        if file_exists {
+            let semaphore_permit = if try_open {
+                self.open_snapshot_semaphore
+                    .try_acquire()
+                    // Unfortunately we have to use map_error because the
+                    // TryAcquireError isn't public.
+                    .map_err(|_err| ErrorKind::SemaphoreTryAcquireError)?
+            } else {
+                executor::block_on(self.open_snapshot_semaphore.acquire())
+            };
+
+            // If it's not in already_open_snapshots, the MDBX environment
+            // must have been closed. MDBX doesn't allow an environment to be
+            // open twice in the same process, so we wait for the last `Arc`
+            // to finish closing it, as in `SnapshotDbManagerSqlite`.
+            while let Some(already_open) =
+                self.already_open_snapshots.read().get(&snapshot_path)
+            {
+                match already_open {
+                    None => {
+                        // Already open for exclusive write
+                        return Ok(None);
+                    }
+                    Some(open_shared_weak) => {
+                        match Weak::upgrade(open_shared_weak) {
+                            None => {
+                                thread::sleep(Duration::from_millis(5));
+                                continue;
+                            }
+                            Some(already_open) => {
+                                return Ok(Some(already_open));
+                            }
+                        }
+                    }
+                }
+            }
+
+            let snapshot_db = Arc::new(SnapshotKvDbMdbx::open(
+                snapshot_path.as_path(),
+                &self.mdbx_config,
+                &self.already_open_snapshots,
+                &self.open_snapshot_semaphore,
+            )?);
+
+            semaphore_permit.forget();
+            self.already_open_snapshots.write().insert(
+                snapshot_path.into(),
+                Some(Arc::downgrade(&snapshot_db)),
+            );
+
+            return Ok(Some(snapshot_db));
+        } else {
+            return Ok(None);
+        }
+    }
+
+    fn open_snapshot_write(
+        &self, snapshot_path: PathBuf, create: bool,
+    ) -> Result<SnapshotKvDbMdbx> {
+        let _open_lock = self.open_create_delete_lock.lock();
+
+        if self
+            .already_open_snapshots
+            .read()
+            .get(&snapshot_path)
+            .is_some()
+        {
+            bail!(ErrorKind::SnapshotAlreadyExists)
+        }
+
+        let semaphore_permit =
+            executor::block_on(self.open_snapshot_semaphore.acquire());
+
+        let snapshot_db = if create {
+            SnapshotKvDbMdbx::create(
+                snapshot_path.as_path(),
+                &self.mdbx_config,
+                &self.already_open_snapshots,
+                &self.open_snapshot_semaphore,
+            )
+        } else {
+            let file_exists = snapshot_path.exists();
+            if file_exists {
+                SnapshotKvDbMdbx::open(
+                    snapshot_path.as_path(),
+                    &self.mdbx_config,
+                    &self.already_open_snapshots,
+                    &self.open_snapshot_semaphore,
+                )
+            } else {
+                bail!(ErrorKind::SnapshotNotFound);
+            }
+        }?;
+
+        semaphore_permit.forget();
+        self.already_open_snapshots
+            .write()
+            .insert(snapshot_path.clone(), None);
+        Ok(snapshot_db)
+    }
+
+    pub fn on_close(
+        already_open_snapshots: &AlreadyOpenSnapshots<SnapshotKvDbMdbx>,
+        open_semaphore: &Arc<Semaphore>, path: &Path, remove_on_close: bool,
+    ) {
+        // Destroy at close.
+        if remove_on_close {
+            // When removal fails, we can not raise the error because this
+            // function is called within a destructor.
+            //
+            // Mazze will remove orphan storage upon restart.
+            Self::fs_remove_snapshot(path);
+        }
+        already_open_snapshots.write().remove(path);
+        open_semaphore.add_permits(1);
+    }
+
+    fn fs_remove_snapshot(path: &Path) {
+        debug!("Remove snapshot at {}", path.display());
+        let path = path.to_owned();
+        thread::spawn(move || {
+            if let Err(e) = fs::remove_dir_all(&path) {
+                error!("remove snapshot err: path={:?} err={:?}", path, e);
+            }
+            debug!("Finish removing snapshot at {}", path.display());
+        });
+    }
+
+    fn get_merge_temp_snapshot_db_path(
+        &self, old_snapshot_epoch_id: &EpochId, new_snapshot_epoch_id: &EpochId,
+    ) -> PathBuf {
+        self.snapshot_path.join(
+            Self::SNAPSHOT_DB_MDBX_DIR_PREFIX.to_string()
+                + "merge_temp_"
+                + &old_snapshot_epoch_id.as_ref().to_hex::<String>()
+                + &new_snapshot_epoch_id.as_ref().to_hex::<String>(),
+        )
+    }
+
+    fn get_full_sync_temp_snapshot_db_path(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+    ) -> PathBuf {
+        self.snapshot_path.join(
+            Self::SNAPSHOT_DB_MDBX_DIR_PREFIX.to_string()
+                + "full_sync_temp_"
+                + &snapshot_epoch_id.as_ref().to_hex::<String>()
+                + &merkle_root.as_ref().to_hex::<String>(),
+        )
+    }
+
+    fn rename_snapshot_db<P: AsRef<Path>>(
+        old_path: P, new_path: P,
+    ) -> Result<()> {
+        Ok(fs::rename(old_path, new_path)?)
+    }
+}
+
+impl SnapshotDbManagerTrait for SnapshotDbManagerMdbx {
+    type SnapshotDb = SnapshotDbMdbx;
+    type SnapshotDbWrite = SnapshotDbWriteableMdbx;
+
+    fn get_snapshot_dir(&self) -> &Path {
+        self.snapshot_path.as_path()
+    }
+
+    fn get_mpt_snapshot_dir(&self) -> &Path {
+        self.mpt_snapshot_path.as_path()
+    }
+
+    fn get_latest_mpt_snapshot_db_name(&self) -> String {
+        Self::SNAPSHOT_DB_MDBX_DIR_PREFIX.to_string()
+            + SnapshotDbManagerSqlite::LATEST_MPT_SNAPSHOT_DIR
+    }
+
+    fn get_snapshot_db_name(&self, snapshot_epoch_id: &EpochId) -> String {
+        Self::SNAPSHOT_DB_MDBX_DIR_PREFIX.to_string()
+            + &snapshot_epoch_id.as_ref().to_hex::<String>()
+    }
+
+    fn get_snapshot_db_path(&self, snapshot_epoch_id: &EpochId) -> PathBuf {
+        self.snapshot_path
+            .join(&self.get_snapshot_db_name(snapshot_epoch_id))
+    }
+
+    fn get_epoch_id_from_snapshot_db_name(
+        &self, snapshot_db_name: &str,
+    ) -> Result<EpochId> {
+        let prefix_len = Self::SNAPSHOT_DB_MDBX_DIR_PREFIX.len();
+        Ok(EpochId::from_str(&snapshot_db_name[prefix_len..])
+            .map_err(|_op| "not correct snapshot db name")?)
+    }
+
+    fn new_snapshot_by_merging<'m>(
+        &self, old_snapshot_epoch_id: &EpochId, snapshot_epoch_id: EpochId,
+        delta_mpt: DeltaMptIterator,
+        mut in_progress_snapshot_info: SnapshotInfo,
+        snapshot_info_map_rwlock: &'m RwLock<PersistedSnapshotInfoMap>,
+        new_epoch_height: u64, recover_mpt_with_kv_snapshot_exist: bool,
+    ) -> Result<(RwLockWriteGuard<'m, PersistedSnapshotInfoMap>, SnapshotInfo)>
+    {
+        info!(
+            "new_snapshot_by_merging: old={:?} new={:?} new epoch height={}, recovering mpt={}",
+            old_snapshot_epoch_id, snapshot_epoch_id, new_epoch_height, recover_mpt_with_kv_snapshot_exist
+        );
+        let temp_db_path = self.get_merge_temp_snapshot_db_path(
+            old_snapshot_epoch_id,
+            &snapshot_epoch_id,
+        );
+        let new_snapshot_db_path =
+            self.get_snapshot_db_path(&snapshot_epoch_id);
+        let in_reconstruct_snapshot_state = self
+            .reconstruct_snapshot_id_for_reboot
+            .write()
+            .take()
+            .is_some_and(|v| v == snapshot_epoch_id);
+
+        let new_snapshot_root = if recover_mpt_with_kv_snapshot_exist {
+            // The MPT is kept in the snapshot, so it's complete whenever the
+            // snapshot exists.
+            self.open_snapshot_readonly(
+                new_snapshot_db_path.clone(),
+                /* try_open = */ false,
+            )?
+            .ok_or(Error::from(ErrorKind::SnapshotNotFound))?
+            .open_snapshot_mpt()?
+            .get_merkle_root()
+        } else {
+            let mut snapshot_db = self.open_snapshot_write(
+                temp_db_path.clone(),
+                /* create = */ true,
+            )?;
+            snapshot_db.dump_delta_mpt(&delta_mpt)?;
+            if *old_snapshot_epoch_id == NULL_EPOCH {
+                snapshot_db.direct_merge(in_reconstruct_snapshot_state)?
+            } else {
+                let old_snapshot_db = self
+                    .open_snapshot_readonly(
+                        self.get_snapshot_db_path(old_snapshot_epoch_id),
+                        /* try_open = */ false,
+                    )?
+                    .ok_or(Error::from(ErrorKind::SnapshotNotFound))?;
+                snapshot_db.copy_and_merge(
+                    &old_snapshot_db,
+                    in_reconstruct_snapshot_state,
+                )?
+            }
+        };
+
+        in_progress_snapshot_info.merkle_root = new_snapshot_root.clone();
+        let locked = snapshot_info_map_rwlock.write();
+
+        if !recover_mpt_with_kv_snapshot_exist {
+            Self::rename_snapshot_db(&temp_db_path, &new_snapshot_db_path)?;
+        }
+
+        Ok((locked, in_progress_snapshot_info))
+    }
+
+    fn get_snapshot_by_epoch_id(
+        &self, snapshot_epoch_id: &EpochId, try_open: bool,
+        _open_mpt_snapshot: bool,
+    ) -> Result<Option<Self::SnapshotDb>> {
+        if snapshot_epoch_id.eq(&NULL_EPOCH) {
+            return Ok(Some(Self::SnapshotDb::get_null_snapshot()));
+        } else {
+            let path = self.get_snapshot_db_path(snapshot_epoch_id);
+            Ok(self
+                .open_snapshot_readonly(path, try_open)?
+                .map(|snapshot_db| SnapshotDbMdbx { snapshot_db }))
+        }
+    }
+
+    fn destroy_snapshot(&self, snapshot_epoch_id: &EpochId) -> Result<()> {
+        debug!("destroy snapshot {:?}", snapshot_epoch_id);
+        let path = self.get_snapshot_db_path(snapshot_epoch_id);
+        let maybe_snapshot = loop {
+            match self.already_open_snapshots.read().get(&path) {
+                Some(Some(snapshot)) => {
+                    match Weak::upgrade(snapshot) {
+                        None => {
+                            // This is transient and we wait for the db to be
+                            // fully closed.
+                            thread::sleep(Duration::from_millis(5));
+                            continue;
+                        }
+                        Some(snapshot) => break Some(snapshot),
+                    }
+                }
+                Some(None) => {
+                    // This should not happen because Mazze always write on a
+                    // snapshot db under a temporary name. All completed
+                    // snapshots are readonly.
+                    if cfg!(debug_assertions) {
+                        unreachable!("Try to destroy a snapshot being open exclusively for write.")
+                    } else {
+                        unsafe { unreachable_unchecked() }
+                    }
+                }
+                None => break None,
+            };
+        };
+
+        match maybe_snapshot {
+            None => {
+                if snapshot_epoch_id.ne(&NULL_EPOCH) {
+                    Self::fs_remove_snapshot(&path);
+                }
+            }
+            Some(snapshot) => {
+                snapshot.set_remove_on_last_close();
+            }
+        };
+
+        Ok(())
+    }
+
+    fn new_temp_snapshot_for_full_sync(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        _epoch_height: u64,
+    ) -> Result<Self::SnapshotDbWrite> {
+        // Only one snapshot is restored by full sync at a time, so the temp
+        // snapshots left by a previous full sync are no longer needed.
+        self.destroy_temp_snapshots_for_full_sync()?;
+
+        let temp_db_path = self.get_full_sync_temp_snapshot_db_path(
+            snapshot_epoch_id,
+            merkle_root,
+        );
+        Ok(SnapshotDbWriteableMdbx {
+            kv_snapshot_db: self
+                .open_snapshot_write(temp_db_path, /* create = */ true)?,
+        })
+    }
+
+    fn open_temp_snapshot_for_full_sync(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        _epoch_height: u64,
+    ) -> Result<Option<Self::SnapshotDbWrite>> {
+        let temp_db_path = self.get_full_sync_temp_snapshot_db_path(
+            snapshot_epoch_id,
+            merkle_root,
+        );
+        if !temp_db_path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(SnapshotDbWriteableMdbx {
+            kv_snapshot_db: self
+                .open_snapshot_write(temp_db_path, /* create = */ false)?,
+        }))
+    }
+
+    fn destroy_temp_snapshots_for_full_sync(&self) -> Result<()> {
+        for entry in fs::read_dir(&self.snapshot_path)? {
+            let entry = entry?;
+            if entry.file_name().to_str().map_or(false, |dir_name| {
+                self.is_full_sync_temp_snapshot_db_name(dir_name)
+            }) {
+                debug!(
+                    "Remove full sync temp snapshot {}",
+                    entry.path().display()
+                );
+                fs::remove_dir_all(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize_full_sync_snapshot<'m>(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        snapshot_info_map_rwlock: &'m RwLock<PersistedSnapshotInfoMap>,
+    ) -> Result<RwLockWriteGuard<'m, PersistedSnapshotInfoMap>> {
+        let temp_db_path = self.get_full_sync_temp_snapshot_db_path(
+            snapshot_epoch_id,
+            merkle_root,
+        );
+        let final_db_path = self.get_snapshot_db_path(snapshot_epoch_id);
+        let locked = snapshot_info_map_rwlock.write();
+
+        Self::rename_snapshot_db(&temp_db_path, &final_db_path)?;
+        Ok(locked)
+    }
+
+    fn recovery_latest_mpt_snapshot_from_checkpoint(
+        &self, _snapshot_epoch_id: &EpochId,
+        _snapshot_epoch_id_before_recovered: Option<EpochId>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn create_mpt_snapshot_from_latest(
+        &self, _new_snapshot_epoch_id: &EpochId,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn try_get_new_snapshot_epoch_from_temp_path(
+        &self, dir_name: &str,
+    ) -> Option<EpochId> {
+        let prefix =
+            Self::SNAPSHOT_DB_MDBX_DIR_PREFIX.to_string() + "merge_temp_";
+
+        if dir_name.starts_with(&prefix) {
+            match EpochId::from_str(
+                &dir_name[(prefix.len() + EpochId::len_bytes() * 2)..],
+            ) {
+                Ok(e) => Some(e),
+                Err(e) => {
+                    error!(
+                        "get new snapshot epoch id from temp path failed: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        }
+    }
+
+    fn try_get_new_snapshot_epoch_from_mpt_temp_path(
+        &self, _dir_name: &str,
+    ) -> Option<EpochId> {
+        None
+    }
+
+    fn is_full_sync_temp_snapshot_db_name(&self, dir_name: &str) -> bool {
+        dir_name.starts_with(
+            &(Self::SNAPSHOT_DB_MDBX_DIR_PREFIX.to_string()
+                + "full_sync_temp_"),
+        )
+    }
+}
+
+use crate::{
+    impls::{
+        delta_mpt::DeltaMptIterator, errors::*,
+        storage_manager::PersistedSnapshotInfoMap,
+    },
+    storage_db::{
+        key_value_db::KeyValueDbTraitSingleWriter, DbValueType,
+        KeyValueDbTypes, SnapshotDbManagerTrait, SnapshotDbTrait,
+        SnapshotDbWriteableTrait, SnapshotInfo, SnapshotMptTraitRead,
+    },
+    MdbxConfig,
+};
+use futures::executor;
+use parking_lot::{Mutex, RwLock, RwLockWriteGuard};
+use primitives::{EpochId, MerkleHash, NULL_EPOCH};
+use rustc_hex::ToHex;
+use std::{
+    fs,
+    hint::unreachable_unchecked,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Weak},
+    thread,
+    time::Duration,
+};
+use tokio::sync::Semaphore;
+
+use super::{
+    snapshot_db_manager_sqlite::{
+        AlreadyOpenSnapshots, SnapshotDbManagerSqlite,
+    },
+    snapshot_db_mdbx::SnapshotDbMdbx,
+    snapshot_kv_db_mdbx::{SnapshotKvDbMdbx, SnapshotMptMdbx},
+};
@@ -100,8 +100,8 @@ pub type AlreadyOpenSnapshots<T> =
 
 impl SnapshotDbManagerSqlite {
     pub const LATEST_MPT_SNAPSHOT_DIR: &'static str = "latest";
-    const MPT_SNAPSHOT_DIR: &'static str = "mpt_snapshot";
-    const SNAPSHOT_DB_SQLITE_DIR_PREFIX: &'static str = "sqlite_";
+    pub const MPT_SNAPSHOT_DIR: &'static str = "mpt_snapshot";
+    pub const SNAPSHOT_DB_SQLITE_DIR_PREFIX: &'static str = "sqlite_";
 
     pub fn new(
         snapshot_path: PathBuf, max_open_snapshots: u16,
@@ -364,7 +364,7 @@ impl SnapshotDbManagerSqlite {
             self.is_mpt_table_in_current_db_for_epoch(new_epoch_height);
         let mpt_db: Option<SnapshotMptDbSqlite> = if !mpt_table_in_current_db {
             let (mpt_snapshot_path, create_mpt) = match mpt_snapshot_path {
-                Some(v) => (v, true),
+                Some(v) => (v, create),
                 _ => {
                     debug!(
                         "new_epoch_height {}, latest_snapshot_id {} {}",
@@ -716,6 +716,13 @@ impl SnapshotDbManagerSqlite {
         )
     }
 
+    fn is_full_sync_temp_snapshot_db_name(&self, dir_name: &str) -> bool {
+        dir_name.starts_with(
+            &(Self::SNAPSHOT_DB_SQLITE_DIR_PREFIX.to_string()
+                + "full_sync_temp_"),
+        )
+    }
+
     fn get_merge_temp_mpt_snapshot_db_path(
         &self, new_snapshot_epoch_id: &EpochId,
     ) -> PathBuf {
@@ -1362,6 +1369,10 @@ impl SnapshotDbManagerTrait for SnapshotDbManagerSqlite {
         &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
         epoch_height: u64,
     ) -> Result<Self::SnapshotDbWrite> {
+        // Only one snapshot is restored by full sync at a time, so the temp
+        // snapshots left by a previous full sync are no longer needed.
+        self.destroy_temp_snapshots_for_full_sync()?;
+
         let mpt_table_in_current_db =
             self.is_mpt_table_in_current_db_for_epoch(epoch_height);
         let temp_mpt_snapshot_path = if mpt_table_in_current_db {
@@ -1391,6 +1402,65 @@ impl SnapshotDbManagerTrait for SnapshotDbManagerSqlite {
         })
     }
 
+    fn open_temp_snapshot_for_full_sync(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        epoch_height: u64,
+    ) -> Result<Option<Self::SnapshotDbWrite>> {
+        let temp_db_path = self.get_full_sync_temp_snapshot_db_path(
+            snapshot_epoch_id,
+            merkle_root,
+        );
+        if !temp_db_path.exists() {
+            return Ok(None);
+        }
+        let mpt_table_in_current_db =
+            self.is_mpt_table_in_current_db_for_epoch(epoch_height);
+        let temp_mpt_snapshot_path = if mpt_table_in_current_db {
+            None
+        } else {
+            let temp_mpt_snapshot_path = self
+                .get_full_sync_temp_mpt_snapshot_db_path(
+                    snapshot_epoch_id,
+                    merkle_root,
+                );
+            if !temp_mpt_snapshot_path.exists() {
+                return Ok(None);
+            }
+            Some(temp_mpt_snapshot_path)
+        };
+
+        let (kv_snapshot_db, mpt_snapshot_db) = self.open_snapshot_write(
+            temp_db_path,
+            /* create = */ false,
+            epoch_height,
+            temp_mpt_snapshot_path,
+            snapshot_epoch_id,
+        )?;
+
+        Ok(Some(SnapshotDbWriteable {
+            kv_snapshot_db,
+            mpt_snapshot_db,
+        }))
+    }
+
+    fn destroy_temp_snapshots_for_full_sync(&self) -> Result<()> {
+        for snapshot_dir in [&self.snapshot_path, &self.mpt_snapshot_path] {
+            for entry in fs::read_dir(snapshot_dir)? {
+                let entry = entry?;
+                if entry.file_name().to_str().map_or(false, |dir_name| {
+                    self.is_full_sync_temp_snapshot_db_name(dir_name)
+                }) {
+                    debug!(
+                        "Remove full sync temp snapshot {}",
+                        entry.path().display()
+                    );
+                    fs::remove_dir_all(entry.path())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn finalize_full_sync_snapshot<'m>(
         &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
         snapshot_info_map_rwlock: &'m RwLock<PersistedSnapshotInfoMap>,
@@ -1549,6 +1619,10 @@ impl SnapshotDbManagerTrait for SnapshotDbManagerSqlite {
     ) -> Option<EpochId> {
         self.try_get_new_snapshot_epoch_from_mpt_temp_path(dir_name)
     }
+
+    fn is_full_sync_temp_snapshot_db_name(&self, dir_name: &str) -> bool {
+        self.is_full_sync_temp_snapshot_db_name(dir_name)
+    }
 }
 
 use crate::{
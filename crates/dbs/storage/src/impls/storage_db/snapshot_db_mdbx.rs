@@ -0,0 +1,165 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+pub struct SnapshotDbMdbx {
+    pub snapshot_db: Arc<SnapshotKvDbMdbx>,
+}
+
+impl KeyValueDbTypes for SnapshotDbMdbx {
+    type ValueType = Box<[u8]>;
+}
+
+impl KeyValueDbTraitRead for SnapshotDbMdbx {
+    fn get(&self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        self.snapshot_db.get(key)
+    }
+}
+
+impl KeyValueDbTraitOwnedRead for SnapshotDbMdbx {
+    fn get_mut(&mut self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        self.snapshot_db.get(key)
+    }
+}
+
+/// A finished snapshot is readonly.
+impl KeyValueDbTraitSingleWriter for SnapshotDbMdbx {
+    fn delete(&mut self, _key: &[u8]) -> Result<Option<Option<Box<[u8]>>>> {
+        unreachable!()
+    }
+
+    fn put(
+        &mut self, _key: &[u8], _value: &[u8],
+    ) -> Result<Option<Option<Box<[u8]>>>> {
+        unreachable!()
+    }
+}
+
+impl<'db> OpenSnapshotMptTrait<'db> for SnapshotDbMdbx {
+    type SnapshotDbAsOwnedType = SnapshotMptMdbx;
+    type SnapshotDbBorrowMutType = SnapshotMptMdbx;
+    type SnapshotDbBorrowSharedType = SnapshotMptMdbx;
+
+    fn open_snapshot_mpt_owned(
+        &'db mut self,
+    ) -> Result<Self::SnapshotDbBorrowMutType> {
+        unreachable!()
+    }
+
+    fn open_snapshot_mpt_as_owned(
+        &'db self,
+    ) -> Result<Self::SnapshotDbAsOwnedType> {
+        self.snapshot_db.open_snapshot_mpt()
+    }
+
+    fn open_snapshot_mpt_shared(
+        &'db self,
+    ) -> Result<Self::SnapshotDbBorrowSharedType> {
+        self.snapshot_db.open_snapshot_mpt()
+    }
+}
+
+impl SnapshotDbMdbx {
+    pub fn dumped_delta_kv_set_keys_iterator(
+        &self,
+    ) -> Result<KvdbMdbxTable<<Self as KeyValueDbTypes>::ValueType>> {
+        self.snapshot_db.dumped_delta_kv_set_keys_iterator()
+    }
+
+    pub fn dumped_delta_kv_delete_keys_iterator(
+        &self,
+    ) -> Result<KvdbMdbxTable<()>> {
+        self.snapshot_db.dumped_delta_kv_delete_keys_iterator()
+    }
+}
+
+/// The MDBX snapshots are opened, created and merged by
+/// `SnapshotDbManagerMdbx` through `SnapshotKvDbMdbx`.
+impl SnapshotDbTrait for SnapshotDbMdbx {
+    type SnapshotKvdbIterTraitTag = KvdbMdbxIteratorTag;
+    type SnapshotKvdbIterType =
+        KvdbMdbxTable<<Self as KeyValueDbTypes>::ValueType>;
+
+    fn get_null_snapshot() -> Self {
+        Self {
+            snapshot_db: Arc::new(SnapshotKvDbMdbx::get_null_snapshot()),
+        }
+    }
+
+    fn open(
+        _snapshot_path: &Path, _readonly: bool,
+        _already_open_snapshots: &AlreadyOpenSnapshots<Self>,
+        _open_semaphore: &Arc<Semaphore>,
+    ) -> Result<SnapshotDbMdbx> {
+        unreachable!()
+    }
+
+    fn create(
+        _snapshot_path: &Path,
+        _already_open_snapshots: &AlreadyOpenSnapshots<Self>,
+        _open_semaphore: &Arc<Semaphore>, _mpt_table_in_current_db: bool,
+    ) -> Result<SnapshotDbMdbx> {
+        unreachable!()
+    }
+
+    fn direct_merge(
+        &mut self, _old_snapshot_db: Option<&Arc<SnapshotDbMdbx>>,
+        _mpt_snapshot: &mut Option<SnapshotMptDbSqlite>,
+        _recover_mpt_with_kv_snapshot_exist: bool,
+        _in_reconstruct_snapshot_state: bool,
+    ) -> Result<MerkleHash> {
+        unreachable!()
+    }
+
+    fn copy_and_merge(
+        &mut self, _old_snapshot_db: &Arc<SnapshotDbMdbx>,
+        _mpt_snapshot_db: &mut Option<SnapshotMptDbSqlite>,
+        _in_reconstruct_snapshot_state: bool,
+    ) -> Result<MerkleHash> {
+        unreachable!()
+    }
+
+    fn start_transaction(&mut self) -> Result<()> {
+        unreachable!()
+    }
+
+    fn commit_transaction(&mut self) -> Result<()> {
+        unreachable!()
+    }
+
+    fn is_mpt_table_in_current_db(&self) -> bool {
+        true
+    }
+
+    fn snapshot_kv_iterator(
+        &self,
+    ) -> Result<
+        Wrap<
+            Self::SnapshotKvdbIterType,
+            dyn KeyValueDbIterableTrait<MptKeyValue, [u8], KvdbMdbxIteratorTag>,
+        >,
+    > {
+        Ok(Wrap(self.snapshot_db.snapshot_kv_iterator()?))
+    }
+}
+
+use crate::{
+    impls::errors::*,
+    storage_db::{
+        KeyValueDbIterableTrait, KeyValueDbTraitOwnedRead, KeyValueDbTraitRead,
+        KeyValueDbTraitSingleWriter, KeyValueDbTypes, OpenSnapshotMptTrait,
+        SnapshotDbTrait,
+    },
+    utils::wrap::Wrap,
+    MptKeyValue,
+};
+use primitives::MerkleHash;
+use std::{path::Path, sync::Arc};
+use tokio::sync::Semaphore;
+
+use super::{
+    kvdb_mdbx::{KvdbMdbxIteratorTag, KvdbMdbxTable},
+    snapshot_db_manager_sqlite::AlreadyOpenSnapshots,
+    snapshot_kv_db_mdbx::{SnapshotKvDbMdbx, SnapshotMptMdbx},
+    snapshot_mpt_db_sqlite::SnapshotMptDbSqlite,
+};
@@ -0,0 +1,585 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+/// The tables of an MDBX snapshot, which live in one MDBX environment.
+///
+/// Writes are buffered and committed in batches, because MDBX allows only one
+/// write transaction at a time and the merge writes one key at a time.
+pub struct SnapshotMdbxTables {
+    db: Arc<Database<NoWriteMap>>,
+    pending: Mutex<PendingWrites>,
+}
+
+#[derive(Default)]
+struct PendingWrites {
+    /// The pending writes of each table, where a `None` value is a deletion.
+    tables: HashMap<&'static str, BTreeMap<Vec<u8>, Option<Box<[u8]>>>>,
+    count: usize,
+}
+
+impl SnapshotMdbxTables {
+    const FLUSH_THRESHOLD: usize = 100_000;
+
+    fn open(path: &Path, config: &MdbxConfig, create: bool) -> Result<Self> {
+        let db = KvdbMdbx::open_database(
+            path,
+            config,
+            SnapshotKvDbMdbx::TABLES.len(),
+        )?;
+        if create {
+            let txn = db.begin_rw_txn()?;
+            for table_name in SnapshotKvDbMdbx::TABLES {
+                txn.create_table(Some(table_name), TableFlags::default())?;
+            }
+            txn.commit()?;
+        }
+
+        Ok(Self {
+            db: Arc::new(db),
+            pending: Default::default(),
+        })
+    }
+
+    fn get(
+        &self, table_name: &'static str, key: &[u8],
+    ) -> Result<Option<Box<[u8]>>> {
+        if let Some(maybe_value) = self
+            .pending
+            .lock()
+            .tables
+            .get(table_name)
+            .and_then(|writes| writes.get(key))
+        {
+            return Ok(maybe_value.clone());
+        }
+
+        let txn = self.db.begin_ro_txn()?;
+        let table = txn.open_table(Some(table_name))?;
+        Ok(txn
+            .get::<Vec<u8>>(&table, key)?
+            .map(|db_value| db_value.into_boxed_slice()))
+    }
+
+    fn write(
+        &self, table_name: &'static str, key: &[u8], maybe_value: Option<&[u8]>,
+    ) -> Result<()> {
+        let mut pending = self.pending.lock();
+        pending
+            .tables
+            .entry(table_name)
+            .or_default()
+            .insert(key.to_vec(), maybe_value.map(Into::into));
+        pending.count += 1;
+        if pending.count >= Self::FLUSH_THRESHOLD {
+            Self::flush_impl(&self.db, &mut pending)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        Self::flush_impl(&self.db, &mut self.pending.lock())
+    }
+
+    fn flush_impl(
+        db: &Database<NoWriteMap>, pending: &mut PendingWrites,
+    ) -> Result<()> {
+        if pending.count == 0 {
+            return Ok(());
+        }
+
+        let txn = db.begin_rw_txn()?;
+        for (table_name, writes) in &pending.tables {
+            let table = txn.open_table(Some(*table_name))?;
+            for (key, maybe_value) in writes {
+                match maybe_value {
+                    Some(value) => {
+                        txn.put(&table, key, value, WriteFlags::empty())?;
+                    }
+                    None => {
+                        txn.del(&table, key, None)?;
+                    }
+                }
+            }
+        }
+        txn.commit()?;
+
+        pending.tables.clear();
+        pending.count = 0;
+        Ok(())
+    }
+
+    fn clear_table(&self, table_name: &'static str) -> Result<()> {
+        let mut pending = self.pending.lock();
+        Self::flush_impl(&self.db, &mut pending)?;
+
+        let txn = self.db.begin_rw_txn()?;
+        let table = txn.open_table(Some(table_name))?;
+        txn.clear_table(&table)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// The pending writes are flushed so that the iteration sees them.
+    fn iterable<ValueType>(
+        &self, table_name: &'static str,
+    ) -> Result<KvdbMdbxTable<ValueType>> {
+        self.flush()?;
+        Ok(KvdbMdbxTable::new(Some(self.db.clone()), table_name))
+    }
+}
+
+pub struct SnapshotKvDbMdbx {
+    // Option because we need an empty snapshot db for empty snapshot.
+    maybe_tables: Option<Arc<SnapshotMdbxTables>>,
+    already_open_snapshots: AlreadyOpenSnapshots<Self>,
+    open_semaphore: Arc<Semaphore>,
+    path: PathBuf,
+    remove_on_close: AtomicBool,
+}
+
+impl Drop for SnapshotKvDbMdbx {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            self.maybe_tables.take();
+            SnapshotDbManagerMdbx::on_close(
+                &self.already_open_snapshots,
+                &self.open_semaphore,
+                &self.path,
+                self.remove_on_close.load(Ordering::Relaxed),
+            )
+        }
+    }
+}
+
+impl SnapshotKvDbMdbx {
+    /// These two tables are temporary table for the merging process, but they
+    /// remain to help other nodes to do 1-step syncing.
+    pub const DELTA_KV_DELETE_TABLE_NAME: &'static str =
+        SnapshotKvDbSqlite::DELTA_KV_DELETE_TABLE_NAME;
+    pub const DELTA_KV_SET_TABLE_NAME: &'static str =
+        SnapshotKvDbSqlite::DELTA_KV_SET_TABLE_NAME;
+    /// Key-Value table. Key is unique key in this table.
+    pub const SNAPSHOT_KV_TABLE_NAME: &'static str =
+        SnapshotKvDbSqlite::SNAPSHOT_KV_TABLE_NAME;
+    /// The MPT is always kept in the snapshot itself.
+    pub const SNAPSHOT_MPT_TABLE_NAME: &'static str = "snapshot_mpt";
+    const TABLES: [&'static str; 4] = [
+        Self::DELTA_KV_DELETE_TABLE_NAME,
+        Self::DELTA_KV_SET_TABLE_NAME,
+        Self::SNAPSHOT_KV_TABLE_NAME,
+        Self::SNAPSHOT_MPT_TABLE_NAME,
+    ];
+
+    pub fn get_null_snapshot() -> Self {
+        Self {
+            maybe_tables: None,
+            already_open_snapshots: Default::default(),
+            open_semaphore: Arc::new(Semaphore::new(0)),
+            path: Default::default(),
+            remove_on_close: Default::default(),
+        }
+    }
+
+    /// Store already_open_snapshots and open_semaphore to update
+    /// SnapshotDbManager on destructor. SnapshotDb itself does not take
+    /// care of the update on these data.
+    pub fn open(
+        snapshot_path: &Path, mdbx_config: &MdbxConfig,
+        already_open_snapshots: &AlreadyOpenSnapshots<Self>,
+        open_semaphore: &Arc<Semaphore>,
+    ) -> Result<SnapshotKvDbMdbx> {
+        Ok(Self {
+            maybe_tables: Some(Arc::new(SnapshotMdbxTables::open(
+                snapshot_path,
+                mdbx_config,
+                /* create = */ false,
+            )?)),
+            already_open_snapshots: already_open_snapshots.clone(),
+            open_semaphore: open_semaphore.clone(),
+            path: snapshot_path.to_path_buf(),
+            remove_on_close: Default::default(),
+        })
+    }
+
+    /// Store already_open_snapshots and open_semaphore to update
+    /// SnapshotDbManager on destructor. SnapshotDb itself does not take
+    /// care of the update on these data.
+    pub fn create(
+        snapshot_path: &Path, mdbx_config: &MdbxConfig,
+        already_open_snapshots: &AlreadyOpenSnapshots<Self>,
+        open_semaphore: &Arc<Semaphore>,
+    ) -> Result<SnapshotKvDbMdbx> {
+        fs::create_dir_all(snapshot_path)?;
+        match SnapshotMdbxTables::open(
+            snapshot_path,
+            mdbx_config,
+            /* create = */ true,
+        ) {
+            Err(e) => {
+                fs::remove_dir_all(&snapshot_path)?;
+                bail!(e);
+            }
+            Ok(tables) => Ok(SnapshotKvDbMdbx {
+                maybe_tables: Some(Arc::new(tables)),
+                already_open_snapshots: already_open_snapshots.clone(),
+                open_semaphore: open_semaphore.clone(),
+                path: snapshot_path.to_path_buf(),
+                remove_on_close: Default::default(),
+            }),
+        }
+    }
+
+    pub fn set_remove_on_last_close(&self) {
+        self.remove_on_close.store(true, Ordering::Relaxed);
+    }
+
+    fn tables(&self) -> &SnapshotMdbxTables {
+        // Safe to unwrap since we are not on a NULL snapshot.
+        self.maybe_tables.as_ref().unwrap()
+    }
+
+    /// Commit the buffered writes.
+    pub fn flush(&self) -> Result<()> {
+        match &self.maybe_tables {
+            None => Ok(()),
+            Some(tables) => tables.flush(),
+        }
+    }
+
+    fn iterable<ValueType>(
+        &self, table_name: &'static str,
+    ) -> Result<KvdbMdbxTable<ValueType>> {
+        match &self.maybe_tables {
+            None => Ok(KvdbMdbxTable::new(None, table_name)),
+            Some(tables) => tables.iterable(table_name),
+        }
+    }
+
+    pub fn snapshot_kv_iterator(
+        &self,
+    ) -> Result<KvdbMdbxTable<<Self as KeyValueDbTypes>::ValueType>> {
+        self.iterable(Self::SNAPSHOT_KV_TABLE_NAME)
+    }
+
+    pub fn dumped_delta_kv_set_keys_iterator(
+        &self,
+    ) -> Result<KvdbMdbxTable<<Self as KeyValueDbTypes>::ValueType>> {
+        self.iterable(Self::DELTA_KV_SET_TABLE_NAME)
+    }
+
+    pub fn dumped_delta_kv_delete_keys_iterator(
+        &self,
+    ) -> Result<KvdbMdbxTable<()>> {
+        self.iterable(Self::DELTA_KV_DELETE_TABLE_NAME)
+    }
+
+    pub fn open_snapshot_mpt(&self) -> Result<SnapshotMptMdbx> {
+        SnapshotMptMdbx::new(self.maybe_tables.clone())
+    }
+
+    pub fn dump_delta_mpt(
+        &mut self, delta_mpt: &DeltaMptIterator,
+    ) -> Result<()> {
+        debug!("dump_delta_mpt starts");
+        delta_mpt.iterate(&mut DeltaMptMergeDumperMdbx {
+            tables: self.tables(),
+        })?;
+        self.flush()
+    }
+
+    /// Dropping is optional, because these tables are necessary to provide
+    /// 1-step syncing.
+    pub fn drop_delta_mpt_dump(&mut self) -> Result<()> {
+        self.tables().clear_table(Self::DELTA_KV_SET_TABLE_NAME)?;
+        self.tables().clear_table(Self::DELTA_KV_DELETE_TABLE_NAME)
+    }
+
+    fn apply_update_to_kvdb(&mut self) -> Result<()> {
+        let mut delete_keys_iter =
+            self.dumped_delta_kv_delete_keys_iterator()?;
+        let mut deletions = delete_keys_iter.iter_range(&[], None)?.take();
+        while let Some((key, ())) = deletions.next()? {
+            self.delete(&key)?;
+        }
+
+        let mut set_keys_iter = self.dumped_delta_kv_set_keys_iterator()?;
+        let mut insertions = set_keys_iter.iter_range(&[], None)?.take();
+        while let Some((key, value)) = insertions.next()? {
+            self.put(&key, &value)?;
+        }
+
+        self.flush()
+    }
+
+    /// Merge the dumped delta MPT into a snapshot created from the NULL
+    /// snapshot.
+    pub fn direct_merge(
+        &mut self, in_reconstruct_snapshot_state: bool,
+    ) -> Result<MerkleHash> {
+        debug!("direct_merge begins.");
+        self.apply_update_to_kvdb()?;
+
+        let mut set_keys_iter = self.dumped_delta_kv_set_keys_iterator()?;
+        let mut delete_keys_iter =
+            self.dumped_delta_kv_delete_keys_iterator()?;
+        let mut mpt_to_modify = self.open_snapshot_mpt()?;
+
+        let mut mpt_merger = MptMerger::new(
+            None,
+            &mut mpt_to_modify as &mut dyn SnapshotMptTraitRw,
+        );
+        let snapshot_root = mpt_merger.merge_insertion_deletion_separated(
+            delete_keys_iter.iter_range(&[], None)?.take(),
+            set_keys_iter.iter_range(&[], None)?.take(),
+            in_reconstruct_snapshot_state,
+        )?;
+        self.flush()?;
+
+        Ok(snapshot_root)
+    }
+
+    /// Merge the dumped delta MPT into a copy of the old snapshot. The MPT
+    /// is saved as a new MPT, with the old snapshot's MPT as base.
+    pub fn copy_and_merge(
+        &mut self, old_snapshot_db: &SnapshotKvDbMdbx,
+        in_reconstruct_snapshot_state: bool,
+    ) -> Result<MerkleHash> {
+        debug!("copy_and_merge begins.");
+        let mut kv_iter = old_snapshot_db.snapshot_kv_iterator()?;
+        let mut iter = kv_iter.iter_range(&[], None)?.take();
+        while let Some((k, v)) = iter.next()? {
+            self.put(&k, &v)?;
+        }
+        self.apply_update_to_kvdb()?;
+
+        let mut set_keys_iter = self.dumped_delta_kv_set_keys_iterator()?;
+        let mut delete_keys_iter =
+            self.dumped_delta_kv_delete_keys_iterator()?;
+        let mut base_mpt = old_snapshot_db.open_snapshot_mpt()?;
+        let mut save_as_mpt = self.open_snapshot_mpt()?;
+
+        let mut mpt_merger = MptMerger::new(
+            Some(&mut base_mpt as &mut dyn SnapshotMptTraitReadAndIterate),
+            &mut save_as_mpt as &mut dyn SnapshotMptTraitRw,
+        );
+        let snapshot_root = mpt_merger.merge_insertion_deletion_separated(
+            delete_keys_iter.iter_range(&[], None)?.take(),
+            set_keys_iter.iter_range(&[], None)?.take(),
+            in_reconstruct_snapshot_state,
+        )?;
+        self.flush()?;
+
+        Ok(snapshot_root)
+    }
+}
+
+impl KeyValueDbTypes for SnapshotKvDbMdbx {
+    type ValueType = Box<[u8]>;
+}
+
+impl KeyValueDbTraitRead for SnapshotKvDbMdbx {
+    fn get(&self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        match &self.maybe_tables {
+            None => Ok(None),
+            Some(tables) => tables.get(Self::SNAPSHOT_KV_TABLE_NAME, key),
+        }
+    }
+}
+
+impl KeyValueDbTraitOwnedRead for SnapshotKvDbMdbx {
+    fn get_mut(&mut self, key: &[u8]) -> Result<Option<Box<[u8]>>> {
+        self.get(key)
+    }
+}
+
+impl KeyValueDbTraitSingleWriter for SnapshotKvDbMdbx {
+    fn delete(&mut self, key: &[u8]) -> Result<Option<Option<Box<[u8]>>>> {
+        self.tables()
+            .write(Self::SNAPSHOT_KV_TABLE_NAME, key, None)?;
+        Ok(None)
+    }
+
+    fn put(
+        &mut self, key: &[u8], value: &[u8],
+    ) -> Result<Option<Option<Box<[u8]>>>> {
+        self.tables()
+            .write(Self::SNAPSHOT_KV_TABLE_NAME, key, Some(value))?;
+        Ok(None)
+    }
+}
+
+/// The snapshot MPT in the `snapshot_mpt` table of an MDBX snapshot.
+pub struct SnapshotMptMdbx {
+    maybe_tables: Option<Arc<SnapshotMdbxTables>>,
+    merkle_root: MerkleHash,
+}
+
+impl SnapshotMptMdbx {
+    fn new(maybe_tables: Option<Arc<SnapshotMdbxTables>>) -> Result<Self> {
+        let mut mpt = Self {
+            maybe_tables,
+            merkle_root: MERKLE_NULL_NODE,
+        };
+        let path_to_root_node = CompressedPathRaw::default();
+        if let Some(rlp) =
+            mpt.load_node_rlp(&mpt_node_path_to_db_key(&path_to_root_node))?
+        {
+            mpt.merkle_root =
+                *SnapshotMptNode::load_rlp_and_check(&rlp, &path_to_root_node)?
+                    .get_merkle();
+        }
+        Ok(mpt)
+    }
+
+    fn load_node_rlp(&self, key: &[u8]) -> Result<Option<SnapshotMptDbValue>> {
+        match &self.maybe_tables {
+            None => Ok(None),
+            Some(tables) => {
+                tables.get(SnapshotKvDbMdbx::SNAPSHOT_MPT_TABLE_NAME, key)
+            }
+        }
+    }
+
+    fn tables(&self) -> &SnapshotMdbxTables {
+        // Safe to unwrap since we are not on a NULL snapshot.
+        self.maybe_tables.as_ref().unwrap()
+    }
+}
+
+impl SnapshotMptTraitRead for SnapshotMptMdbx {
+    fn get_merkle_root(&self) -> MerkleHash {
+        self.merkle_root
+    }
+
+    fn load_node(
+        &mut self, path: &dyn CompressedPathTrait,
+    ) -> Result<Option<SnapshotMptNode>> {
+        match self.load_node_rlp(&mpt_node_path_to_db_key(path))? {
+            None => Ok(None),
+            Some(rlp) => {
+                Ok(Some(SnapshotMptNode::load_rlp_and_check(&rlp, path)?))
+            }
+        }
+    }
+}
+
+impl SnapshotMptTraitReadAndIterate for SnapshotMptMdbx {
+    fn iterate_subtree_trie_nodes_without_root(
+        &mut self, path: &dyn CompressedPathTrait,
+    ) -> Result<Box<dyn SnapshotMptIteraterTrait + '_>> {
+        let begin_key_excl = mpt_node_path_to_db_key(path);
+
+        let mut end_key_excl = begin_key_excl.clone();
+        // The key is non empty. See also comment for compressed_path_to_db_key.
+        *end_key_excl.last_mut().unwrap() += 1;
+
+        let mut table = match &self.maybe_tables {
+            None => KvdbMdbxTable::<SnapshotMptDbValue>::new(
+                None,
+                SnapshotKvDbMdbx::SNAPSHOT_MPT_TABLE_NAME,
+            ),
+            Some(tables) => {
+                tables.iterable(SnapshotKvDbMdbx::SNAPSHOT_MPT_TABLE_NAME)?
+            }
+        };
+        Ok(Box::new(
+            table
+                .iter_range_excl(&begin_key_excl, &end_key_excl)?
+                .take()
+                .map(|(key, value)| {
+                    Ok((
+                        mpt_node_path_from_db_key(&key)?,
+                        SnapshotMptNode::decode(&Rlp::new(&value))?,
+                    ))
+                }),
+        ))
+    }
+}
+
+impl SnapshotMptTraitRw for SnapshotMptMdbx {
+    fn delete_node(&mut self, path: &dyn CompressedPathTrait) -> Result<()> {
+        self.tables().write(
+            SnapshotKvDbMdbx::SNAPSHOT_MPT_TABLE_NAME,
+            &mpt_node_path_to_db_key(path),
+            None,
+        )
+    }
+
+    fn write_node(
+        &mut self, path: &dyn CompressedPathTrait, trie_node: &SnapshotMptNode,
+    ) -> Result<()> {
+        self.tables().write(
+            SnapshotKvDbMdbx::SNAPSHOT_MPT_TABLE_NAME,
+            &mpt_node_path_to_db_key(path),
+            Some(&trie_node.rlp_bytes()),
+        )
+    }
+}
+
+pub struct DeltaMptMergeDumperMdbx<'a> {
+    tables: &'a SnapshotMdbxTables,
+}
+
+impl<'a> KVInserter<MptKeyValue> for DeltaMptMergeDumperMdbx<'a> {
+    fn push(&mut self, x: MptKeyValue) -> Result<()> {
+        let (mpt_key, value) = x;
+        let snapshot_key =
+            StorageKeyWithSpace::from_delta_mpt_key(&mpt_key).to_key_bytes();
+        if value.len() > 0 {
+            self.tables.write(
+                SnapshotKvDbMdbx::DELTA_KV_SET_TABLE_NAME,
+                &snapshot_key,
+                Some(&value),
+            )
+        } else {
+            self.tables.write(
+                SnapshotKvDbMdbx::DELTA_KV_DELETE_TABLE_NAME,
+                &snapshot_key,
+                Some(&[]),
+            )
+        }
+    }
+}
+
+use crate::{
+    impls::{
+        delta_mpt::DeltaMptIterator,
+        errors::*,
+        merkle_patricia_trie::{
+            CompressedPathRaw, CompressedPathTrait, MptKeyValue, MptMerger,
+        },
+        storage_db::{
+            kvdb_mdbx::{KvdbMdbx, KvdbMdbxTable},
+            snapshot_db_manager_mdbx::SnapshotDbManagerMdbx,
+            snapshot_db_manager_sqlite::AlreadyOpenSnapshots,
+            snapshot_kv_db_sqlite::SnapshotKvDbSqlite,
+            snapshot_mpt::{
+                mpt_node_path_from_db_key, mpt_node_path_to_db_key,
+            },
+        },
+    },
+    storage_db::{
+        snapshot_mpt::{SnapshotMptIteraterTrait, SnapshotMptNode},
+        KeyValueDbIterableTrait, KeyValueDbTraitOwnedRead, KeyValueDbTraitRead,
+        KeyValueDbTraitSingleWriter, KeyValueDbTypes, SnapshotMptDbValue,
+        SnapshotMptTraitRead, SnapshotMptTraitReadAndIterate,
+        SnapshotMptTraitRw,
+    },
+    KVInserter, MdbxConfig,
+};
+use fallible_iterator::FallibleIterator;
+use libmdbx::{Database, NoWriteMap, TableFlags, WriteFlags};
+use parking_lot::Mutex;
+use primitives::{MerkleHash, StorageKeyWithSpace, MERKLE_NULL_NODE};
+use rlp::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::Semaphore;
@@ -47,8 +47,11 @@ impl MptValueKind for Box<[u8]> {
     }
 }
 
-pub fn check_key_value_load<Value: MptValueKind>(
-    snapshot_db: &SnapshotDbSqlite,
+pub fn check_key_value_load<
+    SnapshotDb: for<'db> OpenSnapshotMptTrait<'db>,
+    Value: MptValueKind,
+>(
+    snapshot_db: &SnapshotDb,
     mut kv_iter: impl FallibleIterator<Item = (Vec<u8>, Value), Error = Error>,
     check_value: bool,
 ) -> Result<u64> {
@@ -92,7 +95,6 @@ use crate::{
             mpt_cursor::{BasicPathNode, CursorOpenPathTerminal, MptCursor},
             TrieNodeTrait,
         },
-        storage_db::snapshot_db_sqlite::SnapshotDbSqlite,
     },
     storage_db::{snapshot_db::OpenSnapshotMptTrait, SnapshotMptTraitRead},
     utils::access_mode,
@@ -103,7 +105,10 @@ use std::fmt::Debug;
 #[cfg(test)]
 use crate::impls::{
     defaults::DEFAULT_MAX_OPEN_SNAPSHOTS,
-    storage_db::snapshot_kv_db_sqlite::SnapshotDbTrait,
+    storage_db::{
+        snapshot_db_sqlite::SnapshotDbSqlite,
+        snapshot_kv_db_sqlite::SnapshotDbTrait,
+    },
 };
 #[cfg(test)]
 use std::{path::Path, sync::Arc};
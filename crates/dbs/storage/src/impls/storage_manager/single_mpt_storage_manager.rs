@@ -10,6 +10,7 @@ use crate::{
     replicated_state::StateFilter,
     storage_db::DeltaDbManagerTrait,
     ArcDeltaDbWrapper, DeltaMpt, OpenableOnDemandOpenDeltaDbTrait,
+    StateDbBackend,
 };
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use mazze_types::Space;
@@ -32,13 +33,13 @@ pub struct SingleMptStorageManager {
 impl SingleMptStorageManager {
     pub fn new_arc(
         db_path: PathBuf, space: Option<Space>, cache_start_size: u32,
-        cache_size: u32, idle_size: u32,
+        cache_size: u32, idle_size: u32, state_db_backend: &StateDbBackend,
     ) -> Arc<Self> {
         if !db_path.exists() {
             fs::create_dir_all(&db_path).expect("db path create error");
         }
         let db_manager = Arc::new(SingleMptDbManager {
-            db_manager: DeltaDbManager::new(db_path)
+            db_manager: DeltaDbManager::new(db_path, state_db_backend)
                 .expect("DeltaDb initialize error"),
             opened_mpt: Mutex::new(None),
         });
@@ -229,6 +229,7 @@ impl StorageManager {
 
         let delta_db_manager = Arc::new(DeltaDbManager::new(
             storage_conf.path_delta_mpts_dir.clone(),
+            &storage_conf.state_db_backend,
         )?);
         let new_storage_manager_result = Ok(Arc::new(Self {
             delta_db_manager: delta_db_manager.clone(),
@@ -243,6 +244,7 @@ impl StorageManager {
                     storage_conf.use_isolated_db_for_mpt_table,
                     storage_conf.use_isolated_db_for_mpt_table_height,
                     storage_conf.consensus_param.era_epoch_count,
+                    &storage_conf.state_db_backend,
                 )?,
             }),
             delta_mpts_id_gen: Default::default(),
@@ -410,6 +412,51 @@ impl StorageManager {
             .map(Clone::clone)
     }
 
+    pub fn get_snapshot_infos(&self) -> Vec<SnapshotInfo> {
+        self.snapshot_info_map_by_epoch
+            .read()
+            .get_map()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Recompute the merkle root of the snapshot at `snapshot_epoch_id` from
+    /// its key-values. Return `None` if the snapshot doesn't exist.
+    pub fn recompute_snapshot_merkle_root(
+        &self, snapshot_epoch_id: &EpochId,
+    ) -> Result<Option<MerkleHash>> {
+        match self.wait_for_snapshot(
+            snapshot_epoch_id,
+            /* try_open = */ false,
+            /* open_mpt_snapshot = */ false,
+        )? {
+            None => Ok(None),
+            Some(guarded_snapshot_db) => {
+                let (_guard, snapshot_db) = guarded_snapshot_db.into();
+                Ok(Some(Self::compute_snapshot_merkle_root(&snapshot_db)?))
+            }
+        }
+    }
+
+    /// The merkle root of a snapshot computed from its key-values, instead
+    /// of being read from its MPT.
+    pub fn compute_snapshot_merkle_root(
+        snapshot_db: &SnapshotDb,
+    ) -> Result<MerkleHash> {
+        let mut kv_iterator = snapshot_db.snapshot_kv_iterator()?.take();
+        let kvs = kv_iterator.iter_range(&[], None)?.take();
+
+        MptMerger::new(None, &mut SimpleMpt::new_discard_write())
+            .merge_insertion_deletion_separated(
+                fallible_iterator::convert(std::iter::empty::<
+                    Result<(Vec<u8>, ())>,
+                >()),
+                kvs,
+                /* in_reconstruct_snapshot_state = */ false,
+            )
+    }
+
     pub fn get_delta_mpt(
         self: &Arc<Self>, snapshot_epoch_id: &EpochId,
     ) -> Result<Arc<DeltaMpt>> {
@@ -1585,6 +1632,7 @@ use crate::{
             node_ref_map::DeltaMptId,
         },
         errors::*,
+        merkle_patricia_trie::{simple_mpt::SimpleMpt, MptMerger},
         state_manager::{DeltaDbManager, SnapshotDb, SnapshotDbManager},
         storage_db::{
             kvdb_sqlite::{
@@ -1598,7 +1646,7 @@ use crate::{
     snapshot_manager::SnapshotManagerTrait,
     storage_db::{
         DeltaDbManagerTrait, KeyValueDbIterableTrait, SnapshotDbManagerTrait,
-        SnapshotInfo, SnapshotKeptToProvideSyncStatus,
+        SnapshotDbTrait, SnapshotInfo, SnapshotKeptToProvideSyncStatus,
     },
     storage_dir,
     utils::guarded_value::GuardedValue,
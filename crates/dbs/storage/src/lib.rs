@@ -236,11 +236,14 @@ pub use self::{
         node_merkle_proof::{NodeMerkleProof, StorageRootProof},
         proof_merger::StateProofMerger,
         recording_storage::RecordingStorage,
-        snapshot_sync::{FullSyncVerifier, MptSlicer},
-        state_proof::StateProof,
+        snapshot_sync::{FullSyncVerifier, MptSlicer, RestoredChunkBoundary},
+        state_proof::{StateProof, StateProofWithRoots},
         storage_db::{
+            kvdb_mdbx::KvdbMdbx,
             kvdb_rocksdb::KvdbRocksdb,
             kvdb_sqlite::{KvdbSqlite, KvdbSqliteStatements},
+            snapshot_db_manager_by_backend::SnapshotDbManagerByBackend,
+            snapshot_db_manager_mdbx::SnapshotDbManagerMdbx,
             snapshot_db_manager_sqlite::SnapshotDbManagerSqlite,
             sqlite::SqliteConnection,
         },
@@ -54,6 +54,9 @@ pub trait SnapshotDbManagerTrait {
     fn try_get_new_snapshot_epoch_from_mpt_temp_path(
         &self, dir_name: &str,
     ) -> Option<EpochId>;
+    /// The temp snapshots of full sync are kept across restarts, so that an
+    /// interrupted state sync can be resumed.
+    fn is_full_sync_temp_snapshot_db_name(&self, dir_name: &str) -> bool;
 
     // Scan snapshot dir, remove extra files and return the list of missing
     // snapshots.
@@ -102,6 +105,13 @@ pub trait SnapshotDbManagerTrait {
                 continue;
             }
             let dir_name = dir_name.unwrap();
+            if self.is_full_sync_temp_snapshot_db_name(dir_name) {
+                info!(
+                    "keep full sync temp snapshot {}",
+                    entry.path().display()
+                );
+                continue;
+            }
             if !all_snapshots.contains_key(dir_name.as_bytes()) {
                 error!(
                     "Unexpected snapshot path {}, deleted.",
@@ -170,6 +180,13 @@ pub trait SnapshotDbManagerTrait {
             }
 
             let dir_name = dir_name.unwrap();
+            if self.is_full_sync_temp_snapshot_db_name(dir_name) {
+                info!(
+                    "keep full sync temp MPT snapshot {}",
+                    entry.path().display()
+                );
+                continue;
+            }
             if !all_snapshots.contains_key(dir_name.as_bytes())
                 && !self.get_latest_mpt_snapshot_db_name().eq(dir_name)
             {
@@ -218,6 +235,13 @@ pub trait SnapshotDbManagerTrait {
         &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
         new_epoch_height: u64,
     ) -> Result<Self::SnapshotDbWrite>;
+    /// Reopen the temp snapshot created by `new_temp_snapshot_for_full_sync`
+    /// before a restart. Return `None` if it doesn't exist.
+    fn open_temp_snapshot_for_full_sync(
+        &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
+        epoch_height: u64,
+    ) -> Result<Option<Self::SnapshotDbWrite>>;
+    fn destroy_temp_snapshots_for_full_sync(&self) -> Result<()>;
     fn finalize_full_sync_snapshot<'m>(
         &self, snapshot_epoch_id: &EpochId, merkle_root: &MerkleHash,
         snapshot_info_map_rwlock: &'m RwLock<PersistedSnapshotInfoMap>,
@@ -2,6 +2,33 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
+/// Run each of the given test functions, which take the `StateDbBackend` to
+/// test, against every state db backend.
+#[cfg(test)]
+macro_rules! test_with_state_db_backends {
+    ($($test:ident),* $(,)?) => {
+        mod rocksdb {
+            $(
+                #[test]
+                fn $test() {
+                    super::$test(crate::StateDbBackend::Rocksdb);
+                }
+            )*
+        }
+
+        mod mdbx {
+            $(
+                #[test]
+                fn $test() {
+                    super::$test(crate::StateDbBackend::Mdbx(
+                        Default::default(),
+                    ));
+                }
+            )*
+        }
+    };
+}
+
 mod snapshot;
 pub use snapshot::FakeSnapshotMptDb;
 
@@ -67,7 +94,10 @@ pub struct FakeStateManager {
 
 #[cfg(any(test, feature = "testonly_code"))]
 impl FakeStateManager {
-    fn new(mazze_data_dir: String, snapshot_epoch_count: u32) -> Result<Self> {
+    fn new(
+        mazze_data_dir: String, snapshot_epoch_count: u32,
+        state_db_backend: StateDbBackend,
+    ) -> Result<Self> {
         // Use a random directory to prevent conflicts in concurrently running
         // tests.
         let unit_test_data_dir = mazze_data_dir + &random::<u64>().to_string();
@@ -81,6 +111,7 @@ impl FakeStateManager {
         storage_conf.delta_mpts_cache_start_size = 1_000_000;
         storage_conf.delta_mpts_node_map_vec_size = 20_000_000;
         storage_conf.delta_mpts_slab_idle_size = 200_000;
+        storage_conf.state_db_backend = state_db_backend;
 
         Ok(FakeStateManager {
             data_dir: unit_test_data_dir,
@@ -120,6 +151,27 @@ impl DerefMut for FakeStateManager {
 #[cfg(any(test, feature = "testonly_code"))]
 pub fn new_state_manager_for_unit_test_with_snapshot_epoch_count(
     snapshot_epoch_count: u32,
+) -> FakeStateManager {
+    new_state_manager_for_unit_test_with_config(
+        snapshot_epoch_count,
+        StateDbBackend::default(),
+    )
+}
+
+#[cfg(any(test, feature = "testonly_code"))]
+pub fn new_state_manager_for_unit_test_with_backend(
+    state_db_backend: StateDbBackend,
+) -> FakeStateManager {
+    let snapshot_epoch_count = 10;
+    new_state_manager_for_unit_test_with_config(
+        snapshot_epoch_count,
+        state_db_backend,
+    )
+}
+
+#[cfg(any(test, feature = "testonly_code"))]
+fn new_state_manager_for_unit_test_with_config(
+    snapshot_epoch_count: u32, state_db_backend: StateDbBackend,
 ) -> FakeStateManager {
     const WITH_LOGGER: bool = false;
     if WITH_LOGGER {
@@ -147,6 +199,7 @@ pub fn new_state_manager_for_unit_test_with_snapshot_epoch_count(
     FakeStateManager::new(
         "./mazze_unit_test_data_dir".to_string(),
         snapshot_epoch_count,
+        state_db_backend,
     )
     .unwrap()
 }
@@ -259,7 +312,9 @@ pub fn print_mpt_key(key: &[u8]) {
 }
 
 #[cfg(any(test, feature = "testonly_code"))]
-use crate::{impls::state_manager::StateManager, StorageConfiguration};
+use crate::{
+    impls::state_manager::StateManager, StateDbBackend, StorageConfiguration,
+};
 use crate::{
     impls::{
         errors::*,
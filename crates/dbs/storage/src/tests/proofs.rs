@@ -30,6 +30,21 @@ fn portion<T>(v: &Vec<T>, k: usize, n: usize) -> &[T] {
 fn generate_random_state(
     rng: &mut ChaChaRng,
 ) -> (FakeStateManager, State, DeltaMptKeyPadding, Vec<Vec<u8>>) {
+    let (state_manager, state, prev_root, keys) =
+        generate_random_state_with_prev_root(rng);
+    let intermediate_padding = StorageKeyWithSpace::delta_mpt_padding(
+        &prev_root.snapshot_root,
+        &prev_root.intermediate_delta_root,
+    );
+    (state_manager, state, intermediate_padding, keys)
+}
+
+// same as `generate_random_state`, but return the state root of the previous
+// snapshot (i.e. the parent epoch, as snapshot_epoch_count = 1) instead of the
+// intermediate padding derived from it
+fn generate_random_state_with_prev_root(
+    rng: &mut ChaChaRng,
+) -> (FakeStateManager, State, StateRoot, Vec<Vec<u8>>) {
     let snapshot_epoch_count = 1;
     let state_manager =
         new_state_manager_for_unit_test_with_snapshot_epoch_count(
@@ -201,11 +216,6 @@ fn generate_random_state(
 
     keys.shuffle(rng);
 
-    let intermediate_padding = StorageKeyWithSpace::delta_mpt_padding(
-        &root_2.state_root.snapshot_root,
-        &root_2.state_root.intermediate_delta_root,
-    );
-
     let new_state = state_manager
         .get_state_for_next_epoch_inner(
             StateIndex::new_for_next_epoch(
@@ -220,7 +230,7 @@ fn generate_random_state(
         .unwrap()
         .unwrap();
 
-    (state_manager, new_state, intermediate_padding, keys)
+    (state_manager, new_state, root_2.state_root, keys)
 }
 
 fn select_keys(
@@ -454,6 +464,68 @@ fn test_invalid_state_proof() {
     }
 }
 
+#[test]
+fn test_state_proof_with_roots() {
+    let mut rng = get_rng_for_test();
+
+    // note: do not drop state_manager (_mgr)
+    let (_mgr, state, prev_root, keys) =
+        generate_random_state_with_prev_root(&mut rng);
+    let root = state.get_state_root().unwrap().state_root;
+    let root_hash = root.compute_state_root_hash();
+    let prev_root_hash = prev_root.compute_state_root_hash();
+    let nonexistent_keys = generate_nonexistent_keys(&mut rng, &keys);
+
+    let check = |key: &Vec<u8>, should_exist: bool| {
+        let (value, state_proof) = state
+            .get_with_proof(StorageKey::AccountKey(key).with_native_space())
+            .expect("kv lookup should succeed");
+        assert_eq!(value.is_some(), should_exist);
+
+        let proof = StateProofWithRoots {
+            state_proof,
+            state_root: root.clone(),
+            prev_snapshot_state_root: Some(prev_root.clone()),
+        };
+
+        // proof should be serializable
+        let proof: StateProofWithRoots =
+            rlp::decode(&rlp::encode(&proof)).unwrap();
+
+        let key = StorageKey::AccountKey(key)
+            .with_native_space()
+            .to_key_bytes();
+        let value = value.as_ref().map(|b| &**b);
+        assert!(proof.verify(&key, value, &root_hash, Some(&prev_root_hash)));
+
+        // checking against other root hashes should fail
+        assert!(!proof.verify(
+            &key,
+            value,
+            &prev_root_hash,
+            Some(&prev_root_hash)
+        ));
+        assert!(!proof.verify(&key, value, &root_hash, Some(&root_hash)));
+        assert!(!proof.verify(&key, value, &root_hash, None));
+
+        // checking an invalid value should fail
+        let invalid_value = Some(&[0x00; 100][..]);
+        assert!(!proof.verify(
+            &key,
+            invalid_value,
+            &root_hash,
+            Some(&prev_root_hash)
+        ));
+    };
+
+    for key in &keys {
+        check(key, true);
+    }
+    for key in &nonexistent_keys {
+        check(key, false);
+    }
+}
+
 #[test]
 fn test_valid_node_merkle_proof_for_existing_key() {
     let mut rng = get_rng_for_test();
@@ -702,7 +774,7 @@ use crate::{
         new_state_manager_for_unit_test_with_snapshot_epoch_count,
         FakeStateManager, TEST_NUMBER_OF_KEYS,
     },
-    RecordingStorage,
+    RecordingStorage, StateProofWithRoots,
 };
 use mazze_types::H256;
 use primitives::{
@@ -165,10 +165,13 @@ impl FallibleIterator for FakeSnapshotMptDbIter<'_> {
 }
 
 #[cfg(test)]
-fn assert_snapshot_mpt_formation(mpt_kv_iter: &DumpedMptKvIterator) {
+fn assert_snapshot_mpt_formation(
+    mpt_kv_iter: &DumpedMptKvIterator, state_db_backend: StateDbBackend,
+) {
     let snapshot_mpt_nodes;
     let delta_mpt_root = {
-        let state_manager = new_state_manager_for_unit_test();
+        let state_manager =
+            new_state_manager_for_unit_test_with_backend(state_db_backend);
         let mut state = state_manager.get_state_for_genesis_write();
         for (key, value) in &mpt_kv_iter.kv {
             state
@@ -219,8 +222,7 @@ fn assert_snapshot_mpt_formation(mpt_kv_iter: &DumpedMptKvIterator) {
 }
 
 #[cfg(test)]
-#[test]
-fn test_mpt_node_path_to_from_db_key() {
+fn test_mpt_node_path_to_from_db_key(state_db_backend: StateDbBackend) {
     // First, construct some special compressed path in a node.
     let mpt_kv = [
         (vec![0x00, 0x10, 0x00, 0x00], vec![0x00]),
@@ -234,7 +236,8 @@ fn test_mpt_node_path_to_from_db_key() {
     // Compressed path 4: [000_]
     // Compressed path 5: [00]
 
-    let state_manager = new_state_manager_for_unit_test();
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
     let mut state = state_manager.get_state_for_genesis_write();
     for (key, value) in &mpt_kv {
         state
@@ -285,10 +288,12 @@ fn test_mpt_node_path_to_from_db_key() {
 }
 
 #[cfg(test)]
-#[test]
-fn test_merkle_root() {
+fn test_merkle_root(state_db_backend: StateDbBackend) {
     // Merkle root of empty db.
-    assert_snapshot_mpt_formation(&DumpedMptKvIterator::default());
+    assert_snapshot_mpt_formation(
+        &DumpedMptKvIterator::default(),
+        state_db_backend.clone(),
+    );
 
     // Merkle root of random set of keys.
     let mut rng = get_rng_for_test();
@@ -301,10 +306,129 @@ fn test_merkle_root() {
         let mpt_kv_iter = DumpedMptKvIterator {
             kv: keys.iter().map(|k| (k[..].into(), k[..].into())).collect(),
         };
-        assert_snapshot_mpt_formation(&mpt_kv_iter);
+        assert_snapshot_mpt_formation(&mpt_kv_iter, state_db_backend.clone());
+    }
+}
+
+#[cfg(test)]
+fn test_snapshot_merge(state_db_backend: StateDbBackend) {
+    let mut rng = get_rng_for_test();
+    let keys: Vec<Vec<u8>> = generate_keys(TEST_NUMBER_OF_KEYS)
+        .iter()
+        .filter(|_| rng.gen_bool(0.1))
+        .cloned()
+        .collect();
+
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
+    let storage_manager = state_manager.get_storage_manager_arc();
+    let snapshot_db_manager = storage_manager
+        .get_snapshot_manager()
+        .get_snapshot_db_manager();
+
+    let mut state = state_manager.get_state_for_genesis_write();
+    for key in &keys {
+        state
+            .set(
+                StorageKey::AccountKey(key).with_native_space(),
+                key[..].into(),
+            )
+            .expect("Failed to insert key.");
+    }
+    let mut epoch_id = EpochId::default();
+    epoch_id.as_bytes_mut()[0] = 1;
+    let delta_root = state.compute_state_root().unwrap().state_root.delta_root;
+    state.commit(epoch_id).unwrap();
+
+    // Merge the delta mpt into a new snapshot the way consensus does.
+    let delta_mpt = storage_manager.get_delta_mpt(&NULL_EPOCH).unwrap();
+    let delta_mpt_iterator = DeltaMptIterator {
+        maybe_root_node: delta_mpt.get_root_node_ref(&delta_root).unwrap(),
+        mpt: delta_mpt,
+    };
+    let height = storage_manager.get_snapshot_epoch_count() as u64;
+    let info = SnapshotInfo {
+        snapshot_info_kept_to_provide_sync: Default::default(),
+        serve_one_step_sync: false,
+        // Computed by the merge.
+        merkle_root: Default::default(),
+        parent_snapshot_height: 0,
+        height,
+        parent_snapshot_epoch_id: NULL_EPOCH,
+        main_chain_parts: vec![epoch_id],
+    };
+    let (mut snapshot_info_map_locked, snapshot_info) = snapshot_db_manager
+        .new_snapshot_by_merging(
+            &NULL_EPOCH,
+            epoch_id,
+            delta_mpt_iterator,
+            info,
+            &storage_manager.snapshot_info_map_by_epoch,
+            height,
+            /* recover_mpt_with_kv_snapshot_exist = */ false,
+        )
+        .unwrap();
+    storage_manager
+        .register_new_snapshot(
+            snapshot_info.clone(),
+            &mut snapshot_info_map_locked,
+        )
+        .unwrap();
+    drop(snapshot_info_map_locked);
+
+    let snapshot_db = snapshot_db_manager
+        .get_snapshot_by_epoch_id(
+            &epoch_id, /* try_open = */ false,
+            /* open_mpt_snapshot = */ true,
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        snapshot_db
+            .open_snapshot_mpt_shared()
+            .unwrap()
+            .get_merkle_root(),
+        snapshot_info.merkle_root
+    );
+    for key in &keys {
+        assert_eq!(
+            snapshot_db
+                .get(
+                    &StorageKey::AccountKey(key)
+                        .with_native_space()
+                        .to_key_bytes()
+                )
+                .unwrap(),
+            Some(key[..].into())
+        );
     }
+
+    // The snapshot mpt must be the mpt of the snapshot key-values.
+    let mut kv_iterator = snapshot_db.snapshot_kv_iterator().unwrap().take();
+    let snapshot_kv = DumpedMptKvIterator {
+        kv: kv_iterator
+            .iter_range(&[], None)
+            .unwrap()
+            .take()
+            .collect()
+            .unwrap(),
+    };
+    assert_eq!(snapshot_kv.kv.len(), keys.len());
+    let mut snapshot_mpt = FakeSnapshotMptDb::default();
+    snapshot_mpt.reset(/* in_place_mode */ true);
+    let merkle_root = MptMerger::new(None, &mut snapshot_mpt)
+        .merge(&snapshot_kv)
+        .unwrap();
+    assert_eq!(merkle_root, snapshot_info.merkle_root);
 }
 
+#[cfg(test)]
+test_with_state_db_backends!(
+    test_mpt_node_path_to_from_db_key,
+    test_merkle_root,
+    test_snapshot_merge,
+);
+
 #[cfg(test)]
 #[test]
 fn test_delete_all() {
@@ -649,17 +773,22 @@ use crate::{
     impls::merkle_patricia_trie::{MptMerger, TrieNodeTrait},
     impls::storage_db::snapshot_mpt::tests::verify_snapshot_db,
     state_manager::StateManagerTrait,
+    storage_db::{
+        KeyValueDbIterableTrait, KeyValueDbTraitRead, OpenSnapshotMptTrait,
+        SnapshotDbManagerTrait, SnapshotDbTrait, SnapshotInfo,
+    },
     tests::{
-        generate_keys, get_rng_for_test, new_state_manager_for_unit_test,
+        generate_keys, get_rng_for_test,
+        new_state_manager_for_unit_test_with_backend,
         snapshot::verifier::FakeSnapshotDb, DumpedMptKvIterator,
         TEST_NUMBER_OF_KEYS,
     },
-    StateIndex, StorageStateTraitExt,
+    DeltaMptIterator, StateDbBackend, StateIndex, StorageStateTraitExt,
 };
 #[cfg(test)]
 use parking_lot::Mutex;
 #[cfg(test)]
-use primitives::{EpochId, StorageKey, MERKLE_NULL_NODE};
+use primitives::{EpochId, StorageKey, MERKLE_NULL_NODE, NULL_EPOCH};
 #[cfg(test)]
 use rand::Rng;
 #[cfg(test)]
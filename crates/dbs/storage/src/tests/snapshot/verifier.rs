@@ -562,6 +562,10 @@ impl SnapshotDbManagerTrait for FakeSnapshotDbManager {
         unreachable!()
     }
 
+    fn is_full_sync_temp_snapshot_db_name(&self, _dir_name: &str) -> bool {
+        unreachable!()
+    }
+
     fn scan_persist_state(
         &self, _snapshot_info_map: &HashMap<EpochId, SnapshotInfo>,
     ) -> Result<SnapshotPersistState> {
@@ -595,6 +599,17 @@ impl SnapshotDbManagerTrait for FakeSnapshotDbManager {
         Ok(self.temp_snapshot.clone())
     }
 
+    fn open_temp_snapshot_for_full_sync(
+        &self, _snapshot_epoch_id: &EpochId, _merkle_root: &MerkleHash,
+        _epoch_height: u64,
+    ) -> Result<Option<Self::SnapshotDbWrite>> {
+        Ok(Some(self.temp_snapshot.clone()))
+    }
+
+    fn destroy_temp_snapshots_for_full_sync(&self) -> Result<()> {
+        unreachable!()
+    }
+
     fn finalize_full_sync_snapshot<'m>(
         &self, _snapshot_epoch_id: &MerkleHash, _merkle_root: &MerkleHash,
         _snapshot_info_map_rwlock: &'m RwLock<PersistedSnapshotInfoMap>,
@@ -776,6 +791,151 @@ fn test_full_sync_verifier() {
     temp_snapshot.mpt_db.lock().assert_eq(&snapshot_mpt);
 }
 
+#[test]
+fn test_full_sync_verifier_resume() {
+    let mut rng = get_rng_for_test();
+    let mut keys: Vec<Vec<u8>> = generate_keys(TEST_NUMBER_OF_KEYS);
+    keys.sort();
+    let mpt_kv_iter = DumpedMptKvIterator {
+        kv: keys
+            .iter()
+            .map(|k| {
+                (
+                    k[..].into(),
+                    [&k[..], &k[..], &k[..], &k[..]].concat()
+                        [0..(6 + rng.gen::<usize>() % 10)]
+                        .into(),
+                )
+            })
+            .collect(),
+    };
+
+    let mut snapshot_mpt = FakeSnapshotMptDb::default();
+    let merkle_root = MptMerger::new(None, &mut snapshot_mpt)
+        .merge(&mpt_kv_iter)
+        .unwrap();
+
+    let mut total_rlp_size = 0;
+    for (key, value) in &mpt_kv_iter.kv {
+        total_rlp_size += rlp_key_value_len(key.len() as u16, value.len());
+    }
+    let chunk_size = total_rlp_size / 5 as u64;
+
+    let mut slicer = MptSlicer::new(&mut snapshot_mpt).unwrap();
+    let mut chunk_bounds = vec![];
+    let mut chunk_proofs = vec![];
+    loop {
+        slicer.advance(chunk_size).unwrap();
+        match slicer.get_range_end_key() {
+            Some(key) => {
+                chunk_bounds.push(Vec::from(key));
+                chunk_proofs.push(slicer.to_proof());
+            }
+            None => {
+                break;
+            }
+        }
+    }
+    drop(slicer);
+    let number_chunks = chunk_bounds.len() + 1;
+
+    // The keys of each chunk are the keys between its boundaries.
+    let chunk_kvs = |chunk_index: usize| {
+        mpt_kv_iter
+            .kv
+            .iter()
+            .filter(|(key, _)| {
+                (chunk_index == 0
+                    || key[..] >= chunk_bounds[chunk_index - 1][..])
+                    && (chunk_index == number_chunks - 1
+                        || key[..] < chunk_bounds[chunk_index][..])
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let chunk_upper_key = |chunk_index: usize| {
+        if chunk_index < number_chunks - 1 {
+            Some(chunk_bounds[chunk_index].clone())
+        } else {
+            None
+        }
+    };
+
+    let snapshot_db_manager = FakeSnapshotDbManager::default();
+
+    // Restore the first half of the chunks, and keep their boundary nodes as
+    // they would be persisted.
+    let mut full_sync_verifier = FullSyncVerifier::new(
+        number_chunks,
+        chunk_bounds.clone(),
+        chunk_proofs.clone(),
+        merkle_root,
+        &snapshot_db_manager,
+        &NULL_EPOCH,
+        0,
+    )
+    .unwrap();
+    let mut restored_chunk_boundaries = vec![];
+    for i in 0..number_chunks / 2 {
+        let kvs = chunk_kvs(i);
+        let restored_chunk_boundary = full_sync_verifier
+            .restore_chunk_with_boundary(
+                &chunk_upper_key(i),
+                &kvs.iter().map(|kv| kv.0.clone()).collect(),
+                kvs.iter().map(|kv| kv.1.to_vec()).collect(),
+            )
+            .unwrap()
+            .unwrap();
+        restored_chunk_boundaries.push(rlp::encode(&restored_chunk_boundary));
+    }
+    drop(full_sync_verifier);
+
+    // Resume the restoration and restore the other chunks.
+    let mut full_sync_verifier = FullSyncVerifier::open(
+        number_chunks,
+        chunk_bounds.clone(),
+        chunk_proofs,
+        merkle_root,
+        &snapshot_db_manager,
+        &NULL_EPOCH,
+        0,
+    )
+    .unwrap()
+    .unwrap();
+    for encoded in &restored_chunk_boundaries {
+        let restored_chunk_boundary: RestoredChunkBoundary =
+            rlp::decode(encoded).unwrap();
+        full_sync_verifier
+            .resume_chunk(&restored_chunk_boundary)
+            .unwrap();
+    }
+    // A chunk can't be resumed twice.
+    assert!(full_sync_verifier
+        .resume_chunk(&rlp::decode(&restored_chunk_boundaries[0]).unwrap())
+        .is_err());
+    for i in number_chunks / 2..number_chunks {
+        let kvs = chunk_kvs(i);
+        assert!(full_sync_verifier
+            .restore_chunk(
+                &chunk_upper_key(i),
+                &kvs.iter().map(|kv| kv.0.clone()).collect(),
+                kvs.iter().map(|kv| kv.1.to_vec()).collect(),
+            )
+            .unwrap());
+    }
+    assert!(full_sync_verifier.is_completed());
+
+    // Check key-values.
+    let temp_snapshot = &*snapshot_db_manager.temp_snapshot.lock();
+    assert_eq!(temp_snapshot.kv.len(), mpt_kv_iter.kv.len());
+    for (key, value) in &mpt_kv_iter.kv {
+        assert_eq!(temp_snapshot.kv.get(key), Some(value));
+    }
+
+    // Check MPT key-values and subtree size.
+    temp_snapshot.mpt_db.lock().assert_eq(&snapshot_mpt);
+}
+
 use crate::{
     impls::{
         errors::*,
@@ -783,7 +943,7 @@ use crate::{
             mpt_cursor::rlp_key_value_len, CompressedPathTrait, MptMerger,
         },
         snapshot_sync::restoration::{
-            full_sync_verifier::FullSyncVerifier,
+            full_sync_verifier::{FullSyncVerifier, RestoredChunkBoundary},
             mpt_slice_verifier::MptSliceVerifier,
         },
         storage_db::{
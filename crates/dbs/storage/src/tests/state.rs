@@ -2,9 +2,9 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-#[test]
-fn test_empty_genesis_block() {
-    let state_manager = new_state_manager_for_unit_test();
+fn test_empty_genesis_block(state_db_backend: StateDbBackend) {
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
 
     let mut genesis_epoch_id = H256::default();
     genesis_epoch_id.as_bytes_mut()[0] = 1;
@@ -24,10 +24,10 @@ fn test_empty_genesis_block() {
         .unwrap();
 }
 
-#[test]
-fn test_set_get() {
+fn test_set_get(state_db_backend: StateDbBackend) {
     let mut rng = get_rng_for_test();
-    let state_manager = new_state_manager_for_unit_test();
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
     let mut state = state_manager.get_state_for_genesis_write();
     let mut keys: Vec<Vec<u8>> = generate_keys(TEST_NUMBER_OF_KEYS)
         .iter()
@@ -63,9 +63,9 @@ fn test_set_get() {
     state.commit(epoch_id).unwrap();
 }
 
-#[test]
-fn test_get_set_at_second_commit() {
-    let state_manager = new_state_manager_for_unit_test();
+fn test_get_set_at_second_commit(state_db_backend: StateDbBackend) {
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
     let keys: Vec<Vec<u8>> = generate_keys(TEST_NUMBER_OF_KEYS);
     let set_size = TEST_NUMBER_OF_KEYS / 10;
     let (keys_0, keys_1_new, keys_remain, keys_1_overwritten) = (
@@ -163,9 +163,9 @@ fn test_get_set_at_second_commit() {
     state_1.commit(epoch_id_1).unwrap();
 }
 
-#[test]
-fn test_snapshot_random_read_performance() {
-    let state_manager = new_state_manager_for_unit_test();
+fn test_snapshot_random_read_performance(state_db_backend: StateDbBackend) {
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
     let keys: Vec<Vec<u8>> = generate_keys(TEST_NUMBER_OF_KEYS);
 
     const EPOCHS: u8 = 20;
@@ -412,10 +412,10 @@ fn simulate_transactions(
     state_root
 }
 
-#[test]
-fn test_set_delete() {
+fn test_set_delete(state_db_backend: StateDbBackend) {
     let mut rng = get_rng_for_test();
-    let state_manager = new_state_manager_for_unit_test();
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
 
     let mut state = state_manager.get_state_for_genesis_write();
 
@@ -476,10 +476,10 @@ fn test_set_delete() {
     state.commit(epoch_id).unwrap();
 }
 
-#[test]
-fn test_set_delete_all() {
+fn test_set_delete_all(state_db_backend: StateDbBackend) {
     let mut rng = get_rng_for_test();
-    let state_manager = new_state_manager_for_unit_test();
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
 
     let mut state = state_manager.get_state_for_genesis_write();
     let empty_state_root = state.compute_state_root().unwrap();
@@ -567,10 +567,10 @@ fn test_set_delete_all() {
     assert_eq!(state_root, empty_state_root);
 }
 
-#[test]
-fn test_set_order() {
+fn test_set_order(state_db_backend: StateDbBackend) {
     let mut rng = get_rng_for_test();
-    let state_manager = new_state_manager_for_unit_test();
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
     let keys: Vec<Vec<u8>> = generate_keys(500000)
         .iter()
         .filter(|_| rng.gen_bool(0.5))
@@ -632,10 +632,10 @@ fn test_set_order() {
     assert_eq!(merkle_1, merkle_2);
 }
 
-#[test]
-fn test_set_order_concurrent() {
+fn test_set_order_concurrent(state_db_backend: StateDbBackend) {
     let mut rng = get_rng_for_test();
-    let state_manager = new_state_manager_for_unit_test();
+    let state_manager =
+        new_state_manager_for_unit_test_with_backend(state_db_backend);
     let keys = Arc::new(
         generate_keys(TEST_NUMBER_OF_KEYS / 10)
             .iter()
@@ -744,14 +744,26 @@ fn test_set_order_concurrent() {
     }
 }
 
+test_with_state_db_backends!(
+    test_empty_genesis_block,
+    test_set_get,
+    test_get_set_at_second_commit,
+    test_snapshot_random_read_performance,
+    test_set_delete,
+    test_set_delete_all,
+    test_set_order,
+    test_set_order_concurrent,
+);
+
 use crate::{
     state::*,
     state_manager::*,
     tests::{
-        generate_keys, get_rng_for_test, new_state_manager_for_unit_test,
-        FakeStateManager, TEST_NUMBER_OF_KEYS,
+        generate_keys, get_rng_for_test,
+        new_state_manager_for_unit_test_with_backend, FakeStateManager,
+        TEST_NUMBER_OF_KEYS,
     },
-    StateRootWithAuxInfo,
+    StateDbBackend, StateRootWithAuxInfo,
 };
 use mazze_types::{
     address_util::AddressUtil, Address, AddressSpaceUtil, H256, U256,
@@ -2,7 +2,7 @@ use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use mazze_execute_helper::exec_tracer::BlockExecTraces;
 use mazze_internal_common::{DatabaseDecodable, DatabaseEncodable};
-use mazze_types::{Bloom, H256, U256};
+use mazze_types::{AddressWithSpace, Bloom, H256, U256};
 use primitives::BlockReceipts;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use rlp_derive::{RlpDecodable, RlpEncodable};
@@ -339,6 +339,36 @@ impl MallocSizeOf for BlamedHeaderVerifiedRoots {
     }
 }
 
+/// A transaction which touches an address, stored in the address index.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct AddressTransaction {
+    pub transaction_hash: H256,
+    pub block_hash: H256,
+    /// The main block of the epoch where the transaction is executed. It is
+    /// used to skip the entries of the epochs reverted by a main chain
+    /// switch.
+    pub epoch_hash: H256,
+    pub epoch_number: u64,
+}
+
+/// The sequence numbers of the entries of an address in the address index.
+/// The entries in `[first, next)` might exist, and some of them might have
+/// been garbage collected.
+#[derive(Clone, Copy, Debug, Default, RlpEncodable, RlpDecodable)]
+pub struct AddressIndexRange {
+    pub first: u64,
+    pub next: u64,
+}
+
+/// An entry written to the address index when an epoch is executed, kept to
+/// garbage collect the entries of the epoch.
+#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct AddressIndexPosition {
+    pub epoch_hash: H256,
+    pub address: AddressWithSpace,
+    pub seq: u64,
+}
+
 pub fn db_encode_list<T>(list: &[T]) -> Vec<u8>
 where
     T: DatabaseEncodable,
@@ -369,3 +399,6 @@ impl_db_encoding_as_rlp!(CheckpointHashes);
 impl_db_encoding_as_rlp!(EpochExecutionContext);
 impl_db_encoding_as_rlp!(BlockRewardResult);
 impl_db_encoding_as_rlp!(BlamedHeaderVerifiedRoots);
+impl_db_encoding_as_rlp!(AddressTransaction);
+impl_db_encoding_as_rlp!(AddressIndexRange);
+impl_db_encoding_as_rlp!(AddressIndexPosition);
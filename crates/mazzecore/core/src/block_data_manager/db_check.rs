@@ -0,0 +1,712 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Offline integrity check of the blocks db and the state db.
+//!
+//! The blocks db is walked by epoch number from the current checkpoint, so
+//! that only the data kept after garbage collection is checked. The derivable
+//! indices, i.e. the transaction index and the block number index, can be
+//! rebuilt in place from the blocks and their execution results.
+//!
+//! The checker reads the blocks db directly instead of going through a
+//! `BlockDataManager`, whose initialization writes the genesis block, so it
+//! is only written when the indices are repaired. The headers are read as
+//! stored, without computing their PoW hashes, which are not checked.
+//!
+//! The state db is opened by `StorageManager::new`, which cleans it up as on
+//! startup, e.g. the unfinished snapshots and the delta MPTs of missing
+//! snapshots are removed.
+
+use crate::{
+    block_data_manager::{db_manager::DBManager, DataManagerConfiguration},
+    verification::compute_transaction_root,
+};
+use mazze_execute_helper::phantom_tx::build_bloom_and_recover_phantom;
+use mazze_internal_common::{ChainIdParams, EpochExecutionCommitment};
+use mazze_storage::{
+    state_manager::StateIndex, storage_db::SnapshotKeptToProvideSyncStatus,
+    StorageManager,
+};
+use mazze_types::{Space, H256};
+use primitives::{
+    compute_block_number, receipt::TransactionStatus, BlockHeader, Receipt,
+    SignedTransaction, TransactionIndex,
+};
+use std::{fmt, sync::Arc};
+
+#[derive(Default)]
+pub struct DbCheckReport {
+    pub checked_epochs: u64,
+    pub checked_blocks: u64,
+    pub checked_transactions: u64,
+    pub checked_snapshots: u64,
+    pub checked_states: u64,
+    /// The inconsistent index entries which are rebuilt.
+    pub repaired: Vec<String>,
+    pub inconsistencies: Vec<String>,
+}
+
+impl DbCheckReport {
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+}
+
+impl fmt::Display for DbCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "checked {} epochs, {} blocks, {} transactions, {} snapshots, {} states",
+            self.checked_epochs,
+            self.checked_blocks,
+            self.checked_transactions,
+            self.checked_snapshots,
+            self.checked_states
+        )?;
+        if !self.repaired.is_empty() {
+            writeln!(f, "{} index entries repaired:", self.repaired.len())?;
+            for repaired in &self.repaired {
+                writeln!(f, "  {}", repaired)?;
+            }
+        }
+        if self.is_consistent() {
+            write!(f, "no inconsistency found")
+        } else {
+            writeln!(f, "{} inconsistencies:", self.inconsistencies.len())?;
+            for inconsistency in &self.inconsistencies {
+                writeln!(f, "  {}", inconsistency)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub struct DbChecker<'a> {
+    db_manager: &'a DBManager,
+    storage_manager: &'a Arc<StorageManager>,
+    persist_tx_index: bool,
+    persist_block_number_index: bool,
+    chain_id: ChainIdParams,
+    /// Rebuild the index entries which are missing or inconsistent.
+    repair: bool,
+    report: DbCheckReport,
+}
+
+impl<'a> DbChecker<'a> {
+    pub fn new(
+        db_manager: &'a DBManager, storage_manager: &'a Arc<StorageManager>,
+        config: &DataManagerConfiguration, chain_id: ChainIdParams,
+        repair: bool,
+    ) -> Self {
+        Self {
+            db_manager,
+            storage_manager,
+            persist_tx_index: config.persist_tx_index,
+            persist_block_number_index: config.persist_block_number_index,
+            chain_id,
+            repair,
+            report: Default::default(),
+        }
+    }
+
+    pub fn check(mut self) -> DbCheckReport {
+        let db_manager = self.db_manager;
+        // Without a checkpoint, the era starts from the true genesis.
+        let start_epoch = match db_manager.checkpoint_hashes_from_db() {
+            None => 0,
+            Some((cur_era_genesis_hash, _)) => {
+                match db_manager.block_header_without_pow_hash_from_db(
+                    &cur_era_genesis_hash,
+                ) {
+                    Some(header) => header.height(),
+                    None => {
+                        self.inconsistent(format!(
+                            "header of checkpoint {:?} is missing",
+                            cur_era_genesis_hash
+                        ));
+                        0
+                    }
+                }
+            }
+        };
+
+        let mut epoch = start_epoch;
+        // The block number of the first block of the next epoch, if known.
+        let mut next_block_number = None;
+        let mut last_committed_epoch = None;
+        loop {
+            let epoch_hashes =
+                match db_manager.executed_epoch_set_hashes_from_db(epoch) {
+                    Some(epoch_hashes) => epoch_hashes,
+                    // The epoch set of the checkpoint itself may not be
+                    // persisted.
+                    None if epoch == start_epoch => {
+                        epoch += 1;
+                        continue;
+                    }
+                    None => break,
+                };
+            next_block_number =
+                self.check_epoch(epoch, &epoch_hashes, next_block_number);
+            if let Some(main_hash) = epoch_hashes.last() {
+                if let Some(commitment) =
+                    db_manager.epoch_execution_commitment_from_db(main_hash)
+                {
+                    last_committed_epoch = Some((*main_hash, commitment));
+                }
+            }
+            epoch += 1;
+        }
+
+        self.check_snapshots();
+        if let Some((epoch_hash, commitment)) = last_committed_epoch {
+            self.check_state(&epoch_hash, &commitment);
+        }
+
+        self.report
+    }
+
+    fn inconsistent(&mut self, inconsistency: String) {
+        self.report.inconsistencies.push(inconsistency);
+    }
+
+    /// Check the blocks of an epoch and return the block number of the first
+    /// block of the next epoch.
+    fn check_epoch(
+        &mut self, epoch: u64, epoch_hashes: &Vec<H256>,
+        expected_start_block_number: Option<u64>,
+    ) -> Option<u64> {
+        self.report.checked_epochs += 1;
+        let main_hash = match epoch_hashes.last() {
+            Some(main_hash) => *main_hash,
+            None => {
+                self.inconsistent(format!("epoch {} is empty", epoch));
+                return None;
+            }
+        };
+
+        let mut blocks = Vec::with_capacity(epoch_hashes.len());
+        for hash in epoch_hashes {
+            blocks.push(self.check_block(hash));
+        }
+        if let Some((header, _)) = blocks.last().unwrap() {
+            if header.height() != epoch {
+                self.inconsistent(format!(
+                    "main block {:?} of epoch {} has height {}",
+                    main_hash,
+                    epoch,
+                    header.height()
+                ));
+            }
+        }
+
+        let start_block_number =
+            match self.db_manager.execution_context_from_db(&main_hash) {
+                Some(context) => {
+                    if let Some(expected) = expected_start_block_number {
+                        if context.start_block_number != expected {
+                            self.inconsistent(format!(
+                            "epoch {} starts at block number {}, expected {}",
+                            epoch, context.start_block_number, expected
+                        ));
+                        }
+                    }
+                    Some(context.start_block_number)
+                }
+                None => expected_start_block_number,
+            };
+        if let Some(start_block_number) = start_block_number {
+            self.check_block_number_index(start_block_number, epoch_hashes);
+        }
+
+        if blocks.iter().all(Option::is_some) {
+            let blocks = blocks.into_iter().map(Option::unwrap).collect();
+            self.check_transaction_index(
+                epoch,
+                &main_hash,
+                epoch_hashes,
+                blocks,
+            );
+        }
+
+        start_block_number.map(|n| n + epoch_hashes.len() as u64)
+    }
+
+    /// Check that the header and the body of a block exist and match.
+    fn check_block(
+        &mut self, hash: &H256,
+    ) -> Option<(BlockHeader, Vec<Arc<SignedTransaction>>)> {
+        self.report.checked_blocks += 1;
+        let db_manager = self.db_manager;
+        let header = match db_manager
+            .block_header_without_pow_hash_from_db(hash)
+        {
+            Some(header) => header,
+            None => {
+                self.inconsistent(format!("header of {:?} is missing", hash));
+                return None;
+            }
+        };
+        if header.hash() != *hash {
+            self.inconsistent(format!(
+                "header of {:?} has hash {:?}",
+                hash,
+                header.hash()
+            ));
+        }
+        let transactions = match db_manager.block_body_from_db(hash) {
+            Some(transactions) => transactions,
+            None => {
+                self.inconsistent(format!("body of {:?} is missing", hash));
+                return None;
+            }
+        };
+        let transactions_root = compute_transaction_root(&transactions);
+        if transactions_root != *header.transactions_root() {
+            self.inconsistent(format!(
+                "body of {:?} has transactions root {:?}, expected {:?}",
+                hash,
+                transactions_root,
+                header.transactions_root()
+            ));
+        }
+        Some((header, transactions))
+    }
+
+    fn check_block_number_index(
+        &mut self, start_block_number: u64, epoch_hashes: &Vec<H256>,
+    ) {
+        if !self.persist_block_number_index {
+            return;
+        }
+        let db_manager = self.db_manager;
+        for (index, hash) in epoch_hashes.iter().enumerate() {
+            let block_number =
+                compute_block_number(start_block_number, index as u64);
+            let indexed_hash =
+                db_manager.hash_by_block_number_from_db(&block_number);
+            if indexed_hash == Some(*hash) {
+                continue;
+            }
+            let inconsistency = format!(
+                "block number {} is indexed to {:?}, expected {:?}",
+                block_number, indexed_hash, hash
+            );
+            if self.repair {
+                db_manager
+                    .insert_hash_by_block_number_to_db(block_number, hash);
+                self.report.repaired.push(inconsistency);
+            } else {
+                self.inconsistent(inconsistency);
+            }
+        }
+    }
+
+    /// Check the transaction index against the execution results of the
+    /// epoch, in the same way as it's built after execution.
+    fn check_transaction_index(
+        &mut self, epoch: u64, main_hash: &H256, epoch_hashes: &Vec<H256>,
+        blocks: Vec<(BlockHeader, Vec<Arc<SignedTransaction>>)>,
+    ) {
+        if !self.persist_tx_index {
+            return;
+        }
+        let db_manager = self.db_manager;
+        let mut epoch_receipts = Vec::with_capacity(epoch_hashes.len());
+        for hash in epoch_hashes {
+            match db_manager.block_execution_result_from_db(hash) {
+                // The epoch isn't executed yet, or it's executed as a part
+                // of another main chain and will be executed again.
+                Some(result) if result.0 == *main_hash => {
+                    epoch_receipts.push(result.1.block_receipts)
+                }
+                _ => return,
+            }
+        }
+
+        let evm_chain_id =
+            self.chain_id.read().get_chain_id(epoch).in_evm_space();
+        let mut evm_tx_index = 0;
+        for (block_idx, (block_hash, (_, transactions))) in
+            epoch_hashes.iter().zip(blocks.iter()).enumerate()
+        {
+            let receipts = &epoch_receipts[block_idx].receipts;
+            if receipts.len() != transactions.len() {
+                self.inconsistent(format!(
+                    "block {:?} has {} transactions but {} receipts",
+                    block_hash,
+                    transactions.len(),
+                    receipts.len()
+                ));
+                return;
+            }
+
+            let mut mazze_tx_index = 0;
+            for (tx_idx, tx) in transactions.iter().enumerate() {
+                self.report.checked_transactions += 1;
+                let Receipt {
+                    outcome_status,
+                    logs,
+                    ..
+                } = &receipts[tx_idx];
+
+                let rpc_index = match tx.space() {
+                    Space::Native => {
+                        let rpc_index = mazze_tx_index;
+                        mazze_tx_index += 1;
+                        rpc_index
+                    }
+                    Space::Ethereum
+                        if *outcome_status != TransactionStatus::Skipped =>
+                    {
+                        let rpc_index = evm_tx_index;
+                        evm_tx_index += 1;
+                        rpc_index
+                    }
+                    _ => usize::MAX, // this will not be used
+                };
+
+                match outcome_status {
+                    TransactionStatus::Success | TransactionStatus::Failure => {
+                        self.check_one_transaction_index(
+                            &tx.hash,
+                            TransactionIndex {
+                                block_hash: *block_hash,
+                                real_index: tx_idx,
+                                is_phantom: false,
+                                rpc_index: Some(rpc_index),
+                            },
+                        );
+
+                        let (phantom_txs, _) =
+                            build_bloom_and_recover_phantom(logs, tx.hash());
+                        for ptx in phantom_txs {
+                            self.check_one_transaction_index(
+                                &ptx.into_eip155(evm_chain_id).hash(),
+                                TransactionIndex {
+                                    block_hash: *block_hash,
+                                    real_index: tx_idx,
+                                    is_phantom: true,
+                                    rpc_index: Some(evm_tx_index),
+                                },
+                            );
+                            evm_tx_index += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn check_one_transaction_index(
+        &mut self, tx_hash: &H256, expected: TransactionIndex,
+    ) {
+        let db_manager = self.db_manager;
+        let tx_index = db_manager.transaction_index_from_db(tx_hash);
+        if tx_index.as_ref() == Some(&expected) {
+            return;
+        }
+        let inconsistency = format!(
+            "transaction {:?} is indexed to {:?}, expected {:?}",
+            tx_hash, tx_index, expected
+        );
+        if self.repair {
+            db_manager.insert_transaction_index_to_db(tx_hash, &expected);
+            self.report.repaired.push(inconsistency);
+        } else {
+            self.inconsistent(inconsistency);
+        }
+    }
+
+    /// Recompute the merkle roots of the snapshots from their key-values.
+    fn check_snapshots(&mut self) {
+        let storage_manager = self.storage_manager.get_storage_manager();
+        for snapshot_info in storage_manager.get_snapshot_infos() {
+            if snapshot_info.snapshot_info_kept_to_provide_sync
+                == SnapshotKeptToProvideSyncStatus::InfoOnly
+            {
+                continue;
+            }
+            self.report.checked_snapshots += 1;
+            let snapshot_epoch_id = *snapshot_info.get_snapshot_epoch_id();
+            match storage_manager
+                .recompute_snapshot_merkle_root(&snapshot_epoch_id)
+            {
+                Ok(Some(merkle_root))
+                    if merkle_root == snapshot_info.merkle_root => {}
+                Ok(Some(merkle_root)) => self.inconsistent(format!(
+                    "snapshot {:?} has merkle root {:?}, expected {:?}",
+                    snapshot_epoch_id, merkle_root, snapshot_info.merkle_root
+                )),
+                Ok(None) => self.inconsistent(format!(
+                    "snapshot {:?} is missing",
+                    snapshot_epoch_id
+                )),
+                Err(e) => self.inconsistent(format!(
+                    "failed to read snapshot {:?}: {}",
+                    snapshot_epoch_id, e
+                )),
+            }
+        }
+    }
+
+    /// Recompute the state root of the latest executed epoch from the
+    /// snapshot and the delta MPTs.
+    fn check_state(
+        &mut self, epoch_hash: &H256, commitment: &EpochExecutionCommitment,
+    ) {
+        self.report.checked_states += 1;
+        let state_root_with_aux_info = &commitment.state_root_with_aux_info;
+        match self.storage_manager.recompute_state_root(
+            StateIndex::new_for_readonly(epoch_hash, state_root_with_aux_info),
+        ) {
+            Ok(Some(state_root))
+                if state_root == state_root_with_aux_info.state_root => {}
+            Ok(Some(state_root)) => self.inconsistent(format!(
+                "state of epoch {:?} has root {:?}, expected {:?}",
+                epoch_hash, state_root, state_root_with_aux_info.state_root
+            )),
+            Ok(None) => self.inconsistent(format!(
+                "state of epoch {:?} is missing",
+                epoch_hash
+            )),
+            Err(e) => self.inconsistent(format!(
+                "failed to read state of epoch {:?}: {}",
+                epoch_hash, e
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_data_manager::{
+            test_utils::{test_dbs, TestDbs},
+            BlockDbBackend, BlockExecutionResult,
+            BlockExecutionResultWithEpoch, EpochExecutionContext,
+        },
+        pow::PowComputer,
+    };
+    use mazze_internal_common::ChainIdParamsInner;
+    use mazze_storage::{StorageManagerTrait, StorageStateTrait};
+    use mazze_types::{AddressSpaceUtil, AllChainID, Bloom, H160};
+    use primitives::{
+        transaction::native_transaction::NativeTransaction, Block,
+        BlockHeaderBuilder, BlockReceipts, StorageKey,
+    };
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    struct TestDb {
+        db_manager: DBManager,
+        storage_manager: Arc<StorageManager>,
+        block_hash: H256,
+        tx_hash: H256,
+        // Dropped after the dbs are closed.
+        _dir: TempDir,
+    }
+
+    /// A db with one executed epoch of one block with one transaction, its
+    /// indices and its state.
+    fn test_db() -> TestDb {
+        let TestDbs {
+            ledger_db,
+            storage_manager,
+            dir,
+        } = test_dbs("db_check");
+        let db_manager = DBManager::new_from_backend(
+            BlockDbBackend::Rocksdb,
+            ledger_db,
+            Arc::new(PowComputer::new(H256::zero())),
+            H256::zero(),
+        );
+
+        let tx = Arc::new(
+            NativeTransaction::default()
+                .fake_sign(H160::repeat_byte(1).with_native_space()),
+        );
+        let mut header = BlockHeaderBuilder::new()
+            .with_height(0)
+            .with_transactions_root(compute_transaction_root(&vec![tx.clone()]))
+            .build();
+        header.compute_hash();
+        header.pow_hash = Some(H256::zero());
+        let block = Block::new(header, vec![tx.clone()]);
+        let block_hash = block.hash();
+
+        db_manager.insert_block_header_to_db(&block.block_header);
+        db_manager.insert_block_body_to_db(&block);
+        db_manager.insert_executed_epoch_set_hashes_to_db(0, &vec![block_hash]);
+        db_manager.insert_execution_context_to_db(
+            &block_hash,
+            &EpochExecutionContext {
+                start_block_number: 0,
+            },
+        );
+        db_manager.insert_block_execution_result_to_db(
+            &block_hash,
+            &BlockExecutionResultWithEpoch::new(
+                block_hash,
+                BlockExecutionResult {
+                    block_receipts: Arc::new(BlockReceipts {
+                        receipts: vec![Receipt {
+                            outcome_status: TransactionStatus::Success,
+                            ..Default::default()
+                        }],
+                        block_number: 0,
+                        secondary_reward: Default::default(),
+                        tx_execution_error_messages: vec!["".into()],
+                    }),
+                    bloom: Bloom::zero(),
+                },
+            ),
+        );
+        db_manager.insert_hash_by_block_number_to_db(0, &block_hash);
+        db_manager
+            .insert_transaction_index_to_db(&tx.hash, &tx_index(block_hash));
+
+        let mut state = storage_manager.get_state_for_genesis_write();
+        state
+            .set(
+                StorageKey::new_account_key(&H160::repeat_byte(1))
+                    .with_native_space(),
+                vec![1].into(),
+            )
+            .unwrap();
+        state.compute_state_root().unwrap();
+        let state_root_with_aux_info = state.commit(block_hash).unwrap();
+        db_manager.insert_epoch_execution_commitment_to_db(
+            &block_hash,
+            &EpochExecutionCommitment {
+                state_root_with_aux_info,
+                receipts_root: Default::default(),
+                logs_bloom_hash: Default::default(),
+            },
+        );
+
+        TestDb {
+            db_manager,
+            storage_manager,
+            block_hash,
+            tx_hash: tx.hash,
+            _dir: dir,
+        }
+    }
+
+    fn tx_index(block_hash: H256) -> TransactionIndex {
+        TransactionIndex {
+            block_hash,
+            real_index: 0,
+            is_phantom: false,
+            rpc_index: Some(0),
+        }
+    }
+
+    fn check(test_db: &TestDb, repair: bool) -> DbCheckReport {
+        let config = DataManagerConfiguration::new(
+            true, /* persist_tx_index */
+            true, /* persist_block_number_index */
+            Duration::from_millis(300_000),
+            BlockDbBackend::Rocksdb,
+        );
+        DbChecker::new(
+            &test_db.db_manager,
+            &test_db.storage_manager,
+            &config,
+            ChainIdParamsInner::new_simple(AllChainID::new(1, 1)),
+            repair,
+        )
+        .check()
+    }
+
+    #[test]
+    fn test_consistent_db() {
+        let test_db = test_db();
+        let report = check(&test_db, false);
+        assert_eq!(report.checked_epochs, 1);
+        assert_eq!(report.checked_blocks, 1);
+        assert_eq!(report.checked_transactions, 1);
+        assert_eq!(report.checked_states, 1);
+        assert!(report.is_consistent());
+        assert!(report.repaired.is_empty());
+    }
+
+    #[test]
+    fn test_detect_and_repair_indices() {
+        let test_db = test_db();
+        let db_manager = &test_db.db_manager;
+        let wrong_hash = H256::repeat_byte(2);
+        db_manager.insert_hash_by_block_number_to_db(0, &wrong_hash);
+        db_manager.insert_transaction_index_to_db(
+            &test_db.tx_hash,
+            &tx_index(wrong_hash),
+        );
+
+        // Without repair, the entries are reported and left as they are.
+        let report = check(&test_db, false);
+        assert_eq!(report.inconsistencies.len(), 2);
+        assert!(report.repaired.is_empty());
+        assert_eq!(
+            db_manager.hash_by_block_number_from_db(&0),
+            Some(wrong_hash)
+        );
+        assert_eq!(
+            db_manager.transaction_index_from_db(&test_db.tx_hash),
+            Some(tx_index(wrong_hash))
+        );
+
+        // With repair, the entries are rebuilt from the blocks.
+        let report = check(&test_db, true);
+        assert!(report.is_consistent());
+        assert_eq!(report.repaired.len(), 2);
+        assert_eq!(
+            db_manager.hash_by_block_number_from_db(&0),
+            Some(test_db.block_hash)
+        );
+        assert_eq!(
+            db_manager.transaction_index_from_db(&test_db.tx_hash),
+            Some(tx_index(test_db.block_hash))
+        );
+
+        let report = check(&test_db, false);
+        assert!(report.is_consistent());
+        assert!(report.repaired.is_empty());
+    }
+
+    #[test]
+    fn test_detect_state_root_mismatch() {
+        let test_db = test_db();
+        let db_manager = &test_db.db_manager;
+        let mut commitment = db_manager
+            .epoch_execution_commitment_from_db(&test_db.block_hash)
+            .unwrap();
+        let state_root = commitment.state_root_with_aux_info.state_root.clone();
+        commitment.state_root_with_aux_info.state_root.delta_root =
+            H256::repeat_byte(3);
+        db_manager.insert_epoch_execution_commitment_to_db(
+            &test_db.block_hash,
+            &commitment,
+        );
+
+        let report = check(&test_db, false);
+        assert_eq!(report.checked_states, 1);
+        assert_eq!(
+            report.inconsistencies,
+            vec![format!(
+                "state of epoch {:?} has root {:?}, expected {:?}",
+                test_db.block_hash,
+                state_root,
+                commitment.state_root_with_aux_info.state_root
+            )]
+        );
+
+        // The state is not derivable, so it is still reported with repair.
+        let report = check(&test_db, true);
+        assert!(report.repaired.is_empty());
+        assert_eq!(report.inconsistencies.len(), 1);
+    }
+}
@@ -1,15 +1,18 @@
 use crate::{
     block_data_manager::{
-        db_decode_list, db_encode_list, BlamedHeaderVerifiedRoots,
-        BlockExecutionResultWithEpoch, BlockRewardResult, BlockTracesWithEpoch,
-        CheckpointHashes, DataVersionTuple, EpochExecutionContext,
-        LocalBlockInfo,
+        db_decode_list, db_encode_list, AddressIndexPosition,
+        AddressIndexRange, AddressTransaction, BlamedHeaderVerifiedRoots,
+        BlockDbBackend, BlockExecutionResultWithEpoch, BlockRewardResult,
+        BlockTracesWithEpoch, CheckpointHashes, DataVersionTuple,
+        EpochExecutionContext, LocalBlockInfo,
     },
     db::{
-        COL_BLAMED_HEADER_VERIFIED_ROOTS, COL_BLOCKS, COL_BLOCK_TRACES,
-        COL_EPOCH_NUMBER, COL_HASH_BY_BLOCK_NUMBER, COL_MISC, COL_TX_INDEX,
+        COL_ADDRESS_INDEX, COL_BLAMED_HEADER_VERIFIED_ROOTS, COL_BLOCKS,
+        COL_BLOCK_TRACES, COL_EPOCH_NUMBER, COL_HASH_BY_BLOCK_NUMBER, COL_MISC,
+        COL_TX_INDEX,
     },
-    pow::PowComputer,
+    pow::{next_randomx_seed_height, randomx_seed_height, PowComputer},
+    sync::StateSyncProgress,
     verification::VerificationConfig,
 };
 use byteorder::{ByteOrder, LittleEndian};
@@ -21,8 +24,9 @@ use mazze_internal_common::{
 use mazze_parameters::pow::RANDOMX_EPOCH_LENGTH;
 use mazze_storage::{
     storage_db::KeyValueDbTrait, KvdbRocksdb, KvdbSqlite, KvdbSqliteStatements,
+    RestoredChunkBoundary,
 };
-use mazze_types::H256;
+use mazze_types::{AddressWithSpace, Space, H256};
 use primitives::{Block, BlockHeader, SignedTransaction, TransactionIndex};
 use rlp::Rlp;
 use std::{collections::HashMap, fs, path::Path, sync::Arc};
@@ -37,8 +41,11 @@ const EPOCH_CONSENSUS_EXECUTION_INFO_SUFFIX_BYTE: u8 = 5;
 const EPOCH_EXECUTED_BLOCK_SET_SUFFIX_BYTE: u8 = 6;
 const EPOCH_SKIPPED_BLOCK_SET_SUFFIX_BYTE: u8 = 7;
 const BLOCK_REWARD_RESULT_SUFFIX_BYTE: u8 = 8;
+const ADDRESS_INDEX_EPOCH_SUFFIX_BYTE: u8 = 9;
 const BLOCK_TERMINAL_KEY: &[u8] = b"block_terminals";
 const GC_PROGRESS_KEY: &[u8] = b"gc_progress";
+const STATE_SYNC_PROGRESS_KEY: &[u8] = b"state_sync_progress";
+const STATE_SYNC_RESTORED_CHUNK_KEY_PREFIX: &[u8] = b"state_sync_chunk";
 
 #[derive(Clone, Copy, Hash, Ord, PartialOrd, Eq, PartialEq, EnumIter)]
 enum DBTable {
@@ -49,6 +56,7 @@ enum DBTable {
     BlamedHeaderVerifiedRoots,
     BlockTraces,
     HashByBlockNumber,
+    AddressIndex,
 }
 fn rocks_db_col(table: DBTable) -> u32 {
     match table {
@@ -59,6 +67,7 @@ fn rocks_db_col(table: DBTable) -> u32 {
         DBTable::BlamedHeaderVerifiedRoots => COL_BLAMED_HEADER_VERIFIED_ROOTS,
         DBTable::BlockTraces => COL_BLOCK_TRACES,
         DBTable::HashByBlockNumber => COL_HASH_BY_BLOCK_NUMBER,
+        DBTable::AddressIndex => COL_ADDRESS_INDEX,
     }
 }
 
@@ -71,6 +80,7 @@ fn sqlite_db_table(table: DBTable) -> String {
         DBTable::BlamedHeaderVerifiedRoots => "blamed_header_verified_roots",
         DBTable::BlockTraces => "block_traces",
         DBTable::HashByBlockNumber => "hash_by_block_number",
+        DBTable::AddressIndex => "address_index",
     }
     .into()
 }
@@ -115,6 +125,26 @@ impl DBManager {
     ) -> Self {
         Self::new_from_kvdb(db, pow, genesis_hash)
     }
+
+    /// Opens the block db of `backend`. The sqlite backend does not use `db`.
+    pub fn new_from_backend(
+        backend: BlockDbBackend, db: Arc<SystemDB>, pow: Arc<PowComputer>,
+        genesis_hash: H256,
+    ) -> Self {
+        match backend {
+            BlockDbBackend::Rocksdb => {
+                Self::new_from_rocksdb(db, pow, genesis_hash)
+            }
+            BlockDbBackend::Sqlite => Self::new_from_sqlite(
+                Path::new("./sqlite_db"),
+                pow,
+                genesis_hash,
+            ),
+            BlockDbBackend::Paritydb => {
+                Self::new_from_paritydb(db, pow, genesis_hash)
+            }
+        }
+    }
 }
 
 impl DBManager {
@@ -193,12 +223,12 @@ impl DBManager {
     }
 
     pub fn block_header_from_db(&self, hash: &H256) -> Option<BlockHeader> {
-        let mut block_header: BlockHeader =
-            self.load_decodable_val(DBTable::Blocks, hash.as_bytes())?;
+        let mut block_header =
+            self.block_header_without_pow_hash_from_db(hash)?;
 
         let seed_hash = self.get_current_seed_hash(block_header.height());
 
-        VerificationConfig::get_or_fill_header_pow_quality(
+        VerificationConfig::get_or_fill_header_pow_hash(
             &self.pow,
             &mut block_header,
             &seed_hash,
@@ -207,6 +237,13 @@ impl DBManager {
         Some(block_header)
     }
 
+    /// The header as it is stored, whose PoW hash is not computed.
+    pub fn block_header_without_pow_hash_from_db(
+        &self, hash: &H256,
+    ) -> Option<BlockHeader> {
+        self.load_decodable_val(DBTable::Blocks, hash.as_bytes())
+    }
+
     pub fn remove_block_header_from_db(&self, hash: &H256) {
         self.remove_from_db(DBTable::Blocks, hash.as_bytes());
     }
@@ -513,6 +550,127 @@ impl DBManager {
         self.load_decodable_val(DBTable::Misc, GC_PROGRESS_KEY)
     }
 
+    pub fn insert_address_index_range_to_db(
+        &self, address: &AddressWithSpace, range: &AddressIndexRange,
+    ) {
+        self.insert_encodable_val(
+            DBTable::AddressIndex,
+            &address_index_key(address),
+            range,
+        );
+    }
+
+    pub fn address_index_range_from_db(
+        &self, address: &AddressWithSpace,
+    ) -> Option<AddressIndexRange> {
+        self.load_decodable_val(
+            DBTable::AddressIndex,
+            &address_index_key(address),
+        )
+    }
+
+    pub fn remove_address_index_range_from_db(
+        &self, address: &AddressWithSpace,
+    ) {
+        self.remove_from_db(DBTable::AddressIndex, &address_index_key(address))
+    }
+
+    pub fn insert_address_transaction_to_db(
+        &self, address: &AddressWithSpace, seq: u64, value: &AddressTransaction,
+    ) {
+        self.insert_encodable_val(
+            DBTable::AddressIndex,
+            &address_transaction_key(address, seq),
+            value,
+        );
+    }
+
+    pub fn address_transaction_from_db(
+        &self, address: &AddressWithSpace, seq: u64,
+    ) -> Option<AddressTransaction> {
+        self.load_decodable_val(
+            DBTable::AddressIndex,
+            &address_transaction_key(address, seq),
+        )
+    }
+
+    pub fn remove_address_transaction_from_db(
+        &self, address: &AddressWithSpace, seq: u64,
+    ) {
+        self.remove_from_db(
+            DBTable::AddressIndex,
+            &address_transaction_key(address, seq),
+        )
+    }
+
+    pub fn insert_address_index_positions_to_db(
+        &self, epoch_number: u64, positions: &Vec<AddressIndexPosition>,
+    ) {
+        self.insert_encodable_list(
+            DBTable::AddressIndex,
+            &address_index_epoch_key(epoch_number),
+            positions,
+        );
+    }
+
+    pub fn address_index_positions_from_db(
+        &self, epoch_number: u64,
+    ) -> Option<Vec<AddressIndexPosition>> {
+        self.load_decodable_list(
+            DBTable::AddressIndex,
+            &address_index_epoch_key(epoch_number),
+        )
+    }
+
+    pub fn remove_address_index_positions_from_db(&self, epoch_number: u64) {
+        self.remove_from_db(
+            DBTable::AddressIndex,
+            &address_index_epoch_key(epoch_number),
+        )
+    }
+
+    pub fn insert_state_sync_progress_to_db(
+        &self, progress: &StateSyncProgress,
+    ) {
+        self.insert_encodable_val(
+            DBTable::Misc,
+            STATE_SYNC_PROGRESS_KEY,
+            progress,
+        );
+    }
+
+    /// A progress persisted by an incompatible version is ignored.
+    pub fn state_sync_progress_from_db(&self) -> Option<StateSyncProgress> {
+        self.load_might_decodable_val(DBTable::Misc, STATE_SYNC_PROGRESS_KEY)
+    }
+
+    pub fn remove_state_sync_progress_from_db(&self) {
+        self.remove_from_db(DBTable::Misc, STATE_SYNC_PROGRESS_KEY)
+    }
+
+    pub fn insert_restored_chunk_to_db(
+        &self, restored_chunk: &RestoredChunkBoundary,
+    ) {
+        self.insert_encodable_val(
+            DBTable::Misc,
+            &restored_chunk_key(restored_chunk.chunk_index),
+            restored_chunk,
+        );
+    }
+
+    pub fn restored_chunk_from_db(
+        &self, chunk_index: usize,
+    ) -> Option<RestoredChunkBoundary> {
+        self.load_might_decodable_val(
+            DBTable::Misc,
+            &restored_chunk_key(chunk_index),
+        )
+    }
+
+    pub fn remove_restored_chunk_from_db(&self, chunk_index: usize) {
+        self.remove_from_db(DBTable::Misc, &restored_chunk_key(chunk_index))
+    }
+
     /// The functions below are private utils used by the DBManager to access
     /// database
     fn insert_to_db(&self, table: DBTable, db_key: &[u8], value: Vec<u8>) {
@@ -583,28 +741,35 @@ impl DBManager {
     }
 
     pub fn get_current_seed_hash(&self, epoch_height: u64) -> H256 {
-        // Calculate the current epoch
-        let current_epoch = epoch_height / RANDOMX_EPOCH_LENGTH;
-
         // For epoch 0, use genesis block
-        if current_epoch == 0 {
-            return self.genesis_hash;
-        }
-
-        // For all other epochs, use the block at the start of the previous epoch
-        // floor((height - 1) / epoch_length) * epoch_length - epoch_length
-        let seed_height = (current_epoch - 1) * RANDOMX_EPOCH_LENGTH;
-        let seed_hash = self
-            .executed_epoch_set_hashes_from_db(seed_height)
-            .and_then(|hashes| hashes.last().cloned())
-            .unwrap_or_default();
+        let seed_height = match randomx_seed_height(epoch_height) {
+            Some(seed_height) => seed_height,
+            None => return self.genesis_hash,
+        };
+
+        // For all other epochs, use the block at the start of the previous
+        // epoch
+        let seed_hash = self.seed_hash_at(seed_height).unwrap_or_default();
         trace!(
             "get_current_seed hash for epoch {}: {:?}",
-            current_epoch,
+            epoch_height / RANDOMX_EPOCH_LENGTH,
             seed_hash
         );
         seed_hash
     }
+
+    /// The seed hash of the RandomX epoch following the one of
+    /// `epoch_height`. It is known once the first epoch of the current
+    /// RandomX epoch is on the main chain, i.e. `RANDOMX_EPOCH_LENGTH`
+    /// epochs before it is used.
+    pub fn get_next_seed_hash(&self, epoch_height: u64) -> Option<H256> {
+        self.seed_hash_at(next_randomx_seed_height(epoch_height))
+    }
+
+    fn seed_hash_at(&self, seed_height: u64) -> Option<H256> {
+        self.executed_epoch_set_hashes_from_db(seed_height)
+            .and_then(|hashes| hashes.last().cloned())
+    }
 }
 
 fn append_suffix(h: &H256, suffix: u8) -> Vec<u8> {
@@ -658,6 +823,39 @@ fn epoch_consensus_epoch_execution_commitment_key(hash: &H256) -> Vec<u8> {
     append_suffix(hash, EPOCH_CONSENSUS_EXECUTION_INFO_SUFFIX_BYTE)
 }
 
+fn restored_chunk_key(chunk_index: usize) -> Vec<u8> {
+    let mut key = STATE_SYNC_RESTORED_CHUNK_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&(chunk_index as u64).to_be_bytes());
+    key
+}
+
+/// The key of the sequence number range of an address, which is the space
+/// byte followed by the address.
+fn address_index_key(address: &AddressWithSpace) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + 20 + 8);
+    key.push(match address.space {
+        Space::Native => 1,
+        Space::Ethereum => 2,
+    });
+    key.extend_from_slice(address.address.as_bytes());
+    key
+}
+
+/// The entries of an address are keyed by the big-endian sequence number
+/// after the address key, so they are sorted in the order of insertion.
+fn address_transaction_key(address: &AddressWithSpace, seq: u64) -> Vec<u8> {
+    let mut key = address_index_key(address);
+    key.extend_from_slice(&seq.to_be_bytes());
+    key
+}
+
+fn address_index_epoch_key(epoch_number: u64) -> [u8; 9] {
+    let mut epoch_key = [0; 9];
+    LittleEndian::write_u64(&mut epoch_key[0..8], epoch_number);
+    epoch_key[8] = ADDRESS_INDEX_EPOCH_SUFFIX_BYTE;
+    epoch_key
+}
+
 impl MallocSizeOf for DBManager {
     fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
         // Here we only handle the case that all columns are stored within the
@@ -15,7 +15,7 @@ use mazze_storage::{
     state_manager::StateIndex, utils::guarded_value::*, StorageManager,
     StorageManagerTrait,
 };
-use mazze_types::{Bloom, Space, H256};
+use mazze_types::{AddressWithSpace, Bloom, Space, H256};
 use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockUpgradableReadGuard};
 use primitives::{
     block::CompactBlock,
@@ -30,8 +30,11 @@ use std::{
 };
 use threadpool::ThreadPool;
 pub mod block_data_types;
+pub mod db_check;
 pub mod db_gc_manager;
 pub mod db_manager;
+#[cfg(test)]
+mod test_utils;
 pub mod tx_data_manager;
 use crate::block_data_manager::{
     db_manager::DBManager, tx_data_manager::TransactionDataManager,
@@ -46,7 +49,7 @@ use mazze_internal_common::{
     EpochExecutionCommitment, StateAvailabilityBoundary, StateRootWithAuxInfo,
 };
 use metrics::{register_meter_with_group, Meter, MeterTimer};
-use std::{hash::Hash, path::Path, time::Duration};
+use std::{hash::Hash, time::Duration};
 
 lazy_static! {
     static ref TX_POOL_RECOVER_TIMER: Arc<dyn Meter> =
@@ -123,6 +126,9 @@ pub struct BlockDataManager {
     cur_consensus_era_genesis_hash: RwLock<H256>,
     cur_consensus_era_stable_hash: RwLock<H256>,
     instance_id: Mutex<u64>,
+    /// Serializes the updates of the address index, which read and write the
+    /// sequence number ranges of the addresses.
+    address_index_lock: Mutex<()>,
 
     config: DataManagerConfiguration,
 
@@ -130,7 +136,6 @@ pub struct BlockDataManager {
     pub db_manager: DBManager,
 
     // TODO Add MallocSizeOf.
-    #[ignore_malloc_size_of = "Add later"]
     pub pow: Arc<PowComputer>,
 
     /// This is the original genesis block.
@@ -182,23 +187,12 @@ impl BlockDataManager {
             config.tx_cache_index_maintain_timeout,
             worker_pool,
         );
-        let db_manager = match config.block_db_backend {
-            BlockDbBackend::Rocksdb => DBManager::new_from_rocksdb(
-                db,
-                pow.clone(),
-                true_genesis.hash(),
-            ),
-            BlockDbBackend::Sqlite => DBManager::new_from_sqlite(
-                Path::new("./sqlite_db"),
-                pow.clone(),
-                true_genesis.hash(),
-            ),
-            BlockDbBackend::Paritydb => DBManager::new_from_paritydb(
-                db,
-                pow.clone(),
-                true_genesis.hash(),
-            ),
-        };
+        let db_manager = DBManager::new_from_backend(
+            config.block_db_backend,
+            db,
+            pow.clone(),
+            true_genesis.hash(),
+        );
         let previous_db_progress =
             db_manager.gc_progress_from_db().unwrap_or(0);
 
@@ -222,6 +216,7 @@ impl BlockDataManager {
             storage_manager,
             cache_man,
             instance_id: Mutex::new(0),
+            address_index_lock: Mutex::new(()),
             config,
             target_difficulty_manager: TargetDifficultyManager::new(
                 cache_conf.target_difficulties_cache_size_in_count,
@@ -800,6 +795,138 @@ impl BlockDataManager {
         }
     }
 
+    pub fn address_index_enabled(&self) -> bool {
+        self.config.persist_address_index
+    }
+
+    /// Index the transactions of an executed epoch by the addresses they
+    /// touch. The entries of an address are numbered in the order of
+    /// insertion. An epoch already indexed with the same main block, e.g.
+    /// re-executed after a restart, is skipped.
+    pub fn insert_epoch_address_index(
+        &self, epoch_number: u64, epoch_hash: &H256,
+        entries: Vec<(AddressWithSpace, AddressTransaction)>,
+    ) {
+        if !self.config.persist_address_index || entries.is_empty() {
+            return;
+        }
+        let _guard = self.address_index_lock.lock();
+        let mut positions = self
+            .db_manager
+            .address_index_positions_from_db(epoch_number)
+            .unwrap_or_default();
+        if positions.iter().any(|p| p.epoch_hash == *epoch_hash) {
+            return;
+        }
+
+        let mut ranges = HashMap::new();
+        for (address, tx) in entries {
+            let range = ranges.entry(address).or_insert_with(|| {
+                self.db_manager
+                    .address_index_range_from_db(&address)
+                    .unwrap_or_default()
+            });
+            self.db_manager
+                .insert_address_transaction_to_db(&address, range.next, &tx);
+            positions.push(AddressIndexPosition {
+                epoch_hash: *epoch_hash,
+                address,
+                seq: range.next,
+            });
+            range.next += 1;
+        }
+        for (address, range) in ranges {
+            self.db_manager
+                .insert_address_index_range_to_db(&address, &range);
+        }
+        self.db_manager
+            .insert_address_index_positions_to_db(epoch_number, &positions);
+    }
+
+    /// Return at most `limit` transactions touching `address`, from the
+    /// newest to the oldest, starting from the entry numbered `cursor` or the
+    /// newest entry if it is `None`. The entries rejected by `is_main`, i.e.
+    /// those of the epochs reverted by a main chain switch, are skipped. The
+    /// cursor of the next page is returned if there are more entries.
+    pub fn transactions_by_address<F>(
+        &self, address: &AddressWithSpace, cursor: Option<u64>, limit: usize,
+        is_main: F,
+    ) -> (Vec<AddressTransaction>, Option<u64>)
+    where
+        F: Fn(&AddressTransaction) -> bool,
+    {
+        let range = match self.db_manager.address_index_range_from_db(address) {
+            Some(range) => range,
+            None => return (vec![], None),
+        };
+        let mut seq = match cursor {
+            Some(cursor) => range.next.min(cursor.saturating_add(1)),
+            None => range.next,
+        };
+        let mut transactions = vec![];
+        while seq > range.first {
+            if transactions.len() >= limit {
+                return (transactions, Some(seq - 1));
+            }
+            seq -= 1;
+            if let Some(tx) =
+                self.db_manager.address_transaction_from_db(address, seq)
+            {
+                if is_main(&tx) {
+                    transactions.push(tx);
+                }
+            }
+        }
+        (transactions, None)
+    }
+
+    /// Remove the address index entries written when executing the epoch
+    /// `epoch_number`, including those of the reverted executions.
+    fn gc_address_index(&self, epoch_number: u64) {
+        let _guard = self.address_index_lock.lock();
+        let positions = match self
+            .db_manager
+            .address_index_positions_from_db(epoch_number)
+        {
+            Some(positions) => positions,
+            None => return,
+        };
+        let mut addresses = HashSet::new();
+        for position in positions {
+            self.db_manager.remove_address_transaction_from_db(
+                &position.address,
+                position.seq,
+            );
+            addresses.insert(position.address);
+        }
+        // Entries of an address are not removed in order if the main chain
+        // is switched, so only the leading removed entries are dropped from
+        // its range.
+        for address in addresses {
+            let mut range =
+                match self.db_manager.address_index_range_from_db(&address) {
+                    Some(range) => range,
+                    None => continue,
+                };
+            while range.first < range.next
+                && self
+                    .db_manager
+                    .address_transaction_from_db(&address, range.first)
+                    .is_none()
+            {
+                range.first += 1;
+            }
+            if range.first == range.next {
+                self.db_manager.remove_address_index_range_from_db(&address);
+            } else {
+                self.db_manager
+                    .insert_address_index_range_to_db(&address, &range);
+            }
+        }
+        self.db_manager
+            .remove_address_index_positions_from_db(epoch_number);
+    }
+
     pub fn hash_by_block_number(
         &self, block_number: u64, update_cache: bool,
     ) -> Option<H256> {
@@ -1703,6 +1830,9 @@ impl BlockDataManager {
                         }
                     }
                 }
+                if self.config.persist_address_index {
+                    self.gc_address_index(epoch_to_remove);
+                }
             }
         };
         self.gc_epoch_with_defer(
@@ -1762,6 +1892,9 @@ pub enum BlockDbBackend {
 pub struct DataManagerConfiguration {
     pub persist_tx_index: bool,
     pub persist_block_number_index: bool,
+    /// Index the transactions by the addresses they touch. The index is
+    /// garbage collected with the transaction index.
+    pub persist_address_index: bool,
     pub tx_cache_index_maintain_timeout: Duration,
     pub block_db_backend: BlockDbBackend,
     pub paritydb_settings: Option<db::ParityDbOpenConfig>,
@@ -1789,6 +1922,7 @@ impl DataManagerConfiguration {
         Self {
             persist_tx_index,
             persist_block_number_index,
+            persist_address_index: false,
             tx_cache_index_maintain_timeout,
             block_db_backend,
             paritydb_settings: None,
@@ -1802,3 +1936,229 @@ impl DataManagerConfiguration {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_data_manager::test_utils::{test_dbs, TestDbs},
+        genesis_block::genesis_block,
+    };
+    use mazze_executor::machine::{new_machine_with_builtin, VmFactory};
+    use mazze_types::{Address, AddressSpaceUtil, H160, U256};
+    use tempdir::TempDir;
+
+    struct TestDataManager {
+        data_man: BlockDataManager,
+        // Dropped after the dbs are closed.
+        _dir: TempDir,
+    }
+
+    fn test_data_manager() -> TestDataManager {
+        let TestDbs {
+            ledger_db,
+            storage_manager,
+            dir,
+        } = test_dbs("address_index");
+        let machine = Arc::new(new_machine_with_builtin(
+            Default::default(),
+            VmFactory::new(1024 * 32),
+        ));
+        let genesis = Arc::new(genesis_block(
+            &storage_manager,
+            HashMap::new(),
+            Address::zero(),
+            U256::from(10),
+            machine,
+            false, /* need_to_execute */
+            None,
+        ));
+
+        let mut config = DataManagerConfiguration::new(
+            false, /* persist_tx_index */
+            false, /* persist_block_number_index */
+            Duration::from_millis(300_000),
+            BlockDbBackend::Rocksdb,
+        );
+        config.persist_address_index = true;
+        let data_man = BlockDataManager::new(
+            CacheConfig::default(),
+            genesis.clone(),
+            ledger_db,
+            storage_manager,
+            Arc::new(Mutex::new(ThreadPool::new(1))),
+            config,
+            Arc::new(PowComputer::new(genesis.hash())),
+        );
+        TestDataManager {
+            data_man,
+            _dir: dir,
+        }
+    }
+
+    fn address() -> AddressWithSpace {
+        H160::repeat_byte(1).with_native_space()
+    }
+
+    /// Index `tx_count` transactions of `address()` in the epoch
+    /// `epoch_number` with the main block `epoch_hash`.
+    fn index_epoch(
+        data_man: &BlockDataManager, epoch_number: u64, epoch_hash: H256,
+        tx_count: u64,
+    ) -> Vec<AddressTransaction> {
+        let txs: Vec<_> = (0..tx_count)
+            .map(|i| AddressTransaction {
+                transaction_hash: H256::from_low_u64_be(
+                    epoch_hash.to_low_u64_be() * 100 + i,
+                ),
+                block_hash: epoch_hash,
+                epoch_hash,
+                epoch_number,
+            })
+            .collect();
+        data_man.insert_epoch_address_index(
+            epoch_number,
+            &epoch_hash,
+            txs.iter().map(|tx| (address(), tx.clone())).collect(),
+        );
+        txs
+    }
+
+    /// All the transactions of `address()` in the epochs whose main block is
+    /// in `main_chain`, from the newest to the oldest.
+    fn all_transactions(
+        data_man: &BlockDataManager, main_chain: &HashMap<u64, H256>,
+    ) -> Vec<AddressTransaction> {
+        data_man
+            .transactions_by_address(&address(), None, usize::MAX, |tx| {
+                main_chain.get(&tx.epoch_number) == Some(&tx.epoch_hash)
+            })
+            .0
+    }
+
+    fn range(data_man: &BlockDataManager) -> Option<(u64, u64)> {
+        data_man
+            .db_manager
+            .address_index_range_from_db(&address())
+            .map(|range| (range.first, range.next))
+    }
+
+    #[test]
+    fn test_pagination() {
+        let test = test_data_manager();
+        let data_man = &test.data_man;
+        let mut main_chain = HashMap::new();
+        let mut txs = vec![];
+        for epoch in 1..=3 {
+            let epoch_hash = H256::from_low_u64_be(epoch);
+            main_chain.insert(epoch, epoch_hash);
+            txs.extend(index_epoch(data_man, epoch, epoch_hash, 2));
+        }
+        // An epoch indexed again with the same main block is skipped.
+        index_epoch(data_man, 3, H256::from_low_u64_be(3), 2);
+        assert_eq!(range(data_man), Some((0, 6)));
+        txs.reverse();
+
+        let is_main = |tx: &AddressTransaction| {
+            main_chain[&tx.epoch_number] == tx.epoch_hash
+        };
+        let (page, cursor) =
+            data_man.transactions_by_address(&address(), None, 4, is_main);
+        assert_eq!(page, txs[..4]);
+        assert_eq!(cursor, Some(1));
+
+        let (page, cursor) =
+            data_man.transactions_by_address(&address(), cursor, 4, is_main);
+        assert_eq!(page, txs[4..]);
+        assert_eq!(cursor, None);
+
+        // A page ending at the oldest entry has no next page.
+        let (page, cursor) =
+            data_man.transactions_by_address(&address(), Some(1), 2, is_main);
+        assert_eq!(page, txs[4..]);
+        assert_eq!(cursor, None);
+    }
+
+    #[test]
+    fn test_reverted_epochs() {
+        let test = test_data_manager();
+        let data_man = &test.data_man;
+        let epoch_1 = index_epoch(data_man, 1, H256::from_low_u64_be(1), 1);
+        let reverted = index_epoch(data_man, 2, H256::from_low_u64_be(2), 2);
+        // The main chain is switched, and the epoch 2 is executed again with
+        // another main block.
+        let epoch_2 = index_epoch(data_man, 2, H256::from_low_u64_be(22), 1);
+        assert_eq!(range(data_man), Some((0, 4)));
+
+        let mut main_chain = HashMap::new();
+        main_chain.insert(1, H256::from_low_u64_be(1));
+        main_chain.insert(2, H256::from_low_u64_be(22));
+        let expected: Vec<_> =
+            epoch_2.iter().chain(epoch_1.iter()).cloned().collect();
+        assert_eq!(all_transactions(data_man, &main_chain), expected);
+
+        // The reverted entries are skipped without ending the page early.
+        let is_main = |tx: &AddressTransaction| {
+            main_chain[&tx.epoch_number] == tx.epoch_hash
+        };
+        let (page, cursor) =
+            data_man.transactions_by_address(&address(), None, 1, is_main);
+        assert_eq!(page, epoch_2);
+        assert_eq!(cursor, Some(2));
+        let (page, cursor) =
+            data_man.transactions_by_address(&address(), cursor, 1, is_main);
+        assert_eq!(page, epoch_1);
+        assert_eq!(cursor, None);
+
+        main_chain.insert(2, H256::from_low_u64_be(2));
+        let expected: Vec<_> = reverted
+            .iter()
+            .rev()
+            .chain(epoch_1.iter())
+            .cloned()
+            .collect();
+        assert_eq!(all_transactions(data_man, &main_chain), expected);
+    }
+
+    #[test]
+    fn test_gc_address_index() {
+        let test = test_data_manager();
+        let data_man = &test.data_man;
+        let mut main_chain = HashMap::new();
+        for epoch in 1..=3 {
+            main_chain.insert(epoch, H256::from_low_u64_be(epoch));
+        }
+        // Epoch 2 is indexed after epoch 3 with a reverted execution, so the
+        // entries are not in the order of the epochs: 1, 2', 3, 2.
+        index_epoch(data_man, 1, H256::from_low_u64_be(1), 1);
+        index_epoch(data_man, 2, H256::from_low_u64_be(22), 1);
+        let epoch_3 = index_epoch(data_man, 3, H256::from_low_u64_be(3), 1);
+        index_epoch(data_man, 2, H256::from_low_u64_be(2), 1);
+        assert_eq!(range(data_man), Some((0, 4)));
+
+        // The entries of an epoch in the middle are removed, but the range
+        // is kept.
+        data_man.gc_address_index(2);
+        assert_eq!(range(data_man), Some((0, 4)));
+        assert!(data_man
+            .db_manager
+            .address_index_positions_from_db(2)
+            .is_none());
+        let is_any = |_: &AddressTransaction| true;
+        let (all, _) =
+            data_man.transactions_by_address(&address(), None, 10, is_any);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0], epoch_3[0]);
+
+        // Removing the oldest entries trims the range up to the first
+        // remaining entry.
+        data_man.gc_address_index(1);
+        assert_eq!(range(data_man), Some((2, 4)));
+        assert_eq!(all_transactions(data_man, &main_chain), epoch_3);
+
+        // The range is removed with the last entry.
+        data_man.gc_address_index(3);
+        assert_eq!(range(data_man), None);
+        assert!(all_transactions(data_man, &main_chain).is_empty());
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::{db::NUM_COLUMNS, ext_db::SystemDB};
+use mazze_storage::{StorageConfiguration, StorageManager};
+use std::sync::Arc;
+use tempdir::TempDir;
+
+/// An empty ledger db and storage in a temporary directory.
+pub(crate) struct TestDbs {
+    pub ledger_db: Arc<SystemDB>,
+    pub storage_manager: Arc<StorageManager>,
+    pub dir: TempDir,
+}
+
+pub(crate) fn test_dbs(prefix: &str) -> TestDbs {
+    let dir = TempDir::new(prefix).unwrap();
+    let path = dir.path().to_str().unwrap().to_string();
+    let ledger_db = db::open_database(&db::rocksdb_settings(
+        dir.path().join("blockchain_db"),
+        None,
+        db::DatabaseCompactionProfile::default(),
+        NUM_COLUMNS,
+        false,
+    ))
+    .unwrap();
+    let storage_manager = Arc::new(
+        StorageManager::new(StorageConfiguration::new_default(
+            &path,
+            mazze_parameters::consensus::SNAPSHOT_EPOCHS_CAPACITY,
+            mazze_parameters::consensus::ERA_DEFAULT_EPOCH_COUNT,
+        ))
+        .unwrap(),
+    );
+    TestDbs {
+        ledger_db,
+        storage_manager,
+        dir,
+    }
+}
@@ -1,25 +1,30 @@
 use super::ConsensusExecutionHandler;
-use std::{convert::From, sync::Arc};
+use std::{collections::BTreeSet, convert::From, sync::Arc};
 
 use alloy_rpc_types_trace::geth::GethDebugTracingOptions;
 use geth_tracer::{GethTraceWithHash, GethTracer, TxExecContext};
 
 use mazze_statedb::Result as DbResult;
-use mazze_types::{Space, SpaceMap, H256, U256};
+use mazze_types::{
+    AddressSpaceUtil, AddressWithSpace, Space, SpaceMap, H256, U256,
+};
 use primitives::{
     receipt::BlockReceipts, Action, Block, BlockNumber, EpochId, Receipt,
     SignedTransaction, TransactionIndex,
 };
 
 use crate::{
-    block_data_manager::BlockDataManager,
+    block_data_manager::{AddressTransaction, BlockDataManager},
     consensus::consensus_inner::consensus_executor::GOOD_TPS_METER,
     state_prefetcher::{prefetch_accounts, PrefetchTaskHandle},
 };
 use mazze_execute_helper::{
-    exec_tracer::TransactionExecTraces,
-    observer::Observer,
-    tx_outcome::{make_process_tx_outcome, ProcessTxOutcome},
+    exec_tracer::{ExecTrace, ExecTracer, TransactionExecTraces},
+    observer::{vm_tracer::VmTracer, Observer},
+    parity_trace::{ParityTrace, ParityTraceOptions, ParityTraceWithHash},
+    tx_outcome::{
+        geth_trace_with_prestate, make_process_tx_outcome, ProcessTxOutcome,
+    },
 };
 use mazze_executor::{
     executive::{ExecutiveContext, TransactOptions, TransactSettings},
@@ -32,6 +37,7 @@ use mazze_vm_types::Env;
 
 pub enum VirtualCall<'a> {
     GethTrace(GethTask<'a>),
+    ParityTrace(ParityTask<'a>),
 }
 
 pub struct GethTask<'a> {
@@ -40,6 +46,12 @@ pub struct GethTask<'a> {
     pub(super) answer: &'a mut Vec<GethTraceWithHash>,
 }
 
+pub struct ParityTask<'a> {
+    pub(super) tx_hash: Option<H256>,
+    pub(super) options: ParityTraceOptions,
+    pub(super) answer: &'a mut Vec<ParityTraceWithHash>,
+}
+
 impl ConsensusExecutionHandler {
     pub(super) fn process_epoch_transactions<'a>(
         &self, epoch_id: EpochId, state: &mut State,
@@ -89,12 +101,23 @@ impl ConsensusExecutionHandler {
             )?;
         }
 
-        if let Some(VirtualCall::GethTrace(task)) = context.virtual_call {
-            std::mem::swap(&mut epoch_recorder.geth_traces, task.answer);
+        match context.virtual_call {
+            Some(VirtualCall::GethTrace(task)) => {
+                std::mem::swap(&mut epoch_recorder.geth_traces, task.answer);
+            }
+            Some(VirtualCall::ParityTrace(task)) => {
+                std::mem::swap(&mut epoch_recorder.parity_traces, task.answer);
+            }
+            None => {}
         }
 
         if !dry_run && on_local_main {
             self.tx_pool.recycle_transactions(epoch_recorder.repack_tx);
+            self.data_man.insert_epoch_address_index(
+                main_block.block_header.height(),
+                &main_block.hash(),
+                epoch_recorder.address_index,
+            );
         }
 
         debug!("Finish processing tx for epoch");
@@ -243,16 +266,44 @@ impl ConsensusExecutionHandler {
 
         let spec = machine.spec(env.number, env.epoch_height);
 
+        let parity_options =
+            Self::parity_trace_options(transaction, block_context);
+
+        let observer = self.make_observer(
+            transaction,
+            block_context,
+            parity_options,
+            spec.cancun_opcodes,
+        );
+        let geth_prestate = observer
+            .geth_tracer
+            .as_ref()
+            .map_or(false, GethTracer::records_prestate);
         let options = TransactOptions {
-            observer: self.make_observer(transaction, block_context),
+            observer,
             settings: TransactSettings::all_checks(),
         };
 
+        // The checkpoint is used to collect the state changes of the traced
+        // transaction.
+        if parity_options.is_some() || geth_prestate {
+            state.checkpoint();
+        }
+
         let execution_outcome =
             ExecutiveContext::new(state, env, machine, &spec)
                 .transact(transaction, options)?;
         execution_outcome.log(transaction, &block_context.block.hash());
 
+        let index_addresses =
+            self.data_man.address_index_enabled() && on_local_main && !dry_run;
+        let contracts_created = match execution_outcome.try_as_executed() {
+            Some(executed) if index_addresses => {
+                executed.contracts_created.clone()
+            }
+            _ => vec![],
+        };
+
         if let Some(burnt_fee) = execution_outcome
             .try_as_executed()
             .and_then(|e| e.burnt_fee)
@@ -260,12 +311,47 @@ impl ConsensusExecutionHandler {
             state.burn_by_mip1559(burnt_fee);
         };
 
-        let r = make_process_tx_outcome(
+        if let Some(parity_options) = parity_options {
+            let state_diff = if parity_options.state_diff {
+                Some(state.diff_since_checkpoint()?)
+            } else {
+                None
+            };
+            state.discard_checkpoint();
+            recorder.parity_traces.push(ParityTraceWithHash {
+                trace: ParityTrace::from_outcome(
+                    &execution_outcome,
+                    state_diff,
+                ),
+                tx_hash: transaction.hash(),
+                block_hash: block.hash(),
+                space: transaction.space(),
+            });
+        }
+
+        let geth_trace = match block_context.epoch_context.virtual_call {
+            Some(VirtualCall::GethTrace(ref task)) if geth_prestate => {
+                let trace = geth_trace_with_prestate(
+                    &execution_outcome,
+                    state,
+                    &task.opts,
+                    transaction.space(),
+                )?;
+                state.discard_checkpoint();
+                Some(trace)
+            }
+            _ => None,
+        };
+
+        let mut r = make_process_tx_outcome(
             execution_outcome,
             &mut env.accumulated_gas_used,
             transaction.hash,
             &spec,
         );
+        if let Some(geth_trace) = geth_trace {
+            r.geth_trace = geth_trace;
+        }
 
         if r.receipt.tx_success() {
             GOOD_TPS_METER.mark(1);
@@ -273,6 +359,11 @@ impl ConsensusExecutionHandler {
 
         let tx_skipped = r.receipt.tx_skipped();
         let phantom_txs = r.phantom_txs.clone();
+        let touched_addresses = if index_addresses && !tx_skipped {
+            touched_addresses(transaction, &contracts_created, &r.tx_traces)
+        } else {
+            BTreeSet::new()
+        };
 
         recorder.receive_tx_outcome(r, transaction, block_context);
 
@@ -293,6 +384,19 @@ impl ConsensusExecutionHandler {
             },
         );
 
+        let main_block = block_context.epoch_context.main_block;
+        for address in touched_addresses {
+            recorder.address_index.push((
+                address,
+                AddressTransaction {
+                    transaction_hash: hash,
+                    block_hash: block.hash(),
+                    epoch_hash: main_block.hash(),
+                    epoch_number: main_block.block_header.height(),
+                },
+            ));
+        }
+
         // persist tx index for phantom transactions.
         // note: in some cases, main chain reorgs will result in
         // different phantom txs (with different hashes) for the
@@ -319,9 +423,27 @@ impl ConsensusExecutionHandler {
         Ok(())
     }
 
+    /// The options of the parity-style trace of the transaction, if it is
+    /// traced.
+    fn parity_trace_options(
+        transaction: &SignedTransaction, block_context: &BlockProcessContext,
+    ) -> Option<ParityTraceOptions> {
+        match block_context.epoch_context.virtual_call {
+            Some(VirtualCall::ParityTrace(ref task))
+                if task
+                    .tx_hash
+                    .map_or(true, |hash| transaction.hash() == hash) =>
+            {
+                Some(task.options)
+            }
+            _ => None,
+        }
+    }
+
     fn make_observer(
         &self, transaction: &Arc<SignedTransaction>,
         block_context: &BlockProcessContext,
+        parity_options: Option<ParityTraceOptions>, cancun: bool,
     ) -> Observer {
         use alloy_rpc_types_trace::geth::{
             GethDebugBuiltInTracerType::*, GethDebugTracerType::BuiltInTracer,
@@ -341,7 +463,11 @@ impl ConsensusExecutionHandler {
             let support_tracer = matches!(
                 task.opts.tracer,
                 Some(BuiltInTracer(
-                    FourByteTracer | CallTracer | PreStateTracer | NoopTracer
+                    FourByteTracer
+                        | CallTracer
+                        | PreStateTracer
+                        | NoopTracer
+                        | MuxTracer
                 )) | None
             );
             let tx_gas_limit = transaction.gas_limit().as_u64();
@@ -362,6 +488,15 @@ impl ConsensusExecutionHandler {
                 ))
             }
         }
+
+        if let Some(options) = parity_options {
+            if observer.tracer.is_none() {
+                observer.tracer = Some(ExecTracer::default());
+            }
+            if options.vm_trace {
+                observer.vm_tracer = Some(VmTracer::new(cancun));
+            }
+        }
         observer
     }
 
@@ -444,6 +579,8 @@ struct EpochProcessRecorder {
     receipts: Vec<Arc<BlockReceipts>>,
     repack_tx: Vec<Arc<SignedTransaction>>,
     geth_traces: Vec<GethTraceWithHash>,
+    parity_traces: Vec<ParityTraceWithHash>,
+    address_index: Vec<(AddressWithSpace, AddressTransaction)>,
 
     evm_tx_idx: usize,
 }
@@ -459,6 +596,8 @@ struct BlockProcessRecorder {
     tx_error_msg: Vec<String>,
     traces: Vec<TransactionExecTraces>,
     geth_traces: Vec<GethTraceWithHash>,
+    parity_traces: Vec<ParityTraceWithHash>,
+    address_index: Vec<(AddressWithSpace, AddressTransaction)>,
     repack_tx: Vec<Arc<SignedTransaction>>,
     tx_idx: SpaceMap<usize>,
 }
@@ -472,6 +611,8 @@ impl BlockProcessRecorder {
             tx_error_msg: vec![],
             traces: vec![],
             geth_traces: vec![],
+            parity_traces: vec![],
+            address_index: vec![],
             repack_tx: vec![],
             tx_idx,
         }
@@ -550,6 +691,8 @@ impl BlockProcessRecorder {
         epoch_recorder.receipts.push(block_receipts.clone());
         epoch_recorder.repack_tx.extend(self.repack_tx);
         epoch_recorder.geth_traces.extend(self.geth_traces);
+        epoch_recorder.parity_traces.extend(self.parity_traces);
+        epoch_recorder.address_index.extend(self.address_index);
 
         epoch_recorder.evm_tx_idx = self.tx_idx[Space::Ethereum];
 
@@ -574,3 +717,22 @@ impl BlockProcessRecorder {
         );
     }
 }
+
+/// The addresses touched by a transaction: the sender, the receiver, the
+/// created contracts and the addresses in the value transfers of its traces.
+/// The traces are only available if `executive_trace` is enabled.
+fn touched_addresses(
+    transaction: &SignedTransaction, contracts_created: &[AddressWithSpace],
+    traces: &[ExecTrace],
+) -> BTreeSet<AddressWithSpace> {
+    let mut addresses = BTreeSet::new();
+    addresses.insert(transaction.sender());
+    if let Action::Call(to) = transaction.action() {
+        addresses.insert(to.with_space(transaction.space()));
+    }
+    addresses.extend(contracts_created.iter().cloned());
+    for trace in traces.iter().filter(|trace| trace.valid) {
+        addresses.extend(trace.action.transfer_addresses());
+    }
+    addresses
+}
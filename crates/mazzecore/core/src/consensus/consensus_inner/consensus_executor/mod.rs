@@ -36,8 +36,8 @@ use mazze_types::{
 };
 use metrics::{register_meter_with_group, Meter, MeterTimer};
 use primitives::{
-    compute_block_number, receipt::BlockReceipts, Block, BlockHeader,
-    BlockHeaderBuilder, SignedTransaction, MERKLE_NULL_NODE,
+    compute_block_number, receipt::BlockReceipts, AccessList, Block,
+    BlockHeader, BlockHeaderBuilder, SignedTransaction, MERKLE_NULL_NODE,
 };
 
 use crate::{
@@ -57,9 +57,13 @@ use crate::{
     },
     SharedTransactionPool,
 };
-use geth_tracer::GethTraceWithHash;
-use mazze_execute_helper::estimation::{
-    EstimateExt, EstimateRequest, EstimationContext,
+use geth_tracer::{GethTraceWithHash, TxExecContext};
+use mazze_execute_helper::{
+    estimation::{EstimateExt, EstimateRequest, EstimationContext},
+    observer::Observer,
+    overrides::CallOverrides,
+    parity_trace::{ParityTrace, ParityTraceOptions, ParityTraceWithHash},
+    simulation::{simulate_blocks, SimulateBlock, SimulatedBlock},
 };
 use mazze_executor::{
     executive::ExecutionOutcome,
@@ -68,9 +72,9 @@ use mazze_executor::{
 };
 use mazze_vm_types::{Env, Spec};
 
-use alloy_rpc_types_trace::geth::GethDebugTracingOptions;
+use alloy_rpc_types_trace::geth::{GethDebugTracingOptions, GethTrace};
 
-use self::epoch_execution::{GethTask, VirtualCall};
+use self::epoch_execution::{GethTask, ParityTask, VirtualCall};
 
 lazy_static! {
     static ref CONSENSIS_EXECUTION_TIMER: Arc<dyn Meter> =
@@ -633,9 +637,45 @@ impl ConsensusExecutor {
 
     pub fn call_virtual(
         &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
-        request: EstimateRequest,
+        request: EstimateRequest, overrides: &CallOverrides,
     ) -> RpcResult<(ExecutionOutcome, EstimateExt)> {
-        self.handler.call_virtual(tx, epoch_id, epoch_size, request)
+        self.handler
+            .call_virtual(tx, epoch_id, epoch_size, request, overrides)
+    }
+
+    pub fn call_virtual_geth_trace(
+        &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
+        request: EstimateRequest, overrides: &CallOverrides,
+        opts: GethDebugTracingOptions,
+    ) -> RpcResult<(ExecutionOutcome, Option<GethTrace>)> {
+        self.handler.call_virtual_geth_trace(
+            tx, epoch_id, epoch_size, request, overrides, opts,
+        )
+    }
+
+    pub fn simulate_virtual(
+        &self, blocks: Vec<SimulateBlock>, space: Space, epoch_id: &H256,
+        epoch_size: usize, validation: bool,
+    ) -> RpcResult<Vec<SimulatedBlock>> {
+        self.handler
+            .simulate_virtual(blocks, space, epoch_id, epoch_size, validation)
+    }
+
+    pub fn create_access_list_virtual(
+        &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
+        request: EstimateRequest,
+    ) -> RpcResult<(AccessList, ExecutionOutcome)> {
+        self.handler
+            .create_access_list_virtual(tx, epoch_id, epoch_size, request)
+    }
+
+    pub fn call_virtual_parity_trace(
+        &self, txs: Vec<(SignedTransaction, EstimateRequest)>, space: Space,
+        epoch_id: &H256, epoch_size: usize, options: ParityTraceOptions,
+    ) -> RpcResult<Vec<(ExecutionOutcome, ParityTrace)>> {
+        self.handler.call_virtual_parity_trace(
+            txs, space, epoch_id, epoch_size, options,
+        )
     }
 
     pub fn collect_epoch_geth_trace(
@@ -646,6 +686,17 @@ impl ConsensusExecutor {
             .collect_epoch_geth_trace(epoch_block_hashes, tx_hash, opts)
     }
 
+    pub fn collect_epoch_parity_trace(
+        &self, epoch_block_hashes: Vec<H256>, tx_hash: Option<H256>,
+        options: ParityTraceOptions,
+    ) -> RpcResult<Vec<ParityTraceWithHash>> {
+        self.handler.collect_epoch_parity_trace(
+            epoch_block_hashes,
+            tx_hash,
+            options,
+        )
+    }
+
     pub fn stop(&self) {
         // `stopped` is used to allow the execution thread to stopped even the
         // queue is not empty and `ExecutionTask::Stop` has not been
@@ -1220,6 +1271,9 @@ impl ConsensusExecutionHandler {
                             .data_man
                             .db_manager
                             .get_current_seed_hash(block.block_header.height()),
+                        self.machine.params().pow_lower_bound_enabled(
+                            block.block_header.height(),
+                        ),
                     );
                 let mut reward = if pow_quality >= *epoch_difficulty {
                     base_reward_per_block
@@ -1495,8 +1549,148 @@ impl ConsensusExecutionHandler {
 
     pub fn call_virtual(
         &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
-        request: EstimateRequest,
+        request: EstimateRequest, overrides: &CallOverrides,
     ) -> RpcResult<(ExecutionOutcome, EstimateExt)> {
+        let (mut state, env) =
+            self.make_virtual_call_state(tx, epoch_id, epoch_size, overrides)?;
+        let spec = self.machine.spec(env.number, env.epoch_height);
+        let mut ex = EstimationContext::new(
+            &mut state,
+            &env,
+            self.machine.as_ref(),
+            &spec,
+        );
+
+        let r = ex.transact_virtual(tx.clone(), request);
+        trace!("Execution result {:?}", r);
+        Ok(r?)
+    }
+
+    /// Execute a virtual call with a geth tracer attached. The trace is
+    /// `None` if the transaction is not executed at all.
+    pub fn call_virtual_geth_trace(
+        &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
+        request: EstimateRequest, overrides: &CallOverrides,
+        opts: GethDebugTracingOptions,
+    ) -> RpcResult<(ExecutionOutcome, Option<GethTrace>)> {
+        let (mut state, env) =
+            self.make_virtual_call_state(tx, epoch_id, epoch_size, overrides)?;
+        let spec = self.machine.spec(env.number, env.epoch_height);
+        let observer = Observer::geth_tracer(
+            TxExecContext {
+                tx_gas_limit: tx.gas_limit().as_u64(),
+                block_height: env.epoch_height,
+                block_number: env.number,
+            },
+            Arc::clone(&self.machine),
+            opts.clone(),
+        );
+        let mut ex = EstimationContext::new(
+            &mut state,
+            &env,
+            self.machine.as_ref(),
+            &spec,
+        );
+
+        let (outcome, trace) =
+            ex.transact_geth_traced(tx.clone(), request, observer, &opts)?;
+        trace!("Execution result {:?}", outcome);
+        Ok((outcome, trace))
+    }
+
+    /// Generate the access list of a virtual call of `tx` on top of the state
+    /// of `epoch_id`.
+    pub fn create_access_list_virtual(
+        &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
+        request: EstimateRequest,
+    ) -> RpcResult<(AccessList, ExecutionOutcome)> {
+        let (mut state, env) = self.make_virtual_call_state(
+            tx,
+            epoch_id,
+            epoch_size,
+            &CallOverrides::default(),
+        )?;
+        let spec = self.machine.spec(env.number, env.epoch_height);
+        let mut ex = EstimationContext::new(
+            &mut state,
+            &env,
+            self.machine.as_ref(),
+            &spec,
+        );
+
+        let r = ex.create_access_list(tx.clone(), request);
+        trace!("Execution result {:?}", r);
+        Ok(r?)
+    }
+
+    /// Execute the calls `txs` in order with parity-style tracers on top of
+    /// the state of `epoch_id`, keeping the effects of every call, as in
+    /// `trace_callMany`.
+    pub fn call_virtual_parity_trace(
+        &self, txs: Vec<(SignedTransaction, EstimateRequest)>, space: Space,
+        epoch_id: &H256, epoch_size: usize, options: ParityTraceOptions,
+    ) -> RpcResult<Vec<(ExecutionOutcome, ParityTrace)>> {
+        let (mut state, mut env) =
+            self.make_virtual_state(epoch_id, epoch_size, space)?;
+        let spec = self.machine.spec(env.number, env.epoch_height);
+
+        let mut results = Vec::with_capacity(txs.len());
+        for (tx, request) in txs {
+            self.verify_virtual_tx(&tx, &env, &spec)?;
+            env.gas_limit = tx.gas().clone();
+            let mut ex = EstimationContext::new(
+                &mut state,
+                &env,
+                self.machine.as_ref(),
+                &spec,
+            );
+            let r = ex.transact_parity_traced(tx, request, options)?;
+            trace!("Execution result {:?}", r.0);
+            results.push(r);
+        }
+        Ok(results)
+    }
+
+    /// Execute the blocks of calls of a simulation in `space` on top of the
+    /// state of `epoch_id`, keeping the effects of every call.
+    pub fn simulate_virtual(
+        &self, blocks: Vec<SimulateBlock>, space: Space, epoch_id: &H256,
+        epoch_size: usize, validation: bool,
+    ) -> RpcResult<Vec<SimulatedBlock>> {
+        let (mut state, mut env) =
+            self.make_virtual_state(epoch_id, epoch_size, space)?;
+        simulate_blocks(
+            &mut state,
+            &mut env,
+            self.machine.as_ref(),
+            space,
+            blocks,
+            validation,
+            |tx, env, spec| self.verify_virtual_tx(tx, env, spec),
+        )
+    }
+
+    /// Build the state and environment a virtual call of `tx` is executed
+    /// against: the state of `epoch_id`, in the block following the epoch,
+    /// with `overrides` applied on top.
+    fn make_virtual_call_state(
+        &self, tx: &SignedTransaction, epoch_id: &H256, epoch_size: usize,
+        overrides: &CallOverrides,
+    ) -> RpcResult<(State, Env)> {
+        let (mut state, mut env) =
+            self.make_virtual_state(epoch_id, epoch_size, tx.space())?;
+        let spec = self.machine.spec(env.number, env.epoch_height);
+        self.verify_virtual_tx(tx, &env, &spec)?;
+        env.gas_limit = tx.gas().clone();
+        overrides.apply(&mut state, &mut env, tx.space())?;
+        Ok((state, env))
+    }
+
+    /// Build the state of `epoch_id` and the environment of the block
+    /// following the epoch for virtual calls in `space`.
+    fn make_virtual_state(
+        &self, epoch_id: &H256, epoch_size: usize, space: Space,
+    ) -> RpcResult<(State, Env)> {
         let best_block_header = self.data_man.block_header_by_hash(epoch_id);
         if best_block_header.is_none() {
             bail!("invalid epoch id");
@@ -1508,26 +1702,13 @@ impl ConsensusExecutionHandler {
             Some(v) => v.start_block_number + epoch_size as u64,
             None => bail!("cannot obtain the execution context. Database is potentially corrupted!"),
         };
-        let spec = self.machine.spec(start_block_number, block_height);
-        let transitions = &self.machine.params().transition_heights;
-
-        invalid_params_check(
-            "tx",
-            self.verification_config.verify_transaction_common(
-                tx,
-                AllChainID::fake_for_virtual(tx.chain_id().unwrap_or(1)),
-                block_height,
-                transitions,
-                VerifyTxMode::Local(VerifyTxLocalMode::Full, &spec),
-            ),
-        )?;
 
         // Keep the lock until we get the desired State, otherwise the State may
         // expire.
         let state_availability_boundary =
             self.data_man.state_availability_boundary.read();
 
-        let state_space = match tx.space() {
+        let state_space = match space {
             Space::Native => None,
             Space::Ethereum => Some(Space::Ethereum),
         };
@@ -1541,7 +1722,7 @@ impl ConsensusExecutionHandler {
         let state_index = self.data_man.get_state_readonly_index(epoch_id);
         trace!("best_block_header: {:?}", best_block_header);
         let time_stamp = best_block_header.timestamp();
-        let mut state = State::new(StateDb::new(
+        let state = State::new(StateDb::new(
             self.data_man
                 .storage_manager
                 .get_state_no_commit(
@@ -1555,7 +1736,7 @@ impl ConsensusExecutionHandler {
 
         let miner = {
             let mut address = H160::random();
-            if tx.space() == Space::Native {
+            if space == Space::Native {
                 address.set_user_account_type_bits();
             }
             address
@@ -1573,7 +1754,7 @@ impl ConsensusExecutionHandler {
             difficulty: Default::default(),
             accumulated_gas_used: U256::zero(),
             last_hash: epoch_id.clone(),
-            gas_limit: tx.gas().clone(),
+            gas_limit: U256::zero(),
             epoch_height: block_height,
             transaction_epoch_bound: self
                 .verification_config
@@ -1581,28 +1762,81 @@ impl ConsensusExecutionHandler {
             base_gas_price,
             burnt_gas_price,
         };
-        let spec = self.machine.spec(env.number, env.epoch_height);
-        let mut ex = EstimationContext::new(
-            &mut state,
-            &env,
-            self.machine.as_ref(),
-            &spec,
-        );
+        Ok((state, env))
+    }
 
-        let r = ex.transact_virtual(tx.clone(), request);
-        trace!("Execution result {:?}", r);
-        Ok(r?)
+    fn verify_virtual_tx(
+        &self, tx: &SignedTransaction, env: &Env, spec: &Spec,
+    ) -> RpcResult<()> {
+        let transitions = &self.machine.params().transition_heights;
+        invalid_params_check(
+            "tx",
+            self.verification_config.verify_transaction_common(
+                tx,
+                AllChainID::fake_for_virtual(tx.chain_id().unwrap_or(1)),
+                env.epoch_height,
+                transitions,
+                VerifyTxMode::Local(VerifyTxLocalMode::Full, spec),
+            ),
+        )
     }
 
     pub fn collect_epoch_geth_trace(
         &self, epoch_block_hashes: Vec<H256>, tx_hash: Option<H256>,
         opts: GethDebugTracingOptions,
     ) -> RpcResult<Vec<GethTraceWithHash>> {
+        let (epoch_blocks, mut state, start_block_number) =
+            self.make_epoch_replay_state(&epoch_block_hashes)?;
+
+        self.execute_epoch_tx_to_collect_trace(
+            &mut state,
+            &epoch_blocks,
+            start_block_number,
+            tx_hash,
+            opts,
+        )
+        .map_err(|err| err.into())
+    }
+
+    /// Replay the transactions of the epoch with parity-style tracers. Only
+    /// the transaction `tx_hash` is traced if it is specified.
+    pub fn collect_epoch_parity_trace(
+        &self, epoch_block_hashes: Vec<H256>, tx_hash: Option<H256>,
+        options: ParityTraceOptions,
+    ) -> RpcResult<Vec<ParityTraceWithHash>> {
+        let (epoch_blocks, mut state, start_block_number) =
+            self.make_epoch_replay_state(&epoch_block_hashes)?;
+        let epoch_id = epoch_blocks.last().unwrap().hash();
+
+        let mut answer = vec![];
+        let virtual_call = VirtualCall::ParityTrace(ParityTask {
+            tx_hash,
+            options,
+            answer: &mut answer,
+        });
+
+        self.process_epoch_transactions(
+            epoch_id,
+            &mut state,
+            &epoch_blocks,
+            start_block_number,
+            false,
+            Some(virtual_call),
+        )?;
+
+        Ok(answer)
+    }
+
+    /// Load the blocks of an epoch with the state of its parent main block
+    /// and the number of its first block, to replay its transactions.
+    fn make_epoch_replay_state(
+        &self, epoch_block_hashes: &Vec<H256>,
+    ) -> RpcResult<(Vec<Arc<Block>>, State, u64)> {
         // Get blocks in this epoch after skip checking
         let epoch_blocks = self
             .data_man
             .blocks_by_hash_list(
-                &epoch_block_hashes,
+                epoch_block_hashes,
                 true, /* update_cache */
             )
             .expect("blocks exist");
@@ -1637,7 +1871,7 @@ impl ConsensusExecutionHandler {
             )?
             .ok_or("state deleted")?;
         let state_db = StateDb::new(storage);
-        let mut state = State::new(state_db)?;
+        let state = State::new(state_db)?;
 
         let start_block_number = self
             .data_man
@@ -1645,14 +1879,7 @@ impl ConsensusExecutionHandler {
             .map(|v| v.start_block_number)
             .expect("should exist");
 
-        self.execute_epoch_tx_to_collect_trace(
-            &mut state,
-            &epoch_blocks,
-            start_block_number,
-            tx_hash,
-            opts,
-        )
-        .map_err(|err| err.into())
+        Ok((epoch_blocks, state, start_block_number))
     }
 
     /// Execute transactions in the epoch to collect traces.
@@ -17,7 +17,10 @@ use crate::{
         debug_recompute::log_invalid_state_root, outlier_cache::OutlierCache,
         pastset_cache::PastSetCache, MaybeExecutedTxExtraInfo, TransactionInfo,
     },
-    pow::{target_difficulty, PowComputer, ProofOfWorkConfig},
+    pow::{
+        next_randomx_seed_height, target_difficulty, PowComputer,
+        ProofOfWorkConfig,
+    },
     state_exposer::{ConsensusGraphBlockExecutionState, STATE_EXPOSER},
     verification::VerificationConfig,
 };
@@ -27,6 +30,7 @@ use hibitset::{BitSet, BitSetLike, DrainableBitSet};
 use link_cut_tree::{CaterpillarMinLinkCutTree, SizeMinLinkCutTree};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
+use mazze_executor::machine::Machine;
 use mazze_internal_common::{
     consensus_api::StateMaintenanceTrait, EpochExecutionCommitment,
 };
@@ -429,6 +433,7 @@ pub struct ConsensusGraphInner {
     pub inner_conf: ConsensusInnerConfig,
     pub pow_config: ProofOfWorkConfig,
     pub pow: Arc<PowComputer>,
+    machine: Arc<Machine>,
     //executor: Arc<ConsensusExecutor>,
     /// This slab hold consensus graph node data and the array index is the
     /// internal index.
@@ -577,8 +582,9 @@ impl ConsensusGraphNode {
 impl ConsensusGraphInner {
     pub fn with_era_genesis(
         pow_config: ProofOfWorkConfig, pow: Arc<PowComputer>,
-        data_man: Arc<BlockDataManager>, inner_conf: ConsensusInnerConfig,
-        cur_era_genesis_block_hash: &H256, cur_era_stable_block_hash: &H256,
+        machine: Arc<Machine>, data_man: Arc<BlockDataManager>,
+        inner_conf: ConsensusInnerConfig, cur_era_genesis_block_hash: &H256,
+        cur_era_stable_block_hash: &H256,
     ) -> Self {
         let genesis_block_header = data_man
             .block_header_by_hash(cur_era_genesis_block_hash)
@@ -612,6 +618,7 @@ impl ConsensusGraphInner {
             invalid_block_queue: BinaryHeap::new(),
             pow_config,
             pow,
+            machine,
             current_difficulty: initial_difficulty.into(),
             data_man: data_man.clone(),
             inner_conf,
@@ -708,11 +715,18 @@ impl ConsensusGraphInner {
     fn persist_epoch_set_hashes(&mut self, main_index: usize) {
         let height = self.main_index_to_height(main_index);
         let arena_index = self.main_chain[main_index];
-        let epoch_set_hashes = self
+        let epoch_set_hashes: Vec<H256> = self
             .get_ordered_executable_epoch_blocks(arena_index)
             .iter()
             .map(|arena_index| self.arena[*arena_index].hash)
             .collect();
+        // The first epoch of a RandomX epoch seeds the next RandomX epoch, so
+        // its context can be built well before it is needed.
+        if height == next_randomx_seed_height(height) {
+            if let Some(seed_hash) = epoch_set_hashes.last() {
+                self.pow.prepare_seed_hash(seed_hash);
+            }
+        }
         let skipped_set_hashes = self
             .get_or_compute_skipped_epoch_blocks(arena_index)
             .clone();
@@ -1668,6 +1682,9 @@ impl ConsensusGraphInner {
                     .data_man
                     .db_manager
                     .get_current_seed_hash(block_header.height()),
+                self.machine
+                    .params()
+                    .pow_lower_bound_enabled(block_header.height()),
             ));
         let is_heavy = pow_quality
             >= U512::from(self.inner_conf.heavy_block_difficulty_ratio)
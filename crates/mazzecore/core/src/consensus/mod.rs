@@ -38,11 +38,14 @@ use mazze_execute_helper::{
         recover_phantom_traces, ActionType, BlockExecTraces, LocalizedTrace,
         TraceFilter, TransactionExecTraces,
     },
+    overrides::CallOverrides,
+    parity_trace::{ParityTrace, ParityTraceOptions, ParityTraceWithHash},
     phantom_tx::build_bloom_and_recover_phantom,
+    simulation::{SimulateBlock, SimulatedBlock},
 };
 use mazze_executor::{executive::ExecutionOutcome, state::State};
 
-use alloy_rpc_types_trace::geth::GethDebugTracingOptions;
+use alloy_rpc_types_trace::geth::{GethDebugTracingOptions, GethTrace};
 use either::Either;
 use itertools::Itertools;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
@@ -58,7 +61,9 @@ use mazze_parameters::{
 };
 use mazze_statedb::StateDb;
 use mazze_storage::{
-    state::StateTrait, state_manager::StateManagerTrait, StorageState,
+    state::{StateDbGetOriginalMethods, StateTrait},
+    state_manager::StateManagerTrait,
+    StateProofWithRoots, StorageState,
 };
 use mazze_types::{AddressWithSpace, AllChainID, Bloom, Space, H256, U256};
 use metrics::{
@@ -71,8 +76,8 @@ use primitives::{
     filter::{FilterError, LogFilter},
     log_entry::LocalizedLogEntry,
     receipt::Receipt,
-    BlockHeader, EpochId, EpochNumber, SignedTransaction, TransactionIndex,
-    TransactionStatus,
+    AccessList, BlockHeader, EpochId, EpochNumber, SignedTransaction,
+    StorageKeyWithSpace, TransactionIndex, TransactionStatus,
 };
 use rayon::prelude::*;
 use std::{
@@ -258,6 +263,7 @@ impl ConsensusGraph {
             Arc::new(RwLock::new(ConsensusGraphInner::with_era_genesis(
                 pow_config,
                 pow.clone(),
+                txpool.machine(),
                 data_man.clone(),
                 conf.inner_conf.clone(),
                 era_genesis_block_hash,
@@ -1385,7 +1391,7 @@ impl ConsensusGraph {
 
     pub fn call_virtual(
         &self, tx: &SignedTransaction, epoch: EpochNumber,
-        request: EstimateRequest,
+        request: EstimateRequest, overrides: &CallOverrides,
     ) -> RpcResult<(ExecutionOutcome, EstimateExt)> {
         // only allow to call against stated epoch
         self.validate_stated_epoch(&epoch)?;
@@ -1397,7 +1403,86 @@ impl ConsensusGraph {
             bail!("cannot get block hashes in the specified epoch, maybe it does not exist?");
         };
         self.executor
-            .call_virtual(tx, &epoch_id, epoch_size, request)
+            .call_virtual(tx, &epoch_id, epoch_size, request, overrides)
+    }
+
+    /// Like `call_virtual`, but with a geth tracer configured by `opts`
+    /// attached to the execution.
+    pub fn call_virtual_geth_trace(
+        &self, tx: &SignedTransaction, epoch: EpochNumber,
+        request: EstimateRequest, overrides: &CallOverrides,
+        opts: GethDebugTracingOptions,
+    ) -> RpcResult<(ExecutionOutcome, Option<GethTrace>)> {
+        // only allow to call against stated epoch
+        self.validate_stated_epoch(&epoch)?;
+        let (epoch_id, epoch_size) = if let Ok(v) =
+            self.get_block_hashes_by_epoch(epoch)
+        {
+            (v.last().expect("main block always exist").clone(), v.len())
+        } else {
+            bail!("cannot get block hashes in the specified epoch, maybe it does not exist?");
+        };
+        self.executor.call_virtual_geth_trace(
+            tx, &epoch_id, epoch_size, request, overrides, opts,
+        )
+    }
+
+    /// Simulate `blocks` of calls in `space` one after another on top of
+    /// `epoch`. See `mazze_execute_helper::simulation`.
+    pub fn simulate_virtual(
+        &self, blocks: Vec<SimulateBlock>, space: Space, epoch: EpochNumber,
+        validation: bool,
+    ) -> RpcResult<Vec<SimulatedBlock>> {
+        // only allow to call against stated epoch
+        self.validate_stated_epoch(&epoch)?;
+        let (epoch_id, epoch_size) = if let Ok(v) =
+            self.get_block_hashes_by_epoch(epoch)
+        {
+            (v.last().expect("main block always exist").clone(), v.len())
+        } else {
+            bail!("cannot get block hashes in the specified epoch, maybe it does not exist?");
+        };
+        self.executor
+            .simulate_virtual(blocks, space, &epoch_id, epoch_size, validation)
+    }
+
+    /// Generate the access list of `tx` executed as a virtual call on top of
+    /// `epoch`, along with the outcome of executing it with this list.
+    pub fn create_access_list_virtual(
+        &self, tx: &SignedTransaction, epoch: EpochNumber,
+        request: EstimateRequest,
+    ) -> RpcResult<(AccessList, ExecutionOutcome)> {
+        // only allow to call against stated epoch
+        self.validate_stated_epoch(&epoch)?;
+        let (epoch_id, epoch_size) = if let Ok(v) =
+            self.get_block_hashes_by_epoch(epoch)
+        {
+            (v.last().expect("main block always exist").clone(), v.len())
+        } else {
+            bail!("cannot get block hashes in the specified epoch, maybe it does not exist?");
+        };
+        self.executor
+            .create_access_list_virtual(tx, &epoch_id, epoch_size, request)
+    }
+
+    /// Execute `txs` in `space` one after another on top of `epoch` with
+    /// parity-style tracers, as in `trace_callMany`.
+    pub fn call_virtual_parity_trace(
+        &self, txs: Vec<(SignedTransaction, EstimateRequest)>, space: Space,
+        epoch: EpochNumber, options: ParityTraceOptions,
+    ) -> RpcResult<Vec<(ExecutionOutcome, ParityTrace)>> {
+        // only allow to call against stated epoch
+        self.validate_stated_epoch(&epoch)?;
+        let (epoch_id, epoch_size) = if let Ok(v) =
+            self.get_block_hashes_by_epoch(epoch)
+        {
+            (v.last().expect("main block always exist").clone(), v.len())
+        } else {
+            bail!("cannot get block hashes in the specified epoch, maybe it does not exist?");
+        };
+        self.executor.call_virtual_parity_trace(
+            txs, space, &epoch_id, epoch_size, options,
+        )
     }
 
     pub fn collect_epoch_geth_trace(
@@ -1423,6 +1508,31 @@ impl ConsensusGraph {
         )
     }
 
+    /// Replay the transactions of the epoch `epoch_num` with parity-style
+    /// tracers. Only the transaction `tx_hash` is traced if it is specified.
+    pub fn collect_epoch_parity_trace(
+        &self, epoch_num: u64, tx_hash: Option<H256>,
+        options: ParityTraceOptions,
+    ) -> RpcResult<Vec<ParityTraceWithHash>> {
+        // only allow to call against stated epoch
+        let epoch = EpochNumber::Number(epoch_num);
+        self.validate_stated_epoch(&epoch)?;
+
+        let epoch_block_hashes = if let Ok(v) =
+            self.get_block_hashes_by_epoch(epoch)
+        {
+            v
+        } else {
+            bail!("cannot get block hashes in the specified epoch, maybe it does not exist?");
+        };
+
+        self.executor.collect_epoch_parity_trace(
+            epoch_block_hashes,
+            tx_hash,
+            options,
+        )
+    }
+
     /// Get the number of processed blocks (i.e., the number of calls to
     /// on_new_block()
     pub fn get_processed_block_count(&self) -> usize {
@@ -2032,6 +2142,65 @@ impl ConsensusGraph {
         )
     }
 
+    /// Get the values under `keys` in the state of `epoch_number`, each with
+    /// a proof that can be verified offline against the state root hashes
+    /// committed in the block headers (see `StateProofWithRoots::verify`).
+    /// Also return the height of the epoch.
+    pub fn get_state_entries_with_proof(
+        &self, epoch_number: EpochNumber, keys: &[StorageKeyWithSpace],
+        rpc_param_name: &str,
+    ) -> RpcResult<(u64, Vec<(Option<Box<[u8]>>, StateProofWithRoots)>)> {
+        invalid_params_check(
+            rpc_param_name,
+            self.validate_stated_epoch(&epoch_number),
+        )?;
+        let height = invalid_params_check(
+            rpc_param_name,
+            self.get_height_from_epoch_number(epoch_number),
+        )?;
+        let hash = self.inner.read().get_main_hash_from_epoch_number(height)?;
+        let state = self.get_storage_state_by_height_and_hash(height, &hash)?;
+        let state_root = state.get_state_root()?.state_root;
+
+        // The key padding of the intermediate trie is derived from the state
+        // root of the epoch a snapshot period before.
+        let snapshot_epoch_count =
+            self.data_man.get_snapshot_epoch_count() as u64;
+        let prev_snapshot_state_root = if height > snapshot_epoch_count {
+            let prev_hash = self.inner.read().get_main_hash_from_epoch_number(
+                height - snapshot_epoch_count,
+            )?;
+            let commitment = self
+                .data_man
+                .get_epoch_execution_commitment_with_db(&prev_hash)
+                .ok_or_else(|| {
+                    format!(
+                        "Execution commitment of epoch (number={:?} hash={:?}) does not exist",
+                        height - snapshot_epoch_count,
+                        prev_hash
+                    )
+                })?;
+            Some(commitment.state_root_with_aux_info.state_root)
+        } else {
+            None
+        };
+
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            let (value, state_proof) =
+                state.get_original_raw_with_proof(*key)?;
+            entries.push((
+                value,
+                StateProofWithRoots {
+                    state_proof,
+                    state_root: state_root.clone(),
+                    prev_snapshot_state_root: prev_snapshot_state_root.clone(),
+                },
+            ));
+        }
+        Ok((height, entries))
+    }
+
     fn get_state_db_by_epoch_number_with_space(
         &self, epoch_number: EpochNumber, rpc_param_name: &str,
         space: Option<Space>,
@@ -2440,6 +2609,7 @@ impl ConsensusGraphTrait for ConsensusGraph {
         let new_consensus_inner = ConsensusGraphInner::with_era_genesis(
             old_consensus_inner.pow_config.clone(),
             old_consensus_inner.pow.clone(),
+            self.txpool.machine(),
             self.data_man.clone(),
             old_consensus_inner.inner_conf.clone(),
             &cur_era_genesis_hash,
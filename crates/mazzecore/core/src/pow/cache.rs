@@ -1,165 +1,366 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+use malloc_size_of::{MallocShallowSizeOf, MallocSizeOf, MallocSizeOfOps};
+use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use parking_lot::Mutex;
 use rust_randomx::{Context as RandomXContext, Hasher};
 
 use std::str::FromStr;
 
-use std::{collections::HashMap, slice, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    slice,
+    sync::Arc,
+    thread,
+};
 
-use crossbeam_deque::{Steal, Stealer, Worker};
 use parking_lot::RwLock;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 const INITIAL_VMS_PER_STAGE: usize = 4;
+/// Idle VMs kept per seed hash, further VMs are dropped when returned.
+const MAX_IDLE_VMS_PER_SEED: usize = 64;
+
+/// Memory allocated by the RandomX library for a light context (the Argon2
+/// cache) and for a VM (mostly its scratchpad). It is invisible to the
+/// malloc size accounting, so it is estimated.
+const RANDOMX_CONTEXT_BYTES: usize = 256 << 20;
+const RANDOMX_VM_BYTES: usize = 2 << 20;
 
 pub type H256 = [u8; 32];
 
 pub struct RandomXCacheBuilder {
-    global_queue: Worker<Arc<Hasher>>,
-    stealers: Vec<Stealer<Arc<Hasher>>>,
-    context: RwLock<Arc<RandomXContext>>,
+    /// Contexts of the recently used seed hashes, so that headers from
+    /// different RandomX epochs can be verified without rebuilding them.
+    caches: Arc<Mutex<SeedCaches>>,
+    /// The most recently requested seed hash.
     current_seed_hash: RwLock<H256>,
+    /// Shared with the background thread building contexts ahead of time.
+    prefetch: Arc<Mutex<PrefetchState>>,
 }
 
-pub struct VMHandle {
-    vm: Option<Arc<Hasher>>,
-    cache_builder: Arc<RandomXCacheBuilder>,
+/// The context of a seed hash and its VMs not in use.
+struct SeedCache {
+    context: Arc<RandomXContext>,
+    vms: Vec<Hasher>,
 }
 
-impl RandomXCacheBuilder {
-    pub fn new(seed_hash: H256) -> Arc<Self> {
-        let global_queue = Worker::new_fifo();
-        let stealers = vec![global_queue.stealer()];
-
-        let context = Arc::new(RandomXContext::new(&seed_hash, false));
+impl MallocSizeOf for SeedCache {
+    fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
+        RANDOMX_CONTEXT_BYTES
+            + self.vms.len() * RANDOMX_VM_BYTES
+            + self.vms.shallow_size_of(ops)
+    }
+}
 
-        let builder = Arc::new(RandomXCacheBuilder {
-            global_queue,
-            stealers,
-            context: RwLock::new(context),
-            current_seed_hash: RwLock::new(seed_hash),
-        });
+/// Seed caches with least recently used eviction. The context of an evicted
+/// seed hash is freed once the VMs using it are dropped.
+#[derive(DeriveMallocSizeOf)]
+struct SeedCaches {
+    capacity: usize,
+    /// Seed hashes from the least to the most recently used.
+    meta: VecDeque<H256>,
+    cache: HashMap<H256, SeedCache>,
+}
 
-        // Initialize with some VMs
-        for _ in 0..INITIAL_VMS_PER_STAGE {
-            let vm = builder.initialize_new_vm();
-            builder.global_queue.push(vm);
+impl SeedCaches {
+    fn new(capacity: usize) -> Self {
+        SeedCaches {
+            capacity: capacity.max(1),
+            meta: Default::default(),
+            cache: Default::default(),
         }
+    }
 
-        builder
+    fn contains(&self, seed_hash: &H256) -> bool {
+        self.cache.contains_key(seed_hash)
     }
 
-    fn initialize_new_vm(&self) -> Arc<Hasher> {
-        Arc::new(Hasher::new(self.context.read().clone()))
+    /// Returns the cache of `seed_hash` and marks it as the most recently
+    /// used.
+    fn get_mut(&mut self, seed_hash: &H256) -> Option<&mut SeedCache> {
+        let cache = self.cache.get_mut(seed_hash)?;
+        if let Some(index) = self.meta.iter().position(|h| h == seed_hash) {
+            self.meta.remove(index);
+        }
+        self.meta.push_back(*seed_hash);
+        Some(cache)
+    }
+
+    /// Inserts the context of `seed_hash` unless it is cached already, and
+    /// returns the cached context.
+    fn insert(
+        &mut self, seed_hash: H256, context: Arc<RandomXContext>,
+    ) -> Arc<RandomXContext> {
+        if let Some(cache) = self.get_mut(&seed_hash) {
+            return cache.context.clone();
+        }
+        while self.meta.len() >= self.capacity {
+            if let Some(evicted) = self.meta.pop_front() {
+                debug!("Evicting RandomX Context for seed hash: {:?}", evicted);
+                self.cache.remove(&evicted);
+            }
+        }
+        self.meta.push_back(seed_hash);
+        self.cache.insert(
+            seed_hash,
+            SeedCache {
+                context: context.clone(),
+                vms: Vec::new(),
+            },
+        );
+        context
     }
+}
 
-    fn update_context(&self, seed_hash: &H256) {
-        debug!("Updating RandomX Context for seed hash: {:?}", seed_hash);
-        let mut current_hash = self.current_seed_hash.write();
-        if *current_hash != *seed_hash {
-            // Create new context with the new seed hash
-            let new_context = Arc::new(RandomXContext::new(seed_hash, false));
+/// Requests to the background thread building the next context. Only the
+/// latest request is kept, so at most one context is built at a time.
+#[derive(Default)]
+struct PrefetchState {
+    requested: Option<H256>,
+    running: bool,
+}
 
-            // Update the context with new one
-            *self.context.write() = new_context;
+pub struct VMHandle {
+    vm: Option<Hasher>,
+    seed_hash: H256,
+    cache_builder: Arc<RandomXCacheBuilder>,
+}
+
+impl RandomXCacheBuilder {
+    pub fn new(seed_hash: H256, capacity: usize) -> Arc<Self> {
+        let mut caches = SeedCaches::new(capacity);
+        let context = caches.insert(
+            seed_hash,
+            Arc::new(RandomXContext::new(&seed_hash, false)),
+        );
 
-            // Update seed hash
-            *current_hash = *seed_hash;
+        // Initialize with some VMs
+        let seed_cache = caches.get_mut(&seed_hash).expect("just inserted");
+        for _ in 0..INITIAL_VMS_PER_STAGE {
+            seed_cache.vms.push(Hasher::new(context.clone()));
         }
-    }
 
-    pub fn get_seed_hash(&self) -> H256 {
-        self.current_seed_hash.read().clone()
+        Arc::new(RandomXCacheBuilder {
+            caches: Arc::new(Mutex::new(caches)),
+            current_seed_hash: RwLock::new(seed_hash),
+            prefetch: Arc::new(Mutex::new(PrefetchState::default())),
+        })
     }
 
-    fn acquire_vm(&self) -> Arc<Hasher> {
-        let current_context = self.context.read().clone();
-        let current_key = current_context.key();
+    /// Builds the context of `seed_hash` in a background thread and adds it
+    /// to the cache, unless it is cached already.
+    pub fn prepare_context(&self, seed_hash: H256) {
+        if self.is_prepared(&seed_hash) {
+            return;
+        }
 
-        // Try to get a VM from our queue
-        if let Some(vm) = self.global_queue.pop() {
-            // Check if VM needs an update
-            let vm_key = vm.context().key();
-            if vm_key == current_key {
-                return vm; // VM is up-to-date
-            }
+        let mut prefetch = self.prefetch.lock();
+        prefetch.requested = Some(seed_hash);
+        if prefetch.running {
+            // The running thread picks up the request when it is done.
+            return;
+        }
+        prefetch.running = true;
+        drop(prefetch);
 
-            // VM needs an update - clone it and update its context
-            // Note: We need to clone because Arc prevents mutation
-            let new_vm = Hasher::new(current_context);
-            return Arc::new(new_vm);
+        let prefetch = Arc::clone(&self.prefetch);
+        let caches = Arc::clone(&self.caches);
+        let spawned = thread::Builder::new()
+            .name("RandomX prefetch".into())
+            .spawn(move || Self::build_requested_contexts(&prefetch, &caches));
+        if let Err(e) = spawned {
+            warn!("Failed to spawn RandomX prefetch thread: {}", e);
+            self.prefetch.lock().running = false;
         }
+    }
 
-        // Try to steal from other workers
-        for stealer in &self.stealers {
-            match stealer.steal() {
-                Steal::Success(vm) => {
-                    let vm_key = vm.context().key();
-                    if vm_key == current_key {
-                        return vm;
-                    }
-                    let new_vm = Hasher::new(current_context.clone());
-                    return Arc::new(new_vm);
-                }
-                Steal::Empty => continue,
-                Steal::Retry => {
-                    if let Steal::Success(vm) = stealer.steal() {
-                        let vm_key = vm.context().key();
-                        if vm_key == current_key {
-                            return vm;
-                        }
-                        let new_vm = Hasher::new(current_context.clone());
-                        return Arc::new(new_vm);
+    fn is_prepared(&self, seed_hash: &H256) -> bool {
+        self.caches.lock().contains(seed_hash)
+    }
+
+    fn build_requested_contexts(
+        prefetch: &Mutex<PrefetchState>, caches: &Mutex<SeedCaches>,
+    ) {
+        loop {
+            let seed_hash = {
+                let mut prefetch = prefetch.lock();
+                match prefetch.requested.take() {
+                    Some(seed_hash) => seed_hash,
+                    None => {
+                        prefetch.running = false;
+                        return;
                     }
                 }
+            };
+            if caches.lock().contains(&seed_hash) {
+                continue;
             }
+
+            debug!("Preparing RandomX Context for seed hash: {:?}", seed_hash);
+            let context = Arc::new(RandomXContext::new(&seed_hash, false));
+            caches.lock().insert(seed_hash, context);
         }
+    }
+
+    pub fn get_seed_hash(&self) -> H256 {
+        self.current_seed_hash.read().clone()
+    }
 
-        // No VM available, create a new one
-        self.initialize_new_vm()
+    /// Cached seed hashes, from the least to the most recently used.
+    pub fn cached_seed_hashes(&self) -> Vec<H256> {
+        self.caches.lock().meta.iter().cloned().collect()
     }
 
-    fn return_vm_handler(&self, vm: Arc<Hasher>) {
-        // Optionally, we could check if VM's context is current before returning it
-        let current_context = self.context.read().clone();
-        let current_key = current_context.key();
-        let vm_key = vm.context().key();
+    fn acquire_vm(&self, seed_hash: &H256) -> Hasher {
+        let context = {
+            let mut caches = self.caches.lock();
+            match caches.get_mut(seed_hash) {
+                Some(cache) => match cache.vms.pop() {
+                    Some(vm) => return vm,
+                    None => Some(cache.context.clone()),
+                },
+                None => None,
+            }
+        };
+
+        // A missing context is built without holding the lock, so that VMs
+        // of other seed hashes can be acquired meanwhile.
+        let context = context.unwrap_or_else(|| {
+            debug!("Creating RandomX Context for seed hash: {:?}", seed_hash);
+            let context = Arc::new(RandomXContext::new(seed_hash, false));
+            self.caches.lock().insert(*seed_hash, context)
+        });
+        Hasher::new(context)
+    }
 
-        if vm_key == current_key {
-            // VM is current, return it to the queue
-            self.global_queue.push(vm);
+    fn return_vm_handler(&self, seed_hash: &H256, vm: Hasher) {
+        // VMs of evicted seed hashes are dropped
+        if let Some(cache) = self.caches.lock().cache.get_mut(seed_hash) {
+            if cache.vms.len() < MAX_IDLE_VMS_PER_SEED {
+                cache.vms.push(vm);
+            }
         }
-        // If VM is outdated, let it be dropped
     }
 
     pub fn get_vm_handler(self: &Arc<Self>, seed_hash: &H256) -> VMHandle {
-        // Update context if seed hash changed
-        self.update_context(seed_hash);
+        *self.current_seed_hash.write() = *seed_hash;
 
         VMHandle {
-            vm: Some(self.acquire_vm()),
+            vm: Some(self.acquire_vm(seed_hash)),
+            seed_hash: *seed_hash,
             cache_builder: Arc::clone(self),
         }
     }
+}
 
-    /*
-       Hasher -> has update function that takes a context
-       We're storing context locally, should we? No.
-    */
+impl MallocSizeOf for RandomXCacheBuilder {
+    fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.caches.lock().size_of(ops)
+    }
 }
 
 impl VMHandle {
     pub fn get_vm(&self) -> &Hasher {
-        self.vm.as_ref().expect("VM should exist").as_ref()
+        self.vm.as_ref().expect("VM should exist")
     }
 }
 
 impl Drop for VMHandle {
     fn drop(&mut self) {
         if let Some(vm) = self.vm.take() {
-            self.cache_builder.return_vm_handler(vm);
+            self.cache_builder.return_vm_handler(&self.seed_hash, vm);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use malloc_size_of::new_malloc_size_ops;
+    use std::time::{Duration, Instant};
+
+    fn wait_for_prepared(builder: &RandomXCacheBuilder, seed_hash: H256) {
+        let start = Instant::now();
+        while !builder.is_prepared(&seed_hash) {
+            assert!(
+                start.elapsed() < Duration::from_secs(60),
+                "context not prepared"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn context_of(
+        builder: &RandomXCacheBuilder, seed_hash: &H256,
+    ) -> Arc<RandomXContext> {
+        builder.caches.lock().cache[seed_hash].context.clone()
+    }
+
+    #[test]
+    fn test_switch_to_prepared_context() {
+        let (seed_0, seed_1, seed_2) = ([0u8; 32], [1u8; 32], [2u8; 32]);
+        let builder = RandomXCacheBuilder::new(seed_0, 2);
+
+        // A cached seed hash is not prepared again.
+        builder.prepare_context(seed_0);
+        assert!(builder.prefetch.lock().requested.is_none());
+
+        builder.prepare_context(seed_1);
+        wait_for_prepared(&builder, seed_1);
+        let prepared = context_of(&builder, &seed_1);
+
+        // At the boundary, the prepared context is used, and the previous
+        // context is kept for late blocks of the old epoch.
+        let input = [7u8; 64];
+        let hash_1 = builder.get_vm_handler(&seed_1).get_vm().hash(&input);
+        assert_eq!(builder.get_seed_hash(), seed_1);
+        assert!(Arc::ptr_eq(&context_of(&builder, &seed_1), &prepared));
+        assert_eq!(hash_1, Hasher::new(prepared.clone()).hash(&input));
+        assert_eq!(builder.cached_seed_hashes(), vec![seed_0, seed_1]);
+
+        builder.prepare_context(seed_2);
+        wait_for_prepared(&builder, seed_2);
+        assert_eq!(builder.cached_seed_hashes(), vec![seed_1, seed_2]);
+        let hash_2 = builder.get_vm_handler(&seed_2).get_vm().hash(&input);
+        assert_ne!(hash_1, hash_2);
+    }
+
+    #[test]
+    fn test_multiple_seed_hashes() {
+        let (seed_0, seed_1, seed_2) = ([0u8; 32], [1u8; 32], [2u8; 32]);
+        let builder = RandomXCacheBuilder::new(seed_0, 2);
+        let input = [7u8; 64];
+        let hash_0 = builder.get_vm_handler(&seed_0).get_vm().hash(&input);
+        let hash_1 = builder.get_vm_handler(&seed_1).get_vm().hash(&input);
+        let context_0 = context_of(&builder, &seed_0);
+        let context_1 = context_of(&builder, &seed_1);
+
+        // Alternating between cached seed hashes reuses their contexts, and
+        // VMs of both can be used at the same time.
+        for _ in 0..3 {
+            let vm_0 = builder.get_vm_handler(&seed_0);
+            let vm_1 = builder.get_vm_handler(&seed_1);
+            assert_eq!(vm_0.get_vm().hash(&input), hash_0);
+            assert_eq!(vm_1.get_vm().hash(&input), hash_1);
         }
+        assert!(Arc::ptr_eq(&context_of(&builder, &seed_0), &context_0));
+        assert!(Arc::ptr_eq(&context_of(&builder, &seed_1), &context_1));
+
+        // The least recently used seed hash is evicted, while VMs using it
+        // remain valid until they are dropped.
+        let vm_0 = builder.get_vm_handler(&seed_0);
+        let vm_1 = builder.get_vm_handler(&seed_1);
+        builder.get_vm_handler(&seed_2);
+        assert_eq!(builder.cached_seed_hashes(), vec![seed_1, seed_2]);
+        assert_eq!(vm_0.get_vm().hash(&input), hash_0);
+        drop(vm_0);
+        drop(vm_1);
+        assert!(!builder.caches.lock().contains(&seed_0));
+        assert!(!builder.caches.lock().cache[&seed_1].vms.is_empty());
+
+        let mut ops = new_malloc_size_ops();
+        assert!(builder.size_of(&mut ops) >= 2 * RANDOMX_CONTEXT_BYTES);
     }
 }
@@ -10,13 +10,14 @@ use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use mazze_parameters::pow::*;
 use mazze_types::{BigEndianHash, H256, U256, U512};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use static_assertions::_core::str::FromStr;
 use std::{
     collections::{HashMap, VecDeque},
     convert::TryFrom,
-    sync::Arc,
+    sync::{mpsc::channel, Arc},
 };
+use threadpool::ThreadPool;
 
 #[cfg(target_endian = "big")]
 compile_error!("The PoW implementation requires little-endian platform");
@@ -28,6 +29,11 @@ pub struct ProofOfWorkProblem {
     pub difficulty: U256,
     pub boundary: U256,
     pub seed_hash: H256,
+    /// Seed hash of the next RandomX epoch, once it is determined, so that
+    /// miners can build its dataset ahead of the switch.
+    pub next_seed_hash: Option<H256>,
+    /// Whether the two-sided target applies, see `nonce_to_lower_bound`.
+    pub pow_lower_bound: bool,
 }
 
 impl ProofOfWorkProblem {
@@ -43,6 +49,8 @@ impl ProofOfWorkProblem {
             difficulty,
             boundary,
             seed_hash,
+            next_seed_hash: None,
+            pow_lower_bound: false,
         }
     }
 
@@ -56,6 +64,8 @@ impl ProofOfWorkProblem {
             difficulty,
             boundary,
             seed_hash,
+            next_seed_hash: None,
+            pow_lower_bound: false,
         }
     }
 
@@ -69,13 +79,26 @@ impl ProofOfWorkProblem {
             difficulty,
             boundary,
             seed_hash,
+            next_seed_hash: None,
+            pow_lower_bound: false,
         }
     }
+
+    pub fn with_next_seed_hash(mut self, next_seed_hash: Option<H256>) -> Self {
+        self.next_seed_hash = next_seed_hash;
+        self
+    }
+
+    pub fn with_pow_lower_bound(mut self, pow_lower_bound: bool) -> Self {
+        self.pow_lower_bound = pow_lower_bound;
+        self
+    }
+
     #[inline]
     pub fn validate_hash_against_boundary(
-        hash: &H256, _nonce: &U256, boundary: &U256,
+        hash: &H256, nonce: &U256, boundary: &U256, pow_lower_bound: bool,
     ) -> bool {
-        U256::from(hash.as_bytes()) <= *boundary
+        pow_hash_to_quality(hash, nonce, pow_lower_bound) <= *boundary
     }
 }
 
@@ -115,6 +138,18 @@ pub struct ProofOfWorkConfig {
     pub stratum_port: u16,
     pub stratum_secret: Option<H256>,
     pub pow_problem_window_size: usize,
+    pub stratum_pool: Option<StratumPoolConfig>,
+}
+
+/// Stratum pool mode: every worker mines against its own share difficulty,
+/// which is retargeted according to the share rate of the worker.
+#[derive(Debug, Clone, DeriveMallocSizeOf)]
+pub struct StratumPoolConfig {
+    pub initial_share_difficulty: u64,
+    pub min_share_difficulty: u64,
+    pub max_share_difficulty: u64,
+    pub share_target_interval_ms: u64,
+    pub vardiff_retarget_interval_ms: u64,
 }
 
 impl ProofOfWorkConfig {
@@ -133,6 +168,7 @@ impl ProofOfWorkConfig {
                 stratum_port,
                 stratum_secret,
                 pow_problem_window_size,
+                stratum_pool: None,
             }
         } else {
             ProofOfWorkConfig {
@@ -144,6 +180,7 @@ impl ProofOfWorkConfig {
                 stratum_port,
                 stratum_secret,
                 pow_problem_window_size,
+                stratum_pool: None,
             }
         }
     }
@@ -203,27 +240,51 @@ impl ProofOfWorkConfig {
     }
 }
 
-// TODO: update the function logic when we start testing
-// We will use the top 128 bits (excluding the highest bit) to be the lower
-// bound of our PoW. The rationale is to provide a solution for block
-// withholding attack among mining pools.
-pub fn _nonce_to_lower_bound(nonce: &U256) -> U256 {
-    if nonce.is_zero() {
-        U256::zero()
-    } else {
-        let mut buf = [0u8; 32];
-        nonce.to_big_endian(&mut buf);
-        U256::from_big_endian(&buf[0..16])
-    }
+/// Lower bound of the valid PoW hashes of `nonce` under the two-sided target,
+/// which is activated at `TransitionsEpochHeight::pow_lower_bound`.
+///
+/// The top 128 bits of the nonce, excluding the highest bit, are kept in
+/// place and the rest is cleared, so the bound is below 2^255 and a multiple
+/// of 2^128. A PoW hash `h` then meets the boundary `b` of any difficulty
+/// above 1 iff `lower_bound <= h <= lower_bound + b`, i.e. the quality
+/// `h - lower_bound` is at most `b`. The rationale is to provide a solution for
+/// block withholding attack among mining pools: the pool chooses the nonce
+/// prefix, and with it which range of hashes makes a block.
+///
+/// Before the transition the lower bound is always zero. Nonces below 2^128
+/// have a zero lower bound under both rules.
+pub fn nonce_to_lower_bound(nonce: &U256) -> U256 {
+    let mut buf = [0u8; 32];
+    nonce.to_big_endian(&mut buf);
+    buf[0] &= 0x7f;
+    buf[16..].fill(0);
+    U256::from_big_endian(&buf)
 }
 
-pub fn pow_hash_to_quality(hash: &H256, _nonce: &U256) -> U256 {
-    U256::from(hash.as_bytes())
+pub fn pow_hash_to_quality(
+    hash: &H256, nonce: &U256, pow_lower_bound: bool,
+) -> U256 {
+    let hash = U256::from(hash.as_bytes());
+    if pow_lower_bound {
+        // A hash below the lower bound wraps around to a quality of at least
+        // 2^255, which only meets the boundary of difficulty 1.
+        hash.overflowing_sub(nonce_to_lower_bound(nonce)).0
+    } else {
+        hash
+    }
 }
 
-/// This should only be used in tests.
-pub fn pow_quality_to_hash(pow_quality: &U256, nonce: &U256) -> H256 {
-    let lower_bound = _nonce_to_lower_bound(nonce);
+/// A pow hash whose quality is at least `pow_quality`. It fakes the pow of the
+/// blocks generated by tests, benchmarks and the helpers in `sync::utils`, and
+/// must not be used to verify blocks.
+pub fn pow_quality_to_hash(
+    pow_quality: &U256, nonce: &U256, pow_lower_bound: bool,
+) -> H256 {
+    let lower_bound = if pow_lower_bound {
+        nonce_to_lower_bound(nonce)
+    } else {
+        U256::zero()
+    };
     let hash_u256 = if pow_quality.eq(&U256::MAX) {
         U256::one()
     } else {
@@ -234,6 +295,21 @@ pub fn pow_quality_to_hash(pow_quality: &U256, nonce: &U256) -> H256 {
     BigEndianHash::from_uint(&hash_u256)
 }
 
+/// Height of the epoch whose last executed block seeds RandomX at
+/// `epoch_height`: the first epoch of the previous RandomX epoch. `None` for
+/// the first RandomX epoch, which is seeded by the genesis block.
+pub fn randomx_seed_height(epoch_height: u64) -> Option<u64> {
+    (epoch_height / RANDOMX_EPOCH_LENGTH)
+        .checked_sub(1)
+        .map(|randomx_epoch| randomx_epoch * RANDOMX_EPOCH_LENGTH)
+}
+
+/// Height of the epoch that seeds the RandomX epoch following the one of
+/// `epoch_height`, i.e. the first epoch of the current RandomX epoch.
+pub fn next_randomx_seed_height(epoch_height: u64) -> u64 {
+    epoch_height / RANDOMX_EPOCH_LENGTH * RANDOMX_EPOCH_LENGTH
+}
+
 pub fn difficulty_to_boundary(difficulty: &U256) -> U256 {
     if difficulty.is_zero() {
         U256::MAX
@@ -270,7 +346,10 @@ unsafe impl Sync for PowComputer {}
 impl PowComputer {
     pub fn new(seed_hash: H256) -> Self {
         PowComputer {
-            cache_builder: RandomXCacheBuilder::new(seed_hash.into()),
+            cache_builder: RandomXCacheBuilder::new(
+                seed_hash.into(),
+                RANDOMX_SEED_CACHE_SIZE,
+            ),
         }
     }
 
@@ -299,6 +378,101 @@ impl PowComputer {
     pub fn get_seed_hash(&self) -> H256 {
         self.cache_builder.get_seed_hash().into()
     }
+
+    /// Builds the RandomX context of `seed_hash` in the background, so that
+    /// switching to it at the next RandomX epoch does not stall validation.
+    pub fn prepare_seed_hash(&self, seed_hash: &H256) {
+        self.cache_builder
+            .prepare_context(*seed_hash.as_fixed_bytes());
+    }
+
+    /// Seed hashes whose RandomX context is cached, from the least to the
+    /// most recently used.
+    pub fn cached_seed_hashes(&self) -> Vec<H256> {
+        self.cache_builder
+            .cached_seed_hashes()
+            .into_iter()
+            .map(H256::from)
+            .collect()
+    }
+}
+
+impl MallocSizeOf for PowComputer {
+    fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.cache_builder.size_of(ops)
+    }
+}
+
+/// Worker threads computing the PoW hashes of many headers in parallel, e.g.
+/// for a batch of headers received during sync.
+pub struct PowHashPool {
+    pow: Arc<PowComputer>,
+    worker_pool: Mutex<ThreadPool>,
+    threads: usize,
+}
+
+impl PowHashPool {
+    pub fn new(pow: Arc<PowComputer>, threads: usize) -> Self {
+        let threads = threads.max(1);
+        PowHashPool {
+            pow,
+            worker_pool: Mutex::new(ThreadPool::with_name(
+                "PoW Verification".into(),
+                threads,
+            )),
+            threads,
+        }
+    }
+
+    pub fn pow(&self) -> &PowComputer {
+        &self.pow
+    }
+
+    /// Computes the PoW hashes of `(nonce, block_hash, seed_hash)` inputs,
+    /// returned in the input order.
+    pub fn compute_batch(&self, inputs: Vec<(U256, H256, H256)>) -> Vec<H256> {
+        let compute = |pow: &PowComputer, inputs: &[(U256, H256, H256)]| {
+            inputs
+                .iter()
+                .map(|(nonce, block_hash, seed_hash)| {
+                    pow.compute(nonce, block_hash, seed_hash)
+                })
+                .collect::<Vec<_>>()
+        };
+        if inputs.len() <= 1 || self.threads == 1 {
+            return compute(&self.pow, &inputs);
+        }
+
+        let chunk_size = (inputs.len() + self.threads - 1) / self.threads;
+        let chunks: Vec<Vec<_>> =
+            inputs.chunks(chunk_size).map(|c| c.to_vec()).collect();
+        let (sender, receiver) = channel();
+        {
+            let worker_pool = self.worker_pool.lock();
+            for (index, chunk) in chunks.iter().enumerate() {
+                let pow = self.pow.clone();
+                let chunk = chunk.clone();
+                let sender = sender.clone();
+                worker_pool.execute(move || {
+                    sender.send((index, compute(&pow, &chunk))).ok();
+                });
+            }
+        }
+        drop(sender);
+
+        let mut hashes: Vec<Option<Vec<H256>>> = vec![None; chunks.len()];
+        for (index, chunk_hashes) in receiver.iter() {
+            hashes[index] = Some(chunk_hashes);
+        }
+        hashes
+            .into_iter()
+            .zip(&chunks)
+            // A chunk is only missing if its worker panicked.
+            .flat_map(|(chunk_hashes, chunk)| {
+                chunk_hashes.unwrap_or_else(|| compute(&self.pow, chunk))
+            })
+            .collect()
+    }
 }
 
 pub fn validate(
@@ -312,6 +486,7 @@ pub fn validate(
         &hash,
         &nonce,
         &problem.boundary,
+        problem.pow_lower_bound,
     )
 }
 
@@ -489,6 +664,124 @@ impl TargetDifficultyManager {
     }
 }
 
+#[test]
+fn test_randomx_seed_height() {
+    assert_eq!(randomx_seed_height(0), None);
+    assert_eq!(randomx_seed_height(RANDOMX_EPOCH_LENGTH - 1), None);
+    assert_eq!(randomx_seed_height(RANDOMX_EPOCH_LENGTH), Some(0));
+    assert_eq!(
+        randomx_seed_height(3 * RANDOMX_EPOCH_LENGTH - 1),
+        Some(RANDOMX_EPOCH_LENGTH)
+    );
+
+    // The next seed is known from the first epoch of a RandomX epoch on,
+    // and becomes the current seed at the following boundary.
+    for height in [0, 1, RANDOMX_EPOCH_LENGTH - 1, 5 * RANDOMX_EPOCH_LENGTH + 7]
+    {
+        let next_seed_height = next_randomx_seed_height(height);
+        assert!(next_seed_height <= height);
+        let boundary =
+            (height / RANDOMX_EPOCH_LENGTH + 1) * RANDOMX_EPOCH_LENGTH;
+        assert_eq!(
+            randomx_seed_height(boundary - 1),
+            randomx_seed_height(height)
+        );
+        assert_eq!(randomx_seed_height(boundary), Some(next_seed_height));
+    }
+}
+
+#[test]
+fn test_pow_hash_pool() {
+    let pow = Arc::new(PowComputer::new(H256::default()));
+    let pool = PowHashPool::new(pow.clone(), 3);
+    let seed_hashes = [H256::default(), H256::repeat_byte(1)];
+    let inputs: Vec<_> = (0..10u64)
+        .map(|i| {
+            (
+                U256::from(i),
+                H256::from_low_u64_be(i),
+                seed_hashes[i as usize % 2],
+            )
+        })
+        .collect();
+
+    let hashes = pool.compute_batch(inputs.clone());
+    assert_eq!(hashes.len(), inputs.len());
+    for ((nonce, block_hash, seed_hash), hash) in inputs.iter().zip(&hashes) {
+        assert_eq!(*hash, pow.compute(nonce, block_hash, seed_hash));
+    }
+    assert!(pool.compute_batch(Vec::new()).is_empty());
+}
+
+#[test]
+fn test_pow_lower_bound() {
+    let boundary = difficulty_to_boundary(&U256::from(1u64 << 32));
+    let low_nonce = U256::from(u128::MAX);
+    let high_nonce = U256::MAX;
+    let lower_bound = nonce_to_lower_bound(&high_nonce);
+    assert!(nonce_to_lower_bound(&low_nonce).is_zero());
+    assert_eq!(lower_bound, (U256::MAX >> 129) << 128);
+
+    let small_hash = BigEndianHash::from_uint(&boundary);
+    let windowed_hash: H256 = BigEndianHash::from_uint(&(lower_bound + 1));
+    let above_window: H256 =
+        BigEndianHash::from_uint(&(lower_bound + boundary + 1));
+
+    // Before the transition only the hash itself is compared.
+    for nonce in [&low_nonce, &high_nonce] {
+        assert!(ProofOfWorkProblem::validate_hash_against_boundary(
+            &small_hash,
+            nonce,
+            &boundary,
+            false
+        ));
+    }
+    assert!(!ProofOfWorkProblem::validate_hash_against_boundary(
+        &windowed_hash,
+        &high_nonce,
+        &boundary,
+        false
+    ));
+
+    // After the transition the hash must be in the window of the nonce.
+    assert!(ProofOfWorkProblem::validate_hash_against_boundary(
+        &small_hash,
+        &low_nonce,
+        &boundary,
+        true
+    ));
+    assert!(!ProofOfWorkProblem::validate_hash_against_boundary(
+        &small_hash,
+        &high_nonce,
+        &boundary,
+        true
+    ));
+    assert!(ProofOfWorkProblem::validate_hash_against_boundary(
+        &windowed_hash,
+        &high_nonce,
+        &boundary,
+        true
+    ));
+    assert!(!ProofOfWorkProblem::validate_hash_against_boundary(
+        &above_window,
+        &high_nonce,
+        &boundary,
+        true
+    ));
+
+    let difficulty = U256::from(1000);
+    for pow_lower_bound in [false, true] {
+        let hash =
+            pow_quality_to_hash(&difficulty, &high_nonce, pow_lower_bound);
+        assert!(ProofOfWorkProblem::validate_hash_against_boundary(
+            &hash,
+            &high_nonce,
+            &difficulty_to_boundary(&difficulty),
+            pow_lower_bound
+        ));
+    }
+}
+
 #[test]
 fn test_pow() {
     let pow = PowComputer::new(H256::default());
@@ -0,0 +1,332 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! A block stream is a file of the blocks in a range of epochs, written in
+//! consensus order: epoch by epoch, and the skipped blocks before the
+//! executed blocks of each epoch, so that the dependencies of a block are
+//! always before it.
+//!
+//! The file is a stream of RLP items, one `Block` for each block, encoded in
+//! the same way as blocks are sent to peers. Imported blocks are inserted
+//! into the sync graph with full verification, as if they were received from
+//! peers.
+
+use crate::sync::{
+    state::read_rlp_item, Error, ErrorKind, SynchronizationGraph,
+};
+use primitives::Block;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// Write the blocks of the epochs in `[from_epoch, to_epoch]` to `path`, and
+/// return the number of blocks written. `path` must not exist.
+pub fn export_blocks(
+    graph: &SynchronizationGraph, from_epoch: u64, to_epoch: u64, path: &Path,
+) -> Result<usize, Error> {
+    let best_epoch = graph.consensus.best_epoch_number();
+    if from_epoch > to_epoch || to_epoch > best_epoch {
+        bail!(
+            "invalid epoch range [{}, {}], best epoch = {}",
+            from_epoch,
+            to_epoch,
+            best_epoch
+        );
+    }
+    let data_man = &graph.data_man;
+
+    let mut writer = BufWriter::new(
+        OpenOptions::new().write(true).create_new(true).open(path)?,
+    );
+    let mut block_count = 0;
+    for epoch in from_epoch..=to_epoch {
+        let block_hashes = match graph.get_all_block_hashes_by_epoch(epoch) {
+            Ok(block_hashes) => block_hashes,
+            // The epochs before the current era are only kept in db.
+            Err(_) => match data_man.all_epoch_set_hashes_from_db(epoch) {
+                Some(block_hashes) => block_hashes,
+                None => bail!("the blocks of epoch {} are unavailable", epoch),
+            },
+        };
+        for hash in &block_hashes {
+            let block = match data_man.block_by_hash(hash, false) {
+                Some(block) => block,
+                None => bail!("failed to find block={:?} in db", hash),
+            };
+            writer.write_all(&rlp::encode(&*block))?;
+            block_count += 1;
+        }
+    }
+    writer.flush()?;
+
+    info!(
+        "exported blocks, epochs = [{}, {}], blocks = {}, path = {:?}",
+        from_epoch, to_epoch, block_count, path
+    );
+    Ok(block_count)
+}
+
+/// Insert the blocks in the stream at `path` into the sync graph, and return
+/// the number of blocks which are new to the graph. The import stops at the
+/// first invalid block.
+///
+/// The headers are inserted into consensus as in the
+/// `CatchUpSyncBlockHeader` phase, which follows the import.
+pub fn import_blocks(
+    graph: &SynchronizationGraph, path: &Path,
+) -> Result<usize, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut block_count = 0;
+    while let Some(mut block) = read_block(&mut reader)? {
+        let hash = block.hash();
+        if graph.contains_block(&hash) {
+            continue;
+        }
+        graph.data_man.recover_block(&mut block)?;
+
+        let (insert_result, _) = graph.insert_block_header(
+            &mut block.block_header,
+            true,  /* need_to_verify */
+            false, /* bench_mode */
+            true,  /* insert_to_consensus */
+            true,  /* persistent */
+        );
+        if insert_result.is_invalid() {
+            bail!(ErrorKind::InvalidBlock);
+        }
+        if !insert_result.should_process_body() {
+            continue;
+        }
+        let insert_result = graph.insert_block(
+            block, true,  /* need_to_verify */
+            true,  /* persistent */
+            false, /* recover_from_db */
+        );
+        if !insert_result.is_valid() {
+            bail!(ErrorKind::InvalidBlock);
+        }
+        block_count += 1;
+    }
+
+    info!(
+        "imported blocks, blocks = {}, path = {:?}",
+        block_count, path
+    );
+    Ok(block_count)
+}
+
+/// Read the next block in the stream, or `None` at the end of the stream.
+fn read_block(reader: &mut impl BufRead) -> Result<Option<Block>, Error> {
+    if reader.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(rlp::decode(&read_rlp_item(reader)?)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_data_manager::BlockDbBackend, pow,
+        sync::utils::initialize_synchronization_graph, ConsensusGraph,
+    };
+    use mazze_executor::spec::CommonParams;
+    use mazze_parameters::consensus::GENESIS_GAS_LIMIT;
+    use mazze_types::{
+        address_util::AddressUtil, Address, Space, SpaceMap, H256, U256,
+    };
+    use primitives::BlockHeaderBuilder;
+    use std::sync::Arc;
+    use tempdir::TempDir;
+
+    /// A block which is valid under full verification, except that its PoW
+    /// hash is faked as in `create_simple_block`.
+    fn create_block(
+        parent_hash: H256, referee_hashes: Vec<H256>, height: u64, nonce: u64,
+        base_price: SpaceMap<U256>,
+    ) -> Block {
+        create_block_with_difficulty(
+            parent_hash,
+            referee_hashes,
+            height,
+            nonce,
+            base_price,
+            10.into(),
+        )
+    }
+
+    fn create_block_with_difficulty(
+        parent_hash: H256, referee_hashes: Vec<H256>, height: u64, nonce: u64,
+        base_price: SpaceMap<U256>, difficulty: U256,
+    ) -> Block {
+        let mut author = Address::zero();
+        author.set_user_account_type_bits();
+        let mut header = BlockHeaderBuilder::new()
+            .with_parent_hash(parent_hash)
+            .with_height(height)
+            .with_referee_hashes(referee_hashes)
+            .with_gas_limit(GENESIS_GAS_LIMIT.into())
+            .with_nonce(nonce.into())
+            .with_difficulty(difficulty)
+            .with_author(author)
+            .with_base_price(Some(base_price))
+            .build();
+        header.compute_hash();
+        header.pow_hash =
+            Some(pow::pow_quality_to_hash(&difficulty, &header.nonce(), true));
+        Block::new(header, vec![])
+    }
+
+    fn insert_block(
+        graph: &SynchronizationGraph, consensus: &ConsensusGraph,
+        mut block: Block,
+    ) {
+        let hash = block.hash();
+        graph.insert_block_header(
+            &mut block.block_header,
+            false, /* need_to_verify */
+            true,  /* bench_mode */
+            false, /* insert_to_consensus */
+            true,  /* persistent */
+        );
+        graph.insert_block(
+            block, false, /* need_to_verify */
+            true,  /* persistent */
+            false, /* recover_from_db */
+        );
+        consensus.wait_for_generation(&hash);
+    }
+
+    fn initialize_graph(
+        db_dir: &TempDir,
+    ) -> (Arc<SynchronizationGraph>, Arc<ConsensusGraph>, H256) {
+        let (graph, consensus, _, genesis) = initialize_synchronization_graph(
+            db_dir.path().to_str().unwrap(),
+            1,
+            1,
+            1,
+            1,
+            50000,
+            BlockDbBackend::Rocksdb,
+            H256::zero(),
+        );
+        (graph, consensus, genesis.hash())
+    }
+
+    #[test]
+    fn test_export_and_import_blocks() {
+        let source_dir = TempDir::new("block_stream_source").unwrap();
+        let (source, consensus, genesis_hash) = initialize_graph(&source_dir);
+
+        // The base price of empty blocks stays at the minimum.
+        let base_price = CommonParams::default().min_base_price();
+        // Epoch 1 is `a`, and epoch 2 is `b` referenced by `c`.
+        let a = create_block(genesis_hash, vec![], 1, 1, base_price);
+        let b = create_block(genesis_hash, vec![], 1, 2, base_price);
+        let c = create_block(a.hash(), vec![b.hash()], 2, 3, base_price);
+        let mut tampered_base_price = base_price;
+        tampered_base_price[Space::Native] += 1.into();
+        let d = create_block(c.hash(), vec![], 3, 4, tampered_base_price);
+        let hashes = vec![a.hash(), b.hash(), c.hash()];
+        for block in vec![a, b, c] {
+            insert_block(&source, &consensus, block);
+        }
+        assert_eq!(consensus.best_epoch_number(), 2);
+
+        let stream_dir = TempDir::new("block_stream").unwrap();
+        let path = stream_dir.path().join("blocks.rlp");
+        assert_eq!(export_blocks(&source, 1, 2, &path).unwrap(), 3);
+        // An existing file is not overwritten.
+        assert!(export_blocks(&source, 1, 2, &path).is_err());
+
+        // The blocks are written in consensus order.
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let mut exported = Vec::new();
+        while let Some(block) = read_block(&mut reader).unwrap() {
+            exported.push(block.hash());
+        }
+        assert_eq!(exported, hashes);
+
+        // Append a block which does not pass full verification.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&rlp::encode(&d)).unwrap();
+        drop(file);
+
+        let target_dir = TempDir::new("block_stream_target").unwrap();
+        let (target, _, _) = initialize_graph(&target_dir);
+        // The PoW hashes of the blocks are faked and not in the stream, so
+        // PoW verification is skipped as during catch-up.
+        target.verification_config.set_catch_up_mode(true);
+        assert!(import_blocks(&target, &path).is_err());
+        for hash in &hashes {
+            assert!(target.contains_block(hash));
+        }
+        assert!(target.data_man.verified_invalid(&d.hash()).0);
+    }
+
+    #[test]
+    fn test_import_rejects_bad_pow() {
+        let db_dir = TempDir::new("block_stream_pow").unwrap();
+        let (graph, _, genesis_hash) = initialize_graph(&db_dir);
+        assert!(!graph.verification_config.catch_up_mode());
+
+        // No PoW hash meets the boundary of the maximum difficulty, so the
+        // hash computed from the stream fails verification.
+        let base_price = CommonParams::default().min_base_price();
+        let block = create_block_with_difficulty(
+            genesis_hash,
+            vec![],
+            1,
+            1,
+            base_price,
+            U256::MAX,
+        );
+
+        let stream_dir = TempDir::new("block_stream").unwrap();
+        let path = stream_dir.path().join("blocks.rlp");
+        std::fs::write(&path, rlp::encode(&block)).unwrap();
+
+        assert!(import_blocks(&graph, &path).is_err());
+        assert!(graph.data_man.verified_invalid(&block.hash()).0);
+    }
+
+    #[test]
+    fn test_block_stream_round_trip() {
+        let mut parent_hash = H256::zero();
+        let mut blocks = Vec::new();
+        for height in 0..3 {
+            let header = BlockHeaderBuilder::new()
+                .with_parent_hash(parent_hash)
+                .with_height(height)
+                .build();
+            parent_hash = header.hash();
+            blocks.push(Block::new(header, vec![]));
+        }
+        let mut stream = Vec::new();
+        for block in &blocks {
+            stream.extend_from_slice(&rlp::encode(block));
+        }
+
+        let mut reader = stream.as_slice();
+        for block in &blocks {
+            let decoded = read_block(&mut reader).unwrap().unwrap();
+            assert_eq!(decoded.hash(), block.hash());
+            assert_eq!(
+                decoded.block_header.height(),
+                block.block_header.height()
+            );
+        }
+        assert!(read_block(&mut reader).unwrap().is_none());
+
+        // A truncated stream is an error rather than the end of the stream.
+        let mut truncated = &stream[..stream.len() - 1];
+        for _ in 0..blocks.len() - 1 {
+            read_block(&mut truncated).unwrap();
+        }
+        assert!(read_block(&mut truncated).is_err());
+    }
+}
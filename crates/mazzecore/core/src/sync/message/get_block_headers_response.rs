@@ -106,6 +106,9 @@ impl Handleable for GetBlockHeadersResponse {
                 .select(&ctx.manager.syn)
         };
 
+        // Hash the whole batch in parallel before the sequential insertion.
+        ctx.manager.graph.verify_header_pow_batch(&mut self.headers);
+
         // re-request headers requested but not received
         let requested: HashSet<H256> = req.hashes.iter().cloned().collect();
         self.handle_block_headers(
@@ -1,6 +1,7 @@
 // Copyright 2024 Mazze Foundation. All rights reserved.
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
+mod block_stream;
 mod error;
 pub mod message;
 pub mod request_manager;
@@ -18,7 +19,7 @@ pub mod utils;
 
 pub use self::{
     error::{Error, ErrorKind},
-    state::StateSyncConfiguration,
+    state::{StateSyncConfiguration, StateSyncProgress},
     synchronization_graph::{
         SharedSynchronizationGraph, SyncGraphConfig, SyncGraphStatistics,
         SynchronizationGraph, SynchronizationGraphInner,
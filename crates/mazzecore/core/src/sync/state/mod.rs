@@ -2,12 +2,16 @@
 // Mazze is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
+mod snapshot_bundle;
 mod snapshot_chunk_sync;
 mod state_sync_candidate;
 mod state_sync_chunk;
 mod state_sync_manifest;
+mod state_sync_progress;
 pub mod storage;
 
-pub use self::snapshot_chunk_sync::{
-    SnapshotChunkSync, StateSyncConfiguration, Status,
+pub use self::{
+    snapshot_bundle::{export_snapshot_bundle, read_rlp_item},
+    snapshot_chunk_sync::{SnapshotChunkSync, StateSyncConfiguration, Status},
+    state_sync_progress::{discard_state_sync_progress, StateSyncProgress},
 };
@@ -0,0 +1,437 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! A snapshot bundle is an offline checkpoint of the state at a snapshot
+//! epoch. It carries everything that state sync otherwise downloads from
+//! peers, so that a new node can be bootstrapped from a file.
+//!
+//! The file is a stream of RLP items: a `SnapshotBundleHeader` followed by
+//! one `Chunk` for each chunk of the manifest, in key order.
+
+use crate::{
+    block_data_manager::BlockExecutionResult,
+    sync::{
+        message::{Context, SnapshotManifestRequest},
+        state::{
+            state_sync_chunk::restore::Restorer,
+            state_sync_manifest::snapshot_manifest_manager::{
+                RelatedData, SnapshotManifestManager,
+            },
+            storage::{Chunk, RangedManifest, SnapshotSyncCandidate},
+        },
+        Error, ErrorKind, SynchronizationProtocolHandler,
+    },
+};
+use mazze_parameters::consensus::DEFERRED_STATE_EPOCH_COUNT;
+use mazze_types::H256;
+use primitives::{BlockHeader, EpochId, StateRoot};
+use rlp::PayloadInfo;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Bump the version for incompatible changes of the bundle format.
+const SNAPSHOT_BUNDLE_VERSION: u64 = 1;
+
+#[derive(RlpDecodable, RlpEncodable)]
+pub struct SnapshotBundleHeader {
+    pub version: u64,
+    pub snapshot_to_sync: SnapshotSyncCandidate,
+    pub trusted_blame_block: H256,
+    /// The headers from `trusted_blame_block` back to the snapshot epoch,
+    /// newest first.
+    pub headers: Vec<BlockHeader>,
+    pub state_root_vec: Vec<StateRoot>,
+    pub receipt_blame_vec: Vec<H256>,
+    pub bloom_blame_vec: Vec<H256>,
+    pub block_receipts: Vec<BlockExecutionResult>,
+    pub manifest: RangedManifest,
+}
+
+impl SnapshotBundleHeader {
+    /// Check that the bundle is complete and that its headers link
+    /// `trusted_blame_block` to the snapshot epoch through parent hashes.
+    fn validate(&self, trusted_blame_block: &H256) -> Result<(), Error> {
+        if self.version != SNAPSHOT_BUNDLE_VERSION {
+            bail!(ErrorKind::InvalidSnapshotBundle(format!(
+                "unsupported version {}",
+                self.version
+            )));
+        }
+        let (height, snapshot_epoch_id) = match &self.snapshot_to_sync {
+            SnapshotSyncCandidate::FullSync {
+                height,
+                snapshot_epoch_id,
+            } => (*height, snapshot_epoch_id),
+            _ => bail!(ErrorKind::InvalidSnapshotBundle(
+                "only FullSync bundles are supported".into()
+            )),
+        };
+        if self.trusted_blame_block != *trusted_blame_block {
+            bail!(ErrorKind::InvalidSnapshotBundle(format!(
+                "trusted blame block should be {:?}, get {:?}",
+                trusted_blame_block, self.trusted_blame_block
+            )));
+        }
+
+        let (newest, oldest) = match (self.headers.first(), self.headers.last())
+        {
+            (Some(newest), Some(oldest)) => (newest, oldest),
+            _ => bail!(ErrorKind::InvalidSnapshotBundle("no headers".into())),
+        };
+        if newest.hash() != *trusted_blame_block {
+            bail!(ErrorKind::InvalidSnapshotBundle(
+                "headers do not start from the trusted blame block".into()
+            ));
+        }
+        for pair in self.headers.windows(2) {
+            if *pair[0].parent_hash() != pair[1].hash() {
+                bail!(ErrorKind::InvalidSnapshotBundle(format!(
+                    "header {:?} is not the parent of {:?}",
+                    pair[1].hash(),
+                    pair[0].hash()
+                )));
+            }
+        }
+        if oldest.hash() != *snapshot_epoch_id || oldest.height() != height {
+            bail!(ErrorKind::InvalidSnapshotBundle(
+                "headers do not end at the snapshot epoch".into()
+            ));
+        }
+        if newest.height() < height + DEFERRED_STATE_EPOCH_COUNT {
+            bail!(ErrorKind::InvalidSnapshotBundle(
+                "trusted blame block is too close to the snapshot".into()
+            ));
+        }
+
+        if self.state_root_vec.is_empty()
+            || self.state_root_vec.len() != self.receipt_blame_vec.len()
+            || self.state_root_vec.len() != self.bloom_blame_vec.len()
+        {
+            bail!(ErrorKind::InvalidSnapshotBundle(
+                "blame vector length mismatch".into()
+            ));
+        }
+        if self.manifest.next.is_some() {
+            bail!(ErrorKind::InvalidSnapshotBundle(
+                "incomplete manifest".into()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Write the bundle of the snapshot at `snapshot_epoch_id` to `path`, which
+/// must not exist. The snapshot is cut into chunks in the same way as it is
+/// served to peers.
+pub fn export_snapshot_bundle(
+    sync_handler: &SynchronizationProtocolHandler, snapshot_epoch_id: &EpochId,
+    path: &Path,
+) -> Result<(), Error> {
+    let graph = &sync_handler.graph;
+    let data_man = &graph.data_man;
+    let chunk_size = sync_handler.protocol_config.chunk_size_byte;
+
+    let snapshot_header = match data_man.block_header_by_hash(snapshot_epoch_id)
+    {
+        Some(header) => header,
+        None => bail!("unknown snapshot epoch {:?}", snapshot_epoch_id),
+    };
+    let snapshot_to_sync = SnapshotSyncCandidate::FullSync {
+        height: snapshot_header.height(),
+        snapshot_epoch_id: *snapshot_epoch_id,
+    };
+    let trusted_blame_block = match graph
+        .consensus
+        .get_trusted_blame_block_for_snapshot(snapshot_epoch_id)
+    {
+        Some(hash) => hash,
+        None => bail!(
+            "the trusted blame block of snapshot {:?} is unavailable",
+            snapshot_epoch_id
+        ),
+    };
+
+    let mut headers = Vec::new();
+    let mut block_hash = trusted_blame_block;
+    loop {
+        let header = match data_man.block_header_by_hash(&block_hash) {
+            Some(header) => header,
+            None => bail!("failed to find block={:?} in db", block_hash),
+        };
+        block_hash = *header.parent_hash();
+        let reached_snapshot = header.height() <= snapshot_header.height();
+        headers.push((*header).clone());
+        if reached_snapshot {
+            break;
+        }
+    }
+    if headers.last().map(|header| header.hash()) != Some(*snapshot_epoch_id) {
+        bail!(
+            "snapshot {:?} is not an ancestor of the trusted blame block {:?}",
+            snapshot_epoch_id,
+            trusted_blame_block
+        );
+    }
+
+    let (state_root_vec, receipt_blame_vec, bloom_blame_vec) =
+        match SnapshotManifestRequest::get_blame_states(
+            graph,
+            snapshot_epoch_id,
+            &trusted_blame_block,
+        ) {
+            Some(blame_states) => blame_states,
+            None => bail!(
+                "failed to get the blame states of snapshot {:?}",
+                snapshot_epoch_id
+            ),
+        };
+    let block_receipts = match SnapshotManifestRequest::get_block_receipts(
+        graph,
+        snapshot_epoch_id,
+    ) {
+        Some(block_receipts) => block_receipts,
+        None => bail!(
+            "failed to get the receipts of snapshot {:?}",
+            snapshot_epoch_id
+        ),
+    };
+    let manifest = match RangedManifest::load(
+        &snapshot_to_sync,
+        None,
+        &data_man.storage_manager,
+        chunk_size,
+        usize::MAX,
+    )? {
+        Some((manifest, _)) => manifest,
+        None => bail!("snapshot {:?} is unavailable", snapshot_epoch_id),
+    };
+    let chunk_keys = RangedManifest::convert_boundaries_to_chunks(
+        manifest.chunk_boundaries.clone(),
+    );
+
+    let header = SnapshotBundleHeader {
+        version: SNAPSHOT_BUNDLE_VERSION,
+        snapshot_to_sync,
+        trusted_blame_block,
+        headers,
+        state_root_vec,
+        receipt_blame_vec,
+        bloom_blame_vec,
+        block_receipts,
+        manifest,
+    };
+    let mut writer = BufWriter::new(
+        OpenOptions::new().write(true).create_new(true).open(path)?,
+    );
+    writer.write_all(&rlp::encode(&header))?;
+    for chunk_key in &chunk_keys {
+        let chunk = match Chunk::load(
+            snapshot_epoch_id,
+            chunk_key,
+            &data_man.storage_manager,
+            chunk_size * 2,
+        )? {
+            Some(chunk) => chunk,
+            None => bail!("snapshot {:?} is unavailable", snapshot_epoch_id),
+        };
+        writer.write_all(&rlp::encode(&chunk))?;
+    }
+    writer.flush()?;
+
+    info!(
+        "exported snapshot bundle, snapshot_epoch_id = {:?}, chunks = {}, path = {:?}",
+        snapshot_epoch_id,
+        chunk_keys.len(),
+        path
+    );
+    Ok(())
+}
+
+/// Verify the bundle at `path` against `trusted_blame_block` and restore the
+/// snapshot of `epoch_to_sync` from it, in the same way as the chunks
+/// downloaded by state sync are restored.
+pub fn import_snapshot_bundle(
+    ctx: &Context, path: &Path, trusted_blame_block: &H256,
+    epoch_to_sync: &EpochId,
+) -> Result<(SnapshotSyncCandidate, RelatedData), Error> {
+    let data_man = &ctx.manager.graph.data_man;
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let header: SnapshotBundleHeader =
+        rlp::decode(&read_rlp_item(&mut reader)?)?;
+    header.validate(trusted_blame_block)?;
+    let snapshot_epoch_id = *header.snapshot_to_sync.get_snapshot_epoch_id();
+    if snapshot_epoch_id != *epoch_to_sync {
+        bail!(ErrorKind::InvalidSnapshotBundle(format!(
+            "the bundle of epoch {:?} cannot be used to sync epoch {:?}",
+            snapshot_epoch_id, epoch_to_sync
+        )));
+    }
+    // The blame states are validated against the local headers, which must
+    // have been synced from peers.
+    for block_header in &header.headers {
+        if data_man
+            .block_header_by_hash(&block_header.hash())
+            .is_none()
+        {
+            bail!(ErrorKind::InvalidSnapshotBundle(format!(
+                "header {:?} is not synced",
+                block_header.hash()
+            )));
+        }
+    }
+
+    let related_data = SnapshotManifestManager::validate_related_data(
+        ctx,
+        &snapshot_epoch_id,
+        trusted_blame_block,
+        &header.state_root_vec,
+        header.receipt_blame_vec,
+        header.bloom_blame_vec,
+        &header.block_receipts,
+    )?;
+    let snapshot_info = &related_data.snapshot_info;
+    header.manifest.validate(&snapshot_info.merkle_root)?;
+
+    let mut restorer = Restorer::new_with_full_sync_verifier(
+        snapshot_epoch_id,
+        snapshot_info,
+        header.manifest.chunk_boundaries.clone(),
+        header.manifest.chunk_boundary_proofs.clone(),
+        &data_man.storage_manager,
+    )?;
+    let chunk_keys = RangedManifest::convert_boundaries_to_chunks(
+        header.manifest.chunk_boundaries.clone(),
+    );
+    for chunk_key in chunk_keys {
+        let chunk: Chunk = rlp::decode(&read_rlp_item(&mut reader)?)?;
+        chunk.validate(&chunk_key)?;
+        if !restorer.append(chunk_key.clone(), chunk) {
+            bail!(ErrorKind::InvalidSnapshotChunk(format!(
+                "failed to restore {:?}",
+                chunk_key
+            )));
+        }
+    }
+    restorer.finalize_restoration(
+        data_man.storage_manager.clone(),
+        snapshot_info.clone(),
+        related_data.parent_snapshot_info.clone(),
+        related_data
+            .true_state_root_by_blame_info
+            .state_root
+            .delta_root,
+    )?;
+
+    Ok((header.snapshot_to_sync, related_data))
+}
+
+/// Read the next item from a stream of concatenated RLP items.
+pub fn read_rlp_item(reader: &mut impl Read) -> Result<Vec<u8>, Error> {
+    let mut item = vec![0u8; 1];
+    reader.read_exact(&mut item)?;
+    // Long strings and lists have the big-endian payload length after the
+    // first byte.
+    let len_of_len = match item[0] {
+        0xb8..=0xbf => item[0] - 0xb7,
+        0xf8..=0xff => item[0] - 0xf7,
+        _ => 0,
+    } as usize;
+    item.resize(1 + len_of_len, 0);
+    reader.read_exact(&mut item[1..])?;
+
+    let payload_info = PayloadInfo::from(&item)?;
+    item.resize(payload_info.total(), 0);
+    reader.read_exact(&mut item[1 + len_of_len..])?;
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::BlockHeaderBuilder;
+
+    fn header_chain(snapshot_height: u64, len: u64) -> Vec<BlockHeader> {
+        let mut headers = Vec::new();
+        let mut parent_hash = H256::zero();
+        for height in snapshot_height..snapshot_height + len {
+            let header = BlockHeaderBuilder::new()
+                .with_parent_hash(parent_hash)
+                .with_height(height)
+                .build();
+            parent_hash = header.hash();
+            headers.push(header);
+        }
+        headers.reverse();
+        headers
+    }
+
+    fn bundle_header(headers: Vec<BlockHeader>) -> SnapshotBundleHeader {
+        let snapshot = headers.last().unwrap();
+        SnapshotBundleHeader {
+            version: SNAPSHOT_BUNDLE_VERSION,
+            snapshot_to_sync: SnapshotSyncCandidate::FullSync {
+                height: snapshot.height(),
+                snapshot_epoch_id: snapshot.hash(),
+            },
+            trusted_blame_block: headers[0].hash(),
+            headers,
+            state_root_vec: vec![Default::default()],
+            receipt_blame_vec: vec![Default::default()],
+            bloom_blame_vec: vec![Default::default()],
+            block_receipts: vec![],
+            manifest: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_validate_header_chain() {
+        let headers = header_chain(100, DEFERRED_STATE_EPOCH_COUNT + 1);
+        let trusted_blame_block = headers[0].hash();
+        assert!(bundle_header(headers.clone())
+            .validate(&trusted_blame_block)
+            .is_ok());
+        assert!(bundle_header(headers.clone())
+            .validate(&H256::repeat_byte(1))
+            .is_err());
+
+        let mut broken_headers = headers.clone();
+        broken_headers.remove(2);
+        assert!(bundle_header(broken_headers)
+            .validate(&trusted_blame_block)
+            .is_err());
+
+        let too_close = header_chain(100, DEFERRED_STATE_EPOCH_COUNT);
+        let trusted_blame_block = too_close[0].hash();
+        assert!(bundle_header(too_close)
+            .validate(&trusted_blame_block)
+            .is_err());
+    }
+
+    #[test]
+    fn test_bundle_stream_round_trip() {
+        let header = bundle_header(header_chain(0, 10));
+        let chunk = Chunk {
+            keys: vec![vec![1; 10], vec![2; 100]],
+            values: vec![vec![3; 1000], vec![]],
+        };
+        let mut stream = rlp::encode(&header);
+        stream.extend_from_slice(&rlp::encode(&chunk));
+
+        let mut reader = stream.as_slice();
+        let decoded_header: SnapshotBundleHeader =
+            rlp::decode(&read_rlp_item(&mut reader).unwrap()).unwrap();
+        assert_eq!(decoded_header.headers, header.headers);
+        let decoded_chunk: Chunk =
+            rlp::decode(&read_rlp_item(&mut reader).unwrap()).unwrap();
+        assert_eq!(decoded_chunk.keys, chunk.keys);
+        assert_eq!(decoded_chunk.values, chunk.values);
+        assert!(reader.is_empty());
+        assert!(read_rlp_item(&mut reader).is_err());
+    }
+}
@@ -9,6 +9,7 @@ use crate::sync::{
         StateSyncCandidateRequest,
     },
     state::{
+        snapshot_bundle::import_snapshot_bundle,
         state_sync_candidate::state_sync_candidate_manager::StateSyncCandidateManager,
         state_sync_chunk::snapshot_chunk_manager::{
             SnapshotChunkConfig, SnapshotChunkManager,
@@ -16,6 +17,9 @@ use crate::sync::{
         state_sync_manifest::snapshot_manifest_manager::{
             RelatedData, SnapshotManifestConfig, SnapshotManifestManager,
         },
+        state_sync_progress::{
+            discard_state_sync_progress, remove_state_sync_progress,
+        },
         storage::{Chunk, ChunkKey, SnapshotSyncCandidate},
     },
     synchronization_state::PeerFilter,
@@ -30,6 +34,7 @@ use primitives::EpochId;
 use std::{
     collections::HashSet,
     fmt::{Debug, Formatter},
+    path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -228,6 +233,11 @@ impl SnapshotChunkSync {
             if let Some(related_data) = r {
                 // update status
                 inner.status = Status::DownloadingChunks(Instant::now());
+                // The previous restoration and its persisted progress are
+                // replaced by the new manifest.
+                inner.chunk_manager = None;
+                let data_man = &ctx.manager.graph.data_man;
+                remove_state_sync_progress(data_man);
                 inner.chunk_manager =
                     Some(SnapshotChunkManager::new_and_start(
                         ctx,
@@ -247,6 +257,11 @@ impl SnapshotChunkSync {
                             .state_root
                             .delta_root,
                     )?);
+                if let Some(progress) = manifest_manager.state_sync_progress() {
+                    data_man
+                        .db_manager
+                        .insert_state_sync_progress_to_db(&progress);
+                }
                 inner.related_data = Some(related_data);
             }
             debug!("sync state progress: {:?}", *inner);
@@ -340,6 +355,153 @@ impl SnapshotChunkSync {
         }
     }
 
+    /// Resume the restoration of the snapshot persisted before a restart.
+    /// The progress is discarded if the snapshot isn't the one to sync or
+    /// isn't trusted anymore. Return `true` if all chunks had been restored
+    /// and the state is restored.
+    ///
+    /// If some chunks are missing, the status is kept `Inactive`, so
+    /// `update_status` requests candidates and then resumes downloading the
+    /// missing chunks with the chunk manager of the same candidate.
+    pub fn resume_persisted_sync(
+        &self, current_era_genesis: EpochId, epoch_to_sync: EpochId,
+        io: &dyn NetworkContext, sync_handler: &SynchronizationProtocolHandler,
+    ) -> bool {
+        let data_man = &sync_handler.graph.data_man;
+        let progress = match data_man.db_manager.state_sync_progress_from_db() {
+            Some(progress) => progress,
+            None => return false,
+        };
+        let ctx = Context {
+            // node_id is not used here
+            node_id: Default::default(),
+            io,
+            manager: sync_handler,
+        };
+
+        let mut inner = self.inner.write();
+        let (sync_candidate, manifest, related_data) =
+            match progress.validate(&ctx, &epoch_to_sync) {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!("discard persisted state sync progress: {:?}", e);
+                    discard_state_sync_progress(data_man);
+                    return false;
+                }
+            };
+        let mut chunk_manager = match SnapshotChunkManager::resume(
+            &ctx,
+            sync_candidate.clone(),
+            related_data.snapshot_info.clone(),
+            related_data.parent_snapshot_info.clone(),
+            manifest.chunk_boundaries,
+            manifest.chunk_boundary_proofs,
+            self.config.chunk_config(),
+            related_data
+                .true_state_root_by_blame_info
+                .state_root
+                .delta_root,
+        ) {
+            Ok(Some(chunk_manager)) => chunk_manager,
+            Ok(None) => {
+                warn!("discard persisted state sync progress: temp snapshot of {:?} not found", sync_candidate);
+                discard_state_sync_progress(data_man);
+                return false;
+            }
+            Err(e) => {
+                warn!("discard persisted state sync progress: {:?}", e);
+                discard_state_sync_progress(data_man);
+                return false;
+            }
+        };
+        inner.related_data = Some(related_data);
+
+        match chunk_manager.finalize_if_all_restored(&ctx) {
+            Ok(true) => {
+                // Keep the candidate era so that `update_status` does not
+                // restart the sync.
+                inner.sync_candidate_manager.reset(
+                    current_era_genesis,
+                    vec![sync_candidate],
+                    vec![],
+                );
+                inner.status = Status::Completed;
+                info!(
+                    "state sync resumed and completed, epoch = {:?}",
+                    epoch_to_sync
+                );
+                true
+            }
+            Ok(false) => {
+                inner.chunk_manager = Some(chunk_manager);
+                info!("state sync resumed: {:?}", *inner);
+                false
+            }
+            Err(e) => {
+                warn!("failed to finalize resumed state sync: {:?}", e);
+                inner.related_data = None;
+                drop(chunk_manager);
+                discard_state_sync_progress(data_man);
+                false
+            }
+        }
+    }
+
+    /// Restore the state of `epoch_to_sync` from the configured snapshot
+    /// bundle instead of downloading it from peers. Return `true` if the
+    /// state is restored, and state sync from peers is used otherwise.
+    pub fn import_snapshot_bundle(
+        &self, current_era_genesis: EpochId, epoch_to_sync: EpochId,
+        io: &dyn NetworkContext, sync_handler: &SynchronizationProtocolHandler,
+    ) -> bool {
+        let (path, trusted_blame_block) =
+            match &self.config.snapshot_bundle_to_import {
+                Some(bundle) => bundle,
+                None => return false,
+            };
+        info!(
+            "import snapshot bundle {:?}, trusted blame block = {:?}",
+            path, trusted_blame_block
+        );
+
+        // The bundle replaces the state synced from peers before.
+        discard_state_sync_progress(&sync_handler.graph.data_man);
+
+        let mut inner = self.inner.write();
+        match import_snapshot_bundle(
+            &Context {
+                // node_id is not used here
+                node_id: Default::default(),
+                io,
+                manager: sync_handler,
+            },
+            path,
+            trusted_blame_block,
+            &epoch_to_sync,
+        ) {
+            Ok((sync_candidate, related_data)) => {
+                // Keep the candidate era so that `update_status` does not
+                // restart the sync.
+                inner.sync_candidate_manager.reset(
+                    current_era_genesis,
+                    vec![sync_candidate],
+                    vec![],
+                );
+                inner.related_data = Some(related_data);
+                inner.status = Status::Completed;
+                info!("snapshot bundle imported, epoch = {:?}", epoch_to_sync);
+                true
+            }
+            Err(e) => {
+                warn!(
+                    "failed to import snapshot bundle, sync state from peers: {:?}",
+                    e
+                );
+                false
+            }
+        }
+    }
+
     /// TODO Handling manifest requesting separately
     /// Return Some if a candidate is ready and we can start requesting
     /// manifests
@@ -531,6 +693,8 @@ pub struct StateSyncConfiguration {
     pub chunk_request_timeout: Duration,
     pub manifest_request_timeout: Duration,
     pub max_downloading_manifest_attempts: usize,
+    /// The snapshot bundle file and the trusted blame block to verify it.
+    pub snapshot_bundle_to_import: Option<(PathBuf, H256)>,
 }
 
 impl StateSyncConfiguration {
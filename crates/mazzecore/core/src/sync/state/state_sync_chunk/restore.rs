@@ -4,9 +4,10 @@
 
 use crate::sync::state::storage::{Chunk, ChunkKey};
 use mazze_storage::{
-    state_manager::StateManager,
+    state_manager::{SnapshotDbManager, StateManager},
     storage_db::{SnapshotDbManagerTrait, SnapshotInfo},
-    FullSyncVerifier, Result as StorageResult, SnapshotDbManagerSqlite,
+    FullSyncVerifier, RestoredChunkBoundary, Result as StorageResult,
+    TrieProof,
 };
 use primitives::{EpochId, MerkleHash, NULL_EPOCH};
 use std::sync::Arc;
@@ -17,7 +18,7 @@ pub struct Restorer {
 
     /// The verifier for chunks.
     /// Initialized after receiving a valid manifest.
-    verifier: Option<FullSyncVerifier<SnapshotDbManagerSqlite>>,
+    verifier: Option<FullSyncVerifier<SnapshotDbManager>>,
 }
 
 impl Restorer {
@@ -31,34 +32,108 @@ impl Restorer {
         }
     }
 
+    /// Create a restorer of the snapshot described by `snapshot_info` whose
+    /// chunks are cut at `chunk_boundaries`.
+    pub fn new_with_full_sync_verifier(
+        snapshot_epoch_id: EpochId, snapshot_info: &SnapshotInfo,
+        chunk_boundaries: Vec<Vec<u8>>, chunk_boundary_proofs: Vec<TrieProof>,
+        state_manager: &StateManager,
+    ) -> StorageResult<Self> {
+        let mut restorer =
+            Restorer::new(snapshot_epoch_id, snapshot_info.merkle_root);
+
+        let verifier = FullSyncVerifier::new(
+            chunk_boundaries.len() + 1,
+            chunk_boundaries,
+            chunk_boundary_proofs,
+            snapshot_info.merkle_root,
+            state_manager
+                .get_storage_manager()
+                .get_snapshot_manager()
+                .get_snapshot_db_manager(),
+            snapshot_info.get_snapshot_epoch_id(),
+            snapshot_info.height,
+        )?;
+
+        restorer.initialize_verifier(verifier);
+        Ok(restorer)
+    }
+
+    /// Reopen the restorer of a snapshot whose restoration was interrupted
+    /// by a restart. Return `None` if the temp snapshot doesn't exist.
+    pub fn open_with_full_sync_verifier(
+        snapshot_epoch_id: EpochId, snapshot_info: &SnapshotInfo,
+        chunk_boundaries: Vec<Vec<u8>>, chunk_boundary_proofs: Vec<TrieProof>,
+        state_manager: &StateManager,
+    ) -> StorageResult<Option<Self>> {
+        let mut restorer =
+            Restorer::new(snapshot_epoch_id, snapshot_info.merkle_root);
+
+        let verifier = match FullSyncVerifier::open(
+            chunk_boundaries.len() + 1,
+            chunk_boundaries,
+            chunk_boundary_proofs,
+            snapshot_info.merkle_root,
+            state_manager
+                .get_storage_manager()
+                .get_snapshot_manager()
+                .get_snapshot_db_manager(),
+            snapshot_info.get_snapshot_epoch_id(),
+            snapshot_info.height,
+        )? {
+            Some(verifier) => verifier,
+            None => return Ok(None),
+        };
+
+        restorer.initialize_verifier(verifier);
+        Ok(Some(restorer))
+    }
+
     pub fn initialize_verifier(
-        &mut self, verifier: FullSyncVerifier<SnapshotDbManagerSqlite>,
+        &mut self, verifier: FullSyncVerifier<SnapshotDbManager>,
     ) {
         self.verifier = Some(verifier);
     }
 
     /// Append a chunk for restoration.
     pub fn append(&mut self, key: ChunkKey, chunk: Chunk) -> bool {
+        self.append_with_boundary(key, chunk).is_some()
+    }
+
+    /// Append a chunk for restoration, and return its boundary nodes to
+    /// persist if it's restored.
+    pub fn append_with_boundary(
+        &mut self, key: ChunkKey, chunk: Chunk,
+    ) -> Option<RestoredChunkBoundary> {
         match &mut self.verifier {
             // Not waiting for chunks
-            None => false,
+            None => None,
             Some(verifier) => {
-                match verifier.restore_chunk(
+                match verifier.restore_chunk_with_boundary(
                     &key.upper_bound_excl,
                     &chunk.keys,
                     chunk.values,
                 ) {
-                    Ok(true) => true,
-                    Ok(false) => false,
+                    Ok(restored_chunk_boundary) => restored_chunk_boundary,
                     Err(e) => {
                         warn!("error for restore_chunk: err={:?}", e);
-                        false
+                        None
                     }
                 }
             }
         }
     }
 
+    /// Add back a chunk restored before a restart.
+    pub fn resume_chunk(
+        &mut self, restored_chunk_boundary: &RestoredChunkBoundary,
+    ) -> StorageResult<()> {
+        match &mut self.verifier {
+            None => Ok(()),
+            Some(verifier) => verifier.resume_chunk(restored_chunk_boundary),
+        }
+    }
+
     /// Start to restore chunks asynchronously.
     pub fn finalize_restoration(
         &mut self, state_manager: Arc<StateManager>,
@@ -2,14 +2,14 @@ use crate::sync::{
     message::{msgid, Context, SnapshotChunkRequest},
     state::{
         state_sync_chunk::restore::Restorer,
+        state_sync_progress::remove_state_sync_progress,
         storage::{Chunk, ChunkKey, RangedManifest, SnapshotSyncCandidate},
     },
     synchronization_state::PeerFilter,
 };
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use mazze_storage::{
-    storage_db::SnapshotInfo, FullSyncVerifier, Result as StorageResult,
-    TrieProof,
+    storage_db::SnapshotInfo, Result as StorageResult, TrieProof,
 };
 use network::node_table::NodeId;
 use primitives::MerkleHash;
@@ -43,28 +43,14 @@ impl SnapshotChunkManager {
         active_peers: HashSet<NodeId>, config: SnapshotChunkConfig,
         intermediate_trie_root_merkle: MerkleHash,
     ) -> StorageResult<Self> {
-        let mut restorer = Restorer::new(
+        let restorer = Restorer::new_with_full_sync_verifier(
             *snapshot_candidate.get_snapshot_epoch_id(),
-            snapshot_info.merkle_root,
-        );
-
-        let verifier = FullSyncVerifier::new(
-            chunk_boundaries.len() + 1,
+            &snapshot_info,
             chunk_boundaries.clone(),
-            chunk_boundary_proofs.clone(),
-            snapshot_info.merkle_root,
-            ctx.manager
-                .graph
-                .data_man
-                .storage_manager
-                .get_storage_manager()
-                .get_snapshot_manager()
-                .get_snapshot_db_manager(),
-            snapshot_info.get_snapshot_epoch_id(),
-            snapshot_info.height,
+            chunk_boundary_proofs,
+            &ctx.manager.graph.data_man.storage_manager,
         )?;
 
-        restorer.initialize_verifier(verifier);
         let chunks =
             RangedManifest::convert_boundaries_to_chunks(chunk_boundaries);
         let mut chunk_manager = Self {
@@ -84,6 +70,66 @@ impl SnapshotChunkManager {
         Ok(chunk_manager)
     }
 
+    /// Resume the restoration of a snapshot interrupted by a restart. The
+    /// chunks restored before the restart are added back from the db, and
+    /// the others are requested once active peers are set.
+    /// Return `None` if the temp snapshot of the restoration is gone.
+    pub fn resume(
+        ctx: &Context, snapshot_candidate: SnapshotSyncCandidate,
+        snapshot_info: SnapshotInfo,
+        parent_snapshot_info: Option<SnapshotInfo>,
+        chunk_boundaries: Vec<Vec<u8>>, chunk_boundary_proofs: Vec<TrieProof>,
+        config: SnapshotChunkConfig, intermediate_trie_root_merkle: MerkleHash,
+    ) -> StorageResult<Option<Self>> {
+        let data_man = &ctx.manager.graph.data_man;
+        let mut restorer = match Restorer::open_with_full_sync_verifier(
+            *snapshot_candidate.get_snapshot_epoch_id(),
+            &snapshot_info,
+            chunk_boundaries.clone(),
+            chunk_boundary_proofs,
+            &data_man.storage_manager,
+        )? {
+            Some(restorer) => restorer,
+            None => return Ok(None),
+        };
+
+        let mut pending_chunks = VecDeque::new();
+        let mut num_restored = 0;
+        for (chunk_index, chunk_key) in
+            RangedManifest::convert_boundaries_to_chunks(chunk_boundaries)
+                .into_iter()
+                .enumerate()
+        {
+            match data_man.db_manager.restored_chunk_from_db(chunk_index) {
+                Some(restored_chunk_boundary) => {
+                    restorer.resume_chunk(&restored_chunk_boundary)?;
+                    num_restored += 1;
+                }
+                None => pending_chunks.push_back(chunk_key),
+            }
+        }
+        info!(
+            "Resume the restoration of snapshot {:?}, {} chunks restored, {} chunks pending",
+            snapshot_candidate,
+            num_restored,
+            pending_chunks.len()
+        );
+
+        Ok(Some(Self {
+            snapshot_candidate,
+            snapshot_info,
+            parent_snapshot_info,
+            active_peers: Default::default(),
+            pending_chunks,
+            downloading_chunks: Default::default(),
+            downloading_attempts: Default::default(),
+            num_downloaded: num_restored,
+            config,
+            restorer,
+            intermediate_trie_root_merkle,
+        }))
+    }
+
     /// Add a received chunk, and request new ones if needed.
     /// Return `Ok(true)` if all chunks have been received and the snapshot is
     /// reconstructed. Return `Ok(false)` if there are chunks missing.
@@ -105,30 +151,53 @@ impl SnapshotChunkManager {
 
         self.num_downloaded += 1;
 
-        if !self.restorer.append(chunk_key.clone(), chunk) {
-            warn!("Receive invalid chunk during appending {:?}", chunk_key);
-            self.pending_chunks.push_back(chunk_key);
-            self.note_failure(&ctx.node_id)
+        match self.restorer.append_with_boundary(chunk_key.clone(), chunk) {
+            Some(restored_chunk_boundary) => {
+                ctx.manager
+                    .graph
+                    .data_man
+                    .db_manager
+                    .insert_restored_chunk_to_db(&restored_chunk_boundary);
+            }
+            None => {
+                warn!("Receive invalid chunk during appending {:?}", chunk_key);
+                self.pending_chunks.push_back(chunk_key);
+                self.note_failure(&ctx.node_id)
+            }
         }
 
         // begin to restore if all chunks downloaded
-        if self.downloading_chunks.is_empty() && self.pending_chunks.is_empty()
-        {
-            debug!("Snapshot chunks are all downloaded",);
-
-            // start to restore and update status
-            self.restorer.finalize_restoration(
-                ctx.manager.graph.data_man.storage_manager.clone(),
-                self.snapshot_info.clone(),
-                self.parent_snapshot_info.clone(),
-                self.intermediate_trie_root_merkle.clone(),
-            )?;
+        if self.finalize_if_all_restored(ctx)? {
             return Ok(true);
         }
         self.request_chunks(ctx);
         Ok(false)
     }
 
+    /// Finalize the restoration if all chunks have been restored, and return
+    /// whether it's finalized.
+    pub fn finalize_if_all_restored(
+        &mut self, ctx: &Context,
+    ) -> StorageResult<bool> {
+        if !self.downloading_chunks.is_empty()
+            || !self.pending_chunks.is_empty()
+        {
+            return Ok(false);
+        }
+        debug!("Snapshot chunks are all downloaded",);
+
+        // start to restore and update status
+        let data_man = &ctx.manager.graph.data_man;
+        self.restorer.finalize_restoration(
+            data_man.storage_manager.clone(),
+            self.snapshot_info.clone(),
+            self.parent_snapshot_info.clone(),
+            self.intermediate_trie_root_merkle.clone(),
+        )?;
+        remove_state_sync_progress(data_man);
+        Ok(true)
+    }
+
     fn request_chunk_from_peer(
         &mut self, ctx: &Context, peer: &NodeId,
     ) -> Option<ChunkKey> {
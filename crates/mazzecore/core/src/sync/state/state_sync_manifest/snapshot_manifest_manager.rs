@@ -8,7 +8,10 @@ use crate::{
         message::{
             msgid, Context, SnapshotManifestRequest, SnapshotManifestResponse,
         },
-        state::storage::SnapshotSyncCandidate,
+        state::{
+            state_sync_progress::StateSyncProgress,
+            storage::{RangedManifest, SnapshotSyncCandidate},
+        },
         synchronization_state::PeerFilter,
         SynchronizationProtocolHandler,
     },
@@ -46,6 +49,10 @@ pub struct SnapshotManifestManager {
     pub chunk_boundaries: Vec<Vec<u8>>,
     pub chunk_boundary_proofs: Vec<TrieProof>,
 
+    /// The blame states of the initial manifest, kept to persist the sync
+    /// progress.
+    state_root_vec: Vec<StateRoot>,
+    block_receipts: Vec<BlockExecutionResult>,
     related_data: Option<RelatedData>,
     config: SnapshotManifestConfig,
 }
@@ -76,6 +83,8 @@ impl SnapshotManifestManager {
             active_peers,
             chunk_boundaries: vec![],
             chunk_boundary_proofs: vec![],
+            state_root_vec: vec![],
+            block_receipts: vec![],
             related_data: None,
             config,
         };
@@ -126,66 +135,36 @@ impl SnapshotManifestManager {
                     "Initial manifest is not expected".into(),
                 ));
             }
-            let (
-                blame_vec_offset,
-                state_root_with_aux_info,
-                snapshot_info,
-                parent_snapshot_info,
-            ) = match Self::validate_blame_states(
+            let related_data = match Self::validate_related_data(
                 ctx,
                 self.snapshot_candidate.get_snapshot_epoch_id(),
                 &self.trusted_blame_block,
                 &response.state_root_vec,
-                &response.receipt_blame_vec,
-                &response.bloom_blame_vec,
+                response.receipt_blame_vec,
+                response.bloom_blame_vec,
+                &response.block_receipts,
             ) {
-                Some(info_tuple) => info_tuple,
-                None => {
-                    warn!("failed to validate the blame state, re-sync manifest from other peer");
+                Ok(related_data) => related_data,
+                Err(e) => {
+                    warn!("failed to validate the manifest, re-sync manifest from other peer: {:?}", e);
                     self.resync_manifest(ctx);
-                    bail!(ErrorKind::InvalidSnapshotManifest(
-                        "invalid blame state in manifest".into(),
-                    ));
+                    return Err(e);
                 }
             };
 
-            let epoch_receipts =
-                match SnapshotManifestManager::validate_epoch_receipts(
-                    ctx,
-                    blame_vec_offset,
-                    self.snapshot_candidate.get_snapshot_epoch_id(),
-                    &response.receipt_blame_vec,
-                    &response.bloom_blame_vec,
-                    &response.block_receipts,
-                ) {
-                    Some(epoch_receipts) => epoch_receipts,
-                    None => {
-                        warn!("failed to validate the epoch receipts, re-sync manifest from other peer");
-                        self.resync_manifest(ctx);
-                        bail!(ErrorKind::InvalidSnapshotManifest(
-                            "invalid epoch receipts in manifest".into(),
-                        ));
-                    }
-                };
-
             // Check proofs for keys.
-            if let Err(e) =
-                response.manifest.validate(&snapshot_info.merkle_root)
+            if let Err(e) = response
+                .manifest
+                .validate(&related_data.snapshot_info.merkle_root)
             {
                 warn!("failed to validate snapshot manifest, error = {:?}", e);
                 bail!(ErrorKind::InvalidSnapshotManifest(
                     "invalid chunk proofs in manifest".into(),
                 ));
             }
-            self.related_data = Some(RelatedData {
-                true_state_root_by_blame_info: state_root_with_aux_info,
-                blame_vec_offset,
-                receipt_blame_vec: response.receipt_blame_vec,
-                bloom_blame_vec: response.bloom_blame_vec,
-                epoch_receipts,
-                snapshot_info,
-                parent_snapshot_info,
-            });
+            self.state_root_vec = response.state_root_vec;
+            self.block_receipts = response.block_receipts;
+            self.related_data = Some(related_data);
         } else {
             if self.chunk_boundaries.is_empty() {
                 bail!(ErrorKind::InvalidSnapshotManifest(
@@ -284,6 +263,77 @@ impl SnapshotManifestManager {
         self.active_peers.is_empty()
     }
 
+    /// Validate the blame states and the epoch receipts of the snapshot
+    /// against `trusted_blame_block`, and return the data to restore the
+    /// execution state of the snapshot.
+    pub fn validate_related_data(
+        ctx: &Context, snapshot_epoch_id: &EpochId, trusted_blame_block: &H256,
+        state_root_vec: &Vec<StateRoot>, receipt_blame_vec: Vec<H256>,
+        bloom_blame_vec: Vec<H256>, block_receipts: &Vec<BlockExecutionResult>,
+    ) -> Result<RelatedData, Error> {
+        let (
+            blame_vec_offset,
+            state_root_with_aux_info,
+            snapshot_info,
+            parent_snapshot_info,
+        ) = match Self::validate_blame_states(
+            ctx,
+            snapshot_epoch_id,
+            trusted_blame_block,
+            state_root_vec,
+            &receipt_blame_vec,
+            &bloom_blame_vec,
+        ) {
+            Some(info_tuple) => info_tuple,
+            None => bail!(ErrorKind::InvalidSnapshotManifest(
+                "invalid blame state in manifest".into(),
+            )),
+        };
+
+        let epoch_receipts = match Self::validate_epoch_receipts(
+            ctx,
+            blame_vec_offset,
+            snapshot_epoch_id,
+            &receipt_blame_vec,
+            &bloom_blame_vec,
+            block_receipts,
+        ) {
+            Some(epoch_receipts) => epoch_receipts,
+            None => bail!(ErrorKind::InvalidSnapshotManifest(
+                "invalid epoch receipts in manifest".into(),
+            )),
+        };
+
+        Ok(RelatedData {
+            true_state_root_by_blame_info: state_root_with_aux_info,
+            blame_vec_offset,
+            receipt_blame_vec,
+            bloom_blame_vec,
+            epoch_receipts,
+            snapshot_info,
+            parent_snapshot_info,
+        })
+    }
+
+    /// The progress to persist once the whole manifest is received, from
+    /// which the sync can be resumed after a restart.
+    pub fn state_sync_progress(&self) -> Option<StateSyncProgress> {
+        let related_data = self.related_data.as_ref()?;
+        Some(StateSyncProgress::new(
+            self.snapshot_candidate.clone(),
+            self.trusted_blame_block,
+            self.state_root_vec.clone(),
+            related_data.receipt_blame_vec.clone(),
+            related_data.bloom_blame_vec.clone(),
+            self.block_receipts.clone(),
+            RangedManifest {
+                chunk_boundaries: self.chunk_boundaries.clone(),
+                chunk_boundary_proofs: self.chunk_boundary_proofs.clone(),
+                next: None,
+            },
+        ))
+    }
+
     pub fn validate_blame_states(
         ctx: &Context, snapshot_epoch_id: &H256, trusted_blame_block: &H256,
         state_root_vec: &Vec<StateRoot>, receipt_blame_vec: &Vec<H256>,
@@ -0,0 +1,189 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! The progress of state sync is persisted in the blocks db, so that a node
+//! restarted in the middle of state sync resumes restoring the same snapshot
+//! instead of syncing it from scratch.
+//!
+//! The key-values of restored chunks are kept in the temp snapshot of full
+//! sync, and the boundary nodes of each restored chunk are persisted next to
+//! the manifest of the snapshot.
+
+use crate::{
+    block_data_manager::{BlockDataManager, BlockExecutionResult},
+    sync::{
+        message::Context,
+        state::{
+            state_sync_manifest::snapshot_manifest_manager::{
+                RelatedData, SnapshotManifestManager,
+            },
+            storage::{RangedManifest, SnapshotSyncCandidate},
+        },
+        Error,
+    },
+};
+use mazze_types::H256;
+use primitives::{EpochId, StateRoot};
+use rlp::DecoderError;
+use rlp_derive::{RlpDecodable, RlpEncodable};
+
+/// Bump the version for incompatible changes of the persisted progress.
+const STATE_SYNC_PROGRESS_VERSION: u64 = 1;
+
+/// The manifest of the snapshot being restored, along with the blame states
+/// to validate it.
+#[derive(RlpDecodable, RlpEncodable)]
+pub struct StateSyncProgress {
+    version: u64,
+    pub snapshot_to_sync: SnapshotSyncCandidate,
+    pub trusted_blame_block: H256,
+    pub state_root_vec: Vec<StateRoot>,
+    pub receipt_blame_vec: Vec<H256>,
+    pub bloom_blame_vec: Vec<H256>,
+    pub block_receipts: Vec<BlockExecutionResult>,
+    pub manifest: RangedManifest,
+}
+
+impl_db_encoding_as_rlp!(StateSyncProgress);
+
+impl StateSyncProgress {
+    pub fn new(
+        snapshot_to_sync: SnapshotSyncCandidate, trusted_blame_block: H256,
+        state_root_vec: Vec<StateRoot>, receipt_blame_vec: Vec<H256>,
+        bloom_blame_vec: Vec<H256>, block_receipts: Vec<BlockExecutionResult>,
+        manifest: RangedManifest,
+    ) -> Self {
+        Self {
+            version: STATE_SYNC_PROGRESS_VERSION,
+            snapshot_to_sync,
+            trusted_blame_block,
+            state_root_vec,
+            receipt_blame_vec,
+            bloom_blame_vec,
+            block_receipts,
+            manifest,
+        }
+    }
+
+    /// Check that the persisted snapshot is still the one to sync and that
+    /// it's still trusted by consensus, whose trusted blame block may have
+    /// changed across the restart. The blame states and the manifest are
+    /// validated again as a newly received manifest.
+    pub fn validate(
+        self, ctx: &Context, epoch_to_sync: &EpochId,
+    ) -> Result<(SnapshotSyncCandidate, RangedManifest, RelatedData), Error>
+    {
+        if self.version != STATE_SYNC_PROGRESS_VERSION {
+            bail!("unsupported state sync progress version {}", self.version);
+        }
+        let snapshot_epoch_id = *self.snapshot_to_sync.get_snapshot_epoch_id();
+        if snapshot_epoch_id != *epoch_to_sync {
+            bail!(
+                "snapshot {:?} is no longer the epoch to sync {:?}",
+                snapshot_epoch_id,
+                epoch_to_sync
+            );
+        }
+        let trusted_blame_block = ctx
+            .manager
+            .graph
+            .consensus
+            .get_trusted_blame_block_for_snapshot(&snapshot_epoch_id);
+        if trusted_blame_block != Some(self.trusted_blame_block) {
+            bail!(
+                "blame block {:?} of snapshot {:?} is no longer trusted, trusted blame block = {:?}",
+                self.trusted_blame_block,
+                snapshot_epoch_id,
+                trusted_blame_block
+            );
+        }
+        if self.manifest.next.is_some() {
+            bail!("incomplete manifest of snapshot {:?}", snapshot_epoch_id);
+        }
+
+        let related_data = SnapshotManifestManager::validate_related_data(
+            ctx,
+            &snapshot_epoch_id,
+            &self.trusted_blame_block,
+            &self.state_root_vec,
+            self.receipt_blame_vec,
+            self.bloom_blame_vec,
+            &self.block_receipts,
+        )?;
+        self.manifest
+            .validate(&related_data.snapshot_info.merkle_root)?;
+
+        Ok((self.snapshot_to_sync, self.manifest, related_data))
+    }
+}
+
+/// Remove the persisted progress, including the restored chunks.
+pub fn remove_state_sync_progress(data_man: &BlockDataManager) {
+    let db_manager = &data_man.db_manager;
+    if let Some(progress) = db_manager.state_sync_progress_from_db() {
+        for chunk_index in 0..progress.manifest.chunk_boundaries.len() + 1 {
+            db_manager.remove_restored_chunk_from_db(chunk_index);
+        }
+    }
+    db_manager.remove_state_sync_progress_from_db();
+}
+
+/// Remove the persisted progress and the temp snapshot it refers to, when
+/// the restoration can't be resumed.
+pub fn discard_state_sync_progress(data_man: &BlockDataManager) {
+    remove_state_sync_progress(data_man);
+    if let Err(e) = data_man
+        .storage_manager
+        .get_storage_manager()
+        .get_snapshot_manager()
+        .get_snapshot_db_manager()
+        .destroy_temp_snapshots_for_full_sync()
+    {
+        warn!("failed to remove temp snapshots of full sync: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazze_internal_common::{DatabaseDecodable, DatabaseEncodable};
+
+    #[test]
+    fn test_state_sync_progress_db_encoding() {
+        let progress = StateSyncProgress::new(
+            SnapshotSyncCandidate::FullSync {
+                height: 2000,
+                snapshot_epoch_id: H256::repeat_byte(1),
+            },
+            H256::repeat_byte(2),
+            vec![StateRoot::genesis(&Default::default()); 3],
+            vec![H256::repeat_byte(3); 3],
+            vec![H256::repeat_byte(4); 3],
+            vec![],
+            RangedManifest {
+                chunk_boundaries: vec![vec![5, 6]],
+                chunk_boundary_proofs: vec![Default::default()],
+                next: None,
+            },
+        );
+
+        let decoded =
+            StateSyncProgress::db_decode(&progress.db_encode()).unwrap();
+        assert_eq!(decoded.version, STATE_SYNC_PROGRESS_VERSION);
+        assert_eq!(decoded.snapshot_to_sync, progress.snapshot_to_sync);
+        assert_eq!(decoded.trusted_blame_block, progress.trusted_blame_block);
+        assert_eq!(decoded.state_root_vec, progress.state_root_vec);
+        assert_eq!(decoded.receipt_blame_vec, progress.receipt_blame_vec);
+        assert_eq!(decoded.bloom_blame_vec, progress.bloom_blame_vec);
+        assert_eq!(
+            decoded.manifest.chunk_boundaries,
+            progress.manifest.chunk_boundaries
+        );
+        assert_eq!(
+            decoded.manifest.chunk_boundary_proofs,
+            progress.manifest.chunk_boundary_proofs
+        );
+        assert!(decoded.manifest.next.is_none());
+    }
+}
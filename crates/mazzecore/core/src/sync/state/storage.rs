@@ -9,7 +9,7 @@ use mazze_storage::{
     rlp_key_value_len,
     storage_db::{
         key_value_db::KeyValueDbIterableTrait, snapshot_db::SnapshotDbTrait,
-        OpenSnapshotMptTrait,
+        OpenSnapshotMptTrait, SnapshotMptTraitRead,
     },
     MptSlicer, StorageManager, TrieProof,
 };
@@ -267,7 +267,7 @@ impl RangedManifest {
             }
         };
         let mut snapshot_mpt = snapshot_db.open_snapshot_mpt_shared()?;
-        let merkle_root = snapshot_mpt.merkle_root;
+        let merkle_root = snapshot_mpt.get_merkle_root();
         let mut slicer = match start_key {
             Some(ref key) => MptSlicer::new_from_key(&mut snapshot_mpt, key)?,
             None => MptSlicer::new(&mut snapshot_mpt)?,
@@ -37,10 +37,12 @@ use crate::{
     channel::Channel,
     consensus::SharedConsensusGraph,
     error::{BlockError, Error, ErrorKind},
-    pow::{PowComputer, ProofOfWorkConfig},
+    pow::{PowComputer, PowHashPool, ProofOfWorkConfig},
     state_exposer::{SyncGraphBlockState, STATE_EXPOSER},
     statistics::SharedStatistics,
-    sync::synchronization_protocol_handler::FutureBlockContainer,
+    sync::{
+        synchronization_protocol_handler::FutureBlockContainer, SyncPhaseType,
+    },
     verification::*,
     ConsensusGraph, Notifications,
 };
@@ -65,6 +67,7 @@ pub struct SyncGraphConfig {
     pub future_block_buffer_capacity: usize,
     pub enable_state_expose: bool,
     pub is_consortium: bool,
+    pub pow_verification_threads: usize,
 }
 
 #[derive(Debug)]
@@ -898,10 +901,17 @@ pub struct SynchronizationGraph {
     /// Each element is <block_hash, ignore_body>
     new_block_hashes: Arc<Channel<H256>>,
 
+    /// Channel used to publish the sync phase changes to PubSub, along with
+    /// the number of blocks in the sync graph.
+    pub sync_phase_changes: Arc<Channel<(SyncPhaseType, usize)>>,
+
     /// The blocks whose timestamps are near future.
     /// They will be inserted into sync graph inner at their timestamp.
     pub future_blocks: FutureBlockContainer,
 
+    /// Threads computing the PoW hashes of header batches received in sync.
+    pow_hash_pool: PowHashPool,
+
     machine: Arc<Machine>,
 }
 
@@ -963,10 +973,15 @@ impl SynchronizationGraph {
             pow: pow.clone(),
             verification_config,
             sync_config,
+            pow_hash_pool: PowHashPool::new(
+                pow.clone(),
+                sync_config.pow_verification_threads,
+            ),
             consensus: consensus.clone(),
             statistics: consensus.get_statistics().clone(),
             consensus_unprocessed_count: consensus_unprocessed_count.clone(),
             new_block_hashes: notifications.new_block_hashes.clone(),
+            sync_phase_changes: notifications.sync_phase_changes.clone(),
             machine,
         };
 
@@ -1243,6 +1258,45 @@ impl SynchronizationGraph {
             || self.future_blocks.contains(hash)
     }
 
+    /// Computes the PoW hashes of received headers in parallel before they
+    /// are inserted one by one, so that `insert_block_header` only compares
+    /// the filled `pow_hash` against the difficulty. Headers already known
+    /// are skipped.
+    pub fn verify_header_pow_batch(&self, headers: &mut [BlockHeader]) {
+        if self.is_consortium() || self.inner.read().locked_for_catchup {
+            return;
+        }
+        let mut batch: Vec<_> = headers
+            .iter_mut()
+            .filter(|header| {
+                header.pow_hash.is_none()
+                    && !self.contains_block_header(&header.hash())
+            })
+            .map(|header| {
+                let seed_hash = self
+                    .data_man
+                    .db_manager
+                    .get_current_seed_hash(header.height());
+                (header, seed_hash)
+            })
+            .collect();
+        if batch.len() <= 1 {
+            return;
+        }
+
+        let invalid = self
+            .verification_config
+            .verify_pow_batch(&self.pow_hash_pool, &mut batch)
+            .iter()
+            .filter(|result| result.is_err())
+            .count();
+        debug!(
+            "verify_header_pow_batch: {} headers, {} with invalid PoW",
+            batch.len(),
+            invalid
+        );
+    }
+
     fn parent_or_referees_invalid(&self, header: &BlockHeader) -> bool {
         self.data_man.verified_invalid(header.parent_hash()).0
             || header
@@ -1421,6 +1475,8 @@ impl SynchronizationGraph {
                         &self.pow,
                         header,
                         &block_seed_hash,
+                        self.verification_config
+                            .pow_lower_bound_enabled(header.height()),
                     );
                 }
                 return (
@@ -1438,6 +1494,8 @@ impl SynchronizationGraph {
                     &self.pow,
                     header,
                     &block_seed_hash,
+                    self.verification_config
+                        .pow_lower_bound_enabled(header.height()),
                 );
             }
             return (
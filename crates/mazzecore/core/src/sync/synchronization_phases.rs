@@ -3,9 +3,11 @@
 // See http://www.gnu.org/licenses/
 
 use crate::{
+    channel::Channel,
     sync::{
+        block_stream::import_blocks,
         message::DynamicCapability,
-        state::{SnapshotChunkSync, Status},
+        state::{discard_state_sync_progress, SnapshotChunkSync, Status},
         synchronization_protocol_handler::SynchronizationProtocolHandler,
         synchronization_state::SynchronizationState,
         SharedSynchronizationGraph,
@@ -15,9 +17,10 @@ use crate::{
 use mazze_internal_common::StateAvailabilityBoundary;
 use mazze_parameters::sync::CATCH_UP_EPOCH_LAG_THRESHOLD;
 use network::NetworkContext;
-use parking_lot::RwLock;
+use parking_lot::{Condvar, Mutex, RwLock};
 use std::{
     collections::HashMap,
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering as AtomicOrdering},
         Arc,
@@ -101,6 +104,7 @@ impl SynchronizationPhaseManagerInner {
 
 pub struct SynchronizationPhaseManager {
     inner: RwLock<SynchronizationPhaseManagerInner>,
+    phase_changes: Arc<Channel<(SyncPhaseType, usize)>>,
 }
 
 impl SynchronizationPhaseManager {
@@ -109,15 +113,21 @@ impl SynchronizationPhaseManager {
         sync_state: Arc<SynchronizationState>,
         sync_graph: SharedSynchronizationGraph,
         state_sync: Arc<SnapshotChunkSync>, consensus: Arc<ConsensusGraph>,
+        blocks_to_import: Option<PathBuf>, exit: Arc<(Mutex<bool>, Condvar)>,
     ) -> Self {
         let sync_manager = SynchronizationPhaseManager {
             inner: RwLock::new(SynchronizationPhaseManagerInner::new(
                 initial_phase_type,
             )),
+            phase_changes: sync_graph.sync_phase_changes.clone(),
         };
 
         sync_manager.register_phase(Arc::new(
-            CatchUpRecoverBlockHeaderFromDbPhase::new(sync_graph.clone()),
+            CatchUpRecoverBlockHeaderFromDbPhase::new(
+                sync_graph.clone(),
+                blocks_to_import,
+                exit,
+            ),
         ));
         sync_manager.register_phase(Arc::new(
             CatchUpSyncBlockHeaderPhase::new(
@@ -158,6 +168,8 @@ impl SynchronizationPhaseManager {
         sync_handler: &SynchronizationProtocolHandler,
     ) {
         self.inner.write().change_phase_to(phase_type);
+        self.phase_changes
+            .send((phase_type, sync_handler.graph.block_count()));
         let current_phase = self.get_current_phase();
         current_phase.start(io, sync_handler);
     }
@@ -177,13 +189,24 @@ impl SynchronizationPhaseManager {
 pub struct CatchUpRecoverBlockHeaderFromDbPhase {
     pub graph: SharedSynchronizationGraph,
     pub recovered: Arc<AtomicBool>,
+    /// The block stream to import after the graph is recovered. It's cleared
+    /// once the import succeeds, so it's not imported again if the phase is
+    /// restarted.
+    pub blocks_to_import: Arc<Mutex<Option<PathBuf>>>,
+    /// Notified to stop the node if the block stream fails to import.
+    pub exit: Arc<(Mutex<bool>, Condvar)>,
 }
 
 impl CatchUpRecoverBlockHeaderFromDbPhase {
-    pub fn new(graph: SharedSynchronizationGraph) -> Self {
+    pub fn new(
+        graph: SharedSynchronizationGraph, blocks_to_import: Option<PathBuf>,
+        exit: Arc<(Mutex<bool>, Condvar)>,
+    ) -> Self {
         CatchUpRecoverBlockHeaderFromDbPhase {
             graph,
             recovered: Arc::new(AtomicBool::new(false)),
+            blocks_to_import: Arc::new(Mutex::new(blocks_to_import)),
+            exit,
         }
     }
 }
@@ -217,8 +240,30 @@ impl SynchronizationPhaseTrait for CatchUpRecoverBlockHeaderFromDbPhase {
         self.recovered.store(false, AtomicOrdering::SeqCst);
         let recovered = self.recovered.clone();
         let graph = self.graph.clone();
+        let blocks_to_import = self.blocks_to_import.clone();
+        let exit = self.exit.clone();
         std::thread::spawn(move || {
             graph.recover_graph_from_db();
+            // Import the block stream before leaving this phase, so that it's
+            // done before syncing block headers from peers.
+            let path = blocks_to_import.lock().clone();
+            if let Some(path) = path {
+                info!("import blocks from {:?}", path);
+                match import_blocks(&graph, &path) {
+                    Ok(_) => *blocks_to_import.lock() = None,
+                    Err(e) => {
+                        // Stay in this phase instead of syncing the blocks
+                        // from peers, and let the node exit.
+                        error!(
+                            "failed to import blocks from {:?}, stopping: {:?}",
+                            path, e
+                        );
+                        *exit.0.lock() = true;
+                        exit.1.notify_all();
+                        return;
+                    }
+                }
+            }
             recovered.store(true, AtomicOrdering::SeqCst);
             info!("finish recover header graph from db");
         });
@@ -379,6 +424,27 @@ impl SynchronizationPhaseTrait for CatchUpCheckpointPhase {
             {
                 *sync_handler.synced_epoch_id.lock() = Some(epoch_to_sync);
             }
+            // The state sync interrupted before is useless now.
+            discard_state_sync_progress(&sync_handler.graph.data_man);
+            return;
+        }
+
+        if self.state_sync.import_snapshot_bundle(
+            current_era_genesis,
+            epoch_to_sync,
+            io,
+            sync_handler,
+        ) {
+            return;
+        }
+
+        // `next` moves to the next phase once the status is `Completed`.
+        if self.state_sync.resume_persisted_sync(
+            current_era_genesis,
+            epoch_to_sync,
+            io,
+            sync_handler,
+        ) {
             return;
         }
 
@@ -38,13 +38,14 @@ use network::{
     throttling::THROTTLING_SERVICE, Error as NetworkError, HandlerWorkType,
     NetworkContext, NetworkProtocolHandler, UpdateNodeOperation,
 };
-use parking_lot::{Mutex, RwLock};
+use parking_lot::{Condvar, Mutex, RwLock};
 use primitives::{Block, BlockHeader, EpochId, SignedTransaction};
 use rand::{prelude::SliceRandom, Rng};
 use rlp::Rlp;
 use std::{
     cmp::{self, min},
     collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    path::PathBuf,
     sync::Arc,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
@@ -446,6 +447,10 @@ pub struct ProtocolConfiguration {
     pub allow_phase_change_without_peer: bool,
     pub min_phase_change_normal_peer_count: usize,
     pub check_status_genesis: bool,
+    /// The block stream to insert into the sync graph after the graph is
+    /// recovered from db and before syncing block headers from peers.
+    #[ignore_malloc_size_of = "insignificant"]
+    pub blocks_to_import: Option<PathBuf>,
 }
 
 impl SynchronizationProtocolHandler {
@@ -455,6 +460,7 @@ impl SynchronizationProtocolHandler {
         initial_sync_phase: SyncPhaseType,
         sync_graph: SharedSynchronizationGraph,
         light_provider: Arc<LightProvider>, consensus: Arc<ConsensusGraph>,
+        exit: Arc<(Mutex<bool>, Condvar)>,
     ) -> Self {
         let sync_state = Arc::new(SynchronizationState::new(
             protocol_config.is_consortium,
@@ -473,6 +479,7 @@ impl SynchronizationProtocolHandler {
         ));
 
         let state_sync = Arc::new(SnapshotChunkSync::new(state_sync_config));
+        let blocks_to_import = protocol_config.blocks_to_import.clone();
 
         Self {
             protocol_version: SYNCHRONIZATION_PROTOCOL_VERSION,
@@ -487,6 +494,8 @@ impl SynchronizationProtocolHandler {
                 sync_graph.clone(),
                 state_sync.clone(),
                 consensus,
+                blocks_to_import,
+                exit,
             ),
             phase_manager_lock: Mutex::new(0),
             recover_public_queue,
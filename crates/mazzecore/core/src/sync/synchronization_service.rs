@@ -8,7 +8,8 @@ use super::{
 use crate::{
     light_protocol::Provider as LightProvider,
     sync::{
-        request_manager::RequestManager, synchronization_phases::SyncPhaseType,
+        block_stream::export_blocks, request_manager::RequestManager,
+        state::export_snapshot_bundle, synchronization_phases::SyncPhaseType,
         synchronization_protocol_handler::ProtocolConfiguration,
         StateSyncConfiguration, SynchronizationPhaseTrait,
     },
@@ -17,8 +18,9 @@ use crate::{
 use malloc_size_of_derive::MallocSizeOf as DeriveMallocSizeOf;
 use mazze_types::H256;
 use network::{NetworkService, ProtocolId};
-use primitives::{transaction::SignedTransaction, Block};
-use std::sync::Arc;
+use parking_lot::{Condvar, Mutex};
+use primitives::{transaction::SignedTransaction, Block, EpochId};
+use std::{path::Path, sync::Arc};
 
 #[derive(DeriveMallocSizeOf)]
 pub struct SynchronizationService {
@@ -36,7 +38,7 @@ impl SynchronizationService {
         protocol_config: ProtocolConfiguration,
         state_sync_config: StateSyncConfiguration,
         initial_sync_phase: SyncPhaseType, light_provider: Arc<LightProvider>,
-        consensus: Arc<ConsensusGraph>,
+        consensus: Arc<ConsensusGraph>, exit: Arc<(Mutex<bool>, Condvar)>,
     ) -> Self {
         let sync_handler = Arc::new(SynchronizationProtocolHandler::new(
             node_type,
@@ -46,6 +48,7 @@ impl SynchronizationService {
             sync_graph.clone(),
             light_provider,
             consensus,
+            exit,
         ));
 
         assert_eq!(sync_handler.is_consortium(), sync_graph.is_consortium());
@@ -103,6 +106,23 @@ impl SynchronizationService {
         self.relay_blocks(vec![hash])
     }
 
+    /// Write the bundle of the local snapshot at `snapshot_epoch_id` to
+    /// `path`, which can be imported by a new node instead of syncing the
+    /// state from peers.
+    pub fn export_snapshot_bundle(
+        &self, snapshot_epoch_id: &EpochId, path: &Path,
+    ) -> Result<(), Error> {
+        export_snapshot_bundle(&self.protocol_handler, snapshot_epoch_id, path)
+    }
+
+    /// Write the blocks of the epochs in `[from_epoch, to_epoch]` to `path`
+    /// in consensus order, which can be imported by another node.
+    pub fn export_blocks(
+        &self, from_epoch: u64, to_epoch: u64, path: &Path,
+    ) -> Result<usize, Error> {
+        export_blocks(&self.protocol_handler.graph, from_epoch, to_epoch, path)
+    }
+
     pub fn expire_block_gc(&self, timeout: u64) {
         let _res = self.network.with_context(
             self.protocol_handler.clone(),
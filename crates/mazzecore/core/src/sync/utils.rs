@@ -66,9 +66,13 @@ pub fn create_simple_block_impl(
         diff
     };
     // To convert pow_quality back to pow_hash can be inaccurate, but it should
-    // be okay in tests.
-    header.pow_hash =
-        Some(pow::pow_quality_to_hash(&pow_quality, &header.nonce()));
+    // be okay in tests. The two-sided target applies from genesis with the
+    // default `CommonParams` used here.
+    header.pow_hash = Some(pow::pow_quality_to_hash(
+        &pow_quality,
+        &header.nonce(),
+        true,
+    ));
     // println!("simple_block: difficulty={:?} pow_hash={:?} pow_quality={}",
     // pow_quality, header.pow_hash,
     // pow::pow_hash_to_quality(&header.pow_hash.unwrap(), &header.nonce()));
@@ -184,11 +188,13 @@ pub fn initialize_synchronization_graph_with_data_manager(
         machine.clone(),
     );
 
+    let notifications = Notifications::init();
     let txpool = Arc::new(TransactionPool::new(
         TxPoolConfig::default(),
         verification_config.clone(),
         data_man.clone(),
         machine.clone(),
+        notifications.new_pending_transactions.clone(),
     ));
     let statistics = Arc::new(Statistics::new());
 
@@ -205,8 +211,8 @@ pub fn initialize_synchronization_graph_with_data_manager(
         future_block_buffer_capacity: 1,
         enable_state_expose: false,
         is_consortium: false,
+        pow_verification_threads: 1,
     };
-    let notifications = Notifications::init();
     let consensus = Arc::new(ConsensusGraph::new(
         ConsensusConfig {
             chain_id: ChainIdParamsInner::new_simple(AllChainID::new(1, 1)),
@@ -12,6 +12,7 @@ extern crate rand;
 pub use self::transaction_pool_inner::TransactionStatus;
 use crate::{
     block_data_manager::BlockDataManager,
+    channel::Channel,
     consensus::BestInformation,
     transaction_pool::{
         nonce_pool::TxWithReadyInfo, transaction_pool_inner::PendingReason,
@@ -138,6 +139,10 @@ pub struct TransactionPool {
     /// If it's `false`, operations on the tx pool will be ignored to save
     /// memory/CPU cost.
     ready_for_mining: AtomicBool,
+
+    /// Channel used to publish the transactions newly inserted through
+    /// `insert_new_transactions` to PubSub.
+    new_pending_transactions: Arc<Channel<Arc<SignedTransaction>>>,
 }
 
 impl MallocSizeOf for TransactionPool {
@@ -167,6 +172,7 @@ impl TransactionPool {
     pub fn new(
         config: TxPoolConfig, verification_config: VerificationConfig,
         data_man: Arc<BlockDataManager>, machine: Arc<Machine>,
+        new_pending_transactions: Arc<Channel<Arc<SignedTransaction>>>,
     ) -> Self {
         let genesis_hash = data_man.true_genesis.hash();
         let inner = TransactionPoolInner::new(
@@ -197,6 +203,7 @@ impl TransactionPool {
             recycle_tx_requests: Mutex::new(Default::default()),
             machine,
             ready_for_mining: AtomicBool::new(false),
+            new_pending_transactions,
         }
     }
 
@@ -474,6 +481,10 @@ impl TransactionPool {
         INSERT_TXS_SUCCESS_TPS.mark(passed_transactions.len());
         INSERT_TXS_FAILURE_TPS.mark(failure.len());
 
+        for tx in &passed_transactions {
+            self.new_pending_transactions.send(tx.clone());
+        }
+
         (passed_transactions, failure)
     }
 
@@ -4,7 +4,7 @@
 
 use crate::{
     error::{BlockError, Error},
-    pow::{self, PowComputer},
+    pow::{self, PowComputer, PowHashPool},
     sync::{Error as SyncError, ErrorKind as SyncErrorKind},
 };
 use mazze_executor::{machine::Machine, spec::TransitionsEpochHeight};
@@ -271,19 +271,26 @@ impl VerificationConfig {
 
     pub fn get_or_fill_header_pow_quality(
         pow: &PowComputer, header: &mut BlockHeader, seed_hash: &H256,
+        pow_lower_bound: bool,
     ) -> U256 {
         let pow_hash =
             Self::get_or_fill_header_pow_hash(pow, header, seed_hash);
-        pow::pow_hash_to_quality(&pow_hash, &header.nonce())
+        pow::pow_hash_to_quality(&pow_hash, &header.nonce(), pow_lower_bound)
     }
 
     pub fn get_or_compute_header_pow_quality(
         pow: &PowComputer, header: &BlockHeader, seed_hash: &H256,
+        pow_lower_bound: bool,
     ) -> U256 {
         let pow_hash = header
             .pow_hash
             .unwrap_or_else(|| Self::compute_pow_hash(pow, header, seed_hash));
-        pow::pow_hash_to_quality(&pow_hash, &header.nonce())
+        pow::pow_hash_to_quality(&pow_hash, &header.nonce(), pow_lower_bound)
+    }
+
+    /// Whether the two-sided PoW target applies to blocks at `height`.
+    pub fn pow_lower_bound_enabled(&self, height: BlockHeight) -> bool {
+        self.machine.params().pow_lower_bound_enabled(height)
     }
 
     fn compute_pow_hash(
@@ -330,8 +337,20 @@ impl VerificationConfig {
         }
 
         let nonce = header.nonce();
-        let hash = pow.compute(&nonce, &header.problem_hash(), seed_hash);
-        let quality = pow::pow_hash_to_quality(&hash, &nonce);
+        // The PoW hash may have been computed already by `verify_pow_batch`
+        let hash = match header.pow_hash {
+            Some(hash) => hash,
+            None => {
+                let hash = Self::compute_pow_hash(pow, header, seed_hash);
+                header.pow_hash = Some(hash);
+                hash
+            }
+        };
+        let quality = pow::pow_hash_to_quality(
+            &hash,
+            &nonce,
+            self.pow_lower_bound_enabled(header.height()),
+        );
         let boundary = pow::difficulty_to_boundary(&difficulty);
 
         if quality <= boundary {
@@ -357,6 +376,42 @@ impl VerificationConfig {
         Ok(())
     }
 
+    /// Verifies the PoW of many headers like `verify_pow`, but computes the
+    /// missing PoW hashes in parallel on `pool`. The hashes are filled into
+    /// the headers, so verifying them again later does not recompute them.
+    pub fn verify_pow_batch(
+        &self, pool: &PowHashPool, batch: &mut [(&mut BlockHeader, H256)],
+    ) -> Vec<Result<(), Error>> {
+        // Same conditions under which `verify_pow` skips hashing
+        let needs_hash = |header: &BlockHeader, seed_hash: &H256| {
+            header.pow_hash.is_none()
+                && !seed_hash.is_zero()
+                && !header.difficulty().is_zero()
+        };
+        if !self.catch_up_mode() {
+            let inputs = batch
+                .iter()
+                .filter(|(header, seed_hash)| needs_hash(header, seed_hash))
+                .map(|(header, seed_hash)| {
+                    (header.nonce(), header.problem_hash(), *seed_hash)
+                })
+                .collect();
+            let mut hashes = pool.compute_batch(inputs).into_iter();
+            for (header, seed_hash) in batch.iter_mut() {
+                if needs_hash(header, seed_hash) {
+                    header.pow_hash = hashes.next();
+                }
+            }
+        }
+
+        batch
+            .iter_mut()
+            .map(|(header, seed_hash)| {
+                self.verify_pow(pool.pow(), header, seed_hash)
+            })
+            .collect()
+    }
+
     /// Check basic header parameters.
     /// This does not require header to be graph or parental tree ready.
     #[inline]
@@ -7,22 +7,38 @@ use mazze_executor::{
     state::{CleanupMode, State},
 };
 
-use super::observer::{
-    exec_tracer::ErrorUnwind, gasman::GasLimitEstimation, Observer,
+use super::{
+    observer::{
+        access_list::{AccessListKey, AccessListTracer},
+        exec_tracer::ErrorUnwind,
+        gasman::GasLimitEstimation,
+        Observer,
+    },
+    parity_trace::{ParityTrace, ParityTraceOptions},
+    tx_outcome::geth_trace_with_prestate,
 };
+use alloy_rpc_types_trace::geth::{GethDebugTracingOptions, GethTrace};
 use mazze_parameters::{collateral::*, consensus::ONE_MAZZE_IN_MAZZY};
 use mazze_statedb::Result as DbResult;
 use mazze_types::{
     address_util::AddressUtil, Address, AddressSpaceUtil, Space, U256,
 };
 use mazze_vm_types::{self as vm, Env, Spec};
-use primitives::{transaction::Action, SignedTransaction, Transaction};
+use primitives::{
+    transaction::Action, AccessList, SignedTransaction, Transaction,
+};
 use std::{
     cmp::{max, min},
+    collections::BTreeSet,
     fmt::Display,
     ops::{Mul, Shl},
 };
 
+/// The maximum number of executions to find a stable access list. As the
+/// access list only changes the intrinsic gas of the transaction, it is
+/// usually stable after the second execution.
+const MAX_ACCESS_LIST_ITERATIONS: usize = 8;
+
 enum SponsoredType {
     Gas,
     Collateral,
@@ -171,6 +187,186 @@ impl<'a> EstimationContext<'a> {
         )
     }
 
+    /// Executes the transaction once with the geth tracer of `observer`,
+    /// which traces with `opts`, after applying the same sender and nonce
+    /// defaults as `transact_virtual`. No gas or storage estimation is
+    /// performed. The trace is `None` if the transaction is not executed.
+    pub fn transact_geth_traced(
+        &mut self, mut tx: SignedTransaction, request: EstimateRequest,
+        observer: Observer, opts: &GethDebugTracingOptions,
+    ) -> DbResult<(ExecutionOutcome, Option<GethTrace>)> {
+        if let Some((outcome, _)) =
+            self.check_gas_limit_sufficiency(&tx, &request)
+        {
+            return Ok((outcome, None));
+        }
+
+        self.process_estimate_request(&mut tx, &request)?;
+
+        let options = TransactOptions {
+            observer,
+            settings: request
+                .transact_settings(ChargeCollateral::EstimateSender),
+        };
+
+        // The checkpoint keeps the state before the transaction for the
+        // prestate tracer.
+        self.state.checkpoint();
+        let outcome = self.as_executive().transact(&tx, options)?;
+        let trace =
+            geth_trace_with_prestate(&outcome, self.state, opts, tx.space())?;
+        self.state.discard_checkpoint();
+        Ok((outcome, trace))
+    }
+
+    /// Generates the EIP-2930 access list of the transaction. The transaction
+    /// is executed with the access list found so far until the list no
+    /// longer changes. Returns the last list with the outcome of executing
+    /// the transaction with it.
+    pub fn create_access_list(
+        &mut self, mut tx: SignedTransaction, request: EstimateRequest,
+    ) -> DbResult<(AccessList, ExecutionOutcome)> {
+        let mut access_list = tx.access_list().cloned().unwrap_or_default();
+        if let Some((outcome, _)) =
+            self.check_gas_limit_sufficiency(&tx, &request)
+        {
+            return Ok((access_list, outcome));
+        }
+
+        self.process_estimate_request(&mut tx, &request)?;
+
+        let space = tx.space();
+        let builtins = match space {
+            Space::Native => self.machine.builtins(),
+            Space::Ethereum => self.machine.builtins_evm(),
+        };
+        let mut excluded: BTreeSet<Address> =
+            builtins.keys().cloned().collect();
+        if space == Space::Native {
+            excluded.extend(self.machine.internal_contracts().keys().cloned());
+        }
+        excluded.insert(tx.sender().address);
+        if let Action::Call(to) = tx.action() {
+            excluded.insert(*to);
+        }
+
+        let mut iterations = 0;
+        loop {
+            if let Some(tx_access_list) = tx.access_list_mut() {
+                *tx_access_list = access_list.clone();
+            }
+            let options = TransactOptions {
+                observer: AccessListTracer::new(&access_list, excluded.clone()),
+                settings: request
+                    .transact_settings(ChargeCollateral::EstimateSender),
+            };
+
+            self.state.checkpoint();
+            let outcome = self.as_executive().transact(&tx, options)?;
+            self.state.revert_to_checkpoint();
+
+            iterations += 1;
+            let new_access_list = match outcome
+                .try_as_executed()
+                .and_then(|executed| executed.ext_result.get::<AccessListKey>())
+            {
+                Some(new_access_list) => new_access_list.clone(),
+                None => return Ok((access_list, outcome)),
+            };
+            if new_access_list == access_list
+                || iterations >= MAX_ACCESS_LIST_ITERATIONS
+            {
+                return Ok((access_list, outcome));
+            }
+            access_list = new_access_list;
+        }
+    }
+
+    /// Executes the transaction once and keeps its effects on the state, so
+    /// that the following transactions of a simulation observe them. With
+    /// `validation`, the transaction is checked like a packed one: the
+    /// sender must afford it, its nonce must match and gas is charged.
+    pub fn transact_simulated(
+        &mut self, mut tx: SignedTransaction, request: EstimateRequest,
+        validation: bool,
+    ) -> DbResult<ExecutionOutcome> {
+        if let Some((outcome, _)) =
+            self.check_gas_limit_sufficiency(&tx, &request)
+        {
+            return Ok(outcome);
+        }
+
+        let settings = if validation {
+            if !request.has_nonce {
+                *tx.nonce_mut() = self.state.nonce(&tx.sender())?;
+            }
+            TransactSettings {
+                charge_collateral: ChargeCollateral::Normal,
+                charge_gas: true,
+                check_epoch_bound: false,
+                check_base_price: true,
+            }
+        } else {
+            self.process_estimate_request(&mut tx, &request)?;
+            request.transact_settings(ChargeCollateral::EstimateSender)
+        };
+
+        let options = TransactOptions {
+            observer: Observer::virtual_call(),
+            settings,
+        };
+        let outcome = self.as_executive().transact(&tx, options)?;
+        if let Some(burnt_fee) =
+            outcome.try_as_executed().and_then(|e| e.burnt_fee)
+        {
+            self.state.burn_by_mip1559(burnt_fee);
+        }
+        Ok(outcome)
+    }
+
+    /// Executes the transaction once with the parity-style tracers and keeps
+    /// its effects on the state, so that the following calls of
+    /// `trace_callMany` observe them.
+    pub fn transact_parity_traced(
+        &mut self, mut tx: SignedTransaction, request: EstimateRequest,
+        options: ParityTraceOptions,
+    ) -> DbResult<(ExecutionOutcome, ParityTrace)> {
+        if let Some((outcome, _)) =
+            self.check_gas_limit_sufficiency(&tx, &request)
+        {
+            let trace = ParityTrace::from_outcome(&outcome, None);
+            return Ok((outcome, trace));
+        }
+
+        self.process_estimate_request(&mut tx, &request)?;
+
+        let transact_options = TransactOptions {
+            observer: Observer::parity_tracer(
+                options.vm_trace,
+                self.spec.cancun_opcodes,
+            ),
+            settings: request
+                .transact_settings(ChargeCollateral::EstimateSender),
+        };
+
+        self.state.checkpoint();
+        let outcome = self.as_executive().transact(&tx, transact_options)?;
+        if let Some(burnt_fee) =
+            outcome.try_as_executed().and_then(|e| e.burnt_fee)
+        {
+            self.state.burn_by_mip1559(burnt_fee);
+        }
+        let state_diff = if options.state_diff {
+            Some(self.state.diff_since_checkpoint()?)
+        } else {
+            None
+        };
+        self.state.discard_checkpoint();
+
+        let trace = ParityTrace::from_outcome(&outcome, state_diff);
+        Ok((outcome, trace))
+    }
+
     fn check_gas_limit_sufficiency(
         &self, tx: &SignedTransaction, request: &EstimateRequest,
     ) -> Option<(ExecutionOutcome, EstimateExt)> {
@@ -488,3 +684,227 @@ impl EstimateRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{B256, U256 as AlloyU256};
+    use alloy_rpc_types_trace::geth::{
+        GethDebugBuiltInTracerType, MuxFrame, PreStateFrame,
+    };
+    use geth_tracer::{to_alloy_address, to_alloy_h256, TxExecContext};
+    use mazze_executor::{
+        machine::{new_machine_with_builtin, VmFactory},
+        substate::Substate,
+    };
+    use mazze_statedb::StateDb;
+    use mazze_storage::{new_storage_manager_for_testing, StorageManagerTrait};
+    use mazze_types::H256;
+    use primitives::transaction::Eip155Transaction;
+    use std::sync::Arc;
+
+    const BALANCE: u64 = 1_000_000;
+
+    // SLOAD(2); SSTORE(1, 0x2a)
+    const CODE: [u8; 10] =
+        [0x60, 0x02, 0x54, 0x50, 0x60, 0x2a, 0x60, 0x01, 0x55, 0x00];
+
+    fn slot(i: u64) -> B256 {
+        to_alloy_h256(H256::from_low_u64_be(i))
+    }
+
+    /// Execute a call from `sender` to `to` with the prestate tracer, on a
+    /// state where `contract` has `CODE` and the value 7 at slot 2.
+    fn trace_call(
+        sender: Address, contract: Address, to: Address, value: u64, gas: u64,
+        diff_mode: bool,
+    ) -> PreStateFrame {
+        let opts = serde_json::from_str(&format!(
+            r#"{{"tracer":"prestateTracer","tracerConfig":{{"diffMode":{}}}}}"#,
+            diff_mode
+        ))
+        .unwrap();
+        match trace_call_with(sender, contract, to, value, gas, opts) {
+            GethTrace::PreStateTracer(frame) => frame,
+            trace => panic!("unexpected trace {:?}", trace),
+        }
+    }
+
+    /// Execute a call from `sender` to `to` with the tracer of `opts`, on a
+    /// state where `contract` has `CODE` and the value 7 at slot 2.
+    fn trace_call_with(
+        sender: Address, contract: Address, to: Address, value: u64, gas: u64,
+        opts: GethDebugTracingOptions,
+    ) -> GethTrace {
+        let storage_manager = new_storage_manager_for_testing();
+        let mut state = State::new(StateDb::new(
+            storage_manager.get_state_for_genesis_write(),
+        ))
+        .unwrap();
+        let machine = Arc::new(new_machine_with_builtin(
+            Default::default(),
+            VmFactory::new(1024 * 32),
+        ));
+        let env = Env::default();
+        let spec = machine.spec(env.number, env.epoch_height);
+
+        let sender = sender.with_evm_space();
+        state
+            .add_balance(&sender, &BALANCE.into(), CleanupMode::NoEmpty)
+            .unwrap();
+        state.add_total_issued(BALANCE.into());
+        state.add_total_evm_tokens(BALANCE.into());
+        let contract = contract.with_evm_space();
+        state
+            .add_balance(&contract, &U256::zero(), CleanupMode::ForceCreate)
+            .unwrap();
+        state
+            .init_code(&contract, CODE.to_vec(), contract.address)
+            .unwrap();
+        state
+            .set_storage(
+                &contract,
+                H256::from_low_u64_be(2).as_bytes().to_vec(),
+                7.into(),
+                contract.address,
+                &mut Substate::new(),
+            )
+            .unwrap();
+
+        let tx = Eip155Transaction {
+            nonce: U256::zero(),
+            gas_price: U256::one(),
+            gas: gas.into(),
+            action: Action::Call(to),
+            value: value.into(),
+            chain_id: Some(1),
+            data: vec![],
+        }
+        .fake_sign_rpc(sender);
+        let request = EstimateRequest {
+            has_sender: true,
+            has_gas_limit: true,
+            has_gas_price: true,
+            has_nonce: false,
+            has_storage_limit: false,
+        };
+        let observer = Observer::geth_tracer(
+            TxExecContext {
+                tx_gas_limit: tx.gas_limit().as_u64(),
+                block_height: env.epoch_height,
+                block_number: env.number,
+            },
+            machine.clone(),
+            opts.clone(),
+        );
+
+        let (outcome, trace) =
+            EstimationContext::new(&mut state, &env, &machine, &spec)
+                .transact_geth_traced(tx, request, observer, &opts)
+                .unwrap();
+        assert!(matches!(outcome, ExecutionOutcome::Finished(_)));
+        trace.unwrap()
+    }
+
+    #[test]
+    fn test_prestate_of_transfer() {
+        let (sender, receiver, contract) = (
+            Address::from_low_u64_be(1),
+            Address::from_low_u64_be(2),
+            Address::from_low_u64_be(3),
+        );
+        let (sender_key, receiver_key) =
+            (to_alloy_address(sender), to_alloy_address(receiver));
+
+        let prestate =
+            match trace_call(sender, contract, receiver, 10, 21_000, false) {
+                PreStateFrame::Default(prestate) => prestate.0,
+                frame => panic!("unexpected frame {:?}", frame),
+            };
+        assert_eq!(
+            prestate[&sender_key].balance,
+            Some(AlloyU256::from(BALANCE))
+        );
+        assert!(prestate.contains_key(&receiver_key));
+        // The contract is not touched.
+        assert!(!prestate.contains_key(&to_alloy_address(contract)));
+
+        let diff =
+            match trace_call(sender, contract, receiver, 10, 21_000, true) {
+                PreStateFrame::Diff(diff) => diff,
+                frame => panic!("unexpected frame {:?}", frame),
+            };
+        assert_eq!(
+            diff.pre[&sender_key].balance,
+            Some(AlloyU256::from(BALANCE))
+        );
+        assert_eq!(
+            diff.post[&sender_key].balance,
+            Some(AlloyU256::from(BALANCE - 10 - 21_000))
+        );
+        assert_eq!(diff.post[&sender_key].nonce, Some(1));
+        assert_eq!(diff.post[&receiver_key].balance, Some(AlloyU256::from(10)));
+    }
+
+    #[test]
+    fn test_prestate_of_storage_write() {
+        let (sender, contract) =
+            (Address::from_low_u64_be(1), Address::from_low_u64_be(3));
+        let contract_key = to_alloy_address(contract);
+
+        // The read slot and the original value of the written slot.
+        let prestate =
+            match trace_call(sender, contract, contract, 0, 100_000, false) {
+                PreStateFrame::Default(prestate) => prestate.0,
+                frame => panic!("unexpected frame {:?}", frame),
+            };
+        let account = &prestate[&contract_key];
+        assert_eq!(account.code.as_deref(), Some(&CODE[..]));
+        assert_eq!(account.storage.get(&slot(2)), Some(&slot(7)));
+        assert_eq!(account.storage.get(&slot(1)), Some(&slot(0)));
+
+        // Only the written slot is changed.
+        let diff =
+            match trace_call(sender, contract, contract, 0, 100_000, true) {
+                PreStateFrame::Diff(diff) => diff,
+                frame => panic!("unexpected frame {:?}", frame),
+            };
+        let post = &diff.post[&contract_key];
+        assert_eq!(post.storage.get(&slot(1)), Some(&slot(0x2a)));
+        assert!(!post.storage.contains_key(&slot(2)));
+        assert!(diff
+            .pre
+            .get(&contract_key)
+            .map_or(true, |pre| !pre.storage.contains_key(&slot(2))));
+    }
+
+    #[test]
+    fn test_prestate_in_mux() {
+        let (sender, contract) =
+            (Address::from_low_u64_be(1), Address::from_low_u64_be(3));
+        let opts = serde_json::from_str(
+            r#"{"tracer":"muxTracer","tracerConfig":{"prestateTracer":{"diffMode":true},"4byteTracer":null}}"#,
+        )
+        .unwrap();
+
+        let mut frames =
+            match trace_call_with(sender, contract, contract, 0, 100_000, opts)
+            {
+                GethTrace::MuxTracer(MuxFrame(frames)) => frames,
+                trace => panic!("unexpected trace {:?}", trace),
+            };
+        let diff = match frames
+            .remove(&GethDebugBuiltInTracerType::PreStateTracer)
+        {
+            Some(GethTrace::PreStateTracer(PreStateFrame::Diff(diff))) => diff,
+            frame => panic!("unexpected frame {:?}", frame),
+        };
+        assert_eq!(
+            diff.post[&to_alloy_address(contract)].storage.get(&slot(1)),
+            Some(&slot(0x2a))
+        );
+        assert!(
+            frames.contains_key(&GethDebugBuiltInTracerType::FourByteTracer)
+        );
+    }
+}
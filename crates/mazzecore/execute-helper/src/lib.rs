@@ -5,7 +5,10 @@ extern crate error_chain;
 
 pub mod estimation;
 pub mod observer;
+pub mod overrides;
+pub mod parity_trace;
 pub mod phantom_tx;
+pub mod simulation;
 pub mod tx_outcome;
 
 pub use observer::exec_tracer;
@@ -0,0 +1,213 @@
+use mazze_executor::{
+    observer::{
+        AsTracer, CallTracer, CheckpointTracer, DrainTrace,
+        InternalTransferTracer, OpcodeTracer, StorageTracer, TracerTrait,
+    },
+    stack::FrameResult,
+};
+use mazze_types::{Address, BigEndianHash, H256, U256};
+use mazze_vm_interpreter::instructions::Instruction;
+use mazze_vm_types::{ActionParams, InterpreterInfo};
+use primitives::{AccessList, AccessListItem};
+use std::collections::{BTreeMap, BTreeSet};
+
+use typemap::ShareDebugMap;
+
+/// Collects the accounts and storage slots accessed by a transaction, to
+/// build its EIP-2930 access list.
+///
+/// As in geth, accounts in `excluded` (the sender, the recipient and the
+/// precompiles) are only listed if one of their storage slots is accessed.
+#[derive(Default)]
+pub struct AccessListTracer {
+    excluded: BTreeSet<Address>,
+    list: BTreeMap<Address, BTreeSet<H256>>,
+    depth: usize,
+}
+
+pub struct AccessListKey;
+
+impl typemap::Key for AccessListKey {
+    type Value = AccessList;
+}
+
+impl AccessListTracer {
+    /// Create a tracer which starts from the access list `init` the
+    /// transaction is executed with.
+    pub fn new(init: &AccessList, excluded: BTreeSet<Address>) -> Self {
+        let mut tracer = AccessListTracer {
+            excluded,
+            ..Default::default()
+        };
+        for item in init {
+            tracer.add_address(item.address);
+            for slot in &item.storage_keys {
+                tracer.add_slot(item.address, *slot);
+            }
+        }
+        tracer
+    }
+
+    fn add_address(&mut self, address: Address) {
+        self.list.entry(address).or_default();
+    }
+
+    fn add_slot(&mut self, address: Address, slot: H256) {
+        self.list.entry(address).or_default().insert(slot);
+    }
+
+    pub fn access_list(&self) -> AccessList {
+        self.list
+            .iter()
+            .filter(|(address, slots)| {
+                !slots.is_empty() || !self.excluded.contains(address)
+            })
+            .map(|(address, slots)| AccessListItem {
+                address: *address,
+                storage_keys: slots.iter().cloned().collect(),
+            })
+            .collect()
+    }
+}
+
+impl DrainTrace for AccessListTracer {
+    fn drain_trace(self, map: &mut ShareDebugMap) {
+        map.insert::<AccessListKey>(self.access_list());
+    }
+}
+
+impl AsTracer for AccessListTracer {
+    fn as_tracer<'a>(&'a mut self) -> Box<dyn 'a + TracerTrait> {
+        Box::new(self)
+    }
+}
+
+impl CheckpointTracer for AccessListTracer {}
+
+impl InternalTransferTracer for AccessListTracer {}
+
+impl StorageTracer for AccessListTracer {}
+
+impl CallTracer for AccessListTracer {
+    fn record_call(&mut self, _params: &ActionParams) {
+        self.depth += 1;
+    }
+
+    fn record_call_result(&mut self, _result: &FrameResult) {
+        self.depth -= 1;
+    }
+
+    fn record_create(&mut self, params: &ActionParams) {
+        // The contract created by the transaction is its recipient.
+        if self.depth == 0 {
+            self.excluded.insert(params.address);
+        }
+        self.depth += 1;
+    }
+
+    fn record_create_result(&mut self, _result: &FrameResult) {
+        self.depth -= 1;
+    }
+}
+
+impl OpcodeTracer for AccessListTracer {
+    fn do_trace_opcode(&self, enabled: &mut bool) {
+        *enabled |= true;
+    }
+
+    fn step(&mut self, interp: &dyn InterpreterInfo) {
+        let stack = interp.stack();
+        let peek = |n: usize| -> Option<U256> {
+            stack.len().checked_sub(n + 1).map(|i| stack[i])
+        };
+        let to_address = |value: U256| Address::from(H256::from_uint(&value));
+
+        let instruction = match Instruction::from_u8(interp.current_opcode()) {
+            Some(instruction) => instruction,
+            None => return,
+        };
+        match instruction {
+            Instruction::SLOAD | Instruction::SSTORE => {
+                if let Some(slot) = peek(0) {
+                    self.add_slot(
+                        interp.contract_address(),
+                        H256::from_uint(&slot),
+                    );
+                }
+            }
+            Instruction::BALANCE
+            | Instruction::EXTCODESIZE
+            | Instruction::EXTCODECOPY
+            | Instruction::EXTCODEHASH
+            | Instruction::SUICIDE => {
+                if let Some(address) = peek(0).map(to_address) {
+                    if !self.excluded.contains(&address) {
+                        self.add_address(address);
+                    }
+                }
+            }
+            Instruction::CALL
+            | Instruction::CALLCODE
+            | Instruction::DELEGATECALL
+            | Instruction::STATICCALL => {
+                if let Some(address) = peek(1).map(to_address) {
+                    if !self.excluded.contains(&address) {
+                        self.add_address(address);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excluded_accounts() {
+        let sender = Address::from_low_u64_be(1);
+        let touched = Address::from_low_u64_be(2);
+        let slot = H256::from_low_u64_be(3);
+        let init = vec![
+            AccessListItem {
+                address: sender,
+                storage_keys: vec![],
+            },
+            AccessListItem {
+                address: touched,
+                storage_keys: vec![],
+            },
+        ];
+        let mut excluded = BTreeSet::new();
+        excluded.insert(sender);
+
+        let mut tracer = AccessListTracer::new(&init, excluded);
+        assert_eq!(
+            tracer.access_list(),
+            vec![AccessListItem {
+                address: touched,
+                storage_keys: vec![],
+            }]
+        );
+
+        // An excluded account is listed once its storage is accessed.
+        tracer.add_slot(sender, slot);
+        tracer.add_slot(touched, slot);
+        tracer.add_slot(touched, slot);
+        assert_eq!(
+            tracer.access_list(),
+            vec![
+                AccessListItem {
+                    address: sender,
+                    storage_keys: vec![slot],
+                },
+                AccessListItem {
+                    address: touched,
+                    storage_keys: vec![slot],
+                },
+            ]
+        );
+    }
+}
@@ -6,7 +6,9 @@ use super::AddressPocket;
 use mazze_bytes::Bytes;
 use mazze_executor::stack::FrameReturn;
 
-use mazze_types::{Address, Bloom, BloomInput, Space, U256};
+use mazze_types::{
+    Address, AddressSpaceUtil, AddressWithSpace, Bloom, BloomInput, Space, U256,
+};
 use mazze_vm_types::{ActionParams, CallType, CreateType, Result as VmResult};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use rlp_derive::{RlpDecodable, RlpEncodable};
@@ -347,6 +349,51 @@ impl InternalTransferAction {
     }
 }
 
+/// Description of a _suicide_ action, the self-destruct of a contract.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suicide {
+    /// The space
+    pub space: Space,
+    /// The destroyed contract.
+    pub address: Address,
+    /// The account receiving the balance of the contract.
+    pub refund_address: Address,
+    /// The balance of the contract.
+    pub balance: U256,
+}
+
+impl Suicide {
+    /// Returns suicide action bloom.
+    /// The bloom contains the contract and refund addresses.
+    pub fn bloom(&self) -> Bloom {
+        let mut bloom = Bloom::default();
+        bloom.accrue(BloomInput::Raw(self.address.as_bytes()));
+        bloom.accrue(BloomInput::Raw(self.refund_address.as_bytes()));
+        bloom
+    }
+}
+
+/// Description of a _reward_ action, the reward of a block given to its
+/// author. It is not produced by transactions, but computed from the block
+/// reward results of consensus.
+#[derive(Debug, Clone, PartialEq, RlpEncodable, RlpDecodable, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reward {
+    /// The author of the block.
+    pub author: Address,
+    /// The total reward, including the transaction fees.
+    pub value: U256,
+}
+
+impl Reward {
+    /// Returns reward action bloom.
+    /// The bloom contains only the author address.
+    pub fn bloom(&self) -> Bloom {
+        BloomInput::Raw(self.author.as_bytes()).into()
+    }
+}
+
 /// Description of an action that we trace; will be either a call or a create.
 #[derive(Debug, Clone, PartialEq, EnumDiscriminants)]
 #[strum_discriminants(name(ActionType))]
@@ -361,6 +408,10 @@ pub enum Action {
     CreateResult(CreateResult),
     /// It's an internal transfer action
     InternalTransferAction(InternalTransferAction),
+    /// It's a suicide action
+    Suicide(Suicide),
+    /// It's a block reward action
+    Reward(Reward),
 }
 
 impl Encodable for Action {
@@ -387,6 +438,14 @@ impl Encodable for Action {
                 s.append(&4u8);
                 s.append(internal_action);
             }
+            Action::Suicide(ref suicide) => {
+                s.append(&5u8);
+                s.append(suicide);
+            }
+            Action::Reward(ref reward) => {
+                s.append(&6u8);
+                s.append(reward);
+            }
         }
     }
 }
@@ -400,6 +459,8 @@ impl Decodable for Action {
             2 => rlp.val_at(1).map(Action::CallResult),
             3 => rlp.val_at(1).map(Action::CreateResult),
             4 => rlp.val_at(1).map(Action::InternalTransferAction),
+            5 => rlp.val_at(1).map(Action::Suicide),
+            6 => rlp.val_at(1).map(Action::Reward),
             _ => Err(DecoderError::Custom("Invalid action type.")),
         }
     }
@@ -416,6 +477,38 @@ impl Action {
             Action::InternalTransferAction(ref internal_action) => {
                 internal_action.bloom()
             }
+            Action::Suicide(ref suicide) => suicide.bloom(),
+            Action::Reward(ref reward) => reward.bloom(),
+        }
+    }
+
+    /// Returns the addresses whose balances are transferred by the action.
+    pub fn transfer_addresses(&self) -> Vec<AddressWithSpace> {
+        match *self {
+            Action::Call(ref call) if !call.value.is_zero() => vec![
+                call.from.with_space(call.space),
+                call.to.with_space(call.space),
+            ],
+            Action::Create(ref create) if !create.value.is_zero() => {
+                vec![create.from.with_space(create.space)]
+            }
+            Action::InternalTransferAction(ref internal_action) => {
+                [&internal_action.from, &internal_action.to]
+                    .into_iter()
+                    .filter_map(|pocket| match pocket {
+                        AddressPocket::Balance(address) => Some(*address),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            Action::Suicide(ref suicide) => vec![
+                suicide.address.with_space(suicide.space),
+                suicide.refund_address.with_space(suicide.space),
+            ],
+            Action::Reward(ref reward) => {
+                vec![reward.author.with_native_space()]
+            }
+            _ => vec![],
         }
     }
 }
@@ -35,7 +35,9 @@ impl ErrorUnwind {
                 Action::CreateResult(result) => {
                     errors.accept_create_result(result)
                 }
-                Action::InternalTransferAction(_) => {}
+                Action::InternalTransferAction(_)
+                | Action::Suicide(_)
+                | Action::Reward(_) => {}
             }
         }
         errors
@@ -1,5 +1,5 @@
 use super::{
-    action_types::{Action, ActionType},
+    action_types::{Action, ActionType, Suicide},
     trace_types::{ExecTrace, TransactionExecTraces},
 };
 use mazze_types::{Address, Space, H256};
@@ -61,6 +61,8 @@ impl TraceFilter {
     /// Return pairs of (action, result, subtrace_len).
     /// Return `Err` if actions and results do not match.
     ///
+    /// A suicide action has no result, so it is paired with itself.
+    ///
     /// `from_address`, `to_address`, `action_types`, and `space` in `filter`
     /// are applied.
     pub fn filter_trace_pairs(
@@ -128,7 +130,18 @@ impl TraceFilter {
                         sublen_stack.pop();
                     }
                 }
-                Action::InternalTransferAction(_) => {}
+                Action::Suicide(suicide) => {
+                    if suicide.space != self.space {
+                        continue;
+                    }
+                    if let Some(parent_subtraces) = sublen_stack.last_mut() {
+                        *parent_subtraces += 1;
+                    }
+                    if self.suicide_matches(suicide) {
+                        trace_pairs.push((trace.clone(), Some(trace), 0));
+                    }
+                }
+                Action::InternalTransferAction(_) | Action::Reward(_) => {}
             }
         }
         if !stack_index.is_empty() {
@@ -188,7 +201,14 @@ impl TraceFilter {
                         traces.push(trace);
                     }
                 }
-                Action::InternalTransferAction(_) => {
+                Action::Suicide(suicide) => {
+                    if suicide.space == self.space
+                        && self.suicide_matches(suicide)
+                    {
+                        traces.push(trace);
+                    }
+                }
+                Action::InternalTransferAction(_) | Action::Reward(_) => {
                     traces.push(trace);
                 }
             }
@@ -198,6 +218,12 @@ impl TraceFilter {
         }
         Ok(traces)
     }
+
+    fn suicide_matches(&self, suicide: &Suicide) -> bool {
+        self.from_address.matches(&suicide.address)
+            && self.to_address.matches(&suicide.refund_address)
+            && self.action_types.matches(&ActionType::Suicide)
+    }
 }
 
 #[derive(Debug, PartialEq)]
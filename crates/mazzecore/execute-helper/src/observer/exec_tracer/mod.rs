@@ -9,7 +9,7 @@ mod tests;
 
 pub use action_types::{
     Action, ActionType, Call, CallResult, Create, CreateResult,
-    InternalTransferAction, Outcome,
+    InternalTransferAction, Outcome, Reward, Suicide,
 };
 pub use error_unwind::ErrorUnwind;
 pub use filter::TraceFilter;
@@ -30,7 +30,7 @@ use mazze_executor::{
     },
     stack::{FrameResult, FrameReturn},
 };
-use mazze_types::U256;
+use mazze_types::{AddressWithSpace, U256};
 use mazze_vm_types::ActionParams;
 use typemap::ShareDebugMap;
 
@@ -151,6 +151,21 @@ impl CallTracer for ExecTracer {
             self.valid_indices.revert_checkpoint();
         }
     }
+
+    fn record_suicide(
+        &mut self, address: &AddressWithSpace,
+        refund_address: &AddressWithSpace, balance: U256,
+    ) {
+        let action = Action::Suicide(Suicide {
+            space: address.space,
+            address: address.address,
+            refund_address: refund_address.address,
+            balance,
+        });
+
+        self.valid_indices.push(self.traces.len());
+        self.traces.push(action);
+    }
 }
 
 impl StorageTracer for ExecTracer {}
@@ -164,7 +164,9 @@ pub fn recover_phantom_trace_for_call(
             }
             Action::InternalTransferAction(InternalTransferAction {
                 ..
-            }) => {}
+            })
+            | Action::Suicide(_)
+            | Action::Reward(_) => {}
         }
 
         phantom_traces.push(trace);
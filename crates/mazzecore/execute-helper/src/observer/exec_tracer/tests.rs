@@ -1,7 +1,13 @@
 use super::{
-    action_types::{Action, Call},
+    action_types::{
+        Action, Call, CallResult, InternalTransferAction, Outcome, Reward,
+        Suicide,
+    },
+    filter::TraceFilter,
     trace_types::{BlockExecTraces, ExecTrace, TransactionExecTraces},
 };
+use mazze_executor::observer::AddressPocket;
+use mazze_types::{Address, AddressSpaceUtil, Space};
 use mazze_vm_types::CallType;
 use rlp::*;
 
@@ -68,3 +74,109 @@ fn test_trace_serialization() {
     let decoded = ::rlp::decode(&encoded).expect("error decoding block traces");
     assert_eq!(block_traces, decoded);
 }
+
+#[test]
+fn test_suicide_and_reward_serialization() {
+    let traces = TransactionExecTraces(vec![
+        ExecTrace {
+            action: Action::Suicide(Suicide {
+                space: Space::Ethereum,
+                address: Address::from_low_u64_be(1),
+                refund_address: Address::from_low_u64_be(2),
+                balance: 100.into(),
+            }),
+            valid: true,
+        },
+        ExecTrace {
+            action: Action::Reward(Reward {
+                author: Address::from_low_u64_be(3),
+                value: 200.into(),
+            }),
+            valid: true,
+        },
+    ]);
+
+    let encoded = ::rlp::encode(&traces);
+    let decoded = ::rlp::decode(&encoded).expect("error decoding traces");
+    assert_eq!(traces, decoded);
+}
+
+#[test]
+fn test_suicide_trace_pairs() {
+    let call = ExecTrace {
+        action: Action::Call(Call {
+            space: Space::Ethereum,
+            from: Address::from_low_u64_be(2),
+            to: Address::from_low_u64_be(1),
+            value: 0.into(),
+            gas: 0x010c78.into(),
+            input: vec![],
+            call_type: CallType::Call,
+        }),
+        valid: true,
+    };
+    let suicide = ExecTrace {
+        action: Action::Suicide(Suicide {
+            space: Space::Ethereum,
+            address: Address::from_low_u64_be(1),
+            refund_address: Address::from_low_u64_be(2),
+            balance: 100.into(),
+        }),
+        valid: true,
+    };
+    let call_result = ExecTrace {
+        action: Action::CallResult(CallResult {
+            outcome: Outcome::Success,
+            gas_left: 0.into(),
+            return_data: vec![],
+        }),
+        valid: true,
+    };
+
+    let pairs = TraceFilter::space_filter(Space::Ethereum)
+        .filter_trace_pairs(TransactionExecTraces(vec![
+            call.clone(),
+            suicide.clone(),
+            call_result.clone(),
+        ]))
+        .unwrap();
+    assert_eq!(
+        pairs,
+        vec![(call, call_result, 1), (suicide.clone(), suicide, 0)]
+    );
+}
+
+#[test]
+fn test_transfer_addresses() {
+    let call = |value: u64| {
+        Action::Call(Call {
+            space: Space::Ethereum,
+            from: Address::from_low_u64_be(1),
+            to: Address::from_low_u64_be(2),
+            value: value.into(),
+            gas: 0.into(),
+            input: vec![],
+            call_type: CallType::Call,
+        })
+    };
+    assert_eq!(call(0).transfer_addresses(), vec![]);
+    assert_eq!(
+        call(1).transfer_addresses(),
+        vec![
+            Address::from_low_u64_be(1).with_evm_space(),
+            Address::from_low_u64_be(2).with_evm_space()
+        ]
+    );
+
+    let transfer = Action::InternalTransferAction(InternalTransferAction {
+        from: AddressPocket::Balance(
+            Address::from_low_u64_be(3).with_native_space(),
+        ),
+        to: AddressPocket::StorageCollateral(Address::from_low_u64_be(3)),
+        value: 1.into(),
+    });
+    assert_eq!(
+        transfer.transfer_addresses(),
+        vec![Address::from_low_u64_be(3).with_native_space()]
+    );
+}
@@ -1,9 +1,12 @@
+pub mod access_list;
 pub mod exec_tracer;
 pub mod gasman;
 mod utils;
+pub mod vm_tracer;
 
 use exec_tracer::ExecTracer;
 use gasman::GasMan;
+use vm_tracer::VmTracer;
 
 use mazze_executor::{
     executive_observer::{AsTracer, DrainTrace, TracerTrait},
@@ -20,6 +23,7 @@ pub struct Observer {
     pub tracer: Option<ExecTracer>,
     pub gas_man: Option<GasMan>,
     pub geth_tracer: Option<GethTracer>,
+    pub vm_tracer: Option<VmTracer>,
 }
 
 impl Observer {
@@ -28,6 +32,7 @@ impl Observer {
             tracer: Some(ExecTracer::default()),
             gas_man: None,
             geth_tracer: None,
+            vm_tracer: None,
         }
     }
 
@@ -36,6 +41,7 @@ impl Observer {
             tracer: None,
             gas_man: None,
             geth_tracer: None,
+            vm_tracer: None,
         }
     }
 
@@ -44,6 +50,7 @@ impl Observer {
             tracer: Some(ExecTracer::default()),
             gas_man: Some(GasMan::default()),
             geth_tracer: None,
+            vm_tracer: None,
         }
     }
 
@@ -55,6 +62,18 @@ impl Observer {
             tracer: None,
             gas_man: None,
             geth_tracer: Some(GethTracer::new(tx_exec_context, machine, opts)),
+            vm_tracer: None,
+        }
+    }
+
+    /// The observer of the parity-style `trace_*` RPCs, which records the
+    /// call traces and optionally the `vmTrace`.
+    pub fn parity_tracer(vm_trace: bool, cancun: bool) -> Self {
+        Observer {
+            tracer: Some(ExecTracer::default()),
+            gas_man: None,
+            geth_tracer: None,
+            vm_tracer: vm_trace.then(|| VmTracer::new(cancun)),
         }
     }
 }
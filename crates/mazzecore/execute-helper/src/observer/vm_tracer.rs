@@ -0,0 +1,310 @@
+use mazze_executor::{
+    observer::{
+        AsTracer, CallTracer, CheckpointTracer, DrainTrace,
+        InternalTransferTracer, OpcodeTracer, StorageTracer, TracerTrait,
+    },
+    stack::FrameResult,
+};
+use mazze_types::U256;
+use mazze_vm_interpreter::instructions::Instruction;
+use mazze_vm_types::{ActionParams, InterpreterInfo};
+
+use typemap::ShareDebugMap;
+
+/// The opcode-level trace of a frame, as in the parity `vmTrace`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VmTrace {
+    pub code: Vec<u8>,
+    pub ops: Vec<VmOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmOperation {
+    pub pc: usize,
+    pub cost: u64,
+    /// `None` if the operation failed.
+    pub ex: Option<VmExecutedOperation>,
+    /// The trace of the frame created by a call or a create operation.
+    pub sub: Option<VmTrace>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmExecutedOperation {
+    /// The gas left after the operation.
+    pub used: u64,
+    /// The items pushed to the stack.
+    pub push: Vec<U256>,
+    pub mem: Option<MemoryDiff>,
+    pub store: Option<StorageDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryDiff {
+    pub off: usize,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDiff {
+    pub key: U256,
+    pub val: U256,
+}
+
+pub struct VmTraceKey;
+
+impl typemap::Key for VmTraceKey {
+    type Value = VmTrace;
+}
+
+/// The information of an operation recorded before it is executed.
+struct StepInfo {
+    gas: U256,
+    ret: usize,
+    mem: Option<(usize, usize)>,
+    store: Option<StorageDiff>,
+    is_sub_call: bool,
+}
+
+#[derive(Default)]
+struct Frame {
+    trace: VmTrace,
+    step: Option<StepInfo>,
+    /// A call or create operation whose results are only available at the
+    /// next step, after its frame returns.
+    pending_sub_call: Option<StepInfo>,
+}
+
+/// Records the parity-style `vmTrace` of a transaction.
+pub struct VmTracer {
+    cancun: bool,
+    frames: Vec<Frame>,
+    root: Option<VmTrace>,
+}
+
+impl VmTracer {
+    pub fn new(cancun: bool) -> Self {
+        VmTracer {
+            cancun,
+            frames: vec![],
+            root: None,
+        }
+    }
+
+    fn enter(&mut self, params: &ActionParams) {
+        let code = params.code.as_ref().map_or_else(Vec::new, |c| c.to_vec());
+        self.frames.push(Frame {
+            trace: VmTrace { code, ops: vec![] },
+            ..Default::default()
+        });
+    }
+
+    fn exit(&mut self, result: &FrameResult) {
+        let mut frame = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        if let Some(last_op) = frame.trace.ops.last_mut() {
+            match (result, frame.pending_sub_call.take()) {
+                (Err(_), _) => last_op.ex = None,
+                (Ok(r), Some(info)) => {
+                    last_op.ex = Some(VmExecutedOperation {
+                        used: r.gas_left.low_u64(),
+                        push: vec![],
+                        mem: None,
+                        store: info.store,
+                    })
+                }
+                (Ok(_), None) => {}
+            }
+        }
+
+        match self.frames.last_mut() {
+            Some(parent) => {
+                if frame.trace.code.is_empty() {
+                    return;
+                }
+                if let Some(op) = parent.trace.ops.last_mut() {
+                    op.sub = Some(frame.trace);
+                }
+            }
+            None => self.root = Some(frame.trace),
+        }
+    }
+
+    fn ret_count(&self, instruction: Instruction) -> usize {
+        if self.cancun {
+            instruction.info::<true>().ret
+        } else {
+            instruction.info::<false>().ret
+        }
+    }
+
+    /// The memory range written by the operation, computed from its stack
+    /// arguments.
+    fn mem_written(
+        &self, instruction: Instruction, stack: &[U256],
+    ) -> Option<(usize, usize)> {
+        let peek = |n: usize| -> Option<usize> {
+            let value = stack[stack.len().checked_sub(n + 1)?];
+            if value > U256::from(usize::MAX) {
+                None
+            } else {
+                Some(value.as_usize())
+            }
+        };
+        let range = |off: usize, size: usize| Some((peek(off)?, peek(size)?));
+        match instruction {
+            Instruction::MSTORE => Some((peek(0)?, 32)),
+            Instruction::MSTORE8 => Some((peek(0)?, 1)),
+            Instruction::CALLDATACOPY
+            | Instruction::CODECOPY
+            | Instruction::RETURNDATACOPY => range(0, 2),
+            Instruction::JUMPSUB_MCOPY if self.cancun => range(0, 2),
+            Instruction::EXTCODECOPY => range(1, 3),
+            Instruction::CALL | Instruction::CALLCODE => range(5, 6),
+            Instruction::DELEGATECALL | Instruction::STATICCALL => range(4, 5),
+            _ => None,
+        }
+    }
+}
+
+fn executed(
+    info: StepInfo, interp: &dyn InterpreterInfo, push: usize,
+) -> VmExecutedOperation {
+    let stack = interp.stack();
+    let push = stack[stack.len().saturating_sub(push)..].to_vec();
+    let mem = info.mem.filter(|(_, size)| *size > 0).map(|(off, size)| {
+        let mem = interp.mem();
+        let start = off.min(mem.len());
+        let end = off.saturating_add(size).min(mem.len());
+        MemoryDiff {
+            off,
+            data: mem[start..end].to_vec(),
+        }
+    });
+    VmExecutedOperation {
+        used: interp.gas_remainning().low_u64(),
+        push,
+        mem,
+        store: info.store,
+    }
+}
+
+impl DrainTrace for VmTracer {
+    fn drain_trace(self, map: &mut ShareDebugMap) {
+        if let Some(root) = self.root {
+            map.insert::<VmTraceKey>(root);
+        }
+    }
+}
+
+impl AsTracer for VmTracer {
+    fn as_tracer<'a>(&'a mut self) -> Box<dyn 'a + TracerTrait> {
+        Box::new(self)
+    }
+}
+
+impl CheckpointTracer for VmTracer {}
+
+impl InternalTransferTracer for VmTracer {}
+
+impl StorageTracer for VmTracer {}
+
+impl CallTracer for VmTracer {
+    fn record_call(&mut self, params: &ActionParams) {
+        self.enter(params);
+    }
+
+    fn record_call_result(&mut self, result: &FrameResult) {
+        self.exit(result);
+    }
+
+    fn record_create(&mut self, params: &ActionParams) {
+        self.enter(params);
+    }
+
+    fn record_create_result(&mut self, result: &FrameResult) {
+        self.exit(result);
+    }
+}
+
+impl OpcodeTracer for VmTracer {
+    fn do_trace_opcode(&self, enabled: &mut bool) {
+        *enabled |= true;
+    }
+
+    fn step(&mut self, interp: &dyn InterpreterInfo) {
+        let instruction = Instruction::from_u8(interp.current_opcode());
+        let (ret, mem) = match instruction {
+            Some(instruction) => (
+                self.ret_count(instruction),
+                self.mem_written(instruction, interp.stack()),
+            ),
+            None => (0, None),
+        };
+        let stack = interp.stack();
+        let store = match instruction {
+            Some(Instruction::SSTORE) if stack.len() >= 2 => {
+                Some(StorageDiff {
+                    key: stack[stack.len() - 1],
+                    val: stack[stack.len() - 2],
+                })
+            }
+            _ => None,
+        };
+        let is_sub_call = matches!(
+            instruction,
+            Some(
+                Instruction::CALL
+                    | Instruction::CALLCODE
+                    | Instruction::DELEGATECALL
+                    | Instruction::STATICCALL
+                    | Instruction::CREATE
+                    | Instruction::CREATE2
+            )
+        );
+
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        if let Some(info) = frame.pending_sub_call.take() {
+            if let Some(last_op) = frame.trace.ops.last_mut() {
+                last_op.ex = Some(executed(info, interp, 1));
+            }
+        }
+        frame.trace.ops.push(VmOperation {
+            pc: interp.program_counter() as usize,
+            cost: 0,
+            ex: None,
+            sub: None,
+        });
+        frame.step = Some(StepInfo {
+            gas: interp.gas_remainning(),
+            ret,
+            mem,
+            store,
+            is_sub_call,
+        });
+    }
+
+    fn step_end(&mut self, interp: &dyn InterpreterInfo) {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let (info, last_op) =
+            match (frame.step.take(), frame.trace.ops.last_mut()) {
+                (Some(info), Some(last_op)) => (info, last_op),
+                _ => return,
+            };
+        last_op.cost =
+            info.gas.saturating_sub(interp.gas_remainning()).low_u64();
+        if info.is_sub_call {
+            frame.pending_sub_call = Some(info);
+        } else {
+            let push = info.ret;
+            last_op.ex = Some(executed(info, interp, push));
+        }
+    }
+}
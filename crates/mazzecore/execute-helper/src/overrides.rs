@@ -0,0 +1,273 @@
+//! State and block overrides for virtual calls such as `eth_call`. They are
+//! applied to the in-memory `State` and `Env` of the call only; the state is
+//! never committed, so the storage is left untouched.
+
+use mazze_bytes::Bytes;
+use mazze_executor::state::{CleanupMode, State};
+use mazze_statedb::Result as DbResult;
+use mazze_types::{
+    Address, AddressSpaceUtil, AddressWithSpace, BigEndianHash, Space, H256,
+    U256,
+};
+use mazze_vm_types::Env;
+use std::collections::HashMap;
+
+/// Overrides of a single account. `state` replaces the whole storage of the
+/// account, while `state_diff` only replaces the given slots.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountOverride {
+    pub balance: Option<U256>,
+    pub nonce: Option<U256>,
+    pub code: Option<Bytes>,
+    pub state: Option<HashMap<H256, H256>>,
+    pub state_diff: Option<HashMap<H256, H256>>,
+}
+
+/// Account overrides by address, in the space of the call.
+pub type StateOverride = HashMap<Address, AccountOverride>;
+
+/// Overrides of the block the call is executed in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockOverrides {
+    pub number: Option<u64>,
+    pub timestamp: Option<u64>,
+    pub author: Option<Address>,
+    pub base_gas_price: Option<U256>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallOverrides {
+    pub state: Option<StateOverride>,
+    pub block: Option<BlockOverrides>,
+}
+
+impl CallOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.state.is_none() && self.block.is_none()
+    }
+
+    /// Apply the overrides to the state and environment of a virtual call in
+    /// `space`.
+    pub fn apply(
+        &self, state: &mut State, env: &mut Env, space: Space,
+    ) -> DbResult<()> {
+        if let Some(block) = &self.block {
+            block.apply(state, env, space);
+        }
+        if let Some(state_override) = &self.state {
+            apply_state_override(state, space, state_override)?;
+        }
+        Ok(())
+    }
+}
+
+impl BlockOverrides {
+    fn apply(&self, state: &State, env: &mut Env, space: Space) {
+        if let Some(number) = self.number {
+            env.number = number;
+        }
+        if let Some(timestamp) = self.timestamp {
+            env.timestamp = timestamp;
+        }
+        if let Some(author) = self.author {
+            env.author = author;
+        }
+        if let Some(base_gas_price) = self.base_gas_price {
+            *env.base_gas_price.in_space_mut(space) = base_gas_price;
+            *env.burnt_gas_price.in_space_mut(space) =
+                state.burnt_gas_price(base_gas_price);
+        }
+    }
+}
+
+pub fn apply_state_override(
+    state: &mut State, space: Space, state_override: &StateOverride,
+) -> DbResult<()> {
+    for (address, account) in state_override {
+        let address = address.with_space(space);
+
+        if let Some(balance) = account.balance {
+            override_balance(state, &address, balance)?;
+        }
+        if let Some(nonce) = account.nonce {
+            state.set_nonce(&address, &nonce)?;
+        }
+        if let Some(code) = &account.code {
+            // `init_code` requires an existing account.
+            state.add_balance(
+                &address,
+                &U256::zero(),
+                CleanupMode::ForceCreate,
+            )?;
+            state.init_code(&address, code.clone(), address.address)?;
+        }
+        if let Some(storage) = &account.state {
+            state.override_clear_storage(&address)?;
+            override_storage(state, &address, storage)?;
+        }
+        if let Some(storage_diff) = &account.state_diff {
+            override_storage(state, &address, storage_diff)?;
+        }
+    }
+    Ok(())
+}
+
+fn override_balance(
+    state: &mut State, address: &AddressWithSpace, balance: U256,
+) -> DbResult<()> {
+    let current = state.balance(address)?;
+    // Keep the token statistics consistent with the balance change, as the
+    // executive may rely on them.
+    if balance > current {
+        let inc = balance - current;
+        state.add_balance(address, &inc, CleanupMode::ForceCreate)?;
+        state.add_total_issued(inc);
+        if address.space == Space::Ethereum {
+            state.add_total_evm_tokens(inc);
+        }
+    } else if balance < current {
+        let dec = current - balance;
+        state.sub_balance(address, &dec, &mut CleanupMode::NoEmpty)?;
+        state.sub_total_issued(dec);
+        if address.space == Space::Ethereum {
+            state.sub_total_evm_tokens(dec);
+        }
+    }
+    Ok(())
+}
+
+fn override_storage(
+    state: &mut State, address: &AddressWithSpace,
+    storage: &HashMap<H256, H256>,
+) -> DbResult<()> {
+    for (key, value) in storage {
+        state.override_storage(
+            address,
+            key.as_bytes().to_vec(),
+            value.into_uint(),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazze_executor::substate::Substate;
+    use mazze_statedb::StateDb;
+    use mazze_storage::{new_storage_manager_for_testing, StorageManagerTrait};
+
+    fn slot(i: u64) -> H256 {
+        H256::from_low_u64_be(i)
+    }
+
+    #[test]
+    fn test_state_override() {
+        let storage_manager = new_storage_manager_for_testing();
+        let mut state = State::new(StateDb::new(
+            storage_manager.get_state_for_genesis_write(),
+        ))
+        .unwrap();
+
+        let address = Address::random();
+        let evm_address = address.with_evm_space();
+        state
+            .add_balance(&evm_address, &100.into(), CleanupMode::NoEmpty)
+            .unwrap();
+        state.add_total_issued(100.into());
+        state.add_total_evm_tokens(100.into());
+        for i in 1..=2 {
+            state
+                .set_storage(
+                    &evm_address,
+                    slot(i).as_bytes().to_vec(),
+                    i.into(),
+                    Address::zero(),
+                    &mut Substate::new(),
+                )
+                .unwrap();
+        }
+
+        let mut storage_diff = HashMap::new();
+        storage_diff.insert(slot(2), slot(20));
+        let mut state_override = StateOverride::new();
+        state_override.insert(
+            address,
+            AccountOverride {
+                balance: Some(40.into()),
+                nonce: Some(7.into()),
+                code: Some(vec![0x60, 0x00]),
+                state_diff: Some(storage_diff),
+                ..Default::default()
+            },
+        );
+        apply_state_override(&mut state, Space::Ethereum, &state_override)
+            .unwrap();
+
+        assert_eq!(state.balance(&evm_address).unwrap(), 40.into());
+        assert_eq!(state.total_issued_tokens(), 40.into());
+        assert_eq!(state.nonce(&evm_address).unwrap(), 7.into());
+        assert_eq!(
+            *state.code(&evm_address).unwrap().unwrap(),
+            vec![0x60, 0x00]
+        );
+        let storage_at = |state: &State, i: u64| {
+            state.storage_at(&evm_address, slot(i).as_bytes()).unwrap()
+        };
+        assert_eq!(storage_at(&state, 1), 1.into());
+        assert_eq!(storage_at(&state, 2), 20.into());
+        // The override is in the space of the call only.
+        assert_eq!(
+            state.balance(&address.with_native_space()).unwrap(),
+            U256::zero()
+        );
+
+        // A full `state` hides all the other slots.
+        let mut storage = HashMap::new();
+        storage.insert(slot(3), slot(30));
+        let mut state_override = StateOverride::new();
+        state_override.insert(
+            address,
+            AccountOverride {
+                state: Some(storage),
+                ..Default::default()
+            },
+        );
+        apply_state_override(&mut state, Space::Ethereum, &state_override)
+            .unwrap();
+        assert_eq!(storage_at(&state, 1), U256::zero());
+        assert_eq!(storage_at(&state, 2), U256::zero());
+        assert_eq!(storage_at(&state, 3), 30.into());
+        assert_eq!(state.balance(&evm_address).unwrap(), 40.into());
+    }
+
+    #[test]
+    fn test_block_overrides() {
+        let storage_manager = new_storage_manager_for_testing();
+        let mut state = State::new(StateDb::new(
+            storage_manager.get_state_for_genesis_write(),
+        ))
+        .unwrap();
+        let mut env = Env::default();
+
+        let author = Address::random();
+        let overrides = CallOverrides {
+            state: None,
+            block: Some(BlockOverrides {
+                number: Some(10),
+                timestamp: Some(1_000),
+                author: Some(author),
+                base_gas_price: Some(5.into()),
+            }),
+        };
+        overrides
+            .apply(&mut state, &mut env, Space::Ethereum)
+            .unwrap();
+
+        assert_eq!(env.number, 10);
+        assert_eq!(env.timestamp, 1_000);
+        assert_eq!(env.author, author);
+        assert_eq!(*env.base_gas_price.in_space(Space::Ethereum), 5.into());
+        assert_eq!(*env.base_gas_price.in_space(Space::Native), U256::zero());
+    }
+}
@@ -0,0 +1,60 @@
+//! The results of the parity-style ad-hoc tracing, as returned by
+//! `trace_call` and `trace_replayTransaction`.
+
+use super::observer::{
+    exec_tracer::{ExecTrace, ExecTraceKey},
+    vm_tracer::{VmTrace, VmTraceKey},
+};
+use mazze_executor::{executive::ExecutionOutcome, state::AccountDiff};
+use mazze_types::{Space, H256};
+
+/// The optional outputs of a parity-style trace. The call traces are always
+/// recorded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParityTraceOptions {
+    pub vm_trace: bool,
+    pub state_diff: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParityTrace {
+    pub output: Vec<u8>,
+    pub traces: Vec<ExecTrace>,
+    pub vm_trace: Option<VmTrace>,
+    pub state_diff: Option<Vec<AccountDiff>>,
+}
+
+pub struct ParityTraceWithHash {
+    pub trace: ParityTrace,
+    pub tx_hash: H256,
+    pub block_hash: H256,
+    pub space: Space,
+}
+
+impl ParityTrace {
+    /// Collect the traces recorded by `Observer::parity_tracer`. A
+    /// transaction which is not executed at all has empty traces.
+    pub fn from_outcome(
+        outcome: &ExecutionOutcome, state_diff: Option<Vec<AccountDiff>>,
+    ) -> Self {
+        let executed = match outcome.try_as_executed() {
+            Some(executed) => executed,
+            None => {
+                return ParityTrace {
+                    state_diff,
+                    ..Default::default()
+                }
+            }
+        };
+        ParityTrace {
+            output: executed.output.clone(),
+            traces: executed
+                .ext_result
+                .get::<ExecTraceKey>()
+                .cloned()
+                .unwrap_or_default(),
+            vm_trace: executed.ext_result.get::<VmTraceKey>().cloned(),
+            state_diff,
+        }
+    }
+}
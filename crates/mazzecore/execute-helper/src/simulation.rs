@@ -0,0 +1,193 @@
+//! Simulation of ordered calls across one or more blocks on top of a state,
+//! as in `eth_simulateV1`. Unlike a plain virtual call, the effects of every
+//! call are kept, so that later calls observe them.
+
+use super::{
+    estimation::{EstimateRequest, EstimationContext},
+    overrides::CallOverrides,
+};
+use mazze_executor::{
+    executive::ExecutionOutcome, machine::Machine, state::State,
+};
+use mazze_statedb::Error as DbError;
+use mazze_types::{Space, U256};
+use mazze_vm_types::{Env, Spec};
+use primitives::SignedTransaction;
+
+/// A block of calls to simulate.
+#[derive(Debug, Clone, Default)]
+pub struct SimulateBlock {
+    /// Overrides applied before the first call of the block.
+    pub overrides: CallOverrides,
+    pub calls: Vec<(SignedTransaction, EstimateRequest)>,
+}
+
+/// The environment and the outcomes of a simulated block.
+#[derive(Debug)]
+pub struct SimulatedBlock {
+    pub number: u64,
+    pub timestamp: u64,
+    pub base_gas_price: U256,
+    pub gas_used: U256,
+    pub outcomes: Vec<ExecutionOutcome>,
+}
+
+/// Executes `blocks` in order on `state`. `env` is the environment of the
+/// first block; every following block advances its number and timestamp
+/// by one, unless they are overridden. Each call is checked by `verify`
+/// before it is executed.
+pub fn simulate_blocks<E, F>(
+    state: &mut State, env: &mut Env, machine: &Machine, space: Space,
+    blocks: Vec<SimulateBlock>, validation: bool, mut verify: F,
+) -> Result<Vec<SimulatedBlock>, E>
+where
+    E: From<DbError>,
+    F: FnMut(&SignedTransaction, &Env, &Spec) -> Result<(), E>,
+{
+    let mut simulated = Vec::with_capacity(blocks.len());
+    for (idx, block) in blocks.into_iter().enumerate() {
+        if idx > 0 {
+            env.number += 1;
+            env.timestamp += 1;
+            env.epoch_height += 1;
+        }
+        env.accumulated_gas_used = U256::zero();
+        env.gas_limit = block
+            .calls
+            .iter()
+            .map(|(tx, _)| *tx.gas())
+            .max()
+            .unwrap_or_default();
+        block.overrides.apply(state, env, space)?;
+
+        let spec = machine.spec(env.number, env.epoch_height);
+        let mut outcomes = Vec::with_capacity(block.calls.len());
+        for (tx, request) in block.calls {
+            verify(&tx, env, &spec)?;
+            let outcome = EstimationContext::new(state, env, machine, &spec)
+                .transact_simulated(tx, request, validation)?;
+            if let Some(executed) = outcome.try_as_executed() {
+                env.accumulated_gas_used += executed.gas_used;
+            }
+            outcomes.push(outcome);
+        }
+
+        simulated.push(SimulatedBlock {
+            number: env.number,
+            timestamp: env.timestamp,
+            base_gas_price: *env.base_gas_price.in_space(space),
+            gas_used: env.accumulated_gas_used,
+            outcomes,
+        });
+    }
+    Ok(simulated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overrides::BlockOverrides;
+    use mazze_executor::{
+        machine::{new_machine_with_builtin, VmFactory},
+        state::CleanupMode,
+    };
+    use mazze_statedb::StateDb;
+    use mazze_storage::{new_storage_manager_for_testing, StorageManagerTrait};
+    use mazze_types::{address_util::AddressUtil, Address, AddressSpaceUtil};
+    use primitives::transaction::{Action, NativeTransaction};
+
+    #[test]
+    fn test_simulate_blocks() {
+        let storage_manager = new_storage_manager_for_testing();
+        let mut state = State::new(StateDb::new(
+            storage_manager.get_state_for_genesis_write(),
+        ))
+        .unwrap();
+        let machine = new_machine_with_builtin(
+            Default::default(),
+            VmFactory::new(1024 * 32),
+        );
+        let mut env = Env::default();
+
+        let mut sender = Address::random();
+        sender.set_user_account_type_bits();
+        let sender = sender.with_native_space();
+        let mut receiver = Address::random();
+        receiver.set_user_account_type_bits();
+        state
+            .add_balance(&sender, &1_000_000.into(), CleanupMode::NoEmpty)
+            .unwrap();
+        state.add_total_issued(1_000_000.into());
+
+        let transfer = |value: u64| {
+            let tx = NativeTransaction {
+                nonce: U256::zero(),
+                gas_price: U256::one(),
+                gas: 21_000.into(),
+                action: Action::Call(receiver),
+                value: value.into(),
+                storage_limit: 0,
+                epoch_height: 0,
+                chain_id: 1,
+                data: vec![],
+            }
+            .fake_sign(sender);
+            let request = EstimateRequest {
+                has_sender: true,
+                has_gas_limit: true,
+                has_gas_price: true,
+                has_nonce: false,
+                has_storage_limit: true,
+            };
+            (tx, request)
+        };
+
+        let blocks = vec![
+            SimulateBlock {
+                overrides: Default::default(),
+                calls: vec![transfer(10), transfer(20)],
+            },
+            SimulateBlock {
+                overrides: CallOverrides {
+                    state: None,
+                    block: Some(BlockOverrides {
+                        timestamp: Some(1_000),
+                        ..Default::default()
+                    }),
+                },
+                calls: vec![transfer(30)],
+            },
+        ];
+        let simulated = simulate_blocks::<DbError, _>(
+            &mut state,
+            &mut env,
+            &machine,
+            Space::Native,
+            blocks,
+            /* validation = */ true,
+            |_, _, _| Ok(()),
+        )
+        .unwrap();
+
+        assert_eq!(simulated.len(), 2);
+        assert_eq!(simulated[0].number, 0);
+        assert_eq!(simulated[0].gas_used, 42_000.into());
+        assert_eq!(simulated[1].number, 1);
+        assert_eq!(simulated[1].timestamp, 1_000);
+        assert_eq!(simulated[1].gas_used, 21_000.into());
+        for outcome in simulated.iter().flat_map(|b| &b.outcomes) {
+            assert!(matches!(outcome, ExecutionOutcome::Finished(_)));
+        }
+
+        // The nonce and the balances are carried between the calls.
+        assert_eq!(state.nonce(&sender).unwrap(), 3.into());
+        assert_eq!(
+            state.balance(&receiver.with_native_space()).unwrap(),
+            60.into()
+        );
+        assert_eq!(
+            state.balance(&sender).unwrap(),
+            (1_000_000 - 60 - 3 * 21_000).into()
+        );
+    }
+}
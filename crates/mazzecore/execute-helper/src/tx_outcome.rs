@@ -1,10 +1,11 @@
-use mazze_executor::executive::ExecutionOutcome;
-use mazze_types::{H256, U256};
+use mazze_executor::{executive::ExecutionOutcome, state::State};
+use mazze_statedb::Result as DbResult;
+use mazze_types::{Space, H256, U256};
 use mazze_vm_types::Spec;
 use primitives::Receipt;
 
-use alloy_rpc_types_trace::geth::GethTrace;
-use geth_tracer::GethTraceKey;
+use alloy_rpc_types_trace::geth::{GethDebugTracingOptions, GethTrace};
+use geth_tracer::{fill_prestate_traces, GethTraceKey, PrestateTouchedKey};
 
 use super::{
     observer::exec_tracer::{ExecTrace, ExecTraceKey},
@@ -33,6 +34,28 @@ fn geth_traces(outcome: &ExecutionOutcome) -> Option<GethTrace> {
         .and_then(|executed| executed.ext_result.get::<GethTraceKey>().cloned())
 }
 
+/// The geth trace of the outcome traced with `opts`, with its prestate frames
+/// built from the accounts touched by the transaction in `space`. The `state`
+/// must be checkpointed right before the transaction is executed.
+pub fn geth_trace_with_prestate(
+    outcome: &ExecutionOutcome, state: &State, opts: &GethDebugTracingOptions,
+    space: Space,
+) -> DbResult<Option<GethTrace>> {
+    let executed = match outcome.try_as_executed() {
+        Some(executed) => executed,
+        None => return Ok(None),
+    };
+    let mut trace = match executed.ext_result.get::<GethTraceKey>() {
+        Some(trace) => trace.clone(),
+        None => return Ok(None),
+    };
+    if let Some(touched) = executed.ext_result.get::<PrestateTouchedKey>() {
+        let accounts = state.accounts_since_checkpoint(touched)?;
+        fill_prestate_traces(&mut trace, opts, &accounts, space);
+    }
+    Ok(Some(trace))
+}
+
 pub fn make_process_tx_outcome(
     outcome: ExecutionOutcome, accumulated_gas_used: &mut U256, tx_hash: H256,
     spec: &Spec,
@@ -34,6 +34,7 @@ pub fn suicide(
 ) -> vm::Result<()> {
     substate.suicides.insert(contract_address.clone());
     let balance = state.balance(contract_address)?;
+    tracer.record_suicide(contract_address, refund_address, balance);
 
     if refund_address == contract_address
         || (!spec.is_valid_address(&refund_address.address)
@@ -1,10 +1,11 @@
 use crate::stack::FrameResult;
+use mazze_types::{AddressWithSpace, U256};
 use mazze_vm_types::ActionParams;
 
 use impl_tools::autoimpl;
 use impl_trait_for_tuples::impl_for_tuples;
 
-#[impl_for_tuples(3)]
+#[impl_for_tuples(4)]
 #[autoimpl(for<T: trait + ?Sized> &mut T)]
 #[allow(unused_variables)]
 pub trait CallTracer {
@@ -19,4 +20,11 @@ pub trait CallTracer {
 
     /// Prepares create result trace
     fn record_create_result(&mut self, result: &FrameResult) {}
+
+    /// Prepares suicide trace, with the balance sent to `refund_address`.
+    fn record_suicide(
+        &mut self, address: &AddressWithSpace,
+        refund_address: &AddressWithSpace, balance: U256,
+    ) {
+    }
 }
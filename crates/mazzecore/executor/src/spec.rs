@@ -67,9 +67,12 @@ pub struct TransitionsBlockNumber {
     pub cancun_opcodes: BlockNumber,
 }
 
-/// Empty struct for now, will be used for epoch height transitions.
 #[derive(Default, Debug, Clone)]
-pub struct TransitionsEpochHeight {}
+pub struct TransitionsEpochHeight {
+    /// Two-sided PoW target: the nonce also gives a lower bound of the valid
+    /// PoW hashes. See `mazzecore::pow::nonce_to_lower_bound`.
+    pub pow_lower_bound: BlockHeight,
+}
 
 impl Default for CommonParams {
     fn default() -> Self {
@@ -104,6 +107,12 @@ impl CommonParams {
         spec
     }
 
+    /// Whether the PoW hash of a block at `height` must be above the lower
+    /// bound given by its nonce.
+    pub fn pow_lower_bound_enabled(&self, height: BlockHeight) -> bool {
+        height >= self.transition_heights.pow_lower_bound
+    }
+
     #[cfg(test)]
     pub fn spec_for_test(&self, number: u64) -> Spec {
         self.spec(number, number)
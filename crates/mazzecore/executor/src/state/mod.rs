@@ -21,8 +21,8 @@ mod state_object;
 #[cfg(test)]
 pub use state_object::get_state_for_genesis_write;
 pub use state_object::{
-    settle_collateral_for_all, State, StateCommitResult,
-    COMMISSION_PRIVILEGE_SPECIAL_KEY,
+    settle_collateral_for_all, AccountDiff, AccountValues, State,
+    StateCommitResult, COMMISSION_PRIVILEGE_SPECIAL_KEY,
 };
 
 use mazze_types::AddressWithSpace;
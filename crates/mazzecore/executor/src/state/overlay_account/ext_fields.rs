@@ -39,6 +39,22 @@ impl OverlayAccount {
 
         Ok(())
     }
+
+    /// Read the code of the account, loading it from the db if it is not
+    /// cached. Unlike `cache_code`, the account is not changed.
+    pub fn code_or_load(
+        &self, db: &StateDbGeneric,
+    ) -> DbResult<Option<Arc<Bytes>>> {
+        if self.is_code_loaded() {
+            return Ok(self.code.as_ref().map(|info| info.code.clone()));
+        }
+        match db.get_code(&self.address, &self.code_hash)? {
+            Some(info) => Ok(Some(info.code)),
+            None => {
+                bail!(DbErrorKind::IncompleteDatabase(self.address.address))
+            }
+        }
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
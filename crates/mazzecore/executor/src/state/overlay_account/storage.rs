@@ -50,6 +50,21 @@ impl OverlayAccount {
         Ok(())
     }
 
+    /// Overwrite a storage entry without collateral accounting. Only used by
+    /// the state overrides of virtual calls, which are never committed.
+    pub fn override_storage(&mut self, key: Vec<u8>, value: U256) {
+        Arc::make_mut(&mut self.storage_write_cache)
+            .insert(key, StorageValue { value, owner: None });
+    }
+
+    /// Regard all the storage entries on db as deleted. Only used by the state
+    /// overrides of virtual calls, which are never committed.
+    pub fn override_clear_storage(&mut self) {
+        Arc::make_mut(&mut self.storage_write_cache).clear();
+        self.storage_read_cache.write().clear();
+        self.pending_db_clear = true;
+    }
+
     #[cfg(test)]
     pub fn set_storage_simple(&mut self, key: Vec<u8>, value: U256) {
         Arc::make_mut(&mut self.storage_write_cache)
@@ -167,6 +182,11 @@ impl OverlayAccount {
         })
     }
 
+    /// The keys of the storage entries written since last commit.
+    pub fn written_storage_keys(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.storage_write_cache.keys()
+    }
+
     pub fn transient_storage_at(&self, key: &[u8]) -> U256 {
         self.transient_storage.get(key).cloned().unwrap_or_default()
     }
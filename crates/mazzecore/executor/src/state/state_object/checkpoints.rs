@@ -40,7 +40,7 @@ pub(super) struct CheckpointLayer {
     /// An account will only be added only if its cache version is modified. If
     /// an account does not exist in the checkpoint, it is implied to be the
     /// same as in the cache.
-    entries: HashMap<AddressWithSpace, CheckpointEntry>,
+    pub(super) entries: HashMap<AddressWithSpace, CheckpointEntry>,
 }
 
 impl CheckpointLayer {
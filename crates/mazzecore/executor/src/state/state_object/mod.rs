@@ -33,6 +33,10 @@ mod sponsor;
 /// Implements access functions for the account storage entries of `State`.
 mod storage_entry;
 
+/// Implements the comparison of the accounts with their versions in the last
+/// checkpoint, for tracing the state changes of transactions.
+mod state_diff;
+
 mod reward;
 
 #[cfg(test)]
@@ -43,6 +47,7 @@ pub use self::{
     commit::StateCommitResult,
     reward::set_initial_base_fee_prop,
     sponsor::COMMISSION_PRIVILEGE_SPECIAL_KEY,
+    state_diff::{AccountDiff, AccountValues},
 };
 #[cfg(test)]
 pub use tests::get_state_for_genesis_write;
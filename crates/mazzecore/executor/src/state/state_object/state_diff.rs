@@ -0,0 +1,154 @@
+//! State Diff: Compares the accounts modified since the last checkpoint with
+//! their versions recorded in the checkpoint, to report the state changes and
+//! the prestate of a traced transaction.
+
+use super::{checkpoints::CheckpointEntry, OverlayAccount, State};
+use mazze_statedb::{Result as DbResult, StateDbExt};
+use mazze_types::{AddressWithSpace, U256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The fields of an account visible to contracts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountValues {
+    pub balance: U256,
+    pub nonce: U256,
+    pub code: Vec<u8>,
+    /// The changed storage entries only, keyed by the storage key.
+    pub storage: BTreeMap<Vec<u8>, U256>,
+}
+
+/// The change of an account. `pre` or `post` is `None` if the account does
+/// not exist before or after the change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub address: AddressWithSpace,
+    pub pre: Option<AccountValues>,
+    pub post: Option<AccountValues>,
+}
+
+impl State {
+    /// Collect the changes of the accounts modified since the last
+    /// checkpoint, sorted by address. Accounts modified back to their
+    /// original values are omitted.
+    pub fn diff_since_checkpoint(&self) -> DbResult<Vec<AccountDiff>> {
+        let mut diffs = self.accounts_since_checkpoint(&BTreeMap::new())?;
+        diffs.retain_mut(|diff| {
+            retain_changed_storage(&mut diff.pre, &mut diff.post);
+            diff.pre != diff.post
+        });
+        Ok(diffs)
+    }
+
+    /// Collect the accounts modified since the last checkpoint and the
+    /// `touched` accounts, sorted by address, with their values at the
+    /// checkpoint and now. The storage entries are the written ones and the
+    /// `touched` ones, whether changed or not.
+    pub fn accounts_since_checkpoint(
+        &self, touched: &BTreeMap<AddressWithSpace, BTreeSet<Vec<u8>>>,
+    ) -> DbResult<Vec<AccountDiff>> {
+        let checkpoints = self.checkpoints.read();
+        let checkpoint = match checkpoints.last() {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(vec![]),
+        };
+        let cache = self.cache.read();
+
+        let addresses: BTreeSet<&AddressWithSpace> =
+            checkpoint.entries.keys().chain(touched.keys()).collect();
+        let mut accounts = vec![];
+        for address in addresses {
+            let cached = cache.get(address);
+            let cached_account = cached
+                .and_then(|entry| entry.account())
+                .filter(|acc| !acc.removed_without_update());
+            let loaded;
+            let (pre_account, post_account) =
+                match (checkpoint.entries.get(address), cached) {
+                    (Some(CheckpointEntry::Recorded(entry)), _) => {
+                        (entry.account(), cached_account)
+                    }
+                    // The account was not cached at the checkpoint, so the db
+                    // has its version at that time.
+                    (Some(CheckpointEntry::Unchanged), _) => {
+                        loaded = self.load_account(address)?;
+                        (loaded.as_ref(), cached_account)
+                    }
+                    // The account is not modified since the checkpoint.
+                    (None, Some(_)) => (cached_account, cached_account),
+                    (None, None) => {
+                        loaded = self.load_account(address)?;
+                        (loaded.as_ref(), loaded.as_ref())
+                    }
+                };
+
+            let keys: BTreeSet<&Vec<u8>> = pre_account
+                .into_iter()
+                .chain(post_account)
+                .flat_map(|acc| acc.written_storage_keys())
+                .chain(touched.get(address).into_iter().flatten())
+                .collect();
+            accounts.push(AccountDiff {
+                address: *address,
+                pre: pre_account
+                    .map(|acc| self.account_values(acc, &keys))
+                    .transpose()?,
+                post: post_account
+                    .map(|acc| self.account_values(acc, &keys))
+                    .transpose()?,
+            });
+        }
+        Ok(accounts)
+    }
+
+    fn load_account(
+        &self, address: &AddressWithSpace,
+    ) -> DbResult<Option<OverlayAccount>> {
+        Ok(self
+            .db
+            .get_account(address)?
+            .map(|acc| OverlayAccount::from_loaded(address, acc)))
+    }
+
+    fn account_values(
+        &self, acc: &OverlayAccount, keys: &BTreeSet<&Vec<u8>>,
+    ) -> DbResult<AccountValues> {
+        let code = acc
+            .code_or_load(&self.db)?
+            .map_or_else(Vec::new, |code| code.to_vec());
+        let storage = keys
+            .iter()
+            .map(|key| Ok(((*key).clone(), acc.storage_at(&self.db, key)?)))
+            .collect::<DbResult<_>>()?;
+        Ok(AccountValues {
+            balance: *acc.balance(),
+            nonce: *acc.nonce(),
+            code,
+            storage,
+        })
+    }
+}
+
+/// Drop the storage entries which are not changed. An entry of an absent
+/// account is regarded as zero.
+fn retain_changed_storage(
+    pre: &mut Option<AccountValues>, post: &mut Option<AccountValues>,
+) {
+    match (pre, post) {
+        (Some(pre), Some(post)) => {
+            let unchanged: Vec<Vec<u8>> = pre
+                .storage
+                .iter()
+                .filter(|(key, value)| post.storage.get(*key) == Some(*value))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in unchanged {
+                pre.storage.remove(&key);
+                post.storage.remove(&key);
+            }
+        }
+        (Some(values), None) | (None, Some(values)) => {
+            values.storage.retain(|_, value| !value.is_zero());
+        }
+        (None, None) => {}
+    }
+}
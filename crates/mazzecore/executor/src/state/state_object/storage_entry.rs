@@ -85,6 +85,28 @@ impl State {
             .transient_set_storage(key, value))
     }
 
+    /// Overwrite a storage entry without collateral accounting, creating the
+    /// account if it does not exist. Only for the state overrides of virtual
+    /// calls: the resulting state must never be committed.
+    pub fn override_storage(
+        &mut self, address: &AddressWithSpace, key: Vec<u8>, value: U256,
+    ) -> DbResult<()> {
+        self.write_account_or_new_lock(address)?
+            .override_storage(key, value);
+        Ok(())
+    }
+
+    /// Hide all the storage entries of an account, creating the account if it
+    /// does not exist. Only for the state overrides of virtual calls: the
+    /// resulting state must never be committed.
+    pub fn override_clear_storage(
+        &mut self, address: &AddressWithSpace,
+    ) -> DbResult<()> {
+        self.write_account_or_new_lock(address)?
+            .override_clear_storage();
+        Ok(())
+    }
+
     pub fn is_fresh_storage(
         &self, address: &AddressWithSpace,
     ) -> DbResult<bool> {
@@ -23,7 +23,10 @@ use mazze_types::{
     address_util::AddressUtil, Address, AddressSpaceUtil, BigEndianHash, U256,
 };
 use primitives::{EpochId, StorageKey, StorageLayout};
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 fn get_state(
     storage_manager: &Arc<StorageManager>, epoch_id: &EpochId,
@@ -1072,6 +1075,178 @@ fn create_contract_fail_previous_storage() {
     // TODO(69): checking ownership
 }
 
+#[test]
+fn diff_since_checkpoint() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_user_account_type_bits();
+    let address_with_space = address.with_native_space();
+    let mut contract = Address::from_low_u64_be(1);
+    contract.set_contract_type_bits();
+    let contract_with_space = contract.with_native_space();
+    let key = u256_to_vec(&U256::from(0));
+
+    state
+        .add_balance(
+            &address_with_space,
+            &U256::from(100),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.checkpoint();
+    assert!(state.diff_since_checkpoint().unwrap().is_empty());
+
+    state
+        .add_balance(&address_with_space, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .new_contract_with_code(&contract_with_space, U256::zero())
+        .unwrap();
+    state
+        .set_storage(
+            &contract_with_space,
+            key.clone(),
+            U256::one(),
+            contract,
+            &mut Substate::new(),
+        )
+        .unwrap();
+
+    let diffs = state.diff_since_checkpoint().unwrap();
+    assert_eq!(diffs.len(), 2);
+
+    let diff = diffs
+        .iter()
+        .find(|diff| diff.address == address_with_space)
+        .unwrap();
+    let (pre, post) = (diff.pre.as_ref().unwrap(), diff.post.as_ref().unwrap());
+    assert_eq!(pre.balance, U256::from(100));
+    assert_eq!(post.balance, U256::from(101));
+    assert!(pre.storage.is_empty() && post.storage.is_empty());
+
+    let diff = diffs
+        .iter()
+        .find(|diff| diff.address == contract_with_space)
+        .unwrap();
+    assert!(diff.pre.is_none());
+    assert_eq!(
+        diff.post.as_ref().unwrap().storage.get(&key),
+        Some(&U256::one())
+    );
+
+    // The changes are merged into the checkpoint, and undone by reverting.
+    state.revert_to_checkpoint();
+    assert_eq!(state.balance(&address_with_space).unwrap(), U256::from(100));
+}
+
+#[test]
+fn accounts_since_checkpoint() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_user_account_type_bits();
+    let address_with_space = address.with_native_space();
+    let mut other = Address::from_low_u64_be(2);
+    other.set_user_account_type_bits();
+    let other_with_space = other.with_native_space();
+    let mut absent = Address::from_low_u64_be(3);
+    absent.set_user_account_type_bits();
+    let absent_with_space = absent.with_native_space();
+    let mut contract = Address::from_low_u64_be(1);
+    contract.set_contract_type_bits();
+    let contract_with_space = contract.with_native_space();
+    let (read_key, written_key) =
+        (u256_to_vec(&U256::from(0)), u256_to_vec(&U256::from(1)));
+
+    state
+        .add_balance(
+            &address_with_space,
+            &U256::from(100),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state
+        .add_balance(&other_with_space, &U256::from(5), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .new_contract_with_code(&contract_with_space, U256::zero())
+        .unwrap();
+    state
+        .set_storage(
+            &contract_with_space,
+            read_key.clone(),
+            U256::from(7),
+            contract,
+            &mut Substate::new(),
+        )
+        .unwrap();
+    state.checkpoint();
+
+    state
+        .add_balance(&address_with_space, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .set_storage(
+            &contract_with_space,
+            written_key.clone(),
+            U256::one(),
+            contract,
+            &mut Substate::new(),
+        )
+        .unwrap();
+
+    // The other account and the absent account are only read.
+    let mut touched = BTreeMap::new();
+    touched.insert(
+        contract_with_space,
+        vec![read_key.clone()].into_iter().collect(),
+    );
+    touched.insert(other_with_space, BTreeSet::new());
+    touched.insert(absent_with_space, BTreeSet::new());
+
+    let accounts = state.accounts_since_checkpoint(&touched).unwrap();
+    let find = |address| {
+        accounts
+            .iter()
+            .find(|account| account.address == address)
+            .unwrap()
+    };
+    assert_eq!(accounts.len(), 4);
+
+    let account = find(address_with_space);
+    assert_eq!(account.pre.as_ref().unwrap().balance, U256::from(100));
+    assert_eq!(account.post.as_ref().unwrap().balance, U256::from(101));
+
+    // The touched and the written storage entries are reported.
+    let account = find(contract_with_space);
+    let (pre, post) = (
+        account.pre.as_ref().unwrap(),
+        account.post.as_ref().unwrap(),
+    );
+    assert_eq!(pre.storage.get(&read_key), Some(&U256::from(7)));
+    assert_eq!(post.storage.get(&read_key), Some(&U256::from(7)));
+    assert_eq!(pre.storage.get(&written_key), Some(&U256::zero()));
+    assert_eq!(post.storage.get(&written_key), Some(&U256::one()));
+
+    let account = find(other_with_space);
+    assert_eq!(account.pre, account.post);
+    assert_eq!(account.pre.as_ref().unwrap().balance, U256::from(5));
+
+    let account = find(absent_with_space);
+    assert!(account.pre.is_none() && account.post.is_none());
+
+    // Only the changes are left in the diff.
+    let diffs = state.diff_since_checkpoint().unwrap();
+    assert_eq!(diffs.len(), 2);
+    let diff = diffs
+        .iter()
+        .find(|diff| diff.address == contract_with_space)
+        .unwrap();
+    assert_eq!(diff.pre.as_ref().unwrap().storage.len(), 1);
+    assert_eq!(diff.post.as_ref().unwrap().storage.len(), 1);
+}
+
 #[test]
 fn test_automatic_collateral_normal_account() {
     let storage_manager = new_state_manager_for_unit_test();
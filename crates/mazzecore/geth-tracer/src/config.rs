@@ -27,7 +27,10 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 use alloy_rpc_types_trace::{
-    geth::{CallConfig, GethDefaultTracingOptions, PreStateConfig},
+    geth::{
+        CallConfig, GethDebugBuiltInTracerType, GethDefaultTracingOptions,
+        MuxConfig, PreStateConfig,
+    },
     parity::TraceType,
 };
 use std::collections::HashSet;
@@ -176,6 +179,58 @@ impl TracingInspectorConfig {
         Self::none()
     }
 
+    /// Returns a config for geth's
+    /// [MuxTracer](alloy_rpc_trace_types::geth::MuxFrame).
+    ///
+    /// All tracers of the mux share a single execution, so this records
+    /// everything any of the configured tracers needs, see
+    /// [TracingInspectorConfig::merge].
+    pub fn from_geth_mux_config(
+        config: &MuxConfig,
+    ) -> Result<Self, serde_json::Error> {
+        let mut merged = Self::none();
+        for (tracer, tracer_config) in &config.0 {
+            let tracer_config = tracer_config.clone().unwrap_or_default();
+            let c = match tracer {
+                GethDebugBuiltInTracerType::CallTracer => {
+                    Self::from_geth_call_config(
+                        &tracer_config.into_call_config()?,
+                    )
+                }
+                GethDebugBuiltInTracerType::PreStateTracer => {
+                    Self::from_geth_prestate_config(
+                        &tracer_config.into_pre_state_config()?,
+                    )
+                }
+                GethDebugBuiltInTracerType::FourByteTracer
+                | GethDebugBuiltInTracerType::NoopTracer
+                | GethDebugBuiltInTracerType::MuxTracer => Self::none(),
+            };
+            merged = merged.merge(c);
+        }
+        Ok(merged)
+    }
+
+    /// Returns a config which records everything that is recorded by either
+    /// `self` or `other`.
+    ///
+    /// Precompile calls are only excluded if both configs exclude them.
+    pub const fn merge(self, other: Self) -> Self {
+        Self {
+            record_steps: self.record_steps || other.record_steps,
+            record_memory_snapshots: self.record_memory_snapshots
+                || other.record_memory_snapshots,
+            record_stack_snapshots: self
+                .record_stack_snapshots
+                .merge(other.record_stack_snapshots),
+            record_state_diff: self.record_state_diff
+                || other.record_state_diff,
+            exclude_precompile_calls: self.exclude_precompile_calls
+                && other.exclude_precompile_calls,
+            record_logs: self.record_logs || other.record_logs,
+        }
+    }
+
     /// Configure whether calls to precompiles should be ignored.
     ///
     /// If set to `true`, calls to precompiles without value transfers will be
@@ -313,6 +368,16 @@ impl StackSnapshotType {
     pub const fn is_pushes(self) -> bool {
         matches!(self, Self::Pushes)
     }
+
+    /// Returns the snapshot type which records at least as much as both
+    /// `self` and `other`.
+    pub const fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Full, _) | (_, Self::Full) => Self::Full,
+            (Self::Pushes, _) | (_, Self::Pushes) => Self::Pushes,
+            (Self::None, Self::None) => Self::None,
+        }
+    }
 }
 
 /// What kind of tracing style this is.
@@ -360,4 +425,37 @@ mod tests {
         // not required for StateDiff
         assert!(!config.record_state_diff);
     }
+
+    #[test]
+    fn test_mux_config() {
+        let config: MuxConfig = serde_json::from_str(
+            r#"{"callTracer":{"withLog":true},"prestateTracer":null,"4byteTracer":null}"#,
+        )
+        .unwrap();
+        let config =
+            TracingInspectorConfig::from_geth_mux_config(&config).unwrap();
+        assert!(config.record_logs);
+        assert!(!config.record_steps);
+        assert!(!config.record_state_diff);
+
+        let config: MuxConfig =
+            serde_json::from_str(r#"{"callTracer":{"withLog":"yes"}}"#)
+                .unwrap();
+        assert!(TracingInspectorConfig::from_geth_mux_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_merge_config() {
+        let merged = TracingInspectorConfig::default_parity()
+            .merge(TracingInspectorConfig::default_geth());
+        assert!(merged.record_steps);
+        assert!(merged.record_state_diff);
+        assert!(!merged.exclude_precompile_calls);
+        assert!(merged.record_stack_snapshots.is_full());
+        assert_eq!(
+            TracingInspectorConfig::none()
+                .merge(TracingInspectorConfig::none()),
+            TracingInspectorConfig::none()
+        );
+    }
 }
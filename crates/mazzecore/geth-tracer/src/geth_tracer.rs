@@ -1,37 +1,56 @@
 use crate::{
     config::TracingInspectorConfig,
     fourbyte::FourByteInspector,
+    geth_builder::GethTraceBuilder,
     tracing_inspector::TracingInspector,
     types::{LogCallOrder, TxExecContext},
     utils::{to_alloy_address, to_alloy_h256, to_alloy_u256},
 };
-use alloy_primitives::{Address, Bytes, LogData};
+use alloy_primitives::{keccak256, Address, Bytes, LogData, U256};
 use alloy_rpc_types_trace::geth::{
     CallConfig, GethDebugBuiltInTracerType, GethDebugBuiltInTracerType::*,
-    GethDebugTracerType, GethDebugTracingOptions, GethTrace, NoopFrame,
-    PreStateConfig,
+    GethDebugTracerType, GethDebugTracingOptions, GethTrace, MuxConfig,
+    MuxFrame, NoopFrame, PreStateConfig, PreStateFrame, PreStateMode,
 };
 use mazze_executor::{
     machine::Machine,
     observer::{
-        CallTracer, CheckpointTracer, DrainTrace, InternalTransferTracer,
-        OpcodeTracer, StorageTracer,
+        AddressPocket, CallTracer, CheckpointTracer, DrainTrace,
+        InternalTransferTracer, OpcodeTracer, StorageTracer,
     },
     stack::{FrameResult, FrameReturn},
+    state::{AccountDiff, AccountValues},
 };
-use mazze_types::H160;
+use mazze_types::{
+    Address as MazzeAddress, AddressSpaceUtil, AddressWithSpace, BigEndianHash,
+    Space, H160, H256, U256 as MazzeU256,
+};
+use mazze_vm_interpreter::instructions::Instruction;
 use mazze_vm_types::{ActionParams, CallType, Error, InterpreterInfo};
 use revm::{
     db::InMemoryDB,
     interpreter::{Gas, InstructionResult, InterpreterResult},
-    primitives::State,
+    primitives::{
+        Account, AccountInfo, AccountStatus, Bytecode, State, StorageSlot,
+        KECCAK_EMPTY,
+    },
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
 };
-use std::sync::Arc;
+
+/// The accounts and storage entries touched by a transaction traced with the
+/// prestate tracer, keyed by the account.
+pub type PrestateTouched = BTreeMap<AddressWithSpace, BTreeSet<Vec<u8>>>;
 
 pub struct GethTracer {
     inner: TracingInspector,
     //
     fourbyte_inspector: FourByteInspector,
+    // whether function selectors are recorded, either for the 4byte tracer
+    // itself or for a mux tracer which includes it
+    record_fourbyte: bool,
     //
     tx_gas_limit: u64, // tx level gas limit
     //
@@ -42,6 +61,11 @@ pub struct GethTracer {
     opts: GethDebugTracingOptions,
     // gas stack, used to trace gas_spent in call_result/create_result
     pub gas_stack: Vec<u64>,
+    // the touched accounts and storage entries, recorded if the prestate
+    // tracer is used, either by itself or by a mux tracer which includes it
+    prestate_touched: Option<PrestateTouched>,
+    // the space of each call in the call stack
+    spaces: Vec<Space>,
 }
 
 impl GethTracer {
@@ -50,12 +74,27 @@ impl GethTracer {
         opts: GethDebugTracingOptions,
     ) -> Self {
         let TxExecContext { tx_gas_limit, .. } = tx_exec_context;
+        let mut record_fourbyte = false;
+        let mut record_prestate = false;
         let config = match opts.tracer {
             Some(GethDebugTracerType::BuiltInTracer(builtin_tracer)) => {
                 match builtin_tracer {
-                    FourByteTracer | NoopTracer | MuxTracer => {
+                    FourByteTracer => {
+                        record_fourbyte = true;
                         TracingInspectorConfig::none()
                     }
+                    NoopTracer => TracingInspectorConfig::none(),
+                    MuxTracer => {
+                        let c = opts
+                            .tracer_config
+                            .clone()
+                            .into_mux_config()
+                            .expect("should success");
+                        record_fourbyte = c.0.contains_key(&FourByteTracer);
+                        record_prestate = c.0.contains_key(&PreStateTracer);
+                        TracingInspectorConfig::from_geth_mux_config(&c)
+                            .expect("should success")
+                    }
                     CallTracer => {
                         let c = opts
                             .tracer_config
@@ -65,6 +104,7 @@ impl GethTracer {
                         TracingInspectorConfig::from_geth_call_config(&c)
                     }
                     PreStateTracer => {
+                        record_prestate = true;
                         let c = opts
                             .tracer_config
                             .clone()
@@ -83,11 +123,14 @@ impl GethTracer {
         Self {
             inner: TracingInspector::new(config, machine, tx_exec_context),
             fourbyte_inspector: FourByteInspector::new(),
+            record_fourbyte,
             tx_gas_limit,
             depth: 0,
             gas_left: tx_gas_limit,
             opts,
             gas_stack: Vec::new(),
+            prestate_touched: record_prestate.then(PrestateTouched::new),
+            spaces: Vec::new(),
         }
     }
 
@@ -110,8 +153,8 @@ impl GethTracer {
         self.opts.tracer_config.clone().into_call_config().ok()
     }
 
-    fn prestate_config(&self) -> Option<PreStateConfig> {
-        self.opts.tracer_config.clone().into_pre_state_config().ok()
+    fn mux_config(&self) -> Option<MuxConfig> {
+        self.opts.tracer_config.clone().into_mux_config().ok()
     }
 
     pub fn is_fourbyte_tracer(&self) -> bool {
@@ -122,6 +165,28 @@ impl GethTracer {
         self.tx_gas_limit - self.gas_left
     }
 
+    /// Whether the trace has prestate frames, which are built by
+    /// `fill_prestate_traces` after the execution.
+    pub fn records_prestate(&self) -> bool {
+        self.prestate_touched.is_some()
+    }
+
+    fn touch_account(&mut self, address: AddressWithSpace) {
+        if let Some(touched) = &mut self.prestate_touched {
+            touched.entry(address).or_default();
+        }
+    }
+
+    fn touch_storage(&mut self, address: AddressWithSpace, key: Vec<u8>) {
+        if let Some(touched) = &mut self.prestate_touched {
+            touched.entry(address).or_default().insert(key);
+        }
+    }
+
+    fn current_space(&self) -> Space {
+        self.spaces.last().cloned().unwrap_or_default()
+    }
+
     pub fn drain(self) -> GethTrace {
         let trace = match self.tracer_type() {
             Some(t) => match t {
@@ -135,22 +200,39 @@ impl GethTracer {
                         .geth_call_traces(opts, gas_used);
                     GethTrace::CallTracer(frame)
                 }
-                PreStateTracer => {
-                    // TODO replace the empty state and db with a real state
-                    let opts =
-                        self.prestate_config().expect("should have config");
-                    let state = State::default();
-                    let db = InMemoryDB::default();
-                    let frame = self
-                        .inner
-                        .into_geth_builder()
-                        .geth_prestate_traces(state, opts, db)
-                        .unwrap();
-                    GethTrace::PreStateTracer(frame)
-                }
-                NoopTracer | MuxTracer => {
-                    GethTrace::NoopTracer(NoopFrame::default())
+                PreStateTracer => pending_prestate_trace(),
+                MuxTracer => {
+                    let gas_used = self.gas_used();
+                    let config = self.mux_config().expect("should have config");
+                    let builder = self.inner.into_geth_builder();
+                    let mut fourbyte_inspector = Some(self.fourbyte_inspector);
+
+                    let mut frame = HashMap::with_capacity(config.0.len());
+                    for (tracer, tracer_config) in config.0 {
+                        let tracer_config = tracer_config.unwrap_or_default();
+                        let trace = match tracer {
+                            FourByteTracer => fourbyte_inspector
+                                .take()
+                                .expect("tracer types are unique")
+                                .drain(),
+                            CallTracer => {
+                                let opts = tracer_config
+                                    .into_call_config()
+                                    .expect("should have config");
+                                GethTrace::CallTracer(
+                                    builder.geth_call_traces(opts, gas_used),
+                                )
+                            }
+                            PreStateTracer => pending_prestate_trace(),
+                            NoopTracer | MuxTracer => {
+                                GethTrace::NoopTracer(NoopFrame::default())
+                            }
+                        };
+                        frame.insert(tracer, trace);
+                    }
+                    GethTrace::MuxTracer(MuxFrame(frame))
                 }
+                NoopTracer => GethTrace::NoopTracer(NoopFrame::default()),
             },
             None => {
                 let gas_used = self.gas_used();
@@ -173,8 +255,129 @@ impl GethTracer {
     }
 }
 
+/// The prestate frame before it is filled by `fill_prestate_traces`.
+fn pending_prestate_trace() -> GethTrace {
+    GethTrace::PreStateTracer(PreStateFrame::Default(PreStateMode::default()))
+}
+
+/// Build the prestate frames of `trace`, which is traced with `opts`, from
+/// the `accounts` touched by the transaction in `space`, with their values
+/// before and after the execution.
+pub fn fill_prestate_traces(
+    trace: &mut GethTrace, opts: &GethDebugTracingOptions,
+    accounts: &[AccountDiff], space: Space,
+) {
+    let build = |config: Option<PreStateConfig>| {
+        GethTrace::PreStateTracer(geth_prestate_traces(
+            config.unwrap_or_default(),
+            accounts,
+            space,
+        ))
+    };
+    match trace {
+        GethTrace::PreStateTracer(_) => {
+            *trace =
+                build(opts.tracer_config.clone().into_pre_state_config().ok())
+        }
+        GethTrace::MuxTracer(MuxFrame(frames)) => {
+            let config = match opts.tracer_config.clone().into_mux_config() {
+                Ok(config) => config,
+                Err(_) => return,
+            };
+            if let (Some(frame), Some(tracer_config)) = (
+                frames.get_mut(&PreStateTracer),
+                config.0.get(&PreStateTracer),
+            ) {
+                *frame = build(
+                    tracer_config
+                        .clone()
+                        .unwrap_or_default()
+                        .into_pre_state_config()
+                        .ok(),
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+fn geth_prestate_traces(
+    opts: PreStateConfig, accounts: &[AccountDiff], space: Space,
+) -> PreStateFrame {
+    // The accounts before the execution are loaded from `db`, and the
+    // accounts after the execution with the original values of their
+    // storage are in `state`.
+    let mut state = State::default();
+    let mut db = InMemoryDB::default();
+    for account in accounts.iter().filter(|acc| acc.address.space == space) {
+        let address = to_alloy_address(account.address.address);
+        if let Some(pre) = &account.pre {
+            db.insert_account_info(address, account_info(pre));
+        }
+
+        let mut changed = Account::from(
+            account.post.as_ref().map(account_info).unwrap_or_default(),
+        );
+        changed.status = match (&account.pre, &account.post) {
+            (None, Some(_)) => AccountStatus::Created,
+            (Some(_), None) => AccountStatus::SelfDestructed,
+            _ => AccountStatus::Touched,
+        };
+        // Only the 32-byte keys of the contract storage are reported.
+        let keys: BTreeSet<&Vec<u8>> = account
+            .pre
+            .iter()
+            .chain(&account.post)
+            .flat_map(|values| values.storage.keys())
+            .filter(|key| key.len() == H256::len_bytes())
+            .collect();
+        for key in keys {
+            changed.storage.insert(
+                U256::from_be_slice(key),
+                StorageSlot::new_changed(
+                    storage_value(&account.pre, key),
+                    storage_value(&account.post, key),
+                ),
+            );
+        }
+        state.insert(address, changed);
+    }
+
+    let builder = GethTraceBuilder::new(vec![], TracingInspectorConfig::none());
+    builder
+        .geth_prestate_traces(state, opts, db)
+        .unwrap_or_else(|e| match e {})
+}
+
+fn account_info(values: &AccountValues) -> AccountInfo {
+    let (code_hash, code) = if values.code.is_empty() {
+        (KECCAK_EMPTY, None)
+    } else {
+        (
+            keccak256(&values.code),
+            Some(Bytecode::new_raw(values.code.clone().into())),
+        )
+    };
+    AccountInfo {
+        balance: to_alloy_u256(values.balance),
+        nonce: values.nonce.as_u64(),
+        code_hash,
+        code,
+    }
+}
+
+fn storage_value(values: &Option<AccountValues>, key: &Vec<u8>) -> U256 {
+    values
+        .as_ref()
+        .and_then(|values| values.storage.get(key))
+        .map_or(U256::ZERO, |value| to_alloy_u256(*value))
+}
+
 impl DrainTrace for GethTracer {
-    fn drain_trace(self, map: &mut typemap::ShareDebugMap) {
+    fn drain_trace(mut self, map: &mut typemap::ShareDebugMap) {
+        if let Some(touched) = self.prestate_touched.take() {
+            map.insert::<PrestateTouchedKey>(touched);
+        }
         map.insert::<GethTraceKey>(self.drain());
     }
 }
@@ -185,19 +388,47 @@ impl typemap::Key for GethTraceKey {
     type Value = GethTrace;
 }
 
+pub struct PrestateTouchedKey;
+
+impl typemap::Key for PrestateTouchedKey {
+    type Value = PrestateTouched;
+}
+
 impl CheckpointTracer for GethTracer {}
 
-impl InternalTransferTracer for GethTracer {}
+impl InternalTransferTracer for GethTracer {
+    fn trace_internal_transfer(
+        &mut self, from: AddressPocket, to: AddressPocket,
+        _value: mazze_types::U256,
+    ) {
+        for pocket in [from, to] {
+            match pocket {
+                AddressPocket::Balance(address) => self.touch_account(address),
+                AddressPocket::MintBurn | AddressPocket::GasPayment => {}
+                _ => self.touch_account(
+                    pocket.inner_address_or_default().with_native_space(),
+                ),
+            }
+        }
+    }
+}
 
 impl StorageTracer for GethTracer {}
 
 impl CallTracer for GethTracer {
     fn record_call(&mut self, params: &ActionParams) {
-        if self.is_fourbyte_tracer() {
+        if self.record_fourbyte {
             self.fourbyte_inspector.record_call(params);
+        }
+        if self.is_fourbyte_tracer() {
             return;
         }
 
+        self.spaces.push(params.space);
+        for address in [params.sender, params.address, params.code_address] {
+            self.touch_account(address.with_space(params.space));
+        }
+
         let gas_limit = params.gas.as_u64();
         self.gas_stack.push(gas_limit);
 
@@ -249,6 +480,7 @@ impl CallTracer for GethTracer {
         }
 
         self.depth -= 1;
+        self.spaces.pop();
         let mut gas_spent = self.gas_stack.pop().expect("should have value");
 
         if let Ok(r) = result {
@@ -281,6 +513,11 @@ impl CallTracer for GethTracer {
             return;
         }
 
+        self.spaces.push(params.space);
+        for address in [params.sender, params.address] {
+            self.touch_account(address.with_space(params.space));
+        }
+
         let gas_limit = params.gas.as_u64();
         self.gas_stack.push(gas_limit);
 
@@ -316,6 +553,7 @@ impl CallTracer for GethTracer {
         }
 
         self.depth -= 1;
+        self.spaces.pop();
         let mut gas_spent = self.gas_stack.pop().expect("should have value");
 
         if let Ok(r) = result {
@@ -354,7 +592,7 @@ impl CallTracer for GethTracer {
 
 impl OpcodeTracer for GethTracer {
     fn do_trace_opcode(&self, enabled: &mut bool) {
-        if self.inner.config.record_steps {
+        if self.inner.config.record_steps || self.records_prestate() {
             *enabled |= true;
         }
     }
@@ -373,6 +611,10 @@ impl OpcodeTracer for GethTracer {
         if self.inner.config.record_steps {
             self.inner.start_step(interp, self.depth as u64);
         }
+
+        if self.records_prestate() {
+            self.touch_by_opcode(interp);
+        }
     }
 
     fn step_end(&mut self, interp: &dyn InterpreterInfo) {
@@ -421,6 +663,44 @@ impl OpcodeTracer for GethTracer {
     }
 }
 
+impl GethTracer {
+    /// Record the account or the storage entry accessed by the instruction.
+    fn touch_by_opcode(&mut self, interp: &dyn InterpreterInfo) {
+        let space = self.current_space();
+        let stack = interp.stack();
+        let peek = |n: usize| -> Option<MazzeU256> {
+            stack.len().checked_sub(n + 1).map(|i| stack[i])
+        };
+        let to_address =
+            |value: MazzeU256| MazzeAddress::from(H256::from_uint(&value));
+
+        let instruction = match Instruction::from_u8(interp.current_opcode()) {
+            Some(instruction) => instruction,
+            None => return,
+        };
+        match instruction {
+            Instruction::SLOAD | Instruction::SSTORE => {
+                if let Some(key) = peek(0) {
+                    self.touch_storage(
+                        interp.contract_address().with_space(space),
+                        H256::from_uint(&key).as_bytes().to_vec(),
+                    );
+                }
+            }
+            Instruction::BALANCE
+            | Instruction::EXTCODESIZE
+            | Instruction::EXTCODECOPY
+            | Instruction::EXTCODEHASH
+            | Instruction::SUICIDE => {
+                if let Some(address) = peek(0).map(to_address) {
+                    self.touch_account(address.with_space(space));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 pub fn to_instruction_result(frame_result: &FrameResult) -> InstructionResult {
     let result = match frame_result {
         Ok(r) => match r.apply_state {
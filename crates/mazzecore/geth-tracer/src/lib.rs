@@ -13,7 +13,10 @@ use arena::CallTraceArena;
 use config::TracingInspectorConfig;
 use geth_builder::GethTraceBuilder;
 
-pub use geth_tracer::{GethTraceKey, GethTracer};
+pub use geth_tracer::{
+    fill_prestate_traces, GethTraceKey, GethTracer, PrestateTouched,
+    PrestateTouchedKey,
+};
 pub use types::{GethTraceWithHash, TxExecContext};
 pub use utils::{
     from_alloy_address, to_alloy_address, to_alloy_h256, to_alloy_u256,
@@ -205,6 +205,9 @@ pub mod pow {
 
     // The amount of epochs to use for switching mining seed hash
     pub const RANDOMX_EPOCH_LENGTH: u64 = 2048;
+    // The amount of seed hashes whose RandomX context is kept for
+    // verification: the previous, current and next RandomX epoch
+    pub const RANDOMX_SEED_CACHE_SIZE: usize = 3;
 }
 
 pub mod tx_pool {
@@ -251,6 +251,14 @@ impl EthereumTransaction {
             Eip1559(tx) => Some(&tx.access_list),
         }
     }
+
+    pub fn access_list_mut(&mut self) -> Option<&mut AccessList> {
+        match self {
+            Eip155(_tx) => None,
+            Eip2930(tx) => Some(&mut tx.access_list),
+            Eip1559(tx) => Some(&mut tx.access_list),
+        }
+    }
 }
 
 /// Replay protection logic for v part of transaction's signature
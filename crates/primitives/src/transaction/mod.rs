@@ -385,6 +385,13 @@ impl Transaction {
             Transaction::Ethereum(tx) => tx.access_list(),
         }
     }
+
+    pub fn access_list_mut(&mut self) -> Option<&mut AccessList> {
+        match self {
+            Transaction::Native(tx) => tx.access_list_mut(),
+            Transaction::Ethereum(tx) => tx.access_list_mut(),
+        }
+    }
 }
 
 impl Transaction {
@@ -178,6 +178,14 @@ impl TypedNativeTransaction {
             Mip1559(tx) => Some(&tx.access_list),
         }
     }
+
+    pub fn access_list_mut(&mut self) -> Option<&mut AccessList> {
+        match self {
+            Mip155(_tx) => None,
+            Mip2930(tx) => Some(&mut tx.access_list),
+            Mip1559(tx) => Some(&mut tx.access_list),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -25,6 +25,7 @@ extern crate jsonrpc_tcp_server;
 extern crate keccak_hash as hash;
 extern crate mazze_types;
 extern crate parking_lot;
+extern crate rand;
 
 #[macro_use]
 extern crate log;
@@ -37,11 +38,18 @@ extern crate tokio;
 extern crate tokio_io;
 
 mod traits;
+mod vardiff;
 
-pub use traits::{Error, JobDispatcher, PushWorkHandler, ServiceConfiguration};
+pub use traits::{
+    Error, JobDispatcher, PushWorkHandler, ServiceConfiguration, ShareStatus,
+};
+pub use vardiff::{
+    share_difficulty_to_boundary, PoolConfig, ShareStats, Vardiff,
+};
 
 use jsonrpc_core::{
-    to_value, Compatibility, IoDelegate, MetaIoHandler, Metadata, Params, Value,
+    to_value, Compatibility, ErrorCode, IoDelegate, MetaIoHandler, Metadata,
+    Params, Value,
 };
 use jsonrpc_tcp_server::{
     Dispatcher, MetaExtractor, PushMessageError, RequestContext,
@@ -49,18 +57,61 @@ use jsonrpc_tcp_server::{
 };
 use std::sync::Arc;
 
-use crate::traits::Error::InvalidSolution;
+use crate::traits::Error::{InvalidSolution, StaleShare};
 use hash::keccak;
-use mazze_types::H256;
+use mazze_types::{H256, U256};
 use parking_lot::RwLock;
 use std::{
     collections::{HashMap, HashSet},
     net::SocketAddr,
+    time::{Duration, Instant},
 };
 
 type RpcResult = Result<jsonrpc_core::Value, jsonrpc_core::Error>;
 
 const NOTIFY_COUNTER_INITIAL: u32 = 16;
+/// Upper bound of outstanding `mining.subscribe` challenges. Once it is
+/// reached, the challenges of disconnected peers and then the oldest one are
+/// dropped.
+const MAX_PENDING_CHALLENGES: usize = 4096;
+/// Upper bound of outstanding challenges of the connections from one IP
+/// address, so that a single host cannot evict the challenges of others.
+const MAX_PENDING_CHALLENGES_PER_IP: usize = 64;
+/// Hashrate reports older than this are no longer counted.
+const HASHRATE_REPORT_EXPIRY: Duration = Duration::from_secs(120);
+
+/// JSON-RPC error codes of rejected stratum requests, following the codes
+/// commonly used by stratum servers.
+pub mod error_codes {
+    /// The worker failed to authenticate or is not authorized.
+    pub const UNAUTHORIZED_WORKER: i64 = 24;
+    /// `mining.authorize` was called without a preceding
+    /// `mining.subscribe`.
+    pub const NOT_SUBSCRIBED: i64 = 25;
+}
+
+/// Response to a `mining.subscribe` challenge expected by
+/// `mining.authorize`: `keccak(secret ++ challenge ++ worker_name)`.
+///
+/// Both the node and the miner are configured with the same `secret`, so it
+/// never has to be sent over the wire.
+pub fn authorization_response(
+    secret: &H256, challenge: &H256, worker_name: &str,
+) -> H256 {
+    let mut buf = Vec::with_capacity(64 + worker_name.len());
+    buf.extend_from_slice(secret.as_bytes());
+    buf.extend_from_slice(challenge.as_bytes());
+    buf.extend_from_slice(worker_name.as_bytes());
+    keccak(buf)
+}
+
+fn rpc_error(code: i64, message: &str) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: ErrorCode::ServerError(code),
+        message: message.into(),
+        data: None,
+    }
+}
 
 /// Container which owns rpc server and stratum implementation
 pub struct Stratum {
@@ -83,12 +134,25 @@ impl Stratum {
     pub fn start(
         addr: &SocketAddr, dispatcher: Arc<dyn JobDispatcher>,
         secret: Option<H256>,
+    ) -> Result<Arc<Stratum>, Error> {
+        Self::start_with_pool(addr, dispatcher, secret, None)
+    }
+
+    /// Starts the stratum server. With a `pool` configuration every worker
+    /// gets its own share difficulty, announced with `mining.set_difficulty`
+    /// and retargeted according to its share rate.
+    pub fn start_with_pool(
+        addr: &SocketAddr, dispatcher: Arc<dyn JobDispatcher>,
+        secret: Option<H256>, pool: Option<PoolConfig>,
     ) -> Result<Arc<Stratum>, Error> {
         let implementation = Arc::new(StratumImpl {
             dispatcher,
             workers: Arc::new(RwLock::default()),
             secret,
             notify_counter: RwLock::new(NOTIFY_COUNTER_INITIAL),
+            pool,
+            total_shares: RwLock::default(),
+            challenges: RwLock::default(),
         });
 
         let mut delegate = IoDelegate::<StratumImpl, SocketMetadata>::new(
@@ -96,7 +160,10 @@ impl Stratum {
         );
         delegate
             .add_method_with_meta("mining.subscribe", StratumImpl::subscribe);
+        delegate
+            .add_method_with_meta("mining.authorize", StratumImpl::authorize);
         delegate.add_method_with_meta("mining.submit", StratumImpl::submit);
+        delegate.add_method_with_meta("mining.hashrate", StratumImpl::hashrate);
         let mut handler = MetaIoHandler::<SocketMetadata>::with_compatibility(
             Compatibility::Both,
         );
@@ -117,6 +184,39 @@ impl Stratum {
 
         Ok(stratum)
     }
+
+    /// Share statistics of the currently connected workers
+    pub fn worker_stats(&self) -> Vec<WorkerStats> {
+        self.implementation
+            .workers
+            .read()
+            .iter()
+            .map(|(addr, worker)| WorkerStats {
+                worker_id: worker.worker_id.clone(),
+                addr: *addr,
+                difficulty: worker.vardiff.as_ref().map(Vardiff::difficulty),
+                shares: worker.shares,
+                hashrate: worker.hashrate(Instant::now()),
+            })
+            .collect()
+    }
+
+    /// Sum of the hashrates recently reported by the connected workers
+    pub fn hashrate(&self) -> U256 {
+        let now = Instant::now();
+        self.implementation
+            .workers
+            .read()
+            .values()
+            .fold(U256::zero(), |total, worker| {
+                total.saturating_add(worker.hashrate(now))
+            })
+    }
+
+    /// Share statistics accumulated over all workers since start
+    pub fn total_share_stats(&self) -> ShareStats {
+        *self.implementation.total_shares.read()
+    }
 }
 
 impl PushWorkHandler for Stratum {
@@ -137,35 +237,253 @@ impl Drop for Stratum {
     }
 }
 
+/// Share statistics of a connected worker
+#[derive(Debug, Clone)]
+pub struct WorkerStats {
+    pub worker_id: String,
+    pub addr: SocketAddr,
+    /// Current share difficulty, `None` outside of pool mode
+    pub difficulty: Option<u64>,
+    pub shares: ShareStats,
+    /// Hashrate last reported with `mining.hashrate`, zero if outdated
+    pub hashrate: U256,
+}
+
+struct WorkerSession {
+    worker_id: String,
+    /// Share difficulty state, only present in pool mode
+    vardiff: Option<Vardiff>,
+    /// Share difficulty last announced with `mining.set_difficulty`
+    announced_difficulty: Option<u64>,
+    shares: ShareStats,
+    /// Last reported hashrate and when it was reported
+    hashrate: Option<(U256, Instant)>,
+}
+
+impl WorkerSession {
+    fn hashrate(&self, now: Instant) -> U256 {
+        match self.hashrate {
+            Some((hashrate, reported))
+                if now.saturating_duration_since(reported)
+                    < HASHRATE_REPORT_EXPIRY =>
+            {
+                hashrate
+            }
+            _ => U256::zero(),
+        }
+    }
+
+    /// Shares are checked against the easier one of the announced and the
+    /// current difficulty, so that shares mined for the previous job are not
+    /// rejected right after a retarget.
+    fn share_boundary(&self) -> Option<U256> {
+        self.vardiff.as_ref().map(|vardiff| {
+            let difficulty = match self.announced_difficulty {
+                Some(announced) => announced.min(vardiff.difficulty()),
+                None => vardiff.difficulty(),
+            };
+            share_difficulty_to_boundary(difficulty)
+        })
+    }
+}
+
 struct StratumImpl {
     /// Payload manager
     dispatcher: Arc<dyn JobDispatcher>,
-    /// Authorized workers (socket - worker_id)
-    workers: Arc<RwLock<HashMap<SocketAddr, String>>>,
+    /// Authorized workers (socket - worker session)
+    workers: Arc<RwLock<HashMap<SocketAddr, WorkerSession>>>,
     /// Secret if any
     secret: Option<H256>,
     /// Dispatch notify couinter
     notify_counter: RwLock<u32>,
+    /// Pool mode configuration if any
+    pool: Option<PoolConfig>,
+    /// Share statistics over all workers
+    total_shares: RwLock<ShareStats>,
+    /// Outstanding authentication challenges (socket - challenge)
+    challenges: RwLock<HashMap<SocketAddr, PendingChallenge>>,
+}
+
+struct PendingChallenge {
+    challenge: H256,
+    issued: Instant,
 }
 
 impl StratumImpl {
     /// rpc method `mining.subscribe`
+    ///
+    /// Params are `[worker_name]`, or `[worker_name, secret]` for the legacy
+    /// scheme where `keccak(secret)` is the configured secret. Without a
+    /// configured secret the worker is registered and `true` is returned.
+    /// Otherwise the result is a challenge to be answered with
+    /// `mining.authorize`.
     fn subscribe(&self, params: Params, meta: SocketMetadata) -> RpcResult {
-        params.parse::<(String, String)>().map(|(worker_id, secret)|{
-            if let Some(valid_secret) = self.secret {
-                let hash = keccak(secret);
-                if hash != valid_secret {
-                    return to_value(&false);
-                }
+        let mut params = params.parse::<Vec<String>>()?;
+        if params.is_empty() || params.len() > 2 {
+            return Err(jsonrpc_core::Error::invalid_params(
+                "Expected [worker_name] or [worker_name, secret]",
+            ));
+        }
+        let legacy_secret = if params.len() == 2 {
+            params.pop()
+        } else {
+            None
+        };
+        let worker_id = params.pop().expect("checked length above; qed");
+
+        let valid_secret = match self.secret {
+            None => {
+                self.register_worker(meta.addr(), worker_id);
+                return Ok(Value::Bool(true));
+            }
+            Some(valid_secret) => valid_secret,
+        };
+
+        if let Some(secret) = legacy_secret.filter(|s| !s.is_empty()) {
+            if keccak(secret) == valid_secret {
+                self.register_worker(meta.addr(), worker_id);
+                return Ok(Value::Bool(true));
             }
-            debug!(target: "stratum", "New worker #{} registered", worker_id);
-            self.workers.write().insert(meta.addr().clone(), worker_id);
-            to_value(true)
-        }).map(|v| v.expect("Only true/false is returned and it's always serializable; qed"))
+        }
+
+        let challenge =
+            self.issue_challenge(meta.addr(), meta.tcp_dispatcher.as_ref());
+        debug!(target: "stratum", "Worker #{} has to authorize", worker_id);
+        Ok(Value::String(format!("0x{:x}", challenge)))
+    }
+
+    /// rpc method `mining.authorize`
+    ///
+    /// Params are `[worker_name, response]` where `response` is
+    /// `authorization_response` over the challenge of `mining.subscribe`.
+    fn authorize(&self, params: Params, meta: SocketMetadata) -> RpcResult {
+        let (worker_id, response) = params.parse::<(String, String)>()?;
+
+        if let Some(secret) = self.secret {
+            let challenge = self
+                .challenges
+                .write()
+                .remove(meta.addr())
+                .ok_or_else(|| {
+                    rpc_error(error_codes::NOT_SUBSCRIBED, "Not subscribed")
+                })?
+                .challenge;
+            let response =
+                response.trim_start_matches("0x").parse::<H256>().map_err(
+                    |_| jsonrpc_core::Error::invalid_params("Invalid response"),
+                )?;
+            if response
+                != authorization_response(&secret, &challenge, &worker_id)
+            {
+                warn!(target: "stratum", "Worker #{} failed to authorize from {}", worker_id, meta.addr());
+                return Err(rpc_error(
+                    error_codes::UNAUTHORIZED_WORKER,
+                    "Unauthorized worker",
+                ));
+            }
+        }
+
+        self.register_worker(meta.addr(), worker_id);
+        Ok(Value::Bool(true))
+    }
+
+    /// Creates the challenge of the peer `addr`, replacing its previous one.
+    /// When a limit is reached, the oldest challenge of the same IP address,
+    /// or else of any peer, is dropped.
+    fn issue_challenge(
+        &self, addr: &SocketAddr, tcp_dispatcher: Option<&Dispatcher>,
+    ) -> H256 {
+        let challenge = H256::from(rand::random::<[u8; 32]>());
+        let mut challenges = self.challenges.write();
+        challenges.remove(addr);
+        if challenges.len() >= MAX_PENDING_CHALLENGES {
+            if let Some(tcp_dispatcher) = tcp_dispatcher {
+                challenges.retain(|peer, _| tcp_dispatcher.is_connected(peer));
+            }
+        }
+
+        let same_ip = challenges
+            .iter()
+            .filter(|(peer, _)| peer.ip() == addr.ip())
+            .collect::<Vec<_>>();
+        let evicted = if same_ip.len() >= MAX_PENDING_CHALLENGES_PER_IP {
+            oldest_challenge(same_ip.into_iter())
+        } else if challenges.len() >= MAX_PENDING_CHALLENGES {
+            oldest_challenge(challenges.iter())
+        } else {
+            None
+        };
+        if let Some(evicted) = evicted {
+            debug!(target: "stratum", "Too many pending challenges, dropping the one of {}", evicted);
+            challenges.remove(&evicted);
+        }
+
+        challenges.insert(
+            *addr,
+            PendingChallenge {
+                challenge,
+                issued: Instant::now(),
+            },
+        );
+        challenge
+    }
+
+    /// Drops the challenges of the peers which are no longer connected.
+    fn drop_disconnected_challenges(&self, tcp_dispatcher: &Dispatcher) {
+        self.challenges
+            .write()
+            .retain(|addr, _| tcp_dispatcher.is_connected(addr));
+    }
+
+    fn register_worker(&self, addr: &SocketAddr, worker_id: String) {
+        debug!(target: "stratum", "New worker #{} registered", worker_id);
+        let vardiff = self
+            .pool
+            .as_ref()
+            .map(|pool| Vardiff::new(pool, Instant::now()));
+        self.workers.write().insert(
+            addr.clone(),
+            WorkerSession {
+                worker_id,
+                vardiff,
+                announced_difficulty: None,
+                shares: ShareStats::default(),
+                hashrate: None,
+            },
+        );
+    }
+
+    /// With a configured secret, only the worker name authorized on a
+    /// connection may submit on it.
+    fn check_authorized(
+        &self, params: &Params, addr: &SocketAddr,
+    ) -> Result<(), jsonrpc_core::Error> {
+        if self.secret.is_none() {
+            return Ok(());
+        }
+        let worker_id = match params {
+            Params::Array(vals) => vals.first().and_then(Value::as_str),
+            _ => None,
+        };
+        match (self.workers.read().get(addr), worker_id) {
+            (Some(worker), Some(worker_id))
+                if worker.worker_id == worker_id =>
+            {
+                Ok(())
+            }
+            _ => Err(rpc_error(
+                error_codes::UNAUTHORIZED_WORKER,
+                "Unauthorized worker",
+            )),
+        }
     }
 
     /// rpc method `mining.submit`
-    fn submit(&self, params: Params, _meta: SocketMetadata) -> RpcResult {
+    fn submit(&self, params: Params, meta: SocketMetadata) -> RpcResult {
+        self.check_authorized(&params, meta.addr())?;
+        if self.pool.is_some() {
+            return Ok(Value::Array(self.submit_share(params, meta.addr())));
+        }
         Ok(Value::Array(match params {
             Params::Array(vals) => {
                 // first two elements are service messages (worker_id & job_id)
@@ -178,7 +496,7 @@ impl StratumImpl {
                         .collect::<Vec<String>>(),
                 ) {
                     Ok(()) => vec![to_value(true).expect("serializable")],
-                    Err(InvalidSolution(msg)) => {
+                    Err(InvalidSolution(msg)) | Err(StaleShare(msg)) => {
                         // When we have invalid solution, we propagate the
                         // reason to the client
                         warn!("Error because of invalid solution: {:?}", msg);
@@ -200,29 +518,189 @@ impl StratumImpl {
         }))
     }
 
+    /// rpc method `mining.hashrate`
+    ///
+    /// Params are `[worker_name, hashrate]` with the hashrate in H/s as a
+    /// hex string, like `eth_submitHashrate`.
+    fn hashrate(&self, params: Params, meta: SocketMetadata) -> RpcResult {
+        self.check_authorized(&params, meta.addr())?;
+        let (_, hashrate) = params.parse::<(String, String)>()?;
+        let hashrate =
+            hashrate.trim_start_matches("0x").parse::<U256>().map_err(
+                |_| jsonrpc_core::Error::invalid_params("Invalid hashrate"),
+            )?;
+
+        match self.workers.write().get_mut(meta.addr()) {
+            Some(worker) => {
+                trace!(target: "stratum", "Worker {} reported hashrate {}", worker.worker_id, hashrate);
+                worker.hashrate = Some((hashrate, Instant::now()));
+                Ok(Value::Bool(true))
+            }
+            None => Ok(Value::Bool(false)),
+        }
+    }
+
+    /// Pool mode `mining.submit`: checks the share against the share
+    /// boundary of the submitting worker and keeps share statistics.
+    fn submit_share(&self, params: Params, addr: &SocketAddr) -> Vec<Value> {
+        let payload = match params {
+            Params::Array(vals) => vals
+                .iter()
+                .filter_map(|val| match *val {
+                    Value::String(ref s) => Some(s.to_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<String>>(),
+            _ => {
+                trace!(target: "stratum", "Invalid submit work format {:?}", params);
+                return vec![to_value(false).expect("serializable")];
+            }
+        };
+
+        let share_boundary = match self
+            .workers
+            .read()
+            .get(addr)
+            .and_then(WorkerSession::share_boundary)
+        {
+            Some(boundary) => boundary,
+            None => {
+                debug!(target: "stratum", "Share from unsubscribed peer {}", addr);
+                return vec![
+                    to_value(false).expect("serializable"),
+                    to_value("Worker is not subscribed").expect("serializable"),
+                ];
+            }
+        };
+
+        // The dispatcher computes the PoW hash, do not hold any lock here.
+        let result = self.dispatcher.submit_share(payload, share_boundary);
+
+        let mut workers = self.workers.write();
+        let mut total = self.total_shares.write();
+        let worker = workers.get_mut(addr);
+        let (shares, response) = match result {
+            Ok(status) => {
+                let mut shares = ShareStats {
+                    accepted: 1,
+                    ..Default::default()
+                };
+                if status == ShareStatus::Block {
+                    shares.blocks = 1;
+                }
+                (shares, vec![to_value(true).expect("serializable")])
+            }
+            Err(StaleShare(msg)) => {
+                debug!(target: "stratum", "Stale share from {}: {}", addr, msg);
+                (
+                    ShareStats {
+                        stale: 1,
+                        ..Default::default()
+                    },
+                    vec![
+                        to_value(false).expect("serializable"),
+                        to_value(msg).expect("serializable"),
+                    ],
+                )
+            }
+            Err(InvalidSolution(msg)) => {
+                debug!(target: "stratum", "Invalid share from {}: {}", addr, msg);
+                (
+                    ShareStats {
+                        invalid: 1,
+                        ..Default::default()
+                    },
+                    vec![
+                        to_value(false).expect("serializable"),
+                        to_value(msg).expect("serializable"),
+                    ],
+                )
+            }
+            Err(submit_err) => {
+                warn!("Error while submitting share: {:?}", submit_err);
+                (
+                    ShareStats {
+                        invalid: 1,
+                        ..Default::default()
+                    },
+                    vec![to_value(false).expect("serializable")],
+                )
+            }
+        };
+
+        add_share_stats(&mut total, &shares);
+        if let Some(worker) = worker {
+            add_share_stats(&mut worker.shares, &shares);
+            if shares.accepted > 0 {
+                if let (Some(vardiff), Some(pool)) =
+                    (worker.vardiff.as_mut(), self.pool.as_ref())
+                {
+                    vardiff.on_share();
+                    if let Some(difficulty) =
+                        vardiff.retarget(pool, Instant::now())
+                    {
+                        debug!(target: "stratum", "Worker {} retargeted to share difficulty {}", worker.worker_id, difficulty);
+                    }
+                }
+            }
+        }
+
+        response
+    }
+
+    fn next_request_id(&self) -> u32 {
+        let mut counter = self.notify_counter.write();
+        if *counter == ::std::u32::MAX {
+            *counter = NOTIFY_COUNTER_INITIAL;
+        } else {
+            *counter += 1
+        }
+        *counter
+    }
+
     fn push_work_all(
         &self, payload: String, tcp_dispatcher: &Dispatcher,
     ) -> Result<(), Error> {
         let hup_peers = {
-            let workers = self.workers.read();
-            let next_request_id = {
-                let mut counter = self.notify_counter.write();
-                if *counter == ::std::u32::MAX {
-                    *counter = NOTIFY_COUNTER_INITIAL;
-                } else {
-                    *counter += 1
-                }
-                *counter
-            };
+            let mut workers = self.workers.write();
+            let next_request_id = self.next_request_id();
 
             let mut hup_peers = HashSet::with_capacity(0); // most of the cases won't be needed, hence avoid allocation
             let workers_msg = format!("{{ \"id\": {}, \"method\": \"mining.notify\", \"params\": {} }}", next_request_id, payload);
             trace!(target: "stratum", "Pushing work for {} workers (payload: '{}')", workers.len(), &workers_msg);
-            for (ref addr, worker_id) in workers.iter() {
-                trace!(target: "stratum", "Pushing work to {} at addr {}", &worker_id, &addr);
+            let now = Instant::now();
+            for (ref addr, worker) in workers.iter_mut() {
+                // In pool mode, a new share difficulty takes effect with the
+                // next job, so it is announced right before the job.
+                if let (Some(vardiff), Some(pool)) =
+                    (worker.vardiff.as_mut(), self.pool.as_ref())
+                {
+                    vardiff.retarget(pool, now);
+                    let difficulty = vardiff.difficulty();
+                    if worker.announced_difficulty != Some(difficulty) {
+                        let difficulty_msg = format!("{{ \"id\": {}, \"method\": \"mining.set_difficulty\", \"params\": [{}] }}", self.next_request_id(), difficulty);
+                        trace!(target: "stratum", "Setting share difficulty {} for {} at addr {}", difficulty, &worker.worker_id, &addr);
+                        match tcp_dispatcher.push_message(addr, difficulty_msg)
+                        {
+                            Err(PushMessageError::NoSuchPeer) => {
+                                debug!(target: "stratum", "Worker no longer connected: {} addr {}", &worker.worker_id, &addr);
+                                hup_peers.insert(**addr);
+                                continue;
+                            }
+                            Err(e) => {
+                                warn!(target: "stratum", "Unexpected transport error: {:?}", e);
+                            }
+                            Ok(_) => {
+                                worker.announced_difficulty = Some(difficulty);
+                            }
+                        }
+                    }
+                }
+
+                trace!(target: "stratum", "Pushing work to {} at addr {}", &worker.worker_id, &addr);
                 match tcp_dispatcher.push_message(addr, workers_msg.clone()) {
                     Err(PushMessageError::NoSuchPeer) => {
-                        debug!(target: "stratum", "Worker no longer connected: {} addr {}", &worker_id, &addr);
+                        debug!(target: "stratum", "Worker no longer connected: {} addr {}", &worker.worker_id, &addr);
                         hup_peers.insert(**addr);
                     }
                     Err(e) => {
@@ -231,27 +709,43 @@ impl StratumImpl {
                     Ok(_) => {}
                 }
             }
+
+            for hup_peer in hup_peers.iter() {
+                workers.remove(hup_peer);
+            }
             hup_peers
         };
 
         if !hup_peers.is_empty() {
-            let mut workers = self.workers.write();
-            for hup_peer in hup_peers {
-                workers.remove(&hup_peer);
-            }
+            debug!(target: "stratum", "Removed {} disconnected workers", hup_peers.len());
         }
+        self.drop_disconnected_challenges(tcp_dispatcher);
 
         Ok(())
     }
 }
 
+fn oldest_challenge<'a>(
+    challenges: impl Iterator<Item = (&'a SocketAddr, &'a PendingChallenge)>,
+) -> Option<SocketAddr> {
+    challenges
+        .min_by_key(|(_, pending)| pending.issued)
+        .map(|(addr, _)| *addr)
+}
+
+fn add_share_stats(stats: &mut ShareStats, delta: &ShareStats) {
+    stats.accepted += delta.accepted;
+    stats.stale += delta.stale;
+    stats.invalid += delta.invalid;
+    stats.blocks += delta.blocks;
+}
+
 #[derive(Clone)]
 pub struct SocketMetadata {
     addr: SocketAddr,
     // with the new version of jsonrpc-core, SocketMetadata
     // won't have to implement default, so this field will not
     // have to be an Option
-    #[allow(dead_code)]
     tcp_dispatcher: Option<Dispatcher>,
 }
 
@@ -401,6 +895,331 @@ mod tests {
         assert_eq!(1, stratum.implementation.workers.read().len());
     }
 
+    struct ShareManager;
+
+    impl JobDispatcher for ShareManager {
+        fn submit(&self, _payload: Vec<String>) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn submit_share(
+            &self, payload: Vec<String>, _share_boundary: U256,
+        ) -> Result<ShareStatus, Error> {
+            // The job id decides about the outcome of the share.
+            match payload[1].as_str() {
+                "block" => Ok(ShareStatus::Block),
+                "stale" => Err(Error::StaleShare("stale".into())),
+                "invalid" => Err(Error::InvalidSolution("invalid".into())),
+                _ => Ok(ShareStatus::Accepted),
+            }
+        }
+    }
+
+    fn peer_meta(addr: &str) -> SocketMetadata {
+        SocketMetadata {
+            addr: addr.parse().unwrap(),
+            tcp_dispatcher: None,
+        }
+    }
+
+    fn submit_params(job_id: &str) -> Params {
+        Params::Array(
+            vec!["miner1", job_id, "0x1", "0x00"]
+                .into_iter()
+                .map(|s| Value::String(s.into()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn pool_mode_accounts_shares() {
+        let stratum = Stratum::start_with_pool(
+            &"127.0.0.1:19960".parse().unwrap(),
+            Arc::new(ShareManager),
+            None,
+            Some(PoolConfig::default()),
+        )
+        .expect("There should be no error starting stratum");
+        let implementation = &stratum.implementation;
+        let worker = peer_meta("127.0.0.1:30001");
+
+        let response = implementation
+            .submit(submit_params("1"), worker.clone())
+            .unwrap();
+        assert_eq!(
+            Value::Array(vec![
+                Value::Bool(false),
+                Value::String("Worker is not subscribed".into()),
+            ]),
+            response
+        );
+
+        let subscribe = Params::Array(vec![
+            Value::String("miner1".into()),
+            Value::String("".into()),
+        ]);
+        assert_eq!(
+            Value::Bool(true),
+            implementation.subscribe(subscribe, worker.clone()).unwrap()
+        );
+
+        for job_id in &["1", "block", "stale", "invalid", "2"] {
+            implementation
+                .submit(submit_params(job_id), worker.clone())
+                .unwrap();
+        }
+
+        let expected = ShareStats {
+            accepted: 3,
+            stale: 1,
+            invalid: 1,
+            blocks: 1,
+        };
+        let workers = stratum.worker_stats();
+        assert_eq!(1, workers.len());
+        assert_eq!("miner1", workers[0].worker_id);
+        assert_eq!(
+            Some(PoolConfig::default().initial_difficulty),
+            workers[0].difficulty
+        );
+        assert_eq!(expected, workers[0].shares);
+        assert_eq!(expected, stratum.total_share_stats());
+    }
+
+    fn string_params(params: &[&str]) -> Params {
+        Params::Array(
+            params.iter().map(|s| Value::String((*s).into())).collect(),
+        )
+    }
+
+    fn error_code(result: RpcResult) -> ErrorCode {
+        result.expect_err("request should be rejected").code
+    }
+
+    #[test]
+    fn authorizes_with_secret() {
+        let secret = keccak("stratum secret");
+        let stratum = Stratum::start(
+            &"127.0.0.1:19961".parse().unwrap(),
+            Arc::new(VoidManager),
+            Some(secret),
+        )
+        .expect("There should be no error starting stratum");
+        let implementation = &stratum.implementation;
+        let worker = peer_meta("127.0.0.1:30002");
+
+        // Authorizing without a challenge is rejected.
+        assert_eq!(
+            ErrorCode::ServerError(error_codes::NOT_SUBSCRIBED),
+            error_code(
+                implementation.authorize(
+                    string_params(&["miner1", "0x00"]),
+                    worker.clone()
+                )
+            )
+        );
+
+        let challenge = match implementation
+            .subscribe(string_params(&["miner1"]), worker.clone())
+            .unwrap()
+        {
+            Value::String(challenge) => {
+                challenge.trim_start_matches("0x").parse::<H256>().unwrap()
+            }
+            other => panic!("Expected a challenge, got {:?}", other),
+        };
+        assert_eq!(0, implementation.workers.read().len());
+
+        // Submitting before authorization is rejected.
+        assert_eq!(
+            ErrorCode::ServerError(error_codes::UNAUTHORIZED_WORKER),
+            error_code(
+                implementation.submit(submit_params("1"), worker.clone())
+            )
+        );
+
+        let response = authorization_response(&secret, &challenge, "miner1");
+        assert_eq!(
+            Value::Bool(true),
+            implementation
+                .authorize(
+                    string_params(&["miner1", &format!("0x{:x}", response)]),
+                    worker.clone()
+                )
+                .unwrap()
+        );
+        assert_eq!(1, implementation.workers.read().len());
+
+        // Shares are only accepted for the authorized worker name.
+        assert!(implementation
+            .submit(submit_params("1"), worker.clone())
+            .is_ok());
+        let other_worker = string_params(&["miner2", "1", "0x1", "0x00"]);
+        assert_eq!(
+            ErrorCode::ServerError(error_codes::UNAUTHORIZED_WORKER),
+            error_code(implementation.submit(other_worker, worker.clone()))
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_secret() {
+        let secret = keccak("stratum secret");
+        let stratum = Stratum::start(
+            &"127.0.0.1:19962".parse().unwrap(),
+            Arc::new(VoidManager),
+            Some(secret),
+        )
+        .expect("There should be no error starting stratum");
+        let implementation = &stratum.implementation;
+        let worker = peer_meta("127.0.0.1:30003");
+
+        let challenge = match implementation
+            .subscribe(string_params(&["miner1"]), worker.clone())
+            .unwrap()
+        {
+            Value::String(challenge) => {
+                challenge.trim_start_matches("0x").parse::<H256>().unwrap()
+            }
+            other => panic!("Expected a challenge, got {:?}", other),
+        };
+        let response =
+            authorization_response(&keccak("wrong"), &challenge, "miner1");
+        assert_eq!(
+            ErrorCode::ServerError(error_codes::UNAUTHORIZED_WORKER),
+            error_code(implementation.authorize(
+                string_params(&["miner1", &format!("0x{:x}", response)]),
+                worker.clone()
+            ))
+        );
+        // A challenge can only be answered once.
+        let response = authorization_response(&secret, &challenge, "miner1");
+        assert_eq!(
+            ErrorCode::ServerError(error_codes::NOT_SUBSCRIBED),
+            error_code(implementation.authorize(
+                string_params(&["miner1", &format!("0x{:x}", response)]),
+                worker.clone()
+            ))
+        );
+        assert_eq!(0, implementation.workers.read().len());
+
+        // The legacy scheme sends the preimage of the secret.
+        assert_eq!(
+            Value::Bool(true),
+            implementation
+                .subscribe(
+                    string_params(&["miner1", "stratum secret"]),
+                    worker.clone()
+                )
+                .unwrap()
+        );
+        assert_eq!(1, implementation.workers.read().len());
+    }
+
+    #[test]
+    fn subscribe_flood_keeps_other_challenges() {
+        let secret = keccak("stratum secret");
+        let stratum = Stratum::start(
+            &"127.0.0.1:19964".parse().unwrap(),
+            Arc::new(VoidManager),
+            Some(secret),
+        )
+        .expect("There should be no error starting stratum");
+        let implementation = &stratum.implementation;
+        let worker = peer_meta("127.0.0.1:30005");
+
+        let challenge = match implementation
+            .subscribe(string_params(&["miner1"]), worker.clone())
+            .unwrap()
+        {
+            Value::String(challenge) => {
+                challenge.trim_start_matches("0x").parse::<H256>().unwrap()
+            }
+            other => panic!("Expected a challenge, got {:?}", other),
+        };
+
+        // Another host subscribes on more connections than there may be
+        // pending challenges.
+        for port in 0..MAX_PENDING_CHALLENGES as u16 + 1 {
+            let attacker = peer_meta(&format!("127.0.0.2:{}", 1024 + port));
+            implementation
+                .subscribe(string_params(&["attacker"]), attacker)
+                .unwrap();
+        }
+        assert_eq!(
+            MAX_PENDING_CHALLENGES_PER_IP + 1,
+            implementation.challenges.read().len()
+        );
+
+        let response = authorization_response(&secret, &challenge, "miner1");
+        assert_eq!(
+            Value::Bool(true),
+            implementation
+                .authorize(
+                    string_params(&["miner1", &format!("0x{:x}", response)]),
+                    worker.clone()
+                )
+                .unwrap()
+        );
+        assert_eq!(1, implementation.workers.read().len());
+    }
+
+    #[test]
+    fn aggregates_reported_hashrate() {
+        let stratum = Stratum::start(
+            &"127.0.0.1:19963".parse().unwrap(),
+            Arc::new(VoidManager),
+            None,
+        )
+        .expect("There should be no error starting stratum");
+        let implementation = &stratum.implementation;
+        let worker1 = peer_meta("127.0.0.1:30004");
+        let worker2 = peer_meta("127.0.0.1:30005");
+
+        // Reports of unsubscribed peers are ignored.
+        assert_eq!(
+            Value::Bool(false),
+            implementation
+                .hashrate(string_params(&["miner1", "0x64"]), worker1.clone())
+                .unwrap()
+        );
+
+        for (name, worker) in &[("miner1", &worker1), ("miner2", &worker2)] {
+            implementation
+                .subscribe(string_params(&[*name]), (*worker).clone())
+                .unwrap();
+        }
+        assert_eq!(U256::zero(), stratum.hashrate());
+
+        assert_eq!(
+            Value::Bool(true),
+            implementation
+                .hashrate(string_params(&["miner1", "0x64"]), worker1.clone())
+                .unwrap()
+        );
+        implementation
+            .hashrate(string_params(&["miner2", "0x10"]), worker2.clone())
+            .unwrap();
+        assert_eq!(U256::from(0x74), stratum.hashrate());
+
+        // A newer report replaces the previous one, an outdated one is not
+        // counted.
+        implementation
+            .hashrate(string_params(&["miner1", "0x1"]), worker1.clone())
+            .unwrap();
+        implementation
+            .workers
+            .write()
+            .get_mut(worker2.addr())
+            .unwrap()
+            .hashrate =
+            Some((U256::from(0x10), Instant::now() - HASHRATE_REPORT_EXPIRY));
+        assert_eq!(U256::one(), stratum.hashrate());
+
+        assert!(implementation
+            .hashrate(string_params(&["miner1", "zz"]), worker1.clone())
+            .is_err());
+    }
+
     #[test]
     fn can_push_work() {
         let _ = ::env_logger::try_init();
@@ -19,7 +19,7 @@
 // along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
 
 use jsonrpc_tcp_server::PushMessageError;
-use mazze_types::H256;
+use mazze_types::{H256, U256};
 use std;
 
 #[derive(Debug, Clone)]
@@ -27,6 +27,7 @@ pub enum Error {
     NoWork,
     NoWorkers,
     InvalidSolution(String),
+    StaleShare(String),
     Io(String),
     Tcp(String),
     Dispatch(String),
@@ -44,10 +45,30 @@ impl From<PushMessageError> for Error {
     }
 }
 
+/// Result of a share accepted by a `JobDispatcher`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareStatus {
+    /// The share met the share boundary of the worker only
+    Accepted,
+    /// The share also met the block boundary and was forwarded as a block
+    /// solution
+    Block,
+}
+
 /// Interface that can provide pow/blockchain-specific responses for the clients
 pub trait JobDispatcher: Send + Sync {
     // miner job result
     fn submit(&self, payload: Vec<String>) -> Result<(), Error>;
+
+    /// Pool mode share submission. A share is valid if its hash meets
+    /// `share_boundary`; shares that also meet the block boundary must still
+    /// be forwarded as block solutions. By default only block solutions are
+    /// accepted.
+    fn submit_share(
+        &self, payload: Vec<String>, _share_boundary: U256,
+    ) -> Result<ShareStatus, Error> {
+        self.submit(payload).map(|_| ShareStatus::Block)
+    }
 }
 
 /// Interface that can handle requests to push job for workers
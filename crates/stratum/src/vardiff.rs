@@ -0,0 +1,258 @@
+// Copyright 2024 Mazze Foundation. All rights reserved.
+// Mazze is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+//! Per-worker share difficulty and variable difficulty retargeting used by
+//! the stratum pool mode.
+
+use mazze_types::U256;
+use std::time::{Duration, Instant};
+
+/// Retargeting never moves the difficulty by more than this factor at once.
+const MAX_RETARGET_FACTOR: u64 = 4;
+/// Observed share intervals within this percentage of the target interval do
+/// not trigger a retarget.
+const RETARGET_VARIANCE_PERCENT: u128 = 30;
+
+/// Pool mode settings shared by all workers of a stratum server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolConfig {
+    /// Share difficulty assigned to a newly subscribed worker.
+    pub initial_difficulty: u64,
+    /// Lower bound of the share difficulty.
+    pub min_difficulty: u64,
+    /// Upper bound of the share difficulty.
+    pub max_difficulty: u64,
+    /// Expected time between two shares of a single worker.
+    pub target_share_interval: Duration,
+    /// How often the share difficulty of a worker is reconsidered.
+    pub retarget_interval: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            initial_difficulty: 1_000,
+            min_difficulty: 1,
+            max_difficulty: u64::MAX,
+            target_share_interval: Duration::from_secs(10),
+            retarget_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl PoolConfig {
+    fn clamp(&self, difficulty: u64) -> u64 {
+        difficulty
+            .max(self.min_difficulty)
+            .min(self.max_difficulty)
+            .max(1)
+    }
+}
+
+/// Share accounting of a worker or of the whole server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShareStats {
+    /// Shares that met the share target of the worker.
+    pub accepted: u64,
+    /// Shares submitted for a job that is no longer mined.
+    pub stale: u64,
+    /// Malformed, duplicated or low difficulty shares.
+    pub invalid: u64,
+    /// Accepted shares that also met the block boundary.
+    pub blocks: u64,
+}
+
+/// Variable difficulty state of a single worker.
+#[derive(Debug, Clone)]
+pub struct Vardiff {
+    difficulty: u64,
+    window_start: Instant,
+    shares_in_window: u64,
+}
+
+impl Vardiff {
+    pub fn new(config: &PoolConfig, now: Instant) -> Self {
+        Vardiff {
+            difficulty: config.clamp(config.initial_difficulty),
+            window_start: now,
+            shares_in_window: 0,
+        }
+    }
+
+    pub fn difficulty(&self) -> u64 {
+        self.difficulty
+    }
+
+    /// Records an accepted share.
+    pub fn on_share(&mut self) {
+        self.shares_in_window += 1;
+    }
+
+    /// Reconsiders the difficulty once `retarget_interval` has passed since
+    /// the last retarget. Returns the new difficulty if it has changed.
+    pub fn retarget(
+        &mut self, config: &PoolConfig, now: Instant,
+    ) -> Option<u64> {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < config.retarget_interval {
+            return None;
+        }
+
+        let current = self.difficulty;
+        let target_ms = config.target_share_interval.as_millis().max(1);
+        let new_difficulty = if self.shares_in_window == 0 {
+            // No share at all in a whole window, the worker is far too slow
+            // for its current difficulty.
+            current / MAX_RETARGET_FACTOR
+        } else {
+            let actual_ms =
+                (elapsed.as_millis() / self.shares_in_window as u128).max(1);
+            let deviation = if actual_ms > target_ms {
+                actual_ms - target_ms
+            } else {
+                target_ms - actual_ms
+            };
+            if deviation * 100 <= target_ms * RETARGET_VARIANCE_PERCENT {
+                current
+            } else {
+                let scaled = current as u128 * target_ms / actual_ms;
+                let lower = (current / MAX_RETARGET_FACTOR) as u128;
+                let upper = current as u128 * MAX_RETARGET_FACTOR as u128;
+                scaled.max(lower).min(upper).min(u64::MAX as u128) as u64
+            }
+        };
+
+        self.window_start = now;
+        self.shares_in_window = 0;
+
+        let new_difficulty = config.clamp(new_difficulty);
+        if new_difficulty == current {
+            None
+        } else {
+            self.difficulty = new_difficulty;
+            Some(new_difficulty)
+        }
+    }
+}
+
+/// Converts a share difficulty into the boundary a share hash must not
+/// exceed.
+pub fn share_difficulty_to_boundary(difficulty: u64) -> U256 {
+    if difficulty == 0 {
+        U256::MAX
+    } else {
+        U256::MAX / U256::from(difficulty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PoolConfig {
+        PoolConfig {
+            initial_difficulty: 1_000,
+            min_difficulty: 10,
+            max_difficulty: 100_000,
+            target_share_interval: Duration::from_secs(10),
+            retarget_interval: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn no_retarget_before_interval() {
+        let config = config();
+        let start = Instant::now();
+        let mut vardiff = Vardiff::new(&config, start);
+        for _ in 0..100 {
+            vardiff.on_share();
+        }
+        assert_eq!(
+            vardiff.retarget(&config, start + Duration::from_secs(30)),
+            None
+        );
+        assert_eq!(vardiff.difficulty(), 1_000);
+    }
+
+    #[test]
+    fn fast_worker_gets_higher_difficulty() {
+        let config = config();
+        let start = Instant::now();
+        let mut vardiff = Vardiff::new(&config, start);
+        // 12 shares in 60s, i.e. one share every 5s instead of every 10s.
+        for _ in 0..12 {
+            vardiff.on_share();
+        }
+        assert_eq!(
+            vardiff.retarget(&config, start + Duration::from_secs(60)),
+            Some(2_000)
+        );
+    }
+
+    #[test]
+    fn slow_worker_gets_lower_difficulty() {
+        let config = config();
+        let start = Instant::now();
+        let mut vardiff = Vardiff::new(&config, start);
+        // 3 shares in 60s, i.e. one share every 20s instead of every 10s.
+        for _ in 0..3 {
+            vardiff.on_share();
+        }
+        assert_eq!(
+            vardiff.retarget(&config, start + Duration::from_secs(60)),
+            Some(500)
+        );
+    }
+
+    #[test]
+    fn retarget_is_bounded() {
+        let config = config();
+        let start = Instant::now();
+        let mut vardiff = Vardiff::new(&config, start);
+        for _ in 0..10_000 {
+            vardiff.on_share();
+        }
+        assert_eq!(
+            vardiff.retarget(&config, start + Duration::from_secs(60)),
+            Some(4_000)
+        );
+
+        // An idle worker is divided by the maximum factor, but never drops
+        // below the configured minimum.
+        let mut idle = Vardiff::new(&config, start);
+        let mut now = start;
+        let mut last = idle.difficulty();
+        for _ in 0..10 {
+            now += Duration::from_secs(60);
+            if let Some(difficulty) = idle.retarget(&config, now) {
+                assert!(difficulty >= last / MAX_RETARGET_FACTOR);
+                last = difficulty;
+            }
+        }
+        assert_eq!(idle.difficulty(), config.min_difficulty);
+    }
+
+    #[test]
+    fn share_rate_within_variance_keeps_difficulty() {
+        let config = config();
+        let start = Instant::now();
+        let mut vardiff = Vardiff::new(&config, start);
+        // One share every ~8.6s is within 30% of the 10s target.
+        for _ in 0..7 {
+            vardiff.on_share();
+        }
+        assert_eq!(
+            vardiff.retarget(&config, start + Duration::from_secs(60)),
+            None
+        );
+        assert_eq!(vardiff.difficulty(), 1_000);
+    }
+
+    #[test]
+    fn boundary_from_difficulty() {
+        assert_eq!(share_difficulty_to_boundary(0), U256::MAX);
+        assert_eq!(share_difficulty_to_boundary(1), U256::MAX);
+        assert_eq!(share_difficulty_to_boundary(2), U256::MAX / 2);
+    }
+}